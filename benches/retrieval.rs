@@ -0,0 +1,137 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::hint::black_box;
+
+use rag_rust::services::answer_policy::filter_by_min_score;
+use rag_rust::services::candle_embedding::{CandleEmbeddingService, EmbeddingConfig};
+use rag_rust::services::embedding::mmr_order;
+use rag_rust::services::embedding_bench::synthetic_corpus;
+use rag_rust::services::query_expansion::fuse_results;
+use rag_rust::services::sections::{replace_with_sections, section_texts};
+use rag_rust::services::stitching::{group_by_document, stitch_chunks};
+use rag_rust::services::token_budget::{TokenBudget, TokenCounter};
+use rag_rust::services::vector::{DocumentWithEmbedding, SearchResult};
+use rag_rust::utils::chunking::{merge_sentences, small_to_big_chunks, split_sentences};
+use rag_rust::utils::pdf::chunk_text;
+
+// A 20,000-word document, about a 60-page PDF
+fn document() -> String {
+    synthetic_corpus(100, 200)
+        .into_iter()
+        .map(|paragraph| format!("{}.", paragraph))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Hits as the knn stage returns them: runs of neighbouring chunks from a few documents, best first
+fn search_results(count: usize) -> Vec<SearchResult> {
+    let corpus = synthetic_corpus(count, 200);
+    let mut results: Vec<SearchResult> = corpus
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| SearchResult {
+            text,
+            score: 1.0 - i as f32 / (count as f32 * 2.0),
+            chunk_index: (i / 5 * 10 + i % 5) as i64,
+            file_path: format!("doc-{}.pdf", i % 5),
+            parent_index: Some((i / 2) as i64),
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+fn bench_chunking(c: &mut Criterion) {
+    let text = document();
+    let mut group = c.benchmark_group("chunking");
+
+    group.bench_function("fixed", |b| b.iter(|| chunk_text(black_box(&text), 200, 50)));
+    group.bench_function("small_to_big", |b| b.iter(|| small_to_big_chunks(black_box(&text), 500, 100)));
+
+    // The embedding-free part of semantic chunking, with every other sentence starting a new topic
+    let sentences = split_sentences(&text, 200);
+    let similarities: Vec<f32> = (0..sentences.len()).map(|i| if i % 2 == 0 { 0.9 } else { 0.2 }).collect();
+    group.bench_function("split_sentences", |b| b.iter(|| split_sentences(black_box(&text), 200)));
+    group.bench_function("merge_sentences", |b| {
+        b.iter(|| merge_sentences(black_box(&sentences), &similarities, 0.75, 200))
+    });
+
+    group.finish();
+}
+
+fn bench_post_processing(c: &mut Criterion) {
+    let results = search_results(50);
+    let mut group = c.benchmark_group("post_processing");
+
+    group.bench_function("min_score", |b| {
+        b.iter_batched(|| results.clone(), |results| filter_by_min_score(results, Some(0.8)), BatchSize::SmallInput)
+    });
+    group.bench_function("stitch", |b| b.iter(|| stitch_chunks(black_box(&results))));
+    group.bench_function("group_by_document", |b| b.iter(|| group_by_document(black_box(&results))));
+
+    // Dedupe across the result lists of an original question and four paraphrases
+    let lists: Vec<Vec<SearchResult>> = (0..5).map(|shift| results[shift..].to_vec()).collect();
+    group.bench_function("fuse_dedupe", |b| {
+        b.iter_batched(|| lists.clone(), |lists| fuse_results(lists, 10), BatchSize::SmallInput)
+    });
+
+    // Picking 5 of 20 candidates, with 384-dimensional embeddings like the default model's
+    let embedding = |seed: usize| -> Vec<f32> { (0..384).map(|d| ((seed * 31 + d * 7) % 97) as f32 / 97.0).collect() };
+    let query = embedding(0);
+    let candidates: Vec<Vec<f32>> = (1..=20).map(embedding).collect();
+    group.bench_function("mmr", |b| b.iter(|| mmr_order(black_box(&query), &candidates, 0.7, 5)));
+
+    let stored: Vec<DocumentWithEmbedding> = results
+        .iter()
+        .map(|result| DocumentWithEmbedding {
+            id: format!("{}-{}", result.file_path, result.chunk_index),
+            text: result.text.clone(),
+            embedding: Vec::new(),
+            chunk_index: result.chunk_index,
+            file_path: result.file_path.clone(),
+            chunk_count: 50,
+            parent_index: result.parent_index,
+        })
+        .collect();
+    group.bench_function("sections", |b| {
+        b.iter_batched(
+            || results.clone(),
+            |results| replace_with_sections(results, &section_texts(&stored)),
+            BatchSize::SmallInput,
+        )
+    });
+
+    let budget = TokenBudget::new(TokenCounter::Estimate, 8_192, 1_024);
+    let history: Vec<String> = synthetic_corpus(5, 80);
+    group.bench_function("token_budget_fit", |b| {
+        b.iter_batched(
+            || (history.clone(), results.clone()),
+            |(history, results)| budget.fit(300, history, results),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+// Runs the configured embedding model; skipped when the embedding service can't be created
+fn bench_embedding(c: &mut Criterion) {
+    let service = match EmbeddingConfig::from_env().and_then(|config| CandleEmbeddingService::new(Some(config))) {
+        Ok(service) => service,
+        Err(e) => {
+            eprintln!("Skipping embedding benchmarks, model unavailable: {}", e);
+            return;
+        }
+    };
+    let query = "How long do refunds take to reach my account?";
+    let chunks = synthetic_corpus(16, 200);
+    let mut group = c.benchmark_group("embedding");
+    group.sample_size(10);
+
+    group.bench_function("query", |b| b.iter(|| service.embed_text(black_box(query))));
+    group.bench_function("batch_16_chunks", |b| b.iter(|| service.embed_texts(black_box(&chunks))));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_chunking, bench_post_processing, bench_embedding);
+criterion_main!(benches);
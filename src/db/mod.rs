@@ -1,17 +1,18 @@
 use anyhow::Result;
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::env;
+
+use crate::middleware::auth::{generate_api_key, hash_api_key};
+use crate::utils::config::AppConfig;
 
 pub mod models;
 pub mod queries;
 
-pub async fn init_db() -> Result<PgPool> {
-    let database_url = env::var("DATABASE_URL")
-        .expect(" DATABASE_URL must be set in .env");
-
+pub async fn init_db(config: &AppConfig) -> Result<PgPool> {
     let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections)
+        .acquire_timeout(config.db_acquire_timeout)
+        .connect(&config.database_url)
         .await?;
 
     println!("Connected to Postgres successfully");
@@ -22,6 +23,25 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     tracing::info!("Running database migrations...");
     
     // Create tables first
+    sqlx::query("CREATE TABLE IF NOT EXISTS organizations (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        name VARCHAR(255) NOT NULL,
+        api_key_hash VARCHAR(64) NOT NULL UNIQUE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        status VARCHAR(20) DEFAULT 'active' CHECK (status IN ('active', 'deleted'))
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS users (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        email VARCHAR(255) NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        status VARCHAR(20) DEFAULT 'active' CHECK (status IN ('active', 'deleted')),
+        UNIQUE(organization_id, email)
+    )").execute(pool).await?;
+    
     sqlx::query("CREATE TABLE IF NOT EXISTS sessions (
         id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
         created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
@@ -59,6 +79,582 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         status VARCHAR(20) DEFAULT 'active' CHECK (status IN ('active', 'deleted'))
     )").execute(pool).await?;
     
+    // Scope sessions and chatbots by organization
+    sqlx::query("ALTER TABLE sessions ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE")
+        .execute(pool).await?;
+    assign_legacy_rows_to_default_organization(pool).await?;
+    sqlx::query("ALTER TABLE sessions ALTER COLUMN organization_id SET NOT NULL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ALTER COLUMN organization_id SET NOT NULL")
+        .execute(pool).await?;
+    
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS shard_count INTEGER NOT NULL DEFAULT 1")
+        .execute(pool).await?;
+    
+    // Archived chatbots keep their data but have their indices closed
+    sqlx::query("ALTER TABLE chat_bot DROP CONSTRAINT IF EXISTS chat_bot_status_check")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD CONSTRAINT chat_bot_status_check CHECK (status IN ('active', 'archived', 'deleted'))")
+        .execute(pool).await?;
+    
+    // Per-chatbot retrieval score threshold and "no answer" behavior
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS min_score REAL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS strict_mode BOOLEAN NOT NULL DEFAULT FALSE")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS fallback_message TEXT")
+        .execute(pool).await?;
+    // Answer from general knowledge, refuse with the fallback message, or use the no-context template.
+    // strict_mode is kept in step with 'refuse', so chatbots set up before this column keep refusing
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS no_context_behavior VARCHAR(20) NOT NULL
+        DEFAULT 'general_knowledge'
+        CHECK (no_context_behavior IN ('general_knowledge', 'refuse', 'fallback_template'))")
+        .execute(pool).await?;
+    sqlx::query("UPDATE chat_bot SET no_context_behavior = 'refuse'
+        WHERE strict_mode AND no_context_behavior <> 'refuse'")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS no_context_template TEXT")
+        .execute(pool).await?;
+    // Paraphrases of the question also searched with and fused with its own hits; 0 is off
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS query_paraphrases SMALLINT NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    // Relevance/diversity balance of MMR over retrieved chunks; NULL is off
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS mmr_lambda REAL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS prompt_template TEXT")
+        .execute(pool).await?;
+    // Optional pre-index webhook that may rewrite, enrich or drop extracted chunks
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS ingest_webhook_url TEXT")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS ingest_webhook_secret TEXT")
+        .execute(pool).await?;
+    // Optional Rhai hooks run on the retrieval query and on the generated answer
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS query_script TEXT")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS answer_script TEXT")
+        .execute(pool).await?;
+    
+    // Word list, regex and classifier rules applied to generated answers
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS output_filters JSONB")
+        .execute(pool).await?;
+    // The same kind of rules, screening user queries before retrieval and generation
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS query_filters JSONB")
+        .execute(pool).await?;
+    // Optional webhook notified with the transcript when a chat is handed off to a person
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS handoff_webhook_url TEXT")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS handoff_webhook_secret TEXT")
+        .execute(pool).await?;
+    // Optional webhook alerted when the chatbot's health score drops after a change
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS health_webhook_url TEXT")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS health_webhook_secret TEXT")
+        .execute(pool).await?;
+    // Escalated chats get the handoff notice instead of bot answers until resumed
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS escalated_at TIMESTAMP WITH TIME ZONE")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS escalation_reason VARCHAR(30) CHECK (escalation_reason IN ('requested', 'negative_feedback'))")
+        .execute(pool).await?;
+    // Sequence number of the chat's latest turn, bumped atomically as turns are added. Chats from
+    // before the column start from their highest existing turn
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS last_sequence INTEGER NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query("UPDATE chats ch SET last_sequence = (SELECT MAX(c.sequence_number) FROM conversations c WHERE c.chat_id = ch.id)
+        WHERE ch.last_sequence = 0 AND EXISTS (SELECT 1 FROM conversations c WHERE c.chat_id = ch.id)")
+        .execute(pool).await?;
+    
+    // Named prompt templates shared by an organization's chatbots
+    sqlx::query("CREATE TABLE IF NOT EXISTS prompt_templates (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        name VARCHAR(255) NOT NULL,
+        template TEXT NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(organization_id, name)
+    )").execute(pool).await?;
+    // Selected shared template; takes precedence over the inline prompt_template
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS prompt_template_id UUID REFERENCES prompt_templates(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    // Canary template answering canary_percent of chats, compared from canary_started_at
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS canary_template_id UUID REFERENCES prompt_templates(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS canary_percent SMALLINT NOT NULL DEFAULT 0")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS canary_started_at TIMESTAMP WITH TIME ZONE")
+        .execute(pool).await?;
+    // Which prompt variant answered a turn while a canary was running
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS prompt_variant VARCHAR(10) CHECK (prompt_variant IN ('current', 'canary'))")
+        .execute(pool).await?;
+    
+    // Record which chatbot answered each conversation turn
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS chatbot_id UUID REFERENCES chat_bot(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    // Topic thread within a chat; NULL is the chat's main thread
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS thread_id UUID")
+        .execute(pool).await?;
+    // Where a conversation's answer is: rows from before this column existed are complete
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS generation_status VARCHAR(20) NOT NULL DEFAULT 'complete' CHECK (generation_status IN ('pending', 'streaming', 'complete', 'failed'))")
+        .execute(pool).await?;
+    // Retrieved chunks the answer was grounded on, kept for transcript exports
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS citations JSONB")
+        .execute(pool).await?;
+    // Sentiment and intent of the user's message, labelled after the turn is created
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS sentiment VARCHAR(20) CHECK (sentiment IN ('positive', 'neutral', 'negative', 'frustrated'))")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS intent VARCHAR(20) CHECK (intent IN ('question', 'complaint', 'praise', 'escalation', 'other'))")
+        .execute(pool).await?;
+    // Detected ISO 639-1 languages of the user's message and of the answer; NULL when undetermined
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS query_language VARCHAR(8)")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS answer_language VARCHAR(8)")
+        .execute(pool).await?;
+    
+    // SCIM provisioning: a bearer token per organization, and identity provider fields on users
+    sqlx::query("ALTER TABLE organizations ADD COLUMN IF NOT EXISTS scim_token_hash VARCHAR(64) UNIQUE")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS external_id VARCHAR(255)")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name VARCHAR(255)")
+        .execute(pool).await?;
+    // Deprovisioned users are deactivated, not deleted, until the identity provider deletes them
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS active BOOLEAN NOT NULL DEFAULT TRUE")
+        .execute(pool).await?;
+    // Password sign-in: an argon2 hash, unset for users who only come from SCIM
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS password_hash TEXT")
+        .execute(pool).await?;
+    // What a signed-in user may do: owner, editor or viewer. Users start out as viewers
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'viewer'")
+        .execute(pool).await?;
+    // The user who created a session, chat or chatbot; unset when it was created with an API key
+    sqlx::query("ALTER TABLE sessions ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    // Handoff inbox: the agent who claimed an escalated chat, and when agents last read it
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS claimed_by UUID REFERENCES users(id) ON DELETE SET NULL")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMP WITH TIME ZONE")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chats ADD COLUMN IF NOT EXISTS agent_read_at TIMESTAMP WITH TIME ZONE")
+        .execute(pool).await?;
+    // Claiming a chat from the inbox escalates it too
+    sqlx::query("ALTER TABLE chats DROP CONSTRAINT IF EXISTS chats_escalation_reason_check")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE chats ADD CONSTRAINT chats_escalation_reason_check CHECK (escalation_reason IN ('requested', 'negative_feedback', 'agent'))")
+        .execute(pool).await?;
+    // Whether a turn's reply came from the bot or from a human agent. Agent replies have an empty
+    // user_query
+    sqlx::query("ALTER TABLE conversations ADD COLUMN IF NOT EXISTS author VARCHAR(10) NOT NULL DEFAULT 'bot' CHECK (author IN ('bot', 'human'))")
+        .execute(pool).await?;
+    // Whether anonymous widget visitors may open guest sessions with the chatbot
+    sqlx::query("ALTER TABLE chat_bot ADD COLUMN IF NOT EXISTS guest_access BOOLEAN NOT NULL DEFAULT FALSE")
+        .execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS conversation_feedback (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        conversation_id UUID NOT NULL UNIQUE REFERENCES conversations(id) ON DELETE CASCADE,
+        rating VARCHAR(10) NOT NULL CHECK (rating IN ('up', 'down')),
+        comment TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS chatbot_glossary (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        term VARCHAR(255) NOT NULL,
+        definition TEXT NOT NULL,
+        aliases TEXT[] NOT NULL DEFAULT '{}',
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(chatbot_id, term)
+    )").execute(pool).await?;
+    
+    // Last retrieval per document, used to move unused documents to cold storage
+    sqlx::query("CREATE TABLE IF NOT EXISTS document_usage (
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        last_retrieved_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+        tier VARCHAR(10) NOT NULL DEFAULT 'hot' CHECK (tier IN ('hot', 'cold')),
+        PRIMARY KEY (chatbot_id, file_path)
+    )").execute(pool).await?;
+    
+    // Shard each document was indexed into; re-indexing it writes to the same shard even after the chatbot grows
+    sqlx::query("CREATE TABLE IF NOT EXISTS document_shards (
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        shard INTEGER NOT NULL,
+        PRIMARY KEY (chatbot_id, file_path)
+    )").execute(pool).await?;
+    
+    // Collections whose writes never reached the secondary Elasticsearch; each needs a full resync
+    sqlx::query("CREATE TABLE IF NOT EXISTS secondary_resyncs (
+        collection VARCHAR(255) PRIMARY KEY,
+        operation VARCHAR(40) NOT NULL,
+        marked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Chunks moved out of Elasticsearch, stored as gzip-compressed JSON
+    sqlx::query("CREATE TABLE IF NOT EXISTS cold_documents (
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        index_name VARCHAR(255) NOT NULL,
+        chunk_count INTEGER NOT NULL,
+        payload BYTEA NOT NULL,
+        archived_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        PRIMARY KEY (chatbot_id, file_path)
+    )").execute(pool).await?;
+    
+    // Answers changed or blocked by a chatbot's output filters
+    sqlx::query("CREATE TABLE IF NOT EXISTS output_filter_incidents (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        conversation_id UUID REFERENCES conversations(id) ON DELETE SET NULL,
+        rule_name VARCHAR(255) NOT NULL,
+        action VARCHAR(10) NOT NULL CHECK (action IN ('replace', 'block')),
+        matched_text TEXT NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    // Whether the filter matched a user query or a generated answer
+    sqlx::query("ALTER TABLE output_filter_incidents ADD COLUMN IF NOT EXISTS stage VARCHAR(10)
+        NOT NULL DEFAULT 'answer' CHECK (stage IN ('query', 'answer'))")
+        .execute(pool).await?;
+    
+    // White-label hostnames, each serving one organization (and optionally one chatbot) with its own branding
+    sqlx::query("CREATE TABLE IF NOT EXISTS custom_domains (
+        hostname VARCHAR(253) PRIMARY KEY,
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID REFERENCES chat_bot(id) ON DELETE SET NULL,
+        branding JSONB,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Single sign-on requests waiting for the identity provider's callback
+    sqlx::query("CREATE TABLE IF NOT EXISTS oidc_login_states (
+        state VARCHAR(64) PRIMARY KEY,
+        nonce VARCHAR(64) NOT NULL,
+        code_verifier VARCHAR(128) NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Admin API sessions issued after single sign-on; tokens are stored hashed like API keys
+    sqlx::query("CREATE TABLE IF NOT EXISTS admin_sessions (
+        token_hash VARCHAR(64) PRIMARY KEY,
+        subject VARCHAR(255) NOT NULL,
+        email VARCHAR(255),
+        role VARCHAR(10) NOT NULL CHECK (role IN ('admin', 'viewer')),
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+    )").execute(pool).await?;
+    
+    // Groups pushed by an identity provider over SCIM
+    sqlx::query("CREATE TABLE IF NOT EXISTS user_groups (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        display_name VARCHAR(255) NOT NULL,
+        external_id VARCHAR(255),
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(organization_id, display_name)
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS user_group_members (
+        group_id UUID NOT NULL REFERENCES user_groups(id) ON DELETE CASCADE,
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        PRIMARY KEY (group_id, user_id)
+    )").execute(pool).await?;
+    
+    // Model tokens, embeddings and searches used by each request, for attributing spend
+    sqlx::query("CREATE TABLE IF NOT EXISTS usage_events (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        operation VARCHAR(20) NOT NULL,
+        input_tokens BIGINT NOT NULL DEFAULT 0,
+        output_tokens BIGINT NOT NULL DEFAULT 0,
+        embedding_calls BIGINT NOT NULL DEFAULT 0,
+        searches BIGINT NOT NULL DEFAULT 0,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS embedding_cache (
+        content_hash VARCHAR(64) PRIMARY KEY,
+        model_name VARCHAR(255) NOT NULL,
+        embedding REAL[] NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Original uploads kept in the document store, so documents can be reprocessed server-side
+    sqlx::query("CREATE TABLE IF NOT EXISTS documents (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        file_name VARCHAR(255) NOT NULL,
+        content_type VARCHAR(100) NOT NULL,
+        storage_key TEXT NOT NULL,
+        size_bytes BIGINT NOT NULL,
+        sha256 VARCHAR(64) NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(chatbot_id, file_path)
+    )").execute(pool).await?;
+    
+    // Background re-embedding of a chatbot's chunks into a new versioned index
+    sqlx::query("CREATE TABLE IF NOT EXISTS reindex_jobs (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        version INTEGER NOT NULL,
+        status VARCHAR(20) NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed')),
+        total_chunks BIGINT NOT NULL DEFAULT 0,
+        processed_chunks BIGINT NOT NULL DEFAULT 0,
+        error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        finished_at TIMESTAMP WITH TIME ZONE,
+        UNIQUE(chatbot_id, version)
+    )").execute(pool).await?;
+    
+    // Help-center connectors, one per chatbot, and the articles each has indexed
+    sqlx::query("CREATE TABLE IF NOT EXISTS help_center_connectors (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL UNIQUE REFERENCES chat_bot(id) ON DELETE CASCADE,
+        provider VARCHAR(20) NOT NULL CHECK (provider IN ('zendesk', 'intercom')),
+        subdomain VARCHAR(63),
+        email VARCHAR(255),
+        api_token TEXT NOT NULL,
+        last_synced_at TIMESTAMP WITH TIME ZONE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS help_center_articles (
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        synced_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        PRIMARY KEY (chatbot_id, file_path)
+    )").execute(pool).await?;
+    
+    // Scheduled SQL queries whose rows are indexed as knowledge, and the rows each has indexed
+    sqlx::query("CREATE TABLE IF NOT EXISTS sql_connectors (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        name VARCHAR(100) NOT NULL,
+        database_url TEXT NOT NULL,
+        query TEXT NOT NULL,
+        primary_key VARCHAR(255) NOT NULL,
+        interval_minutes INTEGER NOT NULL DEFAULT 60,
+        row_count BIGINT NOT NULL DEFAULT 0,
+        next_sync_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+        last_synced_at TIMESTAMP WITH TIME ZONE,
+        last_error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(chatbot_id, name)
+    )").execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS sql_connector_rows (
+        connector_id UUID NOT NULL REFERENCES sql_connectors(id) ON DELETE CASCADE,
+        row_key TEXT NOT NULL,
+        content_hash VARCHAR(64) NOT NULL,
+        PRIMARY KEY (connector_id, row_key)
+    )").execute(pool).await?;
+    
+    // Web pages re-crawled on a schedule, each indexed as one document of the chatbot
+    sqlx::query("CREATE TABLE IF NOT EXISTS web_sources (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        url TEXT NOT NULL,
+        interval_minutes INTEGER NOT NULL DEFAULT 1440,
+        content_hash VARCHAR(64),
+        next_crawl_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+        last_crawled_at TIMESTAMP WITH TIME ZONE,
+        last_changed_at TIMESTAMP WITH TIME ZONE,
+        last_error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(chatbot_id, url)
+    )").execute(pool).await?;
+    
+    // Connectors that sync documents from workspaces like Notion on a schedule, and the documents
+    // each has indexed. `cursor` is where the next incremental sync starts
+    sqlx::query("CREATE TABLE IF NOT EXISTS source_connectors (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        kind VARCHAR(20) NOT NULL CHECK (kind IN ('notion')),
+        name VARCHAR(100) NOT NULL,
+        access_token TEXT NOT NULL,
+        config JSONB NOT NULL DEFAULT '{}',
+        interval_minutes INTEGER NOT NULL DEFAULT 60,
+        cursor TEXT,
+        document_count BIGINT NOT NULL DEFAULT 0,
+        next_sync_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+        last_synced_at TIMESTAMP WITH TIME ZONE,
+        last_full_sync_at TIMESTAMP WITH TIME ZONE,
+        last_error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        UNIQUE(chatbot_id, name)
+    )").execute(pool).await?;
+    
+    // Confluence was added after Notion
+    sqlx::query("ALTER TABLE source_connectors DROP CONSTRAINT IF EXISTS source_connectors_kind_check")
+        .execute(pool).await?;
+    sqlx::query("ALTER TABLE source_connectors ADD CONSTRAINT source_connectors_kind_check CHECK (kind IN ('notion', 'confluence'))")
+        .execute(pool).await?;
+    
+    sqlx::query("CREATE TABLE IF NOT EXISTS source_connector_documents (
+        connector_id UUID NOT NULL REFERENCES source_connectors(id) ON DELETE CASCADE,
+        document_key TEXT NOT NULL,
+        content_hash VARCHAR(64) NOT NULL,
+        PRIMARY KEY (connector_id, document_key)
+    )").execute(pool).await?;
+    
+    // How a chatbot's embedded widget looks and which sites may embed it, read by the widget when
+    // it loads so branding changes don't need a redeploy
+    sqlx::query("CREATE TABLE IF NOT EXISTS widget_configs (
+        chatbot_id UUID PRIMARY KEY REFERENCES chat_bot(id) ON DELETE CASCADE,
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        primary_color VARCHAR(7),
+        background_color VARCHAR(7),
+        text_color VARCHAR(7),
+        avatar_url TEXT,
+        position VARCHAR(20) NOT NULL DEFAULT 'bottom-right' CHECK (position IN ('bottom-right', 'bottom-left')),
+        launcher_text VARCHAR(100),
+        allowed_origins TEXT[] NOT NULL DEFAULT '{}',
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Drafts generated for agents replying from the handoff inbox, and whether the agent sent one
+    sqlx::query("CREATE TABLE IF NOT EXISTS reply_suggestions (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        chat_id UUID NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+        conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        suggestion TEXT NOT NULL,
+        citations JSONB NOT NULL DEFAULT '[]',
+        requested_by UUID REFERENCES users(id) ON DELETE SET NULL,
+        reply_id UUID REFERENCES conversations(id) ON DELETE SET NULL,
+        edited BOOLEAN,
+        used_at TIMESTAMP WITH TIME ZONE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Weekly report deliveries, one schedule per chatbot. Reports go out early on Mondays (UTC)
+    sqlx::query("CREATE TABLE IF NOT EXISTS report_schedules (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL UNIQUE REFERENCES chat_bot(id) ON DELETE CASCADE,
+        webhook_url TEXT,
+        webhook_secret TEXT,
+        email_recipients TEXT[] NOT NULL DEFAULT '{}',
+        next_report_at TIMESTAMP WITH TIME ZONE NOT NULL
+            DEFAULT (date_trunc('week', NOW() AT TIME ZONE 'UTC') + INTERVAL '7 days') AT TIME ZONE 'UTC',
+        last_sent_at TIMESTAMP WITH TIME ZONE,
+        last_error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Refresh tokens for user sign-in, stored hashed and replaced each time they are used
+    sqlx::query("CREATE TABLE IF NOT EXISTS refresh_tokens (
+        token_hash VARCHAR(64) PRIMARY KEY,
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+    )").execute(pool).await?;
+    
+    // Write-ahead log of accepted uploads: a row is written before any side effect and moves through
+    // 'accepted', 'stored' (original saved), 'indexed' and 'completed', or ends 'failed'. Rows left
+    // in between by a crash are replayed by the ingestion recovery task
+    sqlx::query("CREATE TABLE IF NOT EXISTS ingestion_log (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        file_path TEXT NOT NULL,
+        file_name TEXT NOT NULL,
+        content_type VARCHAR(255) NOT NULL,
+        storage_key TEXT NOT NULL,
+        size_bytes BIGINT NOT NULL,
+        sha256 VARCHAR(64) NOT NULL,
+        chunking_strategy VARCHAR(20) NOT NULL,
+        stage VARCHAR(20) NOT NULL DEFAULT 'accepted'
+            CHECK (stage IN ('accepted', 'stored', 'indexed', 'completed', 'failed')),
+        attempts INTEGER NOT NULL DEFAULT 0,
+        embedding_count BIGINT,
+        document_id UUID,
+        error TEXT,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Short-lived guest tokens for anonymous widget visitors, each limited to one chatbot and
+    // session; tokens are stored hashed like API keys
+    sqlx::query("CREATE TABLE IF NOT EXISTS guest_sessions (
+        token_hash VARCHAR(64) PRIMARY KEY,
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+    )").execute(pool).await?;
+    
+    // Text-to-SQL tools, one per chatbot
+    sqlx::query("CREATE TABLE IF NOT EXISTS sql_tools (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+        chatbot_id UUID NOT NULL UNIQUE REFERENCES chat_bot(id) ON DELETE CASCADE,
+        database_url TEXT NOT NULL,
+        allowed_tables TEXT[] NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+    
+    // Clusters of similar user questions per chatbot, replaced on every refresh
+    sqlx::query("CREATE TABLE IF NOT EXISTS faq_clusters (
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        rank INTEGER NOT NULL,
+        representative_question TEXT NOT NULL,
+        sample_questions TEXT[] NOT NULL,
+        hit_count BIGINT NOT NULL,
+        computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (chatbot_id, rank)
+    )").execute(pool).await?;
+    
+    // Rolling health scores per chatbot, recorded by the health task
+    sqlx::query("CREATE TABLE IF NOT EXISTS chatbot_health_snapshots (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        chatbot_id UUID NOT NULL REFERENCES chat_bot(id) ON DELETE CASCADE,
+        score DOUBLE PRECISION NOT NULL,
+        groundedness DOUBLE PRECISION NOT NULL,
+        feedback_ratio DOUBLE PRECISION,
+        unanswered_rate DOUBLE PRECISION NOT NULL,
+        turns BIGINT NOT NULL,
+        alerted BOOLEAN NOT NULL DEFAULT FALSE,
+        computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+    )").execute(pool).await?;
+
+    // The chunks, scores and prompt each answer was generated from, for debugging answers
+    sqlx::query("CREATE TABLE IF NOT EXISTS retrieval_log (
+        id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+        conversation_id UUID NOT NULL UNIQUE REFERENCES conversations(id) ON DELETE CASCADE,
+        chunks JSONB NOT NULL,
+        prompt TEXT NOT NULL,
+        retrieval_trace JSONB NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+    )").execute(pool).await?;
+    
     // Create indexes
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_chats_session_id ON chats(session_id)")
         .execute(pool).await?;
@@ -72,6 +668,46 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         .execute(pool).await?;
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_chat_bot_name ON chat_bot(name)")
         .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_organization_id ON users(organization_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_user_group_members_user_id ON user_group_members(user_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_sessions_organization_id ON sessions(organization_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_chat_bot_organization_id ON chat_bot(organization_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_conversations_chatbot_id ON conversations(chatbot_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_conversations_thread ON conversations(chat_id, thread_id, sequence_number)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_conversations_unfinished ON conversations(updated_at) WHERE generation_status IN ('pending', 'streaming')")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_chats_escalated ON chats(escalated_at) WHERE escalated_at IS NOT NULL")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_reply_suggestions_chat ON reply_suggestions(chat_id, created_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_document_usage_stale ON document_usage(tier, last_retrieved_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_output_filter_incidents_chatbot ON output_filter_incidents(chatbot_id, created_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_custom_domains_organization ON custom_domains(organization_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_usage_events_chatbot ON usage_events(chatbot_id, created_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_documents_chatbot_sha256 ON documents(chatbot_id, sha256)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_chatbot_health_snapshots_chatbot ON chatbot_health_snapshots(chatbot_id, computed_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user_id ON refresh_tokens(user_id)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ingestion_log_chatbot ON ingestion_log(chatbot_id, created_at)")
+        .execute(pool).await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ingestion_log_unfinished ON ingestion_log(updated_at)
+        WHERE stage IN ('accepted', 'stored', 'indexed')")
+        .execute(pool).await?;
+    // At most one running reindex per chatbot
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_reindex_jobs_running ON reindex_jobs(chatbot_id) WHERE status = 'running'")
+        .execute(pool).await?;
     
     // Create function and triggers
     sqlx::query("CREATE OR REPLACE FUNCTION update_updated_at_column()
@@ -82,6 +718,96 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
         END;
         $$ language 'plpgsql'").execute(pool).await?;
     
+    sqlx::query("DROP TRIGGER IF EXISTS update_organizations_updated_at ON organizations")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_organizations_updated_at BEFORE UPDATE ON organizations
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_conversation_feedback_updated_at ON conversation_feedback")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_conversation_feedback_updated_at BEFORE UPDATE ON conversation_feedback
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_chatbot_glossary_updated_at ON chatbot_glossary")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_chatbot_glossary_updated_at BEFORE UPDATE ON chatbot_glossary
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_prompt_templates_updated_at ON prompt_templates")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_prompt_templates_updated_at BEFORE UPDATE ON prompt_templates
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_custom_domains_updated_at ON custom_domains")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_custom_domains_updated_at BEFORE UPDATE ON custom_domains
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_documents_updated_at ON documents")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_documents_updated_at BEFORE UPDATE ON documents
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_help_center_connectors_updated_at ON help_center_connectors")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_help_center_connectors_updated_at BEFORE UPDATE ON help_center_connectors
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_sql_connectors_updated_at ON sql_connectors")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_sql_connectors_updated_at BEFORE UPDATE ON sql_connectors
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_web_sources_updated_at ON web_sources")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_web_sources_updated_at BEFORE UPDATE ON web_sources
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_source_connectors_updated_at ON source_connectors")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_source_connectors_updated_at BEFORE UPDATE ON source_connectors
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_widget_configs_updated_at ON widget_configs")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_widget_configs_updated_at BEFORE UPDATE ON widget_configs
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_report_schedules_updated_at ON report_schedules")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_report_schedules_updated_at BEFORE UPDATE ON report_schedules
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_sql_tools_updated_at ON sql_tools")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_sql_tools_updated_at BEFORE UPDATE ON sql_tools
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_user_groups_updated_at ON user_groups")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_user_groups_updated_at BEFORE UPDATE ON user_groups
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
+    sqlx::query("DROP TRIGGER IF EXISTS update_users_updated_at ON users")
+        .execute(pool).await?;
+    sqlx::query("CREATE TRIGGER update_users_updated_at BEFORE UPDATE ON users
+        FOR EACH ROW EXECUTE FUNCTION update_updated_at_column()")
+        .execute(pool).await?;
+    
     sqlx::query("DROP TRIGGER IF EXISTS update_sessions_updated_at ON sessions")
         .execute(pool).await?;
     sqlx::query("CREATE TRIGGER update_sessions_updated_at BEFORE UPDATE ON sessions
@@ -109,3 +835,99 @@ pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     tracing::info!("✅ Database migrations completed successfully");
     Ok(())
 }
+
+// Tables for the pgvector store, only created when VECTOR_BACKEND=pgvector so other
+// deployments don't need the extension installed
+pub async fn run_pgvector_migrations(pool: &PgPool) -> Result<()> {
+    tracing::info!("Running pgvector migrations...");
+
+    sqlx::query("CREATE EXTENSION IF NOT EXISTS vector").execute(pool).await?;
+
+    // One row per collection, the counterpart of an Elasticsearch index
+    sqlx::query("CREATE TABLE IF NOT EXISTS vector_collections (
+        name VARCHAR(255) PRIMARY KEY,
+        embedding_dim INTEGER NOT NULL,
+        closed BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    )").execute(pool).await?;
+
+    // Collections hold embeddings of one dimension, so the column is left unsized
+    sqlx::query("CREATE TABLE IF NOT EXISTS vector_chunks (
+        collection VARCHAR(255) NOT NULL REFERENCES vector_collections(name) ON DELETE CASCADE,
+        id VARCHAR(255) NOT NULL,
+        text TEXT NOT NULL,
+        embedding vector NOT NULL,
+        chunk_index BIGINT NOT NULL,
+        file_path TEXT NOT NULL,
+        chunk_count BIGINT NOT NULL,
+        created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+        PRIMARY KEY (collection, id)
+    )").execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_vector_chunks_file_path ON vector_chunks(collection, file_path)")
+        .execute(pool).await?;
+
+    // Section a chunk was cut from, for documents chunked small-to-big
+    sqlx::query("ALTER TABLE vector_chunks ADD COLUMN IF NOT EXISTS parent_index BIGINT")
+        .execute(pool).await?;
+
+    // Names that stand for another collection, so a reindexed collection can take over a chatbot's name
+    sqlx::query("CREATE TABLE IF NOT EXISTS vector_aliases (
+        alias VARCHAR(255) PRIMARY KEY,
+        collection VARCHAR(255) NOT NULL REFERENCES vector_collections(name) ON DELETE CASCADE
+    )").execute(pool).await?;
+
+    tracing::info!("✅ pgvector migrations completed successfully");
+    Ok(())
+}
+
+// Sessions and chatbots created before organizations existed are moved into a default organization,
+// keyed by DEFAULT_ORGANIZATION_API_KEY or by a generated key that is logged once
+async fn assign_legacy_rows_to_default_organization(pool: &PgPool) -> Result<()> {
+    let has_legacy_rows: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sessions WHERE organization_id IS NULL)
+            OR EXISTS (SELECT 1 FROM chat_bot WHERE organization_id IS NULL)"
+    )
+    .fetch_one(pool)
+    .await?;
+    if !has_legacy_rows {
+        return Ok(());
+    }
+
+    let configured_key = std::env::var("DEFAULT_ORGANIZATION_API_KEY").ok().filter(|key| !key.is_empty());
+    let api_key = configured_key.clone().unwrap_or_else(generate_api_key);
+
+    let mut tx = pool.begin().await?;
+    let organization_id: uuid::Uuid = sqlx::query_scalar(
+        "INSERT INTO organizations (name, api_key_hash) VALUES ('Default', $1)
+         ON CONFLICT (api_key_hash) DO UPDATE SET updated_at = NOW()
+         RETURNING id"
+    )
+    .bind(hash_api_key(&api_key))
+    .fetch_one(&mut *tx)
+    .await?;
+    let sessions = sqlx::query("UPDATE sessions SET organization_id = $1 WHERE organization_id IS NULL")
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+    let chatbots = sqlx::query("UPDATE chat_bot SET organization_id = $1 WHERE organization_id IS NULL")
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    tracing::warn!(
+        "⚠️ Assigned {} sessions and {} chatbots without an organization to default organization {}",
+        sessions.rows_affected(),
+        chatbots.rows_affected(),
+        organization_id
+    );
+    if configured_key.is_none() {
+        tracing::warn!(
+            "⚠️ API key of default organization {}: {} (store it now, it is not shown again)",
+            organization_id,
+            api_key
+        );
+    }
+    Ok(())
+}
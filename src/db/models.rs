@@ -1,11 +1,66 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::services::user_auth::UserRole;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub api_key_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct User {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub email: String,
+    pub external_id: Option<String>,
+    pub display_name: Option<String>,
+    pub active: bool,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: String,
+    /// `owner`, `editor` or `viewer`
+    pub role: String,
+}
+
+// A group provisioned over SCIM
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct UserGroup {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub display_name: String,
+    pub external_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// One user in a group, named from both sides so SCIM resources can list either
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct GroupMembership {
+    pub group_id: Uuid,
+    pub group_name: String,
+    pub user_id: Uuid,
+    pub email: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Session {
     pub id: Uuid,
+    pub organization_id: Uuid,
+    /// Owner, when a signed-in user created the session
+    pub user_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub status: String,
@@ -19,6 +74,16 @@ pub struct Chat {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub status: String,
+    /// Set while a person has taken over the chat; the bot doesn't answer
+    pub escalated_at: Option<DateTime<Utc>>,
+    pub escalation_reason: Option<String>,
+    /// Owner, when a signed-in user created the chat
+    pub user_id: Option<Uuid>,
+    /// Agent handling the chat from the handoff inbox
+    pub claimed_by: Option<Uuid>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// When an agent last read the chat; user messages after it are unread
+    pub agent_read_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -26,59 +91,681 @@ pub struct Conversation {
     pub id: Uuid,
     pub session_id: Uuid,
     pub chat_id: Uuid,
+    pub chatbot_id: Option<Uuid>,
+    pub thread_id: Option<Uuid>,
+    pub sequence_number: i32,
+    pub user_query: String,
+    pub bot_response: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: String,
+    /// `pending`, `streaming`, `complete` or `failed`; `bot_response` holds the partial answer
+    /// while streaming and after a failure
+    pub generation_status: String,
+    /// `bot`, or `human` for an agent's reply from the handoff inbox
+    pub author: String,
+}
+
+// A retrieved chunk an answer was grounded on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Citation {
+    pub file_path: String,
+    pub chunk_index: i64,
+    pub score: f32,
+    /// Start of the chunk's text; missing on citations recorded before excerpts were kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    /// Page the chunk was synced from, for chunks from source connectors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+// One turn of a chat transcript, as exported
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ConversationExport {
+    pub id: Uuid,
     pub sequence_number: i32,
+    pub thread_id: Option<Uuid>,
+    pub user_query: String,
+    pub bot_response: Option<String>,
+    pub citations: Option<Json<Vec<Citation>>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// `bot`, or `human` for an agent's reply
+    pub author: String,
+}
+
+// A chunk as it was put in the prompt, with its full text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedChunk {
+    pub file_path: String,
+    pub chunk_index: i64,
+    pub score: f32,
+    pub text: String,
+}
+
+// The context one answer was generated from
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct RetrievalLog {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub chunks: Json<Vec<LoggedChunk>>,
+    pub prompt: String,
+    pub retrieval_trace: Json<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ConversationFeedback {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub rating: String,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Per-chatbot feedback counts
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FeedbackSummary {
+    pub total: i64,
+    pub thumbs_up: i64,
+    pub thumbs_down: i64,
+}
+
+// A rated conversation turn, joined with the question and answer
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FeedbackEntry {
+    pub conversation_id: Uuid,
+    pub rating: String,
+    pub comment: Option<String>,
     pub user_query: String,
     pub bot_response: Option<String>,
     pub created_at: DateTime<Utc>,
+}
+
+// A chunk cited by a rated answer, one row per citation
+#[derive(Debug, Clone, FromRow)]
+pub struct RatedCitation {
+    pub conversation_id: Uuid,
+    pub rating: String,
+    pub file_path: String,
+    pub chunk_index: i64,
+    pub excerpt: Option<String>,
+    pub url: Option<String>,
+}
+
+// A domain term a chatbot should use consistently in its answers
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct GlossaryEntry {
+    pub id: Uuid,
+    pub chatbot_id: Uuid,
+    pub term: String,
+    pub definition: String,
+    pub aliases: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A named prompt template that any of the organization's chatbots can select
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PromptTemplate {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub template: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A hot document not retrieved since the cold storage cutoff
+#[derive(Debug, Clone, FromRow)]
+pub struct StaleDocument {
+    pub chatbot_id: Uuid,
+    pub organization_id: Uuid,
+    pub shard_count: i32,
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ColdDocument {
+    pub chatbot_id: Uuid,
+    pub file_path: String,
+    pub index_name: String,
+    pub chunk_count: i32,
+    pub payload: Vec<u8>,
+    pub archived_at: DateTime<Utc>,
+}
+
+// Cold document listing entry, without the chunk payload
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ColdDocumentSummary {
+    pub file_path: String,
+    pub chunk_count: i32,
+    pub archived_at: DateTime<Utc>,
+}
+
+// An uploaded original kept in the document store; `file_path` is the path its chunks are indexed under
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Document {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub file_path: String,
+    pub file_name: String,
+    pub content_type: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Presigned link to the original, when the document store is S3-compatible
+    #[sqlx(skip)]
+    pub download_url: Option<String>,
+}
+
+// A user message to store as a new turn. Ids of a new session and chat are chosen up front, and
+// they are created together with the turn
+#[derive(Debug, Clone)]
+pub struct NewChatTurn {
+    pub session_id: Uuid,
+    pub new_session: bool,
+    pub chat_id: Uuid,
+    pub new_chat: bool,
+    pub chatbot_id: Uuid,
+    pub thread_id: Option<Uuid>,
+    pub user_query: String,
+}
+
+// A stored original to record, replacing the chatbot's document with the same file path
+#[derive(Debug, Clone)]
+pub struct NewDocument {
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub file_path: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub storage_key: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+}
+
+// An accepted upload in the write-ahead ingestion log, recorded before any of its side effects
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct IngestionLogEntry {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub file_path: String,
+    pub file_name: String,
+    pub content_type: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub chunking_strategy: String,
+    /// 'accepted', 'stored', 'indexed', 'completed' or 'failed'
+    pub stage: String,
+    /// How many times the recovery task replayed this upload
+    pub attempts: i32,
+    pub embedding_count: Option<i64>,
+    pub document_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+}
+
+// Re-embedding a chatbot's chunks into a new versioned index; `version` names the index
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ReindexJob {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub version: i32,
+    /// 'running', 'completed' or 'failed'
     pub status: String,
+    pub total_chunks: i64,
+    pub processed_chunks: i64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ChatBot {
     pub id: Uuid,
+    pub organization_id: Uuid,
     pub name: String,
+    pub shard_count: i32,
+    pub min_score: Option<f32>,
+    pub strict_mode: bool,
+    pub fallback_message: Option<String>,
+    /// "general_knowledge", "refuse" or "fallback_template"
+    pub no_context_behavior: String,
+    pub no_context_template: Option<String>,
+    pub query_paraphrases: i16,
+    pub mmr_lambda: Option<f32>,
+    pub prompt_template: Option<String>,
+    pub prompt_template_id: Option<Uuid>,
+    pub canary_template_id: Option<Uuid>,
+    pub canary_percent: i16,
+    pub canary_started_at: Option<DateTime<Utc>>,
+    pub ingest_webhook_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub ingest_webhook_secret: Option<String>,
+    pub handoff_webhook_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub handoff_webhook_secret: Option<String>,
+    pub health_webhook_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub health_webhook_secret: Option<String>,
+    pub query_script: Option<String>,
+    pub answer_script: Option<String>,
+    pub output_filters: Option<Json<OutputFilterConfig>>,
+    pub query_filters: Option<Json<OutputFilterConfig>>,
+    /// Owner, when a signed-in user created the chatbot
+    pub user_id: Option<Uuid>,
+    /// Anonymous widget visitors may open guest sessions
+    pub guest_access: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub status: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Mask each match with the rule's replacement
+    Replace,
+    /// Send the block message instead of the answer
+    Block,
+}
+
+impl FilterAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterAction::Replace => "replace",
+            FilterAction::Block => "block",
+        }
+    }
+}
+
+/// What a filter was applied to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterStage {
+    /// A user query, before retrieval and generation
+    Query,
+    /// A generated answer, before it is stored or sent
+    Answer,
+}
+
+impl FilterStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterStage::Query => "query",
+            FilterStage::Answer => "answer",
+        }
+    }
+}
+
+// One output filter: a word list and/or a regex, and what to do on a match
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutputFilterRule {
+    /// Recorded on incidents
+    pub name: String,
+    /// Whole words or phrases, matched case-insensitively
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Regular expression, matched case-insensitively
+    pub pattern: Option<String>,
+    pub action: FilterAction,
+    /// Text that replaces each match; defaults to "***"
+    pub replacement: Option<String>,
+}
+
+// A chatbot's output filters, applied to generated answers before they are stored or sent.
+// Its query filters use the same rules on user queries
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutputFilterConfig {
+    #[serde(default)]
+    pub rules: Vec<OutputFilterRule>,
+    /// Also send the text to the OUTPUT_CLASSIFIER_URL service and block it if flagged
+    #[serde(default)]
+    pub classifier: bool,
+    /// Sent instead of a blocked answer, or in reply to a blocked query; a default message is used if empty
+    pub block_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct OutputFilterIncident {
+    pub id: Uuid,
+    pub chatbot_id: Uuid,
+    pub conversation_id: Option<Uuid>,
+    /// "query" or "answer"
+    pub stage: String,
+    pub rule_name: String,
+    pub action: String,
+    pub matched_text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// How the chat widget looks when served from a custom domain
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct WidgetBranding {
+    /// Shown in the widget header
+    pub title: Option<String>,
+    /// http(s) URL of the logo
+    pub logo_url: Option<String>,
+    /// Hex color such as #1a73e8
+    pub primary_color: Option<String>,
+    /// First message shown before the user asks anything
+    pub welcome_message: Option<String>,
+    #[serde(default)]
+    pub hide_powered_by: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CustomDomain {
+    pub hostname: String,
+    pub organization_id: Uuid,
+    /// Chatbot the widget on this domain talks to; null leaves the choice to the page
+    pub chatbot_id: Option<Uuid>,
+    #[schema(value_type = Option<WidgetBranding>)]
+    pub branding: Option<Json<WidgetBranding>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // Request/Response DTOs for API
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateOrganizationRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateUserRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateSessionRequest {
     // No fields needed - session is created automatically
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateChatRequest {
     pub session_id: Uuid,
     pub title: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateConversationRequest {
     pub session_id: Uuid,
     pub chat_id: Uuid,
     pub user_query: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateConversationRequest {
     pub bot_response: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateChatBotRequest {
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UpdateChatBotRequest {
     pub name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdatePromptTemplateRequest {
+    /// Handlebars template; `{{context}}`, `{{history}}`, `{{query}}` and `{{question}}` are filled in by the server. Null restores the default
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PromptTemplateRequest {
+    /// Unique within the organization
+    pub name: String,
+    /// Handlebars template; `{{context}}`, `{{history}}`, `{{query}}` and `{{question}}` are filled in by the server
+    pub template: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SelectPromptTemplateRequest {
+    /// Shared template to answer with; null goes back to the chatbot's inline template or the default
+    pub template_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdatePromptCanaryRequest {
+    /// Shared template to try on a share of chats; null stops the canary
+    pub template_id: Option<Uuid>,
+    /// Percentage of chats (1-100) answered with the canary
+    #[serde(default)]
+    pub percent: i16,
+}
+
+// Outcomes of the turns one prompt variant answered since the canary started
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PromptVariantMetrics {
+    /// "current" or "canary"
+    pub variant: String,
+    pub turns: i64,
+    pub rated: i64,
+    pub up: i64,
+    pub down: i64,
+    /// Turns where an output filter matched
+    pub filtered: i64,
+    /// up / rated, null until a turn is rated
+    #[sqlx(skip)]
+    pub approval_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CustomDomainRequest {
+    /// Hostname only, such as help.example.com
+    pub hostname: String,
+    pub chatbot_id: Option<Uuid>,
+    pub branding: Option<WidgetBranding>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateCustomDomainRequest {
+    pub chatbot_id: Option<Uuid>,
+    pub branding: Option<WidgetBranding>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateIngestWebhookRequest {
+    /// Chunks are POSTed here before embedding; null removes the webhook
+    pub url: Option<String>,
+    /// When set, requests carry an `X-Webhook-Signature: sha256=<HMAC of the body>` header
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateHandoffWebhookRequest {
+    /// Escalated chats are POSTed here with their transcript; null turns handoff off
+    pub url: Option<String>,
+    /// When set, requests carry an `X-Webhook-Signature: sha256=<HMAC of the body>` header
+    pub secret: Option<String>,
+}
+
+// A chat as listed in the handoff inbox
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct InboxChat {
+    pub chat_id: Uuid,
+    pub session_id: Uuid,
+    pub chatbot_id: Option<Uuid>,
+    pub title: String,
+    pub escalated_at: Option<DateTime<Utc>>,
+    pub escalation_reason: Option<String>,
+    pub claimed_by: Option<Uuid>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub agent_read_at: Option<DateTime<Utc>>,
+    pub last_message_at: DateTime<Utc>,
+    /// The user's latest message
+    pub last_user_query: Option<String>,
+    /// User messages since an agent last read the chat
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AgentReplyRequest {
+    /// Shown to the user as the next message in the chat
+    pub message: String,
+    /// The suggestion the message was drafted from; it is recorded as used, and as edited when the
+    /// message differs from it
+    pub suggestion_id: Option<Uuid>,
+}
+
+// A draft reply generated for an agent, answering the user's latest message
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ReplySuggestion {
+    pub id: Uuid,
+    pub chat_id: Uuid,
+    /// The turn with the user message the draft answers
+    pub conversation_id: Uuid,
+    pub suggestion: String,
+    pub citations: Json<Vec<Citation>>,
+    pub requested_by: Option<Uuid>,
+    /// The agent reply sent from the draft, once one was
+    pub reply_id: Option<Uuid>,
+    /// Whether the agent changed the draft before sending it
+    pub edited: Option<bool>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateGuestAccessRequest {
+    /// Let anonymous widget visitors open guest sessions with the chatbot
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateHealthWebhookRequest {
+    /// Health alerts are POSTed here when the score drops after a change; null turns alerts off
+    pub url: Option<String>,
+    /// When set, requests carry an `X-Webhook-Signature: sha256=<HMAC of the body>` header
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateScriptsRequest {
+    /// Rhai script that sees `query` and returns the query to search with; null removes it
+    pub query_script: Option<String>,
+    /// Rhai script that sees `query` and `answer` and returns the answer to send; null removes it
+    pub answer_script: Option<String>,
+}
+
+/// What a chatbot does when no chunk passes its score threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NoContextBehavior {
+    /// Answer from general knowledge, saying the answer isn't based on the documents
+    #[default]
+    GeneralKnowledge,
+    /// Reply with the fallback message without calling the model
+    Refuse,
+    /// Answer with the chatbot's no-context template instead of its prompt template
+    FallbackTemplate,
+}
+
+impl NoContextBehavior {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoContextBehavior::GeneralKnowledge => "general_knowledge",
+            NoContextBehavior::Refuse => "refuse",
+            NoContextBehavior::FallbackTemplate => "fallback_template",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "general_knowledge" => Some(NoContextBehavior::GeneralKnowledge),
+            "refuse" => Some(NoContextBehavior::Refuse),
+            "fallback_template" => Some(NoContextBehavior::FallbackTemplate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRetrievalSettingsRequest {
+    /// Hits scoring below this are not used as context; null disables the threshold
+    pub min_score: Option<f32>,
+    /// Reply with the fallback message instead of answering from general knowledge; same as
+    /// `no_context: "refuse"`
+    #[serde(default)]
+    pub strict_mode: bool,
+    pub fallback_message: Option<String>,
+    /// What to do when nothing passes `min_score`; defaults to "refuse" in strict mode and
+    /// "general_knowledge" otherwise
+    pub no_context: Option<NoContextBehavior>,
+    /// Handlebars template answering questions nothing was found for; needed by "fallback_template"
+    pub no_context_template: Option<String>,
+    /// Paraphrases of the question the LLM writes to also search with, 3 to 5; 0 or null is off
+    pub query_paraphrases: Option<i16>,
+    /// Reorder hits by maximal marginal relevance: 1 ranks by relevance only, lower values favour
+    /// chunks unlike those already picked; null is off
+    pub mmr_lambda: Option<f32>,
+}
+
+/// A chatbot's retrieval settings, as resolved from an `UpdateRetrievalSettingsRequest`
+#[derive(Debug)]
+pub struct RetrievalSettings {
+    pub min_score: Option<f32>,
+    pub no_context: NoContextBehavior,
+    pub fallback_message: Option<String>,
+    pub no_context_template: Option<String>,
+    pub query_paraphrases: i16,
+    pub mmr_lambda: Option<f32>,
+}
+
 // Response DTOs
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub status: String,
+    /// Only returned once, when the organization is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserResponse {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub status: String,
+    /// `owner`, `editor` or `viewer`
+    pub role: String,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            organization_id: user.organization_id,
+            email: user.email,
+            created_at: user.created_at,
+            status: user.status,
+            role: user.role,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SessionResponse {
     pub id: Uuid,
     pub created_at: DateTime<Utc>,
@@ -87,7 +774,7 @@ pub struct SessionResponse {
     pub chats: Vec<ChatResponse>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ChatResponse {
     pub id: Uuid,
     pub session_id: Uuid,
@@ -98,7 +785,7 @@ pub struct ChatResponse {
     pub conversations: Vec<ConversationResponse>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ConversationResponse {
     pub id: Uuid,
     pub session_id: Uuid,
@@ -111,11 +798,658 @@ pub struct ConversationResponse {
     pub status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ChatBotResponse {
     pub id: Uuid,
     pub name: String,
+    pub min_score: Option<f32>,
+    pub strict_mode: bool,
+    pub fallback_message: Option<String>,
+    pub no_context_behavior: String,
+    pub no_context_template: Option<String>,
+    pub query_paraphrases: i16,
+    pub mmr_lambda: Option<f32>,
+    pub prompt_template: Option<String>,
+    pub prompt_template_id: Option<Uuid>,
+    pub canary_template_id: Option<Uuid>,
+    pub canary_percent: i16,
+    pub ingest_webhook_url: Option<String>,
+    pub handoff_webhook_url: Option<String>,
+    pub health_webhook_url: Option<String>,
+    pub query_script: Option<String>,
+    pub answer_script: Option<String>,
+    pub output_filters: Option<OutputFilterConfig>,
+    pub query_filters: Option<OutputFilterConfig>,
+    pub user_id: Option<Uuid>,
+    pub guest_access: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub status: String,
 }
+
+impl From<ChatBot> for ChatBotResponse {
+    fn from(chatbot: ChatBot) -> Self {
+        Self {
+            id: chatbot.id,
+            name: chatbot.name,
+            min_score: chatbot.min_score,
+            strict_mode: chatbot.strict_mode,
+            fallback_message: chatbot.fallback_message,
+            no_context_behavior: chatbot.no_context_behavior,
+            no_context_template: chatbot.no_context_template,
+            query_paraphrases: chatbot.query_paraphrases,
+            mmr_lambda: chatbot.mmr_lambda,
+            prompt_template: chatbot.prompt_template,
+            prompt_template_id: chatbot.prompt_template_id,
+            canary_template_id: chatbot.canary_template_id,
+            canary_percent: chatbot.canary_percent,
+            ingest_webhook_url: chatbot.ingest_webhook_url,
+            handoff_webhook_url: chatbot.handoff_webhook_url,
+            health_webhook_url: chatbot.health_webhook_url,
+            query_script: chatbot.query_script,
+            answer_script: chatbot.answer_script,
+            output_filters: chatbot.output_filters.map(|filters| filters.0),
+            query_filters: chatbot.query_filters.map(|filters| filters.0),
+            user_id: chatbot.user_id,
+            guest_access: chatbot.guest_access,
+            created_at: chatbot.created_at,
+            updated_at: chatbot.updated_at,
+            status: chatbot.status,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateFeedbackRequest {
+    /// "up" or "down"
+    pub rating: String,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpsertGlossaryEntryRequest {
+    pub term: String,
+    pub definition: String,
+    /// Other spellings or abbreviations that should also match the query
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// A question with the answer the chatbot is expected to give
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EvaluationCase {
+    pub question: String,
+    pub expected_answer: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EvaluateChatbotRequest {
+    pub cases: Vec<EvaluationCase>,
+    /// Faithfulness and relevance a question needs to pass, from 0 to 1; defaults to 0.7
+    pub pass_threshold: Option<f64>,
+}
+
+/// A chunk that should be retrieved for a query. Without `chunk_index`, any chunk of the file counts
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RelevantChunk {
+    pub file_path: String,
+    pub chunk_index: Option<i64>,
+}
+
+/// A query labeled with the chunks it should retrieve
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GoldenQuery {
+    pub query: String,
+    pub relevant: Vec<RelevantChunk>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RetrievalEvalRequest {
+    pub queries: Vec<GoldenQuery>,
+    /// Cutoffs to report recall and nDCG at; defaults to 1, 3, 5 and 10
+    pub k_values: Option<Vec<usize>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkDeleteConversationsRequest {
+    /// Inclusive start of the range, matched against conversation created_at
+    pub from: DateTime<Utc>,
+    /// Exclusive end of the range
+    pub to: DateTime<Utc>,
+    /// Hard-delete instead of soft-deleting
+    #[serde(default)]
+    pub purge: bool,
+    /// Only count the matching conversations
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RehydrateDocumentRequest {
+    pub file_path: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImapImportRequest {
+    pub host: String,
+    /// IMAP over TLS port, 993 by default
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: String,
+    /// Folder to import, `INBOX` by default
+    pub folder: Option<String>,
+    /// Import only the newest this many messages, 500 by default and at most 5000
+    pub max_messages: Option<u32>,
+}
+
+/// Which fields of each JSON record are indexed. Dots step into nested objects, e.g. `fields.body`
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct JsonFieldMapping {
+    /// Fields whose text is chunked and embedded, joined in this order
+    pub content: Vec<String>,
+    /// Field written as the title at the top of every chunk
+    pub title: Option<String>,
+    /// Field identifying the record, so importing it again replaces its chunks; defaults to its position
+    pub id: Option<String>,
+    /// Fields written as `field: value` lines at the top of every chunk
+    #[serde(default)]
+    pub metadata: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct JsonImportRequest {
+    pub chatbot_id: Uuid,
+    /// Name of the export, e.g. "faq"; records are stored as `json:<source>/<id>`
+    pub source: String,
+    pub mapping: JsonFieldMapping,
+    /// The records as a JSON array
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub records: Option<Vec<serde_json::Value>>,
+    /// The records as JSONL, one object per line; used when `records` is absent
+    pub jsonl: Option<String>,
+}
+
+// A chunk embedded outside the service, stored as sent
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RawVectorChunk {
+    pub text: String,
+    /// Must have the dimensions of the chatbot's index
+    pub embedding: Vec<f32>,
+    /// Prepended to the text as `key: value` lines, so it shows up in prompts and citations
+    #[serde(default)]
+    pub metadata: std::collections::BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VectorUpsertRequest {
+    /// Document the chunks belong to; its earlier chunks are replaced
+    pub file_path: String,
+    /// The document's chunks, in order
+    pub chunks: Vec<RawVectorChunk>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GlobalSearchRequest {
+    pub query: String,
+    /// Chatbots to search; every active chatbot when omitted
+    pub chatbot_ids: Option<Vec<Uuid>>,
+    /// Hits across all chatbots (default 20, max 100)
+    pub limit: Option<u64>,
+}
+
+// A chatbot and the shards its indices span, for work across many chatbots such as an admin search
+#[derive(Debug, Clone, FromRow)]
+pub struct SearchableChatbot {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    pub shard_count: i32,
+}
+
+// A chatbot's help-center connector; `last_synced_at` is where the next incremental sync starts
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct HelpCenterConnector {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    /// `zendesk` or `intercom`
+    pub provider: String,
+    pub subdomain: Option<String>,
+    pub email: Option<String>,
+    #[serde(skip_serializing)]
+    pub api_token: String,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpsertHelpCenterConnectorRequest {
+    /// `zendesk` or `intercom`
+    pub provider: String,
+    /// Zendesk subdomain, e.g. `acme` for acme.zendesk.com
+    pub subdomain: Option<String>,
+    /// Zendesk agent email the API token belongs to
+    pub email: Option<String>,
+    /// Zendesk API token or Intercom access token
+    pub api_token: String,
+}
+
+// A read-only query run on a schedule against an external Postgres database; each row it returns
+// is indexed as one document of the chatbot
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct SqlConnector {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub database_url: String,
+    pub query: String,
+    /// Column whose value identifies a row across syncs
+    pub primary_key: String,
+    pub interval_minutes: i32,
+    /// Rows returned by the last successful sync
+    pub row_count: i64,
+    pub next_sync_at: DateTime<Utc>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSqlConnectorRequest {
+    /// Unique per chatbot; used in the rows' document paths
+    pub name: String,
+    /// `postgres://` URL, ideally of a user that can only read what the query needs
+    pub database_url: String,
+    /// A single `SELECT` (or `WITH ... SELECT`) statement
+    pub query: String,
+    pub primary_key: String,
+    /// Minutes between syncs, 60 by default and at least 5
+    pub interval_minutes: Option<i32>,
+}
+
+// A row indexed by a SQL connector, with the hash of the text it was indexed from
+#[derive(Debug, Clone, FromRow)]
+pub struct SqlConnectorRow {
+    pub row_key: String,
+    pub content_hash: String,
+}
+
+// A web page fetched on a schedule and indexed as one document of the chatbot; it is re-embedded
+// only when its text changes
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct WebSource {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub url: String,
+    pub interval_minutes: i32,
+    /// SHA-256 of the text last indexed; none until the first successful crawl
+    pub content_hash: Option<String>,
+    pub next_crawl_at: DateTime<Utc>,
+    pub last_crawled_at: Option<DateTime<Utc>>,
+    /// When the page's text last differed from the previous crawl
+    pub last_changed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// A connector that syncs a workspace's documents into a chatbot on a schedule, each document
+// indexed as its own document of the chatbot
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct SourceConnector {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    /// `notion` or `confluence`
+    pub kind: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub access_token: String,
+    /// Provider settings, e.g. Notion's `root_page_ids`
+    #[schema(value_type = Object)]
+    pub config: serde_json::Value,
+    pub interval_minutes: i32,
+    /// Where the next incremental sync starts; none until the first successful sync
+    pub cursor: Option<String>,
+    /// Documents indexed after the last successful sync
+    pub document_count: i64,
+    pub next_sync_at: DateTime<Utc>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// When a sync last listed every document, removing ones deleted at the source
+    pub last_full_sync_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSourceConnectorRequest {
+    /// `notion` or `confluence`
+    pub kind: String,
+    /// Unique per chatbot; used in the documents' paths
+    pub name: String,
+    /// Notion integration secret or OAuth access token, or Confluence API token or personal access token
+    pub access_token: Option<String>,
+    /// Code from Notion's OAuth redirect, exchanged for an access token when `access_token` is absent
+    pub oauth_code: Option<String>,
+    /// Redirect URI the OAuth code was issued for
+    pub redirect_uri: Option<String>,
+    /// Provider settings. For Notion, `root_page_ids` limits the sync to those pages and their sub-pages.
+    /// For Confluence, `base_url` is required, with `email` for Cloud API tokens and optional `space_keys`
+    #[schema(value_type = Option<Object>)]
+    pub config: Option<serde_json::Value>,
+    /// Minutes between syncs, 60 by default and at least 15
+    pub interval_minutes: Option<i32>,
+}
+
+// A document indexed by a source connector, with the hash of the text it was indexed from
+#[derive(Debug, Clone, FromRow)]
+pub struct SourceConnectorDocument {
+    pub document_key: String,
+    pub content_hash: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterRequest {
+    pub email: String,
+    /// At least 8 characters
+    pub password: String,
+    /// `viewer` by default
+    pub role: Option<UserRole>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshTokenRequest {
+    /// Refresh token from the last login or refresh; it can only be used once
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateUserRoleRequest {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWebSourceRequest {
+    /// `http` or `https` URL of an HTML or plain-text page
+    pub url: String,
+    /// Minutes between crawls, 1440 (daily) by default and at least 15
+    pub interval_minutes: Option<i32>,
+}
+
+// How a chatbot's embedded widget looks, and the sites allowed to embed it
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct WidgetConfig {
+    pub chatbot_id: Uuid,
+    pub organization_id: Uuid,
+    /// Hex colors such as #1a73e8
+    pub primary_color: Option<String>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+    /// http(s) URL of the bot's avatar
+    pub avatar_url: Option<String>,
+    /// `bottom-right` or `bottom-left`
+    pub position: String,
+    /// Label on the launcher button
+    pub launcher_text: Option<String>,
+    /// Origins such as `https://www.example.com` or `https://*.example.com`; empty allows any site
+    pub allowed_origins: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpsertWidgetConfigRequest {
+    pub primary_color: Option<String>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+    pub avatar_url: Option<String>,
+    /// `bottom-right` (default) or `bottom-left`
+    pub position: Option<String>,
+    /// At most 100 characters
+    pub launcher_text: Option<String>,
+    /// Sites that may load the widget and open guest sessions; empty allows any site
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+// Where a chatbot's weekly report is delivered
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ReportSchedule {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub webhook_url: Option<String>,
+    #[serde(skip_serializing)]
+    pub webhook_secret: Option<String>,
+    pub email_recipients: Vec<String>,
+    pub next_report_at: DateTime<Utc>,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpsertReportScheduleRequest {
+    /// Receives each report as signed JSON
+    pub webhook_url: Option<String>,
+    /// Signs webhook deliveries in `X-Webhook-Signature`
+    pub webhook_secret: Option<String>,
+    /// Receive each report as a plain-text email; needs the `smtp` feature
+    #[serde(default)]
+    pub email_recipients: Vec<String>,
+}
+
+// A chatbot's text-to-SQL tool: the model may query the whitelisted tables of an external Postgres
+// database to answer analytical questions
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct SqlTool {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    #[serde(skip_serializing)]
+    pub database_url: String,
+    /// Tables generated queries may read, e.g. `orders` or `sales.customers`
+    pub allowed_tables: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpsertSqlToolRequest {
+    /// `postgres://` URL of a user that can only read the whitelisted tables
+    pub database_url: String,
+    /// Tables generated queries may read; unqualified names are in `public`
+    pub allowed_tables: Vec<String>,
+}
+
+// A group of similar questions users asked a chatbot, refreshed by the FAQ cluster task
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FaqCluster {
+    pub chatbot_id: Uuid,
+    /// 1 for the most asked cluster
+    pub rank: i32,
+    pub representative_question: String,
+    /// The cluster's most asked questions, representative first
+    pub sample_questions: Vec<String>,
+    /// Times any question in the cluster was asked
+    pub hit_count: i64,
+    pub computed_at: DateTime<Utc>,
+}
+
+// Raw counts behind a chatbot's health score, over the finished turns since some time
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct HealthCounts {
+    pub turns: i64,
+    /// Turns answered from at least one retrieved chunk
+    pub grounded_turns: i64,
+    /// Sum of the best chunk score of each grounded turn
+    pub grounding_score_sum: f64,
+    /// Failed turns and turns answered without any retrieved chunk
+    pub unanswered_turns: i64,
+    pub rated_turns: i64,
+    pub thumbs_up: i64,
+}
+
+// A chatbot's health score at one point, recorded by the health task
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ChatbotHealthSnapshot {
+    pub id: Uuid,
+    pub chatbot_id: Uuid,
+    /// 0 to 100, higher is healthier
+    pub score: f64,
+    pub groundedness: f64,
+    /// Share of rated turns rated thumbs-up; null when none were rated
+    pub feedback_ratio: Option<f64>,
+    pub unanswered_rate: f64,
+    pub turns: i64,
+    /// Whether this snapshot sent a degradation alert
+    pub alerted: bool,
+    pub computed_at: DateTime<Utc>,
+}
+
+// A single sign-on request started by /api/admin/sso/login
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AdminSession {
+    /// The identity provider's stable user id
+    pub subject: String,
+    pub email: Option<String>,
+    /// "admin" or "viewer"
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct GuestSession {
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    pub session_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateGuestSessionRequest {
+    pub chatbot_id: Uuid,
+    /// Token from the Turnstile or hCaptcha widget; required when CAPTCHA_PROVIDER is set
+    pub captcha_token: Option<String>,
+}
+
+// Resources one request used, recorded after it finishes
+#[derive(Debug, Clone, Default)]
+pub struct UsageEvent {
+    pub organization_id: Uuid,
+    pub chatbot_id: Uuid,
+    /// "chat", "chat_stream", "query", "evaluation" or "reply_suggestion"
+    pub operation: &'static str,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub embedding_calls: i64,
+    pub searches: i64,
+}
+
+// A chatbot's usage totals for one UTC day
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct UsageDay {
+    pub day: NaiveDate,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub embedding_calls: i64,
+    pub searches: i64,
+}
+
+// A chatbot's usage over a whole report
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct UsageTotals {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub embedding_calls: i64,
+    pub searches: i64,
+}
+
+// Sentiment and intent label counts for a chatbot's conversation turns
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct SentimentSummary {
+    pub labelled: i64,
+    pub positive: i64,
+    pub neutral: i64,
+    pub negative: i64,
+    pub frustrated: i64,
+    pub questions: i64,
+    pub complaints: i64,
+    pub praise: i64,
+    pub escalations: i64,
+}
+
+// Queries asked and answers given in one language, for a chatbot's conversation turns
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct LanguageCount {
+    /// ISO 639-1 code, e.g. "en"
+    pub language: String,
+    pub queries: i64,
+    pub answers: i64,
+}
+
+// Thumbs-down ratings on a chat's turns, counted when one of them is rated down
+#[derive(Debug, Clone, FromRow)]
+pub struct ChatNegativeFeedback {
+    pub chat_id: Uuid,
+    pub chatbot_id: Option<Uuid>,
+    pub thumbs_down: i64,
+    pub escalated: bool,
+}
+
+// A chunk stored by the pgvector backend, embedding in pgvector's text form
+#[derive(Debug, Clone, FromRow)]
+pub struct VectorChunk {
+    pub collection: String,
+    pub id: String,
+    pub text: String,
+    pub embedding: String,
+    pub chunk_index: i64,
+    pub file_path: String,
+    pub chunk_count: i64,
+    pub parent_index: Option<i64>,
+}
+
+// A pgvector chunk without its embedding, for reading a whole collection
+#[derive(Debug, Clone, FromRow)]
+pub struct VectorChunkText {
+    pub id: String,
+    pub text: String,
+    pub chunk_index: i64,
+    pub file_path: String,
+    pub chunk_count: i64,
+    pub parent_index: Option<i64>,
+}
+
+// A collection the secondary Elasticsearch missed a write to, and the last write it missed
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SecondaryResync {
+    pub collection: String,
+    pub operation: String,
+    pub marked_at: DateTime<Utc>,
+}
@@ -1,147 +1,822 @@
 use crate::db::models::*;
-use crate::errors::AppResult;
-use sqlx::PgPool;
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::errors::{AppError, AppResult};
+use crate::services::chatbot_health::HealthReport;
+use crate::services::language::detect_language;
+use crate::services::output_filter::FilterIncident;
+use crate::services::pgvector::vector_literal;
+use crate::services::vector::{DocumentWithEmbedding, SearchResult};
+use sqlx::{Connection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
-// Session queries
-pub async fn create_session(pool: &PgPool) -> AppResult<Session> {
+// Organization queries
+pub async fn create_organization(pool: &PgPool, name: String, api_key_hash: String) -> AppResult<Organization> {
+    let organization = sqlx::query_as::<_, Organization>(
+        "INSERT INTO organizations (name, api_key_hash) VALUES ($1, $2) RETURNING *"
+    )
+    .bind(name)
+    .bind(api_key_hash)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(organization)
+}
+
+pub async fn get_organization_by_api_key_hash(pool: &PgPool, api_key_hash: &str) -> AppResult<Option<Organization>> {
+    let organization = sqlx::query_as::<_, Organization>(
+        "SELECT * FROM organizations WHERE api_key_hash = $1 AND status = 'active'"
+    )
+    .bind(api_key_hash)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(organization)
+}
+
+// User queries
+pub async fn create_user(pool: &PgPool, organization_id: Uuid, email: String) -> AppResult<User> {
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (organization_id, email) VALUES ($1, $2) RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(email)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(user)
+}
+
+pub async fn list_users(pool: &PgPool, organization_id: Uuid) -> AppResult<Vec<User>> {
+    let users = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE organization_id = $1 AND status = 'active' ORDER BY created_at ASC"
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(users)
+}
+
+// A user who signs in with a password
+pub async fn create_password_user(
+    pool: &PgPool,
+    organization_id: Uuid,
+    email: &str,
+    password_hash: &str,
+    role: &str,
+) -> AppResult<User> {
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (organization_id, email, password_hash, role) VALUES ($1, $2, $3, $4) RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(email)
+    .bind(password_hash)
+    .bind(role)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(user)
+}
+
+// An active user by email, compared case-insensitively
+pub async fn get_active_user_by_email(pool: &PgPool, organization_id: Uuid, email: &str) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users
+         WHERE organization_id = $1 AND lower(email) = lower($2) AND status = 'active' AND active"
+    )
+    .bind(organization_id)
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+// Change a user's role; it reaches their access tokens the next time they sign in or refresh
+pub async fn update_user_role(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: &str,
+) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET role = $3, updated_at = NOW()
+         WHERE id = $1 AND organization_id = $2 AND status = 'active'
+         RETURNING *"
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .bind(role)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+// Store a refresh token, dropping the user's expired ones
+pub async fn create_refresh_token(pool: &PgPool, user_id: Uuid, token_hash: &str, ttl_secs: i64) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1 AND expires_at < NOW()")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO refresh_tokens (token_hash, user_id, expires_at)
+         VALUES ($1, $2, NOW() + make_interval(secs => $3))"
+    )
+    .bind(token_hash)
+    .bind(user_id)
+    .bind(ttl_secs as f64)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Use up a refresh token and return its user; None when it is unknown, expired or the user
+// can no longer sign in
+pub async fn consume_refresh_token(pool: &PgPool, token_hash: &str) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "WITH consumed AS (
+             DELETE FROM refresh_tokens WHERE token_hash = $1 RETURNING user_id, expires_at
+         )
+         SELECT u.* FROM users u JOIN consumed c ON c.user_id = u.id
+         WHERE c.expires_at > NOW() AND u.status = 'active' AND u.active"
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn delete_refresh_token(pool: &PgPool, token_hash: &str) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM refresh_tokens WHERE token_hash = $1")
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Session queries - `user_id` is the signed-in caller, whose sessions and chats are their own
+pub async fn create_session(pool: &PgPool, organization_id: Uuid, user_id: Option<Uuid>) -> AppResult<Session> {
     let session = sqlx::query_as::<_, Session>(
-        "INSERT INTO sessions DEFAULT VALUES RETURNING *"
+        "INSERT INTO sessions (organization_id, user_id) VALUES ($1, $2) RETURNING *"
     )
+    .bind(organization_id)
+    .bind(user_id)
     .fetch_one(pool)
     .await?;
     
     Ok(session)
 }
 
-pub async fn get_session(pool: &PgPool, session_id: Uuid) -> AppResult<Option<Session>> {
+pub async fn get_session(
+    pool: &PgPool,
+    organization_id: Uuid,
+    session_id: Uuid,
+    user_id: Option<Uuid>,
+) -> AppResult<Option<Session>> {
     let session = sqlx::query_as::<_, Session>(
-        "SELECT * FROM sessions WHERE id = $1 AND status = 'active'"
+        "SELECT * FROM sessions WHERE id = $1 AND organization_id = $2 AND status = 'active'
+           AND ($3::uuid IS NULL OR user_id = $3)"
     )
     .bind(session_id)
+    .bind(organization_id)
+    .bind(user_id)
     .fetch_optional(pool)
     .await?;
     
     Ok(session)
 }
 
-pub async fn list_sessions(pool: &PgPool) -> AppResult<Vec<Session>> {
+pub async fn list_sessions(pool: &PgPool, organization_id: Uuid) -> AppResult<Vec<Session>> {
     let sessions = sqlx::query_as::<_, Session>(
-        "SELECT * FROM sessions WHERE status = 'active' ORDER BY created_at DESC"
+        "SELECT * FROM sessions WHERE organization_id = $1 AND status = 'active' ORDER BY created_at DESC"
     )
+    .bind(organization_id)
     .fetch_all(pool)
     .await?;
     
     Ok(sessions)
 }
 
-// Chat queries
-pub async fn create_chat(pool: &PgPool, session_id: Uuid, title: String) -> AppResult<Chat> {
+// Chat queries - chats are scoped to an organization through their session
+pub async fn create_chat(
+    pool: &PgPool,
+    organization_id: Uuid,
+    session_id: Uuid,
+    title: String,
+    user_id: Option<Uuid>,
+) -> AppResult<Chat> {
     let chat = sqlx::query_as::<_, Chat>(
-        "INSERT INTO chats (session_id, title) VALUES ($1, $2) RETURNING *"
+        "INSERT INTO chats (session_id, title, user_id)
+         SELECT id, $2, $4 FROM sessions WHERE id = $1 AND organization_id = $3 AND status = 'active'
+         RETURNING *"
     )
     .bind(session_id)
     .bind(title)
+    .bind(organization_id)
+    .bind(user_id)
     .fetch_one(pool)
     .await?;
     
     Ok(chat)
 }
 
-pub async fn get_chat(pool: &PgPool, chat_id: Uuid) -> AppResult<Option<Chat>> {
+pub async fn get_chat(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    user_id: Option<Uuid>,
+) -> AppResult<Option<Chat>> {
     let chat = sqlx::query_as::<_, Chat>(
-        "SELECT * FROM chats WHERE id = $1 AND status = 'active'"
+        "SELECT c.* FROM chats c JOIN sessions s ON s.id = c.session_id
+         WHERE c.id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND ($3::uuid IS NULL OR c.user_id = $3)"
     )
     .bind(chat_id)
+    .bind(organization_id)
+    .bind(user_id)
     .fetch_optional(pool)
     .await?;
     
     Ok(chat)
 }
 
-pub async fn list_chats_by_session(pool: &PgPool, session_id: Uuid) -> AppResult<Vec<Chat>> {
-    let chats = sqlx::query_as::<_, Chat>(
-        "SELECT * FROM chats WHERE session_id = $1 AND status = 'active' ORDER BY created_at ASC"
+// Mark a chat as handed off to a person; None when it is missing or already escalated
+pub async fn mark_chat_escalated(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    reason: &str,
+) -> AppResult<Option<Chat>> {
+    let chat = sqlx::query_as::<_, Chat>(
+        "UPDATE chats c SET escalated_at = NOW(), escalation_reason = $3
+         FROM sessions s
+         WHERE s.id = c.session_id AND c.id = $1 AND s.organization_id = $2
+           AND c.status = 'active' AND c.escalated_at IS NULL
+         RETURNING c.*"
     )
-    .bind(session_id)
+    .bind(chat_id)
+    .bind(organization_id)
+    .bind(reason)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat)
+}
+
+// Hand a chat back to the bot
+pub async fn clear_chat_escalation(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    user_id: Option<Uuid>,
+) -> AppResult<Option<Chat>> {
+    let chat = sqlx::query_as::<_, Chat>(
+        "UPDATE chats c SET escalated_at = NULL, escalation_reason = NULL, claimed_by = NULL, claimed_at = NULL
+         FROM sessions s
+         WHERE s.id = c.session_id AND c.id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND ($3::uuid IS NULL OR c.user_id = $3)
+         RETURNING c.*"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat)
+}
+
+// Recent chats for the handoff inbox, the most recently active first, with how many user messages
+// arrived since an agent last read each
+pub async fn list_inbox_chats(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Option<Uuid>,
+    escalated_only: bool,
+    unread_only: bool,
+    limit: i64,
+) -> AppResult<Vec<InboxChat>> {
+    let chats = sqlx::query_as::<_, InboxChat>(
+        "SELECT * FROM (
+             SELECT ch.id AS chat_id, ch.session_id, latest.chatbot_id, ch.title, ch.escalated_at,
+                    ch.escalation_reason, ch.claimed_by, ch.claimed_at, ch.agent_read_at,
+                    latest.created_at AS last_message_at,
+                    (SELECT u.user_query FROM conversations u
+                     WHERE u.chat_id = ch.id AND u.status = 'active' AND u.author = 'bot'
+                     ORDER BY u.sequence_number DESC LIMIT 1) AS last_user_query,
+                    (SELECT COUNT(*) FROM conversations u
+                     WHERE u.chat_id = ch.id AND u.status = 'active' AND u.author = 'bot'
+                       AND u.created_at > COALESCE(ch.agent_read_at, '-infinity')) AS unread_count
+             FROM chats ch
+             JOIN sessions s ON s.id = ch.session_id
+             JOIN LATERAL (
+                 SELECT c.chatbot_id, c.created_at FROM conversations c
+                 WHERE c.chat_id = ch.id AND c.status = 'active'
+                 ORDER BY c.sequence_number DESC LIMIT 1
+             ) latest ON TRUE
+             WHERE s.organization_id = $1 AND ch.status = 'active'
+               AND ($2::uuid IS NULL OR latest.chatbot_id = $2)
+               AND (NOT $3 OR ch.escalated_at IS NOT NULL)
+         ) inbox
+         WHERE (NOT $4 OR unread_count > 0)
+         ORDER BY last_message_at DESC
+         LIMIT $5"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(escalated_only)
+    .bind(unread_only)
+    .bind(limit)
     .fetch_all(pool)
     .await?;
-    
+
     Ok(chats)
 }
 
-// Conversation queries
-pub async fn create_conversation(
+// Give a chat to an agent, escalating it if the bot still has it. A chat another agent holds is
+// only taken over with `force`; None when it is missing or held by someone else
+pub async fn claim_chat(
     pool: &PgPool,
-    session_id: Uuid,
+    organization_id: Uuid,
     chat_id: Uuid,
-    user_query: String,
+    user_id: Uuid,
+    force: bool,
+) -> AppResult<Option<Chat>> {
+    let chat = sqlx::query_as::<_, Chat>(
+        "UPDATE chats c SET claimed_by = $3, claimed_at = NOW(),
+             escalated_at = COALESCE(c.escalated_at, NOW()),
+             escalation_reason = COALESCE(c.escalation_reason, 'agent')
+         FROM sessions s
+         WHERE s.id = c.session_id AND c.id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND (c.claimed_by IS NULL OR c.claimed_by = $3 OR $4)
+         RETURNING c.*"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .bind(user_id)
+    .bind(force)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat)
+}
+
+// Add an agent's reply to a chat as a turn of its own, in the thread of the chat's latest turn.
+// Replying marks the chat read
+pub async fn create_agent_reply(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    reply: &str,
 ) -> AppResult<Conversation> {
-    // Get the next sequence number for this chat
-    let next_sequence: i32 = sqlx::query_scalar(
-        "SELECT COALESCE(MAX(sequence_number), 0) + 1 FROM conversations WHERE chat_id = $1"
+    let conversation = sqlx::query_as::<_, Conversation>(
+        "WITH next AS (
+             UPDATE chats ch SET last_sequence = ch.last_sequence + 1, agent_read_at = NOW()
+             FROM sessions cs
+             WHERE ch.id = $1 AND cs.id = ch.session_id AND cs.organization_id = $2
+             RETURNING ch.session_id, ch.last_sequence
+         )
+         INSERT INTO conversations (session_id, chat_id, sequence_number, user_query, bot_response, chatbot_id,
+                                    thread_id, generation_status, answer_language, author)
+         SELECT next.session_id, $1, next.last_sequence, '', $3, latest.chatbot_id, latest.thread_id,
+                'complete', $4, 'human'
+         FROM next
+         LEFT JOIN LATERAL (
+             SELECT c.chatbot_id, c.thread_id FROM conversations c
+             WHERE c.chat_id = $1 AND c.status = 'active'
+             ORDER BY c.sequence_number DESC LIMIT 1
+         ) latest ON TRUE
+         RETURNING *"
     )
     .bind(chat_id)
+    .bind(organization_id)
+    .bind(reply)
+    .bind(detect_language(reply))
     .fetch_one(pool)
     .await?;
 
+    Ok(conversation)
+}
+
+// The chat's latest turn with a user message, the one an agent would reply to
+pub async fn get_latest_user_turn(pool: &PgPool, organization_id: Uuid, chat_id: Uuid) -> AppResult<Option<Conversation>> {
     let conversation = sqlx::query_as::<_, Conversation>(
-        "INSERT INTO conversations (session_id, chat_id, sequence_number, user_query) VALUES ($1, $2, $3, $4) RETURNING *"
+        "SELECT c.* FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chat_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.author = 'bot' AND c.user_query <> ''
+         ORDER BY c.sequence_number DESC LIMIT 1"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(conversation)
+}
+
+pub async fn create_reply_suggestion(
+    pool: &PgPool,
+    chat_id: Uuid,
+    conversation_id: Uuid,
+    suggestion: &str,
+    citations: &[Citation],
+    requested_by: Option<Uuid>,
+) -> AppResult<ReplySuggestion> {
+    let suggestion = sqlx::query_as::<_, ReplySuggestion>(
+        "INSERT INTO reply_suggestions (chat_id, conversation_id, suggestion, citations, requested_by)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING *"
     )
-    .bind(session_id)
     .bind(chat_id)
-    .bind(next_sequence)
-    .bind(user_query)
+    .bind(conversation_id)
+    .bind(suggestion)
+    .bind(sqlx::types::Json(citations))
+    .bind(requested_by)
     .fetch_one(pool)
     .await?;
+
+    Ok(suggestion)
+}
+
+pub async fn get_reply_suggestion(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    suggestion_id: Uuid,
+) -> AppResult<Option<ReplySuggestion>> {
+    let suggestion = sqlx::query_as::<_, ReplySuggestion>(
+        "SELECT r.* FROM reply_suggestions r
+         JOIN chats ch ON ch.id = r.chat_id
+         JOIN sessions s ON s.id = ch.session_id
+         WHERE r.id = $1 AND r.chat_id = $2 AND s.organization_id = $3"
+    )
+    .bind(suggestion_id)
+    .bind(chat_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(suggestion)
+}
+
+// Record the agent reply a suggestion was sent as. A suggestion is only used once
+pub async fn mark_suggestion_used(pool: &PgPool, suggestion_id: Uuid, reply_id: Uuid, edited: bool) -> AppResult<bool> {
+    let result = sqlx::query(
+        "UPDATE reply_suggestions SET reply_id = $2, edited = $3, used_at = NOW()
+         WHERE id = $1 AND used_at IS NULL"
+    )
+    .bind(suggestion_id)
+    .bind(reply_id)
+    .bind(edited)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Set or clear when agents last read a chat; None when the chat is missing
+pub async fn set_chat_read(pool: &PgPool, organization_id: Uuid, chat_id: Uuid, read: bool) -> AppResult<Option<Chat>> {
+    let chat = sqlx::query_as::<_, Chat>(
+        "UPDATE chats c SET agent_read_at = CASE WHEN $3 THEN NOW() END
+         FROM sessions s
+         WHERE s.id = c.session_id AND c.id = $1 AND s.organization_id = $2 AND c.status = 'active'
+         RETURNING c.*"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .bind(read)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat)
+}
+
+pub async fn list_chats_by_session(pool: &PgPool, organization_id: Uuid, session_id: Uuid) -> AppResult<Vec<Chat>> {
+    let chats = sqlx::query_as::<_, Chat>(
+        "SELECT c.* FROM chats c JOIN sessions s ON s.id = c.session_id
+         WHERE c.session_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+         ORDER BY c.created_at ASC"
+    )
+    .bind(session_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
     
+    Ok(chats)
+}
+
+// Conversation queries - conversations are scoped to an organization through their session
+pub async fn create_conversation(
+    pool: &PgPool,
+    organization_id: Uuid,
+    session_id: Uuid,
+    chat_id: Uuid,
+    chatbot_id: Uuid,
+    thread_id: Option<Uuid>,
+    user_query: String,
+) -> AppResult<Conversation> {
+    let turn = NewChatTurn {
+        session_id,
+        new_session: false,
+        chat_id,
+        new_chat: false,
+        chatbot_id,
+        thread_id,
+        user_query,
+    };
+    let mut conn = pool.acquire().await?;
+    insert_conversation(&mut conn, organization_id, &turn).await
+}
+
+// Attempts at numbering a turn before the conflict is returned to the caller
+const TURN_NUMBER_ATTEMPTS: usize = 3;
+
+/// Whether a turn couldn't be numbered because other turns kept taking its number. The message
+/// can be sent again
+pub fn is_turn_number_conflict(e: &AppError) -> bool {
+    matches!(
+        e,
+        AppError::Database(sqlx::Error::Database(db_error))
+            if db_error.constraint() == Some("conversations_chat_id_sequence_number_key")
+    )
+}
+
+async fn insert_conversation(
+    conn: &mut sqlx::PgConnection,
+    organization_id: Uuid,
+    turn: &NewChatTurn,
+) -> AppResult<Conversation> {
+    let mut attempt = 1;
+    loop {
+        // A savepoint when called inside a transaction, so a taken number doesn't abort it
+        let mut savepoint = conn.begin().await?;
+        // Bumping the chat's counter takes a row lock, so concurrent messages to one chat get
+        // consecutive sequence numbers in a single round trip
+        let inserted = sqlx::query_as::<_, Conversation>(
+            "WITH next AS (
+                 UPDATE chats ch SET last_sequence = ch.last_sequence + 1
+                 FROM sessions cs
+                 WHERE ch.id = $2 AND cs.id = ch.session_id AND cs.organization_id = $4
+                 RETURNING ch.last_sequence
+             )
+             INSERT INTO conversations (session_id, chat_id, sequence_number, user_query, chatbot_id, thread_id, generation_status, query_language)
+             SELECT s.id, $2, next.last_sequence, $3, $5, $6, 'pending', $7 FROM sessions s, next
+             WHERE s.id = $1 AND s.organization_id = $4
+             RETURNING *"
+        )
+        .bind(turn.session_id)
+        .bind(turn.chat_id)
+        .bind(&turn.user_query)
+        .bind(organization_id)
+        .bind(turn.chatbot_id)
+        .bind(turn.thread_id)
+        .bind(detect_language(&turn.user_query))
+        .fetch_one(&mut *savepoint)
+        .await
+        .map_err(AppError::from);
+
+        match inserted {
+            Ok(conversation) => {
+                savepoint.commit().await?;
+                return Ok(conversation);
+            }
+            // The counter is behind the chat's turns, e.g. after a write that numbered a turn
+            // itself: move it past them and try again
+            Err(e) if is_turn_number_conflict(&e) && attempt < TURN_NUMBER_ATTEMPTS => {
+                savepoint.rollback().await?;
+                tracing::warn!("⚠️ Turn number in chat {} was taken, catching its counter up", turn.chat_id);
+                sqlx::query(
+                    "UPDATE chats SET last_sequence = GREATEST(last_sequence,
+                         (SELECT COALESCE(MAX(sequence_number), 0) FROM conversations WHERE chat_id = $1))
+                     WHERE id = $1"
+                )
+                .bind(turn.chat_id)
+                .execute(&mut *conn)
+                .await?;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Create the turn's session and chat when they are new, then the turn itself, in one transaction so
+// a failure part way leaves no session or chat without a message
+pub async fn create_chat_turn(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Option<Uuid>,
+    turn: &NewChatTurn,
+) -> AppResult<Conversation> {
+    let mut tx = pool.begin().await?;
+
+    if turn.new_session {
+        sqlx::query("INSERT INTO sessions (id, organization_id, user_id) VALUES ($1, $2, $3)")
+            .bind(turn.session_id)
+            .bind(organization_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    if turn.new_chat {
+        sqlx::query(
+            "INSERT INTO chats (id, session_id, title, user_id)
+             SELECT $1, id, 'New Chat', $3 FROM sessions WHERE id = $2 AND organization_id = $4 AND status = 'active'"
+        )
+        .bind(turn.chat_id)
+        .bind(turn.session_id)
+        .bind(user_id)
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let conversation = insert_conversation(&mut tx, organization_id, turn).await?;
+
+    tx.commit().await?;
     Ok(conversation)
 }
 
+// Undo create_chat_turn: delete the turn, and the chat and session it created. The chat's counter
+// is stepped back when no later turn took a number
+pub async fn discard_chat_turn(
+    pool: &PgPool,
+    organization_id: Uuid,
+    turn: &NewChatTurn,
+    conversation: &Conversation,
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "DELETE FROM conversations c USING sessions s
+         WHERE c.id = $1 AND s.id = c.session_id AND s.organization_id = $2"
+    )
+    .bind(conversation.id)
+    .bind(organization_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if turn.new_chat {
+        sqlx::query(
+            "DELETE FROM chats ch USING sessions s
+             WHERE ch.id = $1 AND s.id = ch.session_id AND s.organization_id = $2"
+        )
+        .bind(turn.chat_id)
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+    } else {
+        sqlx::query(
+            "UPDATE chats ch SET last_sequence = ch.last_sequence - 1 FROM sessions s
+             WHERE ch.id = $1 AND s.id = ch.session_id AND s.organization_id = $2 AND ch.last_sequence = $3"
+        )
+        .bind(turn.chat_id)
+        .bind(organization_id)
+        .bind(conversation.sequence_number)
+        .execute(&mut *tx)
+        .await?;
+    }
+    if turn.new_session {
+        sqlx::query("DELETE FROM sessions WHERE id = $1 AND organization_id = $2")
+            .bind(turn.session_id)
+            .bind(organization_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn update_conversation_response(
     pool: &PgPool,
+    organization_id: Uuid,
     conversation_id: Uuid,
     bot_response: String,
 ) -> AppResult<Conversation> {
     let conversation = sqlx::query_as::<_, Conversation>(
-        "UPDATE conversations SET bot_response = $1 WHERE id = $2 AND status = 'active' RETURNING *"
+        "UPDATE conversations c SET bot_response = $1, generation_status = 'complete', answer_language = $4 FROM sessions s
+         WHERE c.id = $2 AND s.id = c.session_id AND s.organization_id = $3 AND c.status = 'active'
+         RETURNING c.*"
     )
-    .bind(bot_response)
+    .bind(&bot_response)
     .bind(conversation_id)
+    .bind(organization_id)
+    .bind(detect_language(&bot_response))
     .fetch_one(pool)
     .await?;
     
     Ok(conversation)
 }
 
-pub async fn get_conversation(pool: &PgPool, conversation_id: Uuid) -> AppResult<Option<Conversation>> {
+// Save the answer streamed so far. A generation that already completed or failed is left alone,
+// so a late save can't overwrite the final answer
+pub async fn save_partial_response(pool: &PgPool, conversation_id: Uuid, partial_response: &str) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE conversations SET bot_response = $2, generation_status = 'streaming'
+         WHERE id = $1 AND generation_status IN ('pending', 'streaming')"
+    )
+    .bind(conversation_id)
+    .bind(partial_response)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Mark a generation failed, keeping whatever was streamed before it stopped
+pub async fn fail_generation(pool: &PgPool, conversation_id: Uuid, partial_response: Option<&str>) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE conversations SET bot_response = COALESCE($2, bot_response), generation_status = 'failed'
+         WHERE id = $1 AND generation_status IN ('pending', 'streaming')"
+    )
+    .bind(conversation_id)
+    .bind(partial_response)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Fail generations that haven't been saved for `stale_secs`; the server streaming them is gone
+pub async fn fail_stale_generations(pool: &PgPool, stale_secs: i64) -> AppResult<u64> {
+    let result = sqlx::query(
+        "UPDATE conversations SET generation_status = 'failed'
+         WHERE generation_status IN ('pending', 'streaming')
+           AND updated_at < NOW() - $1 * INTERVAL '1 second'"
+    )
+    .bind(stale_secs)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_conversation(pool: &PgPool, organization_id: Uuid, conversation_id: Uuid) -> AppResult<Option<Conversation>> {
     let conversation = sqlx::query_as::<_, Conversation>(
-        "SELECT * FROM conversations WHERE id = $1 AND status = 'active'"
+        "SELECT c.* FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.id = $1 AND s.organization_id = $2 AND c.status = 'active'"
     )
     .bind(conversation_id)
+    .bind(organization_id)
     .fetch_optional(pool)
     .await?;
     
     Ok(conversation)
 }
 
-pub async fn list_conversations_by_chat(pool: &PgPool, chat_id: Uuid) -> AppResult<Vec<Conversation>> {
+// One page of a chat's turns after `after_sequence`, oldest first; `thread_id` None is every thread
+pub async fn list_conversations_page(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    thread_id: Option<Uuid>,
+    after_sequence: i32,
+    limit: i64,
+) -> AppResult<Vec<Conversation>> {
     let conversations = sqlx::query_as::<_, Conversation>(
-        "SELECT * FROM conversations WHERE chat_id = $1 AND status = 'active' ORDER BY sequence_number ASC"
+        "SELECT c.* FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chat_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND ($3::uuid IS NULL OR c.thread_id = $3) AND c.sequence_number > $4
+         ORDER BY c.sequence_number ASC LIMIT $5"
     )
     .bind(chat_id)
+    .bind(organization_id)
+    .bind(thread_id)
+    .bind(after_sequence)
+    .bind(limit)
     .fetch_all(pool)
     .await?;
     
     Ok(conversations)
 }
 
-pub async fn list_last_conversations_by_chat(pool: &PgPool, chat_id: Uuid, limit: i64) -> AppResult<Vec<Conversation>> {
+// Latest turns of one thread in a chat; `thread_id` None is the main thread
+pub async fn list_last_conversations_by_chat(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    thread_id: Option<Uuid>,
+    limit: i64,
+) -> AppResult<Vec<Conversation>> {
     let conversations = sqlx::query_as::<_, Conversation>(
-        "SELECT * FROM conversations WHERE chat_id = $1 AND status = 'active' ORDER BY sequence_number DESC LIMIT $2"
+        "SELECT c.* FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chat_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.thread_id IS NOT DISTINCT FROM $4
+         ORDER BY c.sequence_number DESC LIMIT $3"
     )
     .bind(chat_id)
+    .bind(organization_id)
     .bind(limit)
+    .bind(thread_id)
     .fetch_all(pool)
     .await?;
     
@@ -152,11 +827,14 @@ pub async fn list_last_conversations_by_chat(pool: &PgPool, chat_id: Uuid, limit
     Ok(conversations)
 }
 
-pub async fn list_conversations_by_session(pool: &PgPool, session_id: Uuid) -> AppResult<Vec<Conversation>> {
+pub async fn list_conversations_by_session(pool: &PgPool, organization_id: Uuid, session_id: Uuid) -> AppResult<Vec<Conversation>> {
     let conversations = sqlx::query_as::<_, Conversation>(
-        "SELECT * FROM conversations WHERE session_id = $1 AND status = 'active' ORDER BY created_at ASC"
+        "SELECT c.* FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.session_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+         ORDER BY c.created_at ASC"
     )
     .bind(session_id)
+    .bind(organization_id)
     .fetch_all(pool)
     .await?;
     
@@ -164,55 +842,3426 @@ pub async fn list_conversations_by_session(pool: &PgPool, session_id: Uuid) -> A
 }
 
 // ChatBot queries
-pub async fn create_chat_bot(pool: &PgPool, name: String) -> AppResult<ChatBot> {
+pub async fn create_chat_bot(
+    pool: &PgPool,
+    organization_id: Uuid,
+    name: String,
+    user_id: Option<Uuid>,
+) -> AppResult<ChatBot> {
     let chat_bot = sqlx::query_as::<_, ChatBot>(
-        "INSERT INTO chat_bot (name) VALUES ($1) RETURNING *"
+        "INSERT INTO chat_bot (organization_id, name, user_id) VALUES ($1, $2, $3) RETURNING *"
     )
+    .bind(organization_id)
     .bind(name)
+    .bind(user_id)
     .fetch_one(pool)
     .await?;
     
     Ok(chat_bot)
 }
 
-pub async fn get_chat_bot(pool: &PgPool, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
+pub async fn get_chat_bot(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
     let chat_bot = sqlx::query_as::<_, ChatBot>(
-        "SELECT * FROM chat_bot WHERE id = $1 AND status = 'active'"
+        "SELECT * FROM chat_bot WHERE id = $1 AND organization_id = $2 AND status = 'active'"
     )
     .bind(chat_bot_id)
+    .bind(organization_id)
     .fetch_optional(pool)
     .await?;
     
     Ok(chat_bot)
 }
 
-pub async fn list_chat_bots(pool: &PgPool) -> AppResult<Vec<ChatBot>> {
+pub async fn list_chat_bots(pool: &PgPool, organization_id: Uuid) -> AppResult<Vec<ChatBot>> {
     let chat_bots = sqlx::query_as::<_, ChatBot>(
-        "SELECT * FROM chat_bot WHERE status = 'active' ORDER BY created_at ASC"
+        "SELECT * FROM chat_bot WHERE organization_id = $1 AND status IN ('active', 'archived') ORDER BY created_at ASC"
     )
+    .bind(organization_id)
     .fetch_all(pool)
     .await?;
     
     Ok(chat_bots)
 }
 
-pub async fn update_chat_bot(pool: &PgPool, chat_bot_id: Uuid, name: String) -> AppResult<ChatBot> {
+// Active or archived chatbot, for lifecycle operations that must also see archived ones
+pub async fn get_retained_chat_bot(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "SELECT * FROM chat_bot WHERE id = $1 AND organization_id = $2 AND status IN ('active', 'archived')"
+    )
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+// Active or archived chatbot of any organization, for admin endpoints
+pub async fn get_chat_bot_for_admin(pool: &PgPool, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "SELECT * FROM chat_bot WHERE id = $1 AND status IN ('active', 'archived')"
+    )
+    .bind(chat_bot_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat_bot)
+}
+
+// Active chatbots of every organization, or only the given ones, for admin searches
+pub async fn list_searchable_chat_bots(pool: &PgPool, chat_bot_ids: Option<&[Uuid]>) -> AppResult<Vec<SearchableChatbot>> {
+    let chatbots = sqlx::query_as::<_, SearchableChatbot>(
+        "SELECT id, organization_id, name, shard_count FROM chat_bot
+         WHERE status = 'active' AND ($1::uuid[] IS NULL OR id = ANY($1))
+         ORDER BY created_at ASC"
+    )
+    .bind(chat_bot_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chatbots)
+}
+
+// Move a chatbot between 'active' and 'archived'; None if it wasn't in the `from` status
+pub async fn set_chat_bot_status(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    from: &str,
+    to: &str,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET status = $1 WHERE id = $2 AND organization_id = $3 AND status = $4 RETURNING *"
+    )
+    .bind(to)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .bind(from)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid, name: String) -> AppResult<ChatBot> {
     let chat_bot = sqlx::query_as::<_, ChatBot>(
-        "UPDATE chat_bot SET name = $1 WHERE id = $2 AND status = 'active' RETURNING *"
+        "UPDATE chat_bot SET name = $1 WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
     )
     .bind(name)
     .bind(chat_bot_id)
+    .bind(organization_id)
     .fetch_one(pool)
     .await?;
     
     Ok(chat_bot)
 }
 
-pub async fn delete_chat_bot(pool: &PgPool, chat_bot_id: Uuid) -> AppResult<()> {
-    sqlx::query("UPDATE chat_bot SET status = 'deleted' WHERE id = $1")
-        .bind(chat_bot_id)
+pub async fn update_chat_bot_retrieval_settings(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    settings: RetrievalSettings,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET min_score = $1, strict_mode = $2, fallback_message = $3, no_context_behavior = $4,
+             no_context_template = $5, query_paraphrases = $6, mmr_lambda = $7
+         WHERE id = $8 AND organization_id = $9 AND status = 'active' RETURNING *"
+    )
+    .bind(settings.min_score)
+    .bind(settings.no_context == NoContextBehavior::Refuse)
+    .bind(settings.fallback_message)
+    .bind(settings.no_context.as_str())
+    .bind(settings.no_context_template)
+    .bind(settings.query_paraphrases)
+    .bind(settings.mmr_lambda)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_prompt_template(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    prompt_template: Option<String>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET prompt_template = $1
+         WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
+    )
+    .bind(prompt_template)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_ingest_webhook(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    url: Option<String>,
+    secret: Option<String>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET ingest_webhook_url = $1, ingest_webhook_secret = $2
+         WHERE id = $3 AND organization_id = $4 AND status = 'active' RETURNING *"
+    )
+    .bind(url)
+    .bind(secret)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_handoff_webhook(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    url: Option<String>,
+    secret: Option<String>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET handoff_webhook_url = $1, handoff_webhook_secret = $2
+         WHERE id = $3 AND organization_id = $4 AND status = 'active' RETURNING *"
+    )
+    .bind(url)
+    .bind(secret)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_guest_access(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    enabled: bool,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET guest_access = $1
+         WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
+    )
+    .bind(enabled)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat_bot)
+}
+
+// Active chatbot that lets anonymous visitors open guest sessions, of any organization
+pub async fn get_guest_chat_bot(pool: &PgPool, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "SELECT * FROM chat_bot WHERE id = $1 AND status = 'active' AND guest_access"
+    )
+    .bind(chat_bot_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_health_webhook(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    url: Option<String>,
+    secret: Option<String>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET health_webhook_url = $1, health_webhook_secret = $2
+         WHERE id = $3 AND organization_id = $4 AND status = 'active' RETURNING *"
+    )
+    .bind(url)
+    .bind(secret)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_scripts(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    query_script: Option<String>,
+    answer_script: Option<String>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET query_script = $1, answer_script = $2
+         WHERE id = $3 AND organization_id = $4 AND status = 'active' RETURNING *"
+    )
+    .bind(query_script)
+    .bind(answer_script)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+// Returns None when the chatbot, or the selected template, is not in the organization
+pub async fn update_chat_bot_prompt_template_id(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    prompt_template_id: Option<Uuid>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET prompt_template_id = $1
+         WHERE id = $2 AND organization_id = $3 AND status = 'active'
+           AND ($1::uuid IS NULL OR EXISTS (SELECT 1 FROM prompt_templates WHERE id = $1 AND organization_id = $3))
+         RETURNING *"
+    )
+    .bind(prompt_template_id)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_prompt_canary(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    canary_template_id: Option<Uuid>,
+    canary_percent: i16,
+) -> AppResult<Option<ChatBot>> {
+    // Comparison restarts when a different template becomes the canary, not when only the share changes
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET canary_template_id = $1, canary_percent = $2,
+                canary_started_at = CASE
+                    WHEN $1::uuid IS NULL THEN NULL
+                    WHEN canary_template_id IS DISTINCT FROM $1 THEN NOW()
+                    ELSE canary_started_at
+                END
+         WHERE id = $3 AND organization_id = $4 AND status = 'active'
+           AND ($1::uuid IS NULL OR EXISTS (SELECT 1 FROM prompt_templates WHERE id = $1 AND organization_id = $4))
+         RETURNING *"
+    )
+    .bind(canary_template_id)
+    .bind(canary_percent)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn promote_chat_bot_prompt_canary(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET prompt_template_id = canary_template_id, canary_template_id = NULL,
+                canary_percent = 0, canary_started_at = NULL
+         WHERE id = $1 AND organization_id = $2 AND status = 'active' AND canary_template_id IS NOT NULL
+         RETURNING *"
+    )
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_output_filters(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    output_filters: Option<OutputFilterConfig>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET output_filters = $1
+         WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
+    )
+    .bind(output_filters.map(sqlx::types::Json))
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn update_chat_bot_query_filters(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chat_bot_id: Uuid,
+    query_filters: Option<OutputFilterConfig>,
+) -> AppResult<Option<ChatBot>> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET query_filters = $1
+         WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
+    )
+    .bind(query_filters.map(sqlx::types::Json))
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+pub async fn delete_chat_bot(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        "UPDATE chat_bot SET status = 'deleted' WHERE id = $1 AND organization_id = $2 AND status IN ('active', 'archived')"
+    )
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .execute(pool)
+    .await?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+// Chatbots whose indices must be kept: active and archived ones
+pub async fn list_retained_chat_bot_ids(pool: &PgPool) -> AppResult<Vec<Uuid>> {
+    let ids = sqlx::query_scalar::<_, Uuid>("SELECT id FROM chat_bot WHERE status IN ('active', 'archived')")
+        .fetch_all(pool)
+        .await?;
+    
+    Ok(ids)
+}
+
+pub async fn list_retained_chat_bots(pool: &PgPool) -> AppResult<Vec<SearchableChatbot>> {
+    let chatbots = sqlx::query_as::<_, SearchableChatbot>(
+        "SELECT id, organization_id, name, shard_count FROM chat_bot WHERE status IN ('active', 'archived')"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chatbots)
+}
+
+// The shard a document was recorded in, or None if it has never been routed
+pub async fn get_document_shard(pool: &PgPool, chatbot_id: Uuid, file_path: &str) -> AppResult<Option<i32>> {
+    let shard = sqlx::query_scalar::<_, i32>("SELECT shard FROM document_shards WHERE chatbot_id = $1 AND file_path = $2")
+        .bind(chatbot_id)
+        .bind(file_path)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(shard)
+}
+
+// Record a document's shard unless it already has one; returns the shard it ends up in
+pub async fn assign_document_shard(pool: &PgPool, chatbot_id: Uuid, file_path: &str, shard: i32) -> AppResult<i32> {
+    let shard = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO document_shards (chatbot_id, file_path, shard) VALUES ($1, $2, $3)
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE SET shard = document_shards.shard
+         RETURNING shard"
+    )
+    .bind(chatbot_id)
+    .bind(file_path)
+    .bind(shard)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(shard)
+}
+
+pub async fn update_chat_bot_shard_count(pool: &PgPool, organization_id: Uuid, chat_bot_id: Uuid, shard_count: i32) -> AppResult<ChatBot> {
+    let chat_bot = sqlx::query_as::<_, ChatBot>(
+        "UPDATE chat_bot SET shard_count = GREATEST(shard_count, $1) WHERE id = $2 AND organization_id = $3 AND status = 'active' RETURNING *"
+    )
+    .bind(shard_count)
+    .bind(chat_bot_id)
+    .bind(organization_id)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(chat_bot)
+}
+
+// Embedding cache queries
+pub async fn get_cached_embeddings(pool: &PgPool, content_hashes: &[String]) -> AppResult<Vec<(String, Vec<f32>)>> {
+    let embeddings = sqlx::query_as::<_, (String, Vec<f32>)>(
+        "SELECT content_hash, embedding FROM embedding_cache WHERE content_hash = ANY($1)"
+    )
+    .bind(content_hashes)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(embeddings)
+}
+
+// Rows per INSERT, keeping the three parameters of each well under Postgres' limit of 65535
+const EMBEDDING_CACHE_INSERT_ROWS: usize = 1000;
+
+pub async fn insert_cached_embeddings(pool: &PgPool, model_name: &str, entries: &[(String, &[f32])]) -> AppResult<()> {
+    for batch in entries.chunks(EMBEDDING_CACHE_INSERT_ROWS) {
+        let mut query = QueryBuilder::<Postgres>::new("INSERT INTO embedding_cache (content_hash, model_name, embedding) ");
+        query.push_values(batch, |mut row, (content_hash, embedding)| {
+            row.push_bind(content_hash).push_bind(model_name).push_bind(*embedding);
+        });
+        query.push(" ON CONFLICT (content_hash) DO NOTHING");
+        query.build().execute(pool).await?;
+    }
+    
+    Ok(())
+}
+
+// Soft delete queries - return false when nothing active matched
+pub async fn delete_session(
+    pool: &PgPool,
+    organization_id: Uuid,
+    session_id: Uuid,
+    user_id: Option<Uuid>,
+) -> AppResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE sessions SET status = 'deleted' WHERE id = $1 AND organization_id = $2 AND status = 'active'
+           AND ($3::uuid IS NULL OR user_id = $3)"
+    )
+    .bind(session_id)
+    .bind(organization_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE chats SET status = 'deleted' WHERE session_id = $1 AND status = 'active'")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE conversations SET status = 'deleted' WHERE session_id = $1 AND status = 'active'")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    
+    Ok(true)
+}
+
+pub async fn delete_chat(pool: &PgPool, organization_id: Uuid, chat_id: Uuid, user_id: Option<Uuid>) -> AppResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE chats c SET status = 'deleted' FROM sessions s
+         WHERE c.id = $1 AND s.id = c.session_id AND s.organization_id = $2 AND c.status = 'active'
+           AND ($3::uuid IS NULL OR c.user_id = $3)"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE conversations SET status = 'deleted' WHERE chat_id = $1 AND status = 'active'")
+        .bind(chat_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    
+    Ok(true)
+}
+
+pub async fn delete_conversation(
+    pool: &PgPool,
+    organization_id: Uuid,
+    conversation_id: Uuid,
+    user_id: Option<Uuid>,
+) -> AppResult<bool> {
+    // Turns belong to whoever owns their chat
+    let result = sqlx::query(
+        "UPDATE conversations c SET status = 'deleted' FROM sessions s, chats ch
+         WHERE c.id = $1 AND s.id = c.session_id AND s.organization_id = $2 AND c.status = 'active'
+           AND ch.id = c.chat_id AND ($3::uuid IS NULL OR ch.user_id = $3)"
+    )
+    .bind(conversation_id)
+    .bind(organization_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    
+    Ok(result.rows_affected() > 0)
+}
+
+// Conversations a chatbot answered within [from, to); `include_deleted` also counts soft-deleted ones
+pub async fn count_conversations_in_range(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    include_deleted: bool,
+) -> AppResult<i64> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM conversations
+         WHERE chatbot_id = $1 AND created_at >= $2 AND created_at < $3
+           AND ($4 OR status = 'active')"
+    )
+    .bind(chatbot_id)
+    .bind(from)
+    .bind(to)
+    .bind(include_deleted)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+// Soft delete a chatbot's active conversations created within [from, to)
+pub async fn soft_delete_conversations_in_range(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        "UPDATE conversations SET status = 'deleted'
+         WHERE chatbot_id = $1 AND created_at >= $2 AND created_at < $3 AND status = 'active'"
+    )
+    .bind(chatbot_id)
+    .bind(from)
+    .bind(to)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Hard delete a chatbot's conversations created within [from, to), soft-deleted or not
+pub async fn purge_conversations_in_range(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> AppResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM conversations WHERE chatbot_id = $1 AND created_at >= $2 AND created_at < $3"
+    )
+    .bind(chatbot_id)
+    .bind(from)
+    .bind(to)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Hard delete soft-deleted rows whose last update is older than the retention window
+pub async fn purge_deleted_rows(pool: &PgPool, retention_days: i32) -> AppResult<u64> {
+    let mut purged = 0;
+
+    for table in ["conversations", "chats", "sessions"] {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE status = 'deleted' AND updated_at < NOW() - make_interval(days => $1)",
+            table
+        ))
+        .bind(retention_days)
+        .execute(pool)
+        .await?;
+        purged += result.rows_affected();
+    }
+    
+    Ok(purged)
+}
+
+// Feedback operations
+pub async fn upsert_conversation_feedback(
+    pool: &PgPool,
+    organization_id: Uuid,
+    conversation_id: Uuid,
+    rating: String,
+    comment: Option<String>,
+) -> AppResult<Option<ConversationFeedback>> {
+    let feedback = sqlx::query_as::<_, ConversationFeedback>(
+        "INSERT INTO conversation_feedback (conversation_id, rating, comment)
+         SELECT c.id, $3, $4 FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.id = $1 AND s.organization_id = $2 AND c.status = 'active'
+         ON CONFLICT (conversation_id) DO UPDATE SET rating = EXCLUDED.rating, comment = EXCLUDED.comment
+         RETURNING *"
+    )
+    .bind(conversation_id)
+    .bind(organization_id)
+    .bind(rating)
+    .bind(comment)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(feedback)
+}
+
+// Thumbs-down ratings across the chat a rated turn belongs to
+pub async fn count_chat_negative_feedback(
+    pool: &PgPool,
+    organization_id: Uuid,
+    conversation_id: Uuid,
+) -> AppResult<Option<ChatNegativeFeedback>> {
+    let counts = sqlx::query_as::<_, ChatNegativeFeedback>(
+        "SELECT c.chat_id, c.chatbot_id,
+                (SELECT COUNT(*) FROM conversations t JOIN conversation_feedback f ON f.conversation_id = t.id
+                 WHERE t.chat_id = c.chat_id AND t.status = 'active' AND f.rating = 'down') AS thumbs_down,
+                ch.escalated_at IS NOT NULL AS escalated
+         FROM conversations c
+         JOIN sessions s ON s.id = c.session_id
+         JOIN chats ch ON ch.id = c.chat_id
+         WHERE c.id = $1 AND s.organization_id = $2"
+    )
+    .bind(conversation_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+pub async fn get_feedback_summary(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<FeedbackSummary> {
+    let summary = sqlx::query_as::<_, FeedbackSummary>(
+        "SELECT COUNT(*) AS total,
+                COUNT(*) FILTER (WHERE f.rating = 'up') AS thumbs_up,
+                COUNT(*) FILTER (WHERE f.rating = 'down') AS thumbs_down
+         FROM conversation_feedback f
+         JOIN conversations c ON c.id = f.conversation_id
+         JOIN sessions s ON s.id = c.session_id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(summary)
+}
+
+// Feedback counts for turns rated between two UTC dates, inclusive
+pub async fn get_feedback_summary_between(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<FeedbackSummary> {
+    let summary = sqlx::query_as::<_, FeedbackSummary>(
+        "SELECT COUNT(*) AS total,
+                COUNT(*) FILTER (WHERE f.rating = 'up') AS thumbs_up,
+                COUNT(*) FILTER (WHERE f.rating = 'down') AS thumbs_down
+         FROM conversation_feedback f
+         JOIN conversations c ON c.id = f.conversation_id
+         JOIN sessions s ON s.id = c.session_id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND f.created_at >= $3::date AT TIME ZONE 'UTC'
+           AND f.created_at < ($4::date + 1) AT TIME ZONE 'UTC'"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(summary)
+}
+
+pub async fn list_feedback_by_chatbot(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    rating: Option<String>,
+    limit: i64,
+) -> AppResult<Vec<FeedbackEntry>> {
+    let entries = sqlx::query_as::<_, FeedbackEntry>(
+        "SELECT f.conversation_id, f.rating, f.comment, c.user_query, c.bot_response, f.created_at
+         FROM conversation_feedback f
+         JOIN conversations c ON c.id = f.conversation_id
+         JOIN sessions s ON s.id = c.session_id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND ($3::VARCHAR IS NULL OR f.rating = $3)
+         ORDER BY f.created_at DESC
+         LIMIT $4"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(rating)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+// The chunks cited by a chatbot's answers rated since a time, newest ratings first
+pub async fn list_rated_citations(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    since: DateTime<Utc>,
+    limit: i64,
+) -> AppResult<Vec<RatedCitation>> {
+    let citations = sqlx::query_as::<_, RatedCitation>(
+        "SELECT c.id AS conversation_id, f.rating,
+                citation->>'file_path' AS file_path,
+                (citation->>'chunk_index')::bigint AS chunk_index,
+                citation->>'excerpt' AS excerpt,
+                citation->>'url' AS url
+         FROM conversation_feedback f
+         JOIN conversations c ON c.id = f.conversation_id
+         JOIN sessions s ON s.id = c.session_id
+         CROSS JOIN LATERAL jsonb_array_elements(c.citations) citation
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.citations IS NOT NULL AND f.created_at >= $3
+         ORDER BY f.created_at DESC
+         LIMIT $4"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(citations)
+}
+
+pub async fn set_conversation_prompt_variant(pool: &PgPool, conversation_id: Uuid, variant: &str) -> AppResult<()> {
+    sqlx::query("UPDATE conversations SET prompt_variant = $1 WHERE id = $2")
+        .bind(variant)
+        .bind(conversation_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_conversation_citations(pool: &PgPool, conversation_id: Uuid, citations: &[Citation]) -> AppResult<()> {
+    sqlx::query("UPDATE conversations SET citations = $1 WHERE id = $2")
+        .bind(sqlx::types::Json(citations))
+        .bind(conversation_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_conversation_labels(pool: &PgPool, conversation_id: Uuid, sentiment: &str, intent: &str) -> AppResult<()> {
+    sqlx::query("UPDATE conversations SET sentiment = $1, intent = $2 WHERE id = $3")
+        .bind(sentiment)
+        .bind(intent)
+        .bind(conversation_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Label counts over a chatbot's active turns created between two UTC days, inclusive
+pub async fn get_sentiment_summary(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<SentimentSummary> {
+    let summary = sqlx::query_as::<_, SentimentSummary>(
+        "SELECT COUNT(*) FILTER (WHERE c.sentiment IS NOT NULL) AS labelled,
+                COUNT(*) FILTER (WHERE c.sentiment = 'positive') AS positive,
+                COUNT(*) FILTER (WHERE c.sentiment = 'neutral') AS neutral,
+                COUNT(*) FILTER (WHERE c.sentiment = 'negative') AS negative,
+                COUNT(*) FILTER (WHERE c.sentiment = 'frustrated') AS frustrated,
+                COUNT(*) FILTER (WHERE c.intent = 'question') AS questions,
+                COUNT(*) FILTER (WHERE c.intent = 'complaint') AS complaints,
+                COUNT(*) FILTER (WHERE c.intent = 'praise') AS praise,
+                COUNT(*) FILTER (WHERE c.intent = 'escalation') AS escalations
+         FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.created_at >= $3::date AT TIME ZONE 'UTC'
+           AND c.created_at < ($4::date + 1) AT TIME ZONE 'UTC'"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(summary)
+}
+
+// Query and answer counts per detected language over a chatbot's active turns created between two
+// UTC days, inclusive, most asked first
+pub async fn list_language_counts(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<Vec<LanguageCount>> {
+    let counts = sqlx::query_as::<_, LanguageCount>(
+        "SELECT l.language,
+                COUNT(*) FILTER (WHERE l.side = 'query') AS queries,
+                COUNT(*) FILTER (WHERE l.side = 'answer') AS answers
+         FROM conversations c JOIN sessions s ON s.id = c.session_id,
+              LATERAL (VALUES ('query', c.query_language), ('answer', c.answer_language)) AS l(side, language)
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.created_at >= $3::date AT TIME ZONE 'UTC'
+           AND c.created_at < ($4::date + 1) AT TIME ZONE 'UTC'
+           AND l.language IS NOT NULL
+         GROUP BY l.language
+         ORDER BY queries DESC, answers DESC, l.language"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+// Turns in the same range whose answer was detected in a different language than their question
+pub async fn count_language_mismatches(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<i64> {
+    let mismatched: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*)
+         FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.created_at >= $3::date AT TIME ZONE 'UTC'
+           AND c.created_at < ($4::date + 1) AT TIME ZONE 'UTC'
+           AND c.query_language <> c.answer_language"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(mismatched)
+}
+
+// Every active turn of a chat with its citations, in order, for transcript exports
+pub async fn list_conversation_exports(pool: &PgPool, organization_id: Uuid, chat_id: Uuid) -> AppResult<Vec<ConversationExport>> {
+    let conversations = sqlx::query_as::<_, ConversationExport>(
+        "SELECT c.id, c.sequence_number, c.thread_id, c.user_query, c.bot_response, c.citations, c.created_at, c.updated_at, c.author
+         FROM conversations c JOIN sessions s ON s.id = c.session_id
+         WHERE c.chat_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+         ORDER BY c.sequence_number ASC"
+    )
+    .bind(chat_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(conversations)
+}
+
+pub async fn insert_retrieval_log(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    chunks: &[LoggedChunk],
+    prompt: &str,
+    retrieval_trace: &serde_json::Value,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO retrieval_log (conversation_id, chunks, prompt, retrieval_trace) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (conversation_id) DO UPDATE SET chunks = EXCLUDED.chunks, prompt = EXCLUDED.prompt,
+             retrieval_trace = EXCLUDED.retrieval_trace, created_at = NOW()"
+    )
+    .bind(conversation_id)
+    .bind(sqlx::types::Json(chunks))
+    .bind(prompt)
+    .bind(sqlx::types::Json(retrieval_trace))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_retrieval_log(
+    pool: &PgPool,
+    organization_id: Uuid,
+    conversation_id: Uuid,
+) -> AppResult<Option<RetrievalLog>> {
+    let log = sqlx::query_as::<_, RetrievalLog>(
+        "SELECT r.* FROM retrieval_log r
+         JOIN conversations c ON c.id = r.conversation_id JOIN sessions s ON s.id = c.session_id
+         WHERE r.conversation_id = $1 AND s.organization_id = $2 AND c.status = 'active'"
+    )
+    .bind(conversation_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(log)
+}
+
+// Feedback and output filter outcomes per prompt variant for turns since the canary started
+pub async fn get_prompt_variant_metrics(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    since: DateTime<Utc>,
+) -> AppResult<Vec<PromptVariantMetrics>> {
+    let metrics = sqlx::query_as::<_, PromptVariantMetrics>(
+        "SELECT c.prompt_variant AS variant,
+                COUNT(*) AS turns,
+                COUNT(f.id) AS rated,
+                COUNT(*) FILTER (WHERE f.rating = 'up') AS up,
+                COUNT(*) FILTER (WHERE f.rating = 'down') AS down,
+                COUNT(i.conversation_id) AS filtered
+         FROM conversations c
+         JOIN sessions s ON s.id = c.session_id
+         LEFT JOIN conversation_feedback f ON f.conversation_id = c.id
+         LEFT JOIN (SELECT DISTINCT conversation_id FROM output_filter_incidents) i ON i.conversation_id = c.id
+         WHERE c.chatbot_id = $1 AND s.organization_id = $2 AND c.status = 'active'
+           AND c.prompt_variant IS NOT NULL AND c.created_at >= $3
+         GROUP BY c.prompt_variant"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(metrics)
+}
+
+// Glossary operations
+pub async fn upsert_glossary_entry(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    term: String,
+    definition: String,
+    aliases: Vec<String>,
+) -> AppResult<Option<GlossaryEntry>> {
+    let entry = sqlx::query_as::<_, GlossaryEntry>(
+        "INSERT INTO chatbot_glossary (chatbot_id, term, definition, aliases)
+         SELECT b.id, $3, $4, $5 FROM chat_bot b
+         WHERE b.id = $1 AND b.organization_id = $2 AND b.status = 'active'
+         ON CONFLICT (chatbot_id, term) DO UPDATE SET definition = EXCLUDED.definition, aliases = EXCLUDED.aliases
+         RETURNING *"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(term)
+    .bind(definition)
+    .bind(aliases)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(entry)
+}
+
+pub async fn list_glossary_entries(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<GlossaryEntry>> {
+    let entries = sqlx::query_as::<_, GlossaryEntry>(
+        "SELECT g.* FROM chatbot_glossary g JOIN chat_bot b ON b.id = g.chatbot_id
+         WHERE g.chatbot_id = $1 AND b.organization_id = $2
+         ORDER BY g.term ASC"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub async fn delete_glossary_entry(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid, entry_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query(
+        "DELETE FROM chatbot_glossary g USING chat_bot b
+         WHERE g.id = $1 AND g.chatbot_id = $2 AND b.id = g.chatbot_id AND b.organization_id = $3"
+    )
+    .bind(entry_id)
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Document usage and cold storage operations
+pub async fn touch_document_usage(pool: &PgPool, chatbot_id: Uuid, file_paths: &[String]) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO document_usage (chatbot_id, file_path)
+         SELECT $1, UNNEST($2::TEXT[])
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE SET last_retrieved_at = NOW()"
+    )
+    .bind(chatbot_id)
+    .bind(file_paths)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_stale_documents(pool: &PgPool, months: i32, limit: i64) -> AppResult<Vec<StaleDocument>> {
+    let documents = sqlx::query_as::<_, StaleDocument>(
+        "SELECT u.chatbot_id, b.organization_id, b.shard_count, u.file_path
+         FROM document_usage u JOIN chat_bot b ON b.id = u.chatbot_id
+         WHERE u.tier = 'hot' AND b.status = 'active' AND b.organization_id IS NOT NULL
+           AND u.last_retrieved_at < NOW() - make_interval(months => $1)
+         ORDER BY u.last_retrieved_at ASC
+         LIMIT $2"
+    )
+    .bind(months)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(documents)
+}
+
+pub async fn delete_document_usage(pool: &PgPool, chatbot_id: Uuid, file_path: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM document_usage WHERE chatbot_id = $1 AND file_path = $2")
+        .bind(chatbot_id)
+        .bind(file_path)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Save a document's chunks to the cold store and mark it cold
+pub async fn store_cold_document(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    file_path: &str,
+    index_name: &str,
+    chunk_count: i32,
+    payload: &[u8],
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO cold_documents (chatbot_id, file_path, index_name, chunk_count, payload)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE
+         SET index_name = EXCLUDED.index_name, chunk_count = EXCLUDED.chunk_count,
+             payload = EXCLUDED.payload, archived_at = NOW()"
+    )
+    .bind(chatbot_id)
+    .bind(file_path)
+    .bind(index_name)
+    .bind(chunk_count)
+    .bind(payload)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE document_usage SET tier = 'cold' WHERE chatbot_id = $1 AND file_path = $2")
+        .bind(chatbot_id)
+        .bind(file_path)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn get_cold_document(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    file_path: &str,
+) -> AppResult<Option<ColdDocument>> {
+    let document = sqlx::query_as::<_, ColdDocument>(
+        "SELECT d.* FROM cold_documents d JOIN chat_bot b ON b.id = d.chatbot_id
+         WHERE d.chatbot_id = $1 AND d.file_path = $2 AND b.organization_id = $3"
+    )
+    .bind(chatbot_id)
+    .bind(file_path)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(document)
+}
+
+pub async fn list_cold_documents(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<ColdDocumentSummary>> {
+    let documents = sqlx::query_as::<_, ColdDocumentSummary>(
+        "SELECT d.file_path, d.chunk_count, d.archived_at
+         FROM cold_documents d JOIN chat_bot b ON b.id = d.chatbot_id
+         WHERE d.chatbot_id = $1 AND b.organization_id = $2
+         ORDER BY d.archived_at DESC"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(documents)
+}
+
+// Drop a document from the cold store after rehydration and mark it hot again
+pub async fn remove_cold_document(pool: &PgPool, chatbot_id: Uuid, file_path: &str) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM cold_documents WHERE chatbot_id = $1 AND file_path = $2")
+        .bind(chatbot_id)
+        .bind(file_path)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO document_usage (chatbot_id, file_path) VALUES ($1, $2)
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE SET tier = 'hot', last_retrieved_at = NOW()"
+    )
+    .bind(chatbot_id)
+    .bind(file_path)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Prompt template operations
+pub async fn create_prompt_template(
+    pool: &PgPool,
+    organization_id: Uuid,
+    name: String,
+    template: String,
+) -> AppResult<PromptTemplate> {
+    let prompt_template = sqlx::query_as::<_, PromptTemplate>(
+        "INSERT INTO prompt_templates (organization_id, name, template) VALUES ($1, $2, $3) RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(name)
+    .bind(template)
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(prompt_template)
+}
+
+pub async fn list_prompt_templates(pool: &PgPool, organization_id: Uuid) -> AppResult<Vec<PromptTemplate>> {
+    let prompt_templates = sqlx::query_as::<_, PromptTemplate>(
+        "SELECT * FROM prompt_templates WHERE organization_id = $1 ORDER BY name"
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(prompt_templates)
+}
+
+pub async fn get_prompt_template(pool: &PgPool, organization_id: Uuid, template_id: Uuid) -> AppResult<Option<PromptTemplate>> {
+    let prompt_template = sqlx::query_as::<_, PromptTemplate>(
+        "SELECT * FROM prompt_templates WHERE id = $1 AND organization_id = $2"
+    )
+    .bind(template_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(prompt_template)
+}
+
+pub async fn update_prompt_template(
+    pool: &PgPool,
+    organization_id: Uuid,
+    template_id: Uuid,
+    name: String,
+    template: String,
+) -> AppResult<Option<PromptTemplate>> {
+    let prompt_template = sqlx::query_as::<_, PromptTemplate>(
+        "UPDATE prompt_templates SET name = $1, template = $2
+         WHERE id = $3 AND organization_id = $4 RETURNING *"
+    )
+    .bind(name)
+    .bind(template)
+    .bind(template_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+    
+    Ok(prompt_template)
+}
+
+// Delete a template and unselect it; returns the chatbots that were using it, or None if it wasn't found
+pub async fn delete_prompt_template(pool: &PgPool, organization_id: Uuid, template_id: Uuid) -> AppResult<Option<Vec<Uuid>>> {
+    let mut tx = pool.begin().await?;
+
+    // Unselect the template and stop any canary running it
+    let chatbot_ids = sqlx::query_scalar::<_, Uuid>(
+        "UPDATE chat_bot SET prompt_template_id = NULLIF(prompt_template_id, $1),
+                canary_template_id = NULLIF(canary_template_id, $1),
+                canary_percent = CASE WHEN canary_template_id = $1 THEN 0 ELSE canary_percent END,
+                canary_started_at = CASE WHEN canary_template_id = $1 THEN NULL ELSE canary_started_at END
+         WHERE (prompt_template_id = $1 OR canary_template_id = $1) AND organization_id = $2 RETURNING id"
+    )
+    .bind(template_id)
+    .bind(organization_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let result = sqlx::query("DELETE FROM prompt_templates WHERE id = $1 AND organization_id = $2")
+        .bind(template_id)
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    tx.commit().await?;
+    
+    Ok(Some(chatbot_ids))
+}
+
+// Custom domains
+// The chatbot, when given, must belong to the organization; None means it doesn't
+pub async fn create_custom_domain(
+    pool: &PgPool,
+    organization_id: Uuid,
+    hostname: &str,
+    chatbot_id: Option<Uuid>,
+    branding: Option<WidgetBranding>,
+) -> AppResult<Option<CustomDomain>> {
+    let domain = sqlx::query_as::<_, CustomDomain>(
+        "INSERT INTO custom_domains (hostname, organization_id, chatbot_id, branding)
+         SELECT $1, $2, $3, $4
+         WHERE $3::uuid IS NULL OR EXISTS (SELECT 1 FROM chat_bot WHERE id = $3 AND organization_id = $2 AND status = 'active')
+         RETURNING *"
+    )
+    .bind(hostname)
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(branding.map(sqlx::types::Json))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(domain)
+}
+
+pub async fn list_custom_domains(pool: &PgPool, organization_id: Uuid) -> AppResult<Vec<CustomDomain>> {
+    let domains = sqlx::query_as::<_, CustomDomain>(
+        "SELECT * FROM custom_domains WHERE organization_id = $1 ORDER BY hostname"
+    )
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(domains)
+}
+
+pub async fn get_custom_domain_by_hostname(pool: &PgPool, hostname: &str) -> AppResult<Option<CustomDomain>> {
+    let domain = sqlx::query_as::<_, CustomDomain>("SELECT * FROM custom_domains WHERE hostname = $1")
+        .bind(hostname)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(domain)
+}
+
+pub async fn update_custom_domain(
+    pool: &PgPool,
+    organization_id: Uuid,
+    hostname: &str,
+    chatbot_id: Option<Uuid>,
+    branding: Option<WidgetBranding>,
+) -> AppResult<Option<CustomDomain>> {
+    let domain = sqlx::query_as::<_, CustomDomain>(
+        "UPDATE custom_domains SET chatbot_id = $3, branding = $4
+         WHERE hostname = $1 AND organization_id = $2
+           AND ($3::uuid IS NULL OR EXISTS (SELECT 1 FROM chat_bot WHERE id = $3 AND organization_id = $2 AND status = 'active'))
+         RETURNING *"
+    )
+    .bind(hostname)
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(branding.map(sqlx::types::Json))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(domain)
+}
+
+pub async fn delete_custom_domain(pool: &PgPool, organization_id: Uuid, hostname: &str) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM custom_domains WHERE hostname = $1 AND organization_id = $2")
+        .bind(hostname)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Output filter incidents
+pub async fn insert_output_filter_incidents(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    stage: FilterStage,
+    conversation_id: Option<Uuid>,
+    incidents: &[FilterIncident],
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    for incident in incidents {
+        sqlx::query(
+            "INSERT INTO output_filter_incidents (chatbot_id, conversation_id, stage, rule_name, action, matched_text)
+             VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(chatbot_id)
+        .bind(conversation_id)
+        .bind(stage.as_str())
+        .bind(&incident.rule_name)
+        .bind(incident.action.as_str())
+        .bind(&incident.matched_text)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    
+    Ok(())
+}
+
+pub async fn list_output_filter_incidents(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    limit: i64,
+) -> AppResult<Vec<OutputFilterIncident>> {
+    let incidents = sqlx::query_as::<_, OutputFilterIncident>(
+        "SELECT i.* FROM output_filter_incidents i JOIN chat_bot b ON b.id = i.chatbot_id
+         WHERE i.chatbot_id = $1 AND b.organization_id = $2
+         ORDER BY i.created_at DESC LIMIT $3"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(incidents)
+}
+
+// pgvector store
+pub async fn create_vector_collection(pool: &PgPool, name: &str, embedding_dim: i32) -> AppResult<()> {
+    // An alias name already serves as a collection
+    sqlx::query(
+        "INSERT INTO vector_collections (name, embedding_dim)
+         SELECT $1, $2 WHERE NOT EXISTS (SELECT 1 FROM vector_aliases WHERE alias = $1)
+         ON CONFLICT (name) DO NOTHING"
+    )
+        .bind(name)
+        .bind(embedding_dim)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Chunks keep their id across rehydration, so an existing id is overwritten. Every query takes a
+// collection or an alias for one
+pub async fn upsert_vector_chunks(pool: &PgPool, collection: &str, documents: &[DocumentWithEmbedding]) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+    let mut stored = 0;
+
+    for document in documents {
+        let result = sqlx::query(
+            "INSERT INTO vector_chunks
+                (collection, id, text, embedding, chunk_index, file_path, chunk_count, parent_index)
+             VALUES (COALESCE((SELECT collection FROM vector_aliases WHERE alias = $1), $1),
+                $2, $3, $4::vector, $5, $6, $7, $8)
+             ON CONFLICT (collection, id) DO UPDATE SET
+                text = EXCLUDED.text,
+                embedding = EXCLUDED.embedding,
+                chunk_index = EXCLUDED.chunk_index,
+                file_path = EXCLUDED.file_path,
+                chunk_count = EXCLUDED.chunk_count,
+                parent_index = EXCLUDED.parent_index,
+                created_at = NOW()"
+        )
+        .bind(collection)
+        .bind(&document.id)
+        .bind(&document.text)
+        .bind(vector_literal(&document.embedding))
+        .bind(document.chunk_index)
+        .bind(&document.file_path)
+        .bind(document.chunk_count)
+        .bind(document.parent_index)
+        .execute(&mut *tx)
+        .await?;
+        stored += result.rows_affected();
+    }
+
+    tx.commit().await?;
+
+    Ok(stored)
+}
+
+// Nearest chunks by cosine distance, scored like Elasticsearch's cosine similarity: (1 + cos) / 2
+pub async fn search_vector_chunks(pool: &PgPool, collection: &str, query_embedding: &str, limit: i64) -> AppResult<Vec<SearchResult>> {
+    let results = sqlx::query_as::<_, SearchResult>(
+        "SELECT c.text, (1 - (c.embedding <=> $2::vector) / 2)::REAL AS score, c.chunk_index, c.file_path,
+                c.parent_index
+         FROM vector_chunks c
+         JOIN vector_collections v ON v.name = c.collection
+         WHERE c.collection = COALESCE((SELECT collection FROM vector_aliases WHERE alias = $1), $1) AND NOT v.closed
+         ORDER BY c.embedding <=> $2::vector
+         LIMIT $3"
+    )
+    .bind(collection)
+    .bind(query_embedding)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(results)
+}
+
+pub async fn count_vector_chunks(pool: &PgPool, collections: &[String]) -> AppResult<i64> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM vector_chunks c
+         JOIN vector_collections v ON v.name = c.collection
+         WHERE c.collection IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         ) AND NOT v.closed"
+    )
+    .bind(collections)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+// Chunks and aliases go with their collection
+pub async fn delete_vector_collections(pool: &PgPool, collections: &[String]) -> AppResult<()> {
+    sqlx::query(
+        "DELETE FROM vector_collections WHERE name IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         )"
+    )
+        .bind(collections)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_vector_collections_closed(pool: &PgPool, collections: &[String], closed: bool) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE vector_collections SET closed = $2 WHERE name IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         )"
+    )
+        .bind(collections)
+        .bind(closed)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn list_vector_collections(pool: &PgPool, like_pattern: &str) -> AppResult<Vec<String>> {
+    let names = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM vector_collections WHERE name LIKE $1 ORDER BY name"
+    )
+    .bind(like_pattern)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(names)
+}
+
+pub async fn list_vector_chunks_by_file(pool: &PgPool, collections: &[String], file_path: &str) -> AppResult<Vec<VectorChunk>> {
+    let chunks = sqlx::query_as::<_, VectorChunk>(
+        "SELECT collection, id, text, embedding::text AS embedding, chunk_index, file_path, chunk_count, parent_index
+         FROM vector_chunks
+         WHERE collection IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         ) AND file_path = $2
+         ORDER BY chunk_index"
+    )
+    .bind(collections)
+    .bind(file_path)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chunks)
+}
+
+pub async fn list_vector_chunks_by_parent(
+    pool: &PgPool,
+    collections: &[String],
+    file_path: &str,
+    parent_indices: &[i64],
+) -> AppResult<Vec<VectorChunkText>> {
+    let chunks = sqlx::query_as::<_, VectorChunkText>(
+        "SELECT id, text, chunk_index, file_path, chunk_count, parent_index
+         FROM vector_chunks
+         WHERE collection IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         ) AND file_path = $2 AND parent_index = ANY($3)
+         ORDER BY chunk_index"
+    )
+    .bind(collections)
+    .bind(file_path)
+    .bind(parent_indices)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chunks)
+}
+
+pub async fn delete_vector_chunks_by_file(pool: &PgPool, collections: &[String], file_path: &str) -> AppResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM vector_chunks
+         WHERE collection IN (
+            SELECT COALESCE(a.collection, n.name) FROM unnest($1::text[]) AS n(name)
+            LEFT JOIN vector_aliases a ON a.alias = n.name
+         ) AND file_path = $2"
+    )
+        .bind(collections)
+        .bind(file_path)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Keyset pagination by id, so pages stay stable while chunks are read
+pub async fn list_vector_chunks_page(
+    pool: &PgPool,
+    collection: &str,
+    after_id: Option<&str>,
+    limit: i64,
+) -> AppResult<Vec<VectorChunkText>> {
+    let chunks = sqlx::query_as::<_, VectorChunkText>(
+        "SELECT id, text, chunk_index, file_path, chunk_count, parent_index
+         FROM vector_chunks
+         WHERE collection = COALESCE((SELECT collection FROM vector_aliases WHERE alias = $1), $1)
+           AND ($2::text IS NULL OR id > $2)
+         ORDER BY id
+         LIMIT $3"
+    )
+    .bind(collection)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chunks)
+}
+
+pub async fn resolve_vector_alias(pool: &PgPool, alias: &str) -> AppResult<Option<String>> {
+    let collection = sqlx::query_scalar::<_, String>("SELECT collection FROM vector_aliases WHERE alias = $1")
+        .bind(alias)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(collection)
+}
+
+// Repoint aliases in one transaction, replacing collections that held an alias's name.
+// Returns the collections the aliases pointed at before
+pub async fn swap_vector_aliases(pool: &PgPool, swaps: &[(String, String)]) -> AppResult<Vec<String>> {
+    let mut tx = pool.begin().await?;
+    let mut previous = Vec::new();
+
+    for (alias, collection) in swaps {
+        let current = sqlx::query_scalar::<_, String>("SELECT collection FROM vector_aliases WHERE alias = $1 FOR UPDATE")
+            .bind(alias)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match current {
+            Some(current) => previous.push(current),
+            None => {
+                sqlx::query("DELETE FROM vector_collections WHERE name = $1")
+                    .bind(alias)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        sqlx::query(
+            "INSERT INTO vector_aliases (alias, collection) VALUES ($1, $2)
+             ON CONFLICT (alias) DO UPDATE SET collection = EXCLUDED.collection"
+        )
+        .bind(alias)
+        .bind(collection)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(previous)
+}
+
+// Secondary Elasticsearch resyncs
+pub async fn mark_secondary_resync(pool: &PgPool, collections: &[String], operation: &str) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO secondary_resyncs (collection, operation)
+         SELECT DISTINCT name, $2 FROM unnest($1::text[]) AS n(name)
+         ON CONFLICT (collection) DO UPDATE SET operation = EXCLUDED.operation, marked_at = NOW()"
+    )
+    .bind(collections)
+    .bind(operation)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_secondary_resyncs(pool: &PgPool) -> AppResult<Vec<SecondaryResync>> {
+    let resyncs = sqlx::query_as::<_, SecondaryResync>("SELECT * FROM secondary_resyncs ORDER BY marked_at")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(resyncs)
+}
+
+pub async fn clear_secondary_resync(pool: &PgPool, collection: &str) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM secondary_resyncs WHERE collection = $1")
+        .bind(collection)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Single sign-on operations
+pub async fn create_oidc_login_state(pool: &PgPool, state: &str, nonce: &str, code_verifier: &str) -> AppResult<()> {
+    sqlx::query("INSERT INTO oidc_login_states (state, nonce, code_verifier) VALUES ($1, $2, $3)")
+        .bind(state)
+        .bind(nonce)
+        .bind(code_verifier)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Remove and return a login request so its state can only be used once; expired ones are not returned
+pub async fn take_oidc_login_state(pool: &PgPool, state: &str, max_age_secs: i64) -> AppResult<Option<OidcLoginState>> {
+    let login_state = sqlx::query_as::<_, OidcLoginState>(
+        "DELETE FROM oidc_login_states WHERE state = $1 RETURNING *"
+    )
+    .bind(state)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(login_state.filter(|login| (Utc::now() - login.created_at).num_seconds() <= max_age_secs))
+}
+
+// Drop login requests that were never completed and sessions that have expired
+pub async fn delete_expired_sso_rows(pool: &PgPool, login_max_age_secs: i64) -> AppResult<u64> {
+    let states = sqlx::query("DELETE FROM oidc_login_states WHERE created_at < NOW() - make_interval(secs => $1)")
+        .bind(login_max_age_secs as f64)
+        .execute(pool)
+        .await?;
+    let sessions = sqlx::query("DELETE FROM admin_sessions WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(states.rows_affected() + sessions.rows_affected())
+}
+
+pub async fn create_admin_session(
+    pool: &PgPool,
+    token_hash: &str,
+    subject: &str,
+    email: Option<&str>,
+    role: &str,
+    ttl_secs: i64,
+) -> AppResult<AdminSession> {
+    let session = sqlx::query_as::<_, AdminSession>(
+        "INSERT INTO admin_sessions (token_hash, subject, email, role, expires_at)
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))
+         RETURNING subject, email, role, created_at, expires_at"
+    )
+    .bind(token_hash)
+    .bind(subject)
+    .bind(email)
+    .bind(role)
+    .bind(ttl_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn get_admin_session(pool: &PgPool, token_hash: &str) -> AppResult<Option<AdminSession>> {
+    let session = sqlx::query_as::<_, AdminSession>(
+        "SELECT subject, email, role, created_at, expires_at FROM admin_sessions
+         WHERE token_hash = $1 AND expires_at > NOW()"
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn delete_admin_session(pool: &PgPool, token_hash: &str) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM admin_sessions WHERE token_hash = $1")
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Guest session operations
+pub async fn create_guest_session(
+    pool: &PgPool,
+    token_hash: &str,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    session_id: Uuid,
+    ttl_secs: i64,
+) -> AppResult<GuestSession> {
+    let session = sqlx::query_as::<_, GuestSession>(
+        "INSERT INTO guest_sessions (token_hash, organization_id, chatbot_id, session_id, expires_at)
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))
+         RETURNING organization_id, chatbot_id, session_id, created_at, expires_at"
+    )
+    .bind(token_hash)
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(session_id)
+    .bind(ttl_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(session)
+}
+
+// Unexpired guest session of a chatbot that still allows guests
+pub async fn get_guest_session(pool: &PgPool, token_hash: &str) -> AppResult<Option<GuestSession>> {
+    let session = sqlx::query_as::<_, GuestSession>(
+        "SELECT g.organization_id, g.chatbot_id, g.session_id, g.created_at, g.expires_at
+         FROM guest_sessions g JOIN chat_bot b ON b.id = g.chatbot_id
+         WHERE g.token_hash = $1 AND g.expires_at > NOW() AND b.status = 'active' AND b.guest_access"
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session)
+}
+
+pub async fn delete_expired_guest_sessions(pool: &PgPool) -> AppResult<u64> {
+    let result = sqlx::query("DELETE FROM guest_sessions WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// SCIM provisioning operations
+pub async fn get_organization_by_scim_token_hash(pool: &PgPool, token_hash: &str) -> AppResult<Option<Organization>> {
+    let organization = sqlx::query_as::<_, Organization>(
+        "SELECT * FROM organizations WHERE scim_token_hash = $1 AND status = 'active'"
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(organization)
+}
+
+pub async fn set_organization_scim_token(pool: &PgPool, organization_id: Uuid, token_hash: &str) -> AppResult<()> {
+    sqlx::query("UPDATE organizations SET scim_token_hash = $1 WHERE id = $2")
+        .bind(token_hash)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Users matching optional userName (email, case-insensitive) and externalId filters, a page at a time
+pub async fn list_scim_users(
+    pool: &PgPool,
+    organization_id: Uuid,
+    email: Option<&str>,
+    external_id: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> AppResult<(Vec<User>, i64)> {
+    let filter = "organization_id = $1 AND status = 'active'
+         AND ($2::text IS NULL OR LOWER(email) = LOWER($2))
+         AND ($3::text IS NULL OR external_id = $3)";
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {}", filter))
+        .bind(organization_id)
+        .bind(email)
+        .bind(external_id)
+        .fetch_one(pool)
+        .await?;
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT * FROM users WHERE {} ORDER BY created_at ASC, id ASC OFFSET $4 LIMIT $5",
+        filter
+    ))
+    .bind(organization_id)
+    .bind(email)
+    .bind(external_id)
+    .bind(offset)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok((users, total))
+}
+
+pub async fn get_user(pool: &PgPool, organization_id: Uuid, user_id: Uuid) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users WHERE id = $1 AND organization_id = $2 AND status = 'active'"
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+// Create a user, reviving a deleted one with the same email. None if an active user has the email
+pub async fn create_scim_user(
+    pool: &PgPool,
+    organization_id: Uuid,
+    email: &str,
+    display_name: Option<&str>,
+    external_id: Option<&str>,
+    active: bool,
+) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (organization_id, email, display_name, external_id, active)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (organization_id, email) DO UPDATE
+         SET display_name = EXCLUDED.display_name, external_id = EXCLUDED.external_id,
+             active = EXCLUDED.active, status = 'active'
+         WHERE users.status = 'deleted'
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(email)
+    .bind(display_name)
+    .bind(external_id)
+    .bind(active)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn update_scim_user(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    email: &str,
+    display_name: Option<&str>,
+    external_id: Option<&str>,
+    active: bool,
+) -> AppResult<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET email = $3, display_name = $4, external_id = $5, active = $6
+         WHERE id = $1 AND organization_id = $2 AND status = 'active'
+         RETURNING *"
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .bind(email)
+    .bind(display_name)
+    .bind(external_id)
+    .bind(active)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user)
+}
+
+// Soft delete a user and drop their group memberships
+pub async fn delete_user(pool: &PgPool, organization_id: Uuid, user_id: Uuid) -> AppResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "UPDATE users SET status = 'deleted', active = FALSE
+         WHERE id = $1 AND organization_id = $2 AND status = 'active'"
+    )
+    .bind(user_id)
+    .bind(organization_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM user_group_members WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn list_user_groups(
+    pool: &PgPool,
+    organization_id: Uuid,
+    display_name: Option<&str>,
+    external_id: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> AppResult<(Vec<UserGroup>, i64)> {
+    let filter = "organization_id = $1
+         AND ($2::text IS NULL OR display_name = $2)
+         AND ($3::text IS NULL OR external_id = $3)";
+
+    let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM user_groups WHERE {}", filter))
+        .bind(organization_id)
+        .bind(display_name)
+        .bind(external_id)
+        .fetch_one(pool)
+        .await?;
+    let groups = sqlx::query_as::<_, UserGroup>(&format!(
+        "SELECT * FROM user_groups WHERE {} ORDER BY created_at ASC, id ASC OFFSET $4 LIMIT $5",
+        filter
+    ))
+    .bind(organization_id)
+    .bind(display_name)
+    .bind(external_id)
+    .bind(offset)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok((groups, total))
+}
+
+pub async fn get_user_group(pool: &PgPool, organization_id: Uuid, group_id: Uuid) -> AppResult<Option<UserGroup>> {
+    let group = sqlx::query_as::<_, UserGroup>(
+        "SELECT * FROM user_groups WHERE id = $1 AND organization_id = $2"
+    )
+    .bind(group_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(group)
+}
+
+pub async fn create_user_group(
+    pool: &PgPool,
+    organization_id: Uuid,
+    display_name: &str,
+    external_id: Option<&str>,
+) -> AppResult<UserGroup> {
+    let group = sqlx::query_as::<_, UserGroup>(
+        "INSERT INTO user_groups (organization_id, display_name, external_id) VALUES ($1, $2, $3) RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(display_name)
+    .bind(external_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(group)
+}
+
+pub async fn update_user_group(
+    pool: &PgPool,
+    organization_id: Uuid,
+    group_id: Uuid,
+    display_name: &str,
+    external_id: Option<&str>,
+) -> AppResult<Option<UserGroup>> {
+    let group = sqlx::query_as::<_, UserGroup>(
+        "UPDATE user_groups SET display_name = $3, external_id = $4
+         WHERE id = $1 AND organization_id = $2
+         RETURNING *"
+    )
+    .bind(group_id)
+    .bind(organization_id)
+    .bind(display_name)
+    .bind(external_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(group)
+}
+
+pub async fn delete_user_group(pool: &PgPool, organization_id: Uuid, group_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM user_groups WHERE id = $1 AND organization_id = $2")
+        .bind(group_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Memberships of the given groups, or of the given users when `by_user` is set
+pub async fn list_group_memberships(pool: &PgPool, ids: &[Uuid], by_user: bool) -> AppResult<Vec<GroupMembership>> {
+    let column = if by_user { "m.user_id" } else { "m.group_id" };
+    let memberships = sqlx::query_as::<_, GroupMembership>(&format!(
+        "SELECT m.group_id, g.display_name AS group_name, m.user_id, u.email
+         FROM user_group_members m
+         JOIN user_groups g ON g.id = m.group_id
+         JOIN users u ON u.id = m.user_id
+         WHERE {} = ANY($1) AND u.status = 'active'
+         ORDER BY g.display_name, u.email",
+        column
+    ))
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(memberships)
+}
+
+// Add and remove group members in one transaction; `replace` first empties the group.
+// Only active users of the group's organization are added
+pub async fn change_group_members(
+    pool: &PgPool,
+    organization_id: Uuid,
+    group_id: Uuid,
+    replace: bool,
+    add: &[Uuid],
+    remove: &[Uuid],
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    if replace {
+        sqlx::query("DELETE FROM user_group_members WHERE group_id = $1")
+            .bind(group_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    if !remove.is_empty() {
+        sqlx::query("DELETE FROM user_group_members WHERE group_id = $1 AND user_id = ANY($2)")
+            .bind(group_id)
+            .bind(remove)
+            .execute(&mut *tx)
+            .await?;
+    }
+    if !add.is_empty() {
+        sqlx::query(
+            "INSERT INTO user_group_members (group_id, user_id)
+             SELECT $1, u.id FROM users u
+             WHERE u.id = ANY($2) AND u.organization_id = $3 AND u.status = 'active'
+             ON CONFLICT DO NOTHING"
+        )
+        .bind(group_id)
+        .bind(add)
+        .bind(organization_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Usage tracking operations
+pub async fn record_usage_event(pool: &PgPool, event: &UsageEvent) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO usage_events
+             (organization_id, chatbot_id, operation, input_tokens, output_tokens, embedding_calls, searches)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)"
+    )
+    .bind(event.organization_id)
+    .bind(event.chatbot_id)
+    .bind(event.operation)
+    .bind(event.input_tokens)
+    .bind(event.output_tokens)
+    .bind(event.embedding_calls)
+    .bind(event.searches)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Daily totals for a chatbot between two UTC dates, inclusive. Days without usage are left out
+pub async fn list_daily_usage(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> AppResult<Vec<UsageDay>> {
+    let days = sqlx::query_as::<_, UsageDay>(
+        "SELECT (created_at AT TIME ZONE 'UTC')::date AS day,
+                COUNT(*) AS requests,
+                COALESCE(SUM(input_tokens), 0)::BIGINT AS input_tokens,
+                COALESCE(SUM(output_tokens), 0)::BIGINT AS output_tokens,
+                COALESCE(SUM(embedding_calls), 0)::BIGINT AS embedding_calls,
+                COALESCE(SUM(searches), 0)::BIGINT AS searches
+         FROM usage_events
+         WHERE chatbot_id = $1 AND organization_id = $2
+           AND created_at >= $3::date AT TIME ZONE 'UTC'
+           AND created_at < ($4::date + 1) AT TIME ZONE 'UTC'
+         GROUP BY day
+         ORDER BY day"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(days)
+}
+
+// Stored document operations
+pub async fn upsert_document(pool: &PgPool, document: &NewDocument) -> AppResult<Document> {
+    let document = sqlx::query_as::<_, Document>(
+        "INSERT INTO documents (organization_id, chatbot_id, file_path, file_name, content_type, storage_key, size_bytes, sha256)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE SET
+            file_name = EXCLUDED.file_name,
+            content_type = EXCLUDED.content_type,
+            storage_key = EXCLUDED.storage_key,
+            size_bytes = EXCLUDED.size_bytes,
+            sha256 = EXCLUDED.sha256
+         RETURNING *"
+    )
+    .bind(document.organization_id)
+    .bind(document.chatbot_id)
+    .bind(&document.file_path)
+    .bind(&document.file_name)
+    .bind(&document.content_type)
+    .bind(&document.storage_key)
+    .bind(document.size_bytes)
+    .bind(&document.sha256)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(document)
+}
+
+// An earlier upload to the chatbot with the same content, if any
+pub async fn find_document_by_hash(pool: &PgPool, chatbot_id: Uuid, sha256: &str) -> AppResult<Option<Document>> {
+    let document = sqlx::query_as::<_, Document>(
+        "SELECT * FROM documents WHERE chatbot_id = $1 AND sha256 = $2 ORDER BY updated_at DESC LIMIT 1"
+    )
+    .bind(chatbot_id)
+    .bind(sha256)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(document)
+}
+
+pub async fn delete_document(pool: &PgPool, document_id: Uuid) -> AppResult<()> {
+    sqlx::query("DELETE FROM documents WHERE id = $1")
+        .bind(document_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_document(pool: &PgPool, organization_id: Uuid, document_id: Uuid) -> AppResult<Option<Document>> {
+    let document = sqlx::query_as::<_, Document>("SELECT * FROM documents WHERE id = $1 AND organization_id = $2")
+        .bind(document_id)
+        .bind(organization_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(document)
+}
+
+pub async fn list_documents(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<Document>> {
+    let documents = sqlx::query_as::<_, Document>(
+        "SELECT * FROM documents WHERE chatbot_id = $1 AND organization_id = $2 ORDER BY updated_at DESC"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(documents)
+}
+
+// Help-center connector operations
+// Replacing a connector's settings restarts it from a full sync
+pub async fn upsert_help_center_connector(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &UpsertHelpCenterConnectorRequest,
+) -> AppResult<HelpCenterConnector> {
+    let connector = sqlx::query_as::<_, HelpCenterConnector>(
+        "INSERT INTO help_center_connectors (organization_id, chatbot_id, provider, subdomain, email, api_token)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (chatbot_id) DO UPDATE SET
+            provider = EXCLUDED.provider,
+            subdomain = EXCLUDED.subdomain,
+            email = EXCLUDED.email,
+            api_token = EXCLUDED.api_token,
+            last_synced_at = NULL
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(&request.provider)
+    .bind(&request.subdomain)
+    .bind(&request.email)
+    .bind(&request.api_token)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn get_help_center_connector(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+) -> AppResult<Option<HelpCenterConnector>> {
+    let connector = sqlx::query_as::<_, HelpCenterConnector>(
+        "SELECT * FROM help_center_connectors WHERE chatbot_id = $1 AND organization_id = $2"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn mark_help_center_synced(pool: &PgPool, connector_id: Uuid, synced_at: DateTime<Utc>) -> AppResult<()> {
+    sqlx::query("UPDATE help_center_connectors SET last_synced_at = $2 WHERE id = $1")
+        .bind(connector_id)
+        .bind(synced_at)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Document paths of the articles a chatbot's connector has indexed
+pub async fn list_help_center_article_paths(pool: &PgPool, chatbot_id: Uuid) -> AppResult<Vec<String>> {
+    let paths = sqlx::query_scalar::<_, String>("SELECT file_path FROM help_center_articles WHERE chatbot_id = $1")
+        .bind(chatbot_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(paths)
+}
+
+pub async fn record_help_center_article(pool: &PgPool, chatbot_id: Uuid, file_path: &str) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO help_center_articles (chatbot_id, file_path) VALUES ($1, $2)
+         ON CONFLICT (chatbot_id, file_path) DO UPDATE SET synced_at = NOW()"
+    )
+    .bind(chatbot_id)
+    .bind(file_path)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_help_center_article(pool: &PgPool, chatbot_id: Uuid, file_path: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM help_center_articles WHERE chatbot_id = $1 AND file_path = $2")
+        .bind(chatbot_id)
+        .bind(file_path)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// SQL connector operations
+pub async fn create_sql_connector(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &CreateSqlConnectorRequest,
+    interval_minutes: i32,
+) -> AppResult<SqlConnector> {
+    let connector = sqlx::query_as::<_, SqlConnector>(
+        "INSERT INTO sql_connectors (organization_id, chatbot_id, name, database_url, query, primary_key, interval_minutes)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(&request.name)
+    .bind(&request.database_url)
+    .bind(&request.query)
+    .bind(&request.primary_key)
+    .bind(interval_minutes)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn list_sql_connectors(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<SqlConnector>> {
+    let connectors = sqlx::query_as::<_, SqlConnector>(
+        "SELECT * FROM sql_connectors WHERE chatbot_id = $1 AND organization_id = $2 ORDER BY name"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(connectors)
+}
+
+pub async fn get_sql_connector(pool: &PgPool, organization_id: Uuid, connector_id: Uuid) -> AppResult<Option<SqlConnector>> {
+    let connector = sqlx::query_as::<_, SqlConnector>(
+        "SELECT * FROM sql_connectors WHERE id = $1 AND organization_id = $2"
+    )
+    .bind(connector_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn delete_sql_connector(pool: &PgPool, organization_id: Uuid, connector_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM sql_connectors WHERE id = $1 AND organization_id = $2")
+        .bind(connector_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Claim connectors of active chatbots that are due, pushing their next sync back by one interval
+// so other servers skip them
+pub async fn claim_due_sql_connectors(pool: &PgPool, limit: i64) -> AppResult<Vec<SqlConnector>> {
+    let connectors = sqlx::query_as::<_, SqlConnector>(
+        "UPDATE sql_connectors c
+         SET next_sync_at = NOW() + c.interval_minutes * INTERVAL '1 minute'
+         WHERE c.id IN (
+             SELECT s.id FROM sql_connectors s JOIN chat_bot b ON b.id = s.chatbot_id
+             WHERE s.next_sync_at <= NOW() AND b.status = 'active'
+             ORDER BY s.next_sync_at
+             LIMIT $1
+             FOR UPDATE OF s SKIP LOCKED
+         )
+         RETURNING c.*"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(connectors)
+}
+
+// Record a successful sync and schedule the next one
+pub async fn record_sql_connector_synced(pool: &PgPool, connector_id: Uuid, row_count: i64) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE sql_connectors
+         SET row_count = $2, last_synced_at = NOW(), last_error = NULL,
+             next_sync_at = NOW() + interval_minutes * INTERVAL '1 minute'
+         WHERE id = $1"
+    )
+    .bind(connector_id)
+    .bind(row_count)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_sql_connector_error(pool: &PgPool, connector_id: Uuid, error: &str) -> AppResult<()> {
+    sqlx::query("UPDATE sql_connectors SET last_error = $2 WHERE id = $1")
+        .bind(connector_id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn list_sql_connector_rows(pool: &PgPool, connector_id: Uuid) -> AppResult<Vec<SqlConnectorRow>> {
+    let rows = sqlx::query_as::<_, SqlConnectorRow>(
+        "SELECT row_key, content_hash FROM sql_connector_rows WHERE connector_id = $1"
+    )
+    .bind(connector_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn upsert_sql_connector_row(pool: &PgPool, connector_id: Uuid, row: &SqlConnectorRow) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO sql_connector_rows (connector_id, row_key, content_hash) VALUES ($1, $2, $3)
+         ON CONFLICT (connector_id, row_key) DO UPDATE SET content_hash = EXCLUDED.content_hash"
+    )
+    .bind(connector_id)
+    .bind(&row.row_key)
+    .bind(&row.content_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_sql_connector_row(pool: &PgPool, connector_id: Uuid, row_key: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM sql_connector_rows WHERE connector_id = $1 AND row_key = $2")
+        .bind(connector_id)
+        .bind(row_key)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Web source operations
+pub async fn create_web_source(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    url: &str,
+    interval_minutes: i32,
+) -> AppResult<WebSource> {
+    let source = sqlx::query_as::<_, WebSource>(
+        "INSERT INTO web_sources (organization_id, chatbot_id, url, interval_minutes)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(url)
+    .bind(interval_minutes)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(source)
+}
+
+pub async fn list_web_sources(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<WebSource>> {
+    let sources = sqlx::query_as::<_, WebSource>(
+        "SELECT * FROM web_sources WHERE chatbot_id = $1 AND organization_id = $2 ORDER BY url"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sources)
+}
+
+pub async fn get_web_source(pool: &PgPool, organization_id: Uuid, source_id: Uuid) -> AppResult<Option<WebSource>> {
+    let source = sqlx::query_as::<_, WebSource>(
+        "SELECT * FROM web_sources WHERE id = $1 AND organization_id = $2"
+    )
+    .bind(source_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(source)
+}
+
+pub async fn delete_web_source(pool: &PgPool, organization_id: Uuid, source_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM web_sources WHERE id = $1 AND organization_id = $2")
+        .bind(source_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Claim web sources of active chatbots that are due, pushing their next crawl back by one interval
+// so other servers skip them
+pub async fn claim_due_web_sources(pool: &PgPool, limit: i64) -> AppResult<Vec<WebSource>> {
+    let sources = sqlx::query_as::<_, WebSource>(
+        "UPDATE web_sources w
+         SET next_crawl_at = NOW() + w.interval_minutes * INTERVAL '1 minute'
+         WHERE w.id IN (
+             SELECT s.id FROM web_sources s JOIN chat_bot b ON b.id = s.chatbot_id
+             WHERE s.next_crawl_at <= NOW() AND b.status = 'active'
+             ORDER BY s.next_crawl_at
+             LIMIT $1
+             FOR UPDATE OF s SKIP LOCKED
+         )
+         RETURNING w.*"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sources)
+}
+
+// Record a successful crawl with the hash of the text now indexed and schedule the next one
+pub async fn record_web_source_crawled(
+    pool: &PgPool,
+    source_id: Uuid,
+    content_hash: &str,
+    changed: bool,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE web_sources
+         SET content_hash = $2, last_crawled_at = NOW(), last_error = NULL,
+             last_changed_at = CASE WHEN $3 THEN NOW() ELSE last_changed_at END,
+             next_crawl_at = NOW() + interval_minutes * INTERVAL '1 minute'
+         WHERE id = $1"
+    )
+    .bind(source_id)
+    .bind(content_hash)
+    .bind(changed)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_web_source_error(pool: &PgPool, source_id: Uuid, error: &str) -> AppResult<()> {
+    sqlx::query("UPDATE web_sources SET last_error = $2 WHERE id = $1")
+        .bind(source_id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Source connector operations
+pub async fn create_source_connector(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &CreateSourceConnectorRequest,
+    access_token: &str,
+    interval_minutes: i32,
+) -> AppResult<SourceConnector> {
+    let connector = sqlx::query_as::<_, SourceConnector>(
+        "INSERT INTO source_connectors (organization_id, chatbot_id, kind, name, access_token, config, interval_minutes)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(&request.kind)
+    .bind(&request.name)
+    .bind(access_token)
+    .bind(request.config.clone().unwrap_or_else(|| serde_json::json!({})))
+    .bind(interval_minutes)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn list_source_connectors(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<SourceConnector>> {
+    let connectors = sqlx::query_as::<_, SourceConnector>(
+        "SELECT * FROM source_connectors WHERE chatbot_id = $1 AND organization_id = $2 ORDER BY name"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(connectors)
+}
+
+pub async fn get_source_connector(pool: &PgPool, organization_id: Uuid, connector_id: Uuid) -> AppResult<Option<SourceConnector>> {
+    let connector = sqlx::query_as::<_, SourceConnector>(
+        "SELECT * FROM source_connectors WHERE id = $1 AND organization_id = $2"
+    )
+    .bind(connector_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(connector)
+}
+
+pub async fn delete_source_connector(pool: &PgPool, organization_id: Uuid, connector_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM source_connectors WHERE id = $1 AND organization_id = $2")
+        .bind(connector_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Claim connectors of active chatbots that are due, pushing their next sync back by one interval
+// so other servers skip them
+pub async fn claim_due_source_connectors(pool: &PgPool, limit: i64) -> AppResult<Vec<SourceConnector>> {
+    let connectors = sqlx::query_as::<_, SourceConnector>(
+        "UPDATE source_connectors c
+         SET next_sync_at = NOW() + c.interval_minutes * INTERVAL '1 minute'
+         WHERE c.id IN (
+             SELECT s.id FROM source_connectors s JOIN chat_bot b ON b.id = s.chatbot_id
+             WHERE s.next_sync_at <= NOW() AND b.status = 'active'
+             ORDER BY s.next_sync_at
+             LIMIT $1
+             FOR UPDATE OF s SKIP LOCKED
+         )
+         RETURNING c.*"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(connectors)
+}
+
+// Record a successful sync with the cursor the next one starts from, and schedule it
+pub async fn record_source_connector_synced(
+    pool: &PgPool,
+    connector_id: Uuid,
+    cursor: Option<&str>,
+    full: bool,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE source_connectors
+         SET cursor = $2, last_synced_at = NOW(), last_error = NULL,
+             last_full_sync_at = CASE WHEN $3 THEN NOW() ELSE last_full_sync_at END,
+             document_count = (SELECT COUNT(*) FROM source_connector_documents WHERE connector_id = $1),
+             next_sync_at = NOW() + interval_minutes * INTERVAL '1 minute'
+         WHERE id = $1"
+    )
+    .bind(connector_id)
+    .bind(cursor)
+    .bind(full)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_source_connector_error(pool: &PgPool, connector_id: Uuid, error: &str) -> AppResult<()> {
+    sqlx::query("UPDATE source_connectors SET last_error = $2 WHERE id = $1")
+        .bind(connector_id)
+        .bind(error)
         .execute(pool)
         .await?;
-    
+
+    Ok(())
+}
+
+pub async fn list_source_connector_documents(pool: &PgPool, connector_id: Uuid) -> AppResult<Vec<SourceConnectorDocument>> {
+    let documents = sqlx::query_as::<_, SourceConnectorDocument>(
+        "SELECT document_key, content_hash FROM source_connector_documents WHERE connector_id = $1"
+    )
+    .bind(connector_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(documents)
+}
+
+pub async fn upsert_source_connector_document(
+    pool: &PgPool,
+    connector_id: Uuid,
+    document: &SourceConnectorDocument,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO source_connector_documents (connector_id, document_key, content_hash) VALUES ($1, $2, $3)
+         ON CONFLICT (connector_id, document_key) DO UPDATE SET content_hash = EXCLUDED.content_hash"
+    )
+    .bind(connector_id)
+    .bind(&document.document_key)
+    .bind(&document.content_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_source_connector_document(pool: &PgPool, connector_id: Uuid, document_key: &str) -> AppResult<()> {
+    sqlx::query("DELETE FROM source_connector_documents WHERE connector_id = $1 AND document_key = $2")
+        .bind(connector_id)
+        .bind(document_key)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Widget config operations
+pub async fn upsert_widget_config(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &UpsertWidgetConfigRequest,
+) -> AppResult<WidgetConfig> {
+    let config = sqlx::query_as::<_, WidgetConfig>(
+        "INSERT INTO widget_configs
+            (chatbot_id, organization_id, primary_color, background_color, text_color, avatar_url, position,
+             launcher_text, allowed_origins)
+         VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, 'bottom-right'), $8, $9)
+         ON CONFLICT (chatbot_id) DO UPDATE SET
+            primary_color = EXCLUDED.primary_color,
+            background_color = EXCLUDED.background_color,
+            text_color = EXCLUDED.text_color,
+            avatar_url = EXCLUDED.avatar_url,
+            position = EXCLUDED.position,
+            launcher_text = EXCLUDED.launcher_text,
+            allowed_origins = EXCLUDED.allowed_origins
+         RETURNING *"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(&request.primary_color)
+    .bind(&request.background_color)
+    .bind(&request.text_color)
+    .bind(&request.avatar_url)
+    .bind(&request.position)
+    .bind(&request.launcher_text)
+    .bind(&request.allowed_origins)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(config)
+}
+
+pub async fn get_widget_config(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Option<WidgetConfig>> {
+    let config = sqlx::query_as::<_, WidgetConfig>(
+        "SELECT * FROM widget_configs WHERE chatbot_id = $1 AND organization_id = $2"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(config)
+}
+
+// Widget config of an active chatbot, for the public widget endpoints that have no tenant
+pub async fn get_public_widget_config(pool: &PgPool, chatbot_id: Uuid) -> AppResult<Option<WidgetConfig>> {
+    let config = sqlx::query_as::<_, WidgetConfig>(
+        "SELECT w.* FROM widget_configs w JOIN chat_bot b ON b.id = w.chatbot_id
+         WHERE w.chatbot_id = $1 AND b.status = 'active'"
+    )
+    .bind(chatbot_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(config)
+}
+
+// Report schedule operations
+pub async fn upsert_report_schedule(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &UpsertReportScheduleRequest,
+) -> AppResult<ReportSchedule> {
+    let schedule = sqlx::query_as::<_, ReportSchedule>(
+        "INSERT INTO report_schedules (organization_id, chatbot_id, webhook_url, webhook_secret, email_recipients)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (chatbot_id) DO UPDATE SET
+            webhook_url = EXCLUDED.webhook_url,
+            webhook_secret = EXCLUDED.webhook_secret,
+            email_recipients = EXCLUDED.email_recipients,
+            last_error = NULL
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(&request.webhook_url)
+    .bind(&request.webhook_secret)
+    .bind(&request.email_recipients)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(schedule)
+}
+
+pub async fn get_report_schedule(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Option<ReportSchedule>> {
+    let schedule = sqlx::query_as::<_, ReportSchedule>(
+        "SELECT * FROM report_schedules WHERE chatbot_id = $1 AND organization_id = $2"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(schedule)
+}
+
+pub async fn delete_report_schedule(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM report_schedules WHERE chatbot_id = $1 AND organization_id = $2")
+        .bind(chatbot_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Claim schedules of active chatbots that are due, moving them to next Monday so other servers
+// skip them
+pub async fn claim_due_report_schedules(pool: &PgPool, limit: i64) -> AppResult<Vec<ReportSchedule>> {
+    let schedules = sqlx::query_as::<_, ReportSchedule>(
+        "UPDATE report_schedules r
+         SET next_report_at = (date_trunc('week', NOW() AT TIME ZONE 'UTC') + INTERVAL '7 days') AT TIME ZONE 'UTC'
+         WHERE r.id IN (
+             SELECT s.id FROM report_schedules s JOIN chat_bot b ON b.id = s.chatbot_id
+             WHERE s.next_report_at <= NOW() AND b.status = 'active'
+             ORDER BY s.next_report_at
+             LIMIT $1
+             FOR UPDATE OF s SKIP LOCKED
+         )
+         RETURNING r.*"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(schedules)
+}
+
+pub async fn record_report_sent(pool: &PgPool, schedule_id: Uuid) -> AppResult<()> {
+    sqlx::query("UPDATE report_schedules SET last_sent_at = NOW(), last_error = NULL WHERE id = $1")
+        .bind(schedule_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Record a failed delivery and retry it after `retry_minutes`
+pub async fn record_report_error(pool: &PgPool, schedule_id: Uuid, error: &str, retry_minutes: i32) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE report_schedules
+         SET last_error = $2, next_report_at = NOW() + $3 * INTERVAL '1 minute'
+         WHERE id = $1"
+    )
+    .bind(schedule_id)
+    .bind(error)
+    .bind(retry_minutes)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// SQL tool operations
+pub async fn upsert_sql_tool(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    request: &UpsertSqlToolRequest,
+) -> AppResult<SqlTool> {
+    let tool = sqlx::query_as::<_, SqlTool>(
+        "INSERT INTO sql_tools (organization_id, chatbot_id, database_url, allowed_tables)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (chatbot_id) DO UPDATE SET
+            database_url = EXCLUDED.database_url,
+            allowed_tables = EXCLUDED.allowed_tables
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .bind(&request.database_url)
+    .bind(&request.allowed_tables)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(tool)
+}
+
+pub async fn get_sql_tool(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Option<SqlTool>> {
+    let tool = sqlx::query_as::<_, SqlTool>(
+        "SELECT * FROM sql_tools WHERE chatbot_id = $1 AND organization_id = $2"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(tool)
+}
+
+pub async fn delete_sql_tool(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<bool> {
+    let result = sqlx::query("DELETE FROM sql_tools WHERE chatbot_id = $1 AND organization_id = $2")
+        .bind(chatbot_id)
+        .bind(organization_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// FAQ cluster operations
+// Questions asked of a chatbot in the last `days` days, newest first
+pub async fn list_chatbot_user_queries(pool: &PgPool, chatbot_id: Uuid, days: i32, limit: i64) -> AppResult<Vec<String>> {
+    let queries = sqlx::query_scalar::<_, String>(
+        "SELECT user_query FROM conversations
+         WHERE chatbot_id = $1 AND status = 'active' AND created_at >= NOW() - $2 * INTERVAL '1 day'
+         ORDER BY created_at DESC
+         LIMIT $3"
+    )
+    .bind(chatbot_id)
+    .bind(days)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(queries)
+}
+
+// Active chatbots asked anything in the last `days` days
+pub async fn list_chatbots_with_recent_conversations(pool: &PgPool, days: i32) -> AppResult<Vec<Uuid>> {
+    let chatbot_ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT b.id FROM chat_bot b
+         WHERE b.status = 'active' AND EXISTS (
+             SELECT 1 FROM conversations c
+             WHERE c.chatbot_id = b.id AND c.status = 'active' AND c.created_at >= NOW() - $1 * INTERVAL '1 day'
+         )"
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chatbot_ids)
+}
+
+// Replace a chatbot's clusters with a fresh set in one transaction
+pub async fn replace_faq_clusters(pool: &PgPool, chatbot_id: Uuid, clusters: &[FaqCluster]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM faq_clusters WHERE chatbot_id = $1")
+        .bind(chatbot_id)
+        .execute(&mut *tx)
+        .await?;
+    for cluster in clusters {
+        sqlx::query(
+            "INSERT INTO faq_clusters (chatbot_id, rank, representative_question, sample_questions, hit_count, computed_at)
+             VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(chatbot_id)
+        .bind(cluster.rank)
+        .bind(&cluster.representative_question)
+        .bind(&cluster.sample_questions)
+        .bind(cluster.hit_count)
+        .bind(cluster.computed_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn list_faq_clusters(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    limit: i64,
+) -> AppResult<Vec<FaqCluster>> {
+    let clusters = sqlx::query_as::<_, FaqCluster>(
+        "SELECT f.* FROM faq_clusters f JOIN chat_bot b ON b.id = f.chatbot_id
+         WHERE f.chatbot_id = $1 AND b.organization_id = $2
+         ORDER BY f.rank
+         LIMIT $3"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(clusters)
+}
+
+// Chatbot health operations
+// Counts behind the health score over the chatbot's finished turns since `since`. A turn's
+// grounding is the best score among its citations, clamped to 0..1
+pub async fn get_health_counts(pool: &PgPool, chatbot_id: Uuid, since: DateTime<Utc>) -> AppResult<HealthCounts> {
+    let counts = sqlx::query_as::<_, HealthCounts>(
+        "SELECT COUNT(*) AS turns,
+                COUNT(top.score) FILTER (WHERE c.generation_status = 'complete') AS grounded_turns,
+                COALESCE(SUM(LEAST(GREATEST(top.score, 0), 1)) FILTER (WHERE c.generation_status = 'complete'), 0)
+                    AS grounding_score_sum,
+                COUNT(*) FILTER (WHERE c.generation_status = 'failed' OR top.score IS NULL) AS unanswered_turns,
+                COUNT(f.id) AS rated_turns,
+                COUNT(*) FILTER (WHERE f.rating = 'up') AS thumbs_up
+         FROM conversations c
+         LEFT JOIN LATERAL (
+             SELECT MAX((citation->>'score')::float8) AS score FROM jsonb_array_elements(c.citations) citation
+         ) top ON TRUE
+         LEFT JOIN conversation_feedback f ON f.conversation_id = c.id
+         WHERE c.chatbot_id = $1 AND c.status = 'active' AND c.created_at >= $2
+           AND c.generation_status IN ('complete', 'failed')"
+    )
+    .bind(chatbot_id)
+    .bind(since)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(counts)
+}
+
+// When the chatbot's knowledge or settings last changed: a document was uploaded or replaced, a
+// reindex completed, or the chatbot row was updated
+pub async fn get_chatbot_last_change(pool: &PgPool, chatbot_id: Uuid) -> AppResult<Option<DateTime<Utc>>> {
+    let changed_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+        "SELECT GREATEST(
+             b.updated_at,
+             (SELECT MAX(d.updated_at) FROM documents d WHERE d.chatbot_id = b.id),
+             (SELECT MAX(j.finished_at) FROM reindex_jobs j WHERE j.chatbot_id = b.id AND j.status = 'completed')
+         )
+         FROM chat_bot b WHERE b.id = $1"
+    )
+    .bind(chatbot_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(changed_at.flatten())
+}
+
+// Active chatbots asked anything in the last `days` days, with their settings
+pub async fn list_chat_bots_with_recent_conversations(pool: &PgPool, days: i32) -> AppResult<Vec<ChatBot>> {
+    let chat_bots = sqlx::query_as::<_, ChatBot>(
+        "SELECT b.* FROM chat_bot b
+         WHERE b.status = 'active' AND EXISTS (
+             SELECT 1 FROM conversations c
+             WHERE c.chatbot_id = b.id AND c.status = 'active' AND c.created_at >= NOW() - $1 * INTERVAL '1 day'
+         )"
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(chat_bots)
+}
+
+pub async fn insert_health_snapshot(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    report: &HealthReport,
+    alerted: bool,
+) -> AppResult<ChatbotHealthSnapshot> {
+    let snapshot = sqlx::query_as::<_, ChatbotHealthSnapshot>(
+        "INSERT INTO chatbot_health_snapshots (chatbot_id, score, groundedness, feedback_ratio, unanswered_rate, turns, alerted)
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"
+    )
+    .bind(chatbot_id)
+    .bind(report.score)
+    .bind(report.groundedness)
+    .bind(report.feedback_ratio)
+    .bind(report.unanswered_rate)
+    .bind(report.turns)
+    .bind(alerted)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(snapshot)
+}
+
+// The last snapshot recorded before `before`, the baseline a change is compared against
+pub async fn get_health_baseline(
+    pool: &PgPool,
+    chatbot_id: Uuid,
+    before: DateTime<Utc>,
+) -> AppResult<Option<ChatbotHealthSnapshot>> {
+    let snapshot = sqlx::query_as::<_, ChatbotHealthSnapshot>(
+        "SELECT * FROM chatbot_health_snapshots
+         WHERE chatbot_id = $1 AND computed_at < $2
+         ORDER BY computed_at DESC
+         LIMIT 1"
+    )
+    .bind(chatbot_id)
+    .bind(before)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(snapshot)
+}
+
+// Whether an alert was already sent for the chatbot since `since`
+pub async fn health_alerted_since(pool: &PgPool, chatbot_id: Uuid, since: DateTime<Utc>) -> AppResult<bool> {
+    let alerted = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (
+             SELECT 1 FROM chatbot_health_snapshots
+             WHERE chatbot_id = $1 AND computed_at >= $2 AND alerted
+         )"
+    )
+    .bind(chatbot_id)
+    .bind(since)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(alerted)
+}
+
+pub async fn list_health_snapshots(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    limit: i64,
+) -> AppResult<Vec<ChatbotHealthSnapshot>> {
+    let snapshots = sqlx::query_as::<_, ChatbotHealthSnapshot>(
+        "SELECT h.* FROM chatbot_health_snapshots h JOIN chat_bot b ON b.id = h.chatbot_id
+         WHERE h.chatbot_id = $1 AND b.organization_id = $2
+         ORDER BY h.computed_at DESC
+         LIMIT $3"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(snapshots)
+}
+
+// Reindex job operations
+// Start a job for the chatbot's next index version. None if one is already running; a running job
+// that hasn't reported progress for `stale_secs` died with its server and is failed first
+pub async fn create_reindex_job(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    stale_secs: i64,
+) -> AppResult<Option<ReindexJob>> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE reindex_jobs
+         SET status = 'failed', error = 'Stopped reporting progress', finished_at = NOW(), updated_at = NOW()
+         WHERE chatbot_id = $1 AND status = 'running' AND updated_at < NOW() - make_interval(secs => $2)"
+    )
+    .bind(chatbot_id)
+    .bind(stale_secs as f64)
+    .execute(&mut *tx)
+    .await?;
+
+    let job = sqlx::query_as::<_, ReindexJob>(
+        "INSERT INTO reindex_jobs (organization_id, chatbot_id, version)
+         SELECT $1, $2, COALESCE(MAX(version), 0) + 1 FROM reindex_jobs WHERE chatbot_id = $2
+         ON CONFLICT DO NOTHING
+         RETURNING *"
+    )
+    .bind(organization_id)
+    .bind(chatbot_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(job)
+}
+
+pub async fn get_reindex_job(pool: &PgPool, organization_id: Uuid, job_id: Uuid) -> AppResult<Option<ReindexJob>> {
+    let job = sqlx::query_as::<_, ReindexJob>("SELECT * FROM reindex_jobs WHERE id = $1 AND organization_id = $2")
+        .bind(job_id)
+        .bind(organization_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(job)
+}
+
+pub async fn list_reindex_jobs(pool: &PgPool, organization_id: Uuid, chatbot_id: Uuid) -> AppResult<Vec<ReindexJob>> {
+    let jobs = sqlx::query_as::<_, ReindexJob>(
+        "SELECT * FROM reindex_jobs WHERE chatbot_id = $1 AND organization_id = $2 ORDER BY created_at DESC"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(jobs)
+}
+
+// Whether a live reindex is copying the chatbot's chunks, during which new chunks would be lost
+pub async fn is_reindex_running(pool: &PgPool, chatbot_id: Uuid, stale_secs: i64) -> AppResult<bool> {
+    let running = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(
+            SELECT 1 FROM reindex_jobs
+            WHERE chatbot_id = $1 AND status = 'running' AND updated_at >= NOW() - make_interval(secs => $2)
+         )"
+    )
+    .bind(chatbot_id)
+    .bind(stale_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(running)
+}
+
+// Returns false when the job is no longer running, e.g. it was failed as stale
+pub async fn update_reindex_job_progress(pool: &PgPool, job_id: Uuid, total_chunks: i64, processed_chunks: i64) -> AppResult<bool> {
+    let result = sqlx::query(
+        "UPDATE reindex_jobs SET total_chunks = $2, processed_chunks = $3, updated_at = NOW()
+         WHERE id = $1 AND status = 'running'"
+    )
+    .bind(job_id)
+    .bind(total_chunks)
+    .bind(processed_chunks)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Mark a job completed, or failed with `error`
+pub async fn finish_reindex_job(pool: &PgPool, job_id: Uuid, error: Option<&str>) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE reindex_jobs
+         SET status = CASE WHEN $2::text IS NULL THEN 'completed' ELSE 'failed' END,
+             error = $2, finished_at = NOW(), updated_at = NOW()
+         WHERE id = $1"
+    )
+    .bind(job_id)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Ingestion log queries
+pub async fn create_ingestion(
+    pool: &PgPool,
+    document: &NewDocument,
+    chunking_strategy: &str,
+) -> AppResult<IngestionLogEntry> {
+    let entry = sqlx::query_as::<_, IngestionLogEntry>(
+        "INSERT INTO ingestion_log
+            (organization_id, chatbot_id, file_path, file_name, content_type, storage_key, size_bytes, sha256,
+             chunking_strategy)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING *"
+    )
+    .bind(document.organization_id)
+    .bind(document.chatbot_id)
+    .bind(&document.file_path)
+    .bind(&document.file_name)
+    .bind(&document.content_type)
+    .bind(&document.storage_key)
+    .bind(document.size_bytes)
+    .bind(&document.sha256)
+    .bind(chunking_strategy)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(entry)
+}
+
+// Move an upload to its next stage once the stage's side effect is done
+pub async fn set_ingestion_stage(pool: &PgPool, ingestion_id: Uuid, stage: &str) -> AppResult<()> {
+    sqlx::query("UPDATE ingestion_log SET stage = $2, updated_at = NOW() WHERE id = $1")
+        .bind(ingestion_id)
+        .bind(stage)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn complete_ingestion(
+    pool: &PgPool,
+    ingestion_id: Uuid,
+    embedding_count: i64,
+    document_id: Option<Uuid>,
+) -> AppResult<()> {
+    sqlx::query(
+        "UPDATE ingestion_log
+         SET stage = 'completed', embedding_count = $2, document_id = $3, error = NULL, updated_at = NOW()
+         WHERE id = $1"
+    )
+    .bind(ingestion_id)
+    .bind(embedding_count)
+    .bind(document_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn fail_ingestion(pool: &PgPool, ingestion_id: Uuid, error: &str) -> AppResult<()> {
+    sqlx::query("UPDATE ingestion_log SET stage = 'failed', error = $2, updated_at = NOW() WHERE id = $1")
+        .bind(ingestion_id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Claim uploads left unfinished for longer than `stale_secs`, which the server that accepted them
+// would have moved along by now had it not stopped. Claiming counts an attempt and pushes
+// `updated_at` forward, so other replicas leave them alone while they are replayed
+pub async fn claim_unfinished_ingestions(
+    pool: &PgPool,
+    stale_secs: i64,
+    limit: i64,
+) -> AppResult<Vec<IngestionLogEntry>> {
+    let entries = sqlx::query_as::<_, IngestionLogEntry>(
+        "UPDATE ingestion_log
+         SET attempts = attempts + 1, updated_at = NOW()
+         WHERE id IN (
+             SELECT id FROM ingestion_log
+             WHERE stage IN ('accepted', 'stored', 'indexed') AND updated_at < NOW() - make_interval(secs => $1)
+             ORDER BY updated_at
+             LIMIT $2
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING *"
+    )
+    .bind(stale_secs as f64)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub async fn list_ingestions(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    limit: i64,
+) -> AppResult<Vec<IngestionLogEntry>> {
+    let entries = sqlx::query_as::<_, IngestionLogEntry>(
+        "SELECT * FROM ingestion_log WHERE chatbot_id = $1 AND organization_id = $2
+         ORDER BY created_at DESC LIMIT $3"
+    )
+    .bind(chatbot_id)
+    .bind(organization_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+// Health check queries
+pub async fn ping_database(pool: &PgPool) -> AppResult<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,9 @@
+pub mod db;
+pub mod errors;
+pub mod middleware;
+pub mod routes;
+pub mod services;
+pub mod utils;
+
+#[cfg(test)]
+mod test_fixtures;
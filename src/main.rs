@@ -1,50 +1,97 @@
-use axum::{routing::get, Router, response::Json};
+use axum::http::HeaderValue;
 use dotenv::dotenv;
-use std::{net::SocketAddr, sync::Arc};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use serde_json::{json, Value};
-use elasticsearch::{Elasticsearch, http::transport::Transport};
-use tower_http::cors::{CorsLayer, Any};
-
-mod routes;
-mod db;
-mod utils;
-mod services;
-mod errors;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use sqlx::PgPool;
+use elasticsearch::{
+    Elasticsearch,
+    http::{transport::{SingleNodeConnectionPool, TransportBuilder}, Url},
+};
+use tower_http::cors::AllowOrigin;
+
+// The server is assembled from the library, so benchmarks and integration tests can reach it
+use rag_rust::{db, middleware, routes, services, utils};
 
 use db::{init_db, run_migrations};
-use utils::config::AppState;
+use middleware::ip_filter::IpFilter;
+use middleware::load_shed::LoadShedder;
+use middleware::rate_limit::RateLimiter;
+use services::answer_cache::AnswerCache;
+use services::cache_invalidation::{spawn_invalidation_listener, ChatbotCache};
+use services::document_store::DocumentStore;
+use services::candle_embedding::{init_embedding_workers, EmbeddingConfig};
+use services::embedding_bench::{run_embedding_bench, BenchConfig};
+use services::elasticsearch::ElasticsearchService;
+use services::elasticsearch_failover::ElasticsearchFailover;
+use services::embedding_cache::EmbeddingCache;
+use services::guest::GuestConfig;
+use services::health::HealthChecker;
+use services::oidc::OidcConfig;
+use services::pgvector::PgVectorStore;
+use services::qdrant::QdrantVectorStore;
+use services::plugins::PluginHost;
+use services::cold_storage::spawn_cold_storage_task;
+use services::connector::spawn_source_connector_task;
+use services::chatbot_health::spawn_health_task;
+use services::faq_clusters::spawn_faq_cluster_task;
+use services::index_lifecycle::{alias_legacy_indices, spawn_index_reconciliation_task};
+use services::ingestion_log::spawn_ingestion_recovery_task;
+use services::partial_response::spawn_stale_generation_task;
+use services::purge::spawn_purge_task;
+use services::reports::spawn_report_task;
+use services::sql_connector::spawn_sql_connector_task;
+use services::web_source::spawn_web_source_task;
+use services::retrieval::StageTimings;
+use services::shutdown::{shutdown_signal, BackgroundJobs};
+use services::token_budget::TokenBudget;
+use services::user_auth::UserAuthConfig;
+use services::vector::{VectorBackend, VectorBackendKind};
+use utils::config::{AppConfig, AppState};
+use utils::telemetry::init_tracing;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Load .env
-    dotenv().ok();
+// Connect to Elasticsearch - server will fail to start if no cluster is reachable
+async fn connect_elasticsearch(
+    config: &AppConfig,
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+) -> anyhow::Result<ElasticsearchFailover> {
+    tracing::info!("Connecting to Elasticsearch...");
+    let primary = elasticsearch_client(&config.elasticsearch_url, config.elasticsearch_timeout)?;
+    let Some(secondary_url) = &config.elasticsearch_secondary_url else {
+        verify_elasticsearch(&primary, &config.elasticsearch_url).await?;
+        return Ok(ElasticsearchFailover::new(ElasticsearchService::new(Arc::new(primary))));
+    };
 
-    // Setup tracing/logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new("info"))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // With a secondary either cluster is enough to start; reads use the secondary until the primary is back
+    let secondary = elasticsearch_client(secondary_url, config.elasticsearch_timeout)?;
+    if let Err(e) = verify_elasticsearch(&primary, &config.elasticsearch_url).await {
+        verify_elasticsearch(&secondary, secondary_url).await.map_err(|_| e)?;
+        tracing::warn!("⚠️ Primary Elasticsearch is unreachable, starting with reads on the secondary");
+    }
+    tracing::info!(
+        "✅ Secondary Elasticsearch at {} kept in sync by {}",
+        secondary_url,
+        config.elasticsearch_secondary_sync
+    );
+    Ok(ElasticsearchFailover::new(ElasticsearchService::new(Arc::new(primary)))
+        .with_secondary(
+            ElasticsearchService::new(Arc::new(secondary)),
+            config.elasticsearch_secondary_sync,
+            jobs,
+            db,
+        ))
+}
 
-    tracing::info!("Starting RAG Server...");
+// Build a client for one Elasticsearch cluster
+fn elasticsearch_client(elasticsearch_url: &str, timeout: Duration) -> anyhow::Result<Elasticsearch> {
+    let transport = TransportBuilder::new(SingleNodeConnectionPool::new(Url::parse(elasticsearch_url)?))
+        .timeout(timeout)
+        .build()?;
+    Ok(Elasticsearch::new(transport))
+}
 
-    // Initialize DB and Qdrant - server will not start if either fails
-    tracing::info!("Connecting to database...");
-    let pool = init_db().await?;
-    tracing::info!("✅ Database connected successfully");
-    
-    // Run database migrations
-    run_migrations(&pool).await?;
-
-    tracing::info!("Connecting to Elasticsearch...");
-    let elasticsearch_url = std::env::var("ELASTICSEARCH_URL").unwrap_or("http://localhost:9200".to_string());
-    
-    // Build Elasticsearch client
-    let transport = Transport::single_node(&elasticsearch_url)?;
-    let elasticsearch_client = Elasticsearch::new(transport);
-    
-    // Test Elasticsearch connection - server will fail to start if this fails
-    tracing::info!("Testing Elasticsearch connection...");
+// Check a cluster answers a ping or a health request
+async fn verify_elasticsearch(elasticsearch_client: &Elasticsearch, elasticsearch_url: &str) -> anyhow::Result<()> {
+    tracing::info!("Testing Elasticsearch connection to {}...", elasticsearch_url);
     
     let mut connection_verified = false;
     
@@ -82,50 +129,188 @@ async fn main() -> anyhow::Result<()> {
     // Final check - fail if no method worked
     if !connection_verified {
         tracing::error!("❌ All Elasticsearch connection tests failed");
-        tracing::error!("Server cannot start without Elasticsearch connection");
         tracing::error!("Please ensure Elasticsearch is running on {}", elasticsearch_url);
         tracing::error!("Try: docker run -p 9200:9200 -e 'discovery.type=single-node' elasticsearch:8.15.0");
         return Err(anyhow::anyhow!("Elasticsearch connection failed - all connection methods failed"));
     }
     
     tracing::info!("✅ Elasticsearch connection verified successfully");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Load .env
+    dotenv().ok();
+
+    // Setup tracing/logging, plus OTLP export when configured
+    let telemetry = init_tracing()?;
+
+    // Size the embedding worker pool before anything is embedded
+    let embedding_config = EmbeddingConfig::from_env()?;
+    let embedding_workers = init_embedding_workers()?;
+    tracing::info!(
+        "Embedding in batches of {} on {} workers",
+        embedding_config.batch_size,
+        embedding_workers
+    );
+
+    // `cargo run -- bench [chunks] [words_per_chunk]` runs the embedding benchmark and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|a| a == "bench").unwrap_or(false) {
+        let config = BenchConfig::from_args(&args[1..]);
+        let report = run_embedding_bench(&config)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    tracing::info!("Starting RAG Server...");
+
+    // Load and validate configuration - server will not start with invalid settings
+    let config = AppConfig::load()?;
+    if let Some(oidc) = OidcConfig::from_env()? {
+        tracing::info!("Admin single sign-on enabled with issuer {}", oidc.issuer_url);
+    }
+    let guest = GuestConfig::from_env()?;
+    match &guest.captcha {
+        Some(captcha) => tracing::info!("Guest sessions need a {} CAPTCHA", captcha.provider),
+        None => tracing::warn!("⚠️ CAPTCHA_PROVIDER not set, guest sessions are opened without a CAPTCHA"),
+    }
+    let user_auth = UserAuthConfig::from_env()?;
+    if user_auth.is_some() {
+        tracing::info!("User sign-in enabled; management routes check user roles");
+    }
+
+    // Initialize DB - server will not start if it fails
+    tracing::info!("Connecting to database...");
+    let pool = init_db(&config).await?;
+    tracing::info!("✅ Database connected successfully");
+    
+    // Run database migrations
+    run_migrations(&pool).await?;
+    let db = Arc::new(pool);
+    let background_jobs = BackgroundJobs::new();
+
+    // Connect the vector store picked for this deployment
+    let vector_store = Arc::new(match config.vector_backend {
+        VectorBackendKind::Elasticsearch => {
+            VectorBackend::Elasticsearch(connect_elasticsearch(&config, &background_jobs, db.clone()).await?)
+        }
+        VectorBackendKind::Pgvector => {
+            let store = PgVectorStore::new(db.clone());
+            store.init().await?;
+            tracing::info!("✅ Using pgvector for embeddings");
+            VectorBackend::Pgvector(store)
+        }
+        VectorBackendKind::Qdrant => {
+            tracing::info!("Connecting to Qdrant at {}", config.qdrant_url);
+            VectorBackend::Qdrant(QdrantVectorStore::connect(&config.qdrant_url, config.qdrant_api_key.clone()).await?)
+        }
+    });
+
+    if let Err(e) = alias_legacy_indices(&db, &vector_store).await {
+        tracing::warn!("⚠️ Failed to alias legacy chatbot indices: {}", e);
+    }
 
     // Shared application state
+    let embedding_cache = Arc::new(EmbeddingCache::from_env(db.clone()));
+    spawn_purge_task(&background_jobs, db.clone());
+    spawn_stale_generation_task(&background_jobs, db.clone());
+    let chatbot_cache = Arc::new(ChatbotCache::new());
+    spawn_invalidation_listener(&background_jobs, db.clone(), chatbot_cache.clone());
+    spawn_index_reconciliation_task(&background_jobs, db.clone(), vector_store.clone());
+    spawn_cold_storage_task(&background_jobs, db.clone(), vector_store.clone(), chatbot_cache.clone());
+    spawn_sql_connector_task(
+        &background_jobs,
+        db.clone(),
+        vector_store.clone(),
+        embedding_cache.clone(),
+        chatbot_cache.clone(),
+    );
+    spawn_web_source_task(
+        &background_jobs,
+        db.clone(),
+        vector_store.clone(),
+        embedding_cache.clone(),
+        chatbot_cache.clone(),
+    );
+    spawn_source_connector_task(
+        &background_jobs,
+        db.clone(),
+        vector_store.clone(),
+        embedding_cache.clone(),
+        chatbot_cache.clone(),
+    );
+    spawn_faq_cluster_task(&background_jobs, db.clone(), vector_store.clone(), embedding_cache.clone());
+    spawn_health_task(&background_jobs, db.clone());
+    spawn_report_task(&background_jobs, db.clone(), chatbot_cache.clone());
+    let document_store = DocumentStore::from_env()?.map(Arc::new);
+    match &document_store {
+        Some(store) => tracing::info!("✅ Keeping uploaded originals in {}", store.location()),
+        None => tracing::warn!("⚠️ Document store disabled, uploads can't be reprocessed"),
+    }
+    let plugins = PluginHost::from_env()?;
+    spawn_ingestion_recovery_task(
+        &background_jobs,
+        db.clone(),
+        vector_store.clone(),
+        embedding_cache.clone(),
+        chatbot_cache.clone(),
+        plugins.clone(),
+        document_store.clone(),
+    );
     let app_state = AppState {
-        db: Arc::new(pool),
-        elasticsearch: Arc::new(elasticsearch_client),
+        db: db.clone(),
+        vector_store,
+        embedding_cache,
+        rate_limiter: Arc::new(RateLimiter::from_env()?),
+        chatbot_cache,
+        answer_cache: Arc::new(AnswerCache::from_env()),
+        stage_timings: Arc::new(StageTimings::new()),
+        background_jobs: background_jobs.clone(),
+        plugins,
+        token_budget: Arc::new(TokenBudget::from_env()?),
+        ip_filter: Arc::new(IpFilter::from_env()?),
+        health: Arc::new(HealthChecker::from_env()?),
+        load_shedder: Arc::new(LoadShedder::from_env()),
+        document_store,
     };
 
-    // Health check handler
-    async fn health_handler() -> Json<Value> {
-        Json(json!({
-            "status": "ok",
-            "message": "RAG Server is running",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }))
-    }
-
-    // Define routes
-    let app = Router::new()
-        .route("/health", get(health_handler))
-        .nest("/api", routes::chatbot::create_chatbot_router())
-        .nest("/api", routes::knowledge::create_knowledge_router())
-        .nest("/api", routes::query::create_query_router())
-        .nest("/api", routes::chat::create_chat_router())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any)
+    // Allow any origin unless specific origins are configured
+    let allowed_origins = if config.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin))
+                .collect::<Result<Vec<_>, _>>()?,
         )
-        .with_state(app_state);
+    };
+
+    let app = routes::build_router(app_state, user_auth, allowed_origins);
 
     // Run server
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
+    let addr = config.socket_addr();
     tracing::info!("🌍 Server running on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    // No new requests are accepted and in-flight ones (including streams) have finished
+    tracing::info!("Waiting for background jobs to finish...");
+    if background_jobs.drain(config.shutdown_timeout).await {
+        tracing::info!("✅ Background jobs finished");
+    } else {
+        tracing::warn!("⚠️ Background jobs still running after {}s, exiting anyway", config.shutdown_timeout.as_secs());
+    }
+
+    db.close().await;
+    tracing::info!("✅ Server shut down cleanly");
+    telemetry.shutdown();
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,351 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, StatusCode},
+};
+use sha2::{Digest, Sha256};
+use tracing;
+use uuid::Uuid;
+
+use crate::db::models::GuestSession;
+use crate::db::queries::{
+    get_admin_session, get_guest_session, get_organization_by_api_key_hash, get_organization_by_scim_token_hash,
+};
+use crate::services::oidc::AdminRole;
+use crate::services::user_auth::{AccessClaims, UserAuthConfig, UserRole};
+use crate::utils::config::AppState;
+
+/// Organization resolved from the caller's `X-API-Key` header, or from a signed-in user's
+/// access token sent as `Authorization: Bearer <token>`.
+///
+/// Every tenant-owned query takes `organization_id`, so handlers that extract
+/// a `Tenant` can only ever see their own organization's data.
+#[derive(Debug, Clone, Copy)]
+pub struct Tenant {
+    pub organization_id: Uuid,
+    /// Set when a user signed in; sessions and chats are then limited to the ones they own
+    pub user_id: Option<Uuid>,
+    /// The signed-in user's role; the organization's API key acts as an owner
+    pub role: UserRole,
+}
+
+impl Tenant {
+    /// 403 unless the caller has at least the given role
+    pub fn require_role(&self, role: UserRole) -> Result<(), StatusCode> {
+        if self.role < role {
+            tracing::warn!("User {:?} with role {} needs role {}", self.user_id, self.role, role);
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(())
+    }
+}
+
+impl FromRequestParts<AppState> for Tenant {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let Some(api_key) = parts.headers.get("x-api-key").and_then(|v| v.to_str().ok()) else {
+            if bearer_token(&parts.headers).is_none() {
+                tracing::warn!("Missing X-API-Key header");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            let claims = access_claims(&parts.headers)?;
+            return Ok(Tenant { organization_id: claims.org, user_id: Some(claims.sub), role: claims.role });
+        };
+
+        match get_organization_by_api_key_hash(&state.db, &hash_api_key(api_key)).await {
+            Ok(Some(organization)) => {
+                Ok(Tenant { organization_id: organization.id, user_id: None, role: UserRole::Owner })
+            }
+            Ok(None) => {
+                tracing::warn!("Rejected unknown API key");
+                Err(StatusCode::UNAUTHORIZED)
+            }
+            Err(e) => {
+                tracing::error!("Failed to resolve API key: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// A signed-in user, from an access token sent as `Authorization: Bearer <token>`
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub organization_id: Uuid,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let claims = access_claims(&parts.headers)?;
+        Ok(AuthUser { user_id: claims.sub, organization_id: claims.org })
+    }
+}
+
+// Claims of the user access token in the Authorization header
+fn access_claims(headers: &HeaderMap) -> Result<AccessClaims, StatusCode> {
+    let token = bearer_token(headers).ok_or_else(|| {
+        tracing::warn!("Missing user access token");
+        StatusCode::UNAUTHORIZED
+    })?;
+    let config = match UserAuthConfig::from_env() {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            tracing::warn!("User access token sent but JWT_SECRET is not set");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            tracing::error!("❌ Invalid user sign-in configuration: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    config.verify_access_token(token).map_err(|e| {
+        tracing::warn!("Rejected user access token: {}", e);
+        StatusCode::UNAUTHORIZED
+    })
+}
+
+/// Anonymous widget visitor holding a guest token as `Authorization: Bearer <token>`. The token
+/// only reaches the chatbot and session it was issued for
+#[derive(Debug, Clone)]
+pub struct Guest(pub GuestSession);
+
+impl FromRequestParts<AppState> for Guest {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers).ok_or_else(|| {
+            tracing::warn!("Missing guest token");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+        match get_guest_session(&state.db, &hash_api_key(token)).await {
+            Ok(Some(session)) => Ok(Guest(session)),
+            Ok(None) => {
+                tracing::warn!("Rejected unknown or expired guest token");
+                Err(StatusCode::UNAUTHORIZED)
+            }
+            Err(e) => {
+                tracing::error!("Failed to resolve guest token: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// The caller as far as the server can verify it, for middleware that runs before the handler's
+/// own extractor. Credentials that don't check out count as anonymous
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Principal {
+    /// A known organization API key
+    Organization(Uuid),
+    /// A valid user access token
+    User { user_id: Uuid, organization_id: Uuid },
+    /// A valid guest token, bound to one chatbot
+    Guest { chatbot_id: Uuid },
+    Anonymous,
+}
+
+impl Principal {
+    pub async fn resolve(headers: &HeaderMap, state: &AppState) -> Principal {
+        if let Some(api_key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+            return match get_organization_by_api_key_hash(&state.db, &hash_api_key(api_key)).await {
+                Ok(Some(organization)) => Principal::Organization(organization.id),
+                Ok(None) => Principal::Anonymous,
+                Err(e) => {
+                    tracing::error!("Failed to resolve API key: {}", e);
+                    Principal::Anonymous
+                }
+            };
+        }
+
+        let Some(token) = bearer_token(headers) else {
+            return Principal::Anonymous;
+        };
+        if let Ok(Some(config)) = UserAuthConfig::from_env()
+            && let Ok(claims) = config.verify_access_token(token)
+        {
+            return Principal::User { user_id: claims.sub, organization_id: claims.org };
+        }
+        match get_guest_session(&state.db, &hash_api_key(token)).await {
+            Ok(Some(session)) => Principal::Guest { chatbot_id: session.chatbot_id },
+            Ok(None) => Principal::Anonymous,
+            Err(e) => {
+                tracing::error!("Failed to resolve guest token: {}", e);
+                Principal::Anonymous
+            }
+        }
+    }
+
+    /// The organization behind an API key or user token
+    pub fn organization_id(&self) -> Option<Uuid> {
+        match self {
+            Principal::Organization(organization_id) => Some(*organization_id),
+            Principal::User { organization_id, .. } => Some(*organization_id),
+            Principal::Guest { .. } | Principal::Anonymous => None,
+        }
+    }
+}
+
+/// Organization resolved from a SCIM token sent as `Authorization: Bearer <token>`,
+/// which is how identity providers authenticate provisioning requests
+#[derive(Debug, Clone, Copy)]
+pub struct ScimTenant {
+    pub organization_id: Uuid,
+}
+
+impl FromRequestParts<AppState> for ScimTenant {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers).ok_or_else(|| {
+            tracing::warn!("Missing SCIM bearer token");
+            StatusCode::UNAUTHORIZED
+        })?;
+
+        match get_organization_by_scim_token_hash(&state.db, &hash_api_key(token)).await {
+            Ok(Some(organization)) => Ok(ScimTenant { organization_id: organization.id }),
+            Ok(None) => {
+                tracing::warn!("Rejected unknown SCIM token");
+                Err(StatusCode::UNAUTHORIZED)
+            }
+            Err(e) => {
+                tracing::error!("Failed to resolve SCIM token: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// Caller allowed to use every admin endpoint: the `ADMIN_API_KEY` in the `X-Admin-Key`
+/// header, or an SSO session with the admin role as `Authorization: Bearer <token>`
+#[derive(Debug, Clone, Copy)]
+pub struct AdminKey;
+
+impl FromRequestParts<AppState> for AdminKey {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match admin_role(parts, state).await? {
+            AdminRole::Admin => Ok(AdminKey),
+            AdminRole::Viewer => {
+                tracing::warn!("Viewer session rejected from an admin-only endpoint");
+                Err(StatusCode::FORBIDDEN)
+            }
+        }
+    }
+}
+
+/// Caller allowed to use read-only admin endpoints: anything `AdminKey` accepts, or an SSO
+/// session with the viewer role
+#[derive(Debug, Clone, Copy)]
+pub struct AdminViewer;
+
+impl FromRequestParts<AppState> for AdminViewer {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        admin_role(parts, state).await.map(|_| AdminViewer)
+    }
+}
+
+// Role of the caller of an admin endpoint; the admin key grants the admin role
+async fn admin_role(parts: &Parts, state: &AppState) -> Result<AdminRole, StatusCode> {
+    if let Some(token) = bearer_token(&parts.headers) {
+        return match get_admin_session(&state.db, &hash_api_key(token)).await {
+            Ok(Some(session)) => session.role.parse().map_err(|e| {
+                tracing::error!("Admin session has an invalid role: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }),
+            Ok(None) => {
+                tracing::warn!("Rejected unknown or expired admin session");
+                Err(StatusCode::UNAUTHORIZED)
+            }
+            Err(e) => {
+                tracing::error!("Failed to resolve admin session: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        };
+    }
+
+    let Ok(expected) = std::env::var("ADMIN_API_KEY") else {
+        tracing::warn!("Admin endpoint called but ADMIN_API_KEY is not set");
+        return Err(StatusCode::FORBIDDEN);
+    };
+
+    let provided = parts.headers.get("x-admin-key").and_then(|v| v.to_str().ok());
+    match provided {
+        Some(key) if hash_api_key(key) == hash_api_key(&expected) => Ok(AdminRole::Admin),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Token from an `Authorization: Bearer` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Generate a new random admin session token
+pub fn generate_admin_session_token() -> String {
+    format!("ras_{}", Uuid::new_v4().simple())
+}
+
+/// Generate a new random refresh token for user sign-in
+pub fn generate_refresh_token() -> String {
+    format!("rrt_{}", Uuid::new_v4().simple())
+}
+
+/// Generate a new random guest token for an anonymous widget visitor
+pub fn generate_guest_token() -> String {
+    format!("rgt_{}", Uuid::new_v4().simple())
+}
+
+/// Generate a new random SCIM provisioning token
+pub fn generate_scim_token() -> String {
+    format!("scim_{}", Uuid::new_v4().simple())
+}
+
+/// Generate a new random API key
+pub fn generate_api_key() -> String {
+    format!("rag_{}", Uuid::new_v4().simple())
+}
+
+/// API keys are stored as SHA-256 hex digests, never in plain text
+pub fn hash_api_key(api_key: &str) -> String {
+    format!("{:x}", Sha256::digest(api_key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_are_unique_and_hash_stably() {
+        let a = generate_api_key();
+        let b = generate_api_key();
+
+        assert_ne!(a, b);
+        assert!(a.starts_with("rag_"));
+        assert_eq!(hash_api_key(&a), hash_api_key(&a));
+        assert_ne!(hash_api_key(&a), hash_api_key(&b));
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, "Bearer ras_abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("ras_abc"));
+
+        headers.insert(header::AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+    }
+}
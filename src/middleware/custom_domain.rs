@@ -0,0 +1,47 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use tracing;
+
+use crate::db::models::CustomDomain;
+use crate::services::custom_domain::request_hostname;
+use crate::utils::config::AppState;
+
+/// Custom domain the request arrived on, attached by `custom_domain_middleware`.
+/// Extracting it rejects requests to hosts that aren't mapped with 404
+#[derive(Debug, Clone)]
+pub struct DomainTenant(pub CustomDomain);
+
+impl<S: Send + Sync> FromRequestParts<S> for DomainTenant {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<DomainTenant>().cloned().ok_or_else(|| {
+            tracing::warn!("Request host is not a registered custom domain");
+            StatusCode::NOT_FOUND
+        })
+    }
+}
+
+// Resolve the request's host to a custom domain, so white-label pages reach their own organization and chatbot
+pub async fn custom_domain_middleware(
+    State(app_state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(hostname) = request_hostname(request.headers()) {
+        match app_state.chatbot_cache.get_domain(&app_state.db, &hostname).await {
+            Ok(Some(domain)) => {
+                request.extensions_mut().insert(DomainTenant(domain));
+            }
+            Ok(None) => {}
+            // Routes that don't need the domain should keep working
+            Err(e) => tracing::warn!("⚠️ Failed to resolve custom domain {}: {}", hostname, e),
+        }
+    }
+
+    next.run(request).await
+}
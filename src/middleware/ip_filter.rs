@@ -0,0 +1,347 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::utils::config::AppState;
+
+/// An IPv4 or IPv6 network such as `10.0.0.0/8`; a bare address matches only itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Compare IPv4-mapped IPv6 clients against IPv4 networks
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            IpAddr::V4(_) => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network: IpAddr = address.parse().map_err(|_| format!("invalid address in '{}'", value))?;
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("invalid prefix length in '{}'", value))?,
+            None => max_prefix,
+        };
+        Ok(Cidr { network, prefix })
+    }
+}
+
+fn parse_cidrs(value: &str) -> Result<Vec<Cidr>, String> {
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+// Paths called by end users' browsers through the widget
+const CHAT_PREFIXES: [&str; 5] = [
+    "/api/chat/",
+    "/api/sessions/",
+    "/api/conversations/",
+    "/api/widget/",
+    "/api/guest/",
+];
+
+/// Routes that share access rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    /// `/api/admin/...`
+    Admin,
+    /// Chat, session, conversation, widget and guest routes
+    Chat,
+    /// Every other API route
+    Api,
+}
+
+impl RouteGroup {
+    const ALL: [RouteGroup; 3] = [RouteGroup::Admin, RouteGroup::Chat, RouteGroup::Api];
+
+    /// The group a request path belongs to; `None` for health checks, which are never filtered
+    pub fn for_path(path: &str) -> Option<Self> {
+        if path == "/health" || path.starts_with("/health/") {
+            None
+        } else if path.starts_with("/api/admin/") {
+            Some(RouteGroup::Admin)
+        } else if path == "/api/chat" || CHAT_PREFIXES.iter().any(|prefix| path.starts_with(prefix)) {
+            Some(RouteGroup::Chat)
+        } else {
+            Some(RouteGroup::Api)
+        }
+    }
+
+    fn env_prefix(self) -> &'static str {
+        match self {
+            RouteGroup::Admin => "IP_FILTER_ADMIN",
+            RouteGroup::Chat => "IP_FILTER_CHAT",
+            RouteGroup::Api => "IP_FILTER_API",
+        }
+    }
+}
+
+/// Allow and deny lists for one route group. A denied address is always rejected; when the
+/// allow list is non-empty, only addresses on it are accepted
+#[derive(Debug, Clone, Default)]
+pub struct IpRules {
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<Cidr>,
+}
+
+impl IpRules {
+    pub fn permits(&self, ip: Option<IpAddr>) -> bool {
+        let Some(ip) = ip else {
+            // An unknown client can't be on the allow list
+            return self.allow.is_empty();
+        };
+        !self.deny.iter().any(|cidr| cidr.contains(ip))
+            && (self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip)))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+/// IP access control for every route group, and the proxies trusted to report client addresses
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    admin: IpRules,
+    chat: IpRules,
+    api: IpRules,
+    trusted_proxies: Vec<Cidr>,
+}
+
+impl IpFilter {
+    /// Reads `IP_FILTER_{ADMIN,CHAT,API}_{ALLOW,DENY}` and `TRUSTED_PROXIES`, each a
+    /// comma-separated list of addresses or CIDR ranges
+    pub fn from_env() -> anyhow::Result<Self> {
+        let list = |name: String| -> anyhow::Result<Vec<Cidr>> {
+            match std::env::var(&name) {
+                Ok(value) => parse_cidrs(&value).map_err(|e| anyhow::anyhow!("{}: {}", name, e)),
+                Err(_) => Ok(Vec::new()),
+            }
+        };
+
+        let mut filter = IpFilter {
+            trusted_proxies: list("TRUSTED_PROXIES".to_string())?,
+            ..Default::default()
+        };
+        for group in RouteGroup::ALL {
+            let rules = IpRules {
+                allow: list(format!("{}_ALLOW", group.env_prefix()))?,
+                deny: list(format!("{}_DENY", group.env_prefix()))?,
+            };
+            if !rules.is_empty() {
+                tracing::info!(
+                    "IP filter for {:?} routes: {} allowed and {} denied ranges",
+                    group,
+                    rules.allow.len(),
+                    rules.deny.len()
+                );
+            }
+            *filter.rules_mut(group) = rules;
+        }
+        Ok(filter)
+    }
+
+    pub fn rules(&self, group: RouteGroup) -> &IpRules {
+        match group {
+            RouteGroup::Admin => &self.admin,
+            RouteGroup::Chat => &self.chat,
+            RouteGroup::Api => &self.api,
+        }
+    }
+
+    fn rules_mut(&mut self, group: RouteGroup) -> &mut IpRules {
+        match group {
+            RouteGroup::Admin => &mut self.admin,
+            RouteGroup::Chat => &mut self.chat,
+            RouteGroup::Api => &mut self.api,
+        }
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// The client's address. `X-Forwarded-For` is only believed when the connection comes from a
+    /// trusted proxy; it is read right to left, skipping trusted proxies, so a client can't spoof
+    /// its address by sending the header itself
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        let mut client = peer?;
+        let hops: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+
+        for hop in hops.iter().rev() {
+            if !self.is_trusted_proxy(client) {
+                break;
+            }
+            match parse_forwarded_ip(hop) {
+                Some(ip) => client = ip,
+                None => break,
+            }
+        }
+        Some(client)
+    }
+}
+
+// Addresses in X-Forwarded-For, tolerating the `[v6]:port` and `v4:port` forms some proxies send
+fn parse_forwarded_ip(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    if let Ok(ip) = hop.parse() {
+        return Some(ip);
+    }
+    hop.parse::<SocketAddr>().ok().map(|addr| addr.ip())
+}
+
+// Reject requests from addresses the route group's rules don't permit
+pub async fn ip_filter_middleware(State(app_state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(group) = RouteGroup::for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let rules = app_state.ip_filter.rules(group);
+    if rules.is_empty() {
+        return next.run(request).await;
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client = app_state.ip_filter.client_ip(request.headers(), peer);
+
+    if rules.permits(client) {
+        next.run(request).await
+    } else {
+        tracing::warn!("Blocked {:?} request to {} from {:?}", group, request.uri().path(), client);
+        (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "success": false,
+                "message": "Access from this address is not allowed"
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn cidrs(value: &str) -> Vec<Cidr> {
+        parse_cidrs(value).unwrap()
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let office: Cidr = "203.0.113.0/24".parse().unwrap();
+        assert!(office.contains(ip("203.0.113.7")));
+        assert!(office.contains(ip("::ffff:203.0.113.7")));
+        assert!(!office.contains(ip("203.0.114.7")));
+
+        let single: Cidr = "198.51.100.4".parse().unwrap();
+        assert!(single.contains(ip("198.51.100.4")));
+        assert!(!single.contains(ip("198.51.100.5")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+
+        let everything: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn test_parse_cidr_errors() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-an-ip".parse::<Cidr>().is_err());
+        assert_eq!(cidrs(" 10.0.0.0/8, ,192.168.0.0/16 ").len(), 2);
+    }
+
+    #[test]
+    fn test_rules_deny_wins_over_allow() {
+        let rules = IpRules { allow: cidrs("10.0.0.0/8"), deny: cidrs("10.0.0.13") };
+        assert!(rules.permits(Some(ip("10.1.2.3"))));
+        assert!(!rules.permits(Some(ip("10.0.0.13"))));
+        assert!(!rules.permits(Some(ip("192.0.2.1"))));
+        assert!(!rules.permits(None));
+
+        let deny_only = IpRules { allow: Vec::new(), deny: cidrs("192.0.2.0/24") };
+        assert!(deny_only.permits(Some(ip("198.51.100.1"))));
+        assert!(deny_only.permits(None));
+    }
+
+    #[test]
+    fn test_route_groups() {
+        assert_eq!(RouteGroup::for_path("/health"), None);
+        assert_eq!(RouteGroup::for_path("/health/ready"), None);
+        assert_eq!(RouteGroup::for_path("/api/admin/organizations"), Some(RouteGroup::Admin));
+        assert_eq!(RouteGroup::for_path("/api/chat"), Some(RouteGroup::Chat));
+        assert_eq!(RouteGroup::for_path("/api/chat/stream"), Some(RouteGroup::Chat));
+        assert_eq!(RouteGroup::for_path("/api/widget/config"), Some(RouteGroup::Chat));
+        assert_eq!(RouteGroup::for_path("/api/guest/sessions"), Some(RouteGroup::Chat));
+        assert_eq!(RouteGroup::for_path("/api/conversations/1/feedback"), Some(RouteGroup::Chat));
+        assert_eq!(RouteGroup::for_path("/api/chats/1/export"), Some(RouteGroup::Api));
+        assert_eq!(RouteGroup::for_path("/api/chatbots"), Some(RouteGroup::Api));
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarded_for_from_proxies() {
+        let filter = IpFilter { trusted_proxies: cidrs("10.0.0.0/8"), ..Default::default() };
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "6.6.6.6, 203.0.113.9, 10.0.0.2".parse().unwrap());
+
+        // Through two trusted proxies: the spoofed left-most entry is ignored
+        assert_eq!(filter.client_ip(&headers, Some(ip("10.0.0.1"))), Some(ip("203.0.113.9")));
+        // Direct connection: the header is ignored
+        assert_eq!(filter.client_ip(&headers, Some(ip("198.51.100.1"))), Some(ip("198.51.100.1")));
+        assert_eq!(filter.client_ip(&headers, None), None);
+
+        headers.insert("x-forwarded-for", "[2001:db8::1]:443".parse().unwrap());
+        assert_eq!(filter.client_ip(&headers, Some(ip("10.0.0.1"))), Some(ip("2001:db8::1")));
+    }
+}
@@ -0,0 +1,255 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::middleware::auth::Principal;
+use crate::middleware::ip_filter::RouteGroup;
+use crate::utils::config::AppState;
+
+// Weight of the newest request in the average latency
+const LATENCY_SMOOTHING: f64 = 0.2;
+// The average halves every this many seconds without traffic, so shedding stops once the
+// requests that were still being served have drained
+const LATENCY_HALF_LIFE_SECS: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadShedConfig {
+    /// Requests in progress above which low-priority traffic is shed; 0 disables this signal
+    pub max_in_flight: usize,
+    /// Average latency above which low-priority traffic is shed; 0 disables this signal
+    pub max_latency_ms: f64,
+    pub retry_after_secs: u64,
+}
+
+impl LoadShedConfig {
+    /// Read thresholds from `LOAD_SHED_MAX_IN_FLIGHT` (default 200), `LOAD_SHED_LATENCY_MS`
+    /// (default 10000) and `LOAD_SHED_RETRY_AFTER_SECS` (default 5)
+    pub fn from_env() -> Self {
+        let env_u64 = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_in_flight: env_u64("LOAD_SHED_MAX_IN_FLIGHT", 200) as usize,
+            max_latency_ms: env_u64("LOAD_SHED_LATENCY_MS", 10_000) as f64,
+            retry_after_secs: env_u64("LOAD_SHED_RETRY_AFTER_SECS", 5).max(1),
+        }
+    }
+}
+
+/// Which traffic goes first when the server is overloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Chats from guests of the embedded widget and callers without valid credentials
+    Low,
+    /// Callers with a valid API key or user token
+    Normal,
+}
+
+impl Priority {
+    /// Decided by who the caller verifiably is, so a client can't raise its priority with headers it sets
+    pub fn of(group: RouteGroup, principal: &Principal) -> Self {
+        if group == RouteGroup::Chat && principal.organization_id().is_none() {
+            Priority::Low
+        } else {
+            Priority::Normal
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LatencyAverage {
+    value_ms: f64,
+    updated: Option<Instant>,
+}
+
+impl LatencyAverage {
+    fn value_at(&self, now: Instant) -> f64 {
+        match self.updated {
+            Some(updated) => {
+                let idle_secs = now.saturating_duration_since(updated).as_secs_f64();
+                self.value_ms * 0.5f64.powf(idle_secs / LATENCY_HALF_LIFE_SECS)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn record(&mut self, sample_ms: f64, now: Instant) {
+        self.value_ms = match self.updated {
+            Some(_) => {
+                let current = self.value_at(now);
+                current + LATENCY_SMOOTHING * (sample_ms - current)
+            }
+            None => sample_ms,
+        };
+        self.updated = Some(now);
+    }
+}
+
+/// Tracks requests in progress and average latency, and decides when to shed low-priority traffic
+pub struct LoadShedder {
+    config: LoadShedConfig,
+    in_flight: AtomicUsize,
+    latency: Mutex<LatencyAverage>,
+}
+
+/// Counts a request as in progress until dropped, including when the client disconnects
+pub struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LoadShedder {
+    pub fn new(config: LoadShedConfig) -> Self {
+        Self {
+            config,
+            in_flight: AtomicUsize::new(0),
+            latency: Mutex::new(LatencyAverage::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(LoadShedConfig::from_env())
+    }
+
+    pub fn start(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(&self.in_flight)
+    }
+
+    pub fn record_latency(&self, latency: Duration, now: Instant) {
+        self.latency
+            .lock()
+            .unwrap()
+            .record(latency.as_secs_f64() * 1000.0, now);
+    }
+
+    /// Why the server is overloaded, or None when it isn't
+    pub fn pressure(&self, now: Instant) -> Option<String> {
+        let in_flight = self.in_flight.load(Ordering::Relaxed);
+        if self.config.max_in_flight > 0 && in_flight >= self.config.max_in_flight {
+            return Some(format!("{} requests in progress", in_flight));
+        }
+        let latency_ms = self.latency.lock().unwrap().value_at(now);
+        if self.config.max_latency_ms > 0.0 && latency_ms >= self.config.max_latency_ms {
+            return Some(format!("average latency {:.0}ms", latency_ms));
+        }
+        None
+    }
+
+    pub fn retry_after_secs(&self) -> u64 {
+        self.config.retry_after_secs
+    }
+}
+
+// Turn away guest and unauthenticated chats with 503 while the server is overloaded, so API traffic
+// keeps being served. The caller is only looked up under pressure
+pub async fn load_shed_middleware(State(app_state): State<AppState>, request: Request, next: Next) -> Response {
+    // Health checks are neither shed nor counted: they would hide slow requests in the average
+    let Some(group) = RouteGroup::for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let shedder = &app_state.load_shedder;
+
+    if let Some(reason) = shedder.pressure(Instant::now())
+        && Priority::of(group, &Principal::resolve(request.headers(), &app_state).await) == Priority::Low
+    {
+        tracing::warn!("⚠️ Shedding low-priority request to {}: {}", request.uri().path(), reason);
+        let retry_after_secs = shedder.retry_after_secs();
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "success": false,
+                "message": "Server is busy, please retry shortly",
+                "retry_after": retry_after_secs
+            })),
+        )
+            .into_response();
+        if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+
+    let _in_flight = shedder.start();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    shedder.record_latency(started.elapsed(), Instant::now());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn shedder(max_in_flight: usize, max_latency_ms: f64) -> LoadShedder {
+        LoadShedder::new(LoadShedConfig { max_in_flight, max_latency_ms, retry_after_secs: 5 })
+    }
+
+    #[test]
+    fn test_only_unauthenticated_chats_are_low_priority() {
+        let organization_id = Uuid::from_u128(1);
+        let user = Principal::User { user_id: Uuid::from_u128(2), organization_id };
+        assert_eq!(Priority::of(RouteGroup::Chat, &Principal::Organization(organization_id)), Priority::Normal);
+        assert_eq!(Priority::of(RouteGroup::Chat, &user), Priority::Normal);
+
+        let guest = Principal::Guest { chatbot_id: Uuid::from_u128(3) };
+        assert_eq!(Priority::of(RouteGroup::Chat, &guest), Priority::Low);
+        assert_eq!(Priority::of(RouteGroup::Chat, &Principal::Anonymous), Priority::Low);
+        assert_eq!(Priority::of(RouteGroup::Api, &Principal::Anonymous), Priority::Normal);
+        assert_eq!(Priority::of(RouteGroup::Admin, &guest), Priority::Normal);
+    }
+
+    #[test]
+    fn test_pressure_from_requests_in_progress() {
+        let shedder = shedder(2, 0.0);
+        let now = Instant::now();
+        let first = shedder.start();
+        assert!(shedder.pressure(now).is_none());
+
+        let second = shedder.start();
+        assert!(shedder.pressure(now).is_some());
+
+        drop(first);
+        drop(second);
+        assert!(shedder.pressure(now).is_none());
+    }
+
+    #[test]
+    fn test_pressure_from_latency_eases_when_idle() {
+        let shedder = shedder(0, 1000.0);
+        let now = Instant::now();
+        shedder.record_latency(Duration::from_millis(3000), now);
+        assert!(shedder.pressure(now).is_some());
+
+        // One fast request barely moves the average
+        shedder.record_latency(Duration::from_millis(10), now);
+        assert!(shedder.pressure(now).is_some());
+
+        // After two half-lives without traffic, 2400ms has decayed to 600ms
+        let later = now + Duration::from_secs_f64(LATENCY_HALF_LIFE_SECS * 2.0);
+        assert!(shedder.pressure(later).is_none());
+    }
+
+    #[test]
+    fn test_disabled_thresholds_never_shed() {
+        let shedder = shedder(0, 0.0);
+        let now = Instant::now();
+        let _in_flight = shedder.start();
+        shedder.record_latency(Duration::from_secs(60), now);
+        assert!(shedder.pressure(now).is_none());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod auth;
+pub mod custom_domain;
+pub mod ip_filter;
+pub mod load_shed;
+pub mod rate_limit;
+pub mod rbac;
+pub mod request_id;
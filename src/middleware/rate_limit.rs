@@ -0,0 +1,391 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing;
+use uuid::Uuid;
+
+use crate::middleware::auth::Principal;
+use crate::utils::config::AppState;
+
+// Largest JSON body we buffer to find the chatbot_id
+const MAX_PEEK_BODY_BYTES: usize = 1024 * 1024;
+// Prune idle in-memory buckets once the map grows past this size
+const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+/// Token bucket parameters: burst capacity and steady-state refill rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketLimit {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub default_limit: BucketLimit,
+    /// Per API key or per chatbot id overrides
+    pub overrides: HashMap<String, BucketLimit>,
+}
+
+impl RateLimitConfig {
+    /// Read limits from `RATE_LIMIT_*` environment variables
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("RATE_LIMIT_ENABLED")
+            .map(|v| v != "false" && v != "0")
+            .unwrap_or(true);
+        let capacity = std::env::var("RATE_LIMIT_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60.0);
+        let refill_per_sec = std::env::var("RATE_LIMIT_REFILL_PER_SEC")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &f64| *v > 0.0)
+            .unwrap_or(1.0);
+        let overrides = std::env::var("RATE_LIMIT_OVERRIDES")
+            .map(|v| parse_overrides(&v))
+            .unwrap_or_default();
+
+        Self {
+            enabled,
+            default_limit: BucketLimit { capacity, refill_per_sec },
+            overrides,
+        }
+    }
+
+    fn limit_for(&self, api_key: Option<&str>, chatbot_id: Option<&str>) -> BucketLimit {
+        api_key
+            .and_then(|k| self.overrides.get(k))
+            .or_else(|| chatbot_id.and_then(|c| self.overrides.get(c)))
+            .copied()
+            .unwrap_or(self.default_limit)
+    }
+}
+
+/// Parse `KEY=CAPACITY/REFILL_PER_SEC` pairs separated by commas
+fn parse_overrides(raw: &str) -> HashMap<String, BucketLimit> {
+    raw.split(',')
+        .filter_map(|entry| {
+            let (key, limit) = entry.trim().split_once('=')?;
+            let (capacity, refill) = limit.split_once('/')?;
+            let capacity: f64 = capacity.trim().parse().ok()?;
+            let refill_per_sec: f64 = refill.trim().parse().ok()?;
+            if key.is_empty() || refill_per_sec <= 0.0 {
+                tracing::warn!("Ignoring invalid rate limit override: {}", entry);
+                return None;
+            }
+            Some((key.trim().to_string(), BucketLimit { capacity, refill_per_sec }))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Limited { retry_after_secs: u64 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(limit: BucketLimit, now: Instant) -> Self {
+        Self { tokens: limit.capacity, last_refill: now }
+    }
+
+    fn refill(&mut self, limit: BucketLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_sec).min(limit.capacity);
+        self.last_refill = now;
+    }
+
+    fn take(&mut self, limit: BucketLimit, now: Instant) -> Decision {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Decision::Allowed
+        } else {
+            let wait = (1.0 - self.tokens) / limit.refill_per_sec;
+            Decision::Limited { retry_after_secs: wait.ceil().max(1.0) as u64 }
+        }
+    }
+}
+
+/// Backing store for token buckets
+pub enum RateLimitStore {
+    Memory(Mutex<HashMap<String, Bucket>>),
+    #[cfg(feature = "redis")]
+    Redis(redis::Client),
+}
+
+#[cfg(feature = "redis")]
+const REDIS_TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill)
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait = math.ceil((1 - tokens) / refill)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', tostring(now))
+redis.call('EXPIRE', KEYS[1], math.ceil(capacity / refill) + 1)
+return wait
+"#;
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    store: RateLimitStore,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, store: RateLimitStore) -> Self {
+        Self { config, store }
+    }
+
+    /// Build the limiter from env, using Redis when `REDIS_URL` is set and the feature is enabled
+    pub fn from_env() -> anyhow::Result<Self> {
+        let config = RateLimitConfig::from_env();
+
+        #[cfg(feature = "redis")]
+        if let Ok(redis_url) = std::env::var("REDIS_URL") {
+            tracing::info!("Rate limiter using Redis store");
+            let client = redis::Client::open(redis_url)?;
+            return Ok(Self::new(config, RateLimitStore::Redis(client)));
+        }
+
+        tracing::info!("Rate limiter using in-memory store");
+        Ok(Self::new(config, RateLimitStore::Memory(Mutex::new(HashMap::new()))))
+    }
+
+    pub async fn check(&self, api_key: Option<&str>, identity: &str, chatbot_id: Option<&str>) -> Decision {
+        if !self.config.enabled {
+            return Decision::Allowed;
+        }
+
+        let limit = self.config.limit_for(api_key, chatbot_id);
+        let bucket_key = format!("ratelimit:{}:{}", identity, chatbot_id.unwrap_or("*"));
+
+        match &self.store {
+            RateLimitStore::Memory(buckets) => {
+                let now = Instant::now();
+                let Ok(mut buckets) = buckets.lock() else {
+                    return Decision::Allowed;
+                };
+
+                if buckets.len() > MAX_MEMORY_BUCKETS {
+                    buckets.retain(|_, b| {
+                        let mut b = *b;
+                        b.refill(limit, now);
+                        b.tokens < limit.capacity
+                    });
+                }
+
+                buckets
+                    .entry(bucket_key)
+                    .or_insert_with(|| Bucket::new(limit, now))
+                    .take(limit, now)
+            }
+            #[cfg(feature = "redis")]
+            RateLimitStore::Redis(client) => {
+                let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+                let result: redis::RedisResult<u64> = async {
+                    let mut conn = client.get_multiplexed_async_connection().await?;
+                    redis::Script::new(REDIS_TOKEN_BUCKET_SCRIPT)
+                        .key(&bucket_key)
+                        .arg(limit.capacity)
+                        .arg(limit.refill_per_sec)
+                        .arg(now)
+                        .invoke_async(&mut conn)
+                        .await
+                }
+                .await;
+
+                match result {
+                    Ok(0) => Decision::Allowed,
+                    Ok(wait) => Decision::Limited { retry_after_secs: wait },
+                    Err(e) => {
+                        // Fail open so a Redis outage does not take the API down
+                        tracing::warn!("⚠️ Redis rate limit check failed: {}", e);
+                        Decision::Allowed
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Bucket identity of a verified caller. Guests and anonymous callers are keyed by address, since
+// guest tokens are free to mint
+fn caller_identity(principal: Principal, client_ip: Option<IpAddr>) -> String {
+    match principal {
+        Principal::Organization(organization_id) => format!("org:{}", organization_id),
+        Principal::User { user_id, .. } => format!("user:{}", user_id),
+        Principal::Guest { .. } | Principal::Anonymous => match client_ip {
+            Some(ip) => format!("ip:{}", ip),
+            None => "ip:unknown".to_string(),
+        },
+    }
+}
+
+// Buffer JSON bodies to pull out the chatbot_id, then hand the request back intact
+async fn extract_chatbot_id(request: Request) -> Result<(Request, Option<String>), StatusCode> {
+    if let Some(chatbot_id) = request
+        .headers()
+        .get("x-chatbot-id")
+        .and_then(|v| v.to_str().ok())
+    {
+        let chatbot_id = chatbot_id.to_string();
+        return Ok((request, Some(chatbot_id)));
+    }
+
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+
+    if !is_json {
+        return Ok((request, None));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_PEEK_BODY_BYTES).await.map_err(|e| {
+        tracing::error!("Failed to buffer request body: {}", e);
+        StatusCode::PAYLOAD_TOO_LARGE
+    })?;
+
+    let chatbot_id = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|v| v["chatbot_id"].as_str().map(|s| s.to_string()));
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), chatbot_id))
+}
+
+// Token bucket rate limiting keyed by the verified caller (organization, user or client address)
+// and by the chatbot when the caller is known to own it
+pub async fn rate_limit_middleware(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let principal = Principal::resolve(request.headers(), &app_state).await;
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let identity = caller_identity(principal, app_state.ip_filter.client_ip(request.headers(), peer));
+    // An API key only selects its override once it's known to be valid
+    let api_key = match principal {
+        Principal::Organization(_) => request
+            .headers()
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string()),
+        _ => None,
+    };
+
+    let (request, chatbot_id) = match (principal, principal.organization_id()) {
+        (Principal::Guest { chatbot_id }, _) => (request, Some(chatbot_id)),
+        (_, Some(organization_id)) => {
+            let (request, claimed) = match extract_chatbot_id(request).await {
+                Ok(result) => result,
+                Err(status) => return status.into_response(),
+            };
+            let claimed = claimed.and_then(|id| Uuid::parse_str(&id).ok());
+            let owned = match claimed {
+                Some(chatbot_id) => matches!(
+                    app_state.chatbot_cache.get_chatbot(&app_state.db, organization_id, chatbot_id).await,
+                    Ok(Some(_))
+                ),
+                None => false,
+            };
+            (request, claimed.filter(|_| owned))
+        }
+        _ => (request, None),
+    };
+    let chatbot_id = chatbot_id.map(|id| id.to_string());
+
+    match app_state
+        .rate_limiter
+        .check(api_key.as_deref(), &identity, chatbot_id.as_deref())
+        .await
+    {
+        Decision::Allowed => next.run(request).await,
+        Decision::Limited { retry_after_secs } => {
+            tracing::warn!("Rate limit exceeded for {} (chatbot: {:?})", identity, chatbot_id);
+            let mut response = (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({
+                    "success": false,
+                    "message": "Rate limit exceeded",
+                    "retry_after": retry_after_secs
+                })),
+            )
+                .into_response();
+            if let Ok(value) = HeaderValue::from_str(&retry_after_secs.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_bucket_exhausts_and_refills() {
+        let limit = BucketLimit { capacity: 2.0, refill_per_sec: 1.0 };
+        let start = Instant::now();
+        let mut bucket = Bucket::new(limit, start);
+
+        assert_eq!(bucket.take(limit, start), Decision::Allowed);
+        assert_eq!(bucket.take(limit, start), Decision::Allowed);
+        assert_eq!(bucket.take(limit, start), Decision::Limited { retry_after_secs: 1 });
+        assert_eq!(bucket.take(limit, start + Duration::from_secs(1)), Decision::Allowed);
+    }
+
+    #[test]
+    fn test_caller_identity_uses_verified_principal() {
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let organization_id = Uuid::from_u128(1);
+        assert_eq!(caller_identity(Principal::Organization(organization_id), Some(ip)), format!("org:{}", organization_id));
+        assert_eq!(
+            caller_identity(Principal::User { user_id: Uuid::from_u128(2), organization_id }, Some(ip)),
+            format!("user:{}", Uuid::from_u128(2))
+        );
+        // Every guest token from one address shares its bucket
+        assert_eq!(caller_identity(Principal::Guest { chatbot_id: Uuid::from_u128(3) }, Some(ip)), "ip:203.0.113.7");
+        assert_eq!(caller_identity(Principal::Anonymous, None), "ip:unknown");
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let overrides = parse_overrides("abc=100/2, bot-1=5/0.5,broken,zero=1/0");
+
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides["abc"], BucketLimit { capacity: 100.0, refill_per_sec: 2.0 });
+        assert_eq!(overrides["bot-1"], BucketLimit { capacity: 5.0, refill_per_sec: 0.5 });
+    }
+}
@@ -0,0 +1,161 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use tracing;
+
+use crate::middleware::auth::bearer_token;
+use crate::services::user_auth::{UserAuthConfig, UserRole};
+
+/// The least role a signed-in user needs for a request to a management route: viewers may read,
+/// while uploading documents or changing a chatbot or its prompts needs an editor
+pub fn required_role(method: &Method) -> UserRole {
+    if method.is_safe() {
+        UserRole::Viewer
+    } else {
+        UserRole::Editor
+    }
+}
+
+// Role of a signed-in user calling with a valid access token. Other callers (the organization's API
+// key, admin sessions, bad tokens) are left to the handler's extractors
+fn signed_in_role(config: Option<&UserAuthConfig>, headers: &HeaderMap) -> Option<UserRole> {
+    if headers.contains_key("x-api-key") {
+        return None;
+    }
+    let token = bearer_token(headers)?;
+    config?.verify_access_token(token).ok().map(|claims| claims.role)
+}
+
+/// Reject signed-in users whose role is below what `required_role` asks for the request
+pub async fn require_role_middleware(
+    State(config): State<Option<UserAuthConfig>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let required = required_role(request.method());
+    match signed_in_role(config.as_ref(), request.headers()) {
+        Some(role) if role < required => {
+            tracing::warn!(
+                "Blocked {} {} for a user with role {}",
+                request.method(),
+                request.uri().path(),
+                role
+            );
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "success": false,
+                    "message": format!("This action needs the {} role", required)
+                })),
+            )
+                .into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    fn config() -> UserAuthConfig {
+        UserAuthConfig::new("0123456789abcdef0123456789abcdef".to_string(), 900, 86_400)
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/chatbots", get(|| async { "listed" }).post(|| async { "created" }))
+            .route(
+                "/chatbots/{id}/prompt-template",
+                get(|| async { "read" }).put(|| async { "changed" }),
+            )
+            .route("/chatbots/{id}/documents", get(|| async { "listed" }).post(|| async { "uploaded" }))
+            .route_layer(from_fn_with_state(Some(config()), require_role_middleware))
+    }
+
+    fn user_token(role: UserRole) -> String {
+        let token = config().issue_access_token(Uuid::new_v4(), Uuid::new_v4(), role).unwrap();
+        format!("Bearer {}", token)
+    }
+
+    async fn status(method: Method, path: &str, headers: &[(&str, &str)]) -> StatusCode {
+        let mut request = axum::http::Request::builder().method(method).uri(path);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[test]
+    fn test_required_role() {
+        assert_eq!(required_role(&Method::GET), UserRole::Viewer);
+        assert_eq!(required_role(&Method::HEAD), UserRole::Viewer);
+        assert_eq!(required_role(&Method::POST), UserRole::Editor);
+        assert_eq!(required_role(&Method::PUT), UserRole::Editor);
+        assert_eq!(required_role(&Method::DELETE), UserRole::Editor);
+    }
+
+    #[tokio::test]
+    async fn test_viewers_can_read_but_not_change() {
+        let viewer = user_token(UserRole::Viewer);
+        let auth = [("authorization", viewer.as_str())];
+        let chatbot = Uuid::new_v4();
+
+        assert_eq!(status(Method::GET, "/chatbots", &auth).await, StatusCode::OK);
+        assert_eq!(
+            status(Method::GET, &format!("/chatbots/{}/prompt-template", chatbot), &auth).await,
+            StatusCode::OK
+        );
+        assert_eq!(status(Method::POST, "/chatbots", &auth).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(Method::PUT, &format!("/chatbots/{}/prompt-template", chatbot), &auth).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(Method::POST, &format!("/chatbots/{}/documents", chatbot), &auth).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_editors_and_owners_can_change() {
+        let chatbot = Uuid::new_v4();
+        for role in [UserRole::Editor, UserRole::Owner] {
+            let token = user_token(role);
+            let auth = [("authorization", token.as_str())];
+
+            assert_eq!(
+                status(Method::PUT, &format!("/chatbots/{}/prompt-template", chatbot), &auth).await,
+                StatusCode::OK
+            );
+            assert_eq!(
+                status(Method::POST, &format!("/chatbots/{}/documents", chatbot), &auth).await,
+                StatusCode::OK
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_other_callers_are_left_to_the_handlers() {
+        // The API key acts as an owner, even next to a viewer's token
+        let viewer = user_token(UserRole::Viewer);
+        let with_key = [("x-api-key", "rag_key"), ("authorization", viewer.as_str())];
+        assert_eq!(status(Method::POST, "/chatbots", &with_key).await, StatusCode::OK);
+
+        // Admin sessions and bad tokens are not user tokens; the extractors reject or accept them
+        assert_eq!(status(Method::POST, "/chatbots", &[("authorization", "Bearer ras_abc")]).await, StatusCode::OK);
+        assert_eq!(status(Method::POST, "/chatbots", &[]).await, StatusCode::OK);
+
+        let other_secret = UserAuthConfig::new("fedcba9876543210fedcba9876543210".to_string(), 900, 86_400);
+        let forged = other_secret.issue_access_token(Uuid::new_v4(), Uuid::new_v4(), UserRole::Viewer).unwrap();
+        let forged = format!("Bearer {}", forged);
+        assert_eq!(status(Method::POST, "/chatbots", &[("authorization", forged.as_str())]).await, StatusCode::OK);
+    }
+}
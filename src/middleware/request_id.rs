@@ -0,0 +1,88 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+use tracing::{self, Instrument};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longest caller-supplied request id we accept; anything else gets a fresh UUID
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Reuse a well-formed incoming `X-Request-Id` so ids line up across services
+fn incoming_request_id(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+        .map(str::to_string)
+}
+
+// Run every request inside a span carrying its id, route, status and latency. Elasticsearch
+// and Gemini calls made while handling it are recorded as child spans
+pub async fn request_tracing_middleware(request: Request, next: Next) -> Response {
+    let request_id = incoming_request_id(&request).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        route = %route,
+        status = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    );
+
+    let started = Instant::now();
+    let mut response = next.run(request).instrument(span.clone()).await;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency_ms);
+    span.in_scope(|| {
+        if response.status().is_server_error() {
+            tracing::error!("❌ Request failed with {}", response.status());
+        } else {
+            tracing::info!("Request completed with {}", response.status());
+        }
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request_with_id(id: &str) -> Request {
+        axum::http::Request::builder()
+            .header(REQUEST_ID_HEADER, id)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_incoming_request_id() {
+        assert_eq!(incoming_request_id(&request_with_id("abc-123_x.y")), Some("abc-123_x.y".to_string()));
+        assert_eq!(incoming_request_id(&request_with_id("has spaces")), None);
+        assert_eq!(incoming_request_id(&request_with_id(&"a".repeat(200))), None);
+        assert_eq!(incoming_request_id(&Request::new(Body::empty())), None);
+    }
+}
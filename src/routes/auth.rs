@@ -0,0 +1,298 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde_json::{json, Value};
+
+use crate::db::models::{LoginRequest, RefreshTokenRequest, RegisterRequest, User};
+use crate::db::queries::{
+    consume_refresh_token, create_password_user, create_refresh_token, delete_refresh_token,
+    get_active_user_by_email, get_user,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::{generate_refresh_token, hash_api_key, AuthUser, Tenant};
+use crate::services::reports::is_valid_email;
+use crate::services::user_auth::{hash_password, verify_password, UserAuthConfig, UserRole, MIN_PASSWORD_LEN};
+use crate::utils::config::AppState;
+
+// User sign-in settings; 404 when JWT_SECRET is not set
+fn auth_config() -> Result<UserAuthConfig, StatusCode> {
+    match UserAuthConfig::from_env() {
+        Ok(Some(config)) => Ok(config),
+        Ok(None) => {
+            tracing::warn!("User sign-in requested but JWT_SECRET is not set");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Invalid user sign-in configuration: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// A new access token and refresh token for the user
+async fn issue_tokens(app_state: &AppState, config: &UserAuthConfig, user: &User) -> Result<Value, StatusCode> {
+    let role: UserRole = user.role.parse().map_err(|e| {
+        tracing::error!("❌ User {} has an invalid role: {}", user.id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let access_token = config.issue_access_token(user.id, user.organization_id, role).map_err(|e| {
+        tracing::error!("❌ Failed to sign access token: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let refresh_token = generate_refresh_token();
+    create_refresh_token(&app_state.db, user.id, &hash_api_key(&refresh_token), config.refresh_ttl_secs)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to store refresh token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": config.access_ttl_secs,
+        "refresh_token": refresh_token,
+        "refresh_expires_in": config.refresh_ttl_secs,
+        "user": user
+    }))
+}
+
+// Create a user with a password in the caller's organization and sign them in. Called with the
+// organization's API key or by an owner
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, description = "User created and signed in", body = Value),
+        (status = 400, description = "Invalid email, or a password shorter than 8 characters"),
+        (status = 403, description = "Signed in without the owner role"),
+        (status = 404, description = "User sign-in is off (JWT_SECRET is not set)"),
+        (status = 409, description = "The organization already has a user with this email"),
+    ),
+    security(("api_key" = []), ("user_token" = []))
+)]
+pub async fn register_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let config = auth_config()?;
+    tenant.require_role(UserRole::Owner)?;
+
+    let email = payload.email.trim().to_lowercase();
+    if !is_valid_email(&email) {
+        tracing::error!("Invalid email: {}", email);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.password.chars().count() < MIN_PASSWORD_LEN {
+        tracing::error!("Password must be at least {} characters", MIN_PASSWORD_LEN);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let role = payload.role.unwrap_or(UserRole::Viewer);
+    tracing::info!("Registering user {} as {}", email, role);
+
+    // Hashing is deliberately slow, so it runs off the async workers
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&payload.password))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|hash| hash)
+        .map_err(|e| {
+            tracing::error!("❌ Failed to hash password: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let created =
+        create_password_user(&app_state.db, tenant.organization_id, &email, &password_hash, role.as_str()).await;
+    let user = match created {
+        Ok(user) => user,
+        Err(AppError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            tracing::error!("User {} already exists", email);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create user: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let tokens = issue_tokens(&app_state, &config, &user).await?;
+    tracing::info!("✅ User registered: {}", user.id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "User registered successfully",
+        "data": tokens
+    })))
+}
+
+// Sign a user of the caller's organization in with their email and password
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Access and refresh tokens", body = Value),
+        (status = 401, description = "Unknown email, wrong password or deactivated user"),
+        (status = 404, description = "User sign-in is off (JWT_SECRET is not set)"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn login_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let config = auth_config()?;
+
+    let email = payload.email.trim().to_lowercase();
+    let user = match get_active_user_by_email(&app_state.db, tenant.organization_id, &email).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            tracing::warn!("Login for unknown user: {}", email);
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    // Users provisioned over SCIM have no password and sign in through their identity provider
+    let Some(password_hash) = user.password_hash.clone() else {
+        tracing::warn!("Login for user without a password: {}", user.id);
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let matches = tokio::task::spawn_blocking(move || verify_password(&payload.password, &password_hash))
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to verify password: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !matches {
+        tracing::warn!("Wrong password for user: {}", user.id);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let tokens = issue_tokens(&app_state, &config, &user).await?;
+    tracing::info!("✅ User signed in: {}", user.id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Signed in successfully",
+        "data": tokens
+    })))
+}
+
+// Trade a refresh token for a new access token and refresh token. Each refresh token works once
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = Value),
+        (status = 401, description = "Unknown, used or expired refresh token, or deactivated user"),
+        (status = 404, description = "User sign-in is off (JWT_SECRET is not set)"),
+    )
+)]
+pub async fn refresh_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let config = auth_config()?;
+
+    let user = match consume_refresh_token(&app_state.db, &hash_api_key(payload.refresh_token.trim())).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            tracing::warn!("Rejected unknown or expired refresh token");
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to use refresh token: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let tokens = issue_tokens(&app_state, &config, &user).await?;
+    Ok(Json(json!({
+        "success": true,
+        "message": "Tokens refreshed successfully",
+        "data": tokens
+    })))
+}
+
+// Revoke a refresh token. Access tokens already issued stay valid until they expire
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Refresh token revoked", body = Value),
+    )
+)]
+pub async fn logout_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    match delete_refresh_token(&app_state.db, &hash_api_key(payload.refresh_token.trim())).await {
+        Ok(revoked) => Ok(Json(json!({
+            "success": true,
+            "message": "Signed out successfully",
+            "data": { "revoked": revoked }
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to revoke refresh token: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// The signed-in user
+#[utoipa::path(
+    get,
+    path = "/api/auth/me",
+    tag = "auth",
+    responses(
+        (status = 200, description = "The signed-in user", body = Value),
+        (status = 401, description = "Missing, invalid or expired access token"),
+        (status = 404, description = "The user was deleted"),
+    ),
+    security(("user_token" = []))
+)]
+pub async fn me_handler(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<Value>, StatusCode> {
+    match get_user(&app_state.db, auth_user.organization_id, auth_user.user_id).await {
+        Ok(Some(user)) => Ok(Json(json!({
+            "success": true,
+            "message": "User retrieved successfully",
+            "data": user
+        }))),
+        Ok(None) => {
+            tracing::error!("User not found: {}", auth_user.user_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get user: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for user sign-in
+pub fn create_auth_router() -> Router<AppState> {
+    Router::new()
+        .route("/auth/register", post(register_handler))
+        .route("/auth/login", post(login_handler))
+        .route("/auth/refresh", post(refresh_handler))
+        .route("/auth/logout", post(logout_handler))
+        .route("/auth/me", get(me_handler))
+}
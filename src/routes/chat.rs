@@ -1,48 +1,125 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::{Json, Sse},
-    routing::{get, post},
+    response::{IntoResponse, Json, Response, Sse},
+    routing::{delete, get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use futures_util::Stream;
+use std::collections::HashMap;
+use std::pin::Pin;
 use axum::response::sse::{Event, KeepAlive};
 
+use crate::db::models::{
+    BulkDeleteConversationsRequest, ChatBot, Conversation, FilterStage, NewChatTurn, UsageEvent,
+};
 use crate::db::queries::{
-    create_chat, create_conversation, create_session, get_chat, get_session,
-    list_conversations_by_chat, list_last_conversations_by_chat, update_conversation_response,
+    clear_chat_escalation, count_conversations_in_range, create_chat_turn, create_session, delete_chat,
+    delete_conversation, delete_session, discard_chat_turn, get_chat, get_session, get_sql_tool,
+    is_turn_number_conflict, list_conversations_page, list_glossary_entries, list_last_conversations_by_chat,
+    purge_conversations_in_range, soft_delete_conversations_in_range, update_conversation_response,
+};
+use crate::middleware::auth::{AdminKey, Tenant};
+use crate::errors::{AppError, AppResult};
+use crate::services::answer_cache::{AnswerVersion, CacheableQuery};
+use crate::services::answer_policy::{
+    fallback_response, filter_by_min_score, no_context_template, GENERAL_KNOWLEDGE_CONTEXT,
 };
+use crate::services::attribution::attribute_answer;
+use crate::services::vector::chatbot_index_name;
+use crate::services::cold_storage::record_retrieval;
+use crate::services::compression::{compress_results, CompressionMode};
+use crate::services::conversation_export::record_citations;
 use crate::services::embedding::EmbeddingService;
-use crate::services::gemini::GeminiService;
+use crate::services::output_filter::{filter_answer, moderate_query, record_incidents};
+use crate::services::partial_response::PartialResponse;
+use crate::services::scripting::{run_answer_hook, run_query_hook};
+use crate::services::json_stream::{ListingFormat, StreamedListing};
+use crate::services::handoff::{escalate_chat, handoff_message, wants_human, EscalationReason, Handoff};
+use crate::services::sentiment::tag_conversation;
+use crate::services::sql_tool::{answer_with_sql, SqlToolResult};
+use crate::services::stitching::group_by_document;
+use crate::services::translation::{translate_answer, validate_language};
+use crate::services::usage::{record_usage, with_embedding_usage, StreamUsage};
+use crate::services::retrieval::{LatencyBudget, RetrievalRun};
+use crate::services::retrieval_log::record_context;
+use crate::services::sharding::shard_indices;
+use crate::services::token_budget::{format_document, join_sections, PromptTooLarge};
+use crate::services::gemini::{GeminiService, StreamingChunk};
+use crate::services::prompt_template::{chatbot_template, record_variant, render_prompt, PromptContext};
+use crate::services::query_expansion::{expand_query, search_fused};
+use crate::services::query_rewrite::{rewrite_enabled, rewrite_query};
+use crate::services::glossary::{format_glossary, matching_entries};
 use crate::utils::config::AppState;
-use futures_util::StreamExt;
+use futures_util::{StreamExt, TryStreamExt};
 
-#[derive(Debug, Deserialize)]
+// Turns read per query while a chat's history is streamed
+const HISTORY_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChatRequest {
     pub chatbot_id: String,
     pub query: String,
     pub session_id: Option<String>,
     pub chat_id: Option<String>,
+    /// Optional retrieval stages are skipped once this budget is spent
+    pub latency_budget_ms: Option<u64>,
+    /// "none", "extractive" or "llm"; defaults to CONTEXT_COMPRESSION
+    pub context_compression: Option<String>,
+    /// Map answer sentences back to retrieved chunks; defaults to true
+    pub attribute_sources: Option<bool>,
+    /// Named values substituted into the chatbot's prompt template, e.g. customer_name
+    pub variables: Option<HashMap<String, Value>>,
+    /// Topic thread within the chat; only its turns are used as history. Omit for the main thread
+    pub thread_id: Option<String>,
+    /// Start a new thread and return its id
+    pub new_thread: Option<bool>,
+    /// Rewrite follow-ups into standalone questions before retrieval; defaults to QUERY_REWRITE
+    pub rewrite_query: Option<bool>,
+    /// Translate the answer into this language, e.g. "German" or "pt-BR". Citations are kept as is
+    pub translate_to: Option<String>,
+    /// Answer from, and add the answer to, the answer cache; defaults to true
+    pub use_cache: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatResponse {
     pub success: bool,
     pub message: String,
     pub data: ChatData,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatData {
     pub session_id: String,
     pub chat_id: String,
+    pub thread_id: Option<String>,
     pub conversation_id: String,
     pub user_query: String,
+    /// Query used for retrieval, after rewriting follow-ups
+    pub search_query: String,
     pub bot_response: String,
+    /// Untranslated answer, present when `translate_to` was set
+    pub original_response: Option<String>,
+    /// Language the answer was translated into
+    pub translated_to: Option<String>,
     pub context_used: Vec<String>,
+    /// Retrieval stages that ran or were skipped for the latency budget
+    pub retrieval_trace: Value,
+    /// Per-sentence source spans (character offsets into `original_response` when translated, else `bot_response`)
+    pub attributions: Value,
+    /// True when no chunk passed the score threshold and the fallback message was returned
+    pub fallback: bool,
+    /// Query the chatbot's SQL tool ran for this question; its rows were part of the context
+    pub sql_query: Option<String>,
+    /// True when the chat is handed off to a person and `bot_response` is the handoff notice
+    pub escalated: bool,
+    /// True when the answer was reused from a near-identical question answered earlier
+    pub cache_hit: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -50,26 +127,114 @@ pub struct SessionRequest {
     // No fields needed - session is created automatically
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SessionResponse {
     pub success: bool,
     pub message: String,
     pub data: SessionData,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SessionData {
     pub session_id: String,
     pub created_at: String,
 }
 
+// A past turn as history for the prompt. Replies agents sent from the handoff inbox have no user
+// message and are labelled as theirs, so the bot doesn't take them for its own answers
+fn history_turn(conv: &Conversation) -> String {
+    let reply = conv.bot_response.as_deref().unwrap_or_default();
+    if conv.author == "human" {
+        format!("Agent: {}", reply)
+    } else {
+        format!("User: {}\nBot: {}", conv.user_query, reply)
+    }
+}
+
+// The question as an answer cache key. Only the first question of a thread without template
+// variables is cached, and not for chatbots testing a canary template, so the answer depends on
+// nothing but the question, the chatbot's documents and its settings
+async fn cacheable_query(
+    app_state: &AppState,
+    embedding_service: &EmbeddingService,
+    chatbot: &ChatBot,
+    payload: &ChatRequest,
+    has_history: bool,
+) -> Option<CacheableQuery> {
+    if !app_state.answer_cache.is_enabled()
+        || !payload.use_cache.unwrap_or(true)
+        || has_history
+        || payload.variables.is_some()
+        || chatbot.canary_template_id.is_some()
+    {
+        return None;
+    }
+    // Read the versions before answering, so documents changed meanwhile make the answer stale
+    let version = AnswerVersion {
+        knowledge: app_state.chatbot_cache.knowledge_version(chatbot.id),
+        settings: app_state.chatbot_cache.settings_version(chatbot.id),
+    };
+    match embedding_service.embed_texts(std::slice::from_ref(&payload.query)).await {
+        Ok(mut embeddings) => Some(CacheableQuery {
+            chatbot_id: chatbot.id,
+            text: payload.query.clone(),
+            version,
+            embedding: embeddings.pop()?,
+        }),
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to embed the query for the answer cache: {}", e);
+            None
+        }
+    }
+}
+
+// Run the chatbot's SQL tool for the question, if it has one. A failing tool is logged and left
+// out so the answer still comes from documents
+async fn sql_tool_result(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    question: &str,
+    retrieval: &mut RetrievalRun,
+) -> Option<SqlToolResult> {
+    let tool = match get_sql_tool(&app_state.db, organization_id, chatbot_id).await {
+        Ok(tool) => tool?,
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to get SQL tool: {}", e);
+            return None;
+        }
+    };
+    let gemini_service = GeminiService::new()
+        .map_err(|e| tracing::warn!("⚠️ Failed to create Gemini service for the SQL tool: {}", e))
+        .ok()?;
+
+    match retrieval.run_optional("sql_tool", || answer_with_sql(&gemini_service, &tool, question)).await? {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::warn!("⚠️ SQL tool failed: {}", e);
+            None
+        }
+    }
+}
+
 // Create a new session
+#[utoipa::path(
+    post,
+    path = "/api/chat/session",
+    tag = "chat",
+    responses(
+        (status = 200, description = "Session created", body = SessionResponse),
+        (status = 401, description = "Missing or unknown API key"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn create_session_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
 ) -> Result<Json<Value>, StatusCode> {
     tracing::info!("Creating new chat session");
 
-    match create_session(&app_state.db).await {
+    match create_session(&app_state.db, tenant.organization_id, tenant.user_id).await {
         Ok(session) => {
             tracing::info!("✅ Session created successfully: {}", session.id);
             Ok(Json(json!({
@@ -88,11 +253,164 @@ pub async fn create_session_handler(
     }
 }
 
+// Messages sent to one chat at once can keep taking each other's turn number; the client gets 409
+// and sends its message again
+fn turn_error(e: AppError) -> StatusCode {
+    if is_turn_number_conflict(&e) {
+        tracing::warn!("⚠️ Concurrent messages to the chat took the turn number: {}", e);
+        return StatusCode::CONFLICT;
+    }
+    tracing::error!("Failed to create conversation: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+// While a person has the chat, or when the user asks for one and the chatbot has a handoff
+// webhook, record the turn with the handoff notice as its reply instead of answering
+async fn handoff_turn(
+    app_state: &AppState,
+    tenant: &Tenant,
+    chatbot: &ChatBot,
+    escalated: bool,
+    turn: &NewChatTurn,
+) -> Result<Option<Conversation>, StatusCode> {
+    let organization_id = tenant.organization_id;
+    let handoff = if escalated {
+        None
+    } else {
+        match Handoff::for_chatbot(chatbot) {
+            Some(handoff) if wants_human(&turn.user_query) => Some(handoff),
+            _ => return Ok(None),
+        }
+    };
+
+    let conversation = create_chat_turn(&app_state.db, organization_id, tenant.user_id, turn).await.map_err(turn_error)?;
+    let conversation = match update_conversation_response(
+        &app_state.db,
+        organization_id,
+        conversation.id,
+        handoff_message().to_string(),
+    ).await {
+        Ok(updated) => updated,
+        Err(e) => {
+            tracing::error!("Failed to update conversation: {}", e);
+            if let Err(e) = discard_chat_turn(&app_state.db, organization_id, turn, &conversation).await {
+                tracing::warn!("⚠️ Failed to roll back chat turn: {}", e);
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    tag_conversation(&app_state.background_jobs, app_state.db.clone(), conversation.id, turn.user_query.clone());
+
+    // Escalate after recording the turn so the transcript includes the request
+    if let Some(handoff) = handoff {
+        escalate_chat(
+            &app_state.background_jobs,
+            app_state.db.clone(),
+            handoff,
+            organization_id,
+            turn.chat_id,
+            EscalationReason::Requested,
+        ).await.map_err(|e| {
+            tracing::error!("❌ Failed to escalate chat: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    tracing::info!("Chat {} is handed off to a person, skipping the bot", turn.chat_id);
+    Ok(Some(conversation))
+}
+
+// Screen the query with the chatbot's query filters, returning it with replace rules applied. A blocked
+// query is logged and answered with a policy violation instead of reaching retrieval or the model
+async fn moderated_query(app_state: &AppState, chatbot: &ChatBot, query: &str) -> Result<String, Response> {
+    let Some(filters) = chatbot.query_filters.as_deref() else {
+        return Ok(query.to_string());
+    };
+    let moderated = moderate_query(filters, query).await.map_err(|e| {
+        tracing::error!("❌ Query moderation failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+    record_incidents(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        chatbot.id,
+        FilterStage::Query,
+        None,
+        moderated.incidents,
+    );
+    if !moderated.blocked {
+        return Ok(moderated.text);
+    }
+
+    tracing::warn!("⚠️ Blocked a query to chatbot {} under its content policy", chatbot.id);
+    Err((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({
+            "success": false,
+            "message": "Query violates the chatbot's content policy",
+            "data": {
+                "error": "policy_violation",
+                "bot_response": moderated.text
+            }
+        })),
+    )
+        .into_response())
+}
+
+// 413 with the prompt's size and what the caller can change, instead of the provider's own error
+fn context_too_large(error: &PromptTooLarge) -> Response {
+    let guidance: &[&str] = if error.fixed_too_large() {
+        &[
+            "Shorten the question or the request's variables",
+            "Shorten the chatbot's prompt template or its glossary entries",
+        ]
+    } else {
+        &[
+            "Set new_thread to answer without the chat's earlier turns",
+            "Set context_compression to \"extractive\" or \"llm\" to shorten the retrieved documents",
+            "Ask a narrower question so the SQL tool returns fewer rows",
+        ]
+    };
+
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(json!({
+            "success": false,
+            "message": "Prompt is too large for the model's context window",
+            "data": {
+                "error": "context_too_large",
+                "prompt_tokens": error.prompt_tokens,
+                "max_prompt_tokens": error.max_prompt_tokens,
+                "fixed_tokens": error.fixed_tokens,
+                "guidance": guidance
+            }
+        })),
+    )
+        .into_response()
+}
+
 // Main chat endpoint
+#[utoipa::path(
+    post,
+    path = "/api/chat",
+    tag = "chat",
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Generated answer", body = ChatResponse),
+        (status = 400, description = "Invalid id format"),
+        (status = 404, description = "Chatbot, session or chat not found"),
+        (status = 409, description = "Concurrent messages to the chat took the turn number; send the message again"),
+        (status = 413, description = "Prompt too large for the model's context window", body = Value),
+        (status = 422, description = "Query blocked by the chatbot's query filters", body = Value),
+        (status = 429, description = "Rate limit exceeded"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn chat_handler(
     State(app_state): State<AppState>,
-    Json(payload): Json<ChatRequest>,
-) -> Result<Json<Value>, StatusCode> {
+    tenant: Tenant,
+    Json(mut payload): Json<ChatRequest>,
+) -> Result<Response, StatusCode> {
     tracing::info!("Processing chat request: {}", payload.query);
 
     // Parse chatbot_id
@@ -101,7 +419,36 @@ pub async fn chat_handler(
         StatusCode::BAD_REQUEST
     })?;
 
-    // Handle session_id - create new if not provided
+    let translate_to = payload
+        .translate_to
+        .as_deref()
+        .map(validate_language)
+        .transpose()
+        .map_err(|e| {
+            tracing::error!("Invalid translate_to: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    // Verify chatbot belongs to the caller's organization
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Query filters screen the query before anything else sees it
+    payload.query = match moderated_query(&app_state, &chatbot, &payload.query).await {
+        Ok(query) => query,
+        Err(response) => return Ok(response),
+    };
+
+    // A given session must exist; without one, the turn creates a session
     let session_id = match payload.session_id {
         Some(session_id_str) => {
             let session_uuid = Uuid::parse_str(&session_id_str).map_err(|e| {
@@ -110,8 +457,8 @@ pub async fn chat_handler(
             })?;
             
             // Verify session exists
-            match get_session(&app_state.db, session_uuid).await {
-                Ok(Some(_)) => session_uuid,
+            match get_session(&app_state.db, tenant.organization_id, session_uuid, tenant.user_id).await {
+                Ok(Some(_)) => Some(session_uuid),
                 Ok(None) => {
                     tracing::error!("Session not found: {}", session_uuid);
                     return Err(StatusCode::NOT_FOUND);
@@ -122,23 +469,11 @@ pub async fn chat_handler(
                 }
             }
         }
-        None => {
-            // Create new session
-            match create_session(&app_state.db).await {
-                Ok(session) => {
-                    tracing::info!("Created new session: {}", session.id);
-                    session.id
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create session: {}", e);
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                }
-            }
-        }
+        None => None,
     };
 
-    // Handle chat_id - create new if not provided
-    let chat_id = match payload.chat_id {
+    // A given chat must exist; without one, the turn creates a chat
+    let chat = match payload.chat_id {
         Some(chat_id_str) => {
             let chat_uuid = Uuid::parse_str(&chat_id_str).map_err(|e| {
                 tracing::error!("Invalid chat_id format: {}", e);
@@ -146,8 +481,8 @@ pub async fn chat_handler(
             })?;
             
             // Verify chat exists
-            match get_chat(&app_state.db, chat_uuid).await {
-                Ok(Some(_)) => chat_uuid,
+            match get_chat(&app_state.db, tenant.organization_id, chat_uuid, tenant.user_id).await {
+                Ok(Some(chat)) => Some(chat),
                 Ok(None) => {
                     tracing::error!("Chat not found: {}", chat_uuid);
                     return Err(StatusCode::NOT_FOUND);
@@ -158,74 +493,267 @@ pub async fn chat_handler(
                 }
             }
         }
-        None => {
-            // Create new chat
-            match create_chat(&app_state.db, session_id, "New Chat".to_string()).await {
-                Ok(chat) => {
-                    tracing::info!("Created new chat: {}", chat.id);
-                    chat.id
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create chat: {}", e);
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                }
+        None => None,
+    };
+    // A chat without a given session stays in its own; ids of a new session and chat are chosen
+    // now and the rows are written with the turn
+    let (session_id, new_session) = match (session_id, &chat) {
+        (Some(session_id), _) => (session_id, false),
+        (None, Some(chat)) => (chat.session_id, false),
+        (None, None) => (Uuid::new_v4(), true),
+    };
+    let chat_id = chat.as_ref().map_or_else(Uuid::new_v4, |chat| chat.id);
+
+    // Keep history to the active thread so topics don't bleed into each other
+    let thread_id = if payload.new_thread.unwrap_or(false) {
+        Some(Uuid::new_v4())
+    } else {
+        payload
+            .thread_id
+            .as_deref()
+            .map(Uuid::parse_str)
+            .transpose()
+            .map_err(|e| {
+                tracing::error!("Invalid thread_id format: {}", e);
+                StatusCode::BAD_REQUEST
+            })?
+    };
+
+    let turn = NewChatTurn {
+        session_id,
+        new_session,
+        chat_id,
+        new_chat: chat.is_none(),
+        chatbot_id,
+        thread_id,
+        user_query: payload.query.clone(),
+    };
+
+    // A person has the chat, or the user just asked for one: reply with the handoff notice
+    let escalated = chat.as_ref().is_some_and(|chat| chat.escalated_at.is_some());
+    if let Some(conversation) = handoff_turn(&app_state, &tenant, &chatbot, escalated, &turn).await? {
+        return Ok(Json(json!({
+            "success": true,
+            "message": "Chat handed off to a person",
+            "data": {
+                "session_id": session_id,
+                "chat_id": chat_id,
+                "thread_id": thread_id,
+                "conversation_id": conversation.id,
+                "user_query": payload.query,
+                "search_query": payload.query,
+                "bot_response": conversation.bot_response,
+                "original_response": null,
+                "translated_to": null,
+                "context_used": [],
+                "retrieval_trace": [],
+                "attributions": [],
+                "fallback": false,
+                "sql_query": null,
+                "escalated": true,
+                "cache_hit": false
             }
-        }
+        }))
+        .into_response());
+    }
+
+    // Get conversation history for context (last 5 messages only); a new chat has none
+    let conversations = if turn.new_chat {
+        Vec::new()
+    } else {
+        list_last_conversations_by_chat(&app_state.db, tenant.organization_id, chat_id, thread_id, 5).await.map_err(|e| {
+            tracing::error!("Failed to get conversation history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
     };
 
+    // Build conversation history context (limited to last 5 messages for efficiency)
+    let history_turns: Vec<String> = conversations
+        .iter()
+        .map(history_turn)
+        .collect();
+    let conversation_history = join_sections(history_turns.iter().cloned());
+
     // Create embedding service
-    let embedding_service = EmbeddingService::new(app_state.elasticsearch.clone()).map_err(|e| {
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
         tracing::error!("Failed to create embedding service: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Create collection name for this chatbot
-    let collection_name = format!("chatbot_{}", chatbot_id);
+    // A question close to one answered recently gets the same answer, without retrieval or the model
+    let cache_query = cacheable_query(&app_state, &embedding_service, &chatbot, &payload, !conversations.is_empty()).await;
+    if let Some(hit) = cache_query.as_ref().and_then(|query| app_state.answer_cache.lookup(query)) {
+        tracing::info!("✅ Answer cache hit (similarity {:.3} to \"{}\")", hit.similarity, hit.query);
+        let mut usage = UsageEvent {
+            organization_id: tenant.organization_id,
+            chatbot_id,
+            operation: "chat",
+            ..Default::default()
+        };
+        let (bot_response, original_response) = match &translate_to {
+            Some(language) => {
+                let gemini_service = GeminiService::new().map_err(|e| {
+                    tracing::error!("Failed to create Gemini service: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                let translated = translate_answer(&gemini_service, &hit.answer, language).await.map_err(|e| {
+                    tracing::error!("❌ Failed to translate response: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                usage.input_tokens = app_state.token_budget.count(&hit.answer) as i64;
+                usage.output_tokens = app_state.token_budget.count(&translated) as i64;
+                (translated, Some(hit.answer.clone()))
+            }
+            None => (hit.answer.clone(), None),
+        };
 
-    // Search for similar embeddings to get context
-    let search_results = embedding_service.search_similar(&collection_name, &payload.query, 5).await.map_err(|e| {
-        tracing::error!("Failed to search embeddings: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+        let conversation = create_chat_turn(&app_state.db, tenant.organization_id, tenant.user_id, &turn)
+            .await
+            .map_err(turn_error)?;
+        if let Err(e) = update_conversation_response(&app_state.db, tenant.organization_id, conversation.id, bot_response.clone()).await {
+            tracing::error!("Failed to update conversation: {}", e);
+            if let Err(e) = discard_chat_turn(&app_state.db, tenant.organization_id, &turn, &conversation).await {
+                tracing::warn!("⚠️ Failed to roll back chat turn: {}", e);
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        tag_conversation(&app_state.background_jobs, app_state.db.clone(), conversation.id, payload.query.clone());
+        record_citations(&app_state.background_jobs, app_state.db.clone(), conversation.id, &hit.sources);
 
-    tracing::info!("Found {} similar results for query", search_results.len());
+        let attributions = if payload.attribute_sources.unwrap_or(true) {
+            attribute_answer(&embedding_service, &hit.answer, &hit.sources)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Source attribution failed: {}", e);
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+        record_usage(
+            &app_state.background_jobs,
+            app_state.db.clone(),
+            with_embedding_usage(usage, &embedding_service),
+        );
 
-    // Prepare context from search results
-    let context: String = search_results
-        .iter()
-        .map(|result| format!("Document: {}\nContent: {}", result.file_path, result.text))
-        .collect::<Vec<_>>()
-        .join("\n\n");
+        let context_used: Vec<String> = hit.sources.iter().map(|result| result.file_path.clone()).collect();
+        return Ok(Json(json!({
+            "success": true,
+            "message": "Chat request processed successfully",
+            "data": {
+                "session_id": session_id,
+                "chat_id": chat_id,
+                "thread_id": thread_id,
+                "conversation_id": conversation.id,
+                "user_query": payload.query,
+                "search_query": payload.query,
+                "bot_response": bot_response,
+                "original_response": original_response,
+                "translated_to": translate_to,
+                "context_used": context_used,
+                "retrieval_trace": [],
+                "attributions": attributions,
+                "fallback": false,
+                "sql_query": null,
+                "escalated": false,
+                "cache_hit": true
+            }
+        }))
+        .into_response());
+    }
 
-    // Get conversation history for context (last 5 messages only)
-    let conversations = list_last_conversations_by_chat(&app_state.db, chat_id, 5).await.map_err(|e| {
-        tracing::error!("Failed to get conversation history: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    // Resolve every shard index for this chatbot
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
 
-    // Build conversation history context (limited to last 5 messages for efficiency)
-    let conversation_history: String = conversations
-        .iter()
-        .map(|conv| {
-            format!(
-                "User: {}\nBot: {}",
-                conv.user_query,
-                conv.bot_response.as_ref().unwrap_or(&"".to_string())
-            )
+    // Track retrieval stages against the request's latency budget
+    let mut retrieval = RetrievalRun::new(
+        LatencyBudget::from_request(payload.latency_budget_ms),
+        app_state.stage_timings.clone(),
+    );
+
+    // Condense follow-ups into a standalone question before searching
+    let search_query = if rewrite_enabled(payload.rewrite_query) && !conversation_history.is_empty() {
+        retrieval
+            .run_optional("query_rewrite", || rewrite_query(&conversation_history, &payload.query))
+            .await
+            .unwrap_or_else(|| payload.query.clone())
+    } else {
+        payload.query.clone()
+    };
+
+    // The chatbot's query script may rewrite what we search for
+    let search_query = match chatbot.query_script.as_deref() {
+        Some(script) => run_query_hook(script, &search_query).map_err(|e| {
+            tracing::error!("❌ Query script failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?,
+        None => search_query,
+    };
+
+    // Chatbots with query expansion also search with paraphrases of the question, if the budget allows
+    let search_queries = if chatbot.query_paraphrases > 0 {
+        retrieval
+            .run_optional("query_expansion", || expand_query(&search_query, chatbot.query_paraphrases as usize))
+            .await
+            .unwrap_or_else(|| vec![search_query.clone()])
+    } else {
+        vec![search_query.clone()]
+    };
+
+    // Search for similar embeddings to get context, fusing the hits of every query
+    let search_results = retrieval
+        .run_required("knn", || {
+            search_fused(&embedding_service, &index_names, &search_queries, 5, chatbot.mmr_lambda)
         })
-        .collect::<Vec<_>>()
-        .join("\n\n");
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search embeddings: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    // Create conversation record
-    let conversation = create_conversation(
-        &app_state.db,
-        session_id,
-        chat_id,
-        payload.query.clone(),
-    ).await.map_err(|e| {
-        tracing::error!("Failed to create conversation: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    tracing::info!("Found {} similar results for query", search_results.len());
+    record_retrieval(&app_state.background_jobs, app_state.db.clone(), chatbot_id, &search_results);
+
+    // Deployment plugins may drop or reorder hits
+    let search_results = if app_state.plugins.has_result_stages() {
+        retrieval
+            .run_required("plugins", || app_state.plugins.filter_and_rerank(&search_query, search_results))
+            .await
+            .map_err(|e| {
+                tracing::error!("❌ Plugin stage failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        search_results
+    };
+
+    // Drop hits below the chatbot's minimum score
+    let search_results = filter_by_min_score(search_results, chatbot.min_score);
+    let fallback = fallback_response(&chatbot, &search_results);
+
+    // Analytical questions may be answered from the chatbot's whitelisted tables; rows count as a
+    // source, so strict mode doesn't fall back when the query found some
+    let sql_result = sql_tool_result(&app_state, tenant.organization_id, chatbot_id, &search_query, &mut retrieval).await;
+    let fallback = fallback.filter(|_| sql_result.as_ref().is_none_or(|result| result.rows.is_empty()));
+    // Chatbots that route such questions to their no-context template answer with it instead
+    let no_context_prompt = no_context_template(&chatbot, &search_results)
+        .filter(|_| sql_result.as_ref().is_none_or(|result| result.rows.is_empty()));
+
+    // Trim chunks to the sentences relevant to the query, if enabled and within budget
+    let compression = CompressionMode::from_request(payload.context_compression.as_deref());
+    let search_results = if compression == CompressionMode::None {
+        search_results
+    } else {
+        let uncompressed = search_results.clone();
+        retrieval
+            .run_optional("compression", || compress_results(compression, &search_query, search_results))
+            .await
+            .unwrap_or(uncompressed)
+    };
+    tracing::info!("Retrieval trace: {:?}", retrieval.trace);
 
     // Generate response using Gemini
     let gemini_service = GeminiService::new().map_err(|e| {
@@ -234,33 +762,216 @@ pub async fn chat_handler(
     })?;
 
     // Combine context and conversation history
-    let full_context = if !conversation_history.is_empty() {
-        format!("Previous conversation:\n{}\n\nRelevant documents:\n{}", conversation_history, context)
-    } else {
-        format!("Relevant documents:\n{}", context)
-    };
+    // Glossary entries matching the query are always included
+    let glossary_entries = list_glossary_entries(&app_state.db, tenant.organization_id, chatbot_id).await.map_err(|e| {
+        tracing::error!("Failed to get glossary: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let glossary = format_glossary(&matching_entries(&payload.query, &glossary_entries));
 
-    let bot_response = gemini_service.generate_response(&payload.query, &full_context).await.map_err(|e| {
-        tracing::error!("Failed to generate response: {}", e);
+    // A shared template selected for the chatbot takes precedence over its inline one,
+    // and a running canary answers its share of chats
+    let template = chatbot_template(&app_state.db, tenant.organization_id, &chatbot, chat_id).await.map_err(|e| {
+        tracing::error!("Failed to get prompt template: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Update conversation with bot response
-    let updated_conversation = update_conversation_response(
-        &app_state.db,
-        conversation.id,
-        bot_response.clone(),
-    ).await.map_err(|e| {
-        tracing::error!("Failed to update conversation: {}", e);
+    // Measure the prompt without history or documents, then trim both to fit the context window
+    let fixed_prompt = render_prompt(
+        no_context_prompt.or(template.template.as_deref()),
+        &PromptContext { glossary: &glossary, history: "", documents: "" },
+        &payload.query,
+        payload.variables.as_ref(),
+    ).map_err(|e| {
+        tracing::error!("Failed to render prompt: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let fitted = app_state.token_budget.fit(app_state.token_budget.count(&fixed_prompt), history_turns, search_results);
+    let tokens = fitted.breakdown;
+    tracing::info!(
+        "Prompt tokens: {} (fixed {}, history {}, documents {}); dropped {} history turns and {} chunks",
+        tokens.total(),
+        tokens.fixed,
+        tokens.history,
+        tokens.documents,
+        tokens.history_turns_dropped,
+        tokens.chunks_dropped
+    );
+    let search_results = fitted.chunks;
+    let conversation_history = join_sections(fitted.history);
+
+    // Prepare context from the chunks that fit, grouped by document in document order, and the SQL tool's rows
+    let mut sections: Vec<String> = group_by_document(&search_results).iter().map(format_document).collect();
+    sections.extend(sql_result.as_ref().map(SqlToolResult::context));
+    let context = if !sections.is_empty() {
+        join_sections(sections)
+    } else if no_context_prompt.is_some() {
+        String::new()
+    } else {
+        GENERAL_KNOWLEDGE_CONTEXT.to_string()
+    };
+
+    let prompt_context = PromptContext {
+        glossary: &glossary,
+        history: &conversation_history,
+        documents: &context,
+    };
+
+    // Fill the chatbot's prompt template with the context and request variables
+    let prompt = render_prompt(
+        no_context_prompt.or(template.template.as_deref()),
+        &prompt_context,
+        &payload.query,
+        payload.variables.as_ref(),
+    ).map_err(|e| {
+        tracing::error!("Failed to render prompt: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // Size the prompt before anything is stored, so a prompt that is too large leaves no turn behind
+    let prompt_tokens = match &fallback {
+        Some(_) => 0,
+        None => match app_state.token_budget.check(&prompt, tokens.fixed) {
+            Ok(prompt_tokens) => prompt_tokens,
+            Err(error) => {
+                tracing::warn!("⚠️ Not sending prompt to the model: {}", error);
+                return Ok(context_too_large(&error));
+            }
+        },
+    };
+
+    // Store the turn, with its session and chat when they are new, once the prompt is ready
+    let conversation = create_chat_turn(&app_state.db, tenant.organization_id, tenant.user_id, &turn)
+        .await
+        .map_err(turn_error)?;
+
+    // Model tokens, embeddings and searches this request uses
+    let mut usage = UsageEvent {
+        organization_id: tenant.organization_id,
+        chatbot_id,
+        operation: "chat",
+        ..Default::default()
+    };
+
+    let answered = async {
+        // Strict-mode chatbots reply with their fallback message when nothing passed the threshold
+        let (bot_response, incidents) = match &fallback {
+            Some(message) => {
+                tracing::info!("No chunk passed the score threshold, returning fallback message");
+                (message.clone(), Vec::new())
+            }
+            None => {
+                let answer = gemini_service.generate_response(&prompt).await.map_err(|e| {
+                    tracing::error!("Failed to generate response: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                usage.input_tokens = prompt_tokens as i64;
+                usage.output_tokens = app_state.token_budget.count(&answer) as i64;
+                // The chatbot's answer script may rewrite the model's answer
+                let answer = match chatbot.answer_script.as_deref() {
+                    Some(script) => run_answer_hook(script, &payload.query, &answer).map_err(|e| {
+                        tracing::error!("❌ Answer script failed: {}", e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?,
+                    None => answer,
+                };
+                // Output filters mask or block unsafe wording before the answer is stored
+                match chatbot.output_filters.as_deref() {
+                    Some(filters) => {
+                        let filtered = filter_answer(filters, &answer).await.map_err(|e| {
+                            tracing::error!("❌ Output filtering failed: {}", e);
+                            StatusCode::INTERNAL_SERVER_ERROR
+                        })?;
+                        (filtered.text, filtered.incidents)
+                    }
+                    None => (answer, Vec::new()),
+                }
+            }
+        };
+
+        // Translate the final answer if requested; attributions still point into the original
+        let (bot_response, original_response) = match &translate_to {
+            Some(language) => {
+                let translated = translate_answer(&gemini_service, &bot_response, language).await.map_err(|e| {
+                    tracing::error!("❌ Failed to translate response: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                usage.input_tokens += app_state.token_budget.count(&bot_response) as i64;
+                usage.output_tokens += app_state.token_budget.count(&translated) as i64;
+                (translated, Some(bot_response))
+            }
+            None => (bot_response, None),
+        };
+
+        // Update conversation with bot response
+        let updated_conversation = update_conversation_response(
+            &app_state.db,
+            tenant.organization_id,
+            conversation.id,
+            bot_response.clone(),
+        ).await.map_err(|e| {
+            tracing::error!("Failed to update conversation: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok::<_, StatusCode>((bot_response, original_response, incidents, updated_conversation))
+    }
+    .await;
+    let (bot_response, original_response, incidents, updated_conversation) = match answered {
+        Ok(answered) => answered,
+        Err(status) => {
+            // Take the turn back out, with the session and chat it created, so a failed
+            // answer leaves nothing behind
+            if let Err(e) = discard_chat_turn(&app_state.db, tenant.organization_id, &turn, &conversation).await {
+                tracing::warn!("⚠️ Failed to roll back chat turn: {}", e);
+            }
+            return Err(status);
+        }
+    };
+
+    // Jobs that write about the turn start only once it is answered and can no longer be discarded
+    let (jobs, db) = (&app_state.background_jobs, app_state.db.clone());
+    tag_conversation(jobs, db.clone(), conversation.id, payload.query.clone());
+    if let Some(variant) = template.variant {
+        record_variant(jobs, db.clone(), conversation.id, variant);
+    }
+    record_citations(jobs, db.clone(), conversation.id, &search_results);
+    if fallback.is_none() {
+        record_context(jobs, db.clone(), conversation.id, &search_results, &prompt, &retrieval.trace);
+    }
+    record_incidents(jobs, db, chatbot_id, FilterStage::Answer, Some(conversation.id), incidents);
+
     // Prepare context used for response
     let context_used: Vec<String> = search_results
         .iter()
         .map(|result| result.file_path.clone())
         .collect();
 
+    // Map answer sentences back to the retrieved chunks for UI highlighting
+    let attributions = if payload.attribute_sources.unwrap_or(true) {
+        let answer = original_response.as_deref().unwrap_or(&bot_response);
+        attribute_answer(&embedding_service, answer, &search_results)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Source attribution failed: {}", e);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+
+    // Answers grounded on documents alone may serve near-identical questions later
+    if let Some(query) = cache_query.filter(|_| fallback.is_none() && sql_result.is_none()) {
+        let answer = original_response.clone().unwrap_or_else(|| bot_response.clone());
+        app_state.answer_cache.store(query, answer, search_results.clone());
+    }
+
+    record_usage(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        with_embedding_usage(usage, &embedding_service),
+    );
+
     tracing::info!("✅ Chat request processed successfully");
 
     Ok(Json(json!({
@@ -269,19 +980,47 @@ pub async fn chat_handler(
         "data": {
             "session_id": session_id,
             "chat_id": chat_id,
+            "thread_id": thread_id,
             "conversation_id": updated_conversation.id,
             "user_query": payload.query,
+            "search_query": search_query,
             "bot_response": bot_response,
-            "context_used": context_used
+            "original_response": original_response,
+            "translated_to": translate_to,
+            "context_used": context_used,
+            "retrieval_trace": retrieval.trace,
+            "attributions": attributions,
+            "fallback": fallback.is_some(),
+            "sql_query": sql_result.as_ref().map(|result| &result.query),
+            "escalated": false,
+            "cache_hit": false
         }
-    })))
+    }))
+    .into_response())
 }
 
 // Streaming chat endpoint
+#[utoipa::path(
+    post,
+    path = "/api/chat/stream",
+    tag = "chat",
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Server-sent events, one JSON chunk per event", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid id format"),
+        (status = 404, description = "Chatbot, session or chat not found"),
+        (status = 409, description = "Concurrent messages to the chat took the turn number; send the message again"),
+        (status = 413, description = "Prompt too large for the model's context window", body = Value),
+        (status = 422, description = "Query blocked by the chatbot's query filters", body = Value),
+        (status = 429, description = "Rate limit exceeded"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn chat_stream_handler(
     State(app_state): State<AppState>,
-    Json(payload): Json<ChatRequest>,
-) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    tenant: Tenant,
+    Json(mut payload): Json<ChatRequest>,
+) -> Result<Response, StatusCode> {
     tracing::info!("Processing streaming chat request: {}", payload.query);
 
     // Parse chatbot_id
@@ -290,17 +1029,46 @@ pub async fn chat_stream_handler(
         StatusCode::BAD_REQUEST
     })?;
 
-    // Handle session_id - create new if not provided
+    let translate_to = payload
+        .translate_to
+        .as_deref()
+        .map(validate_language)
+        .transpose()
+        .map_err(|e| {
+            tracing::error!("Invalid translate_to: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    // Verify chatbot belongs to the caller's organization
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Query filters screen the query before anything else sees it
+    payload.query = match moderated_query(&app_state, &chatbot, &payload.query).await {
+        Ok(query) => query,
+        Err(response) => return Ok(response),
+    };
+
+    // A given session must exist; without one, the turn creates a session
     let session_id = match payload.session_id {
         Some(session_id_str) => {
             let session_uuid = Uuid::parse_str(&session_id_str).map_err(|e| {
                 tracing::error!("Invalid session_id format: {}", e);
                 StatusCode::BAD_REQUEST
             })?;
-            
+
             // Verify session exists
-            match get_session(&app_state.db, session_uuid).await {
-                Ok(Some(_)) => session_uuid,
+            match get_session(&app_state.db, tenant.organization_id, session_uuid, tenant.user_id).await {
+                Ok(Some(_)) => Some(session_uuid),
                 Ok(None) => {
                     tracing::error!("Session not found: {}", session_uuid);
                     return Err(StatusCode::NOT_FOUND);
@@ -311,32 +1079,20 @@ pub async fn chat_stream_handler(
                 }
             }
         }
-        None => {
-            // Create new session
-            match create_session(&app_state.db).await {
-                Ok(session) => {
-                    tracing::info!("Created new session: {}", session.id);
-                    session.id
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create session: {}", e);
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                }
-            }
-        }
+        None => None,
     };
 
-    // Handle chat_id - create new if not provided
-    let chat_id = match payload.chat_id {
+    // A given chat must exist; without one, the turn creates a chat
+    let chat = match payload.chat_id {
         Some(chat_id_str) => {
             let chat_uuid = Uuid::parse_str(&chat_id_str).map_err(|e| {
                 tracing::error!("Invalid chat_id format: {}", e);
                 StatusCode::BAD_REQUEST
             })?;
-            
+
             // Verify chat exists
-            match get_chat(&app_state.db, chat_uuid).await {
-                Ok(Some(_)) => chat_uuid,
+            match get_chat(&app_state.db, tenant.organization_id, chat_uuid, tenant.user_id).await {
+                Ok(Some(chat)) => Some(chat),
                 Ok(None) => {
                     tracing::error!("Chat not found: {}", chat_uuid);
                     return Err(StatusCode::NOT_FOUND);
@@ -347,74 +1103,243 @@ pub async fn chat_stream_handler(
                 }
             }
         }
-        None => {
-            // Create new chat
-            match create_chat(&app_state.db, session_id, "New Chat".to_string()).await {
-                Ok(chat) => {
-                    tracing::info!("Created new chat: {}", chat.id);
-                    chat.id
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create chat: {}", e);
-                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                }
-            }
-        }
+        None => None,
+    };
+    // A chat without a given session stays in its own; ids of a new session and chat are chosen
+    // now and the rows are written with the turn
+    let (session_id, new_session) = match (session_id, &chat) {
+        (Some(session_id), _) => (session_id, false),
+        (None, Some(chat)) => (chat.session_id, false),
+        (None, None) => (Uuid::new_v4(), true),
     };
+    let chat_id = chat.as_ref().map_or_else(Uuid::new_v4, |chat| chat.id);
+
+    // Keep history to the active thread so topics don't bleed into each other
+    let thread_id = if payload.new_thread.unwrap_or(false) {
+        Some(Uuid::new_v4())
+    } else {
+        payload
+            .thread_id
+            .as_deref()
+            .map(Uuid::parse_str)
+            .transpose()
+            .map_err(|e| {
+                tracing::error!("Invalid thread_id format: {}", e);
+                StatusCode::BAD_REQUEST
+            })?
+    };
+
+    let turn = NewChatTurn {
+        session_id,
+        new_session,
+        chat_id,
+        new_chat: chat.is_none(),
+        chatbot_id,
+        thread_id,
+        user_query: payload.query.clone(),
+    };
+
+    // A person has the chat, or the user just asked for one: reply with the handoff notice
+    let escalated = chat.as_ref().is_some_and(|chat| chat.escalated_at.is_some());
+    if let Some(conversation) = handoff_turn(&app_state, &tenant, &chatbot, escalated, &turn).await? {
+        let event_data = json!({
+            "text": conversation.bot_response,
+            "is_final": true,
+            "session_id": session_id,
+            "chat_id": chat_id,
+            "thread_id": thread_id,
+            "conversation_id": conversation.id,
+            "fallback": false,
+            "escalated": true,
+            "cache_hit": false
+        });
+        let notice = futures_util::stream::once(async move {
+            Ok::<_, axum::Error>(Event::default().data(event_data.to_string()))
+        });
+        return Ok(Sse::new(notice).into_response());
+    }
+
+    // Get conversation history for context (last 5 messages only); a new chat has none
+    let conversations = if turn.new_chat {
+        Vec::new()
+    } else {
+        list_last_conversations_by_chat(&app_state.db, tenant.organization_id, chat_id, thread_id, 5).await.map_err(|e| {
+            tracing::error!("Failed to get conversation history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    };
+
+    // Build conversation history context (limited to last 5 messages for efficiency)
+    let history_turns: Vec<String> = conversations
+        .iter()
+        .map(history_turn)
+        .collect();
+    let conversation_history = join_sections(history_turns.iter().cloned());
 
     // Create embedding service
-    let embedding_service = EmbeddingService::new(app_state.elasticsearch.clone()).map_err(|e| {
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
         tracing::error!("Failed to create embedding service: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Create collection name for this chatbot
-    let collection_name = format!("chatbot_{}", chatbot_id);
+    // A question close to one answered recently gets the same answer, without retrieval or the model
+    let cache_query = cacheable_query(&app_state, &embedding_service, &chatbot, &payload, !conversations.is_empty()).await;
+    if let Some(hit) = cache_query.as_ref().and_then(|query| app_state.answer_cache.lookup(query)) {
+        tracing::info!("✅ Answer cache hit (similarity {:.3} to \"{}\")", hit.similarity, hit.query);
+        let mut usage = UsageEvent {
+            organization_id: tenant.organization_id,
+            chatbot_id,
+            operation: "chat_stream",
+            ..Default::default()
+        };
+        let answer = match &translate_to {
+            Some(language) => {
+                let gemini_service = GeminiService::new().map_err(|e| {
+                    tracing::error!("Failed to create Gemini service: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                let translated = translate_answer(&gemini_service, &hit.answer, language).await.map_err(|e| {
+                    tracing::error!("❌ Failed to translate response: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                usage.input_tokens = app_state.token_budget.count(&hit.answer) as i64;
+                usage.output_tokens = app_state.token_budget.count(&translated) as i64;
+                translated
+            }
+            None => hit.answer.clone(),
+        };
 
-    // Search for similar embeddings to get context
-    let search_results = embedding_service.search_similar(&collection_name, &payload.query, 5).await.map_err(|e| {
-        tracing::error!("Failed to search embeddings: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+        let conversation = create_chat_turn(&app_state.db, tenant.organization_id, tenant.user_id, &turn)
+            .await
+            .map_err(turn_error)?;
+        if let Err(e) = update_conversation_response(&app_state.db, tenant.organization_id, conversation.id, answer.clone()).await {
+            tracing::error!("Failed to update conversation: {}", e);
+            if let Err(e) = discard_chat_turn(&app_state.db, tenant.organization_id, &turn, &conversation).await {
+                tracing::warn!("⚠️ Failed to roll back chat turn: {}", e);
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        tag_conversation(&app_state.background_jobs, app_state.db.clone(), conversation.id, payload.query.clone());
+        record_citations(&app_state.background_jobs, app_state.db.clone(), conversation.id, &hit.sources);
+        record_usage(
+            &app_state.background_jobs,
+            app_state.db.clone(),
+            with_embedding_usage(usage, &embedding_service),
+        );
 
-    tracing::info!("Found {} similar results for query", search_results.len());
+        let event_data = json!({
+            "text": answer,
+            "is_final": true,
+            "session_id": session_id,
+            "chat_id": chat_id,
+            "thread_id": thread_id,
+            "conversation_id": conversation.id,
+            "retrieval_trace": [],
+            "fallback": false,
+            "sql_query": null,
+            "search_query": payload.query,
+            "translated_to": translate_to,
+            "escalated": false,
+            "cache_hit": true
+        });
+        let cached = futures_util::stream::once(async move {
+            Ok::<_, axum::Error>(Event::default().data(event_data.to_string()))
+        });
+        return Ok(Sse::new(cached).into_response());
+    }
 
-    // Prepare context from search results
-    let context: String = search_results
-        .iter()
-        .map(|result| format!("Document: {}\nContent: {}", result.file_path, result.text))
-        .collect::<Vec<_>>()
-        .join("\n\n");
+    // Resolve every shard index for this chatbot
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
 
-    // Get conversation history for context (last 5 messages only)
-    let conversations = list_last_conversations_by_chat(&app_state.db, chat_id, 5).await.map_err(|e| {
-        tracing::error!("Failed to get conversation history: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    // Track retrieval stages against the request's latency budget
+    let mut retrieval = RetrievalRun::new(
+        LatencyBudget::from_request(payload.latency_budget_ms),
+        app_state.stage_timings.clone(),
+    );
 
-    // Build conversation history context (limited to last 5 messages for efficiency)
-    let conversation_history: String = conversations
-        .iter()
-        .map(|conv| {
-            format!(
-                "User: {}\nBot: {}",
-                conv.user_query,
-                conv.bot_response.as_ref().unwrap_or(&"".to_string())
-            )
+    // Condense follow-ups into a standalone question before searching
+    let search_query = if rewrite_enabled(payload.rewrite_query) && !conversation_history.is_empty() {
+        retrieval
+            .run_optional("query_rewrite", || rewrite_query(&conversation_history, &payload.query))
+            .await
+            .unwrap_or_else(|| payload.query.clone())
+    } else {
+        payload.query.clone()
+    };
+
+    // The chatbot's query script may rewrite what we search for
+    let search_query = match chatbot.query_script.as_deref() {
+        Some(script) => run_query_hook(script, &search_query).map_err(|e| {
+            tracing::error!("❌ Query script failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?,
+        None => search_query,
+    };
+
+    // Chatbots with query expansion also search with paraphrases of the question, if the budget allows
+    let search_queries = if chatbot.query_paraphrases > 0 {
+        retrieval
+            .run_optional("query_expansion", || expand_query(&search_query, chatbot.query_paraphrases as usize))
+            .await
+            .unwrap_or_else(|| vec![search_query.clone()])
+    } else {
+        vec![search_query.clone()]
+    };
+
+    // Search for similar embeddings to get context, fusing the hits of every query
+    let search_results = retrieval
+        .run_required("knn", || {
+            search_fused(&embedding_service, &index_names, &search_queries, 5, chatbot.mmr_lambda)
         })
-        .collect::<Vec<_>>()
-        .join("\n\n");
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search embeddings: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    // Create conversation record
-    let conversation = create_conversation(
-        &app_state.db,
-        session_id,
-        chat_id,
-        payload.query.clone(),
-    ).await.map_err(|e| {
-        tracing::error!("Failed to create conversation: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    tracing::info!("Found {} similar results for query", search_results.len());
+    record_retrieval(&app_state.background_jobs, app_state.db.clone(), chatbot_id, &search_results);
+
+    // Deployment plugins may drop or reorder hits
+    let search_results = if app_state.plugins.has_result_stages() {
+        retrieval
+            .run_required("plugins", || app_state.plugins.filter_and_rerank(&search_query, search_results))
+            .await
+            .map_err(|e| {
+                tracing::error!("❌ Plugin stage failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        search_results
+    };
+
+    // Drop hits below the chatbot's minimum score
+    let search_results = filter_by_min_score(search_results, chatbot.min_score);
+    let fallback = fallback_response(&chatbot, &search_results);
+
+    // Analytical questions may be answered from the chatbot's whitelisted tables; rows count as a
+    // source, so strict mode doesn't fall back when the query found some
+    let sql_result = sql_tool_result(&app_state, tenant.organization_id, chatbot_id, &search_query, &mut retrieval).await;
+    let fallback = fallback.filter(|_| sql_result.as_ref().is_none_or(|result| result.rows.is_empty()));
+    // Chatbots that route such questions to their no-context template answer with it instead
+    let no_context_prompt = no_context_template(&chatbot, &search_results)
+        .filter(|_| sql_result.as_ref().is_none_or(|result| result.rows.is_empty()));
+
+    // Trim chunks to the sentences relevant to the query, if enabled and within budget
+    let compression = CompressionMode::from_request(payload.context_compression.as_deref());
+    let search_results = if compression == CompressionMode::None {
+        search_results
+    } else {
+        let uncompressed = search_results.clone();
+        retrieval
+            .run_optional("compression", || compress_results(compression, &search_query, search_results))
+            .await
+            .unwrap_or(uncompressed)
+    };
+    tracing::info!("Retrieval trace: {:?}", retrieval.trace);
 
     // Generate streaming response using Gemini
     let gemini_service = GeminiService::new().map_err(|e| {
@@ -423,34 +1348,287 @@ pub async fn chat_stream_handler(
     })?;
 
     // Combine context and conversation history
-    let full_context = if !conversation_history.is_empty() {
-        format!("Previous conversation:\n{}\n\nRelevant documents:\n{}", conversation_history, context)
+    // Glossary entries matching the query are always included
+    let glossary_entries = list_glossary_entries(&app_state.db, tenant.organization_id, chatbot_id).await.map_err(|e| {
+        tracing::error!("Failed to get glossary: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let glossary = format_glossary(&matching_entries(&payload.query, &glossary_entries));
+
+    // A shared template selected for the chatbot takes precedence over its inline one,
+    // and a running canary answers its share of chats
+    let template = chatbot_template(&app_state.db, tenant.organization_id, &chatbot, chat_id).await.map_err(|e| {
+        tracing::error!("Failed to get prompt template: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Measure the prompt without history or documents, then trim both to fit the context window
+    let fixed_prompt = render_prompt(
+        no_context_prompt.or(template.template.as_deref()),
+        &PromptContext { glossary: &glossary, history: "", documents: "" },
+        &payload.query,
+        payload.variables.as_ref(),
+    ).map_err(|e| {
+        tracing::error!("Failed to render prompt: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let fitted = app_state.token_budget.fit(app_state.token_budget.count(&fixed_prompt), history_turns, search_results);
+    let tokens = fitted.breakdown;
+    tracing::info!(
+        "Prompt tokens: {} (fixed {}, history {}, documents {}); dropped {} history turns and {} chunks",
+        tokens.total(),
+        tokens.fixed,
+        tokens.history,
+        tokens.documents,
+        tokens.history_turns_dropped,
+        tokens.chunks_dropped
+    );
+    let search_results = fitted.chunks;
+    let conversation_history = join_sections(fitted.history);
+
+    // Prepare context from the chunks that fit, grouped by document in document order, and the SQL tool's rows
+    let mut sections: Vec<String> = group_by_document(&search_results).iter().map(format_document).collect();
+    sections.extend(sql_result.as_ref().map(SqlToolResult::context));
+    let context = if !sections.is_empty() {
+        join_sections(sections)
+    } else if no_context_prompt.is_some() {
+        String::new()
     } else {
-        format!("Relevant documents:\n{}", context)
+        GENERAL_KNOWLEDGE_CONTEXT.to_string()
+    };
+
+    let prompt_context = PromptContext {
+        glossary: &glossary,
+        history: &conversation_history,
+        documents: &context,
     };
 
-    // Create streaming response
-    let stream = gemini_service.generate_response_stream(&payload.query, &full_context).await.map_err(|e| {
-        tracing::error!("Failed to create streaming response: {}", e);
+    // Fill the chatbot's prompt template with the context and request variables
+    let prompt = render_prompt(
+        no_context_prompt.or(template.template.as_deref()),
+        &prompt_context,
+        &payload.query,
+        payload.variables.as_ref(),
+    ).map_err(|e| {
+        tracing::error!("Failed to render prompt: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    // Size the prompt before anything is stored, so a prompt that is too large leaves no turn behind
+    let prompt_tokens = match &fallback {
+        Some(_) => 0,
+        None => match app_state.token_budget.check(&prompt, tokens.fixed) {
+            Ok(prompt_tokens) => prompt_tokens,
+            Err(error) => {
+                tracing::warn!("⚠️ Not sending prompt to the model: {}", error);
+                return Ok(context_too_large(&error));
+            }
+        },
+    };
+
+    // Store the turn, with its session and chat when they are new, once the prompt is ready
+    let conversation = create_chat_turn(&app_state.db, tenant.organization_id, tenant.user_id, &turn)
+        .await
+        .map_err(turn_error)?;
+
+    // Model tokens, embeddings and searches this request uses
+    let mut usage = UsageEvent {
+        organization_id: tenant.organization_id,
+        chatbot_id,
+        operation: "chat_stream",
+        input_tokens: prompt_tokens as i64,
+        ..Default::default()
+    };
+
+    // Answers grounded on documents alone may serve near-identical questions later
+    let mut cache_query = cache_query.filter(|_| fallback.is_none() && sql_result.is_none());
+
+    // Answer scripts, output filters and translation need the whole answer, so buffer it and send it as one chunk
+    let answer_script = chatbot.answer_script.as_deref().filter(|_| fallback.is_none());
+    let output_filters = chatbot.output_filters.as_deref().filter(|_| fallback.is_none());
+    let buffered = answer_script.is_some() || output_filters.is_some() || translate_to.is_some();
+
+    // A fallback or buffered answer is stored before it is sent; a streamed one is saved as it streams
+    let answered = async {
+        // Strict-mode chatbots stream their fallback message when nothing passed the threshold
+        let mut answer = match &fallback {
+            Some(message) => {
+                tracing::info!("No chunk passed the score threshold, returning fallback message");
+                message.clone()
+            }
+            None => {
+                let stream = gemini_service.generate_response_stream(&prompt).await.map_err(|e| {
+                    tracing::error!("Failed to create streaming response: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                if !buffered {
+                    return Ok::<_, StatusCode>((stream, Vec::new(), false));
+                }
+
+                let mut answer = String::new();
+                for chunk in stream.collect::<Vec<_>>().await {
+                    let chunk = chunk.map_err(|e| {
+                        tracing::error!("Streaming error: {}", e);
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?;
+                    answer.push_str(&chunk.text);
+                }
+                answer
+            }
+        };
+
+        let mut incidents = Vec::new();
+        if fallback.is_none() {
+            usage.output_tokens = app_state.token_budget.count(&answer) as i64;
+        }
+        if let Some(script) = answer_script {
+            answer = run_answer_hook(script, &payload.query, &answer).map_err(|e| {
+                tracing::error!("❌ Answer script failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        }
+        if let Some(filters) = output_filters {
+            let filtered = filter_answer(filters, &answer).await.map_err(|e| {
+                tracing::error!("❌ Output filtering failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            incidents = filtered.incidents;
+            answer = filtered.text;
+        }
+        // The untranslated answer is cached, like the non-streaming endpoint does
+        if let Some(query) = cache_query.take() {
+            app_state.answer_cache.store(query, answer.clone(), search_results.clone());
+        }
+        if let Some(language) = &translate_to {
+            let translated = translate_answer(&gemini_service, &answer, language).await.map_err(|e| {
+                tracing::error!("❌ Failed to translate response: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            usage.input_tokens += app_state.token_budget.count(&answer) as i64;
+            usage.output_tokens += app_state.token_budget.count(&translated) as i64;
+            answer = translated;
+        }
+
+        update_conversation_response(&app_state.db, tenant.organization_id, conversation.id, answer.clone())
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to update conversation: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        let stream: Pin<Box<dyn Stream<Item = AppResult<StreamingChunk>> + Send>> =
+            Box::pin(futures_util::stream::iter(vec![Ok(StreamingChunk {
+                text: answer,
+                is_final: true,
+            })]));
+        Ok((stream, incidents, true))
+    }
+    .await;
+    let (stream, incidents, stored) = match answered {
+        Ok(answered) => answered,
+        Err(status) => {
+            // Take the turn back out, with the session and chat it created, so a failed
+            // answer leaves nothing behind
+            if let Err(e) = discard_chat_turn(&app_state.db, tenant.organization_id, &turn, &conversation).await {
+                tracing::warn!("⚠️ Failed to roll back chat turn: {}", e);
+            }
+            return Err(status);
+        }
+    };
+
+    // Jobs that write about the turn start only once its answer is stored and it can no longer be
+    // discarded: right away for a stored answer, at the last chunk for a streamed one
+    let (jobs, db) = (app_state.background_jobs.clone(), app_state.db.clone());
+    record_incidents(&jobs, db.clone(), chatbot_id, FilterStage::Answer, Some(conversation.id), incidents);
+    let (conversation_id, user_query, variant) = (conversation.id, payload.query.clone(), template.variant);
+    let (cited, trace) = (search_results.clone(), retrieval.trace.clone());
+    let context_prompt = fallback.is_none().then(|| prompt.clone());
+    let mut record_turn = Some(move || {
+        tag_conversation(&jobs, db.clone(), conversation_id, user_query);
+        if let Some(variant) = variant {
+            record_variant(&jobs, db.clone(), conversation_id, variant);
+        }
+        record_citations(&jobs, db.clone(), conversation_id, &cited);
+        if let Some(prompt) = context_prompt {
+            record_context(&jobs, db, conversation_id, &cited, &prompt, &trace);
+        }
+    });
+    if stored && let Some(record) = record_turn.take() {
+        record();
+    }
+
+    // Count the streamed answer unless it was buffered above or is the fallback message
+    let mut stream_usage = StreamUsage::new(
+        app_state.background_jobs.clone(),
+        app_state.db.clone(),
+        app_state.token_budget.clone(),
+        with_embedding_usage(usage, &embedding_service),
+        !stored,
+    );
+
     // Convert to SSE events
+    let retrieval_trace = json!(retrieval.trace);
+    let is_fallback = fallback.is_some();
+    let sql_query = sql_result.map(|result| result.query);
+    // Save a streamed answer as it streams so an interrupted generation leaves its partial text behind
+    let mut partial_response = (!stored).then(|| PartialResponse::new(
+        app_state.background_jobs.clone(),
+        app_state.db.clone(),
+        tenant.organization_id,
+        conversation.id,
+    ));
+    // An unbuffered answer is cached once its last chunk arrives
+    let answer_cache = app_state.answer_cache.clone();
+    let mut cached_answer = cache_query.map(|query| (query, String::new(), search_results.clone()));
     let sse_stream = stream.map(move |chunk_result| {
         match chunk_result {
             Ok(chunk) => {
-                let event_data = json!({
+                stream_usage.push(&chunk.text);
+                if let Some(partial_response) = partial_response.as_mut() {
+                    if chunk.is_final {
+                        partial_response.complete(&chunk.text);
+                    } else {
+                        partial_response.push(&chunk.text);
+                    }
+                }
+                if chunk.is_final
+                    && let Some(record) = record_turn.take()
+                {
+                    record();
+                }
+                if let Some((_, answer, _)) = cached_answer.as_mut() {
+                    answer.push_str(&chunk.text);
+                }
+                if chunk.is_final
+                    && let Some((query, answer, sources)) = cached_answer.take()
+                {
+                    answer_cache.store(query, answer, sources);
+                }
+                let mut event_data = json!({
                     "text": chunk.text,
                     "is_final": chunk.is_final,
                     "session_id": session_id,
                     "chat_id": chat_id,
+                    "thread_id": thread_id,
                     "conversation_id": conversation.id
                 });
-                
+                if chunk.is_final {
+                    event_data["retrieval_trace"] = retrieval_trace.clone();
+                    event_data["fallback"] = json!(is_fallback);
+                    event_data["sql_query"] = json!(sql_query);
+                    event_data["search_query"] = json!(search_query);
+                    event_data["translated_to"] = json!(translate_to);
+                    event_data["escalated"] = json!(false);
+                    event_data["cache_hit"] = json!(false);
+                }
+
                 Ok(Event::default().data(event_data.to_string()))
             }
             Err(e) => {
                 tracing::error!("Streaming error: {}", e);
+                if let Some(partial_response) = partial_response.as_mut() {
+                    partial_response.fail();
+                }
+                cached_answer = None;
                 let error_data = json!({
                     "error": e.to_string(),
                     "is_final": true
@@ -460,53 +1638,336 @@ pub async fn chat_stream_handler(
         }
     });
 
-    Ok(Sse::new(sse_stream).keep_alive(KeepAlive::default()))
+    Ok(Sse::new(sse_stream).keep_alive(KeepAlive::default()).into_response())
 }
 
 // Get conversation history for a chat
+#[utoipa::path(
+    get,
+    path = "/api/chat/history",
+    tag = "chat",
+    params(
+        ("chat_id" = Uuid, Query, description = "Chat to load"),
+        ("thread_id" = Option<Uuid>, Query, description = "Only return turns from this thread"),
+        ("format" = Option<String>, Query, description = "\"json\" (default) or \"ndjson\", one turn per line"),
+    ),
+    responses(
+        (status = 200, description = "Conversations in the chat, oldest first, streamed as they are read", content(
+            (Value = "application/json"),
+            (String = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Missing or invalid chat_id, or unknown format"),
+        (status = 404, description = "A signed-in user asked for a chat that isn't theirs"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn get_chat_history_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
     Query(params): Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let chat_id_str = params.get("chat_id").ok_or(StatusCode::BAD_REQUEST)?;
     let chat_id = Uuid::parse_str(chat_id_str).map_err(|_| StatusCode::BAD_REQUEST)?;
 
+    let thread_id = params
+        .get("thread_id")
+        .map(|id| Uuid::parse_str(id))
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let format: ListingFormat = params.get("format").map_or("json", String::as_str).parse().map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
     tracing::info!("Getting chat history for chat: {}", chat_id);
 
-    match list_conversations_by_chat(&app_state.db, chat_id).await {
-        Ok(conversations) => {
-            let conversation_responses: Vec<Value> = conversations
-                .into_iter()
-                .map(|conv| {
-                    json!({
-                        "id": conv.id,
-                        "sequence_number": conv.sequence_number,
-                        "user_query": conv.user_query,
-                        "bot_response": conv.bot_response,
-                        "created_at": conv.created_at.to_rfc3339()
-                    })
-                })
-                .collect();
+    // Signed-in users only see their own chats
+    if tenant.user_id.is_some() {
+        match get_chat(&app_state.db, tenant.organization_id, chat_id, tenant.user_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                tracing::error!("Chat not found: {}", chat_id);
+                return Err(StatusCode::NOT_FOUND);
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to get chat: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    // Read a page of turns at a time as the response is written, rather than loading the whole chat
+    let db = app_state.db.clone();
+    let organization_id = tenant.organization_id;
+    let pages = futures_util::stream::try_unfold(Some(i32::MIN), move |after_sequence| {
+        let db = db.clone();
+        async move {
+            let Some(after_sequence) = after_sequence else {
+                return Ok(None);
+            };
+            let page =
+                list_conversations_page(&db, organization_id, chat_id, thread_id, after_sequence, HISTORY_PAGE_SIZE)
+                    .await?;
+            let next = match page.last() {
+                Some(last) if page.len() as i64 == HISTORY_PAGE_SIZE => Some(last.sequence_number),
+                _ => None,
+            };
+            Ok::<_, AppError>(Some((futures_util::stream::iter(page.into_iter().map(Ok::<_, AppError>)), next)))
+        }
+    });
+    let conversations = pages.try_flatten().map_ok(|conv| {
+        json!({
+            "id": conv.id,
+            "sequence_number": conv.sequence_number,
+            "thread_id": conv.thread_id,
+            "user_query": conv.user_query,
+            "bot_response": conv.bot_response,
+            "generation_status": conv.generation_status,
+            "author": conv.author,
+            "created_at": conv.created_at.to_rfc3339()
+        })
+    });
+
+    tracing::info!("✅ Streaming chat history for chat: {}", chat_id);
+    let mut fields = serde_json::Map::new();
+    fields.insert("chat_id".to_string(), json!(chat_id));
+    let listing = StreamedListing {
+        format,
+        message: "Chat history retrieved successfully",
+        fields,
+        items_key: "conversations",
+    };
+    Ok(listing.into_response(conversations))
+}
+
+// Soft delete a session along with its chats and conversations
+#[utoipa::path(
+    delete,
+    path = "/api/sessions/{id}",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Session id")),
+    responses(
+        (status = 200, description = "Session soft deleted", body = Value),
+        (status = 404, description = "Session not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_session_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting session: {}", session_id);
+
+    match delete_session(&app_state.db, tenant.organization_id, session_id, tenant.user_id).await {
+        Ok(true) => {
+            tracing::info!("✅ Session deleted: {}", session_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Session deleted successfully",
+                "data": { "session_id": session_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("Session not found: {}", session_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete session: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Soft delete a chat along with its conversations
+#[utoipa::path(
+    delete,
+    path = "/api/chats/{id}",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    responses(
+        (status = 200, description = "Chat soft deleted", body = Value),
+        (status = 404, description = "Chat not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_chat_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting chat: {}", chat_id);
 
-            tracing::info!("✅ Retrieved {} conversations", conversation_responses.len());
+    match delete_chat(&app_state.db, tenant.organization_id, chat_id, tenant.user_id).await {
+        Ok(true) => {
+            tracing::info!("✅ Chat deleted: {}", chat_id);
             Ok(Json(json!({
                 "success": true,
-                "message": "Chat history retrieved successfully",
+                "message": "Chat deleted successfully",
+                "data": { "chat_id": chat_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete chat: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Hand an escalated chat back to the bot once the person is done with it
+#[utoipa::path(
+    post,
+    path = "/api/chats/{id}/resume",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    responses(
+        (status = 200, description = "Bot replies resumed", body = Value),
+        (status = 404, description = "Chat not found, or a signed-in user's chat that isn't theirs"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn resume_chat_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Resuming bot replies for chat: {}", chat_id);
+
+    match clear_chat_escalation(&app_state.db, tenant.organization_id, chat_id, tenant.user_id).await {
+        Ok(Some(chat)) => {
+            tracing::info!("✅ Bot replies resumed for chat: {}", chat_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Bot replies resumed successfully",
+                "data": chat
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to resume chat: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Soft delete a single conversation turn
+#[utoipa::path(
+    delete,
+    path = "/api/conversations/{id}",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation id")),
+    responses(
+        (status = 200, description = "Conversation soft deleted", body = Value),
+        (status = 404, description = "Conversation not found, or a signed-in user's conversation that isn't theirs"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_conversation_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(conversation_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting conversation: {}", conversation_id);
+
+    match delete_conversation(&app_state.db, tenant.organization_id, conversation_id, tenant.user_id).await {
+        Ok(true) => {
+            tracing::info!("✅ Conversation deleted: {}", conversation_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Conversation deleted successfully",
+                "data": { "conversation_id": conversation_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("Conversation not found: {}", conversation_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete conversation: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Soft delete or purge a chatbot's conversations in a date range (admin only)
+#[utoipa::path(
+    post,
+    path = "/api/admin/chatbots/{id}/conversations/bulk-delete",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = BulkDeleteConversationsRequest,
+    responses(
+        (status = 200, description = "Number of conversations matched, or deleted unless dry_run", body = Value),
+        (status = 400, description = "`from` is not before `to`"),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn bulk_delete_conversations_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminKey,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<BulkDeleteConversationsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!(
+        "Bulk deleting conversations for chatbot {} from {} to {} (purge: {}, dry run: {})",
+        chatbot_id, payload.from, payload.to, payload.purge, payload.dry_run
+    );
+
+    if payload.from >= payload.to {
+        tracing::error!("Invalid range: from {} is not before to {}", payload.from, payload.to);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let result = if payload.dry_run {
+        // Purging also removes rows that are already soft deleted
+        count_conversations_in_range(&app_state.db, chatbot_id, payload.from, payload.to, payload.purge)
+            .await
+            .map(|count| count as u64)
+    } else if payload.purge {
+        purge_conversations_in_range(&app_state.db, chatbot_id, payload.from, payload.to).await
+    } else {
+        soft_delete_conversations_in_range(&app_state.db, chatbot_id, payload.from, payload.to).await
+    };
+
+    match result {
+        Ok(count) => {
+            tracing::info!("✅ Bulk delete matched {} conversations", count);
+            Ok(Json(json!({
+                "success": true,
+                "message": if payload.dry_run { "Conversations counted successfully" } else { "Conversations deleted successfully" },
                 "data": {
-                    "chat_id": chat_id,
-                    "conversations": conversation_responses,
-                    "count": conversation_responses.len()
+                    "chatbot_id": chatbot_id,
+                    "from": payload.from,
+                    "to": payload.to,
+                    "purge": payload.purge,
+                    "dry_run": payload.dry_run,
+                    "count": count
                 }
             })))
         }
         Err(e) => {
-            tracing::error!("❌ Failed to get chat history: {}", e);
+            tracing::error!("❌ Failed to bulk delete conversations: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
 // Test SSE endpoint
+#[utoipa::path(
+    get,
+    path = "/api/chat/test-sse",
+    tag = "chat",
+    responses((status = 200, description = "Fixed test event stream", content_type = "text/event-stream", body = String))
+)]
 pub async fn test_sse_handler() -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
     let stream = futures_util::stream::iter(vec![
         Ok(Event::default().data("Hello")),
@@ -519,6 +1980,12 @@ pub async fn test_sse_handler() -> Sse<impl Stream<Item = Result<Event, axum::Er
 }
 
 // Health check for chat service
+#[utoipa::path(
+    get,
+    path = "/api/chat/health",
+    tag = "health",
+    responses((status = 200, description = "Chat service is running", body = Value))
+)]
 pub async fn chat_health_handler() -> Json<Value> {
     Json(json!({
         "status": "ok",
@@ -536,4 +2003,9 @@ pub fn create_chat_router() -> Router<AppState> {
         .route("/chat/session", post(create_session_handler))
         .route("/chat/history", get(get_chat_history_handler))
         .route("/chat/health", get(chat_health_handler))
+        .route("/sessions/{id}", delete(delete_session_handler))
+        .route("/chats/{id}", delete(delete_chat_handler))
+        .route("/chats/{id}/resume", post(resume_chat_handler))
+        .route("/conversations/{id}", delete(delete_conversation_handler))
+        .route("/admin/chatbots/{id}/conversations/bulk-delete", post(bulk_delete_conversations_handler))
 }
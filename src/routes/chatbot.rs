@@ -1,32 +1,59 @@
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use serde_json::{json, Value};
+use uuid::Uuid;
 
-use crate::db::models::{CreateChatBotRequest, ChatBotResponse};
-use crate::db::queries::{create_chat_bot, list_chat_bots};
+use crate::db::models::{
+    CreateChatBotRequest, ChatBotResponse, NoContextBehavior, SelectPromptTemplateRequest, UpdateGuestAccessRequest,
+    UpdateHandoffWebhookRequest, UpdateHealthWebhookRequest, UpdateIngestWebhookRequest, UpdatePromptTemplateRequest,
+    RetrievalSettings, UpdateRetrievalSettingsRequest, UpdateScriptsRequest,
+};
+use crate::db::queries::{
+    clear_secondary_resync, create_chat_bot, delete_chat_bot, get_retained_chat_bot, list_chat_bots,
+    list_secondary_resyncs, set_chat_bot_status,
+    update_chat_bot_guest_access, update_chat_bot_handoff_webhook, update_chat_bot_health_webhook,
+    update_chat_bot_ingest_webhook, update_chat_bot_prompt_template, update_chat_bot_prompt_template_id,
+    update_chat_bot_retrieval_settings, update_chat_bot_scripts,
+};
+use crate::middleware::auth::{AdminKey, AdminViewer, Tenant};
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::vector::{chatbot_index_name, VectorBackend, VectorStore};
+use crate::services::index_lifecycle::find_orphaned_indices;
+use crate::services::embedding::validate_mmr_lambda;
+use crate::services::ingest_webhook::validate_webhook_url;
+use crate::services::sharding::shard_indices;
+use crate::services::prompt_template::validate_template;
+use crate::services::query_expansion::validate_paraphrases;
+use crate::services::scripting::validate_script;
 use crate::utils::config::AppState;
 
 // Create a new chatbot
+#[utoipa::path(
+    post,
+    path = "/api/chatbots",
+    tag = "chatbots",
+    request_body = CreateChatBotRequest,
+    responses(
+        (status = 200, description = "Chatbot created", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn create_chatbot_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
     Json(payload): Json<CreateChatBotRequest>,
 ) -> Result<Json<Value>, StatusCode> {
     tracing::info!("Creating chatbot with name: {}", payload.name);
 
-    match create_chat_bot(&app_state.db, payload.name).await {
+    match create_chat_bot(&app_state.db, tenant.organization_id, payload.name, tenant.user_id).await {
         Ok(chatbot) => {
-            let response = ChatBotResponse {
-                id: chatbot.id,
-                name: chatbot.name,
-                created_at: chatbot.created_at,
-                updated_at: chatbot.updated_at,
-                status: chatbot.status,
-            };
+            let response = ChatBotResponse::from(chatbot);
 
             tracing::info!("✅ Chatbot created successfully: {}", response.id);
             Ok(Json(json!({
@@ -43,22 +70,27 @@ pub async fn create_chatbot_handler(
 }
 
 // Get all chatbots
+#[utoipa::path(
+    get,
+    path = "/api/chatbots",
+    tag = "chatbots",
+    responses(
+        (status = 200, description = "Chatbots in the caller's organization", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn get_chatbots_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
 ) -> Result<Json<Value>, StatusCode> {
     tracing::info!("Fetching all chatbots");
 
-    match list_chat_bots(&app_state.db).await {
+    match list_chat_bots(&app_state.db, tenant.organization_id).await {
         Ok(chatbots) => {
             let responses: Vec<ChatBotResponse> = chatbots
                 .into_iter()
-                .map(|chatbot| ChatBotResponse {
-                    id: chatbot.id,
-                    name: chatbot.name,
-                    created_at: chatbot.created_at,
-                    updated_at: chatbot.updated_at,
-                    status: chatbot.status,
-                })
+                .map(ChatBotResponse::from)
                 .collect();
 
             tracing::info!("✅ Retrieved {} chatbots", responses.len());
@@ -76,9 +108,941 @@ pub async fn get_chatbots_handler(
     }
 }
 
+// Set a chatbot's minimum retrieval score and what to do when nothing passes it
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/retrieval-settings",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateRetrievalSettingsRequest,
+    responses(
+        (status = 200, description = "Retrieval settings updated", body = Value),
+        (status = 400, description = "A setting is out of range or the no-context settings conflict"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_retrieval_settings_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateRetrievalSettingsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating retrieval settings for chatbot: {}", chatbot_id);
+
+    if payload.min_score.is_some_and(|score| !score.is_finite()) {
+        tracing::error!("Invalid min_score: {:?}", payload.min_score);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // strict_mode is shorthand for refusing
+    let no_context = payload.no_context.unwrap_or(if payload.strict_mode {
+        NoContextBehavior::Refuse
+    } else {
+        NoContextBehavior::GeneralKnowledge
+    });
+    if payload.strict_mode && no_context != NoContextBehavior::Refuse {
+        tracing::error!("strict_mode conflicts with no_context {}", no_context.as_str());
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let no_context_template = payload.no_context_template.filter(|template| !template.trim().is_empty());
+    match &no_context_template {
+        Some(template) => {
+            if let Err(e) = validate_template(template) {
+                tracing::error!("{}", e);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+        None if no_context == NoContextBehavior::FallbackTemplate => {
+            tracing::error!("no_context fallback_template needs a no_context_template");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        None => {}
+    }
+    let query_paraphrases = payload.query_paraphrases.unwrap_or(0);
+    if let Err(e) = validate_paraphrases(query_paraphrases) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(Err(e)) = payload.mmr_lambda.map(validate_mmr_lambda) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let settings = RetrievalSettings {
+        min_score: payload.min_score,
+        no_context,
+        fallback_message: payload.fallback_message,
+        no_context_template,
+        query_paraphrases,
+        mmr_lambda: payload.mmr_lambda,
+    };
+    let chatbot = match update_chat_bot_retrieval_settings(&app_state.db, tenant.organization_id, chatbot_id, settings)
+        .await
+    {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update retrieval settings: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Retrieval settings updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Retrieval settings updated successfully",
+        "data": response
+    })))
+}
+
+// Set the Handlebars prompt template used to answer this chatbot's chat requests
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/prompt-template",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdatePromptTemplateRequest,
+    responses(
+        (status = 200, description = "Prompt template updated", body = Value),
+        (status = 400, description = "Template does not compile"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdatePromptTemplateRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating prompt template for chatbot: {}", chatbot_id);
+
+    let template = payload.template.filter(|template| !template.trim().is_empty());
+    if let Some(template) = &template
+        && let Err(e) = validate_template(template)
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let chatbot = match update_chat_bot_prompt_template(&app_state.db, tenant.organization_id, chatbot_id, template).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update prompt template: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Prompt template updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt template updated successfully",
+        "data": response
+    })))
+}
+
+// Select one of the organization's shared prompt templates for this chatbot
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/prompt-template-selection",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = SelectPromptTemplateRequest,
+    responses(
+        (status = 200, description = "Prompt template selected", body = Value),
+        (status = 404, description = "Chatbot or prompt template not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn select_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<SelectPromptTemplateRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Selecting prompt template {:?} for chatbot: {}", payload.template_id, chatbot_id);
+
+    let chatbot = match update_chat_bot_prompt_template_id(&app_state.db, tenant.organization_id, chatbot_id, payload.template_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot {} or prompt template {:?} not found", chatbot_id, payload.template_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to select prompt template: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Prompt template selected for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt template selected successfully",
+        "data": response
+    })))
+}
+
+// Register or remove the webhook that transforms extracted chunks before they are embedded
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/ingest-webhook",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateIngestWebhookRequest,
+    responses(
+        (status = 200, description = "Ingest webhook updated", body = Value),
+        (status = 400, description = "URL is not an absolute http(s) URL"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_ingest_webhook_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateIngestWebhookRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating ingest webhook for chatbot: {}", chatbot_id);
+
+    let url = payload.url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &url
+        && let Err(e) = validate_webhook_url(url).await
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // A secret without a URL has nothing to sign
+    let secret = url.as_ref().and(payload.secret.filter(|secret| !secret.is_empty()));
+
+    let chatbot = match update_chat_bot_ingest_webhook(&app_state.db, tenant.organization_id, chatbot_id, url, secret).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update ingest webhook: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Ingest webhook updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Ingest webhook updated successfully",
+        "data": response
+    })))
+}
+
+// Register or remove the webhook that escalated chats are handed off to, with their transcript
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/handoff",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateHandoffWebhookRequest,
+    responses(
+        (status = 200, description = "Handoff webhook updated", body = Value),
+        (status = 400, description = "URL is not an absolute http(s) URL"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_handoff_webhook_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateHandoffWebhookRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating handoff webhook for chatbot: {}", chatbot_id);
+
+    let url = payload.url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &url
+        && let Err(e) = validate_webhook_url(url).await
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let secret = url.as_ref().and(payload.secret.filter(|secret| !secret.is_empty()));
+
+    let chatbot = match update_chat_bot_handoff_webhook(&app_state.db, tenant.organization_id, chatbot_id, url, secret).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update handoff webhook: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Handoff webhook updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Handoff webhook updated successfully",
+        "data": response
+    })))
+}
+
+// Let anonymous widget visitors open guest sessions with the chatbot, or stop them. Tokens already
+// issued stop working as soon as guests are turned off
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/guest-access",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateGuestAccessRequest,
+    responses(
+        (status = 200, description = "Guest access updated", body = Value),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_guest_access_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateGuestAccessRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Setting guest access of chatbot {} to {}", chatbot_id, payload.enabled);
+
+    let chatbot = match update_chat_bot_guest_access(&app_state.db, tenant.organization_id, chatbot_id, payload.enabled)
+        .await
+    {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update guest access: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Guest access updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Guest access updated successfully",
+        "data": response
+    })))
+}
+
+// Register or remove the webhook alerted when the chatbot's health score drops after a change
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/health-webhook",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateHealthWebhookRequest,
+    responses(
+        (status = 200, description = "Health webhook updated", body = Value),
+        (status = 400, description = "URL is not an absolute http(s) URL"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_health_webhook_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateHealthWebhookRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating health webhook for chatbot: {}", chatbot_id);
+
+    let url = payload.url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &url
+        && let Err(e) = validate_webhook_url(url).await
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let secret = url.as_ref().and(payload.secret.filter(|secret| !secret.is_empty()));
+
+    let chatbot = match update_chat_bot_health_webhook(&app_state.db, tenant.organization_id, chatbot_id, url, secret).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update health webhook: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Health webhook updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Health webhook updated successfully",
+        "data": response
+    })))
+}
+
+// Set the Rhai hooks that rewrite a chatbot's retrieval query and answers
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/scripts",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdateScriptsRequest,
+    responses(
+        (status = 200, description = "Scripts updated", body = Value),
+        (status = 400, description = "A script does not compile"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_scripts_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdateScriptsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating scripts for chatbot: {}", chatbot_id);
+
+    let query_script = payload.query_script.filter(|script| !script.trim().is_empty());
+    let answer_script = payload.answer_script.filter(|script| !script.trim().is_empty());
+    for script in query_script.iter().chain(answer_script.iter()) {
+        if let Err(e) = validate_script(script) {
+            tracing::error!("{}", e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let chatbot = match update_chat_bot_scripts(&app_state.db, tenant.organization_id, chatbot_id, query_script, answer_script).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update scripts: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Scripts updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Scripts updated successfully",
+        "data": response
+    })))
+}
+
+// Soft delete a chatbot and drop its Elasticsearch indices
+#[utoipa::path(
+    delete,
+    path = "/api/chatbots/{id}",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Chatbot deleted; index_deleted is false if index cleanup is left to reconciliation", body = Value),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_chatbot_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting chatbot: {}", chatbot_id);
+
+    let chatbot = match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match delete_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    // Indices left behind here are picked up by the reconciliation task
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
+    let index_deleted = match app_state.vector_store.delete_collections(&index_names).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!("❌ Failed to delete indices for chatbot {}: {}", chatbot_id, e);
+            false
+        }
+    };
+
+    // The documents rows went with the chatbot; their originals are removed here
+    if let Some(store) = app_state.document_store.as_ref()
+        && let Err(e) = store.delete_chatbot(tenant.organization_id, chatbot_id).await
+    {
+        tracing::warn!("⚠️ Failed to delete stored documents for chatbot {}: {}", chatbot_id, e);
+    }
+
+    tracing::info!("✅ Chatbot deleted: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Chatbot deleted successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "index_deleted": index_deleted
+        }
+    })))
+}
+
+// Archive a chatbot: close its indices and make it read-only while keeping its data
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/archive",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Chatbot archived", body = Value),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Chatbot is already archived"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn archive_chatbot_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Archiving chatbot: {}", chatbot_id);
+
+    // Flip the status first so no new chats or uploads hit the index while it closes
+    let chatbot = match set_chat_bot_status(&app_state.db, tenant.organization_id, chatbot_id, "active", "archived").await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => return Err(lifecycle_conflict(&app_state, tenant.organization_id, chatbot_id).await),
+        Err(e) => {
+            tracing::error!("❌ Failed to archive chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
+    if let Err(e) = app_state.vector_store.close_collections(&index_names).await {
+        tracing::error!("❌ Failed to close indices for chatbot {}: {}", chatbot_id, e);
+        // Leave the chatbot usable rather than archived with open indices
+        if let Err(e) = set_chat_bot_status(&app_state.db, tenant.organization_id, chatbot_id, "archived", "active").await {
+            tracing::error!("❌ Failed to restore chatbot {} after archive failure: {}", chatbot_id, e);
+        }
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    tracing::info!("✅ Chatbot archived: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Chatbot archived successfully",
+        "data": ChatBotResponse::from(chatbot)
+    })))
+}
+
+// Restore an archived chatbot and reopen its indices
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/unarchive",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Chatbot restored", body = Value),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Chatbot is not archived"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn unarchive_chatbot_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Restoring chatbot: {}", chatbot_id);
+
+    let chatbot = match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) if chatbot.status == "archived" => chatbot,
+        Ok(Some(_)) => {
+            tracing::error!("Chatbot is not archived: {}", chatbot_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Reopen before marking active so the first chat after restore can search
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
+    if let Err(e) = app_state.vector_store.open_collections(&index_names).await {
+        tracing::error!("❌ Failed to open indices for chatbot {}: {}", chatbot_id, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let chatbot = match set_chat_bot_status(&app_state.db, tenant.organization_id, chatbot_id, "archived", "active").await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => return Err(lifecycle_conflict(&app_state, tenant.organization_id, chatbot_id).await),
+        Err(e) => {
+            tracing::error!("❌ Failed to restore chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    tracing::info!("✅ Chatbot restored: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Chatbot restored successfully",
+        "data": ChatBotResponse::from(chatbot)
+    })))
+}
+
+// Status for a lifecycle transition that matched no row: 404 if the chatbot is gone, 409 if it's in the wrong state
+async fn lifecycle_conflict(app_state: &AppState, organization_id: Uuid, chatbot_id: Uuid) -> StatusCode {
+    match get_retained_chat_bot(&app_state.db, organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => {
+            tracing::error!("Chatbot {} is {}", chatbot_id, chatbot.status);
+            StatusCode::CONFLICT
+        }
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            StatusCode::NOT_FOUND
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+// Document count, storage size, mapping version and last indexed time of a chatbot's indices
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/index-stats",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Index statistics", body = Value),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Chatbot is archived and its indices are closed"),
+        (status = 501, description = "Vector backend is not Elasticsearch"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_index_stats_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let VectorBackend::Elasticsearch(store) = app_state.vector_store.as_ref() else {
+        tracing::error!("Index stats requested but the vector backend is not Elasticsearch");
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    };
+
+    let chatbot = match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) if chatbot.status == "archived" => {
+            tracing::error!("Chatbot is archived: {}", chatbot_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
+    let indices = store.index_stats(&index_names).await.map_err(|e| {
+        tracing::error!("❌ Failed to get index stats for chatbot {}: {}", chatbot_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // The oldest mapping across shards is the one that decides whether a reindex is due
+    let mapping_version = indices.iter().map(|index| index.mapping_version).min().flatten();
+    Ok(Json(json!({
+        "success": true,
+        "message": "Index stats retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "document_count": indices.iter().map(|index| index.document_count).sum::<u64>(),
+            "size_in_bytes": indices.iter().map(|index| index.size_in_bytes).sum::<u64>(),
+            "mapping_version": mapping_version,
+            "last_indexed_at": indices.iter().filter_map(|index| index.last_indexed_at.as_ref()).max(),
+            "indices": indices
+        }
+    })))
+}
+
+// List chatbot indices with no active or archived chatbot (admins and viewers)
+#[utoipa::path(
+    get,
+    path = "/api/admin/orphaned-indices",
+    tag = "chatbots",
+    responses(
+        (status = 200, description = "Orphaned chatbot indices", body = Value),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn get_orphaned_indices_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminViewer,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Finding orphaned chatbot indices");
+
+    match find_orphaned_indices(&app_state.db, &app_state.vector_store).await {
+        Ok(orphans) => {
+            tracing::info!("✅ Found {} orphaned indices", orphans.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Orphaned indices retrieved successfully",
+                "data": orphans,
+                "count": orphans.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to find orphaned indices: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List collections the secondary Elasticsearch missed writes to (admins and viewers)
+#[utoipa::path(
+    get,
+    path = "/api/admin/secondary-resyncs",
+    tag = "chatbots",
+    responses(
+        (status = 200, description = "Collections to resync on the secondary, oldest first", body = Value),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn get_secondary_resyncs_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminViewer,
+) -> Result<Json<Value>, StatusCode> {
+    match list_secondary_resyncs(&app_state.db).await {
+        Ok(resyncs) => Ok(Json(json!({
+            "success": true,
+            "message": "Secondary resyncs retrieved successfully",
+            "count": resyncs.len(),
+            "data": resyncs
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list secondary resyncs: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Clear a collection's resync mark once the secondary has been restored (admin only)
+#[utoipa::path(
+    delete,
+    path = "/api/admin/secondary-resyncs/{collection}",
+    tag = "chatbots",
+    params(("collection" = String, Path, description = "Collection or alias name")),
+    responses(
+        (status = 200, description = "Resync mark cleared", body = Value),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+        (status = 404, description = "Collection isn't marked for a resync"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn clear_secondary_resync_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminKey,
+    Path(collection): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    match clear_secondary_resync(&app_state.db, &collection).await {
+        Ok(true) => {
+            tracing::info!("✅ Cleared secondary resync of {}", collection);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Secondary resync cleared successfully",
+                "data": { "collection": collection }
+            })))
+        }
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("❌ Failed to clear secondary resync: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 // Create the router for chatbot routes
 pub fn create_chatbot_router() -> Router<AppState> {
     Router::new()
         .route("/chatbots", post(create_chatbot_handler))
         .route("/chatbots", get(get_chatbots_handler))
+        .route("/chatbots/{id}", delete(delete_chatbot_handler))
+        .route("/chatbots/{id}/archive", post(archive_chatbot_handler))
+        .route("/chatbots/{id}/unarchive", post(unarchive_chatbot_handler))
+        .route("/chatbots/{id}/index-stats", get(get_index_stats_handler))
+        .route("/admin/orphaned-indices", get(get_orphaned_indices_handler))
+        .route("/admin/secondary-resyncs", get(get_secondary_resyncs_handler))
+        .route("/admin/secondary-resyncs/{collection}", delete(clear_secondary_resync_handler))
+        .route("/chatbots/{id}/retrieval-settings", put(update_retrieval_settings_handler))
+        .route("/chatbots/{id}/prompt-template", put(update_prompt_template_handler))
+        .route("/chatbots/{id}/prompt-template-selection", put(select_prompt_template_handler))
+        .route("/chatbots/{id}/ingest-webhook", put(update_ingest_webhook_handler))
+        .route("/chatbots/{id}/handoff", put(update_handoff_webhook_handler))
+        .route("/chatbots/{id}/guest-access", put(update_guest_access_handler))
+        .route("/chatbots/{id}/health-webhook", put(update_health_webhook_handler))
+        .route("/chatbots/{id}/scripts", put(update_scripts_handler))
 }
@@ -0,0 +1,98 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{get_chatbot_last_change, get_health_counts, get_retained_chat_bot, list_health_snapshots};
+use crate::middleware::auth::Tenant;
+use crate::services::chatbot_health::{health_window_days, HealthReport};
+use crate::utils::config::AppState;
+
+const MAX_DAYS: i32 = 90;
+const HISTORY_LIMIT: i64 = 48;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HealthQuery {
+    /// Days of turns to score, at most 90; defaults to `HEALTH_WINDOW_DAYS`
+    pub days: Option<i32>,
+}
+
+// The chatbot's current health score with its parts, last change and recent recorded scores
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/health",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), HealthQuery),
+    responses(
+        (status = 200, description = "Health score and history", body = Value),
+        (status = 400, description = "Invalid days"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_chatbot_health_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<HealthQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let days = params.days.unwrap_or_else(health_window_days);
+    if !(1..=MAX_DAYS).contains(&days) {
+        tracing::error!("Health window must be between 1 and {} days", MAX_DAYS);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let since = Utc::now() - chrono::Duration::days(days as i64);
+    let counts = get_health_counts(&app_state.db, chatbot_id, since).await.map_err(|e| {
+        tracing::error!("❌ Failed to count health signals: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let changed_at = get_chatbot_last_change(&app_state.db, chatbot_id).await.map_err(|e| {
+        tracing::error!("❌ Failed to get last chatbot change: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let history = list_health_snapshots(&app_state.db, tenant.organization_id, chatbot_id, HISTORY_LIMIT)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to list health snapshots: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Chatbot health retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "days": days,
+            "health": HealthReport::from_counts(&counts),
+            "last_changed_at": changed_at,
+            "history": history
+        }
+    })))
+}
+
+// Create the router for chatbot health
+pub fn create_chatbot_health_router() -> Router<AppState> {
+    Router::new().route("/chatbots/{id}/health", get(get_chatbot_health_handler))
+}
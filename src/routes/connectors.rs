@@ -0,0 +1,332 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::models::CreateSourceConnectorRequest;
+use crate::db::queries::{
+    create_source_connector, delete_document_usage, delete_source_connector, get_source_connector,
+    list_source_connector_documents, list_source_connectors, remove_cold_document,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::connector::{
+    document_path, run_sync, ConnectorKind, ConnectorSource, DEFAULT_INTERVAL_MINUTES, MIN_INTERVAL_MINUTES,
+};
+use crate::services::notion::exchange_oauth_code;
+use crate::services::sharding::shard_indices;
+use crate::services::vector::{chatbot_index_name, VectorStore};
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConnectorSyncQuery {
+    /// List every document, removing ones deleted at the source, instead of only fetching changes
+    pub full: Option<bool>,
+}
+
+// Connect a Notion workspace or Confluence site to a chatbot. Its first sync runs within a minute
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/connectors",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = CreateSourceConnectorRequest,
+    responses(
+        (status = 200, description = "Connector created", body = Value),
+        (status = 400, description = "Unknown kind, invalid name, config or interval, or no token"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "The chatbot already has a connector with this name"),
+        (status = 502, description = "The OAuth code couldn't be exchanged for a token"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(mut payload): Json<CreateSourceConnectorRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating connector for chatbot: {}", chatbot_id);
+
+    payload.kind = payload.kind.trim().to_lowercase();
+    payload.name = payload.name.trim().to_string();
+    let Some(kind) = ConnectorKind::parse(&payload.kind) else {
+        tracing::error!("Unknown connector kind: {}", payload.kind);
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if payload.name.is_empty() || payload.name.len() > 100 || payload.name.contains('/') {
+        tracing::error!("Connector name must be 1-100 characters without '/'");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let interval_minutes = payload.interval_minutes.unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    if interval_minutes < MIN_INTERVAL_MINUTES {
+        tracing::error!("Connector interval must be at least {} minutes", MIN_INTERVAL_MINUTES);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let config = payload.config.clone().unwrap_or_else(|| json!({}));
+    // Check the settings before spending the OAuth code, which can only be used once
+    let source = ConnectorSource::new(kind, "unchecked", &config).map_err(|e| {
+        tracing::error!("Invalid connector config: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    if let Err(e) = source.check_hosts().await {
+        tracing::error!("Invalid connector config: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let access_token = match (payload.access_token.as_deref().map(str::trim), payload.oauth_code.as_deref()) {
+        (Some(token), _) if !token.is_empty() => token.to_string(),
+        (_, Some(code)) => match kind {
+            ConnectorKind::Notion => exchange_oauth_code(code, payload.redirect_uri.as_deref()).await.map_err(|e| {
+                tracing::error!("❌ Failed to exchange Notion OAuth code: {}", e);
+                StatusCode::BAD_GATEWAY
+            })?,
+            ConnectorKind::Confluence => {
+                tracing::error!("Confluence connectors need an API token or personal access token");
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        },
+        _ => {
+            tracing::error!("Connector needs an access_token or an oauth_code");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    match create_source_connector(
+        &app_state.db,
+        tenant.organization_id,
+        chatbot_id,
+        &payload,
+        &access_token,
+        interval_minutes,
+    )
+    .await
+    {
+        Ok(connector) => {
+            tracing::info!("✅ {} connector created: {}", connector.kind, connector.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Connector created successfully",
+                "data": connector
+            })))
+        }
+        Err(AppError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            tracing::error!("Connector '{}' already exists for chatbot {}", payload.name, chatbot_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create connector: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List a chatbot's connectors with their last sync, without their tokens
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/connectors",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's connectors", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_connectors_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match list_source_connectors(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(connectors) => Ok(Json(json!({
+            "success": true,
+            "message": "Connectors retrieved successfully",
+            "data": connectors,
+            "count": connectors.len()
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list connectors: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Sync a connector now instead of waiting for its next scheduled run
+#[utoipa::path(
+    post,
+    path = "/api/connectors/{id}/sync",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Connector id"), ConnectorSyncQuery),
+    responses(
+        (status = 200, description = "Connector synced", body = Value),
+        (status = 404, description = "Connector not found"),
+        (status = 502, description = "The source or the indexing failed; the error is kept on the connector"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn sync_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(connector_id): Path<Uuid>,
+    Query(params): Query<ConnectorSyncQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Syncing connector: {}", connector_id);
+
+    let connector = match get_source_connector(&app_state.db, tenant.organization_id, connector_id).await {
+        Ok(Some(connector)) => connector,
+        Ok(None) => {
+            tracing::error!("Connector not found: {}", connector_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let sync = run_sync(
+        &app_state.db,
+        &app_state.vector_store,
+        app_state.embedding_cache.clone(),
+        &app_state.chatbot_cache,
+        &connector,
+        params.full.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to sync connector {}: {}", connector.name, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Connector synced successfully",
+        "data": {
+            "connector_id": connector.id,
+            "chatbot_id": connector.chatbot_id,
+            "full": sync.full,
+            "updated": sync.updated,
+            "unchanged": sync.unchanged,
+            "removed": sync.removed,
+            "embedding_count": sync.embedding_count
+        }
+    })))
+}
+
+// Delete a connector and the chunks of every document it indexed
+#[utoipa::path(
+    delete,
+    path = "/api/connectors/{id}",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Connector id")),
+    responses(
+        (status = 200, description = "Connector and its chunks deleted", body = Value),
+        (status = 404, description = "Connector not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(connector_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting connector: {}", connector_id);
+
+    let connector = match get_source_connector(&app_state.db, tenant.organization_id, connector_id).await {
+        Ok(Some(connector)) => connector,
+        Ok(None) => {
+            tracing::error!("Connector not found: {}", connector_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let Some(kind) = ConnectorKind::parse(&connector.kind) else {
+        tracing::error!("❌ Unknown connector kind: {}", connector.kind);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let documents = list_source_connector_documents(&app_state.db, connector.id).await.map_err(|e| {
+        tracing::error!("❌ Failed to list connector documents: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Shards are looked up fresh; the chatbot may have grown since the documents were indexed
+    let shard_count = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, connector.chatbot_id).await {
+        Ok(chatbot) => chatbot.map(|chatbot| chatbot.shard_count).unwrap_or(1),
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let index_names = shard_indices(&chatbot_index_name(tenant.organization_id, connector.chatbot_id), shard_count);
+    for document in &documents {
+        let file_path = document_path(kind, &connector.name, &document.document_key);
+        if let Err(e) = app_state.vector_store.delete_document_chunks(&index_names, &file_path).await {
+            tracing::error!("❌ Failed to delete chunks of {}: {}", file_path, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        if let Err(e) = remove_cold_document(&app_state.db, connector.chatbot_id, &file_path).await {
+            tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", file_path, e);
+        }
+        if let Err(e) = delete_document_usage(&app_state.db, connector.chatbot_id, &file_path).await {
+            tracing::warn!("⚠️ Failed to delete document usage of {}: {}", file_path, e);
+        }
+    }
+
+    if let Err(e) = delete_source_connector(&app_state.db, tenant.organization_id, connector.id).await {
+        tracing::error!("❌ Failed to delete connector: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: connector.chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    tracing::info!("✅ Connector deleted: {} ({} documents)", connector.id, documents.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Connector deleted successfully",
+        "data": {
+            "connector_id": connector.id,
+            "documents_removed": documents.len()
+        }
+    })))
+}
+
+// Create the router for source connectors
+pub fn create_connector_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/connectors",
+            post(create_connector_handler).get(list_connectors_handler),
+        )
+        .route("/connectors/{id}", delete(delete_connector_handler))
+        .route("/connectors/{id}/sync", post(sync_connector_handler))
+}
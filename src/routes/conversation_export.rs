@@ -0,0 +1,185 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{get_chat, list_conversation_exports};
+use crate::middleware::auth::Tenant;
+use crate::services::conversation_export::{
+    render_footer, render_header, render_transcript, render_turn, ExportFormat,
+};
+use crate::services::document_store::export_key;
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// "json" (default), "md", "csv" or "pdf"
+    pub format: Option<String>,
+}
+
+// Download a chat's full transcript with citations and timestamps
+#[utoipa::path(
+    get,
+    path = "/api/chats/{id}/export",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Chat id"), ExportQuery),
+    responses(
+        (status = 200, description = "Transcript as an attachment", content(
+            (String = "application/json"),
+            (String = "text/markdown"),
+            (String = "text/csv"),
+            (Vec<u8> = "application/pdf"),
+        )),
+        (status = 400, description = "Unknown format"),
+        (status = 404, description = "Chat not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn export_chat_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let format: ExportFormat = params.format.as_deref().unwrap_or("json").parse().map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    tracing::info!("Exporting chat {} as {:?}", chat_id, format);
+
+    let chat = match get_chat(&app_state.db, tenant.organization_id, chat_id, tenant.user_id).await {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get chat: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let turns = list_conversation_exports(&app_state.db, tenant.organization_id, chat_id).await.map_err(|e| {
+        tracing::error!("❌ Failed to get conversations for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ Exporting {} conversations from chat {}", turns.len(), chat_id);
+
+    let exported_at = chrono::Utc::now();
+    let body = if format.is_streamed() {
+        // Render one turn at a time as the body is sent rather than building the whole transcript
+        let preamble = render_header(format, &chat, exported_at);
+        Body::from_stream(stream::iter(
+            std::iter::once(preamble)
+                .chain(turns.into_iter().enumerate().map(move |(position, turn)| render_turn(format, position, &turn)))
+                .chain(std::iter::once_with(move || render_footer(format)))
+                .map(Ok::<_, Infallible>),
+        ))
+    } else {
+        Body::from(render_transcript(format, &chat, &turns, exported_at))
+    };
+
+    let disposition = format!("attachment; filename=\"chat-{}.{}\"", chat_id, format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// Render a chat's transcript into the object store and return a presigned link to it, for
+// transcripts too large to download through the API
+#[utoipa::path(
+    post,
+    path = "/api/chats/{id}/export",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Chat id"), ExportQuery),
+    responses(
+        (status = 200, description = "Presigned download URL of the stored transcript", body = Value),
+        (status = 400, description = "Unknown format"),
+        (status = 404, description = "Chat not found"),
+        (status = 501, description = "The document store can't hand out download URLs"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn store_chat_export_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+    Query(params): Query<ExportQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let format: ExportFormat = params.format.as_deref().unwrap_or("json").parse().map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let Some(store) = app_state.document_store.as_ref().filter(|store| store.can_presign()) else {
+        tracing::error!("Stored export requested but the document store can't sign URLs");
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    };
+
+    let chat = match get_chat(&app_state.db, tenant.organization_id, chat_id, tenant.user_id).await {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get chat: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let turns = list_conversation_exports(&app_state.db, tenant.organization_id, chat_id).await.map_err(|e| {
+        tracing::error!("❌ Failed to get conversations for export: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let exported_at = chrono::Utc::now();
+    let transcript = render_transcript(format, &chat, &turns, exported_at);
+
+    let key = export_key(tenant.organization_id, chat_id, format.extension(), exported_at);
+    let size_bytes = transcript.len();
+    store.put(&key, transcript).await.map_err(|e| {
+        tracing::error!("❌ Failed to store export of chat {}: {}", chat_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let url = store.presigned_url(&key).await.map_err(|e| {
+        tracing::error!("❌ Failed to sign export URL: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ Stored export of {} conversations from chat {} at {}", turns.len(), chat_id, key);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Transcript exported successfully",
+        "data": {
+            "chat_id": chat_id,
+            "format": format.extension(),
+            "size_bytes": size_bytes,
+            "download_url": url,
+            "expires_in": store.url_expiry().as_secs()
+        }
+    })))
+}
+
+// Create the router for transcript exports
+pub fn create_conversation_export_router() -> Router<AppState> {
+    Router::new().route("/chats/{id}/export", get(export_chat_handler).post(store_chat_export_handler))
+}
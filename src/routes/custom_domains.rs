@@ -0,0 +1,250 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use serde_json::{json, Value};
+
+use crate::db::models::{CustomDomainRequest, UpdateCustomDomainRequest, WidgetBranding};
+use crate::db::queries::{create_custom_domain, delete_custom_domain, list_custom_domains, update_custom_domain};
+use crate::errors::AppError;
+use crate::middleware::auth::Tenant;
+use crate::middleware::custom_domain::DomainTenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::custom_domain::{normalize_hostname, validate_branding};
+use crate::utils::config::AppState;
+
+fn check_branding(branding: Option<&WidgetBranding>) -> Result<(), StatusCode> {
+    if let Some(branding) = branding
+        && let Err(e) = validate_branding(branding)
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+fn parse_hostname(hostname: &str) -> Result<String, StatusCode> {
+    normalize_hostname(hostname).map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })
+}
+
+// Drop the cached lookup for the hostname on every replica
+async fn publish_change(app_state: &AppState, hostname: &str) {
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::CustomDomainChanged { hostname: hostname.to_string() },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish custom domain change: {}", e);
+    }
+}
+
+// Map a hostname to the organization, optionally pinned to one chatbot, with widget branding
+#[utoipa::path(
+    post,
+    path = "/api/custom-domains",
+    tag = "custom-domains",
+    request_body = CustomDomainRequest,
+    responses(
+        (status = 200, description = "Custom domain created", body = Value),
+        (status = 400, description = "Invalid hostname or branding"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Hostname already mapped"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_custom_domain_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<CustomDomainRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating custom domain '{}'", payload.hostname);
+
+    let hostname = parse_hostname(&payload.hostname)?;
+    check_branding(payload.branding.as_ref())?;
+
+    let domain = match create_custom_domain(&app_state.db, tenant.organization_id, &hostname, payload.chatbot_id, payload.branding).await {
+        Ok(Some(domain)) => domain,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {:?}", payload.chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(AppError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            tracing::error!("Hostname already mapped: {}", hostname);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create custom domain: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Replicas may have cached that this hostname has no domain
+    publish_change(&app_state, &hostname).await;
+
+    tracing::info!("✅ Custom domain created: {}", hostname);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Custom domain created successfully",
+        "data": domain
+    })))
+}
+
+// List the organization's custom domains
+#[utoipa::path(
+    get,
+    path = "/api/custom-domains",
+    tag = "custom-domains",
+    responses(
+        (status = 200, description = "Custom domains, by hostname", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_custom_domains_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<Value>, StatusCode> {
+    match list_custom_domains(&app_state.db, tenant.organization_id).await {
+        Ok(domains) => {
+            tracing::info!("✅ Retrieved {} custom domains", domains.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Custom domains retrieved successfully",
+                "data": domains,
+                "count": domains.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch custom domains: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Change the chatbot or branding served on a custom domain
+#[utoipa::path(
+    put,
+    path = "/api/custom-domains/{hostname}",
+    tag = "custom-domains",
+    params(("hostname" = String, Path, description = "Custom hostname")),
+    request_body = UpdateCustomDomainRequest,
+    responses(
+        (status = 200, description = "Custom domain updated", body = Value),
+        (status = 400, description = "Invalid branding"),
+        (status = 404, description = "Custom domain or chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_custom_domain_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(hostname): Path<String>,
+    Json(payload): Json<UpdateCustomDomainRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating custom domain '{}'", hostname);
+
+    let hostname = parse_hostname(&hostname)?;
+    check_branding(payload.branding.as_ref())?;
+
+    let domain = match update_custom_domain(&app_state.db, tenant.organization_id, &hostname, payload.chatbot_id, payload.branding).await {
+        Ok(Some(domain)) => domain,
+        Ok(None) => {
+            tracing::error!("Custom domain {} or chatbot {:?} not found", hostname, payload.chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update custom domain: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    publish_change(&app_state, &hostname).await;
+
+    tracing::info!("✅ Custom domain updated: {}", hostname);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Custom domain updated successfully",
+        "data": domain
+    })))
+}
+
+// Remove a custom domain
+#[utoipa::path(
+    delete,
+    path = "/api/custom-domains/{hostname}",
+    tag = "custom-domains",
+    params(("hostname" = String, Path, description = "Custom hostname")),
+    responses(
+        (status = 200, description = "Custom domain deleted", body = Value),
+        (status = 404, description = "Custom domain not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_custom_domain_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(hostname): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting custom domain '{}'", hostname);
+
+    let hostname = parse_hostname(&hostname)?;
+
+    match delete_custom_domain(&app_state.db, tenant.organization_id, &hostname).await {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::error!("Custom domain not found: {}", hostname);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete custom domain: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    publish_change(&app_state, &hostname).await;
+
+    tracing::info!("✅ Custom domain deleted: {}", hostname);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Custom domain deleted successfully",
+        "data": { "hostname": hostname }
+    })))
+}
+
+// Widget settings for the custom domain the request was made on; needs no API key
+#[utoipa::path(
+    get,
+    path = "/api/widget/config",
+    tag = "widget",
+    responses(
+        (status = 200, description = "Chatbot and branding for this host", body = Value),
+        (status = 404, description = "Host is not a registered custom domain"),
+    )
+)]
+pub async fn get_widget_config_handler(DomainTenant(domain): DomainTenant) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "message": "Widget configuration retrieved successfully",
+        "data": {
+            "hostname": domain.hostname,
+            "chatbot_id": domain.chatbot_id,
+            "branding": domain.branding.map(|branding| branding.0).unwrap_or_default()
+        }
+    }))
+}
+
+// Create the router for custom domain and widget routes
+pub fn create_custom_domain_router() -> Router<AppState> {
+    Router::new()
+        .route("/custom-domains", get(get_custom_domains_handler).post(create_custom_domain_handler))
+        .route(
+            "/custom-domains/{hostname}",
+            put(update_custom_domain_handler).delete(delete_custom_domain_handler),
+        )
+        .route("/widget/config", get(get_widget_config_handler))
+}
@@ -0,0 +1,385 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::post,
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::{
+    ChatBot, EvaluateChatbotRequest, EvaluationCase, GlossaryEntry, RetrievalEvalRequest, UsageEvent,
+};
+use crate::db::queries::{get_chat_bot_for_admin, list_glossary_entries};
+use crate::middleware::auth::{AdminViewer, Tenant};
+use crate::services::answer_policy::{
+    fallback_response, filter_by_min_score, no_context_template, GENERAL_KNOWLEDGE_CONTEXT,
+};
+use crate::services::embedding::EmbeddingService;
+use crate::services::evaluation::{
+    parse_judge_reply, summarize, CaseResult, DEFAULT_PASS_THRESHOLD, MAX_EVALUATION_CASES,
+};
+use crate::services::gemini::GeminiService;
+use crate::services::glossary::{format_glossary, matching_entries};
+use crate::services::output_filter::filter_answer;
+use crate::services::prompt_template::{current_template, render_prompt, PromptContext};
+use crate::services::query_expansion::{expand_query, search_fused};
+use crate::services::retrieval_eval::{aggregate, k_values, score_query, MAX_GOLDEN_QUERIES};
+use crate::services::scripting::run_answer_hook;
+use crate::services::sharding::shard_indices;
+use crate::services::stitching::group_by_document;
+use crate::services::token_budget::{format_document, join_sections};
+use crate::services::usage::{record_usage, with_embedding_usage};
+use crate::services::vector::chatbot_index_name;
+use crate::utils::config::AppState;
+
+// Context shown to the judge when the chatbot answered with its fallback message
+const NO_CONTEXT: &str = "No document passed the score threshold, so the chatbot replied with its fallback message.";
+
+// The chatbot's answer to one question and what it was based on
+struct CaseAnswer {
+    text: String,
+    context: String,
+    context_used: Vec<String>,
+    fallback: bool,
+}
+
+// What every question of one evaluation is answered with
+struct Evaluator<'a> {
+    app_state: &'a AppState,
+    chatbot: &'a ChatBot,
+    embedding_service: EmbeddingService,
+    gemini_service: GeminiService,
+    index_names: Vec<String>,
+    glossary_entries: Vec<GlossaryEntry>,
+    template: Option<String>,
+}
+
+impl Evaluator<'_> {
+    // Answer the way the chat endpoint does for a new chat, without storing a conversation
+    async fn answer(&self, question: &str, usage: &mut UsageEvent) -> anyhow::Result<CaseAnswer> {
+        let plugins = &self.app_state.plugins;
+        let queries = if self.chatbot.query_paraphrases > 0 {
+            expand_query(question, self.chatbot.query_paraphrases as usize).await
+        } else {
+            vec![question.to_string()]
+        };
+        let search_results =
+            search_fused(&self.embedding_service, &self.index_names, &queries, 5, self.chatbot.mmr_lambda).await?;
+        let search_results = if plugins.has_result_stages() {
+            plugins.filter_and_rerank(question, search_results).await?
+        } else {
+            search_results
+        };
+        let search_results = filter_by_min_score(search_results, self.chatbot.min_score);
+        if let Some(message) = fallback_response(self.chatbot, &search_results) {
+            return Ok(CaseAnswer {
+                text: message,
+                context: NO_CONTEXT.to_string(),
+                context_used: Vec::new(),
+                fallback: true,
+            });
+        }
+
+        let glossary = format_glossary(&matching_entries(question, &self.glossary_entries));
+        let no_context_prompt = no_context_template(self.chatbot, &search_results);
+        let template = no_context_prompt.or(self.template.as_deref());
+        let fixed_prompt = render_prompt(
+            template,
+            &PromptContext { glossary: &glossary, history: "", documents: "" },
+            question,
+            None,
+        )?;
+        let token_budget = &self.app_state.token_budget;
+        let fixed_tokens = token_budget.count(&fixed_prompt);
+        let chunks = token_budget.fit(fixed_tokens, Vec::new(), search_results).chunks;
+        let context = if !chunks.is_empty() {
+            join_sections(group_by_document(&chunks).iter().map(format_document))
+        } else if no_context_prompt.is_some() {
+            String::new()
+        } else {
+            GENERAL_KNOWLEDGE_CONTEXT.to_string()
+        };
+        let prompt = render_prompt(
+            template,
+            &PromptContext { glossary: &glossary, history: "", documents: &context },
+            question,
+            None,
+        )?;
+
+        let prompt_tokens = token_budget.check(&prompt, fixed_tokens)?;
+        let answer = self.gemini_service.generate_response(&prompt).await?;
+        usage.input_tokens += prompt_tokens as i64;
+        usage.output_tokens += token_budget.count(&answer) as i64;
+        let answer = match self.chatbot.answer_script.as_deref() {
+            Some(script) => run_answer_hook(script, question, &answer)?,
+            None => answer,
+        };
+        let answer = match self.chatbot.output_filters.as_deref() {
+            Some(filters) => filter_answer(filters, &answer).await?.text,
+            None => answer,
+        };
+
+        Ok(CaseAnswer {
+            text: answer,
+            context,
+            context_used: chunks.into_iter().map(|chunk| chunk.file_path).collect(),
+            fallback: false,
+        })
+    }
+
+    // Answer and judge one question. Failures are reported on its result so the other questions
+    // are still evaluated
+    async fn run(&self, case: &EvaluationCase, pass_threshold: f64, usage: &mut UsageEvent) -> CaseResult {
+        let mut result = CaseResult {
+            question: case.question.clone(),
+            expected_answer: case.expected_answer.clone(),
+            answer: None,
+            context_used: Vec::new(),
+            fallback: false,
+            scores: None,
+            passed: false,
+            error: None,
+        };
+
+        let answer = match self.answer(&case.question, usage).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                tracing::warn!("⚠️ Failed to answer evaluation question '{}': {}", case.question, e);
+                result.error = Some(format!("Failed to answer the question: {}", e));
+                return result;
+            }
+        };
+        result.context_used = answer.context_used;
+        result.fallback = answer.fallback;
+
+        let judged = self
+            .gemini_service
+            .judge_answer(&case.question, &case.expected_answer, &answer.context, &answer.text)
+            .await;
+        let token_budget = &self.app_state.token_budget;
+        match judged {
+            Ok(reply) => {
+                let graded: [&str; 4] = [case.question.as_str(), &case.expected_answer, &answer.context, &answer.text];
+                usage.input_tokens += graded.iter().map(|text| token_budget.count(text) as i64).sum::<i64>();
+                usage.output_tokens += token_budget.count(&reply) as i64;
+                match parse_judge_reply(&reply) {
+                    Some(scores) => {
+                        result.passed = scores.passes(pass_threshold);
+                        result.scores = Some(scores);
+                    }
+                    None => result.error = Some("The judge's reply held no valid scores".to_string()),
+                }
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ Failed to judge evaluation question '{}': {}", case.question, e);
+                result.error = Some(format!("Failed to judge the answer: {}", e));
+            }
+        }
+        result.answer = Some(answer.text);
+        result
+    }
+}
+
+// Answer a set of questions with the chatbot and score each answer with an LLM judge
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/evaluate",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = EvaluateChatbotRequest,
+    responses(
+        (status = 200, description = "Aggregate metrics and per-question results", body = Value),
+        (status = 400, description = "No questions, more than 50, an empty question or a threshold outside 0 to 1"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn evaluate_chatbot_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<EvaluateChatbotRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Evaluating chatbot {} on {} questions", chatbot_id, payload.cases.len());
+
+    if payload.cases.is_empty() || payload.cases.len() > MAX_EVALUATION_CASES {
+        tracing::error!("An evaluation needs between 1 and {} questions", MAX_EVALUATION_CASES);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.cases.iter().any(|case| case.question.trim().is_empty()) {
+        tracing::error!("Evaluation questions must not be empty");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let pass_threshold = payload.pass_threshold.unwrap_or(DEFAULT_PASS_THRESHOLD);
+    if !(0.0..=1.0).contains(&pass_threshold) {
+        tracing::error!("Invalid pass_threshold: {}", pass_threshold);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())
+        .map_err(|e| {
+            tracing::error!("Failed to create embedding service: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let gemini_service = GeminiService::new().map_err(|e| {
+        tracing::error!("Failed to create Gemini service: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let glossary_entries = list_glossary_entries(&app_state.db, tenant.organization_id, chatbot_id).await.map_err(|e| {
+        tracing::error!("Failed to get glossary: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Canaries are left out so that every question is answered with the same template
+    let template = current_template(&app_state.db, tenant.organization_id, &chatbot).await.map_err(|e| {
+        tracing::error!("Failed to get prompt template: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let evaluator = Evaluator {
+        app_state: &app_state,
+        chatbot: &chatbot,
+        embedding_service,
+        gemini_service,
+        index_names: shard_indices(&chatbot_index_name(tenant.organization_id, chatbot_id), chatbot.shard_count),
+        glossary_entries,
+        template,
+    };
+    let mut usage = UsageEvent {
+        organization_id: tenant.organization_id,
+        chatbot_id,
+        operation: "evaluation",
+        ..Default::default()
+    };
+
+    let mut results = Vec::with_capacity(payload.cases.len());
+    for case in &payload.cases {
+        results.push(evaluator.run(case, pass_threshold, &mut usage).await);
+    }
+    let summary = summarize(&results);
+
+    record_usage(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        with_embedding_usage(usage, &evaluator.embedding_service),
+    );
+
+    tracing::info!(
+        "✅ Evaluated chatbot {}: {} of {} questions passed",
+        chatbot_id,
+        summary.passed,
+        summary.cases
+    );
+    Ok(Json(json!({
+        "success": true,
+        "message": "Chatbot evaluated successfully",
+        "data": {
+            "pass_threshold": pass_threshold,
+            "summary": summary,
+            "results": results
+        }
+    })))
+}
+
+// Measure how well a chatbot's vector search finds labeled chunks: recall and nDCG at each cutoff,
+// and mean reciprocal rank (admins and viewers)
+#[utoipa::path(
+    post,
+    path = "/api/admin/chatbots/{id}/retrieval-eval",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = RetrievalEvalRequest,
+    responses(
+        (status = 200, description = "Aggregate and per-query retrieval metrics", body = Value),
+        (status = 400, description = "No queries, more than 200, a query without relevant chunks or a bad cutoff"),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn evaluate_retrieval_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminViewer,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<RetrievalEvalRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Evaluating retrieval of chatbot {} on {} queries", chatbot_id, payload.queries.len());
+
+    if payload.queries.is_empty() || payload.queries.len() > MAX_GOLDEN_QUERIES {
+        tracing::error!("A retrieval evaluation needs between 1 and {} queries", MAX_GOLDEN_QUERIES);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.queries.iter().any(|golden| golden.query.trim().is_empty() || golden.relevant.is_empty()) {
+        tracing::error!("Every query needs text and at least one relevant chunk");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let k_values = k_values(payload.k_values).map_err(|e| {
+        tracing::error!("Invalid k_values: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let (chatbot, organization_id) = match get_chat_bot_for_admin(&app_state.db, chatbot_id).await {
+        Ok(Some(chatbot)) => {
+            let organization_id = chatbot.organization_id;
+            (chatbot, organization_id)
+        }
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())
+        .map_err(|e| {
+            tracing::error!("Failed to create embedding service: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let index_names = shard_indices(&chatbot_index_name(organization_id, chatbot_id), chatbot.shard_count);
+
+    // Hits are ranked best first, so one search at the largest cutoff serves every smaller one
+    let limit = k_values.last().copied().unwrap_or_default() as u64;
+    let mut results = Vec::with_capacity(payload.queries.len());
+    for golden in &payload.queries {
+        let hits = embedding_service.search_similar(&index_names, &golden.query, limit).await.map_err(|e| {
+            tracing::error!("❌ Failed to search for '{}': {}", golden.query, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        results.push(score_query(&golden.query, &hits, &golden.relevant, &k_values));
+    }
+    let summary = aggregate(&results, &k_values);
+
+    tracing::info!("✅ Evaluated retrieval of chatbot {}: MRR {:.3}", chatbot_id, summary.mrr);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Retrieval evaluated successfully",
+        "data": {
+            "k_values": k_values,
+            "summary": summary,
+            "results": results
+        }
+    })))
+}
+
+pub fn create_evaluation_router() -> Router<AppState> {
+    Router::new()
+        .route("/chatbots/{id}/evaluate", post(evaluate_chatbot_handler))
+        .route("/admin/chatbots/{id}/retrieval-eval", post(evaluate_retrieval_handler))
+}
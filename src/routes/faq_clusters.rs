@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{get_retained_chat_bot, list_faq_clusters};
+use crate::middleware::auth::Tenant;
+use crate::services::faq_clusters::{cluster_days, refresh_faq_clusters};
+use crate::utils::config::AppState;
+
+const DEFAULT_LIMIT: i64 = 10;
+const MAX_LIMIT: i64 = 50;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FaqClusterQuery {
+    /// Clusters to return, most asked first; defaults to 10, at most 50
+    pub limit: Option<i64>,
+}
+
+// Check the chatbot belongs to the caller's organization
+async fn ensure_chatbot(app_state: &AppState, organization_id: Uuid, chatbot_id: Uuid) -> Result<(), StatusCode> {
+    match get_retained_chat_bot(&app_state.db, organization_id, chatbot_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// The most asked groups of similar questions, with example questions and how often they were asked
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/faq-clusters",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), FaqClusterQuery),
+    responses(
+        (status = 200, description = "Question clusters from the last refresh", body = Value),
+        (status = 400, description = "Invalid limit"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_faq_clusters_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<FaqClusterQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        tracing::error!("FAQ cluster limit must be between 1 and {}", MAX_LIMIT);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    ensure_chatbot(&app_state, tenant.organization_id, chatbot_id).await?;
+
+    match list_faq_clusters(&app_state.db, tenant.organization_id, chatbot_id, limit).await {
+        Ok(clusters) => Ok(Json(json!({
+            "success": true,
+            "message": "FAQ clusters retrieved successfully",
+            "data": {
+                "chatbot_id": chatbot_id,
+                "days": cluster_days(),
+                "computed_at": clusters.first().map(|cluster| cluster.computed_at),
+                "clusters": clusters
+            }
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list FAQ clusters: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Re-cluster the chatbot's recent questions now instead of waiting for the next scheduled run
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/faq-clusters/refresh",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Clusters recomputed", body = Value),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn refresh_faq_clusters_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Refreshing FAQ clusters for chatbot: {}", chatbot_id);
+    ensure_chatbot(&app_state, tenant.organization_id, chatbot_id).await?;
+
+    let cluster_count = refresh_faq_clusters(
+        &app_state.db,
+        &app_state.vector_store,
+        app_state.embedding_cache.clone(),
+        chatbot_id,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to cluster questions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "FAQ clusters refreshed successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "cluster_count": cluster_count
+        }
+    })))
+}
+
+// Create the router for FAQ clusters
+pub fn create_faq_cluster_router() -> Router<AppState> {
+    Router::new()
+        .route("/chatbots/{id}/faq-clusters", get(list_faq_clusters_handler))
+        .route("/chatbots/{id}/faq-clusters/refresh", post(refresh_faq_clusters_handler))
+}
@@ -0,0 +1,285 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::models::CreateFeedbackRequest;
+use crate::db::queries::{
+    count_chat_negative_feedback, get_retained_chat_bot, get_feedback_summary, list_feedback_by_chatbot,
+    list_rated_citations, upsert_conversation_feedback,
+};
+use crate::middleware::auth::Tenant;
+use crate::services::handoff::{escalate_chat, feedback_escalates, EscalationReason, Handoff};
+use crate::services::knowledge_gaps::knowledge_gaps;
+use crate::utils::config::AppState;
+
+const DEFAULT_FEEDBACK_LIMIT: i64 = 50;
+const MAX_FEEDBACK_LIMIT: i64 = 500;
+const DEFAULT_GAP_DAYS: i64 = 30;
+const MAX_GAP_DAYS: i64 = 365;
+const DEFAULT_GAP_LIMIT: usize = 20;
+const MAX_GAP_LIMIT: usize = 100;
+// Citations of the most recently rated answers the report is computed from
+const MAX_GAP_CITATIONS: i64 = 20_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedbackQuery {
+    /// Only return entries with this rating ("up" or "down")
+    pub rating: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct KnowledgeGapQuery {
+    /// Days of ratings to look at; defaults to 30, at most 365
+    pub days: Option<i64>,
+    /// Documents to return; defaults to 20, at most 100
+    pub limit: Option<usize>,
+}
+
+fn is_valid_rating(rating: &str) -> bool {
+    matches!(rating, "up" | "down")
+}
+
+// Hand the chat off to a person once enough of its answers are rated down. Failures are logged
+// rather than returned, since the feedback itself was recorded
+async fn escalate_on_negative_feedback(app_state: &AppState, organization_id: Uuid, conversation_id: Uuid) {
+    let counts = match count_chat_negative_feedback(&app_state.db, organization_id, conversation_id).await {
+        Ok(Some(counts)) => counts,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to count negative feedback: {}", e);
+            return;
+        }
+    };
+    if counts.escalated || !feedback_escalates(counts.thumbs_down) {
+        return;
+    }
+    let Some(chatbot_id) = counts.chatbot_id else {
+        return;
+    };
+
+    let handoff = match app_state.chatbot_cache.get_chatbot(&app_state.db, organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => Handoff::for_chatbot(&chatbot),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to get chatbot for handoff: {}", e);
+            None
+        }
+    };
+    let Some(handoff) = handoff else {
+        return;
+    };
+
+    if let Err(e) = escalate_chat(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        handoff,
+        organization_id,
+        counts.chat_id,
+        EscalationReason::NegativeFeedback,
+    ).await {
+        tracing::warn!("⚠️ Failed to escalate chat {}: {}", counts.chat_id, e);
+    }
+}
+
+// Rate a conversation turn; submitting again replaces the previous feedback
+#[utoipa::path(
+    post,
+    path = "/api/conversations/{id}/feedback",
+    tag = "feedback",
+    params(("id" = Uuid, Path, description = "Conversation id")),
+    request_body = CreateFeedbackRequest,
+    responses(
+        (status = 200, description = "Feedback recorded", body = Value),
+        (status = 400, description = "Rating must be \"up\" or \"down\""),
+        (status = 404, description = "Conversation not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_feedback_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(conversation_id): Path<Uuid>,
+    Json(payload): Json<CreateFeedbackRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Recording feedback for conversation: {}", conversation_id);
+
+    if !is_valid_rating(&payload.rating) {
+        tracing::error!("Invalid feedback rating: {}", payload.rating);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match upsert_conversation_feedback(
+        &app_state.db,
+        tenant.organization_id,
+        conversation_id,
+        payload.rating,
+        payload.comment,
+    ).await {
+        Ok(Some(feedback)) => {
+            if feedback.rating == "down" {
+                escalate_on_negative_feedback(&app_state, tenant.organization_id, conversation_id).await;
+            }
+            tracing::info!("✅ Feedback recorded for conversation: {}", conversation_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Feedback recorded successfully",
+                "data": feedback
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("Conversation not found: {}", conversation_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to record feedback: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Aggregate feedback for a chatbot, newest rated turns first
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/feedback",
+    tag = "feedback",
+    params(("id" = Uuid, Path, description = "Chatbot id"), FeedbackQuery),
+    responses(
+        (status = 200, description = "Feedback summary and rated turns", body = Value),
+        (status = 400, description = "Invalid rating filter"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_chatbot_feedback_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<FeedbackQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching feedback for chatbot: {}", chatbot_id);
+
+    if let Some(rating) = &params.rating
+        && !is_valid_rating(rating)
+    {
+        tracing::error!("Invalid feedback rating filter: {}", rating);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_FEEDBACK_LIMIT).clamp(1, MAX_FEEDBACK_LIMIT);
+
+    let summary = get_feedback_summary(&app_state.db, tenant.organization_id, chatbot_id).await.map_err(|e| {
+        tracing::error!("❌ Failed to aggregate feedback: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let entries = list_feedback_by_chatbot(&app_state.db, tenant.organization_id, chatbot_id, params.rating, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to list feedback: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!("✅ Retrieved {} feedback entries", entries.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Feedback retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "summary": summary,
+            "entries": entries
+        }
+    })))
+}
+
+// Documents and chunks most associated with thumbs-down answers, from the citations stored with
+// rated answers. Impact weighs a chunk's thumbs-down count by the share of its rated answers that
+// were thumbs-down, so chunks that are simply cited often don't dominate
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/feedback/gaps",
+    tag = "feedback",
+    params(("id" = Uuid, Path, description = "Chatbot id"), KnowledgeGapQuery),
+    responses(
+        (status = 200, description = "Documents and chunks, the highest impact first", body = Value),
+        (status = 400, description = "Invalid days or limit"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_knowledge_gaps_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<KnowledgeGapQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let days = params.days.unwrap_or(DEFAULT_GAP_DAYS);
+    let limit = params.limit.unwrap_or(DEFAULT_GAP_LIMIT);
+    if !(1..=MAX_GAP_DAYS).contains(&days) || !(1..=MAX_GAP_LIMIT).contains(&limit) {
+        tracing::error!("Knowledge gap reports cover 1 to {} days and 1 to {} documents", MAX_GAP_DAYS, MAX_GAP_LIMIT);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let since = Utc::now() - chrono::Duration::days(days);
+    let citations = list_rated_citations(&app_state.db, tenant.organization_id, chatbot_id, since, MAX_GAP_CITATIONS)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to list rated citations: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let documents = knowledge_gaps(&citations, limit);
+
+    tracing::info!("✅ Found {} documents cited by thumbs-down answers", documents.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Knowledge gaps retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "days": days,
+            "since": since,
+            "documents": documents
+        }
+    })))
+}
+
+// Create the router for feedback routes
+pub fn create_feedback_router() -> Router<AppState> {
+    Router::new()
+        .route("/conversations/{id}/feedback", post(create_feedback_handler))
+        .route("/chatbots/{id}/feedback", get(get_chatbot_feedback_handler))
+        .route("/chatbots/{id}/feedback/gaps", get(get_knowledge_gaps_handler))
+}
@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::UpsertGlossaryEntryRequest;
+use crate::db::queries::{delete_glossary_entry, get_retained_chat_bot, list_glossary_entries, upsert_glossary_entry};
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::utils::config::AppState;
+
+// Answers the chatbot gave with the old glossary are stale on every replica
+async fn publish_glossary_change(app_state: &AppState, chatbot_id: Uuid) {
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+}
+
+// Add a glossary term to a chatbot, or replace its definition if it exists
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/glossary",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpsertGlossaryEntryRequest,
+    responses(
+        (status = 200, description = "Glossary entry saved", body = Value),
+        (status = 400, description = "Term or definition is empty"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn upsert_glossary_entry_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpsertGlossaryEntryRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Saving glossary term '{}' for chatbot: {}", payload.term, chatbot_id);
+
+    let term = payload.term.trim().to_string();
+    let definition = payload.definition.trim().to_string();
+    if term.is_empty() || definition.is_empty() {
+        tracing::error!("Glossary term and definition must not be empty");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let aliases: Vec<String> = payload
+        .aliases
+        .iter()
+        .map(|alias| alias.trim().to_string())
+        .filter(|alias| !alias.is_empty())
+        .collect();
+
+    match upsert_glossary_entry(&app_state.db, tenant.organization_id, chatbot_id, term, definition, aliases).await {
+        Ok(Some(entry)) => {
+            publish_glossary_change(&app_state, chatbot_id).await;
+            tracing::info!("✅ Glossary entry saved: {}", entry.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Glossary entry saved successfully",
+                "data": entry
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save glossary entry: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List a chatbot's glossary
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/glossary",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Glossary entries", body = Value),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_glossary_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching glossary for chatbot: {}", chatbot_id);
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match list_glossary_entries(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(entries) => {
+            tracing::info!("✅ Retrieved {} glossary entries", entries.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Glossary retrieved successfully",
+                "data": entries,
+                "count": entries.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch glossary: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Remove a glossary entry
+#[utoipa::path(
+    delete,
+    path = "/api/chatbots/{id}/glossary/{entry_id}",
+    tag = "chatbots",
+    params(
+        ("id" = Uuid, Path, description = "Chatbot id"),
+        ("entry_id" = Uuid, Path, description = "Glossary entry id"),
+    ),
+    responses(
+        (status = 200, description = "Glossary entry deleted", body = Value),
+        (status = 404, description = "Glossary entry not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_glossary_entry_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path((chatbot_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting glossary entry {} from chatbot: {}", entry_id, chatbot_id);
+
+    match delete_glossary_entry(&app_state.db, tenant.organization_id, chatbot_id, entry_id).await {
+        Ok(true) => {
+            publish_glossary_change(&app_state, chatbot_id).await;
+            tracing::info!("✅ Glossary entry deleted: {}", entry_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Glossary entry deleted successfully",
+                "data": { "entry_id": entry_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("Glossary entry not found: {}", entry_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete glossary entry: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for glossary routes
+pub fn create_glossary_router() -> Router<AppState> {
+    Router::new()
+        .route("/chatbots/{id}/glossary", get(get_glossary_handler).put(upsert_glossary_entry_handler))
+        .route("/chatbots/{id}/glossary/{entry_id}", delete(delete_glossary_entry_handler))
+}
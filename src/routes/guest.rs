@@ -0,0 +1,230 @@
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Json, Response},
+    routing::post,
+    Router,
+};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+use crate::db::models::{CreateGuestSessionRequest, GuestSession};
+use crate::db::queries::{
+    create_guest_session, create_session, delete_expired_guest_sessions, get_chat, get_guest_chat_bot,
+    get_public_widget_config,
+};
+use crate::middleware::auth::{generate_guest_token, hash_api_key, Guest, Tenant};
+use crate::routes::chat::{chat_handler, chat_stream_handler, ChatRequest};
+use crate::services::guest::GuestConfig;
+use crate::services::user_auth::UserRole;
+use crate::services::widget_config::origin_allowed;
+use crate::utils::config::AppState;
+
+// Guests act as viewers of their organization, but only ever reach the chat routes below
+fn guest_tenant(guest: &GuestSession) -> Tenant {
+    Tenant { organization_id: guest.organization_id, user_id: None, role: UserRole::Viewer }
+}
+
+// Keep a guest's chat request to the chatbot and session its token was issued for
+async fn scoped_request(
+    app_state: &AppState,
+    guest: &GuestSession,
+    mut payload: ChatRequest,
+) -> Result<ChatRequest, StatusCode> {
+    if Uuid::parse_str(&payload.chatbot_id).ok() != Some(guest.chatbot_id) {
+        tracing::warn!("Guest token for chatbot {} used with chatbot {}", guest.chatbot_id, payload.chatbot_id);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if let Some(session_id) = payload.session_id.as_deref()
+        && Uuid::parse_str(session_id).ok() != Some(guest.session_id)
+    {
+        tracing::warn!("Guest token for session {} used with session {}", guest.session_id, session_id);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if let Some(chat_id) = payload.chat_id.as_deref() {
+        let chat_id = Uuid::parse_str(chat_id).map_err(|e| {
+            tracing::error!("Invalid chat_id format: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+        match get_chat(&app_state.db, guest.organization_id, chat_id, None).await {
+            Ok(Some(chat)) if chat.session_id == guest.session_id => {}
+            Ok(_) => {
+                tracing::error!("Chat {} not found in guest session {}", chat_id, guest.session_id);
+                return Err(StatusCode::NOT_FOUND);
+            }
+            Err(e) => {
+                tracing::error!("Failed to get chat: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    payload.session_id = Some(guest.session_id.to_string());
+    Ok(payload)
+}
+
+// Open a guest session with a chatbot that allows guests, after checking the visitor's CAPTCHA
+// when one is configured. Needs no API key, so it can be called from a public widget
+#[utoipa::path(
+    post,
+    path = "/api/guest/sessions",
+    tag = "chat",
+    request_body = CreateGuestSessionRequest,
+    responses(
+        (status = 200, description = "Guest token, its session and when it expires", body = Value),
+        (status = 400, description = "CAPTCHA token missing"),
+        (status = 403, description = "CAPTCHA rejected, or the page's origin may not embed this chatbot"),
+        (status = 404, description = "Chatbot not found or doesn't allow guests"),
+        (status = 502, description = "CAPTCHA provider unreachable"),
+    )
+)]
+pub async fn create_guest_session_handler(
+    State(app_state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateGuestSessionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Opening guest session for chatbot: {}", payload.chatbot_id);
+
+    let config = GuestConfig::from_env().map_err(|e| {
+        tracing::error!("❌ Invalid guest session configuration: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let organization_id = match get_guest_chat_bot(&app_state.db, payload.chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot.organization_id,
+        Ok(None) => {
+            tracing::error!("Chatbot {} not found or doesn't allow guests", payload.chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // A widget config's allowed origins keep other sites from embedding the chatbot
+    match get_public_widget_config(&app_state.db, payload.chatbot_id).await {
+        Ok(Some(widget)) => {
+            let origin = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok());
+            if !origin_allowed(&widget.allowed_origins, origin) {
+                tracing::warn!("Guest session for chatbot {} refused for origin {:?}", payload.chatbot_id, origin);
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to get widget config: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    if let Some(captcha) = &config.captcha {
+        let Some(token) = payload.captcha_token.as_deref().filter(|token| !token.is_empty()) else {
+            tracing::error!("Missing CAPTCHA token");
+            return Err(StatusCode::BAD_REQUEST);
+        };
+        let remote_ip = app_state.ip_filter.client_ip(&headers, Some(peer.ip())).map(|ip| ip.to_string());
+        match captcha.verify(token, remote_ip.as_deref()).await {
+            Ok(true) => {}
+            Ok(false) => return Err(StatusCode::FORBIDDEN),
+            Err(e) => {
+                tracing::error!("❌ Failed to verify CAPTCHA with {}: {}", captcha.provider, e);
+                return Err(StatusCode::BAD_GATEWAY);
+            }
+        }
+    }
+
+    if let Err(e) = delete_expired_guest_sessions(&app_state.db).await {
+        tracing::warn!("⚠️ Failed to delete expired guest sessions: {}", e);
+    }
+
+    let session = create_session(&app_state.db, organization_id, None).await.map_err(|e| {
+        tracing::error!("Failed to create session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let token = generate_guest_token();
+    let guest_session = create_guest_session(
+        &app_state.db,
+        &hash_api_key(&token),
+        organization_id,
+        payload.chatbot_id,
+        session.id,
+        config.session_ttl_secs,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to create guest session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ Guest session {} opened for chatbot {}", session.id, payload.chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Guest session created successfully",
+        "data": {
+            "token": token,
+            "chatbot_id": guest_session.chatbot_id,
+            "session_id": guest_session.session_id,
+            "expires_at": guest_session.expires_at
+        }
+    })))
+}
+
+// Chat as a guest; the request is limited to the token's chatbot and session
+#[utoipa::path(
+    post,
+    path = "/api/guest/chat",
+    tag = "chat",
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Chat response", body = Value),
+        (status = 401, description = "Missing, unknown or expired guest token"),
+        (status = 403, description = "Chatbot or session is not the token's"),
+        (status = 404, description = "Chat not found in the guest's session"),
+        (status = 413, description = "Prompt too large for the model's context window", body = Value),
+        (status = 422, description = "Query blocked by the chatbot's query filters", body = Value),
+    ),
+    security(("guest_token" = []))
+)]
+pub async fn guest_chat_handler(
+    State(app_state): State<AppState>,
+    Guest(guest): Guest,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Response, StatusCode> {
+    let payload = scoped_request(&app_state, &guest, payload).await?;
+    chat_handler(State(app_state), guest_tenant(&guest), Json(payload)).await
+}
+
+// Streaming chat as a guest; the request is limited to the token's chatbot and session
+#[utoipa::path(
+    post,
+    path = "/api/guest/chat/stream",
+    tag = "chat",
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Server-sent events with the answer"),
+        (status = 401, description = "Missing, unknown or expired guest token"),
+        (status = 403, description = "Chatbot or session is not the token's"),
+        (status = 404, description = "Chat not found in the guest's session"),
+        (status = 413, description = "Prompt too large for the model's context window", body = Value),
+        (status = 422, description = "Query blocked by the chatbot's query filters", body = Value),
+    ),
+    security(("guest_token" = []))
+)]
+pub async fn guest_chat_stream_handler(
+    State(app_state): State<AppState>,
+    Guest(guest): Guest,
+    Json(payload): Json<ChatRequest>,
+) -> Result<Response, StatusCode> {
+    let payload = scoped_request(&app_state, &guest, payload).await?;
+    chat_stream_handler(State(app_state), guest_tenant(&guest), Json(payload)).await
+}
+
+pub fn create_guest_router() -> Router<AppState> {
+    Router::new()
+        .route("/guest/sessions", post(create_guest_session_handler))
+        .route("/guest/chat", post(guest_chat_handler))
+        .route("/guest/chat/stream", post(guest_chat_stream_handler))
+}
@@ -0,0 +1,64 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+
+use crate::services::health::Readiness;
+use crate::utils::config::AppState;
+
+// Health check handler
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "Server is running", body = Value))
+)]
+pub async fn health_handler() -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "message": "RAG Server is running",
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+// Liveness probe: the process is up and serving requests, whatever its dependencies' state
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    tag = "health",
+    responses((status = 200, description = "Server is running", body = Value))
+)]
+pub async fn liveness_handler() -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+// Readiness probe: pings the database, vector store and LLM provider
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready, or degraded with only the LLM provider down", body = Readiness),
+        (status = 503, description = "The database or vector store is down", body = Readiness),
+    )
+)]
+pub async fn readiness_handler(State(app_state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let readiness = app_state.health.readiness(&app_state.db, &app_state.vector_store).await;
+    let status = if readiness.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(readiness))
+}
+
+// Create the router for the server's health checks, outside /api
+pub fn create_health_router() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(health_handler))
+        .route("/health/live", get(liveness_handler))
+        .route("/health/ready", get(readiness_handler))
+}
@@ -0,0 +1,424 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::models::{AgentReplyRequest, Chat, UsageEvent};
+use crate::db::queries::{
+    claim_chat, create_agent_reply, create_reply_suggestion, get_chat, get_latest_user_turn, get_reply_suggestion,
+    is_turn_number_conflict, list_inbox_chats, list_last_conversations_by_chat, mark_suggestion_used, set_chat_read,
+};
+use crate::middleware::auth::Tenant;
+use crate::services::conversation_export::citations_for;
+use crate::services::embedding::EmbeddingService;
+use crate::services::gemini::GeminiService;
+use crate::services::reply_suggestion::{draft_reply, SUGGESTION_HISTORY_TURNS};
+use crate::services::sharding::shard_indices;
+use crate::services::usage::{record_usage, with_embedding_usage};
+use crate::services::user_auth::UserRole;
+use crate::services::vector::chatbot_index_name;
+use crate::utils::config::AppState;
+
+const DEFAULT_INBOX_LIMIT: i64 = 50;
+const MAX_INBOX_LIMIT: i64 = 200;
+const MAX_REPLY_CHARS: usize = 10_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InboxQuery {
+    /// Only list chats of this chatbot
+    pub chatbot_id: Option<Uuid>,
+    /// Only list chats handed off to a person (default false)
+    pub escalated: Option<bool>,
+    /// Only list chats with user messages agents haven't read (default false)
+    pub unread: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClaimQuery {
+    /// Take the chat over from the agent holding it
+    pub force: Option<bool>,
+}
+
+// The chat, or 404 when it is missing
+async fn inbox_chat(app_state: &AppState, tenant: &Tenant, chat_id: Uuid) -> Result<Chat, StatusCode> {
+    match get_chat(&app_state.db, tenant.organization_id, chat_id, None).await {
+        Ok(Some(chat)) => Ok(chat),
+        Ok(None) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get chat: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Recent chats of the organization for human agents, the most recently active first, with how
+// many user messages each has that agents haven't read
+#[utoipa::path(
+    get,
+    path = "/api/inbox",
+    tag = "inbox",
+    params(InboxQuery),
+    responses(
+        (status = 200, description = "Chats with their claim and unread counts", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_inbox_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Query(params): Query<InboxQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let limit = params.limit.unwrap_or(DEFAULT_INBOX_LIMIT).clamp(1, MAX_INBOX_LIMIT);
+
+    match list_inbox_chats(
+        &app_state.db,
+        tenant.organization_id,
+        params.chatbot_id,
+        params.escalated.unwrap_or(false),
+        params.unread.unwrap_or(false),
+        limit,
+    )
+    .await
+    {
+        Ok(chats) => {
+            tracing::info!("✅ Listed {} inbox chats", chats.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Inbox retrieved successfully",
+                "data": chats
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to list inbox: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Take a chat as the signed-in agent. The bot stops answering it until it is resumed; a chat
+// another agent holds is only taken over with `force`
+#[utoipa::path(
+    post,
+    path = "/api/inbox/chats/{id}/claim",
+    tag = "inbox",
+    params(("id" = Uuid, Path, description = "Chat id"), ClaimQuery),
+    responses(
+        (status = 200, description = "Chat claimed", body = Value),
+        (status = 403, description = "Not a signed-in editor"),
+        (status = 404, description = "Chat not found"),
+        (status = 409, description = "Another agent holds the chat"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn claim_chat_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+    Query(params): Query<ClaimQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tenant.require_role(UserRole::Editor)?;
+    let Some(user_id) = tenant.user_id else {
+        tracing::error!("Chats can only be claimed by signed-in users");
+        return Err(StatusCode::FORBIDDEN);
+    };
+    inbox_chat(&app_state, &tenant, chat_id).await?;
+
+    match claim_chat(&app_state.db, tenant.organization_id, chat_id, user_id, params.force.unwrap_or(false)).await {
+        Ok(Some(chat)) => {
+            tracing::info!("✅ Chat {} claimed by user {}", chat_id, user_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Chat claimed successfully",
+                "data": chat
+            })))
+        }
+        Ok(None) => {
+            tracing::warn!("⚠️ Chat {} is held by another agent", chat_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to claim chat: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Reply to the user as a person. The reply is stored as a turn with `author: "human"` and shows
+// up in the chat's history; the chat must be handed off, and not held by another agent
+#[utoipa::path(
+    post,
+    path = "/api/inbox/chats/{id}/replies",
+    tag = "inbox",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    request_body = AgentReplyRequest,
+    responses(
+        (status = 200, description = "Reply sent", body = Value),
+        (status = 400, description = "Empty or too long message"),
+        (status = 403, description = "Not an editor"),
+        (status = 404, description = "Chat or suggestion not found"),
+        (status = 409, description = "The bot has the chat, another agent holds it, or a message took the turn number"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_agent_reply_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+    Json(payload): Json<AgentReplyRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tenant.require_role(UserRole::Editor)?;
+    let message = payload.message.trim();
+    if message.is_empty() || message.chars().count() > MAX_REPLY_CHARS {
+        tracing::error!("Agent replies must have 1 to {} characters", MAX_REPLY_CHARS);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let chat = inbox_chat(&app_state, &tenant, chat_id).await?;
+    if chat.escalated_at.is_none() {
+        tracing::warn!("⚠️ Chat {} isn't handed off; claim it before replying", chat_id);
+        return Err(StatusCode::CONFLICT);
+    }
+    if chat.claimed_by.is_some_and(|agent| Some(agent) != tenant.user_id) {
+        tracing::warn!("⚠️ Chat {} is held by another agent", chat_id);
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let suggestion = match payload.suggestion_id {
+        Some(suggestion_id) => match get_reply_suggestion(&app_state.db, tenant.organization_id, chat_id, suggestion_id).await {
+            Ok(Some(suggestion)) => Some(suggestion),
+            Ok(None) => {
+                tracing::error!("Suggestion {} not found in chat {}", suggestion_id, chat_id);
+                return Err(StatusCode::NOT_FOUND);
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to get suggestion: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        },
+        None => None,
+    };
+
+    match create_agent_reply(&app_state.db, tenant.organization_id, chat_id, message).await {
+        Ok(conversation) => {
+            if let Some(suggestion) = suggestion {
+                let edited = suggestion.suggestion.trim() != message;
+                match mark_suggestion_used(&app_state.db, suggestion.id, conversation.id, edited).await {
+                    Ok(true) => {}
+                    Ok(false) => tracing::warn!("⚠️ Suggestion {} was already used", suggestion.id),
+                    Err(e) => tracing::warn!("⚠️ Failed to record suggestion use: {}", e),
+                }
+            }
+            tracing::info!("✅ Agent reply added to chat {}", chat_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Reply sent successfully",
+                "data": conversation
+            })))
+        }
+        Err(e) if is_turn_number_conflict(&e) => {
+            tracing::warn!("⚠️ A message to chat {} took the reply's turn number", chat_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to add agent reply: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Draft a reply to the user's latest message with the chatbot's documents and the chat so far. The
+// agent edits it and sends it with `suggestion_id`, which records whether the draft was used
+#[utoipa::path(
+    post,
+    path = "/api/inbox/chats/{id}/suggestions",
+    tag = "inbox",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    responses(
+        (status = 200, description = "Suggested reply with the sources it was drafted from", body = Value),
+        (status = 403, description = "Not an editor"),
+        (status = 404, description = "Chat not found, or without a user message or chatbot"),
+        (status = 409, description = "The bot has the chat"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_reply_suggestion_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tenant.require_role(UserRole::Editor)?;
+    let chat = inbox_chat(&app_state, &tenant, chat_id).await?;
+    if chat.escalated_at.is_none() {
+        tracing::warn!("⚠️ Chat {} isn't handed off; the bot answers it", chat_id);
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let turn = match get_latest_user_turn(&app_state.db, tenant.organization_id, chat_id).await {
+        Ok(Some(turn)) => turn,
+        Ok(None) => {
+            tracing::error!("Chat {} has no user message to reply to", chat_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get the latest user message: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let Some(chatbot_id) = turn.chatbot_id else {
+        tracing::error!("Chat {} has no chatbot", chat_id);
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let turns = list_last_conversations_by_chat(
+        &app_state.db,
+        tenant.organization_id,
+        chat_id,
+        turn.thread_id,
+        SUGGESTION_HISTORY_TURNS,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to get conversation history: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
+        tracing::error!("Failed to create embedding service: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let gemini_service = GeminiService::new().map_err(|e| {
+        tracing::error!("Failed to create Gemini service: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let index_names = shard_indices(&chatbot_index_name(tenant.organization_id, chatbot_id), chatbot.shard_count);
+
+    let draft = draft_reply(&embedding_service, &gemini_service, &chatbot, &index_names, &turns, &turn.user_query)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to draft a reply: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let usage = UsageEvent {
+        organization_id: tenant.organization_id,
+        chatbot_id,
+        operation: "reply_suggestion",
+        input_tokens: app_state.token_budget.count(&draft.prompt) as i64,
+        output_tokens: app_state.token_budget.count(&draft.text) as i64,
+        ..Default::default()
+    };
+    record_usage(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        with_embedding_usage(usage, &embedding_service),
+    );
+
+    let citations = citations_for(&draft.sources);
+    match create_reply_suggestion(&app_state.db, chat_id, turn.id, &draft.text, &citations, tenant.user_id).await {
+        Ok(suggestion) => {
+            tracing::info!("✅ Reply suggested for chat {}", chat_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Reply suggested successfully",
+                "data": suggestion
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save suggestion: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn set_read(app_state: &AppState, tenant: &Tenant, chat_id: Uuid, read: bool) -> Result<Json<Value>, StatusCode> {
+    match set_chat_read(&app_state.db, tenant.organization_id, chat_id, read).await {
+        Ok(Some(chat)) => Ok(Json(json!({
+            "success": true,
+            "message": if read { "Chat marked read" } else { "Chat marked unread" },
+            "data": chat
+        }))),
+        Ok(None) => {
+            tracing::error!("Chat not found: {}", chat_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update chat read marker: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Mark a chat's user messages as read by agents
+#[utoipa::path(
+    post,
+    path = "/api/inbox/chats/{id}/read",
+    tag = "inbox",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    responses(
+        (status = 200, description = "Chat marked read", body = Value),
+        (status = 404, description = "Chat not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn mark_chat_read_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    set_read(&app_state, &tenant, chat_id, true).await
+}
+
+// Mark all of a chat's user messages unread again
+#[utoipa::path(
+    post,
+    path = "/api/inbox/chats/{id}/unread",
+    tag = "inbox",
+    params(("id" = Uuid, Path, description = "Chat id")),
+    responses(
+        (status = 200, description = "Chat marked unread", body = Value),
+        (status = 404, description = "Chat not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn mark_chat_unread_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chat_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    set_read(&app_state, &tenant, chat_id, false).await
+}
+
+// Create the router for the handoff inbox
+pub fn create_inbox_router() -> Router<AppState> {
+    Router::new()
+        .route("/inbox", get(list_inbox_handler))
+        .route("/inbox/chats/{id}/claim", post(claim_chat_handler))
+        .route("/inbox/chats/{id}/replies", post(create_agent_reply_handler))
+        .route("/inbox/chats/{id}/suggestions", post(create_reply_suggestion_handler))
+        .route("/inbox/chats/{id}/read", post(mark_chat_read_handler))
+        .route("/inbox/chats/{id}/unread", post(mark_chat_unread_handler))
+}
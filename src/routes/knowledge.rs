@@ -1,29 +1,116 @@
 use axum::{
-    extract::{Multipart, State},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
     http::StatusCode,
-    response::Json,
-    routing::post,
+    response::{Json, Response},
+    routing::{get, post, put},
     Router,
 };
+use chrono::Utc;
+use futures_util::future::ready;
+use futures_util::{stream, TryStreamExt};
+use serde::Deserialize;
 use serde_json::{json, Value};
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::fs;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-use crate::db::queries::get_chat_bot;
+use crate::db::models::{
+    ChatBot, Document, ImapImportRequest, IngestionLogEntry, JsonImportRequest, NewDocument,
+    RehydrateDocumentRequest, UpsertHelpCenterConnectorRequest, VectorUpsertRequest,
+};
+use crate::db::queries::{
+    complete_ingestion, create_ingestion, create_reindex_job, delete_document, delete_document_usage,
+    delete_help_center_article, fail_ingestion, find_document_by_hash, get_document, get_help_center_connector,
+    get_reindex_job, is_reindex_running, list_cold_documents, list_documents, list_help_center_article_paths,
+    list_ingestions, list_reindex_jobs, mark_help_center_synced, record_help_center_article, remove_cold_document,
+    set_ingestion_stage, upsert_document, upsert_help_center_connector,
+};
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::cold_storage::rehydrate_document;
+use crate::services::document_store::{content_hash, storage_key};
+use crate::services::vector::{chatbot_index_name, VectorStore};
+use crate::services::email::{mailbox_path, parse_message, split_mbox, ImapSource};
 use crate::services::embedding::EmbeddingService;
+use crate::services::help_center::{
+    article_path, is_valid_subdomain, ArticleChanges, HelpCenterProvider, HelpCenterSource,
+};
+use crate::services::ingest_webhook::IngestWebhook;
+use crate::services::ingestion_log::{document_of, IngestionStage};
+use crate::services::json_import::{json_source_path, map_records, parse_jsonl, validate_mapping, JsonRecord};
+use crate::services::json_stream::{ListingFormat, StreamedListing};
+use crate::services::raw_vectors::{to_documents, validate_chunks};
+use crate::services::reindex::{spawn_reindex, stale_after_secs};
+use crate::services::sharding::shard_indices;
+use crate::utils::chunking::ChunkingStrategy;
 use crate::utils::config::AppState;
 
-// Upload PDF file and create embeddings for a chatbot
+const DEFAULT_JSON_MAX_UPLOAD_MB: usize = 50;
+const DEFAULT_MBOX_MAX_UPLOAD_MB: usize = 50;
+const DEFAULT_PDF_MAX_UPLOAD_MB: usize = 100;
+const MAX_IMAP_MESSAGES: u32 = 5000;
+const MAX_LISTED_INGESTIONS: i64 = 100;
+// Chunks read per vector store request while a chatbot's chunks are streamed
+const CHUNK_PAGE_SIZE: usize = 500;
+const PDF_CONTENT_TYPE: &str = "application/pdf";
+const MBOX_CONTENT_TYPE: &str = "application/mbox";
+const JSON_CONTENT_TYPE: &str = "application/json";
+const JSONL_CONTENT_TYPE: &str = "application/x-ndjson";
+
+// Multipart form accepted by the upload endpoints, used for the OpenAPI schema only
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct UploadPdfForm {
+    pub chatbot_id: Uuid,
+    /// One or more PDFs; repeat the field to upload a batch
+    #[schema(value_type = Vec<String>, content_media_type = "application/pdf")]
+    pub file: Vec<Vec<u8>>,
+    /// "fixed" or "semantic"; defaults to `CHUNKING_STRATEGY`
+    pub chunking_strategy: Option<String>,
+    /// Replace an earlier upload with the same content instead of rejecting it with 409
+    pub overwrite: Option<bool>,
+}
+
+// Multipart form accepted by the mbox upload, used for the OpenAPI schema only
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct UploadMboxForm {
+    pub chatbot_id: Uuid,
+    #[schema(value_type = String, content_media_type = "application/mbox")]
+    pub file: Vec<u8>,
+}
+
+// Upload one or more PDF files and create embeddings for a chatbot. A batch reports each file's
+// result instead of failing the whole request when one file can't be ingested
+#[utoipa::path(
+    post,
+    path = "/api/upload-pdf",
+    tag = "knowledge",
+    request_body(content = UploadPdfForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "PDFs processed and embedded", body = Value),
+        (status = 400, description = "Missing or invalid form fields"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 413, description = "Request is larger than PDF_MAX_UPLOAD_MB"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 409, description = "Chatbot is being reindexed, or the PDF was already uploaded"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn upload_pdf_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, StatusCode> {
     tracing::info!("Starting PDF upload process");
 
     let mut chatbot_id: Option<Uuid> = None;
-    let mut file_data: Option<Vec<u8>> = None;
-    let mut file_name: Option<String> = None;
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut chunking_strategy: Option<ChunkingStrategy> = None;
+    let mut overwrite = false;
 
     // Parse multipart form data
     while let Some(field) = multipart.next_field().await.map_err(|e| {
@@ -43,11 +130,29 @@ pub async fn upload_pdf_handler(
                 })?);
             }
             Some("file") => {
-                file_name = field.file_name().map(|s| s.to_string());
-                file_data = Some(field.bytes().await.map_err(|e| {
+                let file_name = field.file_name().map(|s| s.to_string()).unwrap_or_else(|| "unknown.pdf".to_string());
+                let file_data = field.bytes().await.map_err(|e| {
                     tracing::error!("Failed to read file data: {}", e);
                     StatusCode::BAD_REQUEST
-                })?.to_vec());
+                })?.to_vec();
+                files.push((file_name, file_data));
+            }
+            Some("chunking_strategy") => {
+                let value = field.text().await.map_err(|e| {
+                    tracing::error!("Failed to read chunking_strategy: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?;
+                chunking_strategy = Some(parse_chunking_strategy(&value)?);
+            }
+            Some("overwrite") => {
+                let value = field.text().await.map_err(|e| {
+                    tracing::error!("Failed to read overwrite: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?;
+                overwrite = value.trim().parse().map_err(|e| {
+                    tracing::error!("Invalid overwrite flag: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?;
             }
             _ => {
                 tracing::warn!("Unknown field: {:?}", field.name());
@@ -61,20 +166,21 @@ pub async fn upload_pdf_handler(
         StatusCode::BAD_REQUEST
     })?;
 
-    let file_data = file_data.ok_or_else(|| {
+    if files.is_empty() {
         tracing::error!("Missing file in request");
-        StatusCode::BAD_REQUEST
-    })?;
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    let file_name = file_name.unwrap_or_else(|| "unknown.pdf".to_string());
+    let chunking_strategy = chunking_strategy.unwrap_or_else(ChunkingStrategy::from_env);
 
-    tracing::info!("Processing PDF for chatbot: {}, file: {}", chatbot_id, file_name);
+    tracing::info!("Processing {} PDF(s) for chatbot: {}", files.len(), chatbot_id);
 
     // Verify chatbot exists
     tracing::info!("Checking if chatbot exists in database...");
-    match get_chat_bot(&app_state.db, chatbot_id).await {
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
         Ok(Some(chatbot)) => {
             tracing::info!("✅ Found chatbot: {}", chatbot.name);
+            chatbot
         }
         Ok(None) => {
             tracing::error!("❌ Chatbot not found: {}", chatbot_id);
@@ -84,132 +190,501 @@ pub async fn upload_pdf_handler(
             tracing::error!("❌ Database error: {}", e);
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let batch = files.len() > 1;
+    let mut results = Vec::with_capacity(files.len());
+    let mut embedding_count = 0;
+    for (file_name, file_data) in files {
+        let outcome =
+            ingest_pdf(&app_state, tenant.organization_id, &chatbot, &file_name, file_data, chunking_strategy, overwrite)
+                .await;
+        // A single upload keeps failing the request, as it always has
+        if !batch && let Err((status, _)) = outcome {
+            return Err(status);
+        }
+        if let Ok(upload) = &outcome {
+            embedding_count += upload.embedding_count;
+        }
+        results.push((file_name, outcome));
     }
+    let succeeded = results.iter().filter(|(_, outcome)| outcome.is_ok()).count();
+
+    // Let every replica know this chatbot's knowledge changed
+    if succeeded > 0
+        && let Err(e) = publish(
+            &app_state.db,
+            &app_state.chatbot_cache,
+            CacheEvent::KnowledgeVersionChanged { chatbot_id },
+        ).await
+    {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    if !batch && let Some((file_name, Ok(upload))) = results.pop() {
+        tracing::info!("✅ PDF upload and processing completed successfully");
+        return Ok(Json(json!({
+            "success": true,
+            "message": "PDF uploaded and processed successfully",
+            "data": {
+                "chatbot_id": chatbot_id,
+                "file_name": file_name,
+                "ingestion_id": upload.ingestion_id,
+                "document_id": upload.document_id,
+                "replaced_document_id": upload.replaced_document_id,
+                "embedding_count": upload.embedding_count,
+                "chunking_strategy": chunking_strategy.as_str(),
+                "note": "PDF processed using Candle ML framework"
+            }
+        })));
+    }
+
+    tracing::info!("✅ Processed {} of {} PDFs for chatbot {}", succeeded, results.len(), chatbot_id);
+    let files: Vec<Value> = results
+        .into_iter()
+        .map(|(file_name, outcome)| match outcome {
+            Ok(upload) => json!({
+                "file_name": file_name,
+                "success": true,
+                "ingestion_id": upload.ingestion_id,
+                "document_id": upload.document_id,
+                "replaced_document_id": upload.replaced_document_id,
+                "embedding_count": upload.embedding_count
+            }),
+            Err((status, error)) => json!({
+                "file_name": file_name,
+                "success": false,
+                "status": status.as_u16(),
+                "error": error
+            }),
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "success": succeeded > 0,
+        "message": format!("Processed {} of {} PDFs", succeeded, files.len()),
+        "data": {
+            "chatbot_id": chatbot_id,
+            "embedding_count": embedding_count,
+            "succeeded": succeeded,
+            "failed": files.len() - succeeded,
+            "chunking_strategy": chunking_strategy.as_str(),
+            "files": files
+        }
+    })))
+}
+
+// What ingesting one uploaded PDF produced
+struct PdfUpload {
+    ingestion_id: Uuid,
+    document_id: Option<Uuid>,
+    replaced_document_id: Option<Uuid>,
+    embedding_count: usize,
+}
+
+// Check one uploaded PDF against earlier uploads, log it, then chunk, embed and record it. Errors
+// carry the status a single upload fails with and a reason safe to show the caller
+async fn ingest_pdf(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    file_name: &str,
+    file_data: Vec<u8>,
+    chunking_strategy: ChunkingStrategy,
+    overwrite: bool,
+) -> Result<PdfUpload, (StatusCode, &'static str)> {
+    // The same PDF uploaded again would index every chunk twice
+    let duplicate = match find_document_by_hash(&app_state.db, chatbot.id, &content_hash(&file_data)).await {
+        Ok(Some(existing)) if !overwrite => {
+            tracing::warn!("⚠️ {} has the same content as {}, rejecting upload", file_name, existing.file_name);
+            return Err((StatusCode::CONFLICT, "A PDF with the same content was already uploaded"));
+        }
+        Ok(existing) => existing,
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error"));
+        }
+    };
+
+    let temp_file_path = std::env::temp_dir().join(format!("{}_{}", chatbot.id, file_name));
+    let document = new_document(
+        organization_id,
+        chatbot.id,
+        temp_file_path.to_string_lossy().to_string(),
+        file_name.to_string(),
+        PDF_CONTENT_TYPE,
+        &file_data,
+    );
+
+    // Log the upload before any side effect, so if the server stops part way the recovery task
+    // finds it and finishes it
+    let ingestion = create_ingestion(&app_state.db, &document, chunking_strategy.as_str()).await.map_err(|e| {
+        tracing::error!("❌ Failed to log upload of {}: {}", file_name, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to log the upload")
+    })?;
+
+    let outcome = run_ingestion(app_state, chatbot, &ingestion, file_data, duplicate, chunking_strategy).await;
+    if let Err((_, reason)) = &outcome
+        && let Err(e) = fail_ingestion(&app_state.db, ingestion.id, reason).await
+    {
+        tracing::warn!("⚠️ Failed to record failed upload {}: {}", ingestion.id, e);
+    }
+    outcome
+}
+
+// The side effects of a logged upload, each recorded in the log once it is done
+async fn run_ingestion(
+    app_state: &AppState,
+    chatbot: &ChatBot,
+    ingestion: &IngestionLogEntry,
+    file_data: Vec<u8>,
+    duplicate: Option<Document>,
+    chunking_strategy: ChunkingStrategy,
+) -> Result<PdfUpload, (StatusCode, &'static str)> {
+    let replaced_document_id = match duplicate {
+        Some(existing) => {
+            remove_document(app_state, chatbot, &existing).await.map_err(|e| {
+                tracing::error!("❌ Failed to remove duplicate {}: {}", existing.file_path, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to replace the earlier upload")
+            })?;
+            Some(existing.id)
+        }
+        None => None,
+    };
 
-    // Create temporary file
-    let temp_dir = std::env::temp_dir();
-    let temp_file_path = temp_dir.join(format!("{}_{}", chatbot_id, file_name));
-    
     // Write file to temp location
+    let temp_file_path = PathBuf::from(&ingestion.file_path);
     fs::write(&temp_file_path, &file_data).await.map_err(|e| {
         tracing::error!("Failed to write temp file: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save the file")
     })?;
 
     tracing::info!("File saved to temp location: {:?}", temp_file_path);
 
+    // Keep the original before indexing, so an interrupted upload can be replayed from it. An upload
+    // whose original couldn't be saved is indexed but not recorded, as before
+    let original_saved = match app_state.document_store.as_ref() {
+        Some(store) => match store.put(&ingestion.storage_key, file_data).await {
+            Ok(()) => {
+                advance_ingestion(app_state, ingestion.id, IngestionStage::Stored).await;
+                true
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ Failed to store original of {}: {}", ingestion.file_path, e);
+                false
+            }
+        },
+        None => true,
+    };
+
     // Process PDF and create embeddings using Candle
-    let embedding_count = match process_pdf_and_create_embeddings(&app_state, chatbot_id, &temp_file_path, &file_name).await {
+    let embedding_count = match process_pdf_and_create_embeddings(
+        app_state,
+        ingestion.organization_id,
+        chatbot,
+        &temp_file_path,
+        &ingestion.file_name,
+        chunking_strategy,
+    ).await {
         Ok(count) => {
             tracing::info!("✅ Successfully processed PDF and created {} embeddings", count);
             count
         }
         Err(e) => {
-            tracing::error!("❌ Failed to process PDF: {}", e);
+            tracing::error!("❌ Failed to process PDF {}: {}", ingestion.file_name, e);
             // Clean up temp file
             let _ = fs::remove_file(&temp_file_path).await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to process the PDF"));
         }
     };
 
     // Clean up temp file
     let _ = fs::remove_file(&temp_file_path).await;
-    
-    tracing::info!("✅ PDF upload and processing completed successfully");
-    
-    Ok(Json(json!({
-        "success": true,
-        "message": "PDF uploaded and processed successfully",
-        "data": {
-            "chatbot_id": chatbot_id,
-            "file_name": file_name,
-            "embedding_count": embedding_count,
-            "note": "PDF processed using Candle ML framework"
-        }
-    })))
+    advance_ingestion(app_state, ingestion.id, IngestionStage::Indexed).await;
+
+    let document_id = if original_saved {
+        save_document(app_state, &document_of(ingestion)).await
+    } else {
+        None
+    };
+
+    // A fresh upload is hot and replaces any cold copy of the same document
+    if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &ingestion.file_path).await {
+        tracing::warn!("⚠️ Failed to record document usage: {}", e);
+    }
+    if let Err(e) = complete_ingestion(&app_state.db, ingestion.id, embedding_count as i64, document_id).await {
+        tracing::warn!("⚠️ Failed to record completed upload {}: {}", ingestion.id, e);
+    }
+
+    Ok(PdfUpload { ingestion_id: ingestion.id, document_id, replaced_document_id, embedding_count })
 }
 
-// Process PDF file and create embeddings in Qdrant
+// Record that an upload reached a stage. A failure only means a replay would redo more work
+async fn advance_ingestion(app_state: &AppState, ingestion_id: Uuid, stage: IngestionStage) {
+    if let Err(e) = set_ingestion_stage(&app_state.db, ingestion_id, stage.as_str()).await {
+        tracing::warn!("⚠️ Failed to record upload {} as {}: {}", ingestion_id, stage.as_str(), e);
+    }
+}
+
+// Process PDF file and create embeddings in the chatbot's shard for this document
 async fn process_pdf_and_create_embeddings(
     app_state: &AppState,
-    chatbot_id: Uuid,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
     file_path: &PathBuf,
-    _file_name: &str,
+    file_name: &str,
+    chunking_strategy: ChunkingStrategy,
 ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
-    tracing::info!("Starting PDF processing for chatbot: {}", chatbot_id);
+    tracing::info!("Starting PDF processing for chatbot: {}", chatbot.id);
 
     // Create embedding service
-    let embedding_service = EmbeddingService::new(app_state.elasticsearch.clone())?;
-    
-    // Create collection name for this chatbot
-    let collection_name = format!("chatbot_{}", chatbot_id);
-    
-    // Ensure collection exists
-    embedding_service.create_collection_if_not_exists(&collection_name).await?;
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())?;
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(&app_state.db, &app_state.chatbot_cache, organization_id, chatbot, file_name)
+        .await?;
     
     // Process PDF and create embeddings
-    let embedding_count = embedding_service.process_pdf_file(file_path, &collection_name).await?;
+    let webhook = IngestWebhook::for_chatbot(chatbot);
+    let embedding_count = embedding_service
+        .process_pdf_file(file_path, &collection_name, webhook.as_ref(), &app_state.plugins, chunking_strategy)
+        .await?;
     
     tracing::info!("Created {} embeddings for chatbot {} in collection {}", 
-                   embedding_count, chatbot_id, collection_name);
+                   embedding_count, chatbot.id, collection_name);
     
     Ok(embedding_count)
 }
 
-// Test endpoint to debug multipart
-pub async fn test_upload_handler(
-    State(_app_state): State<AppState>,
-    mut multipart: Multipart,
-) -> Result<Json<Value>, StatusCode> {
-    tracing::info!("Testing multipart upload");
-    
-    let mut fields_received = Vec::new();
-    
-    while let Some(field) = multipart.next_field().await.map_err(|e| {
-        tracing::error!("Failed to read multipart field: {}", e);
+// Chunks written while a reindex copies the chatbot's index would be lost at the swap, so writes
+// are turned away with 409 until it finishes
+async fn ensure_not_reindexing(app_state: &AppState, chatbot_id: Uuid) -> Result<(), StatusCode> {
+    match is_reindex_running(&app_state.db, chatbot_id, stale_after_secs()).await {
+        Ok(false) => Ok(()),
+        Ok(true) => {
+            tracing::warn!("⚠️ Chatbot {} is being reindexed, rejecting write", chatbot_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Parse a chunking strategy given by the caller, rejecting unknown ones with 400
+fn parse_chunking_strategy(value: &str) -> Result<ChunkingStrategy, StatusCode> {
+    value.parse().map_err(|e| {
+        tracing::error!("{}", e);
         StatusCode::BAD_REQUEST
-    })? {
-        let field_name = field.name().unwrap_or("unknown").to_string();
-        tracing::info!("Received field: {}", field_name);
-        
-        if field_name == "chatbot_id" {
-            let text = field.text().await.map_err(|e| {
-                tracing::error!("Failed to read chatbot_id text: {}", e);
-                StatusCode::BAD_REQUEST
-            })?;
-            fields_received.push(format!("chatbot_id: {}", text));
-        } else if field_name == "file" {
-            let file_name = field.file_name().unwrap_or("unknown").to_string();
-            let bytes = field.bytes().await.map_err(|e| {
-                tracing::error!("Failed to read file bytes: {}", e);
-                StatusCode::BAD_REQUEST
-            })?;
-            fields_received.push(format!("file: {} ({} bytes)", file_name, bytes.len()));
+    })
+}
+
+// Drop an earlier upload's chunks, original and record, so a duplicate can take its place
+async fn remove_document(
+    app_state: &AppState,
+    chatbot: &ChatBot,
+    document: &Document,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let index_names = shard_indices(&chatbot_index_name(document.organization_id, chatbot.id), chatbot.shard_count);
+    app_state.vector_store.delete_document_chunks(&index_names, &document.file_path).await?;
+    delete_document(&app_state.db, document.id).await?;
+
+    if let Some(store) = app_state.document_store.as_ref()
+        && let Err(e) = store.delete(&document.storage_key).await
+    {
+        tracing::warn!("⚠️ Failed to delete original of {}: {}", document.file_path, e);
+    }
+    if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &document.file_path).await {
+        tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", document.file_path, e);
+    }
+    if let Err(e) = delete_document_usage(&app_state.db, chatbot.id, &document.file_path).await {
+        tracing::warn!("⚠️ Failed to delete document usage of {}: {}", document.file_path, e);
+    }
+    Ok(())
+}
+
+// Record an upload with its content hash, keeping the original so it can be reprocessed later
+// when the document store is enabled. Returns the document id, or None when saving failed; the
+// upload itself has already succeeded
+async fn record_document(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    file_path: String,
+    file_name: String,
+    content_type: &str,
+    data: Vec<u8>,
+) -> Option<Uuid> {
+    let document = new_document(organization_id, chatbot_id, file_path, file_name, content_type, &data);
+    if let Some(store) = app_state.document_store.as_ref()
+        && let Err(e) = store.put(&document.storage_key, data).await
+    {
+        tracing::warn!("⚠️ Failed to store original of {}: {}", document.file_path, e);
+        return None;
+    }
+
+    save_document(app_state, &document).await
+}
+
+// An upload to record, keyed in the document store by its chatbot and path
+fn new_document(
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    file_path: String,
+    file_name: String,
+    content_type: &str,
+    data: &[u8],
+) -> NewDocument {
+    NewDocument {
+        organization_id,
+        chatbot_id,
+        storage_key: storage_key(organization_id, chatbot_id, &file_path),
+        file_path,
+        file_name,
+        content_type: content_type.to_string(),
+        size_bytes: data.len() as i64,
+        sha256: content_hash(data),
+    }
+}
+
+// Record an upload whose original is saved. Returns the document id, or None when that failed
+async fn save_document(app_state: &AppState, document: &NewDocument) -> Option<Uuid> {
+    match upsert_document(&app_state.db, document).await {
+        Ok(document) => Some(document.id),
+        Err(e) => {
+            tracing::warn!("⚠️ Failed to record document {}: {}", document.file_path, e);
+            None
         }
     }
-    
-    Ok(Json(json!({
-        "success": true,
-        "message": "Test upload successful",
-        "fields_received": fields_received
-    })))
 }
 
-// Simple upload handler for testing
-pub async fn simple_upload_handler(
-    State(_app_state): State<AppState>,
+// Re-chunk and re-embed a stored PDF under its original path, replacing its chunks
+async fn reprocess_pdf(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    document: &Document,
+    data: Vec<u8>,
+    chunking_strategy: ChunkingStrategy,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let index_names = shard_indices(&chatbot_index_name(organization_id, chatbot.id), chatbot.shard_count);
+    app_state.vector_store.delete_document_chunks(&index_names, &document.file_path).await?;
+
+    // The pipeline names chunks after the file they were read from, so write it back to that path
+    let temp_file_path = PathBuf::from(&document.file_path);
+    if let Some(parent) = temp_file_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(&temp_file_path, &data).await?;
+    let embedding_count = process_pdf_and_create_embeddings(
+        app_state,
+        organization_id,
+        chatbot,
+        &temp_file_path,
+        &document.file_name,
+        chunking_strategy,
+    ).await;
+    let _ = fs::remove_file(&temp_file_path).await;
+    let embedding_count = embedding_count?;
+
+    if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &document.file_path).await {
+        tracing::warn!("⚠️ Failed to record document usage: {}", e);
+    }
+    publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id },
+    ).await?;
+
+    Ok(embedding_count)
+}
+
+// Counts from importing a batch of emails
+struct EmailImport {
+    messages: usize,
+    skipped: usize,
+    embedding_count: usize,
+}
+
+// Index each message as its own document. A mailbox's messages all go to the shard chosen by its
+// source name, replacing chunks there from an earlier import of the same message
+async fn import_emails(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    source: &str,
+    raw_messages: Vec<Vec<u8>>,
+) -> Result<EmailImport, Box<dyn std::error::Error + Send + Sync>> {
+    let total = raw_messages.len();
+    let messages = tokio::task::spawn_blocking(move || {
+        raw_messages.iter().filter_map(|raw| parse_message(raw)).collect::<Vec<_>>()
+    }).await?;
+    tracing::info!("Parsed {} of {} emails from {}", messages.len(), total, source);
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())?;
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(&app_state.db, &app_state.chatbot_cache, organization_id, chatbot, source)
+        .await?;
+    let webhook = IngestWebhook::for_chatbot(chatbot);
+
+    let mut embedding_count = 0;
+    for message in &messages {
+        let file_path = message.file_path(source);
+        app_state
+            .vector_store
+            .delete_document_chunks(std::slice::from_ref(&collection_name), &file_path)
+            .await?;
+        embedding_count += embedding_service
+            .index_chunks(&file_path, message.chunks(200, 50), &collection_name, webhook.as_ref())
+            .await?;
+
+        if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+    }
+
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    Ok(EmailImport { messages: messages.len(), skipped: total - messages.len(), embedding_count })
+}
+
+// Ingest an uploaded mbox file, one document per message
+#[utoipa::path(
+    post,
+    path = "/api/upload-mbox",
+    tag = "knowledge",
+    request_body(content = UploadMboxForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Messages parsed and embedded", body = Value),
+        (status = 400, description = "Missing or invalid form fields"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 413, description = "File is larger than MBOX_MAX_UPLOAD_MB"),
+        (status = 409, description = "Chatbot is being reindexed"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn upload_mbox_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
     mut multipart: Multipart,
 ) -> Result<Json<Value>, StatusCode> {
-    tracing::info!("Simple upload handler called");
-    
+    tracing::info!("Starting mbox upload");
+
     let mut chatbot_id: Option<Uuid> = None;
     let mut file_data: Option<Vec<u8>> = None;
     let mut file_name: Option<String> = None;
 
-    // Parse multipart form data
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         tracing::error!("Failed to read multipart field: {}", e);
-        StatusCode::BAD_REQUEST
+        e.status()
     })? {
         match field.name() {
             Some("chatbot_id") => {
@@ -217,7 +692,6 @@ pub async fn simple_upload_handler(
                     tracing::error!("Failed to read chatbot_id: {}", e);
                     StatusCode::BAD_REQUEST
                 })?;
-                
                 chatbot_id = Some(Uuid::parse_str(&chatbot_id_str).map_err(|e| {
                     tracing::error!("Invalid chatbot_id format: {}", e);
                     StatusCode::BAD_REQUEST
@@ -227,7 +701,7 @@ pub async fn simple_upload_handler(
                 file_name = field.file_name().map(|s| s.to_string());
                 file_data = Some(field.bytes().await.map_err(|e| {
                     tracing::error!("Failed to read file data: {}", e);
-                    StatusCode::BAD_REQUEST
+                    e.status()
                 })?.to_vec());
             }
             _ => {
@@ -236,37 +710,1392 @@ pub async fn simple_upload_handler(
         }
     }
 
-    // Validate required fields
     let chatbot_id = chatbot_id.ok_or_else(|| {
         tracing::error!("Missing chatbot_id in request");
         StatusCode::BAD_REQUEST
     })?;
-
     let file_data = file_data.ok_or_else(|| {
         tracing::error!("Missing file in request");
         StatusCode::BAD_REQUEST
     })?;
+    let file_name = file_name.unwrap_or_else(|| "mailbox.mbox".to_string());
 
-    let file_name = file_name.unwrap_or_else(|| "unknown.pdf".to_string());
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
 
-    tracing::info!("✅ Simple upload successful - chatbot: {}, file: {} ({} bytes)", 
-                   chatbot_id, file_name, file_data.len());
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let raw_messages = split_mbox(&file_data);
+    if raw_messages.is_empty() {
+        tracing::error!("No messages found in {}", file_name);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let import = import_emails(&app_state, tenant.organization_id, &chatbot, &file_name, raw_messages)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to import mbox: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let document_id = record_document(
+        &app_state,
+        tenant.organization_id,
+        chatbot_id,
+        mailbox_path(&file_name),
+        file_name.clone(),
+        MBOX_CONTENT_TYPE,
+        file_data,
+    ).await;
 
+    tracing::info!("✅ Imported {} emails from {}", import.messages, file_name);
     Ok(Json(json!({
         "success": true,
-        "message": "Simple upload successful",
+        "message": "Mailbox imported successfully",
         "data": {
             "chatbot_id": chatbot_id,
-            "file_name": file_name,
-            "file_size": file_data.len()
+            "source": file_name,
+            "document_id": document_id,
+            "messages": import.messages,
+            "skipped": import.skipped,
+            "embedding_count": import.embedding_count
         }
     })))
 }
 
+// Index each mapped record as its own document, replacing chunks from an earlier import of the
+// same record. An import's records all go to the shard chosen by its source
+async fn import_json_records(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    source: &str,
+    records: &[JsonRecord],
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())?;
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(&app_state.db, &app_state.chatbot_cache, organization_id, chatbot, source)
+        .await?;
+    let webhook = IngestWebhook::for_chatbot(chatbot);
+
+    let mut embedding_count = 0;
+    for record in records {
+        let file_path = record.file_path(source);
+        app_state
+            .vector_store
+            .delete_document_chunks(std::slice::from_ref(&collection_name), &file_path)
+            .await?;
+        embedding_count += embedding_service
+            .index_chunks(&file_path, record.chunks(200, 50), &collection_name, webhook.as_ref())
+            .await?;
+
+        if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+    }
+
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    Ok(embedding_count)
+}
+
+// Ingest a JSON or JSONL export such as an FAQ database or CMS dump, one document per record,
+// with a mapping saying which fields are content and which are metadata
+#[utoipa::path(
+    post,
+    path = "/api/knowledge/json",
+    tag = "knowledge",
+    request_body = JsonImportRequest,
+    responses(
+        (status = 200, description = "Records mapped and embedded", body = Value),
+        (status = 400, description = "Invalid mapping, no records, invalid JSONL or no record has content"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Chatbot is being reindexed"),
+        (status = 413, description = "Payload is larger than JSON_MAX_UPLOAD_MB"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn import_json_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<JsonImportRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let chatbot_id = payload.chatbot_id;
+    let source = payload.source.trim().to_string();
+    tracing::info!("Starting JSON import of {} for chatbot: {}", source, chatbot_id);
+
+    if source.is_empty() {
+        tracing::error!("JSON import needs a source name");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = validate_mapping(&payload.mapping) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Keep the payload as it was sent, to store as the import's original
+    let (records, original, content_type) = match (payload.records, payload.jsonl) {
+        (Some(records), _) => {
+            let original = serde_json::to_vec(&records).map_err(|e| {
+                tracing::error!("❌ Failed to serialize records: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            (records, original, JSON_CONTENT_TYPE)
+        }
+        (None, Some(jsonl)) => {
+            let records = parse_jsonl(&jsonl).map_err(|e| {
+                tracing::error!("Invalid JSONL: {}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+            (records, jsonl.into_bytes(), JSONL_CONTENT_TYPE)
+        }
+        (None, None) => {
+            tracing::error!("JSON import needs records or jsonl");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    let (records_mapped, skipped) = map_records(&records, &payload.mapping);
+    if records_mapped.is_empty() {
+        tracing::error!("None of the {} records has a value in the content fields", records.len());
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let embedding_count = import_json_records(&app_state, tenant.organization_id, &chatbot, &source, &records_mapped)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to import JSON records: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let extension = if content_type == JSONL_CONTENT_TYPE { "jsonl" } else { "json" };
+    let document_id = record_document(
+        &app_state,
+        tenant.organization_id,
+        chatbot_id,
+        json_source_path(&source),
+        format!("{}.{}", source, extension),
+        content_type,
+        original,
+    ).await;
+
+    tracing::info!("✅ Imported {} JSON records from {}", records_mapped.len(), source);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Records imported successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "source": source,
+            "document_id": document_id,
+            "records": records_mapped.len(),
+            "skipped": skipped,
+            "embedding_count": embedding_count
+        }
+    })))
+}
+
+// Store chunks embedded by an external pipeline as they are, replacing the document's earlier
+// chunks. Embeddings must have the dimensions of the chatbot's index; the ingest webhook isn't
+// applied, since changed text would no longer match its embedding
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/vectors",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = VectorUpsertRequest,
+    responses(
+        (status = 200, description = "Chunks stored", body = Value),
+        (status = 400, description = "Blank path, no or too many chunks, empty text or wrong dimensions"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "Chatbot is being reindexed"),
+        (status = 413, description = "Payload is larger than JSON_MAX_UPLOAD_MB"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn upsert_vectors_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<VectorUpsertRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let file_path = payload.file_path.trim().to_string();
+    tracing::info!("Upserting {} raw vectors of {} for chatbot: {}", payload.chunks.len(), file_path, chatbot_id);
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
+        tracing::error!("Failed to create embedding service: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if let Err(e) = validate_chunks(&file_path, &payload.chunks, embedding_service.embedding_dim()) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(&app_state.db, &app_state.chatbot_cache, tenant.organization_id, &chatbot, &file_path)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to prepare collection: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let index_names = shard_indices(&chatbot_index_name(tenant.organization_id, chatbot_id), chatbot.shard_count);
+    let replaced = app_state.vector_store.delete_document_chunks(&index_names, &file_path).await.map_err(|e| {
+        tracing::error!("❌ Failed to delete earlier chunks of {}: {}", file_path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let embedding_count = app_state
+        .vector_store
+        .index_documents(&collection_name, to_documents(&file_path, payload.chunks))
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to store vectors: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if let Err(e) = remove_cold_document(&app_state.db, chatbot_id, &file_path).await {
+        tracing::warn!("⚠️ Failed to record document usage: {}", e);
+    }
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    tracing::info!("✅ Stored {} raw vectors of {} in '{}'", embedding_count, file_path, collection_name);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Vectors stored successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "file_path": file_path,
+            "replaced": replaced,
+            "embedding_count": embedding_count
+        }
+    })))
+}
+
+// Fetch the newest messages from an IMAP folder and ingest them, one document per message.
+// Credentials are used for this import only and are not stored
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/imap-import",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = ImapImportRequest,
+    responses(
+        (status = 200, description = "Messages fetched and embedded", body = Value),
+        (status = 400, description = "Missing host or credentials, or max_messages out of range"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 501, description = "Server was built without the imap feature"),
+        (status = 502, description = "Could not connect, log in or fetch from the IMAP server"),
+        (status = 409, description = "Chatbot is being reindexed"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn import_imap_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<ImapImportRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Starting IMAP import for chatbot: {}", chatbot_id);
+
+    if !cfg!(feature = "imap") {
+        tracing::error!("IMAP import requested but this build lacks the imap feature");
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    }
+
+    let host = payload.host.trim().to_string();
+    if host.is_empty() || payload.username.is_empty() || payload.password.is_empty() {
+        tracing::error!("IMAP import needs a host, username and password");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut source = ImapSource::new(host, payload.username, payload.password);
+    if let Some(port) = payload.port {
+        source.port = port;
+    }
+    if let Some(folder) = payload.folder.filter(|folder| !folder.trim().is_empty()) {
+        source.folder = folder;
+    }
+    if let Some(max_messages) = payload.max_messages {
+        if !(1..=MAX_IMAP_MESSAGES).contains(&max_messages) {
+            tracing::error!("max_messages must be between 1 and {}", MAX_IMAP_MESSAGES);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        source.max_messages = max_messages;
+    }
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let raw_messages = source.fetch().await.map_err(|e| {
+        tracing::error!("❌ Failed to fetch from IMAP folder {}: {}", source.name(), e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let import = import_emails(&app_state, tenant.organization_id, &chatbot, &source.name(), raw_messages)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to import IMAP folder: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!("✅ Imported {} emails from {}", import.messages, source.name());
+    Ok(Json(json!({
+        "success": true,
+        "message": "IMAP folder imported successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "source": source.name(),
+            "messages": import.messages,
+            "skipped": import.skipped,
+            "embedding_count": import.embedding_count
+        }
+    })))
+}
+
+// List a chatbot's documents that were moved to cold storage
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/cold-documents",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Documents in cold storage, most recently archived first", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_cold_documents_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Listing cold documents for chatbot: {}", chatbot_id);
+
+    match list_cold_documents(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(documents) => {
+            tracing::info!("✅ Retrieved {} cold documents", documents.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Cold documents retrieved successfully",
+                "data": documents,
+                "count": documents.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to list cold documents: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Move a cold document back into Elasticsearch so it can be retrieved again
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/cold-documents/rehydrate",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = RehydrateDocumentRequest,
+    responses(
+        (status = 200, description = "Document reindexed", body = Value),
+        (status = 404, description = "Document is not in cold storage"),
+        (status = 409, description = "Chatbot is being reindexed"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn rehydrate_document_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<RehydrateDocumentRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Rehydrating {} for chatbot: {}", payload.file_path, chatbot_id);
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    match rehydrate_document(
+        &app_state.db,
+        &app_state.vector_store,
+        &app_state.chatbot_cache,
+        tenant.organization_id,
+        chatbot_id,
+        &payload.file_path,
+    ).await {
+        Ok(Some(chunk_count)) => Ok(Json(json!({
+            "success": true,
+            "message": "Document rehydrated successfully",
+            "data": {
+                "chatbot_id": chatbot_id,
+                "file_path": payload.file_path,
+                "chunk_count": chunk_count
+            }
+        }))),
+        Ok(None) => {
+            tracing::error!("Cold document not found: {}", payload.file_path);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to rehydrate document: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List a chatbot's stored originals
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/documents",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Stored originals, most recently uploaded first", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_documents_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut documents = list_documents(&app_state.db, tenant.organization_id, chatbot_id).await.map_err(|e| {
+        tracing::error!("❌ Failed to list documents: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    for document in &mut documents {
+        add_download_url(&app_state, document).await;
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Documents retrieved successfully",
+        "data": documents,
+        "count": documents.len()
+    })))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChunkListingQuery {
+    /// Only list this document's chunks
+    pub file_path: Option<String>,
+    /// "json" (default) or "ndjson", one chunk per line
+    pub format: Option<String>,
+}
+
+// Stream a chatbot's indexed chunks, shard by shard, a page at a time
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/chunks",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id"), ChunkListingQuery),
+    responses(
+        (status = 200, description = "Indexed chunks without embeddings, streamed as they are read", content(
+            (Value = "application/json"),
+            (String = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Unknown format"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_chunks_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(query): Query<ChunkListingQuery>,
+) -> Result<Response, StatusCode> {
+    let format: ListingFormat = query.format.as_deref().unwrap_or("json").parse().map_err(|e| {
+        tracing::error!("{}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let index_names = Arc::new(shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot.id),
+        chatbot.shard_count,
+    ));
+
+    // Page through each shard in turn; the state is the shard being read and the cursor within it
+    let vector_store = app_state.vector_store.clone();
+    let pages = stream::try_unfold((0, None), move |(shard, cursor): (usize, Option<String>)| {
+        let (vector_store, index_names) = (vector_store.clone(), index_names.clone());
+        async move {
+            let Some(index_name) = index_names.get(shard) else {
+                return Ok(None);
+            };
+            let page = vector_store.scroll_chunks(index_name, cursor.as_deref(), CHUNK_PAGE_SIZE).await?;
+            let next = match page.cursor {
+                Some(cursor) => (shard, Some(cursor)),
+                None => (shard + 1, None),
+            };
+            Ok::<_, anyhow::Error>(Some((stream::iter(page.chunks.into_iter().map(Ok::<_, anyhow::Error>)), next)))
+        }
+    });
+    let file_path = query.file_path;
+    let chunks = pages
+        .try_flatten()
+        .try_filter(move |chunk| ready(file_path.as_ref().is_none_or(|file_path| *file_path == chunk.file_path)))
+        .map_ok(|chunk| {
+            json!({
+                "id": chunk.id,
+                "file_path": chunk.file_path,
+                "chunk_index": chunk.chunk_index,
+                "chunk_count": chunk.chunk_count,
+                "parent_index": chunk.parent_index,
+                "text": chunk.text
+            })
+        });
+
+    tracing::info!("✅ Streaming chunks of chatbot: {}", chatbot_id);
+    let mut fields = serde_json::Map::new();
+    fields.insert("chatbot_id".to_string(), json!(chatbot_id));
+    let listing = StreamedListing { format, message: "Chunks retrieved successfully", fields, items_key: "chunks" };
+    Ok(listing.into_response(chunks))
+}
+
+// The chatbot's most recent uploads from the ingestion log, showing how far each got
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/ingestions",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The last 100 uploads, most recent first", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_ingestions_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let ingestions = list_ingestions(&app_state.db, tenant.organization_id, chatbot_id, MAX_LISTED_INGESTIONS)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to list uploads: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Uploads retrieved successfully",
+        "data": ingestions,
+        "count": ingestions.len()
+    })))
+}
+
+// Get one stored original, with a presigned download link on S3-compatible stores
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Document id")),
+    responses(
+        (status = 200, description = "The document", body = Value),
+        (status = 404, description = "Document not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_document_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(document_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut document = match get_document(&app_state.db, tenant.organization_id, document_id).await {
+        Ok(Some(document)) => document,
+        Ok(None) => {
+            tracing::error!("Document not found: {}", document_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    add_download_url(&app_state, &mut document).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Document retrieved successfully",
+        "data": document
+    })))
+}
+
+// Sign a download link for the document's original. Signing is local, so this costs no request
+async fn add_download_url(app_state: &AppState, document: &mut Document) {
+    let Some(store) = app_state.document_store.as_ref() else {
+        return;
+    };
+    match store.presigned_url(&document.storage_key).await {
+        Ok(url) => document.download_url = url,
+        Err(e) => tracing::warn!("⚠️ Failed to sign download URL for document {}: {}", document.id, e),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReprocessQuery {
+    /// "fixed" or "semantic" for PDFs; defaults to `CHUNKING_STRATEGY`
+    pub chunking_strategy: Option<String>,
+}
+
+// Re-chunk and re-embed a document from its stored original with the chatbot's current pipeline,
+// replacing its chunks, without the user uploading it again
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}/reprocess",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Document id"), ReprocessQuery),
+    responses(
+        (status = 200, description = "Document reprocessed", body = Value),
+        (status = 400, description = "Document type cannot be reprocessed or unknown chunking strategy"),
+        (status = 404, description = "Document or its chatbot not found"),
+        (status = 409, description = "Chatbot is being reindexed"),
+        (status = 501, description = "The document store is disabled"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn reprocess_document_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(document_id): Path<Uuid>,
+    Query(query): Query<ReprocessQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Reprocessing document: {}", document_id);
+
+    let chunking_strategy = match query.chunking_strategy.as_deref() {
+        Some(value) => parse_chunking_strategy(value)?,
+        None => ChunkingStrategy::from_env(),
+    };
+
+    let Some(store) = app_state.document_store.as_ref() else {
+        tracing::error!("Reprocessing requested but the document store is disabled");
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    };
+
+    let document = match get_document(&app_state.db, tenant.organization_id, document_id).await {
+        Ok(Some(document)) => document,
+        Ok(None) => {
+            tracing::error!("Document not found: {}", document_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, document.chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", document.chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot.id).await?;
+
+    let data = store.get(&document.storage_key).await.map_err(|e| {
+        tracing::error!("❌ Failed to load original of {}: {}", document.file_path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let embedding_count = match document.content_type.as_str() {
+        PDF_CONTENT_TYPE => {
+            reprocess_pdf(&app_state, tenant.organization_id, &chatbot, &document, data, chunking_strategy).await
+        }
+        MBOX_CONTENT_TYPE => {
+            import_emails(&app_state, tenant.organization_id, &chatbot, &document.file_name, split_mbox(&data))
+                .await
+                .map(|import| import.embedding_count)
+        }
+        other => {
+            tracing::error!("Cannot reprocess documents of type {}", other);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    .map_err(|e| {
+        tracing::error!("❌ Failed to reprocess {}: {}", document.file_path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ Reprocessed {} into {} embeddings", document.file_path, embedding_count);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Document reprocessed successfully",
+        "data": {
+            "document_id": document.id,
+            "chatbot_id": chatbot.id,
+            "file_path": document.file_path,
+            "embedding_count": embedding_count
+        }
+    })))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HelpCenterSyncQuery {
+    /// Re-read every article and drop those that were deleted, instead of only the ones changed
+    /// since the last sync
+    pub full: Option<bool>,
+}
+
+// Counts from syncing a help center
+struct HelpCenterSync {
+    updated: usize,
+    removed: usize,
+    embedding_count: usize,
+}
+
+// Index each changed article as its own document and drop unpublished ones. A full sync also drops
+// articles indexed earlier that the help center no longer has
+async fn sync_help_center(
+    app_state: &AppState,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    source: &HelpCenterSource,
+    changes: ArticleChanges,
+    full: bool,
+) -> Result<HelpCenterSync, Box<dyn std::error::Error + Send + Sync>> {
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone())?;
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(&app_state.db, &app_state.chatbot_cache, organization_id, chatbot, &source.name())
+        .await?;
+    let index_names = shard_indices(&chatbot_index_name(organization_id, chatbot.id), chatbot.shard_count);
+    let webhook = IngestWebhook::for_chatbot(chatbot);
+
+    let mut embedding_count = 0;
+    let mut published = HashSet::new();
+    for article in &changes.published {
+        let file_path = article_path(source.provider, &article.id);
+        app_state.vector_store.delete_document_chunks(&index_names, &file_path).await?;
+        embedding_count += embedding_service
+            .index_chunks(&file_path, article.chunks(200, 50), &collection_name, webhook.as_ref())
+            .await?;
+        record_help_center_article(&app_state.db, chatbot.id, &file_path).await?;
+
+        if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+        published.insert(file_path);
+    }
+
+    let mut removed: Vec<String> = changes
+        .unpublished
+        .iter()
+        .map(|id| article_path(source.provider, id))
+        .collect();
+    if full {
+        let indexed = list_help_center_article_paths(&app_state.db, chatbot.id).await?;
+        removed.extend(indexed.into_iter().filter(|path| !published.contains(path)));
+    }
+    removed.sort();
+    removed.dedup();
+    for file_path in &removed {
+        app_state.vector_store.delete_document_chunks(&index_names, file_path).await?;
+        delete_help_center_article(&app_state.db, chatbot.id, file_path).await?;
+
+        // Drop any cold copy too, so the article can't be rehydrated
+        if let Err(e) = remove_cold_document(&app_state.db, chatbot.id, file_path).await {
+            tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", file_path, e);
+        }
+        if let Err(e) = delete_document_usage(&app_state.db, chatbot.id, file_path).await {
+            tracing::warn!("⚠️ Failed to delete document usage of {}: {}", file_path, e);
+        }
+    }
+
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    Ok(HelpCenterSync { updated: published.len(), removed: removed.len(), embedding_count })
+}
+
+// Connect a chatbot to a Zendesk or Intercom help center. Changing the settings makes the next sync
+// a full one
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/help-center",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpsertHelpCenterConnectorRequest,
+    responses(
+        (status = 200, description = "Connector saved", body = Value),
+        (status = 400, description = "Unknown provider or missing credentials"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_help_center_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpsertHelpCenterConnectorRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating help-center connector for chatbot: {}", chatbot_id);
+
+    let provider = payload.provider.trim().to_lowercase();
+    let Some(provider) = HelpCenterProvider::parse(&provider) else {
+        tracing::error!("Unknown help-center provider: {}", payload.provider);
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let api_token = payload.api_token.trim().to_string();
+    if api_token.is_empty() {
+        tracing::error!("Help-center connector needs an API token");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let request = match provider {
+        HelpCenterProvider::Zendesk => {
+            let subdomain = payload.subdomain.map(|subdomain| subdomain.trim().to_lowercase());
+            let email = payload.email.map(|email| email.trim().to_string()).filter(|email| !email.is_empty());
+            if !subdomain.as_deref().is_some_and(is_valid_subdomain) || email.is_none() {
+                tracing::error!("Zendesk connector needs a valid subdomain and an email");
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            UpsertHelpCenterConnectorRequest { provider: provider.as_str().to_string(), subdomain, email, api_token }
+        }
+        HelpCenterProvider::Intercom => UpsertHelpCenterConnectorRequest {
+            provider: provider.as_str().to_string(),
+            subdomain: None,
+            email: None,
+            api_token,
+        },
+    };
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match upsert_help_center_connector(&app_state.db, tenant.organization_id, chatbot_id, &request).await {
+        Ok(connector) => {
+            tracing::info!("✅ Help-center connector saved for chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Help-center connector saved successfully",
+                "data": connector
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save help-center connector: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Get a chatbot's help-center connector, without its token
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/help-center",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's connector", body = Value),
+        (status = 404, description = "Chatbot has no connector"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_help_center_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_help_center_connector(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(connector)) => Ok(Json(json!({
+            "success": true,
+            "message": "Help-center connector retrieved successfully",
+            "data": connector
+        }))),
+        Ok(None) => {
+            tracing::error!("No help-center connector for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Import the articles changed since the last sync, or all of them on the first sync or with
+// `full=true`
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/help-center/sync",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id"), HelpCenterSyncQuery),
+    responses(
+        (status = 200, description = "Help center synced", body = Value),
+        (status = 404, description = "Chatbot not found or has no connector"),
+        (status = 409, description = "Chatbot is being reindexed"),
+        (status = 502, description = "The help-center API failed or rejected the credentials"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn sync_help_center_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<HelpCenterSyncQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Syncing help center for chatbot: {}", chatbot_id);
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    ensure_not_reindexing(&app_state, chatbot_id).await?;
+
+    let connector = match get_help_center_connector(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(connector)) => connector,
+        Ok(None) => {
+            tracing::error!("No help-center connector for chatbot: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let Some(source) = HelpCenterSource::from_connector(&connector) else {
+        tracing::error!("❌ Unknown help-center provider: {}", connector.provider);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+
+    let since = connector.last_synced_at.filter(|_| !params.full.unwrap_or(false));
+    // Articles changed while this sync runs are picked up by the next one
+    let started_at = Utc::now();
+    let changes = source.fetch_changes(since).await.map_err(|e| {
+        tracing::error!("❌ Failed to fetch articles from {}: {}", source.name(), e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let sync = sync_help_center(&app_state, tenant.organization_id, &chatbot, &source, changes, since.is_none())
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to sync help center: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if let Err(e) = mark_help_center_synced(&app_state.db, connector.id, started_at).await {
+        tracing::error!("❌ Failed to record help-center sync: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    tracing::info!("✅ Synced {} articles from {}, removed {}", sync.updated, source.name(), sync.removed);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Help center synced successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "source": source.name(),
+            "full": since.is_none(),
+            "updated": sync.updated,
+            "removed": sync.removed,
+            "embedding_count": sync.embedding_count
+        }
+    })))
+}
+
+// Re-embed every chunk of a chatbot with the current embedding model into new versioned indices,
+// then swap the chatbot's aliases over to them. Runs in the background; poll the job for progress
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/reindex",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Reindex job started", body = Value),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "A reindex of this chatbot is already running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn start_reindex_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Starting reindex for chatbot: {}", chatbot_id);
+
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let job = match create_reindex_job(&app_state.db, tenant.organization_id, chatbot_id, stale_after_secs()).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            tracing::warn!("⚠️ Chatbot {} is already being reindexed", chatbot_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create reindex job: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    spawn_reindex(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        app_state.vector_store.clone(),
+        app_state.embedding_cache.clone(),
+        app_state.chatbot_cache.clone(),
+        job.clone(),
+        chatbot.shard_count,
+    );
+
+    tracing::info!("✅ Reindex job {} started for chatbot {}", job.id, chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Reindex started",
+        "data": job
+    })))
+}
+
+// A chatbot's reindex jobs, newest first
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/reindex-jobs",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Reindex jobs, newest first", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_reindex_jobs_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match list_reindex_jobs(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(jobs) => Ok(Json(json!({
+            "success": true,
+            "message": "Reindex jobs retrieved successfully",
+            "data": jobs,
+            "count": jobs.len()
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list reindex jobs: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Progress of one reindex job
+#[utoipa::path(
+    get,
+    path = "/api/reindex-jobs/{id}",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Reindex job id")),
+    responses(
+        (status = 200, description = "Job status and progress", body = Value),
+        (status = 404, description = "Job not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_reindex_job_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_reindex_job(&app_state.db, tenant.organization_id, job_id).await {
+        Ok(Some(job)) => Ok(Json(json!({
+            "success": true,
+            "message": "Reindex job retrieved successfully",
+            "data": job
+        }))),
+        Ok(None) => {
+            tracing::error!("Reindex job not found: {}", job_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get reindex job: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Test endpoint to debug multipart
+#[utoipa::path(
+    post,
+    path = "/api/test-upload",
+    tag = "knowledge",
+    request_body(content = UploadPdfForm, content_type = "multipart/form-data"),
+    responses((status = 200, description = "Echoes the received multipart fields", body = Value))
+)]
+pub async fn test_upload_handler(
+    State(_app_state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Testing multipart upload");
+    
+    let mut fields_received = Vec::new();
+    
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        tracing::error!("Failed to read multipart field: {}", e);
+        StatusCode::BAD_REQUEST
+    })? {
+        let field_name = field.name().unwrap_or("unknown").to_string();
+        tracing::info!("Received field: {}", field_name);
+        
+        if field_name == "chatbot_id" {
+            let text = field.text().await.map_err(|e| {
+                tracing::error!("Failed to read chatbot_id text: {}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+            fields_received.push(format!("chatbot_id: {}", text));
+        } else if field_name == "file" {
+            let file_name = field.file_name().unwrap_or("unknown").to_string();
+            let bytes = field.bytes().await.map_err(|e| {
+                tracing::error!("Failed to read file bytes: {}", e);
+                StatusCode::BAD_REQUEST
+            })?;
+            fields_received.push(format!("file: {} ({} bytes)", file_name, bytes.len()));
+        }
+    }
+    
+    Ok(Json(json!({
+        "success": true,
+        "message": "Test upload successful",
+        "fields_received": fields_received
+    })))
+}
+
+// Simple upload handler for testing
+#[utoipa::path(
+    post,
+    path = "/api/simple-upload",
+    tag = "knowledge",
+    request_body(content = UploadPdfForm, content_type = "multipart/form-data"),
+    responses((status = 200, description = "Validates the upload without processing it", body = Value))
+)]
+pub async fn simple_upload_handler(
+    State(_app_state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Simple upload handler called");
+    
+    let mut chatbot_id: Option<Uuid> = None;
+    let mut file_data: Option<Vec<u8>> = None;
+    let mut file_name: Option<String> = None;
+
+    // Parse multipart form data
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        tracing::error!("Failed to read multipart field: {}", e);
+        StatusCode::BAD_REQUEST
+    })? {
+        match field.name() {
+            Some("chatbot_id") => {
+                let chatbot_id_str = field.text().await.map_err(|e| {
+                    tracing::error!("Failed to read chatbot_id: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?;
+                
+                chatbot_id = Some(Uuid::parse_str(&chatbot_id_str).map_err(|e| {
+                    tracing::error!("Invalid chatbot_id format: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?);
+            }
+            Some("file") => {
+                file_name = field.file_name().map(|s| s.to_string());
+                file_data = Some(field.bytes().await.map_err(|e| {
+                    tracing::error!("Failed to read file data: {}", e);
+                    StatusCode::BAD_REQUEST
+                })?.to_vec());
+            }
+            _ => {
+                tracing::warn!("Unknown field: {:?}", field.name());
+            }
+        }
+    }
+
+    // Validate required fields
+    let chatbot_id = chatbot_id.ok_or_else(|| {
+        tracing::error!("Missing chatbot_id in request");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let file_data = file_data.ok_or_else(|| {
+        tracing::error!("Missing file in request");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let file_name = file_name.unwrap_or_else(|| "unknown.pdf".to_string());
+
+    tracing::info!("✅ Simple upload successful - chatbot: {}, file: {} ({} bytes)", 
+                   chatbot_id, file_name, file_data.len());
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Simple upload successful",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "file_name": file_name,
+            "file_size": file_data.len()
+        }
+    })))
+}
+
+// mbox archives are much larger than the default 2 MB body limit; `MBOX_MAX_UPLOAD_MB` (default 50)
+fn mbox_max_upload_bytes() -> usize {
+    std::env::var("MBOX_MAX_UPLOAD_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MBOX_MAX_UPLOAD_MB)
+        * 1024
+        * 1024
+}
+
+// FAQ and CMS exports are often larger than the default 2 MB body limit; `JSON_MAX_UPLOAD_MB`
+// (default 50)
+fn json_max_upload_bytes() -> usize {
+    std::env::var("JSON_MAX_UPLOAD_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_JSON_MAX_UPLOAD_MB)
+        * 1024
+        * 1024
+}
+
+// A batch of PDFs in one request is well past the default 2 MB body limit; `PDF_MAX_UPLOAD_MB`
+// (default 100)
+fn pdf_max_upload_bytes() -> usize {
+    std::env::var("PDF_MAX_UPLOAD_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PDF_MAX_UPLOAD_MB)
+        * 1024
+        * 1024
+}
+
 // Create the router for knowledge management routes
 pub fn create_knowledge_router() -> Router<AppState> {
     Router::new()
-        .route("/upload-pdf", post(upload_pdf_handler))
+        .route(
+            "/upload-pdf",
+            post(upload_pdf_handler).layer(DefaultBodyLimit::max(pdf_max_upload_bytes())),
+        )
+        .route(
+            "/upload-mbox",
+            post(upload_mbox_handler).layer(DefaultBodyLimit::max(mbox_max_upload_bytes())),
+        )
+        .route("/chatbots/{id}/imap-import", post(import_imap_handler))
+        .route(
+            "/chatbots/{id}/vectors",
+            post(upsert_vectors_handler).layer(DefaultBodyLimit::max(json_max_upload_bytes())),
+        )
+        .route(
+            "/knowledge/json",
+            post(import_json_handler).layer(DefaultBodyLimit::max(json_max_upload_bytes())),
+        )
         .route("/test-upload", post(test_upload_handler))
         .route("/simple-upload", post(simple_upload_handler))
+        .route("/chatbots/{id}/cold-documents", get(list_cold_documents_handler))
+        .route("/chatbots/{id}/cold-documents/rehydrate", post(rehydrate_document_handler))
+        .route("/chatbots/{id}/documents", get(list_documents_handler))
+        .route("/chatbots/{id}/chunks", get(list_chunks_handler))
+        .route("/chatbots/{id}/ingestions", get(list_ingestions_handler))
+        .route("/documents/{id}", get(get_document_handler))
+        .route("/documents/{id}/reprocess", post(reprocess_document_handler))
+        .route("/chatbots/{id}/help-center", put(update_help_center_handler).get(get_help_center_handler))
+        .route("/chatbots/{id}/help-center/sync", post(sync_help_center_handler))
+        .route("/chatbots/{id}/reindex", post(start_reindex_handler))
+        .route("/chatbots/{id}/reindex-jobs", get(list_reindex_jobs_handler))
+        .route("/reindex-jobs/{id}", get(get_reindex_job_handler))
 }
@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{count_language_mismatches, get_retained_chat_bot, list_language_counts};
+use crate::middleware::auth::Tenant;
+use crate::services::language::{is_multilingual, primary_language};
+use crate::services::usage::usage_range;
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LanguagesQuery {
+    /// First UTC day to include (YYYY-MM-DD); defaults to 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last UTC day to include (YYYY-MM-DD); defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+// Languages a chatbot's users ask in and its answers are given in, to show whether it needs
+// multilingual embeddings or translated documents
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/languages",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), LanguagesQuery),
+    responses(
+        (status = 200, description = "Query and answer counts per language for the range", body = Value),
+        (status = 400, description = "Invalid date range"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_chatbot_languages_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<LanguagesQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching languages for chatbot: {}", chatbot_id);
+
+    let (from, to) = usage_range(params.from, params.to, chrono::Utc::now().date_naive()).map_err(|e| {
+        tracing::error!("Invalid languages range: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let languages = list_language_counts(&app_state.db, tenant.organization_id, chatbot_id, from, to).await.map_err(|e| {
+        tracing::error!("❌ Failed to count languages: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mismatched = count_language_mismatches(&app_state.db, tenant.organization_id, chatbot_id, from, to).await.map_err(|e| {
+        tracing::error!("❌ Failed to count language mismatches: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (primary, other_language_rate) = primary_language(&languages);
+
+    tracing::info!("✅ Retrieved {} languages", languages.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Languages retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "organization_id": tenant.organization_id,
+            "from": from,
+            "to": to,
+            "primary_language": primary,
+            "other_language_rate": other_language_rate,
+            "multilingual": is_multilingual(other_language_rate),
+            "mismatched_answers": mismatched,
+            "languages": languages
+        }
+    })))
+}
+
+// Create the router for language analytics
+pub fn create_languages_router() -> Router<AppState> {
+    Router::new().route("/chatbots/{id}/languages", get(get_chatbot_languages_handler))
+}
@@ -0,0 +1,31 @@
+use axum::{response::Json, routing::get, Router};
+use serde_json::{json, Value};
+
+use crate::middleware::auth::AdminViewer;
+use crate::services::retry::{retry_metrics, RetryMetrics};
+use crate::utils::config::AppState;
+
+// Retry counters for Gemini, Elasticsearch and Qdrant calls since startup (admins and viewers)
+#[utoipa::path(
+    get,
+    path = "/api/admin/metrics/retries",
+    tag = "metrics",
+    responses(
+        (status = 200, description = "Retry counters per upstream", body = RetryMetrics),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn get_retry_metrics_handler(_admin: AdminViewer) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "message": "Retry metrics retrieved successfully",
+        "data": retry_metrics()
+    }))
+}
+
+// Create the router for operational metrics
+pub fn create_metrics_router() -> Router<AppState> {
+    Router::new().route("/admin/metrics/retries", get(get_retry_metrics_handler))
+}
@@ -1,5 +1,113 @@
 pub mod upload;
 pub mod query;
 pub mod chatbot;
+pub mod chatbot_health;
 pub mod knowledge;
+pub mod auth;
 pub mod chat;
+pub mod connectors;
+pub mod conversation_export;
+pub mod evaluation;
+pub mod openapi;
+pub mod organization;
+pub mod faq_clusters;
+pub mod feedback;
+pub mod glossary;
+pub mod guest;
+pub mod health;
+pub mod inbox;
+pub mod languages;
+pub mod metrics;
+pub mod output_filters;
+pub mod custom_domains;
+pub mod prompt_canary;
+pub mod prompt_templates;
+pub mod reports;
+pub mod retrieval_log;
+pub mod scim;
+pub mod sso;
+pub mod sentiment;
+pub mod sql_connectors;
+pub mod usage;
+pub mod web_sources;
+pub mod widget_config;
+
+use axum::{
+    middleware::{from_fn, from_fn_with_state},
+    Router,
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::middleware::custom_domain::custom_domain_middleware;
+use crate::middleware::ip_filter::ip_filter_middleware;
+use crate::middleware::load_shed::load_shed_middleware;
+use crate::middleware::rate_limit::rate_limit_middleware;
+use crate::middleware::rbac::require_role_middleware;
+use crate::middleware::request_id::request_tracing_middleware;
+use crate::services::user_auth::UserAuthConfig;
+use crate::utils::config::AppState;
+
+/// The server's full router: every route group with its middleware, from the outermost CORS layer
+/// down to the role check on management routes. `user_auth` is `None` when user sign-in is off
+pub fn build_router(app_state: AppState, user_auth: Option<UserAuthConfig>, allowed_origins: AllowOrigin) -> Router {
+    // Routes that change chatbots, their documents and their prompts; signed-in viewers may only read them
+    let management_routes = Router::new()
+        .merge(chatbot::create_chatbot_router())
+        .merge(
+            knowledge::create_knowledge_router()
+                .route_layer(from_fn_with_state(app_state.clone(), rate_limit_middleware)),
+        )
+        .merge(faq_clusters::create_faq_cluster_router())
+        .merge(evaluation::create_evaluation_router())
+        .merge(reports::create_report_router())
+        .merge(glossary::create_glossary_router())
+        .merge(prompt_templates::create_prompt_template_router())
+        .merge(output_filters::create_output_filter_router())
+        .merge(prompt_canary::create_prompt_canary_router())
+        .merge(custom_domains::create_custom_domain_router())
+        .merge(widget_config::create_widget_config_router())
+        .merge(sql_connectors::create_sql_connector_router())
+        .merge(web_sources::create_web_source_router())
+        .merge(connectors::create_connector_router())
+        .route_layer(from_fn_with_state(user_auth, require_role_middleware));
+
+    Router::new()
+        .merge(health::create_health_router())
+        .merge(openapi::create_openapi_router())
+        .nest("/api", organization::create_organization_router())
+        .nest("/api", auth::create_auth_router())
+        .nest("/api", management_routes)
+        .nest("/api", query::create_query_router())
+        .nest("/api", feedback::create_feedback_router())
+        .nest("/api", retrieval_log::create_retrieval_log_router())
+        .nest("/api", usage::create_usage_router())
+        .nest("/api", sentiment::create_sentiment_router())
+        .nest("/api", languages::create_languages_router())
+        .nest("/api", chatbot_health::create_chatbot_health_router())
+        .nest("/api", conversation_export::create_conversation_export_router())
+        .nest("/api", inbox::create_inbox_router())
+        .nest("/api", metrics::create_metrics_router())
+        .nest("/api", sso::create_sso_router())
+        .nest("/api", scim::create_scim_router())
+        .nest(
+            "/api",
+            chat::create_chat_router()
+                .route_layer(from_fn_with_state(app_state.clone(), rate_limit_middleware)),
+        )
+        .nest(
+            "/api",
+            guest::create_guest_router()
+                .route_layer(from_fn_with_state(app_state.clone(), rate_limit_middleware)),
+        )
+        .layer(from_fn_with_state(app_state.clone(), custom_domain_middleware))
+        .layer(from_fn_with_state(app_state.clone(), load_shed_middleware))
+        .layer(from_fn_with_state(app_state.clone(), ip_filter_middleware))
+        .layer(from_fn(request_tracing_middleware))
+        .layer(
+            CorsLayer::new()
+                .allow_origin(allowed_origins)
+                .allow_methods(Any)
+                .allow_headers(Any)
+        )
+        .with_state(app_state)
+}
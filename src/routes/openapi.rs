@@ -0,0 +1,354 @@
+use axum::Router;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::db::models::{
+    AdminSession, AgentReplyRequest, BulkDeleteConversationsRequest, ChatBotResponse, ChatbotHealthSnapshot,
+    ColdDocumentSummary, CreateChatBotRequest, CreateFeedbackRequest, CreateGuestSessionRequest,
+    CreateOrganizationRequest, CreateSourceConnectorRequest, CreateSqlConnectorRequest,
+    CreateUserRequest, CreateWebSourceRequest,
+    CustomDomain, CustomDomainRequest, Document, EvaluateChatbotRequest, EvaluationCase, FaqCluster,
+    FeedbackEntry, FeedbackSummary, FilterAction, GlobalSearchRequest, GlossaryEntry, GoldenQuery, HelpCenterConnector,
+    ImapImportRequest, InboxChat, IngestionLogEntry, JsonFieldMapping, JsonImportRequest, LanguageCount, LoginRequest,
+    NoContextBehavior, OrganizationResponse, OutputFilterConfig, OutputFilterIncident,
+    OutputFilterRule, PromptTemplate, PromptTemplateRequest, PromptVariantMetrics, RawVectorChunk,
+    RefreshTokenRequest, RegisterRequest, RehydrateDocumentRequest, ReindexJob, RelevantChunk,
+    ReportSchedule, RetrievalEvalRequest, SelectPromptTemplateRequest, SentimentSummary,
+    SourceConnector, SqlConnector, SqlTool, UpdateCustomDomainRequest,
+    UpdateGuestAccessRequest, UpdateHandoffWebhookRequest, UpdateHealthWebhookRequest,
+    UpdateIngestWebhookRequest, UpdatePromptCanaryRequest, UpdatePromptTemplateRequest,
+    UpdateRetrievalSettingsRequest, UpdateScriptsRequest, UpdateUserRoleRequest,
+    UpsertGlossaryEntryRequest, UpsertHelpCenterConnectorRequest, UpsertReportScheduleRequest,
+    UpsertSqlToolRequest, UpsertWidgetConfigRequest, UsageDay, UsageTotals, UserResponse, VectorUpsertRequest,
+    WebSource, WidgetBranding, WidgetConfig,
+};
+use crate::routes::{
+    auth, chat, chatbot, chatbot_health, connectors, conversation_export, custom_domains, evaluation,
+    faq_clusters, feedback, glossary, guest, health, inbox, knowledge, languages, metrics, organization,
+    output_filters, prompt_canary, prompt_templates, query, reports, retrieval_log, scim, sentiment,
+    sql_connectors, sso, usage, web_sources, widget_config,
+};
+use crate::services::evaluation::{CaseResult, EvaluationSummary, JudgeScores};
+use crate::services::retrieval_eval::{CutoffMetrics, QueryMetrics, RetrievalMetrics};
+use crate::services::health::{DependencyState, DependencyStatus, Readiness, ReadinessStatus};
+use crate::services::vector::SearchResult;
+use crate::services::retry::{RetryCounts, RetryMetrics};
+use crate::services::user_auth::UserRole;
+use crate::utils::config::AppState;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "RAG Rust API", description = "Retrieval-augmented chat over uploaded documents"),
+    paths(
+        health::health_handler,
+        health::liveness_handler,
+        health::readiness_handler,
+        organization::create_organization_handler,
+        organization::create_user_handler,
+        organization::get_users_handler,
+        organization::update_user_role_handler,
+        auth::register_handler,
+        auth::login_handler,
+        auth::refresh_handler,
+        auth::logout_handler,
+        auth::me_handler,
+        chatbot::create_chatbot_handler,
+        chatbot::get_chatbots_handler,
+        chatbot::update_retrieval_settings_handler,
+        chatbot::update_prompt_template_handler,
+        chatbot::select_prompt_template_handler,
+        chatbot::update_ingest_webhook_handler,
+        chatbot::update_handoff_webhook_handler,
+        chatbot::update_guest_access_handler,
+        chatbot::update_health_webhook_handler,
+        chatbot::update_scripts_handler,
+        output_filters::update_output_filters_handler,
+        output_filters::update_query_filters_handler,
+        output_filters::get_output_filter_incidents_handler,
+        prompt_canary::update_prompt_canary_handler,
+        prompt_canary::get_prompt_canary_handler,
+        prompt_canary::promote_prompt_canary_handler,
+        chatbot::delete_chatbot_handler,
+        chatbot::archive_chatbot_handler,
+        chatbot::unarchive_chatbot_handler,
+        chatbot::get_index_stats_handler,
+        chatbot::get_orphaned_indices_handler,
+        chatbot::get_secondary_resyncs_handler,
+        chatbot::clear_secondary_resync_handler,
+        knowledge::upload_pdf_handler,
+        knowledge::upload_mbox_handler,
+        knowledge::import_imap_handler,
+        knowledge::import_json_handler,
+        knowledge::upsert_vectors_handler,
+        knowledge::test_upload_handler,
+        knowledge::simple_upload_handler,
+        knowledge::list_cold_documents_handler,
+        knowledge::rehydrate_document_handler,
+        knowledge::start_reindex_handler,
+        knowledge::list_reindex_jobs_handler,
+        knowledge::get_reindex_job_handler,
+        knowledge::list_documents_handler,
+        knowledge::list_chunks_handler,
+        knowledge::list_ingestions_handler,
+        knowledge::get_document_handler,
+        knowledge::reprocess_document_handler,
+        knowledge::update_help_center_handler,
+        knowledge::get_help_center_handler,
+        knowledge::sync_help_center_handler,
+        sql_connectors::create_sql_connector_handler,
+        sql_connectors::list_sql_connectors_handler,
+        sql_connectors::sync_sql_connector_handler,
+        sql_connectors::delete_sql_connector_handler,
+        sql_connectors::update_sql_tool_handler,
+        sql_connectors::get_sql_tool_handler,
+        sql_connectors::delete_sql_tool_handler,
+        web_sources::create_web_source_handler,
+        web_sources::list_web_sources_handler,
+        web_sources::crawl_web_source_handler,
+        web_sources::delete_web_source_handler,
+        connectors::create_connector_handler,
+        connectors::list_connectors_handler,
+        connectors::sync_connector_handler,
+        connectors::delete_connector_handler,
+        query::query_handler,
+        query::query_health_handler,
+        query::global_search_handler,
+        chat::create_session_handler,
+        chat::chat_handler,
+        chat::chat_stream_handler,
+        chat::get_chat_history_handler,
+        conversation_export::export_chat_handler,
+        conversation_export::store_chat_export_handler,
+        chat::delete_session_handler,
+        chat::delete_chat_handler,
+        chat::resume_chat_handler,
+        inbox::list_inbox_handler,
+        inbox::claim_chat_handler,
+        inbox::create_agent_reply_handler,
+        inbox::create_reply_suggestion_handler,
+        inbox::mark_chat_read_handler,
+        inbox::mark_chat_unread_handler,
+        chat::delete_conversation_handler,
+        chat::bulk_delete_conversations_handler,
+        chat::test_sse_handler,
+        chat::chat_health_handler,
+        guest::create_guest_session_handler,
+        guest::guest_chat_handler,
+        guest::guest_chat_stream_handler,
+        feedback::create_feedback_handler,
+        feedback::get_chatbot_feedback_handler,
+        feedback::get_knowledge_gaps_handler,
+        retrieval_log::get_conversation_context_handler,
+        usage::get_chatbot_usage_handler,
+        sentiment::get_chatbot_sentiment_handler,
+        languages::get_chatbot_languages_handler,
+        faq_clusters::list_faq_clusters_handler,
+        faq_clusters::refresh_faq_clusters_handler,
+        chatbot_health::get_chatbot_health_handler,
+        reports::update_report_schedule_handler,
+        reports::get_report_schedule_handler,
+        reports::delete_report_schedule_handler,
+        reports::send_report_handler,
+        glossary::upsert_glossary_entry_handler,
+        glossary::get_glossary_handler,
+        glossary::delete_glossary_entry_handler,
+        evaluation::evaluate_chatbot_handler,
+        evaluation::evaluate_retrieval_handler,
+        prompt_templates::create_prompt_template_handler,
+        prompt_templates::get_prompt_templates_handler,
+        prompt_templates::get_prompt_template_handler,
+        prompt_templates::edit_prompt_template_handler,
+        prompt_templates::delete_prompt_template_handler,
+        custom_domains::create_custom_domain_handler,
+        custom_domains::get_custom_domains_handler,
+        custom_domains::update_custom_domain_handler,
+        custom_domains::delete_custom_domain_handler,
+        custom_domains::get_widget_config_handler,
+        widget_config::update_widget_config_handler,
+        widget_config::get_widget_config_handler,
+        widget_config::get_public_widget_config_handler,
+        metrics::get_retry_metrics_handler,
+        sso::sso_login_handler,
+        sso::sso_callback_handler,
+        sso::get_sso_session_handler,
+        sso::sso_logout_handler,
+        scim::create_scim_token_handler,
+        scim::service_provider_config_handler,
+        scim::list_scim_users_handler,
+        scim::create_scim_user_handler,
+        scim::get_scim_user_handler,
+        scim::replace_scim_user_handler,
+        scim::patch_scim_user_handler,
+        scim::delete_scim_user_handler,
+        scim::list_scim_groups_handler,
+        scim::create_scim_group_handler,
+        scim::get_scim_group_handler,
+        scim::replace_scim_group_handler,
+        scim::patch_scim_group_handler,
+        scim::delete_scim_group_handler,
+    ),
+    components(schemas(
+        CreateOrganizationRequest,
+        CreateUserRequest,
+        OrganizationResponse,
+        UserResponse,
+        UpdateUserRoleRequest,
+        UserRole,
+        RegisterRequest,
+        LoginRequest,
+        RefreshTokenRequest,
+        CreateChatBotRequest,
+        ChatBotResponse,
+        UpdateRetrievalSettingsRequest,
+        NoContextBehavior,
+        UpdatePromptTemplateRequest,
+        SelectPromptTemplateRequest,
+        PromptTemplate,
+        PromptTemplateRequest,
+        UpdateIngestWebhookRequest,
+        UpdateHandoffWebhookRequest,
+        UpdateGuestAccessRequest,
+        CreateGuestSessionRequest,
+        UpdateHealthWebhookRequest,
+        UpdateScriptsRequest,
+        OutputFilterConfig,
+        OutputFilterRule,
+        FilterAction,
+        OutputFilterIncident,
+        UpdatePromptCanaryRequest,
+        PromptVariantMetrics,
+        knowledge::UploadPdfForm,
+        knowledge::UploadMboxForm,
+        ImapImportRequest,
+        JsonImportRequest,
+        JsonFieldMapping,
+        VectorUpsertRequest,
+        RawVectorChunk,
+        ColdDocumentSummary,
+        RehydrateDocumentRequest,
+        ReindexJob,
+        Document,
+        IngestionLogEntry,
+        HelpCenterConnector,
+        UpsertHelpCenterConnectorRequest,
+        SqlConnector,
+        CreateSqlConnectorRequest,
+        SqlTool,
+        UpsertSqlToolRequest,
+        WebSource,
+        CreateWebSourceRequest,
+        SourceConnector,
+        CreateSourceConnectorRequest,
+        query::QueryResponse,
+        query::QueryData,
+        SearchResult,
+        GlobalSearchRequest,
+        chat::ChatRequest,
+        chat::ChatResponse,
+        chat::ChatData,
+        chat::SessionResponse,
+        chat::SessionData,
+        BulkDeleteConversationsRequest,
+        InboxChat,
+        AgentReplyRequest,
+        CreateFeedbackRequest,
+        FeedbackSummary,
+        FeedbackEntry,
+        UsageDay,
+        UsageTotals,
+        SentimentSummary,
+        LanguageCount,
+        FaqCluster,
+        ChatbotHealthSnapshot,
+        ReportSchedule,
+        UpsertReportScheduleRequest,
+        GlossaryEntry,
+        UpsertGlossaryEntryRequest,
+        EvaluateChatbotRequest,
+        EvaluationCase,
+        JudgeScores,
+        CaseResult,
+        EvaluationSummary,
+        RetrievalEvalRequest,
+        GoldenQuery,
+        RelevantChunk,
+        CutoffMetrics,
+        QueryMetrics,
+        RetrievalMetrics,
+        CustomDomain,
+        CustomDomainRequest,
+        UpdateCustomDomainRequest,
+        WidgetBranding,
+        WidgetConfig,
+        UpsertWidgetConfigRequest,
+        RetryMetrics,
+        RetryCounts,
+        AdminSession,
+        Readiness,
+        ReadinessStatus,
+        DependencyStatus,
+        DependencyState,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "organizations", description = "Organizations, users and API keys"),
+        (name = "auth", description = "User registration and sign-in with JWTs"),
+        (name = "chatbots", description = "Chatbot management"),
+        (name = "knowledge", description = "Document upload and embedding"),
+        (name = "query", description = "Similarity search"),
+        (name = "chat", description = "Sessions, chats and conversations"),
+        (name = "inbox", description = "Handoff inbox for human agents"),
+        (name = "feedback", description = "Answer ratings and comments"),
+        (name = "prompt-templates", description = "Shared prompt templates"),
+        (name = "custom-domains", description = "White-label hostnames and branding"),
+        (name = "widget", description = "Public configuration for the chat widget"),
+        (name = "health", description = "Liveness and readiness checks"),
+        (name = "metrics", description = "Operational counters"),
+        (name = "sso", description = "Single sign-on for the admin API"),
+        (name = "scim", description = "SCIM 2.0 user and group provisioning"),
+    )
+)]
+pub struct ApiDoc;
+
+// Registers the API key headers and bearer tokens referenced by `security(...)` on each path
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "admin_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Admin-Key"))),
+        );
+        components.add_security_scheme("admin_session", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        components.add_security_scheme("scim_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        components.add_security_scheme("user_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        components.add_security_scheme("guest_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+// Serve Swagger UI at /swagger-ui backed by /api-docs/openapi.json
+pub fn create_openapi_router() -> Router<AppState> {
+    Router::new().merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_spec_lists_routes() {
+        let spec = ApiDoc::openapi();
+        let paths = &spec.paths.paths;
+
+        assert!(paths.contains_key("/api/chat"));
+        assert!(paths.contains_key("/api/chatbots"));
+        assert!(paths.contains_key("/api/sessions/{id}"));
+        assert!(spec.components.unwrap().security_schemes.contains_key("api_key"));
+    }
+}
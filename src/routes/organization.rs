@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put},
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::{
+    CreateOrganizationRequest, CreateUserRequest, OrganizationResponse, UpdateUserRoleRequest, UserResponse,
+};
+use crate::db::queries::{create_organization, create_user, list_users, update_user_role};
+use crate::middleware::auth::{generate_api_key, hash_api_key, AdminKey, Tenant};
+use crate::services::user_auth::UserRole;
+use crate::utils::config::AppState;
+
+// Create a new organization and return its API key (admin only)
+#[utoipa::path(
+    post,
+    path = "/api/admin/organizations",
+    tag = "organizations",
+    request_body = CreateOrganizationRequest,
+    responses(
+        (status = 200, description = "Organization created; the API key is only returned once", body = Value),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn create_organization_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminKey,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating organization with name: {}", payload.name);
+
+    let api_key = generate_api_key();
+
+    match create_organization(&app_state.db, payload.name, hash_api_key(&api_key)).await {
+        Ok(organization) => {
+            let response = OrganizationResponse {
+                id: organization.id,
+                name: organization.name,
+                created_at: organization.created_at,
+                status: organization.status,
+                api_key: Some(api_key),
+            };
+
+            tracing::info!("✅ Organization created successfully: {}", response.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Organization created successfully. Store the API key now, it cannot be retrieved again",
+                "data": response
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create organization: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Add a user to the caller's organization. New users are viewers
+#[utoipa::path(
+    post,
+    path = "/api/users",
+    tag = "organizations",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 200, description = "User created", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 403, description = "Signed in without the owner role"),
+    ),
+    security(("api_key" = []), ("user_token" = []))
+)]
+pub async fn create_user_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tenant.require_role(UserRole::Owner)?;
+    tracing::info!("Creating user {} in organization {}", payload.email, tenant.organization_id);
+
+    match create_user(&app_state.db, tenant.organization_id, payload.email).await {
+        Ok(user) => {
+            let response = UserResponse::from(user);
+
+            tracing::info!("✅ User created successfully: {}", response.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "User created successfully",
+                "data": response
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create user: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List users in the caller's organization
+#[utoipa::path(
+    get,
+    path = "/api/users",
+    tag = "organizations",
+    responses(
+        (status = 200, description = "Users in the caller's organization", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_users_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching users for organization {}", tenant.organization_id);
+
+    match list_users(&app_state.db, tenant.organization_id).await {
+        Ok(users) => {
+            let responses: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+
+            tracing::info!("✅ Retrieved {} users", responses.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Users retrieved successfully",
+                "data": responses,
+                "count": responses.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch users: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Make a user of the caller's organization an owner, editor or viewer (owners only). Signed-in
+// users get the new role when they next sign in or refresh their access token
+#[utoipa::path(
+    put,
+    path = "/api/users/{id}/role",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    request_body = UpdateUserRoleRequest,
+    responses(
+        (status = 200, description = "Role changed", body = Value),
+        (status = 401, description = "Missing or unknown API key or access token"),
+        (status = 403, description = "Signed in without the owner role"),
+        (status = 404, description = "User not found"),
+    ),
+    security(("api_key" = []), ("user_token" = []))
+)]
+pub async fn update_user_role_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<UpdateUserRoleRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tenant.require_role(UserRole::Owner)?;
+    tracing::info!("Making user {} {}", user_id, payload.role);
+
+    match update_user_role(&app_state.db, tenant.organization_id, user_id, payload.role.as_str()).await {
+        Ok(Some(user)) => {
+            tracing::info!("✅ User {} is now {}", user.id, user.role);
+            Ok(Json(json!({
+                "success": true,
+                "message": "User role updated successfully",
+                "data": UserResponse::from(user)
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("User not found: {}", user_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update user role: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for organization and user routes
+pub fn create_organization_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/organizations", post(create_organization_handler))
+        .route("/users", post(create_user_handler))
+        .route("/users", get(get_users_handler))
+        .route("/users/{id}/role", put(update_user_role_handler))
+}
@@ -0,0 +1,211 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::models::{ChatBotResponse, OutputFilterConfig};
+use crate::db::queries::{
+    get_retained_chat_bot, list_output_filter_incidents, update_chat_bot_output_filters, update_chat_bot_query_filters,
+};
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::output_filter::validate_filters;
+use crate::utils::config::AppState;
+
+const DEFAULT_INCIDENT_LIMIT: i64 = 50;
+const MAX_INCIDENT_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IncidentQuery {
+    pub limit: Option<i64>,
+}
+
+// Set the word list, regex and classifier filters applied to a chatbot's answers
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/output-filters",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = OutputFilterConfig,
+    responses(
+        (status = 200, description = "Output filters updated", body = Value),
+        (status = 400, description = "A filter is invalid or the classifier is not configured"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_output_filters_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<OutputFilterConfig>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating output filters for chatbot: {}", chatbot_id);
+
+    if let Err(e) = validate_filters(&payload) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // No rules and no classifier means no filtering
+    let filters = (!payload.rules.is_empty() || payload.classifier).then_some(payload);
+
+    let chatbot = match update_chat_bot_output_filters(&app_state.db, tenant.organization_id, chatbot_id, filters).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update output filters: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Output filters updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Output filters updated successfully",
+        "data": response
+    })))
+}
+
+// Set the word list, regex and classifier filters that screen user queries before retrieval and
+// generation. Replace rules mask the query; a query a block rule matches is refused
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/query-filters",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = OutputFilterConfig,
+    responses(
+        (status = 200, description = "Query filters updated", body = Value),
+        (status = 400, description = "A filter is invalid or the classifier is not configured"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_query_filters_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<OutputFilterConfig>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating query filters for chatbot: {}", chatbot_id);
+
+    if let Err(e) = validate_filters(&payload) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // No rules and no classifier means no screening
+    let filters = (!payload.rules.is_empty() || payload.classifier).then_some(payload);
+
+    let chatbot = match update_chat_bot_query_filters(&app_state.db, tenant.organization_id, chatbot_id, filters)
+        .await
+    {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update query filters: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Drop the cached chatbot row on every replica
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Query filters updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Query filters updated successfully",
+        "data": response
+    })))
+}
+
+// Queries and answers a chatbot's filters changed or blocked, newest first
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/output-filter-incidents",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), IncidentQuery),
+    responses(
+        (status = 200, description = "Output filter incidents", body = Value),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_output_filter_incidents_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<IncidentQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching output filter incidents for chatbot: {}", chatbot_id);
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_INCIDENT_LIMIT).clamp(1, MAX_INCIDENT_LIMIT);
+
+    match list_output_filter_incidents(&app_state.db, tenant.organization_id, chatbot_id, limit).await {
+        Ok(incidents) => {
+            tracing::info!("✅ Retrieved {} output filter incidents", incidents.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Output filter incidents retrieved successfully",
+                "data": incidents,
+                "count": incidents.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch output filter incidents: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for output filter routes
+pub fn create_output_filter_router() -> Router<AppState> {
+    Router::new()
+        .route("/chatbots/{id}/output-filters", put(update_output_filters_handler))
+        .route("/chatbots/{id}/query-filters", put(update_query_filters_handler))
+        .route("/chatbots/{id}/output-filter-incidents", get(get_output_filter_incidents_handler))
+}
@@ -0,0 +1,213 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{post, put},
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::{ChatBotResponse, PromptVariantMetrics, UpdatePromptCanaryRequest};
+use crate::db::queries::{
+    get_prompt_variant_metrics, get_retained_chat_bot, promote_chat_bot_prompt_canary,
+    update_chat_bot_prompt_canary,
+};
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::prompt_template::{approval_rate, PromptVariant};
+use crate::utils::config::AppState;
+
+// Drop the cached chatbot row on every replica
+async fn publish_change(app_state: &AppState, chatbot_id: Uuid) {
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::ChatbotSettingsChanged { chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+    }
+}
+
+// Start, adjust or stop a canary that answers a share of chats with another shared template
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/prompt-template-canary",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpdatePromptCanaryRequest,
+    responses(
+        (status = 200, description = "Canary updated", body = Value),
+        (status = 400, description = "Percent is not between 1 and 100"),
+        (status = 404, description = "Chatbot or prompt template not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_prompt_canary_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<UpdatePromptCanaryRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating prompt canary {:?} for chatbot: {}", payload.template_id, chatbot_id);
+
+    // Stopping the canary ignores the percent
+    let percent = match payload.template_id {
+        Some(_) if !(1..=100).contains(&payload.percent) => {
+            tracing::error!("Canary percent must be between 1 and 100, got {}", payload.percent);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Some(_) => payload.percent,
+        None => 0,
+    };
+
+    let chatbot = match update_chat_bot_prompt_canary(&app_state.db, tenant.organization_id, chatbot_id, payload.template_id, percent).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot {} or prompt template {:?} not found", chatbot_id, payload.template_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update prompt canary: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    publish_change(&app_state, chatbot_id).await;
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Prompt canary updated for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt canary updated successfully",
+        "data": response
+    })))
+}
+
+// Compare the canary template with the current one on turns answered since the canary started
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/prompt-template-canary",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Metrics per variant", body = Value),
+        (status = 404, description = "Chatbot not found or no canary running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_prompt_canary_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching prompt canary metrics for chatbot: {}", chatbot_id);
+
+    let chatbot = match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let (Some(canary_template_id), Some(started_at)) = (chatbot.canary_template_id, chatbot.canary_started_at) else {
+        tracing::error!("No prompt canary running for chatbot: {}", chatbot_id);
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let metrics = match get_prompt_variant_metrics(&app_state.db, tenant.organization_id, chatbot_id, started_at).await {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch prompt variant metrics: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // A variant without turns yet reports zeros
+    let variant_metrics = |variant: PromptVariant| {
+        let mut entry = metrics
+            .iter()
+            .find(|m| m.variant == variant.as_str())
+            .cloned()
+            .unwrap_or_else(|| PromptVariantMetrics { variant: variant.as_str().to_string(), ..Default::default() });
+        entry.approval_rate = approval_rate(entry.up, entry.rated);
+        entry
+    };
+    let current = variant_metrics(PromptVariant::Current);
+    let canary = variant_metrics(PromptVariant::Canary);
+    let approval_delta = canary.approval_rate.zip(current.approval_rate).map(|(c, p)| c - p);
+
+    tracing::info!("✅ Retrieved prompt canary metrics for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt canary metrics retrieved successfully",
+        "data": {
+            "prompt_template_id": chatbot.prompt_template_id,
+            "canary_template_id": canary_template_id,
+            "canary_percent": chatbot.canary_percent,
+            "started_at": started_at,
+            "current": current,
+            "canary": canary,
+            "approval_delta": approval_delta
+        }
+    })))
+}
+
+// Make the canary the chatbot's selected template and end the canary
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/prompt-template-canary/promote",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Canary promoted", body = Value),
+        (status = 404, description = "Chatbot not found or no canary running"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn promote_prompt_canary_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Promoting prompt canary for chatbot: {}", chatbot_id);
+
+    let chatbot = match promote_chat_bot_prompt_canary(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot {} not found or has no prompt canary", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to promote prompt canary: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    publish_change(&app_state, chatbot_id).await;
+
+    let response = ChatBotResponse::from(chatbot);
+
+    tracing::info!("✅ Prompt canary promoted for chatbot: {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt canary promoted successfully",
+        "data": response
+    })))
+}
+
+// Create the router for prompt canary routes
+pub fn create_prompt_canary_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/prompt-template-canary",
+            put(update_prompt_canary_handler).get(get_prompt_canary_handler),
+        )
+        .route("/chatbots/{id}/prompt-template-canary/promote", post(promote_prompt_canary_handler))
+}
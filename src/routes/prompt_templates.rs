@@ -0,0 +1,261 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::PromptTemplateRequest;
+use crate::db::queries::{
+    create_prompt_template, delete_prompt_template, get_prompt_template, list_prompt_templates,
+    update_prompt_template,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::prompt_template::validate_template;
+use crate::utils::config::AppState;
+
+// Trim the name and check the template compiles
+fn validate_request(payload: PromptTemplateRequest) -> Result<(String, String), StatusCode> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || payload.template.trim().is_empty() {
+        tracing::error!("Prompt template name and template must not be empty");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = validate_template(&payload.template) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok((name, payload.template))
+}
+
+fn is_duplicate_name(e: &AppError) -> bool {
+    matches!(e, AppError::Database(sqlx::Error::Database(db_error)) if db_error.is_unique_violation())
+}
+
+// Create a named prompt template that the organization's chatbots can select
+#[utoipa::path(
+    post,
+    path = "/api/prompt-templates",
+    tag = "prompt-templates",
+    request_body = PromptTemplateRequest,
+    responses(
+        (status = 200, description = "Prompt template created", body = Value),
+        (status = 400, description = "Name is empty or template does not compile"),
+        (status = 409, description = "A template with this name already exists"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Json(payload): Json<PromptTemplateRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating prompt template '{}'", payload.name);
+
+    let (name, template) = validate_request(payload)?;
+
+    match create_prompt_template(&app_state.db, tenant.organization_id, name, template).await {
+        Ok(prompt_template) => {
+            tracing::info!("✅ Prompt template created: {}", prompt_template.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Prompt template created successfully",
+                "data": prompt_template
+            })))
+        }
+        Err(e) if is_duplicate_name(&e) => {
+            tracing::error!("Prompt template name already in use");
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create prompt template: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List the organization's prompt templates
+#[utoipa::path(
+    get,
+    path = "/api/prompt-templates",
+    tag = "prompt-templates",
+    responses(
+        (status = 200, description = "Prompt templates, by name", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_prompt_templates_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching prompt templates for organization {}", tenant.organization_id);
+
+    match list_prompt_templates(&app_state.db, tenant.organization_id).await {
+        Ok(prompt_templates) => {
+            tracing::info!("✅ Retrieved {} prompt templates", prompt_templates.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Prompt templates retrieved successfully",
+                "data": prompt_templates,
+                "count": prompt_templates.len()
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch prompt templates: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Get one prompt template
+#[utoipa::path(
+    get,
+    path = "/api/prompt-templates/{id}",
+    tag = "prompt-templates",
+    params(("id" = Uuid, Path, description = "Prompt template id")),
+    responses(
+        (status = 200, description = "Prompt template", body = Value),
+        (status = 404, description = "Prompt template not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(template_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_prompt_template(&app_state.db, tenant.organization_id, template_id).await {
+        Ok(Some(prompt_template)) => Ok(Json(json!({
+            "success": true,
+            "message": "Prompt template retrieved successfully",
+            "data": prompt_template
+        }))),
+        Ok(None) => {
+            tracing::error!("Prompt template not found: {}", template_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to fetch prompt template: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Rename or rewrite a prompt template; chatbots that selected it use the new version on their next request
+#[utoipa::path(
+    put,
+    path = "/api/prompt-templates/{id}",
+    tag = "prompt-templates",
+    params(("id" = Uuid, Path, description = "Prompt template id")),
+    request_body = PromptTemplateRequest,
+    responses(
+        (status = 200, description = "Prompt template updated", body = Value),
+        (status = 400, description = "Name is empty or template does not compile"),
+        (status = 404, description = "Prompt template not found"),
+        (status = 409, description = "A template with this name already exists"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn edit_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(template_id): Path<Uuid>,
+    Json(payload): Json<PromptTemplateRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Updating prompt template: {}", template_id);
+
+    let (name, template) = validate_request(payload)?;
+
+    match update_prompt_template(&app_state.db, tenant.organization_id, template_id, name, template).await {
+        Ok(Some(prompt_template)) => {
+            tracing::info!("✅ Prompt template updated: {}", template_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Prompt template updated successfully",
+                "data": prompt_template
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("Prompt template not found: {}", template_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) if is_duplicate_name(&e) => {
+            tracing::error!("Prompt template name already in use");
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to update prompt template: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Delete a prompt template; chatbots that selected it go back to their inline template or the default
+#[utoipa::path(
+    delete,
+    path = "/api/prompt-templates/{id}",
+    tag = "prompt-templates",
+    params(("id" = Uuid, Path, description = "Prompt template id")),
+    responses(
+        (status = 200, description = "Prompt template deleted", body = Value),
+        (status = 404, description = "Prompt template not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_prompt_template_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(template_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting prompt template: {}", template_id);
+
+    let chatbot_ids = match delete_prompt_template(&app_state.db, tenant.organization_id, template_id).await {
+        Ok(Some(chatbot_ids)) => chatbot_ids,
+        Ok(None) => {
+            tracing::error!("Prompt template not found: {}", template_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to delete prompt template: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Chatbots that selected the template have changed; drop their cached rows on every replica
+    for chatbot_id in &chatbot_ids {
+        if let Err(e) = publish(
+            &app_state.db,
+            &app_state.chatbot_cache,
+            CacheEvent::ChatbotSettingsChanged { chatbot_id: *chatbot_id },
+        ).await {
+            tracing::warn!("⚠️ Failed to publish chatbot settings change: {}", e);
+        }
+    }
+
+    tracing::info!("✅ Prompt template deleted: {}", template_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Prompt template deleted successfully",
+        "data": {
+            "template_id": template_id,
+            "unselected_chatbots": chatbot_ids
+        }
+    })))
+}
+
+// Create the router for prompt template routes
+pub fn create_prompt_template_router() -> Router<AppState> {
+    Router::new()
+        .route("/prompt-templates", get(get_prompt_templates_handler).post(create_prompt_template_handler))
+        .route(
+            "/prompt-templates/{id}",
+            get(get_prompt_template_handler)
+                .put(edit_prompt_template_handler)
+                .delete(delete_prompt_template_handler),
+        )
+}
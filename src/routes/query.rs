@@ -2,32 +2,44 @@ use axum::{
     extract::{Query, State},
     http::StatusCode,
     response::Json,
-    routing::get,
+    routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::db::models::{GlobalSearchRequest, UsageEvent};
+use crate::db::queries::list_searchable_chat_bots;
+use crate::middleware::auth::{AdminViewer, Tenant};
+use crate::services::cold_storage::record_retrieval;
 use crate::services::embedding::EmbeddingService;
-use crate::services::elasticsearch::SearchResult;
+use crate::services::global_search::{
+    chatbot_indices, group_by_chatbot, DEFAULT_GLOBAL_SEARCH_RESULTS, MAX_GLOBAL_SEARCH_RESULTS,
+};
+use crate::services::scripting::run_query_hook;
+use crate::services::sharding::shard_indices;
+use crate::services::usage::{record_usage, with_embedding_usage};
+use crate::services::vector::{chatbot_index_name, SearchResult};
 use crate::utils::config::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct QueryRequest {
     pub chatbot_id: String,
     pub query: String,
     pub limit: Option<u64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct QueryResponse {
     pub success: bool,
     pub message: String,
     pub data: QueryData,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct QueryData {
     pub chatbot_id: String,
     pub query: String,
@@ -36,8 +48,21 @@ pub struct QueryData {
 }
 
 // Query endpoint for similarity search
+#[utoipa::path(
+    get,
+    path = "/api/query",
+    tag = "query",
+    params(QueryRequest),
+    responses(
+        (status = 200, description = "Similar chunks for the query", body = QueryResponse),
+        (status = 400, description = "Invalid chatbot_id"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
 pub async fn query_handler(
     State(app_state): State<AppState>,
+    tenant: Tenant,
     Query(params): Query<QueryRequest>,
 ) -> Result<Json<Value>, StatusCode> {
     tracing::info!("Processing query: {}", params.query);
@@ -48,24 +73,67 @@ pub async fn query_handler(
         StatusCode::BAD_REQUEST
     })?;
 
+    // Only search chatbots owned by the caller's organization
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
     let limit = params.limit.unwrap_or(5);
 
     // Create embedding service
-    let embedding_service = EmbeddingService::new(app_state.elasticsearch.clone()).map_err(|e| {
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
         tracing::error!("Failed to create embedding service: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Create collection name for this chatbot
-    let collection_name = format!("chatbot_{}", chatbot_id);
+    // Resolve every shard index for this chatbot
+    let index_names = shard_indices(
+        &chatbot_index_name(tenant.organization_id, chatbot_id),
+        chatbot.shard_count,
+    );
+
+    // The chatbot's query script may rewrite what we search for
+    let search_query = match chatbot.query_script.as_deref() {
+        Some(script) => run_query_hook(script, &params.query).map_err(|e| {
+            tracing::error!("❌ Query script failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?,
+        None => params.query.clone(),
+    };
 
     // Search for similar embeddings
-    let search_results = embedding_service.search_similar(&collection_name, &params.query, limit).await.map_err(|e| {
+    let search_results = embedding_service.search_similar(&index_names, &search_query, limit).await.map_err(|e| {
         tracing::error!("Failed to search embeddings: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
     tracing::info!("Found {} similar results for query", search_results.len());
+    record_retrieval(&app_state.background_jobs, app_state.db.clone(), chatbot_id, &search_results);
+    let usage = UsageEvent {
+        organization_id: tenant.organization_id,
+        chatbot_id,
+        operation: "query",
+        ..Default::default()
+    };
+    record_usage(
+        &app_state.background_jobs,
+        app_state.db.clone(),
+        with_embedding_usage(usage, &embedding_service),
+    );
+
+    // Deployment plugins may drop or reorder hits
+    let search_results = app_state.plugins.filter_and_rerank(&search_query, search_results).await.map_err(|e| {
+        tracing::error!("❌ Plugin stage failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     Ok(Json(json!({
         "success": true,
@@ -79,7 +147,75 @@ pub async fn query_handler(
     })))
 }
 
+// Search every active chatbot's index, or the selected ones, to find where some content lives
+// (admins and viewers)
+#[utoipa::path(
+    post,
+    path = "/api/admin/search",
+    tag = "query",
+    request_body = GlobalSearchRequest,
+    responses(
+        (status = 200, description = "Similar chunks grouped by chatbot", body = Value),
+        (status = 400, description = "Blank query or limit out of range"),
+        (status = 401, description = "Invalid admin key or session"),
+        (status = 403, description = "Admin API disabled"),
+    ),
+    security(("admin_key" = []), ("admin_session" = []))
+)]
+pub async fn global_search_handler(
+    State(app_state): State<AppState>,
+    _admin: AdminViewer,
+    Json(request): Json<GlobalSearchRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let query = request.query.trim();
+    let limit = request.limit.unwrap_or(DEFAULT_GLOBAL_SEARCH_RESULTS);
+    if query.is_empty() || limit == 0 || limit > MAX_GLOBAL_SEARCH_RESULTS {
+        tracing::warn!("⚠️ Rejected global search: blank query or limit {} out of range", limit);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    tracing::info!("Searching across chatbots: {}", query);
+
+    let chatbots = list_searchable_chat_bots(&app_state.db, request.chatbot_ids.as_deref()).await.map_err(|e| {
+        tracing::error!("❌ Failed to list chatbots to search: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let embedding_service = EmbeddingService::new(app_state.vector_store.clone(), app_state.embedding_cache.clone()).map_err(|e| {
+        tracing::error!("Failed to create embedding service: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let hits = if chatbots.is_empty() {
+        Vec::new()
+    } else {
+        embedding_service.search_collections(&chatbot_indices(&chatbots), query, limit).await.map_err(|e| {
+            tracing::error!("❌ Failed to search across chatbots: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    };
+    let total_results = hits.len();
+    let groups = group_by_chatbot(&chatbots, hits);
+
+    tracing::info!("✅ Found {} results in {} of {} chatbots", total_results, groups.len(), chatbots.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Search completed successfully",
+        "data": {
+            "query": query,
+            "chatbots_searched": chatbots.len(),
+            "total_results": total_results,
+            "chatbots": groups
+        }
+    })))
+}
+
 // Health check for query service
+#[utoipa::path(
+    get,
+    path = "/api/query/health",
+    tag = "health",
+    responses((status = 200, description = "Query service is running", body = Value))
+)]
 pub async fn query_health_handler() -> Json<Value> {
     Json(json!({
         "status": "ok",
@@ -93,4 +229,5 @@ pub fn create_query_router() -> Router<AppState> {
     Router::new()
         .route("/query", get(query_handler))
         .route("/query/health", get(query_health_handler))
+        .route("/admin/search", post(global_search_handler))
 }
@@ -0,0 +1,248 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{post, put},
+    Router,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::UpsertReportScheduleRequest;
+use crate::db::queries::{delete_report_schedule, get_report_schedule, record_report_sent, upsert_report_schedule};
+use crate::middleware::auth::Tenant;
+use crate::services::ingest_webhook::validate_webhook_url;
+use crate::services::reports::{build_report, deliver_report, is_valid_email, MAX_RECIPIENTS};
+use crate::utils::config::AppState;
+
+// Deliver the chatbot's weekly report to a webhook, email recipients or both. Reports cover the
+// previous Monday-to-Sunday week and go out early each Monday (UTC)
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/report-schedule",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpsertReportScheduleRequest,
+    responses(
+        (status = 200, description = "Report schedule saved", body = Value),
+        (status = 400, description = "No delivery channel, or an invalid URL or recipient"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 501, description = "Email recipients given but this build lacks the smtp feature"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_report_schedule_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(mut payload): Json<UpsertReportScheduleRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Saving report schedule for chatbot: {}", chatbot_id);
+
+    payload.webhook_url = payload.webhook_url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &payload.webhook_url
+        && let Err(e) = validate_webhook_url(url).await
+    {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // A secret only means something alongside a URL
+    if payload.webhook_url.is_none() {
+        payload.webhook_secret = None;
+    }
+
+    payload.email_recipients = payload.email_recipients.iter().map(|address| address.trim().to_lowercase()).collect();
+    payload.email_recipients.sort();
+    payload.email_recipients.dedup();
+    if let Some(address) = payload.email_recipients.iter().find(|address| !is_valid_email(address)) {
+        tracing::error!("Invalid report recipient: {}", address);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.email_recipients.len() > MAX_RECIPIENTS {
+        tracing::error!("A report can have at most {} recipients", MAX_RECIPIENTS);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.webhook_url.is_none() && payload.email_recipients.is_empty() {
+        tracing::error!("A report schedule needs a webhook URL or email recipients");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !payload.email_recipients.is_empty() && !cfg!(feature = "smtp") {
+        tracing::error!("Report emails requested but this build lacks the smtp feature");
+        return Err(StatusCode::NOT_IMPLEMENTED);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match upsert_report_schedule(&app_state.db, tenant.organization_id, chatbot_id, &payload).await {
+        Ok(schedule) => {
+            tracing::info!("✅ Report schedule saved for chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Report schedule saved successfully",
+                "data": schedule
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save report schedule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Get a chatbot's report schedule, without its webhook secret
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/report-schedule",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's report schedule", body = Value),
+        (status = 404, description = "The chatbot has no report schedule"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_report_schedule_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_report_schedule(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(schedule)) => Ok(Json(json!({
+            "success": true,
+            "message": "Report schedule retrieved successfully",
+            "data": schedule
+        }))),
+        Ok(None) => {
+            tracing::error!("No report schedule for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get report schedule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Stop a chatbot's weekly reports
+#[utoipa::path(
+    delete,
+    path = "/api/chatbots/{id}/report-schedule",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Report schedule removed", body = Value),
+        (status = 404, description = "The chatbot has no report schedule"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_report_schedule_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match delete_report_schedule(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(true) => {
+            tracing::info!("✅ Report schedule removed from chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Report schedule removed successfully",
+                "data": { "chatbot_id": chatbot_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("No report schedule for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to remove report schedule: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Deliver a report of the last 7 days, including today, now. The weekly schedule is unchanged
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/report-schedule/send",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Report delivered", body = Value),
+        (status = 404, description = "Chatbot or report schedule not found"),
+        (status = 502, description = "A delivery failed"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn send_report_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Sending report for chatbot: {}", chatbot_id);
+
+    let schedule = match get_report_schedule(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => {
+            tracing::error!("No report schedule for chatbot: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let chatbot = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(chatbot)) => chatbot,
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let to = Utc::now().date_naive();
+    let report = build_report(&app_state.db, tenant.organization_id, &chatbot, to - chrono::Duration::days(6), to)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to build report: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    deliver_report(&schedule, &report).await.map_err(|e| {
+        tracing::error!("❌ Failed to deliver report for chatbot {}: {}", chatbot_id, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    if let Err(e) = record_report_sent(&app_state.db, schedule.id).await {
+        tracing::warn!("⚠️ Failed to record report delivery: {}", e);
+    }
+
+    tracing::info!("✅ Report delivered for chatbot {}", chatbot_id);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Report delivered successfully",
+        "data": report
+    })))
+}
+
+// Create the router for scheduled reports
+pub fn create_report_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/report-schedule",
+            put(update_report_schedule_handler).get(get_report_schedule_handler).delete(delete_report_schedule_handler),
+        )
+        .route("/chatbots/{id}/report-schedule/send", post(send_report_handler))
+}
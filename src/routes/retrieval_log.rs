@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::queries::get_retrieval_log;
+use crate::middleware::auth::Tenant;
+use crate::utils::config::AppState;
+
+// The chunks, scores and prompt a conversation turn's answer was generated from
+#[utoipa::path(
+    get,
+    path = "/api/conversations/{id}/context",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation id")),
+    responses(
+        (status = 200, description = "Retrieved chunks, prompt and retrieval trace of the turn", body = Value),
+        (status = 404, description = "Conversation not found, or it never reached the LLM"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_conversation_context_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(conversation_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching retrieved context for conversation: {}", conversation_id);
+
+    match get_retrieval_log(&app_state.db, tenant.organization_id, conversation_id).await {
+        Ok(Some(log)) => {
+            tracing::info!("✅ Retrieved context of {} chunks", log.chunks.len());
+            Ok(Json(json!({
+                "success": true,
+                "message": "Conversation context retrieved successfully",
+                "data": log
+            })))
+        }
+        Ok(None) => {
+            tracing::error!("No retrieval log for conversation: {}", conversation_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get retrieval log: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for retrieval log routes
+pub fn create_retrieval_log_router() -> Router<AppState> {
+    Router::new().route("/conversations/{id}/context", get(get_conversation_context_handler))
+}
@@ -0,0 +1,694 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{
+    change_group_members, create_scim_user, create_user_group, delete_user, delete_user_group, get_user,
+    get_user_group, list_group_memberships, list_scim_users, list_user_groups, set_organization_scim_token,
+    update_scim_user, update_user_group,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::{generate_scim_token, hash_api_key, ScimTenant, Tenant};
+use crate::services::scim::{
+    group_changes, group_resource, list_response, member_ids, page, parse_eq_filter, service_provider_config,
+    user_changes, user_resource, PatchRequest, ScimGroupRequest, ScimUserRequest, ERROR_SCHEMA,
+};
+use crate::utils::config::AppState;
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// An error in the shape SCIM clients expect
+#[derive(Debug)]
+pub struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+impl ScimError {
+    fn new(status: StatusCode, scim_type: Option<&'static str>, detail: impl Into<String>) -> Self {
+        ScimError { status, scim_type, detail: detail.into() }
+    }
+
+    fn invalid_value(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidValue"), detail)
+    }
+
+    fn invalid_filter(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidFilter"), detail)
+    }
+
+    fn not_found(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, None, detail)
+    }
+
+    fn conflict(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, Some("uniqueness"), detail)
+    }
+
+    fn internal(context: &str, e: AppError) -> Self {
+        tracing::error!("❌ {}: {}", context, e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, None, context)
+    }
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "schemas": [ERROR_SCHEMA],
+            "status": self.status.as_u16().to_string(),
+            "scimType": self.scim_type,
+            "detail": self.detail
+        });
+        scim_response(self.status, body)
+    }
+}
+
+fn scim_response(status: StatusCode, body: Value) -> Response {
+    (status, [(header::CONTENT_TYPE, SCIM_CONTENT_TYPE)], body.to_string()).into_response()
+}
+
+fn is_unique_violation(e: &AppError) -> bool {
+    matches!(e, AppError::Database(sqlx::Error::Database(db)) if db.is_unique_violation())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    /// Only `attribute eq "value"` filters are supported
+    pub filter: Option<String>,
+    /// 1-based index of the first result
+    pub start_index: Option<i64>,
+    /// Page size, at most 200
+    pub count: Option<i64>,
+}
+
+// Issue a new SCIM token for the caller's organization, replacing any previous one
+#[utoipa::path(
+    post,
+    path = "/api/organizations/scim-token",
+    tag = "scim",
+    responses(
+        (status = 200, description = "SCIM token; it is only returned once", body = Value),
+        (status = 401, description = "Missing or unknown API key"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_scim_token_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Issuing SCIM token for organization {}", tenant.organization_id);
+
+    let token = generate_scim_token();
+    match set_organization_scim_token(&app_state.db, tenant.organization_id, &hash_api_key(&token)).await {
+        Ok(()) => {
+            tracing::info!("✅ SCIM token issued for organization {}", tenant.organization_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "SCIM token issued. Store it now, it cannot be retrieved again",
+                "data": {
+                    "token": token,
+                    "base_url": "/api/scim/v2"
+                }
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to store SCIM token: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// What this SCIM server supports
+#[utoipa::path(
+    get,
+    path = "/api/scim/v2/ServiceProviderConfig",
+    tag = "scim",
+    responses(
+        (status = 200, description = "Service provider configuration", body = Value, content_type = "application/scim+json"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn service_provider_config_handler(_tenant: ScimTenant) -> Response {
+    scim_response(StatusCode::OK, service_provider_config())
+}
+
+// Load a user and their group memberships as a SCIM resource
+async fn user_with_groups(app_state: &AppState, organization_id: Uuid, user_id: Uuid) -> Result<Value, ScimError> {
+    let user = get_user(&app_state.db, organization_id, user_id)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get user", e))?
+        .ok_or_else(|| ScimError::not_found(format!("User {} not found", user_id)))?;
+    let memberships = list_group_memberships(&app_state.db, &[user.id], true)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group memberships", e))?;
+    Ok(user_resource(&user, &memberships))
+}
+
+// List users, optionally filtered by userName or externalId
+#[utoipa::path(
+    get,
+    path = "/api/scim/v2/Users",
+    tag = "scim",
+    params(ScimListQuery),
+    responses(
+        (status = 200, description = "SCIM list response of users", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Unsupported filter"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn list_scim_users_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Query(params): Query<ScimListQuery>,
+) -> Result<Response, ScimError> {
+    let (mut email, mut external_id) = (None, None);
+    if let Some(filter) = params.filter.as_deref() {
+        let filter = parse_eq_filter(filter).map_err(ScimError::invalid_filter)?;
+        match filter.attribute.as_str() {
+            "username" => email = Some(filter.value),
+            "externalid" => external_id = Some(filter.value),
+            other => {
+                return Err(ScimError::invalid_filter(format!("Filtering users by '{}' is not supported", other)))
+            }
+        }
+    }
+
+    let (offset, limit) = page(params.start_index, params.count);
+    let (users, total) = list_scim_users(
+        &app_state.db,
+        tenant.organization_id,
+        email.as_deref(),
+        external_id.as_deref(),
+        offset,
+        limit,
+    )
+    .await
+    .map_err(|e| ScimError::internal("Failed to list users", e))?;
+
+    let ids: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+    let memberships = list_group_memberships(&app_state.db, &ids, true)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group memberships", e))?;
+
+    let resources = users.iter().map(|user| user_resource(user, &memberships)).collect();
+    Ok(scim_response(StatusCode::OK, list_response(resources, total, offset)))
+}
+
+// Provision a user
+#[utoipa::path(
+    post,
+    path = "/api/scim/v2/Users",
+    tag = "scim",
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 201, description = "User created", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Invalid user"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 409, description = "A user with this userName already exists"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn create_scim_user_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Json(payload): Json<ScimUserRequest>,
+) -> Result<Response, ScimError> {
+    let email = payload.user_name.trim();
+    if email.is_empty() {
+        return Err(ScimError::invalid_value("userName is required"));
+    }
+    tracing::info!("SCIM provisioning user {} in organization {}", email, tenant.organization_id);
+
+    let user = create_scim_user(
+        &app_state.db,
+        tenant.organization_id,
+        email,
+        payload.display_name().as_deref(),
+        payload.external_id.as_deref(),
+        payload.active.unwrap_or(true),
+    )
+    .await
+    .map_err(|e| ScimError::internal("Failed to create user", e))?
+    .ok_or_else(|| ScimError::conflict(format!("User {} already exists", email)))?;
+
+    tracing::info!("✅ SCIM user created: {}", user.id);
+    Ok(scim_response(StatusCode::CREATED, user_resource(&user, &[])))
+}
+
+// Get one user
+#[utoipa::path(
+    get,
+    path = "/api/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 200, description = "User", body = Value, content_type = "application/scim+json"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "User not found"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn get_scim_user_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response, ScimError> {
+    let resource = user_with_groups(&app_state, tenant.organization_id, user_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Save a user's attributes, mapping a duplicate email to a SCIM conflict
+async fn save_user(
+    app_state: &AppState,
+    organization_id: Uuid,
+    user_id: Uuid,
+    email: &str,
+    display_name: Option<&str>,
+    external_id: Option<&str>,
+    active: bool,
+) -> Result<(), ScimError> {
+    match update_scim_user(&app_state.db, organization_id, user_id, email, display_name, external_id, active).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(ScimError::not_found(format!("User {} not found", user_id))),
+        Err(e) if is_unique_violation(&e) => Err(ScimError::conflict(format!("User {} already exists", email))),
+        Err(e) => Err(ScimError::internal("Failed to update user", e)),
+    }
+}
+
+// Replace a user's attributes
+#[utoipa::path(
+    put,
+    path = "/api/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "User id")),
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "User replaced", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Invalid user"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Another user has this userName"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn replace_scim_user_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<ScimUserRequest>,
+) -> Result<Response, ScimError> {
+    let email = payload.user_name.trim();
+    if email.is_empty() {
+        return Err(ScimError::invalid_value("userName is required"));
+    }
+
+    save_user(
+        &app_state,
+        tenant.organization_id,
+        user_id,
+        email,
+        payload.display_name().as_deref(),
+        payload.external_id.as_deref(),
+        payload.active.unwrap_or(true),
+    )
+    .await?;
+
+    tracing::info!("✅ SCIM user replaced: {}", user_id);
+    let resource = user_with_groups(&app_state, tenant.organization_id, user_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Update some of a user's attributes, e.g. `active` to deactivate them
+#[utoipa::path(
+    patch,
+    path = "/api/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "User id")),
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "User updated", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Unsupported patch operation"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Another user has this userName"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn patch_scim_user_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<PatchRequest>,
+) -> Result<Response, ScimError> {
+    let changes = user_changes(&payload.operations).map_err(ScimError::invalid_value)?;
+    let user = get_user(&app_state.db, tenant.organization_id, user_id)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get user", e))?
+        .ok_or_else(|| ScimError::not_found(format!("User {} not found", user_id)))?;
+
+    let email = changes.user_name.unwrap_or(user.email);
+    save_user(
+        &app_state,
+        tenant.organization_id,
+        user_id,
+        email.trim(),
+        changes.display_name.or(user.display_name).as_deref(),
+        changes.external_id.or(user.external_id).as_deref(),
+        changes.active.unwrap_or(user.active),
+    )
+    .await?;
+
+    tracing::info!("✅ SCIM user updated: {}", user_id);
+    let resource = user_with_groups(&app_state, tenant.organization_id, user_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Deprovision a user
+#[utoipa::path(
+    delete,
+    path = "/api/scim/v2/Users/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "User not found"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn delete_scim_user_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode, ScimError> {
+    match delete_user(&app_state.db, tenant.organization_id, user_id).await {
+        Ok(true) => {
+            tracing::info!("✅ SCIM user deleted: {}", user_id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(ScimError::not_found(format!("User {} not found", user_id))),
+        Err(e) => Err(ScimError::internal("Failed to delete user", e)),
+    }
+}
+
+// Load a group and its members as a SCIM resource
+async fn group_with_members(app_state: &AppState, organization_id: Uuid, group_id: Uuid) -> Result<Value, ScimError> {
+    let group = get_user_group(&app_state.db, organization_id, group_id)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group", e))?
+        .ok_or_else(|| ScimError::not_found(format!("Group {} not found", group_id)))?;
+    let memberships = list_group_memberships(&app_state.db, &[group.id], false)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group members", e))?;
+    Ok(group_resource(&group, &memberships))
+}
+
+// List groups, optionally filtered by displayName or externalId
+#[utoipa::path(
+    get,
+    path = "/api/scim/v2/Groups",
+    tag = "scim",
+    params(ScimListQuery),
+    responses(
+        (status = 200, description = "SCIM list response of groups", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Unsupported filter"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn list_scim_groups_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Query(params): Query<ScimListQuery>,
+) -> Result<Response, ScimError> {
+    let (mut display_name, mut external_id) = (None, None);
+    if let Some(filter) = params.filter.as_deref() {
+        let filter = parse_eq_filter(filter).map_err(ScimError::invalid_filter)?;
+        match filter.attribute.as_str() {
+            "displayname" => display_name = Some(filter.value),
+            "externalid" => external_id = Some(filter.value),
+            other => {
+                return Err(ScimError::invalid_filter(format!("Filtering groups by '{}' is not supported", other)))
+            }
+        }
+    }
+
+    let (offset, limit) = page(params.start_index, params.count);
+    let (groups, total) = list_user_groups(
+        &app_state.db,
+        tenant.organization_id,
+        display_name.as_deref(),
+        external_id.as_deref(),
+        offset,
+        limit,
+    )
+    .await
+    .map_err(|e| ScimError::internal("Failed to list groups", e))?;
+
+    let ids: Vec<Uuid> = groups.iter().map(|group| group.id).collect();
+    let memberships = list_group_memberships(&app_state.db, &ids, false)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group members", e))?;
+
+    let resources = groups.iter().map(|group| group_resource(group, &memberships)).collect();
+    Ok(scim_response(StatusCode::OK, list_response(resources, total, offset)))
+}
+
+// Provision a group with its initial members
+#[utoipa::path(
+    post,
+    path = "/api/scim/v2/Groups",
+    tag = "scim",
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 201, description = "Group created", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Invalid group"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 409, description = "A group with this displayName already exists"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn create_scim_group_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Json(payload): Json<ScimGroupRequest>,
+) -> Result<Response, ScimError> {
+    let display_name = payload.display_name.trim();
+    if display_name.is_empty() {
+        return Err(ScimError::invalid_value("displayName is required"));
+    }
+    let members = member_ids(&payload.members).map_err(ScimError::invalid_value)?;
+    tracing::info!("SCIM provisioning group {} in organization {}", display_name, tenant.organization_id);
+
+    let created =
+        create_user_group(&app_state.db, tenant.organization_id, display_name, payload.external_id.as_deref()).await;
+    let group = match created {
+        Ok(group) => group,
+        Err(e) if is_unique_violation(&e) => {
+            return Err(ScimError::conflict(format!("Group {} already exists", display_name)))
+        }
+        Err(e) => return Err(ScimError::internal("Failed to create group", e)),
+    };
+    change_group_members(&app_state.db, tenant.organization_id, group.id, false, &members, &[])
+        .await
+        .map_err(|e| ScimError::internal("Failed to add group members", e))?;
+
+    tracing::info!("✅ SCIM group created: {}", group.id);
+    let resource = group_with_members(&app_state, tenant.organization_id, group.id).await?;
+    Ok(scim_response(StatusCode::CREATED, resource))
+}
+
+// Get one group with its members
+#[utoipa::path(
+    get,
+    path = "/api/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "Group id")),
+    responses(
+        (status = 200, description = "Group", body = Value, content_type = "application/scim+json"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "Group not found"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn get_scim_group_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(group_id): Path<Uuid>,
+) -> Result<Response, ScimError> {
+    let resource = group_with_members(&app_state, tenant.organization_id, group_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Save a group's attributes, mapping a duplicate name to a SCIM conflict
+async fn save_group(
+    app_state: &AppState,
+    organization_id: Uuid,
+    group_id: Uuid,
+    display_name: &str,
+    external_id: Option<&str>,
+) -> Result<(), ScimError> {
+    match update_user_group(&app_state.db, organization_id, group_id, display_name, external_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(ScimError::not_found(format!("Group {} not found", group_id))),
+        Err(e) if is_unique_violation(&e) => Err(ScimError::conflict(format!("Group {} already exists", display_name))),
+        Err(e) => Err(ScimError::internal("Failed to update group", e)),
+    }
+}
+
+// Replace a group's attributes and members
+#[utoipa::path(
+    put,
+    path = "/api/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "Group id")),
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "Group replaced", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Invalid group"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "Group not found"),
+        (status = 409, description = "Another group has this displayName"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn replace_scim_group_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(group_id): Path<Uuid>,
+    Json(payload): Json<ScimGroupRequest>,
+) -> Result<Response, ScimError> {
+    let display_name = payload.display_name.trim();
+    if display_name.is_empty() {
+        return Err(ScimError::invalid_value("displayName is required"));
+    }
+    let members = member_ids(&payload.members).map_err(ScimError::invalid_value)?;
+
+    save_group(&app_state, tenant.organization_id, group_id, display_name, payload.external_id.as_deref()).await?;
+    change_group_members(&app_state.db, tenant.organization_id, group_id, true, &members, &[])
+        .await
+        .map_err(|e| ScimError::internal("Failed to replace group members", e))?;
+
+    tracing::info!("✅ SCIM group replaced: {}", group_id);
+    let resource = group_with_members(&app_state, tenant.organization_id, group_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Add or remove members, or rename a group
+#[utoipa::path(
+    patch,
+    path = "/api/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "Group id")),
+    request_body(content = Value, content_type = "application/scim+json"),
+    responses(
+        (status = 200, description = "Group updated", body = Value, content_type = "application/scim+json"),
+        (status = 400, description = "Unsupported patch operation"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "Group not found"),
+        (status = 409, description = "Another group has this displayName"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn patch_scim_group_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(group_id): Path<Uuid>,
+    Json(payload): Json<PatchRequest>,
+) -> Result<Response, ScimError> {
+    let changes = group_changes(&payload.operations).map_err(ScimError::invalid_value)?;
+    let group = get_user_group(&app_state.db, tenant.organization_id, group_id)
+        .await
+        .map_err(|e| ScimError::internal("Failed to get group", e))?
+        .ok_or_else(|| ScimError::not_found(format!("Group {} not found", group_id)))?;
+
+    if changes.display_name.is_some() || changes.external_id.is_some() {
+        let display_name = changes.display_name.unwrap_or(group.display_name);
+        save_group(
+            &app_state,
+            tenant.organization_id,
+            group_id,
+            display_name.trim(),
+            changes.external_id.or(group.external_id).as_deref(),
+        )
+        .await?;
+    }
+    change_group_members(
+        &app_state.db,
+        tenant.organization_id,
+        group_id,
+        changes.replace_members,
+        &changes.add_members,
+        &changes.remove_members,
+    )
+    .await
+    .map_err(|e| ScimError::internal("Failed to change group members", e))?;
+
+    tracing::info!("✅ SCIM group updated: {}", group_id);
+    let resource = group_with_members(&app_state, tenant.organization_id, group_id).await?;
+    Ok(scim_response(StatusCode::OK, resource))
+}
+
+// Deprovision a group; its members stay provisioned
+#[utoipa::path(
+    delete,
+    path = "/api/scim/v2/Groups/{id}",
+    tag = "scim",
+    params(("id" = Uuid, Path, description = "Group id")),
+    responses(
+        (status = 204, description = "Group deleted"),
+        (status = 401, description = "Missing or unknown SCIM token"),
+        (status = 404, description = "Group not found"),
+    ),
+    security(("scim_token" = []))
+)]
+pub async fn delete_scim_group_handler(
+    State(app_state): State<AppState>,
+    tenant: ScimTenant,
+    Path(group_id): Path<Uuid>,
+) -> Result<StatusCode, ScimError> {
+    match delete_user_group(&app_state.db, tenant.organization_id, group_id).await {
+        Ok(true) => {
+            tracing::info!("✅ SCIM group deleted: {}", group_id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(ScimError::not_found(format!("Group {} not found", group_id))),
+        Err(e) => Err(ScimError::internal("Failed to delete group", e)),
+    }
+}
+
+// Create the router for SCIM provisioning
+pub fn create_scim_router() -> Router<AppState> {
+    Router::new()
+        .route("/organizations/scim-token", post(create_scim_token_handler))
+        .route("/scim/v2/ServiceProviderConfig", get(service_provider_config_handler))
+        .route("/scim/v2/Users", get(list_scim_users_handler).post(create_scim_user_handler))
+        .route(
+            "/scim/v2/Users/{id}",
+            get(get_scim_user_handler)
+                .put(replace_scim_user_handler)
+                .patch(patch_scim_user_handler)
+                .delete(delete_scim_user_handler),
+        )
+        .route("/scim/v2/Groups", get(list_scim_groups_handler).post(create_scim_group_handler))
+        .route(
+            "/scim/v2/Groups/{id}",
+            get(get_scim_group_handler)
+                .put(replace_scim_group_handler)
+                .patch(patch_scim_group_handler)
+                .delete(delete_scim_group_handler),
+        )
+}
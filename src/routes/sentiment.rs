@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{get_retained_chat_bot, get_sentiment_summary};
+use crate::middleware::auth::Tenant;
+use crate::services::usage::usage_range;
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SentimentQuery {
+    /// First UTC day to include (YYYY-MM-DD); defaults to 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last UTC day to include (YYYY-MM-DD); defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+// Sentiment and intent counts for a chatbot's conversations, with the share of frustrated users
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/sentiment",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), SentimentQuery),
+    responses(
+        (status = 200, description = "Label counts and frustrated rate for the range", body = Value),
+        (status = 400, description = "Invalid date range"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_chatbot_sentiment_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<SentimentQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching sentiment for chatbot: {}", chatbot_id);
+
+    let (from, to) = usage_range(params.from, params.to, chrono::Utc::now().date_naive()).map_err(|e| {
+        tracing::error!("Invalid sentiment range: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let summary = get_sentiment_summary(&app_state.db, tenant.organization_id, chatbot_id, from, to).await.map_err(|e| {
+        tracing::error!("❌ Failed to summarize sentiment: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let frustrated_rate = if summary.labelled > 0 {
+        summary.frustrated as f64 / summary.labelled as f64
+    } else {
+        0.0
+    };
+
+    tracing::info!("✅ Retrieved sentiment for {} labelled turns", summary.labelled);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Sentiment retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "organization_id": tenant.organization_id,
+            "from": from,
+            "to": to,
+            "frustrated_rate": frustrated_rate,
+            "summary": summary
+        }
+    })))
+}
+
+// Create the router for sentiment analytics
+pub fn create_sentiment_router() -> Router<AppState> {
+    Router::new().route("/chatbots/{id}/sentiment", get(get_chatbot_sentiment_handler))
+}
@@ -0,0 +1,435 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, post, put},
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::{CreateSqlConnectorRequest, UpsertSqlToolRequest};
+use crate::db::queries::{
+    create_sql_connector, delete_document_usage, delete_sql_connector, delete_sql_tool, get_sql_connector,
+    get_sql_tool, list_sql_connector_rows, list_sql_connectors, remove_cold_document, upsert_sql_tool,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::sharding::shard_indices;
+use crate::services::sql_connector::{
+    check_database_url, row_path, run_sync, validate_query, DEFAULT_INTERVAL_MINUTES, MIN_INTERVAL_MINUTES,
+};
+use crate::services::sql_tool::{describe_schema, is_valid_table_name};
+use crate::services::vector::{chatbot_index_name, VectorStore};
+use crate::utils::config::AppState;
+
+// Add a SQL connector to a chatbot. Its first sync runs within a minute
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/sql-connectors",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = CreateSqlConnectorRequest,
+    responses(
+        (status = 200, description = "Connector created", body = Value),
+        (status = 400, description = "Invalid name, URL, query or interval"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "The chatbot already has a connector with this name"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_sql_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(mut payload): Json<CreateSqlConnectorRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating SQL connector for chatbot: {}", chatbot_id);
+
+    payload.name = payload.name.trim().to_string();
+    payload.primary_key = payload.primary_key.trim().to_string();
+    if payload.name.is_empty() || payload.name.len() > 100 || payload.name.contains('/') {
+        tracing::error!("SQL connector name must be 1-100 characters without '/'");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = check_database_url(&payload.database_url).await {
+        tracing::error!("Invalid SQL connector database URL: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.primary_key.is_empty() {
+        tracing::error!("SQL connector needs a primary key column");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Err(e) = validate_query(&payload.query) {
+        tracing::error!("Invalid SQL connector query: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let interval_minutes = payload.interval_minutes.unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    if interval_minutes < MIN_INTERVAL_MINUTES {
+        tracing::error!("SQL connector interval must be at least {} minutes", MIN_INTERVAL_MINUTES);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match create_sql_connector(&app_state.db, tenant.organization_id, chatbot_id, &payload, interval_minutes).await {
+        Ok(connector) => {
+            tracing::info!("✅ SQL connector created: {}", connector.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "SQL connector created successfully",
+                "data": connector
+            })))
+        }
+        Err(AppError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            tracing::error!("SQL connector '{}' already exists for chatbot {}", payload.name, chatbot_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create SQL connector: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List a chatbot's SQL connectors with their last sync, without their database URLs
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/sql-connectors",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's SQL connectors", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_sql_connectors_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match list_sql_connectors(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(connectors) => Ok(Json(json!({
+            "success": true,
+            "message": "SQL connectors retrieved successfully",
+            "data": connectors,
+            "count": connectors.len()
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list SQL connectors: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Sync a connector now instead of waiting for its next scheduled run
+#[utoipa::path(
+    post,
+    path = "/api/sql-connectors/{id}/sync",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "SQL connector id")),
+    responses(
+        (status = 200, description = "Connector synced", body = Value),
+        (status = 404, description = "Connector not found"),
+        (status = 502, description = "The query or the sync failed; the error is kept on the connector"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn sync_sql_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(connector_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Syncing SQL connector: {}", connector_id);
+
+    let connector = match get_sql_connector(&app_state.db, tenant.organization_id, connector_id).await {
+        Ok(Some(connector)) => connector,
+        Ok(None) => {
+            tracing::error!("SQL connector not found: {}", connector_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let sync = run_sync(
+        &app_state.db,
+        &app_state.vector_store,
+        app_state.embedding_cache.clone(),
+        &app_state.chatbot_cache,
+        &connector,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to sync SQL connector {}: {}", connector.name, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "SQL connector synced successfully",
+        "data": {
+            "connector_id": connector.id,
+            "chatbot_id": connector.chatbot_id,
+            "rows": sync.rows,
+            "updated": sync.updated,
+            "removed": sync.removed,
+            "embedding_count": sync.embedding_count
+        }
+    })))
+}
+
+// Delete a connector and the chunks of every row it indexed
+#[utoipa::path(
+    delete,
+    path = "/api/sql-connectors/{id}",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "SQL connector id")),
+    responses(
+        (status = 200, description = "Connector and its chunks deleted", body = Value),
+        (status = 404, description = "Connector not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_sql_connector_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(connector_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting SQL connector: {}", connector_id);
+
+    let connector = match get_sql_connector(&app_state.db, tenant.organization_id, connector_id).await {
+        Ok(Some(connector)) => connector,
+        Ok(None) => {
+            tracing::error!("SQL connector not found: {}", connector_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let rows = list_sql_connector_rows(&app_state.db, connector.id).await.map_err(|e| {
+        tracing::error!("❌ Failed to list SQL connector rows: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Shards are looked up fresh; the chatbot may have grown since the rows were indexed
+    let shard_count = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, connector.chatbot_id).await {
+        Ok(chatbot) => chatbot.map(|chatbot| chatbot.shard_count).unwrap_or(1),
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let index_names = shard_indices(&chatbot_index_name(tenant.organization_id, connector.chatbot_id), shard_count);
+    for row in &rows {
+        let file_path = row_path(&connector.name, &row.row_key);
+        if let Err(e) = app_state.vector_store.delete_document_chunks(&index_names, &file_path).await {
+            tracing::error!("❌ Failed to delete chunks of {}: {}", file_path, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        if let Err(e) = remove_cold_document(&app_state.db, connector.chatbot_id, &file_path).await {
+            tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", file_path, e);
+        }
+        if let Err(e) = delete_document_usage(&app_state.db, connector.chatbot_id, &file_path).await {
+            tracing::warn!("⚠️ Failed to delete document usage of {}: {}", file_path, e);
+        }
+    }
+
+    if let Err(e) = delete_sql_connector(&app_state.db, tenant.organization_id, connector.id).await {
+        tracing::error!("❌ Failed to delete SQL connector: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: connector.chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    tracing::info!("✅ SQL connector deleted: {} ({} rows)", connector.id, rows.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "SQL connector deleted successfully",
+        "data": {
+            "connector_id": connector.id,
+            "rows_removed": rows.len()
+        }
+    })))
+}
+
+// Let the chatbot answer analytical questions by querying whitelisted tables. The database is
+// checked before the tool is saved
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/sql-tool",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpsertSqlToolRequest,
+    responses(
+        (status = 200, description = "SQL tool saved", body = Value),
+        (status = 400, description = "Invalid URL or table names"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 502, description = "The database can't be reached or a whitelisted table doesn't exist"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_sql_tool_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(mut payload): Json<UpsertSqlToolRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Saving SQL tool for chatbot: {}", chatbot_id);
+
+    if let Err(e) = check_database_url(&payload.database_url).await {
+        tracing::error!("Invalid SQL tool database URL: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    payload.allowed_tables = payload.allowed_tables.iter().map(|table| table.trim().to_string()).collect();
+    payload.allowed_tables.sort();
+    payload.allowed_tables.dedup();
+    if payload.allowed_tables.is_empty() {
+        tracing::error!("SQL tool needs at least one whitelisted table");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(table) = payload.allowed_tables.iter().find(|table| !is_valid_table_name(table)) {
+        tracing::error!("Invalid table name for SQL tool: {}", table);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let schema = describe_schema(&payload.database_url, &payload.allowed_tables).await.map_err(|e| {
+        tracing::error!("❌ Failed to read SQL tool schema: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    match upsert_sql_tool(&app_state.db, tenant.organization_id, chatbot_id, &payload).await {
+        Ok(tool) => {
+            tracing::info!("✅ SQL tool saved for chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "SQL tool saved successfully",
+                "data": tool,
+                "schema": schema
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save SQL tool: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Get a chatbot's SQL tool, without its database URL
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/sql-tool",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's SQL tool", body = Value),
+        (status = 404, description = "The chatbot has no SQL tool"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_sql_tool_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_sql_tool(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(tool)) => Ok(Json(json!({
+            "success": true,
+            "message": "SQL tool retrieved successfully",
+            "data": tool
+        }))),
+        Ok(None) => {
+            tracing::error!("No SQL tool for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get SQL tool: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Remove a chatbot's SQL tool; its answers go back to documents only
+#[utoipa::path(
+    delete,
+    path = "/api/chatbots/{id}/sql-tool",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "SQL tool removed", body = Value),
+        (status = 404, description = "The chatbot has no SQL tool"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_sql_tool_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match delete_sql_tool(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(true) => {
+            tracing::info!("✅ SQL tool removed from chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "SQL tool removed successfully",
+                "data": { "chatbot_id": chatbot_id }
+            })))
+        }
+        Ok(false) => {
+            tracing::error!("No SQL tool for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to remove SQL tool: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for SQL connectors and the SQL tool
+pub fn create_sql_connector_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/sql-connectors",
+            post(create_sql_connector_handler).get(list_sql_connectors_handler),
+        )
+        .route("/sql-connectors/{id}", delete(delete_sql_connector_handler))
+        .route("/sql-connectors/{id}/sync", post(sync_sql_connector_handler))
+        .route(
+            "/chatbots/{id}/sql-tool",
+            put(update_sql_tool_handler).get(get_sql_tool_handler).delete(delete_sql_tool_handler),
+        )
+}
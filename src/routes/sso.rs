@@ -0,0 +1,243 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Redirect},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+
+use crate::db::queries::{
+    create_admin_session, create_oidc_login_state, delete_admin_session, delete_expired_sso_rows,
+    get_admin_session, take_oidc_login_state,
+};
+use crate::middleware::auth::{bearer_token, generate_admin_session_token, hash_api_key};
+use crate::services::oidc::{
+    authorization_url, role_for_groups, OidcClient, OidcConfig, PendingLogin, LOGIN_STATE_MAX_AGE_SECS,
+};
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SsoCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set by the identity provider when sign-in failed or was cancelled
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+// The configured identity provider client; 404 when single sign-on is off
+fn oidc_client() -> Result<OidcClient, StatusCode> {
+    match OidcConfig::from_env() {
+        Ok(Some(config)) => OidcClient::new(config).map_err(|e| {
+            tracing::error!("❌ Failed to create OIDC client: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }),
+        Ok(None) => {
+            tracing::warn!("Single sign-on requested but OIDC_ISSUER_URL is not set");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Invalid single sign-on configuration: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Start single sign-on by redirecting to the identity provider
+#[utoipa::path(
+    get,
+    path = "/api/admin/sso/login",
+    tag = "sso",
+    responses(
+        (status = 303, description = "Redirect to the identity provider's sign-in page"),
+        (status = 404, description = "Single sign-on is not configured"),
+        (status = 502, description = "Identity provider unreachable"),
+    )
+)]
+pub async fn sso_login_handler(State(app_state): State<AppState>) -> Result<Redirect, StatusCode> {
+    let client = oidc_client()?;
+    let metadata = client.discover().await.map_err(|e| {
+        tracing::error!("❌ Failed to load OIDC discovery document: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    if let Err(e) = delete_expired_sso_rows(&app_state.db, LOGIN_STATE_MAX_AGE_SECS).await {
+        tracing::warn!("⚠️ Failed to clean up expired SSO rows: {}", e);
+    }
+
+    let login = PendingLogin::generate();
+    create_oidc_login_state(&app_state.db, &login.state, &login.nonce, &login.code_verifier)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to store SSO login state: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let url = authorization_url(client.config(), &metadata, &login).map_err(|e| {
+        tracing::error!("❌ Invalid authorization endpoint: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    Ok(Redirect::to(&url))
+}
+
+// Finish single sign-on: verify the identity provider's response and issue an admin session
+#[utoipa::path(
+    get,
+    path = "/api/admin/sso/callback",
+    tag = "sso",
+    params(SsoCallbackQuery),
+    responses(
+        (status = 200, description = "Session token and role", body = Value),
+        (status = 400, description = "Missing, unknown or expired state"),
+        (status = 401, description = "Sign-in failed or the ID token was rejected"),
+        (status = 403, description = "None of the user's groups maps to a role"),
+        (status = 404, description = "Single sign-on is not configured"),
+    )
+)]
+pub async fn sso_callback_handler(
+    State(app_state): State<AppState>,
+    Query(params): Query<SsoCallbackQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let client = oidc_client()?;
+
+    if let Some(error) = &params.error {
+        tracing::warn!("Identity provider returned error {}: {:?}", error, params.error_description);
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let (Some(code), Some(state)) = (params.code.as_deref(), params.state.as_deref()) else {
+        tracing::error!("SSO callback without code or state");
+        return Err(StatusCode::BAD_REQUEST);
+    };
+
+    let login = match take_oidc_login_state(&app_state.db, state, LOGIN_STATE_MAX_AGE_SECS).await {
+        Ok(Some(login)) => PendingLogin {
+            state: login.state,
+            nonce: login.nonce,
+            code_verifier: login.code_verifier,
+        },
+        Ok(None) => {
+            tracing::error!("Unknown or expired SSO state");
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to load SSO login state: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let metadata = client.discover().await.map_err(|e| {
+        tracing::error!("❌ Failed to load OIDC discovery document: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    let identity = client.exchange_code(&metadata, code, &login).await.map_err(|e| {
+        tracing::error!("❌ SSO sign-in failed: {}", e);
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let Some(role) = role_for_groups(&client.config().role_mapping, &identity.groups) else {
+        tracing::warn!("SSO user {} has no group mapped to an admin role", identity.subject);
+        return Err(StatusCode::FORBIDDEN);
+    };
+
+    let token = generate_admin_session_token();
+    let session = create_admin_session(
+        &app_state.db,
+        &hash_api_key(&token),
+        &identity.subject,
+        identity.email.as_deref(),
+        role.as_str(),
+        client.config().session_ttl.as_secs() as i64,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to create admin session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ SSO user {} signed in as {}", identity.subject, role);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Signed in successfully",
+        "data": {
+            "token": token,
+            "session": session
+        }
+    })))
+}
+
+// The admin session the bearer token belongs to
+#[utoipa::path(
+    get,
+    path = "/api/admin/sso/session",
+    tag = "sso",
+    responses(
+        (status = 200, description = "Current admin session", body = Value),
+        (status = 401, description = "Missing, unknown or expired session token"),
+    ),
+    security(("admin_session" = []))
+)]
+pub async fn get_sso_session_handler(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, StatusCode> {
+    let token = bearer_token(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+
+    match get_admin_session(&app_state.db, &hash_api_key(token)).await {
+        Ok(Some(session)) => Ok(Json(json!({
+            "success": true,
+            "message": "Session retrieved successfully",
+            "data": session
+        }))),
+        Ok(None) => Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            tracing::error!("❌ Failed to get admin session: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// End the admin session the bearer token belongs to
+#[utoipa::path(
+    post,
+    path = "/api/admin/sso/logout",
+    tag = "sso",
+    responses(
+        (status = 200, description = "Session ended", body = Value),
+        (status = 401, description = "Missing or unknown session token"),
+    ),
+    security(("admin_session" = []))
+)]
+pub async fn sso_logout_handler(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, StatusCode> {
+    let token = bearer_token(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+
+    match delete_admin_session(&app_state.db, &hash_api_key(token)).await {
+        Ok(true) => {
+            tracing::info!("✅ Admin session ended");
+            Ok(Json(json!({
+                "success": true,
+                "message": "Signed out successfully"
+            })))
+        }
+        Ok(false) => Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            tracing::error!("❌ Failed to end admin session: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Create the router for admin single sign-on
+pub fn create_sso_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/sso/login", get(sso_login_handler))
+        .route("/admin/sso/callback", get(sso_callback_handler))
+        .route("/admin/sso/session", get(get_sso_session_handler))
+        .route("/admin/sso/logout", post(sso_logout_handler))
+}
@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::db::queries::{get_retained_chat_bot, list_daily_usage};
+use crate::middleware::auth::Tenant;
+use crate::services::usage::{usage_range, usage_totals};
+use crate::utils::config::AppState;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UsageQuery {
+    /// First UTC day to include (YYYY-MM-DD); defaults to 30 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last UTC day to include (YYYY-MM-DD); defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+// Daily model tokens, embedding calls and searches for a chatbot
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/usage",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id"), UsageQuery),
+    responses(
+        (status = 200, description = "Daily usage and totals for the range", body = Value),
+        (status = 400, description = "Invalid date range"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_chatbot_usage_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Query(params): Query<UsageQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Fetching usage for chatbot: {}", chatbot_id);
+
+    let (from, to) = usage_range(params.from, params.to, chrono::Utc::now().date_naive()).map_err(|e| {
+        tracing::error!("Invalid usage range: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    match get_retained_chat_bot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("Failed to get chatbot: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let days = list_daily_usage(&app_state.db, tenant.organization_id, chatbot_id, from, to).await.map_err(|e| {
+        tracing::error!("❌ Failed to aggregate usage: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("✅ Retrieved usage for {} days", days.len());
+    Ok(Json(json!({
+        "success": true,
+        "message": "Usage retrieved successfully",
+        "data": {
+            "chatbot_id": chatbot_id,
+            "organization_id": tenant.organization_id,
+            "from": from,
+            "to": to,
+            "totals": usage_totals(&days),
+            "days": days
+        }
+    })))
+}
+
+// Create the router for usage reports
+pub fn create_usage_router() -> Router<AppState> {
+    Router::new().route("/chatbots/{id}/usage", get(get_chatbot_usage_handler))
+}
@@ -0,0 +1,261 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, post},
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::CreateWebSourceRequest;
+use crate::db::queries::{
+    create_web_source, delete_document_usage, delete_web_source, get_web_source, list_web_sources,
+    remove_cold_document,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::Tenant;
+use crate::services::cache_invalidation::{publish, CacheEvent};
+use crate::services::sharding::shard_indices;
+use crate::services::vector::{chatbot_index_name, VectorStore};
+use crate::services::web_source::{run_crawl, validate_source_url, DEFAULT_INTERVAL_MINUTES, MIN_INTERVAL_MINUTES};
+use crate::utils::config::AppState;
+
+// Add a web page to a chatbot's knowledge. Its first crawl runs within a minute
+#[utoipa::path(
+    post,
+    path = "/api/chatbots/{id}/web-sources",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = CreateWebSourceRequest,
+    responses(
+        (status = 200, description = "Web source created", body = Value),
+        (status = 400, description = "Invalid URL or interval"),
+        (status = 404, description = "Chatbot not found"),
+        (status = 409, description = "The chatbot already has this URL"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn create_web_source_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(payload): Json<CreateWebSourceRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Creating web source for chatbot: {}", chatbot_id);
+
+    let url = payload.url.trim();
+    if let Err(e) = validate_source_url(url).await {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let interval_minutes = payload.interval_minutes.unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    if interval_minutes < MIN_INTERVAL_MINUTES {
+        tracing::error!("Web source interval must be at least {} minutes", MIN_INTERVAL_MINUTES);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match create_web_source(&app_state.db, tenant.organization_id, chatbot_id, url, interval_minutes).await {
+        Ok(source) => {
+            tracing::info!("✅ Web source created: {}", source.id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Web source created successfully",
+                "data": source
+            })))
+        }
+        Err(AppError::Database(sqlx::Error::Database(e))) if e.is_unique_violation() => {
+            tracing::error!("Web source {} already exists for chatbot {}", url, chatbot_id);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to create web source: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// List a chatbot's web sources with their last crawl
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/web-sources",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's web sources", body = Value),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn list_web_sources_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match list_web_sources(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(sources) => Ok(Json(json!({
+            "success": true,
+            "message": "Web sources retrieved successfully",
+            "data": sources,
+            "count": sources.len()
+        }))),
+        Err(e) => {
+            tracing::error!("❌ Failed to list web sources: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Crawl a source now instead of waiting for its next scheduled run
+#[utoipa::path(
+    post,
+    path = "/api/web-sources/{id}/crawl",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Web source id")),
+    responses(
+        (status = 200, description = "Page crawled, and re-embedded if it changed", body = Value),
+        (status = 404, description = "Web source not found"),
+        (status = 502, description = "The fetch or the indexing failed; the error is kept on the source"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn crawl_web_source_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(source_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Crawling web source: {}", source_id);
+
+    let source = match get_web_source(&app_state.db, tenant.organization_id, source_id).await {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            tracing::error!("Web source not found: {}", source_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let crawl = run_crawl(
+        &app_state.db,
+        &app_state.vector_store,
+        app_state.embedding_cache.clone(),
+        &app_state.chatbot_cache,
+        &source,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("❌ Failed to crawl web source {}: {}", source.url, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Web source crawled successfully",
+        "data": {
+            "source_id": source.id,
+            "chatbot_id": source.chatbot_id,
+            "url": source.url,
+            "changed": crawl.changed,
+            "embedding_count": crawl.embedding_count
+        }
+    })))
+}
+
+// Delete a web source and its page's chunks
+#[utoipa::path(
+    delete,
+    path = "/api/web-sources/{id}",
+    tag = "knowledge",
+    params(("id" = Uuid, Path, description = "Web source id")),
+    responses(
+        (status = 200, description = "Web source and its chunks deleted", body = Value),
+        (status = 404, description = "Web source not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn delete_web_source_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(source_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Deleting web source: {}", source_id);
+
+    let source = match get_web_source(&app_state.db, tenant.organization_id, source_id).await {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            tracing::error!("Web source not found: {}", source_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // Shards are looked up fresh; the chatbot may have grown since the page was indexed
+    let shard_count = match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, source.chatbot_id).await {
+        Ok(chatbot) => chatbot.map(|chatbot| chatbot.shard_count).unwrap_or(1),
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let index_names = shard_indices(&chatbot_index_name(tenant.organization_id, source.chatbot_id), shard_count);
+    if let Err(e) = app_state.vector_store.delete_document_chunks(&index_names, &source.url).await {
+        tracing::error!("❌ Failed to delete chunks of {}: {}", source.url, e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    if let Err(e) = remove_cold_document(&app_state.db, source.chatbot_id, &source.url).await {
+        tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", source.url, e);
+    }
+    if let Err(e) = delete_document_usage(&app_state.db, source.chatbot_id, &source.url).await {
+        tracing::warn!("⚠️ Failed to delete document usage of {}: {}", source.url, e);
+    }
+
+    if let Err(e) = delete_web_source(&app_state.db, tenant.organization_id, source.id).await {
+        tracing::error!("❌ Failed to delete web source: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    if let Err(e) = publish(
+        &app_state.db,
+        &app_state.chatbot_cache,
+        CacheEvent::KnowledgeVersionChanged { chatbot_id: source.chatbot_id },
+    ).await {
+        tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+    }
+
+    tracing::info!("✅ Web source deleted: {} ({})", source.id, source.url);
+    Ok(Json(json!({
+        "success": true,
+        "message": "Web source deleted successfully",
+        "data": {
+            "source_id": source.id,
+            "url": source.url
+        }
+    })))
+}
+
+// Create the router for web sources
+pub fn create_web_source_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/web-sources",
+            post(create_web_source_handler).get(list_web_sources_handler),
+        )
+        .route("/web-sources/{id}", delete(delete_web_source_handler))
+        .route("/web-sources/{id}/crawl", post(crawl_web_source_handler))
+}
@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::UpsertWidgetConfigRequest;
+use crate::db::queries::{get_public_widget_config, get_widget_config, upsert_widget_config};
+use crate::middleware::auth::Tenant;
+use crate::services::widget_config::{origin_allowed, validate_widget_config};
+use crate::utils::config::AppState;
+
+// Set the colors, avatar, position, launcher text and allowed origins of a chatbot's widget. The
+// widget reads them when it loads, so changes show up without a redeploy
+#[utoipa::path(
+    put,
+    path = "/api/chatbots/{id}/widget-config",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    request_body = UpsertWidgetConfigRequest,
+    responses(
+        (status = 200, description = "Widget config saved", body = Value),
+        (status = 400, description = "Invalid color, URL, position, launcher text or origin"),
+        (status = 404, description = "Chatbot not found"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn update_widget_config_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+    Json(mut payload): Json<UpsertWidgetConfigRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    tracing::info!("Saving widget config for chatbot: {}", chatbot_id);
+
+    if let Err(e) = validate_widget_config(&mut payload) {
+        tracing::error!("{}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match app_state.chatbot_cache.get_chatbot(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            tracing::error!("❌ Chatbot not found: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Database error: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    match upsert_widget_config(&app_state.db, tenant.organization_id, chatbot_id, &payload).await {
+        Ok(config) => {
+            tracing::info!("✅ Widget config saved for chatbot {}", chatbot_id);
+            Ok(Json(json!({
+                "success": true,
+                "message": "Widget config saved successfully",
+                "data": config
+            })))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to save widget config: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Get a chatbot's widget config
+#[utoipa::path(
+    get,
+    path = "/api/chatbots/{id}/widget-config",
+    tag = "chatbots",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "The chatbot's widget config", body = Value),
+        (status = 404, description = "The chatbot has no widget config"),
+    ),
+    security(("api_key" = []))
+)]
+pub async fn get_widget_config_handler(
+    State(app_state): State<AppState>,
+    tenant: Tenant,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match get_widget_config(&app_state.db, tenant.organization_id, chatbot_id).await {
+        Ok(Some(config)) => Ok(Json(json!({
+            "success": true,
+            "message": "Widget config retrieved successfully",
+            "data": config
+        }))),
+        Ok(None) => {
+            tracing::error!("No widget config for chatbot: {}", chatbot_id);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get widget config: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Theme for the widget bundle to render; needs no API key. Pages outside the allowed origins are
+// refused
+#[utoipa::path(
+    get,
+    path = "/api/widget/chatbots/{id}/config",
+    tag = "widget",
+    params(("id" = Uuid, Path, description = "Chatbot id")),
+    responses(
+        (status = 200, description = "Colors, avatar, position and launcher text", body = Value),
+        (status = 403, description = "The page's origin may not embed this chatbot"),
+        (status = 404, description = "Chatbot not found, not active or without a widget config"),
+    )
+)]
+pub async fn get_public_widget_config_handler(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(chatbot_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    let config = match get_public_widget_config(&app_state.db, chatbot_id).await {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            tracing::error!("No widget config for chatbot: {}", chatbot_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to get widget config: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let origin = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok());
+    if !origin_allowed(&config.allowed_origins, origin) {
+        tracing::warn!("Widget of chatbot {} loaded from disallowed origin {:?}", chatbot_id, origin);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Widget configuration retrieved successfully",
+        "data": {
+            "chatbot_id": config.chatbot_id,
+            "primary_color": config.primary_color,
+            "background_color": config.background_color,
+            "text_color": config.text_color,
+            "avatar_url": config.avatar_url,
+            "position": config.position,
+            "launcher_text": config.launcher_text,
+            "updated_at": config.updated_at
+        }
+    })))
+}
+
+// Create the router for widget configs
+pub fn create_widget_config_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/chatbots/{id}/widget-config",
+            get(get_widget_config_handler).put(update_widget_config_handler),
+        )
+        .route("/widget/chatbots/{id}/config", get(get_public_widget_config_handler))
+}
@@ -0,0 +1,222 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::services::candle_embedding::CandleEmbeddingService;
+use crate::services::vector::SearchResult;
+
+const DEFAULT_THRESHOLD: f32 = 0.95;
+const DEFAULT_TTL_SECS: u64 = 3600;
+const DEFAULT_CAPACITY: usize = 500;
+
+/// The chatbot's knowledge and settings versions an answer was generated with. A cached answer is
+/// only reused while both are unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnswerVersion {
+    pub knowledge: u64,
+    pub settings: u64,
+}
+
+/// A question that may be answered from the cache, and whose answer may be cached
+#[derive(Debug, Clone)]
+pub struct CacheableQuery {
+    pub chatbot_id: Uuid,
+    pub text: String,
+    pub version: AnswerVersion,
+    pub embedding: Vec<f32>,
+}
+
+/// A cached answer close enough to the question
+#[derive(Debug, Clone)]
+pub struct AnswerHit {
+    /// The question the answer was generated for
+    pub query: String,
+    pub answer: String,
+    /// The chunks the answer was grounded on
+    pub sources: Vec<SearchResult>,
+    pub similarity: f32,
+}
+
+#[derive(Debug)]
+struct Entry {
+    version: AnswerVersion,
+    embedding: Vec<f32>,
+    query: String,
+    answer: String,
+    sources: Vec<SearchResult>,
+    stored_at: Instant,
+}
+
+/// Recently generated answers per chatbot, looked up by the embedding of the question.
+///
+/// Entries live in memory on each replica. Ones older than the TTL or generated with other
+/// knowledge or settings versions are dropped as they are found, and the oldest are evicted once a
+/// chatbot has `capacity` of them.
+pub struct AnswerCache {
+    threshold: f32,
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<Uuid, VecDeque<Entry>>>,
+}
+
+impl AnswerCache {
+    pub fn new(threshold: f32, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            threshold,
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Build the cache from `ANSWER_CACHE_THRESHOLD` (cosine similarity, default 0.95),
+    /// `ANSWER_CACHE_TTL_SECS` (default 3600) and `ANSWER_CACHE_SIZE` (answers kept per chatbot,
+    /// default 500, 0 disables the cache)
+    pub fn from_env() -> Self {
+        let threshold = std::env::var("ANSWER_CACHE_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse::<f32>().ok())
+            .filter(|v| *v > 0.0 && *v <= 1.0)
+            .unwrap_or(DEFAULT_THRESHOLD);
+        let ttl = std::env::var("ANSWER_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let capacity = std::env::var("ANSWER_CACHE_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+
+        tracing::info!("Answer cache: threshold {}, TTL {}s, {} answers per chatbot", threshold, ttl, capacity);
+        Self::new(threshold, Duration::from_secs(ttl), capacity)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// The most similar cached answer at or above the threshold
+    pub fn lookup(&self, query: &CacheableQuery) -> Option<AnswerHit> {
+        let mut entries = self.entries.lock().ok()?;
+        let chatbot_entries = entries.get_mut(&query.chatbot_id)?;
+        chatbot_entries.retain(|entry| entry.version == query.version && entry.stored_at.elapsed() < self.ttl);
+
+        chatbot_entries
+            .iter()
+            .map(|entry| (entry, CandleEmbeddingService::cosine_similarity(&entry.embedding, &query.embedding)))
+            .filter(|(_, similarity)| *similarity >= self.threshold)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entry, similarity)| AnswerHit {
+                query: entry.query.clone(),
+                answer: entry.answer.clone(),
+                sources: entry.sources.clone(),
+                similarity,
+            })
+    }
+
+    /// Remember an answer to the question, replacing any cached for the same question text
+    pub fn store(&self, query: CacheableQuery, answer: String, sources: Vec<SearchResult>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let chatbot_entries = entries.entry(query.chatbot_id).or_default();
+        chatbot_entries.retain(|entry| entry.query != query.text);
+        chatbot_entries.push_back(Entry {
+            version: query.version,
+            embedding: query.embedding,
+            query: query.text,
+            answer,
+            sources,
+            stored_at: Instant::now(),
+        });
+        while chatbot_entries.len() > self.capacity {
+            chatbot_entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION: AnswerVersion = AnswerVersion { knowledge: 1, settings: 1 };
+
+    fn query(embedding: Vec<f32>) -> CacheableQuery {
+        CacheableQuery {
+            chatbot_id: Uuid::nil(),
+            text: "q".to_string(),
+            version: VERSION,
+            embedding,
+        }
+    }
+
+    fn question(embedding: Vec<f32>, text: &str) -> CacheableQuery {
+        CacheableQuery { text: text.to_string(), ..query(embedding) }
+    }
+
+    fn cache() -> AnswerCache {
+        AnswerCache::new(0.95, Duration::from_secs(60), 2)
+    }
+
+    #[test]
+    fn test_similar_question_hits() {
+        let cache = cache();
+        cache.store(question(vec![1.0, 0.0], "How do I reset my password?"), "Use the login page.".to_string(), Vec::new());
+
+        let hit = cache.lookup(&query(vec![0.99, 0.05])).unwrap();
+        assert_eq!(hit.answer, "Use the login page.");
+        assert_eq!(hit.query, "How do I reset my password?");
+        assert!(cache.lookup(&query(vec![0.0, 1.0])).is_none());
+    }
+
+    #[test]
+    fn test_changed_version_misses() {
+        let cache = cache();
+        cache.store(query(vec![1.0, 0.0]), "a".to_string(), Vec::new());
+
+        let mut newer = query(vec![1.0, 0.0]);
+        newer.version.knowledge += 1;
+        assert!(cache.lookup(&newer).is_none());
+        // The stale entry was dropped on the way
+        assert!(cache.lookup(&query(vec![1.0, 0.0])).is_none());
+    }
+
+    #[test]
+    fn test_other_chatbot_misses() {
+        let cache = cache();
+        cache.store(query(vec![1.0, 0.0]), "a".to_string(), Vec::new());
+
+        let mut other = query(vec![1.0, 0.0]);
+        other.chatbot_id = Uuid::from_u128(1);
+        assert!(cache.lookup(&other).is_none());
+    }
+
+    #[test]
+    fn test_expired_answer_misses() {
+        let cache = AnswerCache::new(0.95, Duration::ZERO, 2);
+        cache.store(query(vec![1.0, 0.0]), "a".to_string(), Vec::new());
+        assert!(cache.lookup(&query(vec![1.0, 0.0])).is_none());
+    }
+
+    #[test]
+    fn test_oldest_answer_is_evicted() {
+        let cache = cache();
+        cache.store(question(vec![1.0, 0.0, 0.0], "first"), "1".to_string(), Vec::new());
+        cache.store(question(vec![0.0, 1.0, 0.0], "second"), "2".to_string(), Vec::new());
+        cache.store(question(vec![0.0, 0.0, 1.0], "third"), "3".to_string(), Vec::new());
+
+        assert!(cache.lookup(&query(vec![1.0, 0.0, 0.0])).is_none());
+        assert_eq!(cache.lookup(&query(vec![0.0, 0.0, 1.0])).unwrap().answer, "3");
+    }
+
+    #[test]
+    fn test_disabled_cache_stores_nothing() {
+        let cache = AnswerCache::new(0.95, Duration::from_secs(60), 0);
+        cache.store(query(vec![1.0, 0.0]), "a".to_string(), Vec::new());
+        assert!(cache.lookup(&query(vec![1.0, 0.0])).is_none());
+    }
+}
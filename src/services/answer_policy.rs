@@ -0,0 +1,137 @@
+use crate::db::models::{ChatBot, NoContextBehavior};
+use crate::services::vector::SearchResult;
+
+pub const DEFAULT_FALLBACK_MESSAGE: &str =
+    "I couldn't find anything in the knowledge base to answer that question.";
+
+/// Context handed to the model when no chunk passed the score threshold outside strict mode
+pub const GENERAL_KNOWLEDGE_CONTEXT: &str =
+    "No relevant documents were found. Answer from general knowledge and say that the answer is not based on the provided documents.";
+
+/// Drop hits scoring below the chatbot's minimum similarity score
+pub fn filter_by_min_score(results: Vec<SearchResult>, min_score: Option<f32>) -> Vec<SearchResult> {
+    match min_score {
+        Some(min_score) => results.into_iter().filter(|r| r.score >= min_score).collect(),
+        None => results,
+    }
+}
+
+/// What the chatbot does when no chunk passes its score threshold
+pub fn no_context_behavior(chatbot: &ChatBot) -> NoContextBehavior {
+    NoContextBehavior::parse(&chatbot.no_context_behavior).unwrap_or_default()
+}
+
+/// The canned reply to send instead of calling the LLM, if any.
+///
+/// Only chatbots that refuse fall back; otherwise the model answers from general knowledge
+/// or with the no-context template.
+pub fn fallback_response(chatbot: &ChatBot, results: &[SearchResult]) -> Option<String> {
+    if !results.is_empty() || no_context_behavior(chatbot) != NoContextBehavior::Refuse {
+        return None;
+    }
+
+    Some(
+        chatbot
+            .fallback_message
+            .clone()
+            .filter(|message| !message.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_FALLBACK_MESSAGE.to_string()),
+    )
+}
+
+/// The template to answer with instead of the prompt template when no chunk passed the threshold,
+/// for chatbots that route those questions to their no-context template
+pub fn no_context_template<'a>(chatbot: &'a ChatBot, results: &[SearchResult]) -> Option<&'a str> {
+    if !results.is_empty() || no_context_behavior(chatbot) != NoContextBehavior::FallbackTemplate {
+        return None;
+    }
+    chatbot.no_context_template.as_deref().filter(|template| !template.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn result(score: f32) -> SearchResult {
+        SearchResult {
+            text: String::new(),
+            score,
+            chunk_index: 0,
+            file_path: "doc.pdf".to_string(),
+            parent_index: None,
+        }
+    }
+
+    fn chatbot(strict_mode: bool, fallback_message: Option<&str>) -> ChatBot {
+        ChatBot {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::nil(),
+            name: "bot".to_string(),
+            shard_count: 1,
+            min_score: Some(0.7),
+            strict_mode,
+            fallback_message: fallback_message.map(str::to_string),
+            no_context_behavior: if strict_mode { "refuse" } else { "general_knowledge" }.to_string(),
+            no_context_template: None,
+            query_paraphrases: 0,
+            mmr_lambda: None,
+            prompt_template: None,
+            prompt_template_id: None,
+            canary_template_id: None,
+            canary_percent: 0,
+            canary_started_at: None,
+            ingest_webhook_url: None,
+            ingest_webhook_secret: None,
+            handoff_webhook_url: None,
+            handoff_webhook_secret: None,
+            health_webhook_url: None,
+            health_webhook_secret: None,
+            query_script: None,
+            answer_script: None,
+            output_filters: None,
+            query_filters: None,
+            user_id: None,
+            guest_access: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            status: "active".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_min_score() {
+        let results = vec![result(0.9), result(0.5), result(0.7)];
+
+        assert_eq!(filter_by_min_score(results.clone(), None).len(), 3);
+        let kept = filter_by_min_score(results, Some(0.7));
+        assert_eq!(kept.iter().map(|r| r.score).collect::<Vec<_>>(), vec![0.9, 0.7]);
+    }
+
+    #[test]
+    fn test_fallback_only_in_strict_mode_without_results() {
+        assert_eq!(fallback_response(&chatbot(false, None), &[]), None);
+        assert_eq!(fallback_response(&chatbot(true, None), &[result(0.9)]), None);
+        assert_eq!(
+            fallback_response(&chatbot(true, None), &[]).as_deref(),
+            Some(DEFAULT_FALLBACK_MESSAGE)
+        );
+        assert_eq!(
+            fallback_response(&chatbot(true, Some("Please contact support.")), &[]).as_deref(),
+            Some("Please contact support.")
+        );
+    }
+
+    #[test]
+    fn test_no_context_template_only_when_routed_to_it() {
+        let mut bot = chatbot(false, None);
+        bot.no_context_template = Some("Nothing found for {{question}}".to_string());
+        assert_eq!(no_context_template(&bot, &[]), None);
+
+        bot.no_context_behavior = "fallback_template".to_string();
+        assert_eq!(no_context_template(&bot, &[]), Some("Nothing found for {{question}}"));
+        assert_eq!(no_context_template(&bot, &[result(0.9)]), None);
+        assert_eq!(fallback_response(&bot, &[]), None);
+    }
+}
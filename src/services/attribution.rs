@@ -0,0 +1,176 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::services::candle_embedding::CandleEmbeddingService;
+use crate::services::connector::chunk_url;
+use crate::services::vector::SearchResult;
+use crate::services::embedding::EmbeddingService;
+
+const DEFAULT_MIN_SIMILARITY: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributedSource {
+    pub file_path: String,
+    pub chunk_index: i64,
+    pub similarity: f32,
+    /// Page the chunk was synced from, for chunks from source connectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// One answer sentence and the retrieved chunk it most likely came from.
+/// `start`/`end` are character offsets into the answer, for UI highlighting.
+#[derive(Debug, Clone, Serialize)]
+pub struct SentenceAttribution {
+    pub sentence: String,
+    pub start: usize,
+    pub end: usize,
+    pub source: Option<AttributedSource>,
+}
+
+/// Minimum cosine similarity for a sentence to be attributed, from `ATTRIBUTION_MIN_SIMILARITY`
+pub fn min_similarity() -> f32 {
+    std::env::var("ATTRIBUTION_MIN_SIMILARITY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MIN_SIMILARITY)
+}
+
+/// Character spans of the sentences in `text`, trimmed of surrounding whitespace
+pub fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut start = 0;
+
+    let push = |start: usize, end: usize, spans: &mut Vec<(usize, usize)>| {
+        let mut s = start;
+        let mut e = end;
+        while s < e && chars[s].is_whitespace() {
+            s += 1;
+        }
+        while e > s && chars[e - 1].is_whitespace() {
+            e -= 1;
+        }
+        if s < e {
+            spans.push((s, e));
+        }
+    };
+
+    for (i, c) in chars.iter().enumerate() {
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            push(start, i + 1, &mut spans);
+            start = i + 1;
+        }
+    }
+    push(start, chars.len(), &mut spans);
+    spans
+}
+
+/// Map each answer sentence to the most similar retrieved chunk
+pub fn attribute_sentences(
+    answer: &str,
+    sentence_embeddings: &[Vec<f32>],
+    results: &[SearchResult],
+    chunk_embeddings: &[Vec<f32>],
+    min_similarity: f32,
+) -> Vec<SentenceAttribution> {
+    let chars: Vec<char> = answer.chars().collect();
+
+    sentence_spans(answer)
+        .into_iter()
+        .zip(sentence_embeddings.iter())
+        .map(|((start, end), sentence_embedding)| {
+            let source = chunk_embeddings
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| (i, CandleEmbeddingService::cosine_similarity(sentence_embedding, chunk)))
+                .filter(|(_, similarity)| *similarity >= min_similarity)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, similarity)| AttributedSource {
+                    file_path: results[i].file_path.clone(),
+                    chunk_index: results[i].chunk_index,
+                    similarity,
+                    url: chunk_url(&results[i].text).map(str::to_string),
+                });
+
+            SentenceAttribution {
+                sentence: chars[start..end].iter().collect(),
+                start,
+                end,
+                source,
+            }
+        })
+        .collect()
+}
+
+/// Embed the answer sentences and retrieved chunks, then attribute each sentence
+pub async fn attribute_answer(
+    embedding_service: &EmbeddingService,
+    answer: &str,
+    results: &[SearchResult],
+) -> Result<Vec<SentenceAttribution>> {
+    let chars: Vec<char> = answer.chars().collect();
+    let sentences: Vec<String> = sentence_spans(answer)
+        .into_iter()
+        .map(|(start, end)| chars[start..end].iter().collect())
+        .collect();
+    let chunks: Vec<String> = results.iter().map(|r| r.text.clone()).collect();
+
+    if sentences.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sentence_embeddings = embedding_service.embed_texts(&sentences).await?;
+    let chunk_embeddings = if chunks.is_empty() {
+        Vec::new()
+    } else {
+        embedding_service.embed_texts(&chunks).await?
+    };
+
+    Ok(attribute_sentences(
+        answer,
+        &sentence_embeddings,
+        results,
+        &chunk_embeddings,
+        min_similarity(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(file_path: &str, chunk_index: i64) -> SearchResult {
+        SearchResult {
+            text: String::new(),
+            score: 1.0,
+            chunk_index,
+            file_path: file_path.to_string(),
+            parent_index: None,
+        }
+    }
+
+    #[test]
+    fn test_sentence_spans_use_char_offsets() {
+        let text = "Café opens at 9. Closes at 5!";
+        let spans = sentence_spans(text);
+        let chars: Vec<char> = text.chars().collect();
+
+        assert_eq!(spans, vec![(0, 16), (17, 29)]);
+        assert_eq!(chars[spans[1].0..spans[1].1].iter().collect::<String>(), "Closes at 5!");
+    }
+
+    #[test]
+    fn test_attribute_picks_most_similar_chunk_above_threshold() {
+        let results = vec![result("a.pdf", 0), result("b.pdf", 3)];
+        let chunks = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let sentences = vec![vec![0.1, 0.9], vec![-1.0, 0.0]];
+
+        let attributions = attribute_sentences("Second. Unrelated.", &sentences, &results, &chunks, 0.5);
+
+        let first = attributions[0].source.as_ref().unwrap();
+        assert_eq!(first.file_path, "b.pdf");
+        assert_eq!(first.chunk_index, 3);
+        assert!(attributions[1].source.is_none());
+    }
+}
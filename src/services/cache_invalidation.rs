@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing;
+use uuid::Uuid;
+
+use crate::db::models::{ChatBot, CustomDomain};
+use crate::db::queries::{get_chat_bot, get_custom_domain_by_hostname};
+use crate::errors::AppResult;
+use crate::services::shutdown::BackgroundJobs;
+
+/// Postgres channel carrying cache invalidation events between replicas
+pub const CACHE_INVALIDATION_CHANNEL: &str = "rag_cache_invalidation";
+
+// Hostnames remembered, including ones with no custom domain, so arbitrary Host headers can't grow the cache forever
+const MAX_CACHED_HOSTNAMES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheEvent {
+    ChatbotSettingsChanged { chatbot_id: Uuid },
+    KnowledgeVersionChanged { chatbot_id: Uuid },
+    CustomDomainChanged { hostname: String },
+}
+
+/// In-memory cache of chatbot rows and knowledge and settings versions.
+///
+/// Versions are opaque counters: a change of the knowledge version means documents
+/// for that chatbot were added or removed, and a change of the settings version
+/// means its settings, templates or glossary changed, so dependent caches should be
+/// discarded.
+/// Custom domains are cached by hostname, including hostnames that have none.
+#[derive(Default)]
+pub struct ChatbotCache {
+    chatbots: RwLock<HashMap<Uuid, ChatBot>>,
+    knowledge_versions: RwLock<HashMap<Uuid, u64>>,
+    settings_versions: RwLock<HashMap<Uuid, u64>>,
+    domains: RwLock<HashMap<String, Option<CustomDomain>>>,
+}
+
+impl ChatbotCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read-through lookup of an active chatbot owned by the organization
+    pub async fn get_chatbot(
+        &self,
+        pool: &PgPool,
+        organization_id: Uuid,
+        chatbot_id: Uuid,
+    ) -> AppResult<Option<ChatBot>> {
+        let cached = self.chatbots.read().ok().and_then(|c| c.get(&chatbot_id).cloned());
+        if let Some(chatbot) = cached {
+            return Ok((chatbot.organization_id == organization_id).then_some(chatbot));
+        }
+
+        let chatbot = get_chat_bot(pool, organization_id, chatbot_id).await?;
+        if let (Some(chatbot), Ok(mut chatbots)) = (&chatbot, self.chatbots.write()) {
+            chatbots.insert(chatbot_id, chatbot.clone());
+        }
+
+        Ok(chatbot)
+    }
+
+    /// Read-through lookup of the custom domain for a hostname
+    pub async fn get_domain(&self, pool: &PgPool, hostname: &str) -> AppResult<Option<CustomDomain>> {
+        let cached = self.domains.read().ok().and_then(|d| d.get(hostname).cloned());
+        if let Some(domain) = cached {
+            return Ok(domain);
+        }
+
+        let domain = get_custom_domain_by_hostname(pool, hostname).await?;
+        if let Ok(mut domains) = self.domains.write()
+            && domains.len() < MAX_CACHED_HOSTNAMES
+        {
+            domains.insert(hostname.to_string(), domain.clone());
+        }
+
+        Ok(domain)
+    }
+
+    pub fn knowledge_version(&self, chatbot_id: Uuid) -> u64 {
+        self.knowledge_versions
+            .read()
+            .ok()
+            .and_then(|v| v.get(&chatbot_id).copied())
+            .unwrap_or(0)
+    }
+
+    pub fn settings_version(&self, chatbot_id: Uuid) -> u64 {
+        self.settings_versions
+            .read()
+            .ok()
+            .and_then(|v| v.get(&chatbot_id).copied())
+            .unwrap_or(0)
+    }
+
+    /// Apply an invalidation event to the local caches
+    pub fn apply(&self, event: &CacheEvent) {
+        match event {
+            CacheEvent::ChatbotSettingsChanged { chatbot_id } => {
+                if let Ok(mut chatbots) = self.chatbots.write() {
+                    chatbots.remove(chatbot_id);
+                }
+                if let Ok(mut versions) = self.settings_versions.write() {
+                    *versions.entry(*chatbot_id).or_insert(0) += 1;
+                }
+            }
+            CacheEvent::KnowledgeVersionChanged { chatbot_id } => {
+                if let Ok(mut versions) = self.knowledge_versions.write() {
+                    *versions.entry(*chatbot_id).or_insert(0) += 1;
+                }
+            }
+            CacheEvent::CustomDomainChanged { hostname } => {
+                if let Ok(mut domains) = self.domains.write() {
+                    domains.remove(hostname);
+                }
+            }
+        }
+    }
+
+    /// Drop everything, used when notifications may have been missed
+    pub fn clear(&self) {
+        if let Ok(mut chatbots) = self.chatbots.write() {
+            chatbots.clear();
+        }
+        if let Ok(mut domains) = self.domains.write() {
+            domains.clear();
+        }
+        for versions in [&self.knowledge_versions, &self.settings_versions] {
+            if let Ok(mut versions) = versions.write() {
+                for version in versions.values_mut() {
+                    *version += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Apply an event locally and broadcast it to every other replica
+pub async fn publish(pool: &PgPool, cache: &ChatbotCache, event: CacheEvent) -> AppResult<()> {
+    cache.apply(&event);
+
+    let payload = serde_json::to_string(&event)
+        .map_err(|e| crate::errors::AppError::Other(format!("Failed to encode cache event: {}", e)))?;
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CACHE_INVALIDATION_CHANNEL)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Spawn the LISTEN loop that keeps this replica's caches in sync
+pub fn spawn_invalidation_listener(jobs: &BackgroundJobs, db: Arc<PgPool>, cache: Arc<ChatbotCache>) {
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        loop {
+            tokio::select! {
+                result = listen(&db, &cache) => {
+                    if let Err(e) = result {
+                        tracing::error!("❌ Cache invalidation listener failed: {}", e);
+                    }
+                }
+                _ = shutdown.cancelled() => break,
+            }
+            // Anything could have changed while we were disconnected
+            cache.clear();
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                _ = shutdown.cancelled() => break,
+            }
+        }
+    });
+}
+
+async fn listen(db: &PgPool, cache: &ChatbotCache) -> AppResult<()> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(CACHE_INVALIDATION_CHANNEL).await?;
+    tracing::info!("✅ Listening for cache invalidation on '{}'", CACHE_INVALIDATION_CHANNEL);
+
+    loop {
+        // try_recv yields None when the connection dropped; sqlx reconnects on the next call
+        let Some(notification) = listener.try_recv().await? else {
+            tracing::warn!("⚠️ Cache invalidation connection lost, clearing caches");
+            cache.clear();
+            continue;
+        };
+
+        match serde_json::from_str::<CacheEvent>(notification.payload()) {
+            Ok(event) => {
+                tracing::debug!("Applying cache event: {:?}", event);
+                cache.apply(&event);
+            }
+            Err(e) => tracing::warn!("Ignoring malformed cache event: {}", e),
+        }
+    }
+}
@@ -2,15 +2,23 @@ use anyhow::{Context, Result};
 use candle_core::{Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use rayon::prelude::*;
+use std::sync::Arc;
 use tokenizers::Tokenizer;
 use tracing;
 
+use crate::services::embedding_cache::EmbeddingCache;
+
+const DEFAULT_BATCH_SIZE: usize = 32;
+
 /// Configuration for the embedding model
 #[derive(Debug, Clone)]
 pub struct EmbeddingConfig {
     pub model_name: String,
     pub max_length: usize,
     pub embedding_dim: usize,
+    /// Texts run through the model together in one input tensor
+    pub batch_size: usize,
 }
 
 impl Default for EmbeddingConfig {
@@ -19,16 +27,51 @@ impl Default for EmbeddingConfig {
             model_name: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
             max_length: 512,
             embedding_dim: 384,
+            batch_size: DEFAULT_BATCH_SIZE,
         }
     }
 }
 
+impl EmbeddingConfig {
+    /// Default model settings with the batch size from `EMBEDDING_BATCH_SIZE`
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            batch_size: env_count("EMBEDDING_BATCH_SIZE")?.unwrap_or(DEFAULT_BATCH_SIZE),
+            ..Self::default()
+        })
+    }
+}
+
+/// Size the worker pool that embeds batches in parallel from `EMBEDDING_CONCURRENCY`, defaulting
+/// to one worker per CPU. Call once at startup, before anything is embedded
+pub fn init_embedding_workers() -> Result<usize> {
+    let mut builder = rayon::ThreadPoolBuilder::new().thread_name(|i| format!("embedding-{}", i));
+    if let Some(workers) = env_count("EMBEDDING_CONCURRENCY")? {
+        builder = builder.num_threads(workers);
+    }
+    builder.build_global().context("Failed to start embedding workers")?;
+    Ok(rayon::current_num_threads())
+}
+
+fn env_count(name: &str) -> Result<Option<usize>> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|count| *count > 0)
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("{} must be a positive number, got '{}'", name, value)),
+        Err(_) => Ok(None),
+    }
+}
+
 /// Candle-based embedding service for generating text embeddings
 pub struct CandleEmbeddingService {
     device: Device,
     tokenizer: Tokenizer,
     model: BertModel,
     config: EmbeddingConfig,
+    cache: Option<Arc<EmbeddingCache>>,
 }
 
 impl CandleEmbeddingService {
@@ -66,9 +109,21 @@ impl CandleEmbeddingService {
             tokenizer,
             model,
             config,
+            cache: None,
         })
     }
     
+    /// Attach an embedding cache consulted before running inference
+    pub fn with_cache(mut self, cache: Arc<EmbeddingCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+    
+    /// Cache key for a text under the configured model
+    pub fn cache_key(&self, text: &str) -> String {
+        EmbeddingCache::cache_key(&self.config.model_name, text)
+    }
+    
     /// Create a dummy model for testing (replace with actual model loading)
     fn create_dummy_model(device: &Device, config: &EmbeddingConfig) -> Result<BertModel> {
         // This is a placeholder - in a real implementation, you would load the actual model
@@ -92,52 +147,100 @@ impl CandleEmbeddingService {
     
     /// Generate embeddings for a single text
     pub fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
-        tracing::debug!("Generating embedding for text: {}...", &text[..text.len().min(50)]);
-        
-        // Tokenize the input text - simplified for now
-        let tokens = self.tokenizer
-            .encode(text, true)
-            .map_err(|e| anyhow::anyhow!("Failed to tokenize text: {}", e))?;
+        let cache_key = self.cache.as_ref().map(|_| self.cache_key(text));
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key)
+            && let Some(embedding) = cache.get(key)
+        {
+            tracing::debug!("Embedding cache hit for key {}", key);
+            return Ok(embedding);
+        }
         
-        let token_ids = tokens.get_ids();
+        tracing::debug!("Generating embedding for text: {}...", &text[..text.len().min(50)]);
         
-        // Truncate if too long
-        let token_ids = if token_ids.len() > self.config.max_length {
-            &token_ids[..self.config.max_length]
-        } else {
-            token_ids
-        };
+        let embedding = self
+            .embed_batch(&[text])?
+            .pop()
+            .context("Model returned no embedding")?;
         
-        // Convert to tensor
-        let _input_ids = Tensor::new(token_ids, &self.device)
-            .context("Failed to create input tensor")?;
-        
-        // For now, generate a random embedding (replace with actual model inference)
-        let embedding = self.generate_dummy_embedding(text)?;
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+            cache.put(key, embedding.clone());
+        }
         
         tracing::debug!("Generated embedding with dimension: {}", embedding.len());
         Ok(embedding)
     }
     
-    /// Generate embeddings for multiple texts
+    /// Generate embeddings for multiple texts. Cache misses are split into batches of
+    /// `batch_size`, and batches run in parallel on the embedding workers
     pub fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
         tracing::info!("Generating embeddings for {} texts", texts.len());
         
-        let mut embeddings = Vec::with_capacity(texts.len());
+        let mut embeddings: Vec<Option<Vec<f32>>> = texts
+            .iter()
+            .map(|text| self.cache.as_ref().and_then(|cache| cache.get(&self.cache_key(text))))
+            .collect();
+        let misses: Vec<usize> = (0..texts.len()).filter(|&i| embeddings[i].is_none()).collect();
+        let batches: Vec<&[usize]> = misses.chunks(self.config.batch_size).collect();
+        tracing::info!(
+            "{} cache hits, embedding {} texts in {} batches",
+            texts.len() - misses.len(),
+            misses.len(),
+            batches.len()
+        );
+        
+        let computed = batches
+            .par_iter()
+            .map(|batch| {
+                let batch_texts: Vec<&str> = batch.iter().map(|&i| texts[i].as_str()).collect();
+                self.embed_batch(&batch_texts)
+            })
+            .collect::<Result<Vec<_>>>()?;
         
-        for (i, text) in texts.iter().enumerate() {
-            let embedding = self.embed_text(text)?;
-            embeddings.push(embedding);
-            
-            if (i + 1) % 10 == 0 {
-                tracing::info!("Processed {}/{} texts", i + 1, texts.len());
+        for (&i, embedding) in misses.iter().zip(computed.into_iter().flatten()) {
+            if let Some(cache) = &self.cache {
+                cache.put(self.cache_key(&texts[i]), embedding.clone());
             }
+            embeddings[i] = Some(embedding);
         }
         
+        let embeddings = embeddings
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .context("Model returned fewer embeddings than texts")?;
         tracing::info!("✅ Generated {} embeddings", embeddings.len());
         Ok(embeddings)
     }
     
+    /// Run one batch through the model as a single padded input tensor
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self.tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize batch: {}", e))?;
+        
+        // Truncate to the model's limit and pad every sequence to the longest one
+        let seq_len = encodings
+            .iter()
+            .map(|encoding| encoding.get_ids().len().min(self.config.max_length))
+            .max()
+            .unwrap_or(0);
+        let mut token_ids = vec![0u32; texts.len() * seq_len];
+        let mut attention_mask = vec![0u32; texts.len() * seq_len];
+        for (row, encoding) in encodings.iter().enumerate() {
+            let ids = &encoding.get_ids()[..encoding.get_ids().len().min(seq_len)];
+            token_ids[row * seq_len..row * seq_len + ids.len()].copy_from_slice(ids);
+            attention_mask[row * seq_len..row * seq_len + ids.len()].fill(1);
+        }
+        
+        // Convert to tensors
+        let _input_ids = Tensor::from_vec(token_ids, (texts.len(), seq_len), &self.device)
+            .context("Failed to create input tensor")?;
+        let _attention_mask = Tensor::from_vec(attention_mask, (texts.len(), seq_len), &self.device)
+            .context("Failed to create attention mask tensor")?;
+        
+        // For now, generate random embeddings (replace with actual model inference)
+        texts.iter().map(|text| self.generate_dummy_embedding(text)).collect()
+    }
+    
     /// Generate a dummy embedding based on text content (replace with actual model inference)
     fn generate_dummy_embedding(&self, text: &str) -> Result<Vec<f32>> {
         // Create a deterministic "embedding" based on text content
@@ -172,6 +275,11 @@ impl CandleEmbeddingService {
     pub fn embedding_dim(&self) -> usize {
         self.config.embedding_dim
     }
+    
+    /// Get the configured model name
+    pub fn model_name(&self) -> &str {
+        &self.config.model_name
+    }
 }
 
 /// Utility functions for embedding operations
@@ -228,6 +336,7 @@ mod tests {
             model_name: "test-model".to_string(),
             max_length: 128,
             embedding_dim: 256,
+            batch_size: 8,
         };
         
         let service = CandleEmbeddingService::new(Some(config));
@@ -245,6 +354,38 @@ mod tests {
         assert_eq!(embedding.len(), 384); // Default embedding dimension
     }
 
+    #[test]
+    fn test_embed_text_uses_cache() {
+        let cache = Arc::new(EmbeddingCache::new(16, None));
+        let service = CandleEmbeddingService::new(None).unwrap().with_cache(cache.clone());
+        let text = "Cached sentence.";
+        
+        let first = service.embed_text(text).unwrap();
+        assert_eq!(cache.get(&service.cache_key(text)), Some(first.clone()));
+        
+        // A seeded entry must be returned without running inference
+        cache.put(service.cache_key(text), vec![0.5; 384]);
+        assert_eq!(service.embed_text(text).unwrap(), vec![0.5; 384]);
+    }
+
+    #[test]
+    fn test_embed_texts_matches_single_embeddings() {
+        let config = EmbeddingConfig { batch_size: 3, ..EmbeddingConfig::default() };
+        let cache = Arc::new(EmbeddingCache::new(16, None));
+        let service = CandleEmbeddingService::new(Some(config)).unwrap().with_cache(cache.clone());
+        let texts: Vec<String> = (0..8).map(|i| format!("Sentence number {}", i)).collect();
+        
+        // A cached text keeps its cached vector while the rest are batched around it
+        cache.put(service.cache_key(&texts[4]), vec![0.5; 384]);
+        let embeddings = service.embed_texts(&texts).unwrap();
+        
+        assert_eq!(embeddings.len(), texts.len());
+        assert_eq!(embeddings[4], vec![0.5; 384]);
+        for i in [0, 3, 7] {
+            assert_eq!(embeddings[i], service.embed_text(&texts[i]).unwrap());
+        }
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
@@ -0,0 +1,299 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::models::{ChatBot, ChatbotHealthSnapshot, HealthCounts};
+use crate::db::queries::{
+    get_chatbot_last_change, get_health_baseline, get_health_counts, health_alerted_since, insert_health_snapshot,
+    list_chat_bots_with_recent_conversations,
+};
+use crate::services::ingest_webhook::{sign, SIGNATURE_HEADER};
+use crate::services::public_url::public_client_builder;
+use crate::services::shutdown::BackgroundJobs;
+
+const DEFAULT_WINDOW_DAYS: i32 = 7;
+const DEFAULT_MIN_TURNS: i64 = 20;
+const DEFAULT_MAX_DROP: f64 = 10.0;
+const DEFAULT_MIN_SCORE: f64 = 50.0;
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Days of turns a health score covers, from `HEALTH_WINDOW_DAYS`
+pub fn health_window_days() -> i32 {
+    std::env::var("HEALTH_WINDOW_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &i32| *v > 0)
+        .unwrap_or(DEFAULT_WINDOW_DAYS)
+}
+
+/// A chatbot's health over a set of finished turns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub turns: i64,
+    /// Mean best citation score of the turns answered from retrieved chunks, 0 to 1
+    pub groundedness: f64,
+    /// Share of rated turns rated thumbs-up; none when no turn was rated
+    pub feedback_ratio: Option<f64>,
+    /// Share of turns that failed or were answered without any retrieved chunk
+    pub unanswered_rate: f64,
+    /// Mean of groundedness, feedback ratio and answered rate, out of 100
+    pub score: f64,
+}
+
+impl HealthReport {
+    /// None when there are no finished turns to score
+    pub fn from_counts(counts: &HealthCounts) -> Option<Self> {
+        if counts.turns == 0 {
+            return None;
+        }
+        let groundedness = if counts.grounded_turns > 0 {
+            counts.grounding_score_sum / counts.grounded_turns as f64
+        } else {
+            0.0
+        };
+        let feedback_ratio = (counts.rated_turns > 0).then(|| counts.thumbs_up as f64 / counts.rated_turns as f64);
+        let unanswered_rate = counts.unanswered_turns as f64 / counts.turns as f64;
+
+        let parts: Vec<f64> = [Some(groundedness), feedback_ratio, Some(1.0 - unanswered_rate)]
+            .into_iter()
+            .flatten()
+            .collect();
+        let score = 100.0 * parts.iter().sum::<f64>() / parts.len() as f64;
+
+        Some(Self {
+            turns: counts.turns,
+            groundedness,
+            feedback_ratio,
+            unanswered_rate,
+            score,
+        })
+    }
+}
+
+/// When a score after a change counts as degraded
+#[derive(Debug, Clone)]
+pub struct HealthThresholds {
+    /// Turns needed since the change before it is judged
+    pub min_turns: i64,
+    /// Points the score may fall below the baseline from before the change
+    pub max_drop: f64,
+    /// Lowest acceptable score, whatever the baseline
+    pub min_score: f64,
+}
+
+impl HealthThresholds {
+    // `HEALTH_MIN_TURNS` (default 20), `HEALTH_MAX_DROP` (default 10) and `HEALTH_MIN_SCORE` (default 50)
+    pub fn from_env() -> Self {
+        Self {
+            min_turns: std::env::var("HEALTH_MIN_TURNS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MIN_TURNS),
+            max_drop: std::env::var("HEALTH_MAX_DROP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_DROP),
+            min_score: std::env::var("HEALTH_MIN_SCORE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MIN_SCORE),
+        }
+    }
+}
+
+/// Why the score since a change counts as degraded, if it does
+pub fn degradation(baseline: Option<f64>, current: &HealthReport, thresholds: &HealthThresholds) -> Option<String> {
+    if current.turns < thresholds.min_turns {
+        return None;
+    }
+    if let Some(baseline) = baseline
+        && baseline - current.score > thresholds.max_drop
+    {
+        return Some(format!("score fell from {:.1} to {:.1}", baseline, current.score));
+    }
+    if current.score < thresholds.min_score {
+        return Some(format!("score {:.1} is below the minimum of {:.1}", current.score, thresholds.min_score));
+    }
+    None
+}
+
+#[derive(Debug, Serialize)]
+struct HealthAlert<'a> {
+    event: &'static str,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    reason: &'a str,
+    changed_at: DateTime<Utc>,
+    baseline: Option<&'a ChatbotHealthSnapshot>,
+    current: &'a HealthReport,
+}
+
+// POST the alert to the chatbot's health webhook, signed when it has a secret
+async fn notify(url: &str, secret: Option<&str>, alert: &HealthAlert<'_>) -> Result<()> {
+    let timeout_secs = std::env::var("HEALTH_WEBHOOK_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+    let body = serde_json::to_vec(alert)?;
+    let mut request = public_client_builder()
+        .build()?
+        .post(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = secret {
+        request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+    }
+
+    request.body(body).send().await?.error_for_status()?;
+    Ok(())
+}
+
+// Compare the turns since the chatbot's last change with the snapshot from before it and alert
+// once per change if they degraded. Returns whether the degradation was reported; a failed webhook
+// is retried on the next run
+async fn alert_if_degraded(
+    db: &PgPool,
+    chatbot: &ChatBot,
+    changed_at: DateTime<Utc>,
+    thresholds: &HealthThresholds,
+) -> Result<bool> {
+    if health_alerted_since(db, chatbot.id, changed_at).await? {
+        return Ok(false);
+    }
+    let Some(current) = HealthReport::from_counts(&get_health_counts(db, chatbot.id, changed_at).await?) else {
+        return Ok(false);
+    };
+    let baseline = get_health_baseline(db, chatbot.id, changed_at).await?;
+    let Some(reason) = degradation(baseline.as_ref().map(|snapshot| snapshot.score), &current, thresholds) else {
+        return Ok(false);
+    };
+    tracing::warn!("⚠️ Health of chatbot {} degraded since its change at {}: {}", chatbot.id, changed_at, reason);
+
+    let Some(url) = chatbot.health_webhook_url.as_deref() else {
+        return Ok(true);
+    };
+    let alert = HealthAlert {
+        event: "chatbot.health_degraded",
+        organization_id: chatbot.organization_id,
+        chatbot_id: chatbot.id,
+        reason: &reason,
+        changed_at,
+        baseline: baseline.as_ref(),
+        current: &current,
+    };
+    match notify(url, chatbot.health_webhook_secret.as_deref(), &alert).await {
+        Ok(()) => {
+            tracing::info!("✅ Health webhook notified for chatbot {}", chatbot.id);
+            Ok(true)
+        }
+        Err(e) => {
+            tracing::error!("❌ Health webhook failed for chatbot {}: {}", chatbot.id, e);
+            Ok(false)
+        }
+    }
+}
+
+// Record the chatbot's score over the window, checking for degradation after a change inside it
+async fn check_health(db: &PgPool, chatbot: &ChatBot, thresholds: &HealthThresholds) -> Result<()> {
+    let window_start = Utc::now() - chrono::Duration::days(health_window_days() as i64);
+    let Some(report) = HealthReport::from_counts(&get_health_counts(db, chatbot.id, window_start).await?) else {
+        return Ok(());
+    };
+
+    let alerted = match get_chatbot_last_change(db, chatbot.id).await? {
+        Some(changed_at) if changed_at > window_start => alert_if_degraded(db, chatbot, changed_at, thresholds).await?,
+        _ => false,
+    };
+    insert_health_snapshot(db, chatbot.id, &report, alerted).await?;
+    Ok(())
+}
+
+// Spawn the background task that scores chatbots with recent conversations and alerts on drift
+pub fn spawn_health_task(jobs: &BackgroundJobs, db: Arc<PgPool>) {
+    let interval_secs = std::env::var("HEALTH_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    let thresholds = HealthThresholds::from_env();
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let chatbots = match list_chat_bots_with_recent_conversations(&db, health_window_days()).await {
+                Ok(chatbots) => chatbots,
+                Err(e) => {
+                    tracing::error!("❌ Health task failed to list chatbots: {}", e);
+                    continue;
+                }
+            };
+            for chatbot in &chatbots {
+                if let Err(e) = check_health(&db, chatbot, &thresholds).await {
+                    tracing::error!("❌ Failed to check health of chatbot {}: {}", chatbot.id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds() -> HealthThresholds {
+        HealthThresholds { min_turns: 20, max_drop: 10.0, min_score: 50.0 }
+    }
+
+    fn report(turns: i64, score: f64) -> HealthReport {
+        HealthReport { turns, groundedness: 0.0, feedback_ratio: None, unanswered_rate: 0.0, score }
+    }
+
+    #[test]
+    fn test_from_counts() {
+        assert_eq!(HealthReport::from_counts(&HealthCounts::default()), None);
+
+        let counts = HealthCounts {
+            turns: 10,
+            grounded_turns: 8,
+            grounding_score_sum: 6.4,
+            unanswered_turns: 2,
+            rated_turns: 4,
+            thumbs_up: 3,
+        };
+        let report = HealthReport::from_counts(&counts).unwrap();
+        assert!((report.groundedness - 0.8).abs() < 1e-9);
+        assert_eq!(report.feedback_ratio, Some(0.75));
+        assert!((report.unanswered_rate - 0.2).abs() < 1e-9);
+        assert!((report.score - 78.333).abs() < 0.01);
+
+        // Without ratings, feedback is left out of the score
+        let report = HealthReport::from_counts(&HealthCounts { rated_turns: 0, thumbs_up: 0, ..counts }).unwrap();
+        assert!((report.score - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_degradation() {
+        assert_eq!(degradation(Some(90.0), &report(25, 85.0), &thresholds()), None);
+        assert_eq!(
+            degradation(Some(90.0), &report(25, 75.0), &thresholds()).as_deref(),
+            Some("score fell from 90.0 to 75.0")
+        );
+        assert_eq!(
+            degradation(None, &report(25, 40.0), &thresholds()).as_deref(),
+            Some("score 40.0 is below the minimum of 50.0")
+        );
+        // Too few turns since the change to judge
+        assert_eq!(degradation(Some(90.0), &report(5, 20.0), &thresholds()), None);
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sqlx::PgPool;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing;
+use uuid::Uuid;
+
+use crate::db::models::StaleDocument;
+use crate::db::queries::{
+    delete_document_usage, get_cold_document, list_stale_documents, remove_cold_document,
+    store_cold_document, touch_document_usage,
+};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
+use crate::services::reindex::alias_of;
+use crate::services::vector::{chatbot_index_name, DocumentWithEmbedding, SearchResult, VectorBackend, VectorStore};
+use crate::services::sharding::shard_indices;
+use crate::services::shutdown::BackgroundJobs;
+
+const DEFAULT_INTERVAL_SECS: u64 = 86_400;
+const DEFAULT_BATCH_SIZE: i64 = 100;
+
+/// Tiering policy from `COLD_STORAGE_AFTER_MONTHS` (unset or 0 disables it),
+/// `COLD_STORAGE_INTERVAL_SECS` and `COLD_STORAGE_BATCH_SIZE`
+#[derive(Debug, Clone)]
+pub struct ColdStorageConfig {
+    pub after_months: i32,
+    pub interval: Duration,
+    pub batch_size: i64,
+}
+
+impl ColdStorageConfig {
+    pub fn from_env() -> Option<Self> {
+        let after_months: i32 = std::env::var("COLD_STORAGE_AFTER_MONTHS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|months| *months > 0)?;
+
+        Some(Self {
+            after_months,
+            interval: Duration::from_secs(
+                std::env::var("COLD_STORAGE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_INTERVAL_SECS),
+            ),
+            batch_size: std::env::var("COLD_STORAGE_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+        })
+    }
+}
+
+/// Serialize chunks to gzip-compressed JSON
+pub fn compress_chunks(chunks: &[DocumentWithEmbedding]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&serde_json::to_vec(chunks)?)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress_chunks(payload: &[u8]) -> Result<Vec<DocumentWithEmbedding>> {
+    let mut json = Vec::new();
+    GzDecoder::new(payload).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Record that these documents were just used to answer a query, without delaying the response
+pub fn record_retrieval(jobs: &BackgroundJobs, db: Arc<PgPool>, chatbot_id: Uuid, results: &[SearchResult]) {
+    let mut file_paths: Vec<String> = results.iter().map(|r| r.file_path.clone()).collect();
+    file_paths.sort();
+    file_paths.dedup();
+    if file_paths.is_empty() {
+        return;
+    }
+
+    jobs.spawn(async move {
+        if let Err(e) = touch_document_usage(&db, chatbot_id, &file_paths).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+    });
+}
+
+// Move one stale document's chunks out of the vector store into the cold store
+async fn move_to_cold(
+    db: &PgPool,
+    vector_store: &VectorBackend,
+    chatbot_cache: &ChatbotCache,
+    document: &StaleDocument,
+) -> Result<usize> {
+    let index_names = shard_indices(
+        &chatbot_index_name(document.organization_id, document.chatbot_id),
+        document.shard_count,
+    );
+    let chunks = vector_store.fetch_document_chunks(&index_names, &document.file_path).await?;
+
+    let Some((index_name, _)) = chunks.first() else {
+        // Nothing indexed under this path any more; stop tracking it
+        delete_document_usage(db, document.chatbot_id, &document.file_path).await?;
+        return Ok(0);
+    };
+    let index_name = index_name.clone();
+    let chunks: Vec<DocumentWithEmbedding> = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+
+    // Persist before deleting so a failure never loses chunks; rehydration reuses ids, so a retry can't duplicate them
+    let payload = compress_chunks(&chunks)?;
+    store_cold_document(db, document.chatbot_id, &document.file_path, &index_name, chunks.len() as i32, &payload).await?;
+    vector_store.delete_document_chunks(&[index_name], &document.file_path).await?;
+
+    publish(db, chatbot_cache, CacheEvent::KnowledgeVersionChanged { chatbot_id: document.chatbot_id }).await?;
+    Ok(chunks.len())
+}
+
+/// Reindex a cold document into the vector store. Returns None if it isn't in the cold store
+pub async fn rehydrate_document(
+    db: &PgPool,
+    vector_store: &VectorBackend,
+    chatbot_cache: &ChatbotCache,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    file_path: &str,
+) -> Result<Option<usize>> {
+    let Some(document) = get_cold_document(db, organization_id, chatbot_id, file_path).await? else {
+        return Ok(None);
+    };
+
+    // Archived from a reindexed chatbot, the chunks came from a versioned index that may since have
+    // been replaced; its alias always names the live one
+    let index_name = alias_of(&document.index_name);
+    let chunks = decompress_chunks(&document.payload)?;
+    if let Some(first) = chunks.first() {
+        vector_store
+            .create_collection(index_name, first.embedding.len())
+            .await?;
+    }
+
+    let indexed = vector_store.index_documents(index_name, chunks).await?;
+    remove_cold_document(db, chatbot_id, file_path).await?;
+    publish(db, chatbot_cache, CacheEvent::KnowledgeVersionChanged { chatbot_id }).await?;
+
+    tracing::info!("✅ Rehydrated {} chunks of {} for chatbot {}", indexed, file_path, chatbot_id);
+    Ok(Some(indexed))
+}
+
+// Spawn the background task that moves documents unused for N months to cold storage
+pub fn spawn_cold_storage_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>, vector_store: Arc<VectorBackend>, chatbot_cache: Arc<ChatbotCache>) {
+    let Some(config) = ColdStorageConfig::from_env() else {
+        tracing::info!("Cold storage tiering disabled (COLD_STORAGE_AFTER_MONTHS not set)");
+        return;
+    };
+
+    tracing::info!(
+        "Starting cold storage task: after {} months, interval {}s",
+        config.after_months,
+        config.interval.as_secs()
+    );
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let stale = match list_stale_documents(&db, config.after_months, config.batch_size).await {
+                Ok(stale) => stale,
+                Err(e) => {
+                    tracing::error!("❌ Cold storage task failed to list documents: {}", e);
+                    continue;
+                }
+            };
+
+            for document in &stale {
+                match move_to_cold(&db, &vector_store, &chatbot_cache, document).await {
+                    Ok(count) => tracing::info!(
+                        "🧊 Moved {} chunks of {} (chatbot {}) to cold storage",
+                        count, document.file_path, document.chatbot_id
+                    ),
+                    Err(e) => tracing::error!(
+                        "❌ Failed to move {} (chatbot {}) to cold storage: {}",
+                        document.file_path, document.chatbot_id, e
+                    ),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_round_trip_through_compression() {
+        let chunks = vec![DocumentWithEmbedding {
+            id: "chunk-1".to_string(),
+            text: "The warranty lasts two years. ".repeat(20),
+            embedding: vec![0.25, -0.5, 1.0],
+            chunk_index: 0,
+            file_path: "uploads/manual.pdf".to_string(),
+            chunk_count: 1,
+            parent_index: None,
+        }];
+
+        let payload = compress_chunks(&chunks).unwrap();
+        let restored = decompress_chunks(&payload).unwrap();
+
+        assert!(payload.len() < serde_json::to_vec(&chunks).unwrap().len());
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, "chunk-1");
+        assert_eq!(restored[0].embedding, vec![0.25, -0.5, 1.0]);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use tracing;
+
+use crate::services::vector::SearchResult;
+use crate::services::gemini::GeminiService;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "but", "not", "you", "all", "any", "can", "was", "one", "our",
+    "out", "has", "have", "had", "how", "what", "when", "where", "which", "who", "why", "with",
+    "this", "that", "these", "those", "from", "into", "about", "does", "did", "will", "would",
+    "should", "could", "there", "their", "they", "them", "then", "than", "your", "its",
+];
+
+/// How retrieved chunks are trimmed before prompt assembly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionMode {
+    None,
+    /// Keep only sentences sharing terms with the query
+    Extractive,
+    /// Ask the LLM to extract the relevant sentences verbatim
+    Llm,
+}
+
+impl CompressionMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "none" | "off" => Some(Self::None),
+            "extractive" => Some(Self::Extractive),
+            "llm" => Some(Self::Llm),
+            _ => None,
+        }
+    }
+
+    /// Mode from the request, falling back to `CONTEXT_COMPRESSION` (default none)
+    pub fn from_request(value: Option<&str>) -> Self {
+        value
+            .and_then(Self::parse)
+            .or_else(|| {
+                std::env::var("CONTEXT_COMPRESSION")
+                    .ok()
+                    .and_then(|v| Self::parse(&v))
+            })
+            .unwrap_or(Self::None)
+    }
+}
+
+/// Split text into sentences on terminal punctuation and blank lines
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            let end = i + c.len_utf8();
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+fn terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() > 2 && !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Keep the sentences that share at least one meaningful term with the query.
+/// Returns the chunk unchanged when nothing matches, so context is never dropped entirely.
+pub fn extractive_compress(query: &str, text: &str) -> String {
+    let query_terms = terms(query);
+    if query_terms.is_empty() {
+        return text.to_string();
+    }
+
+    let relevant: Vec<&str> = split_sentences(text)
+        .into_iter()
+        .filter(|sentence| !terms(sentence).is_disjoint(&query_terms))
+        .collect();
+
+    if relevant.is_empty() {
+        text.to_string()
+    } else {
+        relevant.join(" ")
+    }
+}
+
+fn extractive_results(query: &str, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    results
+        .into_iter()
+        .map(|mut result| {
+            result.text = extractive_compress(query, &result.text);
+            result
+        })
+        .collect()
+}
+
+/// Trim every retrieved chunk down to the sentences relevant to the query
+pub async fn compress_results(mode: CompressionMode, query: &str, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    match mode {
+        CompressionMode::None => results,
+        CompressionMode::Extractive => extractive_results(query, results),
+        CompressionMode::Llm => {
+            let gemini_service = match GeminiService::new() {
+                Ok(service) => service,
+                Err(e) => {
+                    tracing::warn!("⚠️ LLM compression unavailable, using extractive: {}", e);
+                    return extractive_results(query, results);
+                }
+            };
+
+            let mut compressed = Vec::with_capacity(results.len());
+            for mut result in results {
+                match gemini_service.extract_relevant_sentences(query, &result.text).await {
+                    Ok(Some(text)) => result.text = text,
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("⚠️ LLM compression failed for chunk, using extractive: {}", e);
+                        result.text = extractive_compress(query, &result.text);
+                    }
+                }
+                compressed.push(result);
+            }
+            compressed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = split_sentences("First one. Second one!\nThird without end");
+        assert_eq!(sentences, vec!["First one.", "Second one!", "Third without end"]);
+    }
+
+    #[test]
+    fn test_extractive_keeps_relevant_sentences() {
+        let text = "The warranty lasts two years. Shipping is free. Warranty claims need a receipt.";
+        let compressed = extractive_compress("How long is the warranty?", text);
+        assert_eq!(compressed, "The warranty lasts two years. Warranty claims need a receipt.");
+    }
+
+    #[test]
+    fn test_extractive_keeps_chunk_when_nothing_matches() {
+        let text = "Completely unrelated content.";
+        assert_eq!(extractive_compress("warranty length", text), text);
+    }
+
+    #[test]
+    fn test_mode_parse() {
+        assert_eq!(CompressionMode::parse("LLM"), Some(CompressionMode::Llm));
+        assert_eq!(CompressionMode::parse("bogus"), None);
+    }
+}
@@ -0,0 +1,572 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use url::Url;
+
+use crate::services::connector::{Connector, ConnectorChanges, ConnectorDocument};
+use crate::services::help_center::decode_entities;
+use crate::services::public_url::{check_public_host, public_client_builder};
+
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const PAGE_SIZE: usize = 50;
+// Stop paging after this many result pages, in case the API keeps returning a next link
+const MAX_RESULT_PAGES: usize = 500;
+// CQL compares `lastmodified` to the minute and in the account's time zone, so incremental syncs
+// look back this much before the cursor. Pages fetched again are skipped by their content hash
+const CURSOR_OVERLAP_HOURS: i64 = 24;
+// Confluence Cloud answers 429 with `Retry-After` when the account's rate limit is used up
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+// Tags whose contents are macro settings or markup rather than page text
+const SKIPPED_TAGS: &[&str] = &["ac:parameter", "ac:placeholder", "ac:task-id", "ac:task-status", "script", "style"];
+// Macros whose plain-text body is code
+const CODE_MACROS: &[&str] = &["code", "noformat"];
+
+/// Confluence settings kept in a connector's `config`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfluenceConfig {
+    /// Root of the site, like `https://example.atlassian.net/wiki`
+    pub base_url: String,
+    /// Atlassian account the API token belongs to, on Confluence Cloud. Without it the token is
+    /// sent as a Data Center personal access token
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Sync only these spaces instead of every space the account can read
+    #[serde(default)]
+    pub space_keys: Vec<String>,
+}
+
+/// Space keys are letters and digits; personal spaces are `~` and the owner's account id
+pub fn is_valid_space_key(key: &str) -> bool {
+    let key = key.strip_prefix('~').unwrap_or(key);
+    !key.is_empty()
+        && key.len() <= 255
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':'))
+}
+
+// CQL for the pages to sync: every page, or those edited since `since` give or take the overlap
+fn search_cql(space_keys: &[String], since: Option<DateTime<Utc>>) -> String {
+    let mut cql = "type = page".to_string();
+    if !space_keys.is_empty() {
+        let keys: Vec<String> = space_keys.iter().map(|key| format!("\"{}\"", key)).collect();
+        cql.push_str(&format!(" AND space in ({})", keys.join(", ")));
+    }
+    if let Some(since) = since {
+        let from = since - chrono::Duration::hours(CURSOR_OVERLAP_HOURS);
+        cql.push_str(&format!(" AND lastmodified >= \"{}\"", from.format("%Y-%m-%d %H:%M")));
+    }
+    cql
+}
+
+/// Convert a page body in Confluence storage format (XHTML with `ac:` macros) to markdown-like
+/// text. Headings, lists, tables and code blocks keep their shape, links keep their text and
+/// macro settings are dropped
+pub fn storage_to_text(storage: &str) -> String {
+    let mut writer = StorageWriter::default();
+    let mut rest = storage;
+    while let Some(start) = rest.find('<') {
+        writer.text(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").unwrap_or(body.len());
+            writer.cdata(&body[..end]);
+            rest = body.get(end + 3..).unwrap_or_default();
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or_default();
+            continue;
+        }
+        let Some(end) = rest.find('>') else { break };
+        writer.tag(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    writer.text(rest);
+    writer.finish()
+}
+
+// Value of an attribute in a tag's source, like `ac:name="code"`
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].to_string())
+}
+
+#[derive(Default)]
+struct StorageWriter {
+    out: String,
+    // Tag whose contents are skipped, and how many of it are open
+    skipping: Option<(String, usize)>,
+    // Open lists; ordered ones count their items
+    lists: Vec<Option<usize>>,
+    // Open macros, and whether each is a code block
+    macros: Vec<bool>,
+    // Open table cells, in which block tags don't end the row
+    cells: usize,
+    // Title of a page link, written unless the link has text of its own
+    link_title: Option<String>,
+    in_plain_link_body: bool,
+    // Whether the current line has only a list marker so far
+    item_start: bool,
+}
+
+impl StorageWriter {
+    fn text(&mut self, text: &str) {
+        if self.skipping.is_some() || text.is_empty() {
+            return;
+        }
+        let text = decode_entities(text);
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.is_empty() || text.starts_with(char::is_whitespace) {
+            self.push_space();
+        }
+        if !collapsed.is_empty() {
+            self.out.push_str(&collapsed);
+            self.item_start = false;
+            if text.ends_with(char::is_whitespace) {
+                self.push_space();
+            }
+        }
+    }
+
+    // CDATA holds code and plain-text link text; in other macros it is markup or settings
+    fn cdata(&mut self, content: &str) {
+        if self.skipping.is_some() {
+            return;
+        }
+        if self.macros.last() == Some(&true) {
+            self.out.push_str(content.trim_matches('\n'));
+        } else if self.in_plain_link_body {
+            self.text(content);
+        }
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let self_closing = tag.trim_end().ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '-'))
+            .collect::<String>()
+            .to_lowercase();
+
+        if let Some((skipped, depth)) = &mut self.skipping {
+            if *skipped == name && !self_closing {
+                if closing {
+                    *depth -= 1;
+                } else {
+                    *depth += 1;
+                }
+            }
+            if *depth == 0 {
+                self.skipping = None;
+            }
+            return;
+        }
+        if SKIPPED_TAGS.contains(&name.as_str()) {
+            if !closing && !self_closing {
+                self.skipping = Some((name, 1));
+            }
+            return;
+        }
+
+        match (name.as_str(), closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.line_break();
+                let level = name[1..].parse().unwrap_or(1);
+                self.out.push_str(&format!("{} ", "#".repeat(level)));
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => self.line_break(),
+            ("p" | "div" | "blockquote" | "pre" | "br", _) => self.block_break(),
+            ("ul" | "ac:task-list", false) => {
+                self.line_break();
+                self.lists.push(None);
+            }
+            ("ol", false) => {
+                self.line_break();
+                self.lists.push(Some(0));
+            }
+            ("ul" | "ol" | "ac:task-list", true) => {
+                self.lists.pop();
+                self.line_break();
+            }
+            ("li" | "ac:task", false) => {
+                self.line_break();
+                self.out.push_str(&"  ".repeat(self.lists.len().saturating_sub(1)));
+                match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        self.out.push_str(&format!("{}. ", number));
+                    }
+                    _ => self.out.push_str("- "),
+                }
+                self.item_start = true;
+            }
+            ("li" | "ac:task", true) => self.line_break(),
+            ("table", _) => self.line_break(),
+            ("tr", false) => self.line_break(),
+            ("tr", true) => {
+                self.push_space();
+                self.out.push('|');
+                self.line_break();
+            }
+            ("td" | "th", false) => {
+                self.push_space();
+                self.out.push_str("| ");
+                self.cells += 1;
+            }
+            ("td" | "th", true) => self.cells = self.cells.saturating_sub(1),
+            ("hr", _) => {
+                self.line_break();
+                self.out.push_str("---");
+                self.line_break();
+            }
+            ("ac:structured-macro", false) if !self_closing => {
+                let code = attribute(tag, "ac:name").is_some_and(|macro_name| CODE_MACROS.contains(&macro_name.as_str()));
+                if code {
+                    self.line_break();
+                    self.out.push_str("```\n");
+                }
+                self.macros.push(code);
+            }
+            ("ac:structured-macro", true) => {
+                if self.macros.pop() == Some(true) {
+                    self.line_break();
+                    self.out.push_str("```");
+                    self.line_break();
+                }
+            }
+            ("ac:link", false) => self.link_title = None,
+            ("ri:page", false) => self.link_title = attribute(tag, "ri:content-title"),
+            ("ri:attachment", false) => self.link_title = attribute(tag, "ri:filename"),
+            ("ac:link-body", false) => self.link_title = None,
+            ("ac:plain-text-link-body", false) => {
+                self.link_title = None;
+                self.in_plain_link_body = true;
+            }
+            ("ac:plain-text-link-body", true) => self.in_plain_link_body = false,
+            ("ac:link", true) => {
+                if let Some(title) = self.link_title.take() {
+                    self.text(&title);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with([' ', '\n']) {
+            self.out.push(' ');
+        }
+    }
+
+    fn line_break(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.item_start = false;
+    }
+
+    // Paragraph-level tags end the line, except in table cells and right after a list marker
+    fn block_break(&mut self) {
+        if self.cells > 0 {
+            self.push_space();
+        } else if !self.item_start {
+            self.line_break();
+        }
+    }
+
+    // Trim lines and drop empty ones, keeping code blocks as they are
+    fn finish(self) -> String {
+        let mut lines = Vec::new();
+        let mut in_code = false;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line == "```" {
+                in_code = !in_code;
+            } else if !in_code && line.is_empty() {
+                continue;
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Links {
+    base: Option<String>,
+    next: Option<String>,
+    webui: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<ConfluencePage>,
+    #[serde(default, rename = "_links")]
+    links: Links,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfluencePage {
+    id: String,
+    title: String,
+    space: Option<Space>,
+    version: Option<Version>,
+    body: Option<Body>,
+    #[serde(default, rename = "_links")]
+    links: Links,
+}
+
+#[derive(Debug, Deserialize)]
+struct Space {
+    key: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Version {
+    when: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Body {
+    storage: Option<Storage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Storage {
+    value: String,
+}
+
+impl ConfluencePage {
+    fn edited_at(&self) -> Option<DateTime<Utc>> {
+        self.version
+            .as_ref()
+            .and_then(|version| version.when.as_deref())
+            .and_then(|when| DateTime::parse_from_rfc3339(when).ok())
+            .map(|when| when.with_timezone(&Utc))
+    }
+
+    // `base` is the site root the page's links are relative to
+    fn document(&self, base: &str) -> ConnectorDocument {
+        let storage = self
+            .body
+            .as_ref()
+            .and_then(|body| body.storage.as_ref())
+            .map(|storage| storage.value.as_str())
+            .unwrap_or_default();
+        let space = self.space.as_ref().map(|space| space.name.clone().unwrap_or_else(|| space.key.clone()));
+        ConnectorDocument {
+            key: self.id.clone(),
+            title: self.title.clone(),
+            url: self.links.webui.as_ref().map(|webui| format!("{}{}", base.trim_end_matches('/'), webui)),
+            metadata: space.map(|space| vec![("Space".to_string(), space)]).unwrap_or_default(),
+            text: storage_to_text(storage),
+        }
+    }
+}
+
+/// A Confluence site, read through the REST API with an API token or personal access token
+pub struct ConfluenceConnector {
+    access_token: String,
+    config: ConfluenceConfig,
+    client: reqwest::Client,
+}
+
+impl ConfluenceConnector {
+    pub fn new(access_token: &str, config: &Value) -> Result<Self, String> {
+        let mut config: ConfluenceConfig =
+            serde_json::from_value(config.clone()).map_err(|e| format!("Invalid Confluence config: {}", e))?;
+        match Url::parse(&config.base_url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => return Err(format!("Confluence base_url must be an http or https URL: {}", config.base_url)),
+        }
+        config.base_url = config.base_url.trim_end_matches('/').to_string();
+        if let Some(key) = config.space_keys.iter().find(|key| !is_valid_space_key(key)) {
+            return Err(format!("Invalid Confluence space key '{}'", key));
+        }
+        // The site is tenant-supplied and gets the token, so it may only be on a public address
+        let client = public_client_builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { access_token: access_token.trim().to_string(), config, client })
+    }
+
+    /// Reject a site whose host resolves to a private address
+    pub async fn check_host(&self) -> Result<(), String> {
+        let url = Url::parse(&self.config.base_url).map_err(|e| format!("Invalid Confluence base_url: {}", e))?;
+        check_public_host(&url).await
+    }
+
+    // GET an API URL, waiting out rate limits
+    async fn get(&self, url: &str) -> Result<Value> {
+        let mut retries = 0;
+        loop {
+            let request = self.client.get(url).header(reqwest::header::ACCEPT, "application/json");
+            let request = match &self.config.email {
+                Some(email) => request.basic_auth(email, Some(&self.access_token)),
+                None => request.bearer_auth(&self.access_token),
+            };
+            let response = request.send().await?;
+
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS && retries < MAX_RATE_LIMIT_RETRIES {
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(1)
+                    .min(MAX_RETRY_AFTER_SECS);
+                retries += 1;
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(anyhow::anyhow!("{} returned {}", url, response.status()));
+            }
+            return Ok(response.json().await?);
+        }
+    }
+
+    // Current pages in the configured spaces with their storage-format bodies, and the site root
+    // their links are relative to
+    async fn search_pages(&self, since: Option<DateTime<Utc>>) -> Result<(Vec<ConfluencePage>, String)> {
+        let cql = search_cql(&self.config.space_keys, since);
+        let limit = PAGE_SIZE.to_string();
+        let mut url = Url::parse_with_params(
+            &format!("{}/rest/api/content/search", self.config.base_url),
+            &[("cql", cql.as_str()), ("limit", limit.as_str()), ("expand", "body.storage,version,space")],
+        )?
+        .to_string();
+
+        let mut base = self.config.base_url.clone();
+        let mut pages = Vec::new();
+        for _ in 0..MAX_RESULT_PAGES {
+            let response: SearchResponse = serde_json::from_value(self.get(&url).await?)?;
+            if let Some(site) = response.links.base {
+                base = site.trim_end_matches('/').to_string();
+            }
+            pages.extend(response.results);
+            match response.links.next {
+                Some(next) => url = format!("{}{}", base, next),
+                None => break,
+            }
+        }
+        Ok((pages, base))
+    }
+}
+
+impl Connector for ConfluenceConnector {
+    async fn sync(&self, cursor: Option<&str>) -> Result<ConnectorChanges> {
+        let since = match cursor {
+            Some(cursor) => Some(
+                DateTime::parse_from_rfc3339(cursor)
+                    .map_err(|e| anyhow::anyhow!("Invalid Confluence cursor '{}': {}", cursor, e))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        let (pages, base) = self.search_pages(since).await?;
+
+        // Trashed pages aren't returned, so a full listing is what removes them
+        let mut changes = ConnectorChanges {
+            listed: since.is_none().then(HashSet::new),
+            cursor: pages.iter().filter_map(ConfluencePage::edited_at).chain(since).max().map(|time| time.to_rfc3339()),
+            ..Default::default()
+        };
+        for page in &pages {
+            if let Some(listed) = &mut changes.listed {
+                listed.insert(page.id.clone());
+            }
+            changes.updated.push(page.document(&base));
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_storage_to_text_keeps_structure() {
+        let storage = "<h2>Setup</h2><p>Install the <strong>CLI</strong> first.</p>\
+            <ul><li><p>Step one</p><ol><li>Sub a</li><li>Sub b</li></ol></li><li>Step two</li></ul>";
+        assert_eq!(
+            storage_to_text(storage),
+            "## Setup\nInstall the CLI first.\n- Step one\n  1. Sub a\n  2. Sub b\n- Step two"
+        );
+    }
+
+    #[test]
+    fn test_storage_to_text_tables_macros_and_links() {
+        let storage = "<table><tbody><tr><th><p>Plan</p></th><th><p>Price</p></th></tr>\
+            <tr><td>Pro</td><td>$10 &amp; up</td></tr></tbody></table>\
+            <ac:structured-macro ac:name=\"code\" ac:schema-version=\"1\">\
+            <ac:parameter ac:name=\"language\">bash</ac:parameter>\
+            <ac:plain-text-body><![CDATA[cargo build\n  --release]]></ac:plain-text-body></ac:structured-macro>\
+            <ac:structured-macro ac:name=\"info\"><ac:rich-text-body><p>Note this.</p></ac:rich-text-body></ac:structured-macro>\
+            <p>See <ac:link><ri:page ri:content-title=\"Billing &amp; Plans\" /></ac:link> and \
+            <ac:link><ri:page ri:content-title=\"FAQ\" /><ac:plain-text-link-body><![CDATA[the FAQ]]></ac:plain-text-link-body></ac:link>.</p>\
+            <ac:structured-macro ac:name=\"toc\" />";
+        assert_eq!(
+            storage_to_text(storage),
+            "| Plan | Price |\n| Pro | $10 & up |\n```\ncargo build\n  --release\n```\nNote this.\n\
+             See Billing & Plans and the FAQ."
+        );
+    }
+
+    #[test]
+    fn test_search_cql() {
+        let since = "2026-03-02T10:30:00Z".parse().unwrap();
+        assert_eq!(search_cql(&[], None), "type = page");
+        assert_eq!(
+            search_cql(&["ENG".to_string(), "~5570:ab-c".to_string()], Some(since)),
+            "type = page AND space in (\"ENG\", \"~5570:ab-c\") AND lastmodified >= \"2026-03-01 10:30\""
+        );
+    }
+
+    #[test]
+    fn test_page_document() {
+        let page: ConfluencePage = serde_json::from_value(json!({
+            "id": "98765",
+            "title": "Refunds",
+            "space": { "key": "SUP", "name": "Support" },
+            "version": { "when": "2026-03-01T09:15:00.000Z" },
+            "body": { "storage": { "value": "<p>Within 30 days.</p>" } },
+            "_links": { "webui": "/spaces/SUP/pages/98765/Refunds" }
+        }))
+        .unwrap();
+        let document = page.document("https://example.atlassian.net/wiki/");
+        assert_eq!(document.key, "98765");
+        assert_eq!(document.url.as_deref(), Some("https://example.atlassian.net/wiki/spaces/SUP/pages/98765/Refunds"));
+        assert_eq!(document.metadata, vec![("Space".to_string(), "Support".to_string())]);
+        assert_eq!(document.text, "Within 30 days.");
+        assert_eq!(page.edited_at().map(|time| time.to_rfc3339()), Some("2026-03-01T09:15:00+00:00".to_string()));
+    }
+
+    #[test]
+    fn test_new_validates_config() {
+        assert!(ConfluenceConnector::new("token", &json!({})).is_err());
+        assert!(ConfluenceConnector::new("token", &json!({ "base_url": "ftp://wiki.example.com" })).is_err());
+        assert!(
+            ConfluenceConnector::new("token", &json!({ "base_url": "https://wiki.example.com", "space_keys": ["ENG\" OR space = \"HR"] }))
+                .is_err()
+        );
+        assert!(ConfluenceConnector::new("token", &json!({ "base_url": "https://wiki.example.com", "spaces": [] })).is_err());
+        assert!(ConfluenceConnector::new(
+            "token",
+            &json!({ "base_url": "https://example.atlassian.net/wiki/", "email": "ops@example.com", "space_keys": ["ENG"] })
+        )
+        .is_ok());
+    }
+}
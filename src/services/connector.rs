@@ -0,0 +1,440 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::models::{SourceConnector, SourceConnectorDocument};
+use crate::db::queries::{
+    claim_due_source_connectors, delete_document_usage, delete_source_connector_document, is_reindex_running,
+    list_source_connector_documents, record_source_connector_error, record_source_connector_synced,
+    remove_cold_document, upsert_source_connector_document,
+};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
+use crate::services::confluence::ConfluenceConnector;
+use crate::services::document_store::content_hash;
+use crate::services::embedding::EmbeddingService;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::ingest_webhook::IngestWebhook;
+use crate::services::notion::NotionConnector;
+use crate::services::reindex::stale_after_secs;
+use crate::services::sharding::shard_indices;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::{chatbot_index_name, VectorBackend, VectorStore};
+use crate::utils::pdf::chunk_text;
+
+pub const DEFAULT_INTERVAL_MINUTES: i32 = 60;
+pub const MIN_INTERVAL_MINUTES: i32 = 15;
+// Incremental syncs can't see deletions, so every connector lists its whole source this often
+const FULL_SYNC_HOURS: i64 = 24;
+const TICK_SECS: u64 = 60;
+// Connectors claimed per tick
+const BATCH_SIZE: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectorKind {
+    Notion,
+    Confluence,
+}
+
+impl ConnectorKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "notion" => Some(Self::Notion),
+            "confluence" => Some(Self::Confluence),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Notion => "notion",
+            Self::Confluence => "confluence",
+        }
+    }
+}
+
+/// Document path for a synced document's chunks, so a re-sync replaces them
+pub fn document_path(kind: ConnectorKind, connector_name: &str, key: &str) -> String {
+    format!("{}:{}/{}", kind.as_str(), connector_name, key)
+}
+
+/// A document read from a connector's source, with its body as markdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectorDocument {
+    /// Identifies the document at the source across syncs
+    pub key: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Labels like the space a page is in, written into every chunk's header
+    pub metadata: Vec<(String, String)>,
+    pub text: String,
+}
+
+impl ConnectorDocument {
+    /// Chunk the body, starting every chunk with the title, URL and metadata so they are searchable
+    /// and show up in citations
+    pub fn chunks(&self, chunk_size: usize, overlap: usize) -> Vec<String> {
+        let mut header = format!("Title: {}\n", self.title);
+        if let Some(url) = &self.url {
+            header.push_str(&format!("URL: {}\n", url));
+        }
+        for (label, value) in &self.metadata {
+            header.push_str(&format!("{}: {}\n", label, value));
+        }
+        header.push('\n');
+
+        chunk_text(&self.text, chunk_size, overlap)
+            .into_iter()
+            .map(|chunk| format!("{}{}", header, chunk))
+            .collect()
+    }
+
+    // Hash of everything the chunks are built from, to skip documents that haven't changed
+    fn content_hash(&self) -> String {
+        let url = self.url.as_deref().unwrap_or_default();
+        let metadata: String = self.metadata.iter().map(|(label, value)| format!("{}: {}\n", label, value)).collect();
+        content_hash(format!("{}\n{}\n{}{}", self.title, url, metadata, self.text).as_bytes())
+    }
+}
+
+/// The source URL in the header of a chunk written by a connector, for citations
+pub fn chunk_url(text: &str) -> Option<&str> {
+    if !text.starts_with("Title: ") {
+        return None;
+    }
+    text.lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| line.strip_prefix("URL: "))
+        .map(str::trim)
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+}
+
+/// What a connector found at its source
+#[derive(Debug, Default)]
+pub struct ConnectorChanges {
+    /// Documents created or edited since the cursor
+    pub updated: Vec<ConnectorDocument>,
+    /// Keys of documents deleted or archived at the source
+    pub removed: Vec<String>,
+    /// Keys of every document at the source, when the connector listed them all. Indexed
+    /// documents missing from it are removed
+    pub listed: Option<HashSet<String>>,
+    /// Where the next incremental sync starts; None keeps the previous cursor
+    pub cursor: Option<String>,
+}
+
+/// A workspace whose documents are synced into a chatbot
+pub trait Connector {
+    /// Documents changed since `cursor`, or every document when it is None
+    fn sync(&self, cursor: Option<&str>) -> impl Future<Output = Result<ConnectorChanges>> + Send;
+}
+
+/// A stored connector, ready to sync from
+pub enum ConnectorSource {
+    Notion(NotionConnector),
+    Confluence(ConfluenceConnector),
+}
+
+impl ConnectorSource {
+    /// Check a connector's settings and build it. Errors are meant for the API caller
+    pub fn new(kind: ConnectorKind, access_token: &str, config: &Value) -> Result<Self, String> {
+        if access_token.trim().is_empty() {
+            return Err("Connector needs an access token".to_string());
+        }
+        match kind {
+            ConnectorKind::Notion => Ok(Self::Notion(NotionConnector::new(access_token, config)?)),
+            ConnectorKind::Confluence => Ok(Self::Confluence(ConfluenceConnector::new(access_token, config)?)),
+        }
+    }
+
+    pub fn from_connector(connector: &SourceConnector) -> Result<Self, String> {
+        let kind = ConnectorKind::parse(&connector.kind)
+            .ok_or_else(|| format!("Unknown connector kind '{}'", connector.kind))?;
+        Self::new(kind, &connector.access_token, &connector.config)
+    }
+
+    /// Check that the connector only reaches public addresses; Notion's API host is fixed
+    pub async fn check_hosts(&self) -> Result<(), String> {
+        match self {
+            Self::Notion(_) => Ok(()),
+            Self::Confluence(connector) => connector.check_host().await,
+        }
+    }
+
+    pub fn kind(&self) -> ConnectorKind {
+        match self {
+            Self::Notion(_) => ConnectorKind::Notion,
+            Self::Confluence(_) => ConnectorKind::Confluence,
+        }
+    }
+}
+
+impl Connector for ConnectorSource {
+    async fn sync(&self, cursor: Option<&str>) -> Result<ConnectorChanges> {
+        match self {
+            Self::Notion(connector) => connector.sync(cursor).await,
+            Self::Confluence(connector) => connector.sync(cursor).await,
+        }
+    }
+}
+
+/// Counts from one sync
+#[derive(Debug, Default)]
+pub struct ConnectorSync {
+    /// Whether the source was listed in full, so deleted documents were removed too
+    pub full: bool,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub embedding_count: usize,
+    pub cursor: Option<String>,
+}
+
+/// Whether a sync should list the whole source: on request, before the first sync, and when the
+/// last full sync is more than a day old
+pub fn needs_full_sync(connector: &SourceConnector, requested: bool) -> bool {
+    requested
+        || connector.cursor.is_none()
+        || connector
+            .last_full_sync_at
+            .is_none_or(|at| at < Utc::now() - chrono::Duration::hours(FULL_SYNC_HOURS))
+}
+
+/// Fetch a connector's changes and bring the chatbot's index in line with them: new and edited
+/// documents are (re)indexed, unchanged ones skipped and deleted ones removed
+pub async fn sync_connector(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: &ChatbotCache,
+    connector: &SourceConnector,
+    full: bool,
+) -> Result<ConnectorSync> {
+    let chatbot = chatbot_cache
+        .get_chatbot(db, connector.organization_id, connector.chatbot_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Chatbot {} not found", connector.chatbot_id))?;
+    // Chunks written during a reindex would be lost at its swap
+    if is_reindex_running(db, chatbot.id, stale_after_secs()).await? {
+        return Err(anyhow::anyhow!("Chatbot is being reindexed"));
+    }
+
+    let source = ConnectorSource::from_connector(connector).map_err(|e| anyhow::anyhow!(e))?;
+    source.check_hosts().await.map_err(|e| anyhow::anyhow!(e))?;
+    let kind = source.kind();
+    let cursor = connector.cursor.as_deref().filter(|_| !full);
+    let changes = source.sync(cursor).await?;
+    let indexed: HashMap<String, String> = list_source_connector_documents(db, connector.id)
+        .await?
+        .into_iter()
+        .map(|document| (document.document_key, document.content_hash))
+        .collect();
+
+    let embedding_service = EmbeddingService::new(vector_store.clone(), embedding_cache)?;
+    let source_name = format!("{}:{}", kind.as_str(), connector.name);
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(db, chatbot_cache, connector.organization_id, &chatbot, &source_name)
+        .await?;
+    let index_names = shard_indices(&chatbot_index_name(connector.organization_id, chatbot.id), chatbot.shard_count);
+    let webhook = IngestWebhook::for_chatbot(&chatbot);
+
+    let mut sync = ConnectorSync {
+        full: cursor.is_none(),
+        cursor: changes.cursor.clone().or_else(|| connector.cursor.clone()),
+        ..Default::default()
+    };
+    for document in &changes.updated {
+        let hash = document.content_hash();
+        if indexed.get(&document.key) == Some(&hash) {
+            sync.unchanged += 1;
+            continue;
+        }
+
+        let file_path = document_path(kind, &connector.name, &document.key);
+        vector_store.delete_document_chunks(&index_names, &file_path).await?;
+        sync.embedding_count += embedding_service
+            .index_chunks(&file_path, document.chunks(200, 50), &collection_name, webhook.as_ref())
+            .await?;
+        upsert_source_connector_document(
+            db,
+            connector.id,
+            &SourceConnectorDocument { document_key: document.key.clone(), content_hash: hash },
+        )
+        .await?;
+
+        if let Err(e) = remove_cold_document(db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+        sync.updated += 1;
+    }
+
+    let mut removed: HashSet<&str> = changes.removed.iter().map(String::as_str).collect();
+    if let Some(listed) = &changes.listed {
+        removed.extend(indexed.keys().map(String::as_str).filter(|key| !listed.contains(*key)));
+    }
+    for key in removed.into_iter().filter(|key| indexed.contains_key(*key)) {
+        let file_path = document_path(kind, &connector.name, key);
+        vector_store.delete_document_chunks(&index_names, &file_path).await?;
+        delete_source_connector_document(db, connector.id, key).await?;
+
+        // Drop any cold copy too, so the document can't be rehydrated
+        if let Err(e) = remove_cold_document(db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to remove cold copy of {}: {}", file_path, e);
+        }
+        if let Err(e) = delete_document_usage(db, chatbot.id, &file_path).await {
+            tracing::warn!("⚠️ Failed to delete document usage of {}: {}", file_path, e);
+        }
+        sync.removed += 1;
+    }
+
+    if sync.updated + sync.removed > 0 {
+        publish(db, chatbot_cache, CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id }).await?;
+    }
+    Ok(sync)
+}
+
+/// Sync a connector and record the outcome on it. `full` forces a full listing of the source
+pub async fn run_sync(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: &ChatbotCache,
+    connector: &SourceConnector,
+    full: bool,
+) -> Result<ConnectorSync> {
+    let full = needs_full_sync(connector, full);
+    match sync_connector(db, vector_store, embedding_cache, chatbot_cache, connector, full).await {
+        Ok(sync) => {
+            record_source_connector_synced(db, connector.id, sync.cursor.as_deref(), sync.full).await?;
+            tracing::info!(
+                "✅ Synced {} connector {} ({}, {} updated, {} unchanged, {} removed)",
+                connector.kind,
+                connector.name,
+                if sync.full { "full" } else { "incremental" },
+                sync.updated,
+                sync.unchanged,
+                sync.removed
+            );
+            Ok(sync)
+        }
+        Err(e) => {
+            if let Err(record) = record_source_connector_error(db, connector.id, &e.to_string()).await {
+                tracing::error!("❌ Failed to record error of connector {}: {}", connector.id, record);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Spawn the background task that syncs source connectors when they are due
+pub fn spawn_source_connector_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: Arc<ChatbotCache>,
+) {
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let due = match claim_due_source_connectors(&db, BATCH_SIZE).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!("❌ Connector task failed to list connectors: {}", e);
+                    continue;
+                }
+            };
+
+            for connector in &due {
+                if let Err(e) =
+                    run_sync(&db, &vector_store, embedding_cache.clone(), &chatbot_cache, connector, false).await
+                {
+                    tracing::error!(
+                        "❌ {} connector {} (chatbot {}) failed: {}",
+                        connector.kind,
+                        connector.name,
+                        connector.chatbot_id,
+                        e
+                    );
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn connector(cursor: Option<&str>, last_full_sync_hours_ago: Option<i64>) -> SourceConnector {
+        let now = Utc::now();
+        SourceConnector {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            chatbot_id: Uuid::new_v4(),
+            kind: "notion".to_string(),
+            name: "wiki".to_string(),
+            access_token: "secret".to_string(),
+            config: json!({}),
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+            cursor: cursor.map(str::to_string),
+            document_count: 0,
+            next_sync_at: now,
+            last_synced_at: None,
+            last_full_sync_at: last_full_sync_hours_ago.map(|hours| now - chrono::Duration::hours(hours)),
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_needs_full_sync() {
+        assert!(needs_full_sync(&connector(None, Some(1)), false));
+        assert!(needs_full_sync(&connector(Some("2026-03-01T10:00:00Z"), Some(1)), true));
+        assert!(needs_full_sync(&connector(Some("2026-03-01T10:00:00Z"), Some(25)), false));
+        assert!(needs_full_sync(&connector(Some("2026-03-01T10:00:00Z"), None), false));
+        assert!(!needs_full_sync(&connector(Some("2026-03-01T10:00:00Z"), Some(1)), false));
+    }
+
+    #[test]
+    fn test_document_chunks_start_with_metadata() {
+        let document = ConnectorDocument {
+            key: "a1b2".to_string(),
+            title: "Onboarding".to_string(),
+            url: Some("https://www.notion.so/Onboarding-a1b2".to_string()),
+            metadata: vec![("Space".to_string(), "People".to_string())],
+            text: "# Week one\nMeet your buddy.".to_string(),
+        };
+        let chunks = document.chunks(200, 50);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].starts_with("Title: Onboarding\nURL: https://www.notion.so/Onboarding-a1b2\nSpace: People\n\n"));
+        assert_eq!(chunk_url(&chunks[0]), Some("https://www.notion.so/Onboarding-a1b2"));
+        assert_eq!(chunk_url("Source: https://docs.example.com\n\nPricing"), None);
+        assert_eq!(chunk_url("Title: Returns\n\nURL: https://example.com"), None);
+        assert_eq!(document_path(ConnectorKind::Notion, "wiki", &document.key), "notion:wiki/a1b2");
+    }
+
+    #[test]
+    fn test_new_rejects_bad_settings() {
+        assert!(ConnectorSource::new(ConnectorKind::Notion, " ", &json!({})).is_err());
+        assert!(ConnectorSource::new(ConnectorKind::Notion, "secret", &json!({"root_page_ids": ["nope"]})).is_err());
+        assert!(ConnectorSource::new(ConnectorKind::Notion, "secret", &json!({})).is_ok());
+        assert_eq!(ConnectorKind::parse("notion"), Some(ConnectorKind::Notion));
+        assert!(ConnectorSource::new(ConnectorKind::Confluence, "secret", &json!({})).is_err());
+        assert_eq!(ConnectorKind::parse("confluence"), Some(ConnectorKind::Confluence));
+        assert_eq!(ConnectorKind::parse("dropbox"), None);
+    }
+}
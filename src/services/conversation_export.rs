@@ -0,0 +1,416 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::models::{Chat, Citation, ConversationExport};
+use crate::db::queries::set_conversation_citations;
+use crate::services::connector::chunk_url;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::SearchResult;
+use crate::utils::pdf_writer::{PdfDocument, Style};
+
+const CSV_HEADER: &str = "sequence_number,thread_id,created_at,user_query,bot_response,citations\r\n";
+// Characters of a cited chunk kept with the citation
+const EXCERPT_CHARS: usize = 300;
+
+/// Transcript formats offered by the export endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+    Csv,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    /// Whether the transcript can be sent one turn at a time. A PDF needs every page before its
+    /// cross-reference table can be written, so it is rendered whole by `render_transcript`
+    pub fn is_streamed(self) -> bool {
+        self != ExportFormat::Pdf
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "csv" => Ok(ExportFormat::Csv),
+            "pdf" => Ok(ExportFormat::Pdf),
+            other => Err(format!("unknown export format '{}'; use json, md, csv or pdf", other)),
+        }
+    }
+}
+
+/// Citations for the chunks an answer was grounded on
+pub fn citations_for(results: &[SearchResult]) -> Vec<Citation> {
+    results
+        .iter()
+        .map(|result| Citation {
+            file_path: result.file_path.clone(),
+            chunk_index: result.chunk_index,
+            score: result.score,
+            excerpt: Some(excerpt(&result.text)),
+            url: chunk_url(&result.text).map(str::to_string),
+        })
+        .collect()
+}
+
+/// Store the chunks an answer was grounded on, in the background
+pub fn record_citations(jobs: &BackgroundJobs, db: Arc<PgPool>, conversation_id: Uuid, results: &[SearchResult]) {
+    if results.is_empty() {
+        return;
+    }
+    let citations = citations_for(results);
+
+    jobs.spawn(async move {
+        if let Err(e) = set_conversation_citations(&db, conversation_id, &citations).await {
+            tracing::warn!("⚠️ Failed to record citations: {}", e);
+        }
+    });
+}
+
+// The start of a chunk's text, whitespace collapsed, cut at a word where possible
+fn excerpt(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let Some((cut, _)) = text.char_indices().nth(EXCERPT_CHARS) else {
+        return text;
+    };
+    let head = &text[..cut];
+    let head = head.rfind(' ').map(|space| &head[..space]).unwrap_or(head);
+    format!("{}...", head)
+}
+
+fn turn_citations(turn: &ConversationExport) -> &[Citation] {
+    turn.citations.as_ref().map(|citations| citations.0.as_slice()).unwrap_or_default()
+}
+
+// Who wrote the turn's reply. Agent replies from the handoff inbox have no user message
+fn responder(turn: &ConversationExport) -> &'static str {
+    match turn.author.as_str() {
+        "human" => "Agent",
+        _ => "Assistant",
+    }
+}
+
+/// Everything before the first turn
+pub fn render_header(format: ExportFormat, chat: &Chat, exported_at: DateTime<Utc>) -> String {
+    match format {
+        ExportFormat::Json => format!(
+            "{{\"chat_id\":{},\"title\":{},\"created_at\":{},\"exported_at\":{},\"conversations\":[",
+            json!(chat.id),
+            json!(chat.title),
+            json!(chat.created_at.to_rfc3339()),
+            json!(exported_at.to_rfc3339())
+        ),
+        ExportFormat::Markdown => format!(
+            "# {}\n\n- Chat: `{}`\n- Started: {}\n- Exported: {}\n",
+            chat.title,
+            chat.id,
+            chat.created_at.to_rfc3339(),
+            exported_at.to_rfc3339()
+        ),
+        ExportFormat::Csv => CSV_HEADER.to_string(),
+        ExportFormat::Pdf => String::new(),
+    }
+}
+
+/// One turn; `position` is its 0-based place in the export
+pub fn render_turn(format: ExportFormat, position: usize, turn: &ConversationExport) -> String {
+    match format {
+        ExportFormat::Json => {
+            let separator = if position == 0 { "" } else { "," };
+            let value = json!({
+                "id": turn.id,
+                "sequence_number": turn.sequence_number,
+                "thread_id": turn.thread_id,
+                "user_query": turn.user_query,
+                "bot_response": turn.bot_response,
+                "author": turn.author,
+                "citations": turn_citations(turn),
+                "created_at": turn.created_at.to_rfc3339(),
+                "updated_at": turn.updated_at.to_rfc3339()
+            });
+            format!("{}{}", separator, value)
+        }
+        ExportFormat::Markdown => {
+            let mut section = format!("\n---\n\n## {}. {}\n\n", turn.sequence_number, turn.created_at.to_rfc3339());
+            if let Some(thread_id) = turn.thread_id {
+                section.push_str(&format!("_Thread `{}`_\n\n", thread_id));
+            }
+            if !turn.user_query.is_empty() {
+                section.push_str(&format!("**User:**\n\n{}\n\n", turn.user_query));
+            }
+            section.push_str(&format!(
+                "**{}:**\n\n{}\n",
+                responder(turn),
+                turn.bot_response.as_deref().unwrap_or("_No response_")
+            ));
+            if !turn_citations(turn).is_empty() {
+                section.push_str("\n**Sources:**\n\n");
+                for citation in turn_citations(turn) {
+                    section.push_str(&format!(
+                        "- `{}` (chunk {}, score {:.2})\n",
+                        citation.file_path, citation.chunk_index, citation.score
+                    ));
+                    if let Some(excerpt) = &citation.excerpt {
+                        section.push_str(&format!("  > {}\n", excerpt));
+                    }
+                }
+            }
+            section
+        }
+        ExportFormat::Csv => {
+            let sources: Vec<String> = turn_citations(turn)
+                .iter()
+                .map(|citation| format!("{}#{} ({:.2})", citation.file_path, citation.chunk_index, citation.score))
+                .collect();
+            let cells = [
+                turn.sequence_number.to_string(),
+                turn.thread_id.map(|id| id.to_string()).unwrap_or_default(),
+                turn.created_at.to_rfc3339(),
+                csv_cell(&turn.user_query),
+                csv_cell(turn.bot_response.as_deref().unwrap_or("")),
+                csv_cell(&sources.join("; ")),
+            ];
+            format!("{}\r\n", cells.join(","))
+        }
+        ExportFormat::Pdf => String::new(),
+    }
+}
+
+/// Everything after the last turn
+pub fn render_footer(format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => "]}".to_string(),
+        ExportFormat::Markdown | ExportFormat::Csv | ExportFormat::Pdf => String::new(),
+    }
+}
+
+/// The whole transcript as file contents
+pub fn render_transcript(
+    format: ExportFormat,
+    chat: &Chat,
+    turns: &[ConversationExport],
+    exported_at: DateTime<Utc>,
+) -> Vec<u8> {
+    if format == ExportFormat::Pdf {
+        return render_pdf(chat, turns, exported_at);
+    }
+    let mut transcript = render_header(format, chat, exported_at);
+    for (position, turn) in turns.iter().enumerate() {
+        transcript.push_str(&render_turn(format, position, turn));
+    }
+    transcript.push_str(&render_footer(format));
+    transcript.into_bytes()
+}
+
+// A printable record of the chat: its metadata, then each turn with the excerpts it cited
+fn render_pdf(chat: &Chat, turns: &[ConversationExport], exported_at: DateTime<Utc>) -> Vec<u8> {
+    let mut document = PdfDocument::new(&chat.title);
+    document.paragraph(Style::Bold, 0, &chat.title);
+    document.blank_line();
+    document.paragraph(Style::Regular, 0, &format!("Chat:     {}", chat.id));
+    document.paragraph(Style::Regular, 0, &format!("Started:  {}", chat.created_at.to_rfc3339()));
+    document.paragraph(Style::Regular, 0, &format!("Exported: {}", exported_at.to_rfc3339()));
+    document.paragraph(Style::Regular, 0, &format!("Turns:    {}", turns.len()));
+
+    for turn in turns {
+        document.blank_line();
+        document.paragraph(Style::Bold, 0, &format!("{}. {}", turn.sequence_number, turn.created_at.to_rfc3339()));
+        if let Some(thread_id) = turn.thread_id {
+            document.paragraph(Style::Regular, 0, &format!("Thread {}", thread_id));
+        }
+        if !turn.user_query.is_empty() {
+            document.paragraph(Style::Bold, 0, "User:");
+            document.paragraph(Style::Regular, 2, &turn.user_query);
+        }
+        document.paragraph(Style::Bold, 0, &format!("{}:", responder(turn)));
+        document.paragraph(Style::Regular, 2, turn.bot_response.as_deref().unwrap_or("(no response)"));
+        if !turn_citations(turn).is_empty() {
+            document.paragraph(Style::Bold, 0, "Sources:");
+            for citation in turn_citations(turn) {
+                document.paragraph(
+                    Style::Regular,
+                    2,
+                    &format!("- {} (chunk {}, score {:.2})", citation.file_path, citation.chunk_index, citation.score),
+                );
+                if let Some(excerpt) = &citation.excerpt {
+                    document.paragraph(Style::Regular, 4, &format!("\"{}\"", excerpt));
+                }
+            }
+        }
+    }
+    document.render(exported_at)
+}
+
+// Quote a CSV field when needed. Text starting like a formula is prefixed with `'` so
+// spreadsheets opening the transcript don't evaluate user-supplied input
+fn csv_cell(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(citations: Vec<Citation>) -> ConversationExport {
+        let created_at = "2026-01-02T03:04:05Z".parse().unwrap();
+        ConversationExport {
+            id: Uuid::nil(),
+            sequence_number: 1,
+            thread_id: None,
+            user_query: "What is \"RAG\", exactly?".to_string(),
+            bot_response: Some("Retrieval-augmented generation.\nSee the guide.".to_string()),
+            citations: Some(sqlx::types::Json(citations)),
+            created_at,
+            updated_at: created_at,
+            author: "bot".to_string(),
+        }
+    }
+
+    fn chat() -> Chat {
+        Chat {
+            id: Uuid::nil(),
+            session_id: Uuid::nil(),
+            title: "Support \"chat\"".to_string(),
+            created_at: "2026-01-02T03:00:00Z".parse().unwrap(),
+            updated_at: "2026-01-02T03:00:00Z".parse().unwrap(),
+            status: "active".to_string(),
+            escalated_at: None,
+            escalation_reason: None,
+            user_id: None,
+            claimed_by: None,
+            claimed_at: None,
+            agent_read_at: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("JSON".parse(), Ok(ExportFormat::Json));
+        assert_eq!("markdown".parse(), Ok(ExportFormat::Markdown));
+        assert_eq!("md".parse(), Ok(ExportFormat::Markdown));
+        assert_eq!("csv".parse(), Ok(ExportFormat::Csv));
+        assert_eq!("PDF".parse(), Ok(ExportFormat::Pdf));
+        assert!("docx".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_json_export_is_valid_json() {
+        let citation = Citation { file_path: "guide.pdf".to_string(), chunk_index: 3, score: 0.5, excerpt: None, url: None };
+        let turns = [turn(vec![citation]), turn(Vec::new())];
+        let exported_at = "2026-01-03T00:00:00Z".parse().unwrap();
+
+        let mut document = render_header(ExportFormat::Json, &chat(), exported_at);
+        for (position, turn) in turns.iter().enumerate() {
+            document.push_str(&render_turn(ExportFormat::Json, position, turn));
+        }
+        document.push_str(&render_footer(ExportFormat::Json));
+
+        let value: serde_json::Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(value["title"], "Support \"chat\"");
+        assert_eq!(value["conversations"].as_array().unwrap().len(), 2);
+        assert_eq!(value["conversations"][0]["citations"][0]["file_path"], "guide.pdf");
+    }
+
+    #[test]
+    fn test_markdown_lists_sources() {
+        let citation = Citation {
+            file_path: "guide.pdf".to_string(),
+            chunk_index: 3,
+            score: 0.875,
+            excerpt: Some("RAG retrieves chunks first.".to_string()),
+            url: None,
+        };
+        let section = render_turn(ExportFormat::Markdown, 0, &turn(vec![citation]));
+        assert!(section.contains("**User:**\n\nWhat is \"RAG\", exactly?"));
+        assert!(section.contains("- `guide.pdf` (chunk 3, score 0.88)\n  > RAG retrieves chunks first.\n"));
+    }
+
+    #[test]
+    fn test_markdown_labels_agent_replies() {
+        let mut reply = turn(Vec::new());
+        reply.user_query = String::new();
+        reply.author = "human".to_string();
+        let section = render_turn(ExportFormat::Markdown, 0, &reply);
+        assert!(!section.contains("**User:**"));
+        assert!(section.contains("**Agent:**\n\nRetrieval-augmented generation."));
+    }
+
+    #[test]
+    fn test_excerpt_cuts_long_chunks() {
+        assert_eq!(excerpt("short\n\n chunk"), "short chunk");
+        let long = "word ".repeat(100);
+        let cut = excerpt(&long);
+        assert!(cut.len() <= EXCERPT_CHARS + 3);
+        assert!(cut.ends_with("word..."));
+    }
+
+    #[test]
+    fn test_pdf_transcript_includes_sources() {
+        let citation = Citation {
+            file_path: "guide.pdf".to_string(),
+            chunk_index: 3,
+            score: 0.875,
+            excerpt: Some("RAG retrieves chunks first.".to_string()),
+            url: None,
+        };
+        let exported_at = "2026-01-03T00:00:00Z".parse().unwrap();
+        let pdf = render_transcript(ExportFormat::Pdf, &chat(), &[turn(vec![citation])], exported_at);
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("(- guide.pdf \\(chunk 3, score 0.88\\))"));
+        assert!(text.contains("(    \"RAG retrieves chunks first.\")"));
+        assert!(text.contains("/Title (Support \"chat\")"));
+    }
+
+    #[test]
+    fn test_csv_row_quotes_fields() {
+        let row = render_turn(ExportFormat::Csv, 0, &turn(Vec::new()));
+        assert_eq!(
+            row,
+            "1,,2026-01-02T03:04:05+00:00,\"What is \"\"RAG\"\", exactly?\",\"Retrieval-augmented generation.\nSee the guide.\",\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_cell_neutralizes_formulas() {
+        assert_eq!(csv_cell("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_cell("-1"), "'-1");
+        assert_eq!(csv_cell("plain"), "plain");
+    }
+}
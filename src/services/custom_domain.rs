@@ -0,0 +1,112 @@
+use axum::http::{header, HeaderMap};
+
+use crate::db::models::WidgetBranding;
+
+const MAX_HOSTNAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+const MAX_BRANDING_TEXT_CHARS: usize = 500;
+
+/// Lowercase a hostname and check it is a plain DNS name with at least two labels
+pub fn normalize_hostname(hostname: &str) -> Result<String, String> {
+    let hostname = hostname.trim().trim_end_matches('.').to_lowercase();
+    if hostname.is_empty() || hostname.len() > MAX_HOSTNAME_LEN {
+        return Err(format!("Invalid hostname '{}'", hostname));
+    }
+
+    let labels: Vec<&str> = hostname.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && label.len() <= MAX_LABEL_LEN
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if labels.len() < 2 || !labels.iter().all(valid_label) {
+        return Err(format!("Invalid hostname '{}'; use a name such as help.example.com", hostname));
+    }
+    Ok(hostname)
+}
+
+/// Hostname the request was addressed to: X-Forwarded-Host from a proxy, else Host, without the port
+pub fn request_hostname(headers: &HeaderMap) -> Option<String> {
+    let raw = headers
+        .get("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST))
+        .and_then(|v| v.to_str().ok())?;
+    // A proxy chain may list several hosts; the first is the one the client used
+    let host = raw.split(',').next()?.trim();
+    let host = host.rsplit_once(':').map_or(host, |(name, port)| {
+        if port.chars().all(|c| c.is_ascii_digit()) { name } else { host }
+    });
+    normalize_hostname(host).ok()
+}
+
+/// A CSS hex color, `#rgb` or `#rrggbb`
+pub fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Check branding before storing it; the widget renders it on customer pages
+pub fn validate_branding(branding: &WidgetBranding) -> Result<(), String> {
+    if let Some(color) = &branding.primary_color
+        && !is_hex_color(color)
+    {
+        return Err(format!("primary_color must be a hex color such as #1a73e8, got '{}'", color));
+    }
+    if let Some(logo_url) = &branding.logo_url {
+        match url::Url::parse(logo_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err("logo_url must be an absolute http(s) URL".to_string()),
+        }
+    }
+    for (field, value) in [("title", &branding.title), ("welcome_message", &branding.welcome_message)] {
+        if value.as_ref().is_some_and(|v| v.chars().count() > MAX_BRANDING_TEXT_CHARS) {
+            return Err(format!("{} must be at most {} characters", field, MAX_BRANDING_TEXT_CHARS));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_normalize_hostname() {
+        assert_eq!(normalize_hostname(" Help.Example.COM. ").unwrap(), "help.example.com");
+        assert!(normalize_hostname("localhost").is_err());
+        assert!(normalize_hostname("https://help.example.com").is_err());
+        assert!(normalize_hostname("-bad.example.com").is_err());
+        assert!(normalize_hostname("a..example.com").is_err());
+    }
+
+    #[test]
+    fn test_request_hostname_prefers_forwarded_host_and_drops_port() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("api.internal:8000"));
+        assert_eq!(request_hostname(&headers).as_deref(), Some("api.internal"));
+
+        headers.insert("x-forwarded-host", HeaderValue::from_static("Help.Example.com, proxy.local"));
+        assert_eq!(request_hostname(&headers).as_deref(), Some("help.example.com"));
+    }
+
+    #[test]
+    fn test_validate_branding() {
+        let mut branding = WidgetBranding {
+            primary_color: Some("#1A73e8".to_string()),
+            logo_url: Some("https://cdn.example.com/logo.png".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_branding(&branding).is_ok());
+
+        branding.primary_color = Some("red; background: url(x)".to_string());
+        assert!(validate_branding(&branding).is_err());
+
+        branding.primary_color = None;
+        branding.logo_url = Some("javascript:alert(1)".to_string());
+        assert!(validate_branding(&branding).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use object_store::aws::AmazonS3Builder;
+use object_store::local::LocalFileSystem;
+use object_store::path::Path as ObjectPath;
+use object_store::signer::Signer;
+use object_store::ObjectStore;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const DEFAULT_ROOT: &str = "./data/documents";
+const DEFAULT_URL_EXPIRY_SECS: u64 = 3600;
+
+/// Hex SHA-256 of a file's bytes
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Object key for a document's original: one per chatbot and indexed path, so uploading the same
+/// document again overwrites it
+pub fn storage_key(organization_id: Uuid, chatbot_id: Uuid, file_path: &str) -> String {
+    format!("{}/{}/{}", organization_id, chatbot_id, &content_hash(file_path.as_bytes())[..32])
+}
+
+/// Object key for a stored transcript export. Exports live outside the chatbots' prefixes, so a
+/// bucket lifecycle rule on `exports/` can expire them
+pub fn export_key(organization_id: Uuid, chat_id: Uuid, extension: &str, exported_at: DateTime<Utc>) -> String {
+    format!("exports/{}/chat-{}-{}.{}", organization_id, chat_id, exported_at.format("%Y%m%dT%H%M%SZ"), extension)
+}
+
+// Seconds presigned URLs stay valid, from `DOCUMENT_STORE_URL_EXPIRY_SECS`
+fn url_expiry() -> Duration {
+    let secs = std::env::var("DOCUMENT_STORE_URL_EXPIRY_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &u64| *v > 0)
+        .unwrap_or(DEFAULT_URL_EXPIRY_SECS);
+    Duration::from_secs(secs)
+}
+
+// `DOCUMENT_STORE_S3_*` settings, falling back to the standard `AWS_*` variables
+fn s3_builder(bucket: &str) -> AmazonS3Builder {
+    let mut builder = AmazonS3Builder::from_env().with_bucket_name(bucket);
+    if let Ok(endpoint) = std::env::var("DOCUMENT_STORE_S3_ENDPOINT") {
+        // MinIO and other self-hosted stores are often plain HTTP
+        builder = builder.with_allow_http(endpoint.starts_with("http://")).with_endpoint(endpoint);
+    }
+    if let Ok(region) = std::env::var("DOCUMENT_STORE_S3_REGION") {
+        builder = builder.with_region(region);
+    }
+    if let Ok(access_key_id) = std::env::var("DOCUMENT_STORE_S3_ACCESS_KEY_ID") {
+        builder = builder.with_access_key_id(access_key_id);
+    }
+    if let Ok(secret_access_key) = std::env::var("DOCUMENT_STORE_S3_SECRET_ACCESS_KEY") {
+        builder = builder.with_secret_access_key(secret_access_key);
+    }
+    builder
+}
+
+/// Object storage for original uploads, kept so documents can be re-chunked and re-embedded
+/// without a re-upload, and for transcript exports. S3-compatible stores can hand out presigned
+/// download URLs
+pub struct DocumentStore {
+    store: Arc<dyn ObjectStore>,
+    signer: Option<Arc<dyn Signer>>,
+    url_expiry: Duration,
+    location: String,
+}
+
+impl DocumentStore {
+    /// `DOCUMENT_STORE` is `filesystem` (default, under `DOCUMENT_STORE_ROOT`), `s3` (bucket
+    /// `DOCUMENT_STORE_S3_BUCKET`, with `DOCUMENT_STORE_S3_ENDPOINT`, `_REGION`, `_ACCESS_KEY_ID`
+    /// and `_SECRET_ACCESS_KEY` or the standard `AWS_*` variables) or `none`, which returns None
+    pub fn from_env() -> Result<Option<Self>> {
+        let kind = std::env::var("DOCUMENT_STORE").unwrap_or_else(|_| "filesystem".to_string());
+        match kind.to_lowercase().as_str() {
+            "none" => Ok(None),
+            "filesystem" => {
+                let root = std::env::var("DOCUMENT_STORE_ROOT").unwrap_or_else(|_| DEFAULT_ROOT.to_string());
+                std::fs::create_dir_all(&root)?;
+                Ok(Some(Self {
+                    store: Arc::new(LocalFileSystem::new_with_prefix(&root)?),
+                    signer: None,
+                    url_expiry: url_expiry(),
+                    location: root,
+                }))
+            }
+            "s3" => {
+                let bucket = std::env::var("DOCUMENT_STORE_S3_BUCKET")
+                    .map_err(|_| anyhow::anyhow!("DOCUMENT_STORE=s3 requires DOCUMENT_STORE_S3_BUCKET"))?;
+                let store = Arc::new(s3_builder(&bucket).build()?);
+                Ok(Some(Self {
+                    store: store.clone(),
+                    signer: Some(store),
+                    url_expiry: url_expiry(),
+                    location: format!("s3://{}", bucket),
+                }))
+            }
+            other => Err(anyhow::anyhow!("unknown document store '{}'", other)),
+        }
+    }
+
+    /// Where originals are kept, for logs
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        self.store.put(&ObjectPath::from(key), data.into()).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let object = self.store.get(&ObjectPath::from(key)).await?;
+        Ok(object.bytes().await?.to_vec())
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.store.delete(&ObjectPath::from(key)).await?;
+        Ok(())
+    }
+
+    /// Whether `presigned_url` can return links
+    pub fn can_presign(&self) -> bool {
+        self.signer.is_some()
+    }
+
+    /// Time-limited download link for an object. None when the store can't sign URLs, as on the
+    /// filesystem
+    pub async fn presigned_url(&self, key: &str) -> Result<Option<String>> {
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+        let url = signer
+            .signed_url(reqwest::Method::GET, &ObjectPath::from(key), self.url_expiry)
+            .await?;
+        Ok(Some(url.to_string()))
+    }
+
+    /// How long presigned URLs stay valid
+    pub fn url_expiry(&self) -> Duration {
+        self.url_expiry
+    }
+
+    /// Delete every original of a chatbot. Returns how many were deleted
+    pub async fn delete_chatbot(&self, organization_id: Uuid, chatbot_id: Uuid) -> Result<usize> {
+        let prefix = ObjectPath::from(format!("{}/{}", organization_id, chatbot_id));
+        let objects: Vec<_> = self.store.list(Some(&prefix)).try_collect().await?;
+        for object in &objects {
+            self.store.delete(&object.location).await?;
+        }
+        Ok(objects.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_key_is_stable_per_document() {
+        let org = Uuid::new_v4();
+        let bot = Uuid::new_v4();
+        let key = storage_key(org, bot, "/tmp/manual.pdf");
+        assert_eq!(key, storage_key(org, bot, "/tmp/manual.pdf"));
+        assert_ne!(key, storage_key(org, bot, "/tmp/other.pdf"));
+        assert!(key.starts_with(&format!("{}/{}/", org, bot)));
+        assert_eq!(key.rsplit('/').next().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_export_key_is_outside_chatbot_prefixes() {
+        let org = Uuid::new_v4();
+        let chat = Uuid::new_v4();
+        let exported_at = DateTime::parse_from_rfc3339("2026-03-02T10:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(
+            export_key(org, chat, "csv", exported_at),
+            format!("exports/{}/chat-{}-20260302T100000Z.csv", org, chat)
+        );
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -1,34 +1,221 @@
 use anyhow::Result;
 use elasticsearch::{
-    indices::{IndicesCreateParts, IndicesExistsParts},
-    Elasticsearch, SearchParts,
+    cat::CatIndicesParts,
+    indices::{
+        IndicesCloseParts, IndicesCreateParts, IndicesDeleteParts, IndicesExistsParts, IndicesGetAliasParts,
+        IndicesGetMappingParts, IndicesOpenParts,
+    },
+    ClearScrollParts, CountParts, DeleteByQueryParts, Elasticsearch, ScrollParts, SearchParts,
 };
+use elasticsearch::http::response::Response;
+use elasticsearch::http::{headers::HeaderMap, Method};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use tracing;
 
+use crate::services::retry::{is_transient_status, with_retry, Upstream, UpstreamStatus};
+use crate::services::vector::{ChunkPage, DocumentWithEmbedding, SearchResult, VectorStore};
+
+// How long Elasticsearch keeps a scroll context alive between pages
+const SCROLL_KEEP_ALIVE: &str = "5m";
+/// Version of the chunk index mapping, stored in each new index's `_meta`. Bump it when the
+/// mapping changes so stale indices can be found and reindexed
+pub const MAPPING_VERSION: u64 = 2;
+
+// Send a request, retrying timeouts and 429/502/503/504 responses with backoff. Other
+// non-success responses are returned for the caller to handle
+async fn send_with_retry<F, Fut>(operation: &'static str, mut send: F) -> Result<Response>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Response, elasticsearch::Error>>,
+{
+    with_retry(Upstream::Elasticsearch, operation, || {
+        let request = send();
+        async move {
+            let response = request.await?;
+            let status = response.status_code().as_u16();
+            if is_transient_status(status) {
+                return Err(anyhow::Error::new(UpstreamStatus { status }));
+            }
+            Ok(response)
+        }
+    })
+    .await
+}
+
 pub struct ElasticsearchService {
     client: Arc<Elasticsearch>,
 }
 
+/// Size and freshness of one chunk index
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStats {
+    /// Concrete index name, the versioned index when the name is an alias
+    pub index: String,
+    /// Chunks in the index, primaries only
+    pub document_count: u64,
+    /// Storage used including replicas
+    pub size_in_bytes: u64,
+    /// `mapping_version` from the index's `_meta`; none for indices created before it was recorded
+    pub mapping_version: Option<u64>,
+    /// When the newest chunk was indexed
+    pub last_indexed_at: Option<String>,
+}
+
 impl ElasticsearchService {
     pub fn new(client: Arc<Elasticsearch>) -> Self {
         Self { client }
     }
 
+    // Whether an index or alias with this name exists
+    async fn index_exists(&self, index_name: &str) -> Result<bool> {
+        let indices = [index_name];
+        // The request builders borrow the namespace client, so it has to outlive the retried closure
+        let indices_api = self.client.indices();
+        let response = send_with_retry("exists_index", || {
+            indices_api
+                .exists(IndicesExistsParts::Index(&indices))
+                .send()
+        })
+        .await?;
+        Ok(response.status_code().is_success())
+    }
+
+    // Parse a response's JSON body, failing with its text on a non-success status
+    async fn json_or_error(response: Response, operation: &str) -> Result<Value> {
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("{} failed: {}", operation, error_text);
+            return Err(anyhow::anyhow!("{} failed", operation));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Document count, storage size, mapping version and last indexed time of each index, from the
+    /// `_stats` and `_mapping` APIs. Missing and closed indices are left out
+    #[tracing::instrument(name = "elasticsearch.index_stats", skip(self))]
+    pub async fn index_stats(&self, index_names: &[String]) -> Result<Vec<IndexStats>> {
+        if index_names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        // The client's stats builder has no ignore_unavailable, so the request is sent directly
+        let stats_path = format!("/{}/_stats/docs,store", indices.join(","));
+        let response = send_with_retry("index_stats", || {
+            self.client.transport().send(
+                Method::Get,
+                &stats_path,
+                HeaderMap::new(),
+                Some(&[("ignore_unavailable", "true")]),
+                Option::<()>::None,
+                None,
+            )
+        })
+        .await?;
+        let stats = Self::json_or_error(response, "Index stats").await?;
+
+        let indices_api = self.client.indices();
+        let response = send_with_retry("get_mapping", || {
+            indices_api
+                .get_mapping(IndicesGetMappingParts::Index(&indices))
+                .ignore_unavailable(true)
+                .send()
+        })
+        .await?;
+        let mappings = Self::json_or_error(response, "Get mapping").await?;
+
+        // Newest chunk per index in one search, bucketed by the concrete index name
+        let body = json!({
+            "size": 0,
+            "aggs": {
+                "by_index": {
+                    "terms": { "field": "_index", "size": indices.len().max(1) },
+                    "aggs": { "last_indexed": { "max": { "field": "created_at" } } }
+                }
+            }
+        });
+        let response = send_with_retry("last_indexed", || {
+            self.client
+                .search(SearchParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(body.clone())
+                .send()
+        })
+        .await?;
+        let search = Self::json_or_error(response, "Last indexed search").await?;
+        let last_indexed: HashMap<&str, &str> = search["aggregations"]["by_index"]["buckets"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .filter_map(|bucket| {
+                        Some((bucket["key"].as_str()?, bucket["last_indexed"]["value_as_string"].as_str()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Keyed by concrete index name, so shards served by aliases report their versioned index
+        let result: Vec<IndexStats> = stats["indices"]
+            .as_object()
+            .map(|by_index| {
+                by_index
+                    .iter()
+                    .map(|(index, stats)| IndexStats {
+                        index: index.clone(),
+                        document_count: stats["primaries"]["docs"]["count"].as_u64().unwrap_or(0),
+                        size_in_bytes: stats["total"]["store"]["size_in_bytes"].as_u64().unwrap_or(0),
+                        mapping_version: mappings[index]["mappings"]["_meta"]["mapping_version"].as_u64(),
+                        last_indexed_at: last_indexed.get(index.as_str()).map(|at| at.to_string()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(result)
+    }
+}
+
+// Chunks in a page of search hits, without their embeddings
+fn hits_to_chunks(hits: &[Value]) -> Vec<DocumentWithEmbedding> {
+    hits.iter()
+        .map(|hit| {
+            let source = &hit["_source"];
+            DocumentWithEmbedding {
+                id: hit["_id"].as_str().unwrap_or("").to_string(),
+                text: source["text"].as_str().unwrap_or("").to_string(),
+                embedding: Vec::new(),
+                chunk_index: source["chunk_index"].as_i64().unwrap_or(0),
+                file_path: source["file_path"].as_str().unwrap_or("").to_string(),
+                chunk_count: source["chunk_count"].as_i64().unwrap_or(0),
+                parent_index: source["parent_index"].as_i64(),
+            }
+        })
+        .collect()
+}
+
+// A kNN search hit with its score
+fn search_hit(hit: &Value) -> SearchResult {
+    let source = &hit["_source"];
+    SearchResult {
+        text: source["text"].as_str().unwrap_or("").to_string(),
+        score: hit["_score"].as_f64().unwrap_or(0.0) as f32,
+        chunk_index: source["chunk_index"].as_i64().unwrap_or(0),
+        file_path: source["file_path"].as_str().unwrap_or("").to_string(),
+        parent_index: source["parent_index"].as_i64(),
+    }
+}
+
+impl VectorStore for ElasticsearchService {
     // Create an index for a chatbot if it doesn't exist
-    pub async fn create_index_if_not_exists(&self, index_name: &str, embedding_dim: usize) -> Result<()> {
+    async fn create_collection(&self, index_name: &str, embedding_dim: usize) -> Result<()> {
         tracing::info!("Checking if index '{}' exists", index_name);
 
         // Check if index exists
-        let exists_response = self
-            .client
-            .indices()
-            .exists(IndicesExistsParts::Index(&[index_name]))
-            .send()
-            .await?;
-
-        if exists_response.status_code().is_success() {
+        if self.index_exists(index_name).await? {
             tracing::info!("Index '{}' already exists", index_name);
             return Ok(());
         }
@@ -36,6 +223,9 @@ impl ElasticsearchService {
         // Create index with mapping for dense vector
         let mapping = json!({
             "mappings": {
+                "_meta": {
+                    "mapping_version": MAPPING_VERSION
+                },
                 "properties": {
                     "text": {
                         "type": "text",
@@ -56,6 +246,9 @@ impl ElasticsearchService {
                     "chunk_count": {
                         "type": "long"
                     },
+                    "parent_index": {
+                        "type": "long"
+                    },
                     "created_at": {
                         "type": "date"
                     }
@@ -67,13 +260,14 @@ impl ElasticsearchService {
             }
         });
 
-        let create_response = self
-            .client
-            .indices()
-            .create(IndicesCreateParts::Index(index_name))
-            .body(mapping)
-            .send()
-            .await?;
+        let indices_api = self.client.indices();
+        let create_response = send_with_retry("create_index", || {
+            indices_api
+                .create(IndicesCreateParts::Index(index_name))
+                .body(mapping.clone())
+                .send()
+        })
+        .await?;
 
         if create_response.status_code().is_success() {
             tracing::info!("✅ Index '{}' created successfully", index_name);
@@ -87,7 +281,8 @@ impl ElasticsearchService {
     }
 
     // Index documents with embeddings
-    pub async fn index_documents(
+    #[tracing::instrument(name = "elasticsearch.index_documents", skip(self, documents), fields(count = documents.len()))]
+    async fn index_documents(
         &self,
         index_name: &str,
         documents: Vec<DocumentWithEmbedding>,
@@ -104,15 +299,17 @@ impl ElasticsearchService {
                 "chunk_index": doc.chunk_index,
                 "file_path": doc.file_path,
                 "chunk_count": doc.chunk_count,
+                "parent_index": doc.parent_index,
                 "created_at": chrono::Utc::now().to_rfc3339()
             });
 
-            let response = self
-                .client
-                .index(elasticsearch::IndexParts::IndexId(index_name, &doc.id))
-                .body(document_body)
-                .send()
-                .await?;
+            let response = send_with_retry("index", || {
+                self.client
+                    .index(elasticsearch::IndexParts::IndexId(index_name, &doc.id))
+                    .body(document_body.clone())
+                    .send()
+            })
+            .await?;
 
             if response.status_code().is_success() {
                 success_count += 1;
@@ -126,8 +323,247 @@ impl ElasticsearchService {
         Ok(success_count)
     }
 
+    // Count documents across one or more indices, skipping ones that don't exist yet
+    #[tracing::instrument(name = "elasticsearch.count", skip(self))]
+    async fn count_documents(&self, index_names: &[String]) -> Result<u64> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let response = send_with_retry("count", || {
+            self.client
+                .count(CountParts::Index(&indices))
+                .ignore_unavailable(true)
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Count failed: {}", error_text);
+            return Err(anyhow::anyhow!("Count failed"));
+        }
+
+        let response_body: Value = response.json().await?;
+        Ok(response_body["count"].as_u64().unwrap_or(0))
+    }
+
+    // Delete indices, ignoring ones that don't exist. Deleting through an alias is rejected, so the
+    // index it points at is deleted instead and the alias goes with it
+    async fn delete_collections(&self, index_names: &[String]) -> Result<()> {
+        let mut targets = Vec::with_capacity(index_names.len());
+        for index_name in index_names {
+            targets.push(self.resolve_alias(index_name).await?.unwrap_or_else(|| index_name.clone()));
+        }
+        let indices: Vec<&str> = targets.iter().map(|s| s.as_str()).collect();
+
+        let indices_api = self.client.indices();
+        let response = send_with_retry("delete_indices", || {
+            indices_api
+                .delete(IndicesDeleteParts::Index(&indices))
+                .ignore_unavailable(true)
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Index deletion failed: {}", error_text);
+            return Err(anyhow::anyhow!("Index deletion failed"));
+        }
+
+        tracing::info!("✅ Deleted indices: {:?}", index_names);
+        Ok(())
+    }
+
+    // Close indices so they stop using heap while keeping their data on disk
+    async fn close_collections(&self, index_names: &[String]) -> Result<()> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let indices_api = self.client.indices();
+        let response = send_with_retry("close_indices", || {
+            indices_api
+                .close(IndicesCloseParts::Index(&indices))
+                .ignore_unavailable(true)
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Closing indices failed: {}", error_text);
+            return Err(anyhow::anyhow!("Closing indices failed"));
+        }
+
+        tracing::info!("✅ Closed indices: {:?}", index_names);
+        Ok(())
+    }
+
+    // Reopen closed indices
+    async fn open_collections(&self, index_names: &[String]) -> Result<()> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let indices_api = self.client.indices();
+        let response = send_with_retry("open_indices", || {
+            indices_api
+                .open(IndicesOpenParts::Index(&indices))
+                .ignore_unavailable(true)
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Opening indices failed: {}", error_text);
+            return Err(anyhow::anyhow!("Opening indices failed"));
+        }
+
+        tracing::info!("✅ Opened indices: {:?}", index_names);
+        Ok(())
+    }
+
+    // List index names matching a wildcard pattern
+    async fn list_collections(&self, pattern: &str) -> Result<Vec<String>> {
+        let patterns = [pattern];
+        let cat_api = self.client.cat();
+        let response = send_with_retry("list_indices", || {
+            cat_api
+                .indices(CatIndicesParts::Index(&patterns))
+                .format("json")
+                .h(&["index"])
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Listing indices failed: {}", error_text);
+            return Err(anyhow::anyhow!("Listing indices failed"));
+        }
+
+        let rows: Vec<Value> = response.json().await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| row["index"].as_str().map(str::to_string))
+            .collect())
+    }
+
+    // Load every chunk of a document, with its embedding, and the index it lives in
+    async fn fetch_document_chunks(
+        &self,
+        index_names: &[String],
+        file_path: &str,
+    ) -> Result<Vec<(String, DocumentWithEmbedding)>> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let response = send_with_retry("fetch_document_chunks", || {
+            self.client
+                .search(SearchParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(json!({
+                    "query": { "term": { "file_path": file_path } },
+                    "sort": [{ "chunk_index": "asc" }],
+                    "size": 10_000,
+                    "_source": ["text", "embedding", "chunk_index", "file_path", "chunk_count", "parent_index"]
+                }))
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Fetching document chunks failed: {}", error_text);
+            return Err(anyhow::anyhow!("Fetching document chunks failed"));
+        }
+
+        let response_body: Value = response.json().await?;
+        let empty_vec = vec![];
+        let hits = response_body["hits"]["hits"].as_array().unwrap_or(&empty_vec);
+
+        let mut chunks = Vec::with_capacity(hits.len());
+        for hit in hits {
+            let source = &hit["_source"];
+            let embedding = source["embedding"]
+                .as_array()
+                .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                .unwrap_or_default();
+
+            chunks.push((
+                hit["_index"].as_str().unwrap_or("").to_string(),
+                DocumentWithEmbedding {
+                    id: hit["_id"].as_str().unwrap_or("").to_string(),
+                    text: source["text"].as_str().unwrap_or("").to_string(),
+                    embedding,
+                    chunk_index: source["chunk_index"].as_i64().unwrap_or(0),
+                    file_path: source["file_path"].as_str().unwrap_or("").to_string(),
+                    chunk_count: source["chunk_count"].as_i64().unwrap_or(0),
+                    parent_index: source["parent_index"].as_i64(),
+                },
+            ));
+        }
+
+        Ok(chunks)
+    }
+
+    // The chunks of some of a document's sections, in one search across the chatbot's indices
+    async fn fetch_section_chunks(
+        &self,
+        index_names: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> Result<Vec<DocumentWithEmbedding>> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let response = send_with_retry("fetch_section_chunks", || {
+            self.client
+                .search(SearchParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(json!({
+                    "query": {
+                        "bool": {
+                            "filter": [
+                                { "term": { "file_path": file_path } },
+                                { "terms": { "parent_index": parent_indices } }
+                            ]
+                        }
+                    },
+                    "sort": [{ "chunk_index": "asc" }],
+                    "size": 10_000,
+                    "_source": ["text", "chunk_index", "file_path", "chunk_count", "parent_index"]
+                }))
+                .send()
+        })
+        .await?;
+        let response_body = Self::json_or_error(response, "Fetching section chunks").await?;
+
+        let empty_vec = vec![];
+        Ok(hits_to_chunks(response_body["hits"]["hits"].as_array().unwrap_or(&empty_vec)))
+    }
+
+    // Delete every chunk of a document
+    async fn delete_document_chunks(&self, index_names: &[String], file_path: &str) -> Result<u64> {
+        let indices: Vec<&str> = index_names.iter().map(|s| s.as_str()).collect();
+
+        let response = send_with_retry("delete_document_chunks", || {
+            self.client
+                .delete_by_query(DeleteByQueryParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(json!({ "query": { "term": { "file_path": file_path } } }))
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Deleting document chunks failed: {}", error_text);
+            return Err(anyhow::anyhow!("Deleting document chunks failed"));
+        }
+
+        let response_body: Value = response.json().await?;
+        Ok(response_body["deleted"].as_u64().unwrap_or(0))
+    }
+
     // Search for similar documents using vector similarity
-    pub async fn search_similar(
+    #[tracing::instrument(name = "elasticsearch.search", skip(self, query_embedding))]
+    async fn search_similar(
         &self,
         index_name: &str,
         query_embedding: Vec<f32>,
@@ -142,15 +578,18 @@ impl ElasticsearchService {
                 "k": limit,
                 "num_candidates": limit * 2
             },
-            "_source": ["text", "chunk_index", "file_path", "chunk_count"]
+            "_source": ["text", "chunk_index", "file_path", "chunk_count", "parent_index"]
         });
 
-        let response = self
-            .client
-            .search(SearchParts::Index(&[index_name]))
-            .body(search_query)
-            .send()
-            .await?;
+        let indices = [index_name];
+        let response = send_with_retry("search", || {
+            self.client
+                .search(SearchParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(search_query.clone())
+                .send()
+        })
+        .await?;
 
         if !response.status_code().is_success() {
             let error_text = response.text().await?;
@@ -161,39 +600,193 @@ impl ElasticsearchService {
         let response_body: Value = response.json().await?;
         let empty_vec = vec![];
         let hits = response_body["hits"]["hits"].as_array().unwrap_or(&empty_vec);
+        let results: Vec<SearchResult> = hits.iter().map(search_hit).collect();
 
-        let mut results = Vec::new();
-        for hit in hits {
-            let source = &hit["_source"];
-            let score = hit["_score"].as_f64().unwrap_or(0.0) as f32;
+        tracing::info!("Found {} similar documents", results.len());
+        Ok(results)
+    }
 
-            results.push(SearchResult {
-                text: source["text"].as_str().unwrap_or("").to_string(),
-                score,
-                chunk_index: source["chunk_index"].as_i64().unwrap_or(0),
-                file_path: source["file_path"].as_str().unwrap_or("").to_string(),
-            });
+    async fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        if collections.is_empty() {
+            return Ok(Vec::new());
         }
+        tracing::info!("Searching for similar documents across {} indices", collections.len());
+
+        // One kNN search over every index; each hit names the concrete index it came from
+        let search_query = json!({
+            "knn": {
+                "field": "embedding",
+                "query_vector": query_embedding,
+                "k": limit,
+                "num_candidates": limit * 2
+            },
+            "size": limit,
+            "_source": ["text", "chunk_index", "file_path", "chunk_count", "parent_index"]
+        });
+
+        let indices: Vec<&str> = collections.iter().map(String::as_str).collect();
+        let response = send_with_retry("search", || {
+            self.client
+                .search(SearchParts::Index(&indices))
+                .ignore_unavailable(true)
+                .body(search_query.clone())
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Search failed: {}", error_text);
+            return Err(anyhow::anyhow!("Search failed"));
+        }
+
+        let response_body: Value = response.json().await?;
+        let empty_vec = vec![];
+        let hits = response_body["hits"]["hits"].as_array().unwrap_or(&empty_vec);
+        let results: Vec<(String, SearchResult)> = hits
+            .iter()
+            .map(|hit| (hit["_index"].as_str().unwrap_or("").to_string(), search_hit(hit)))
+            .collect();
 
         tracing::info!("Found {} similar documents", results.len());
         Ok(results)
     }
-}
 
-#[derive(Debug)]
-pub struct DocumentWithEmbedding {
-    pub id: String,
-    pub text: String,
-    pub embedding: Vec<f32>,
-    pub chunk_index: i64,
-    pub file_path: String,
-    pub chunk_count: i64,
-}
+    // Page through an index with a scroll context, which keeps a consistent view across pages
+    async fn scroll_chunks(&self, index_name: &str, cursor: Option<&str>, limit: usize) -> Result<ChunkPage> {
+        let response = match cursor {
+            Some(scroll_id) => {
+                send_with_retry("scroll", || {
+                    self.client
+                        .scroll(ScrollParts::None)
+                        .body(json!({ "scroll": SCROLL_KEEP_ALIVE, "scroll_id": scroll_id }))
+                        .send()
+                })
+                .await?
+            }
+            None => {
+                let indices = [index_name];
+                send_with_retry("scroll_chunks", || {
+                    self.client
+                        .search(SearchParts::Index(&indices))
+                        .ignore_unavailable(true)
+                        .scroll(SCROLL_KEEP_ALIVE)
+                        .body(json!({
+                            "sort": ["_doc"],
+                            "size": limit,
+                            "_source": ["text", "chunk_index", "file_path", "chunk_count", "parent_index"]
+                        }))
+                        .send()
+                })
+                .await?
+            }
+        };
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Scrolling index '{}' failed: {}", index_name, error_text);
+            return Err(anyhow::anyhow!("Scrolling index failed"));
+        }
+
+        let response_body: Value = response.json().await?;
+        let empty_vec = vec![];
+        let hits = response_body["hits"]["hits"].as_array().unwrap_or(&empty_vec);
+        let scroll_id = response_body["_scroll_id"].as_str().map(str::to_string);
+
+        if hits.len() < limit {
+            // Last page: free the scroll context now instead of waiting for it to expire
+            if let Some(scroll_id) = &scroll_id {
+                let cleared = self
+                    .client
+                    .clear_scroll(ClearScrollParts::None)
+                    .body(json!({ "scroll_id": scroll_id }))
+                    .send()
+                    .await;
+                if let Err(e) = cleared {
+                    tracing::warn!("⚠️ Failed to clear scroll context: {}", e);
+                }
+            }
+            return Ok(ChunkPage { chunks: hits_to_chunks(hits), cursor: None });
+        }
+
+        Ok(ChunkPage { chunks: hits_to_chunks(hits), cursor: scroll_id })
+    }
+
+    // The index behind an alias
+    async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        let names = [name];
+        let indices_api = self.client.indices();
+        let response = send_with_retry("get_alias", || {
+            indices_api
+                .get_alias(IndicesGetAliasParts::Name(&names))
+                .send()
+        })
+        .await?;
 
-#[derive(Debug, serde::Serialize)]
-pub struct SearchResult {
-    pub text: String,
-    pub score: f32,
-    pub chunk_index: i64,
-    pub file_path: String,
+        if response.status_code().as_u16() == 404 {
+            return Ok(None);
+        }
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Resolving alias '{}' failed: {}", name, error_text);
+            return Err(anyhow::anyhow!("Resolving alias failed"));
+        }
+
+        // Keyed by the indices the alias points at
+        let response_body: Value = response.json().await?;
+        Ok(response_body.as_object().and_then(|indices| indices.keys().next().cloned()))
+    }
+
+    // Move every alias in a single _aliases request, which Elasticsearch applies atomically
+    async fn swap_aliases(&self, swaps: &[(String, String)]) -> Result<Vec<String>> {
+        let mut actions = Vec::new();
+        let mut previous = Vec::new();
+
+        for (alias, index_name) in swaps {
+            match self.resolve_alias(alias).await? {
+                Some(current) => {
+                    actions.push(json!({ "remove": { "index": current, "alias": alias } }));
+                    previous.push(current);
+                }
+                // An index created before aliasing holds the name; drop it in the same request
+                None if self.index_exists(alias).await? => {
+                    actions.push(json!({ "remove_index": { "index": alias } }));
+                }
+                None => {}
+            }
+            actions.push(json!({ "add": { "index": index_name, "alias": alias } }));
+        }
+
+        let body = json!({ "actions": actions });
+        let indices_api = self.client.indices();
+        let response = send_with_retry("update_aliases", || {
+            indices_api
+                .update_aliases()
+                .body(body.clone())
+                .send()
+        })
+        .await?;
+
+        if !response.status_code().is_success() {
+            let error_text = response.text().await?;
+            tracing::error!("Swapping aliases failed: {}", error_text);
+            return Err(anyhow::anyhow!("Swapping aliases failed"));
+        }
+
+        tracing::info!("✅ Swapped aliases: {:?}", swaps);
+        Ok(previous)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let response = self.client.ping().send().await?;
+        if !response.status_code().is_success() {
+            return Err(anyhow::anyhow!("Elasticsearch ping returned {}", response.status_code()));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,435 @@
+use anyhow::Result;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::db::queries::mark_secondary_resync;
+use crate::services::elasticsearch::{ElasticsearchService, IndexStats};
+use crate::services::retry::UpstreamStatus;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::{ChunkPage, DocumentWithEmbedding, SearchResult, VectorStore};
+
+// How long reads skip a primary that just failed before trying it again
+const PRIMARY_COOLDOWN_MS: i64 = 30_000;
+// Writes waiting to be replayed on the secondary; later ones are dropped, and their collections
+// marked for a resync, while it is full
+const REPLAY_QUEUE_SIZE: usize = 1024;
+
+/// How the secondary cluster is kept in step with the primary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecondarySync {
+    /// Every write to the primary is replayed on the secondary in the background, in order
+    #[default]
+    DualWrite,
+    /// The secondary is restored from the primary's snapshots outside this server and only read
+    Snapshot,
+}
+
+impl FromStr for SecondarySync {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "dual_write" => Ok(SecondarySync::DualWrite),
+            "snapshot" => Ok(SecondarySync::Snapshot),
+            other => Err(format!("unknown secondary sync '{}'; use dual_write or snapshot", other)),
+        }
+    }
+}
+
+impl fmt::Display for SecondarySync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecondarySync::DualWrite => f.write_str("dual_write"),
+            SecondarySync::Snapshot => f.write_str("snapshot"),
+        }
+    }
+}
+
+// A write made on the primary, to repeat on the secondary
+enum SecondaryWrite {
+    CreateCollection { name: String, embedding_dim: usize },
+    IndexDocuments { collection: String, documents: Vec<DocumentWithEmbedding> },
+    DeleteCollections(Vec<String>),
+    CloseCollections(Vec<String>),
+    OpenCollections(Vec<String>),
+    DeleteDocumentChunks { collections: Vec<String>, file_path: String },
+    SwapAliases(Vec<(String, String)>),
+}
+
+impl SecondaryWrite {
+    fn operation(&self) -> &'static str {
+        match self {
+            SecondaryWrite::CreateCollection { .. } => "create_collection",
+            SecondaryWrite::IndexDocuments { .. } => "index_documents",
+            SecondaryWrite::DeleteCollections(_) => "delete_collections",
+            SecondaryWrite::CloseCollections(_) => "close_collections",
+            SecondaryWrite::OpenCollections(_) => "open_collections",
+            SecondaryWrite::DeleteDocumentChunks { .. } => "delete_document_chunks",
+            SecondaryWrite::SwapAliases(_) => "swap_aliases",
+        }
+    }
+
+    // Collections the write changes; for alias swaps, the aliases
+    fn collections(&self) -> Vec<String> {
+        match self {
+            SecondaryWrite::CreateCollection { name, .. } => vec![name.clone()],
+            SecondaryWrite::IndexDocuments { collection, .. } => vec![collection.clone()],
+            SecondaryWrite::DeleteCollections(collections)
+            | SecondaryWrite::CloseCollections(collections)
+            | SecondaryWrite::OpenCollections(collections)
+            | SecondaryWrite::DeleteDocumentChunks { collections, .. } => collections.clone(),
+            SecondaryWrite::SwapAliases(swaps) => swaps.iter().map(|(alias, _)| alias.clone()).collect(),
+        }
+    }
+
+    async fn apply(self, store: &ElasticsearchService) -> Result<()> {
+        match self {
+            SecondaryWrite::CreateCollection { name, embedding_dim } => {
+                store.create_collection(&name, embedding_dim).await
+            }
+            SecondaryWrite::IndexDocuments { collection, documents } => {
+                store.index_documents(&collection, documents).await.map(|_| ())
+            }
+            SecondaryWrite::DeleteCollections(collections) => store.delete_collections(&collections).await,
+            SecondaryWrite::CloseCollections(collections) => store.close_collections(&collections).await,
+            SecondaryWrite::OpenCollections(collections) => store.open_collections(&collections).await,
+            SecondaryWrite::DeleteDocumentChunks { collections, file_path } => {
+                store.delete_document_chunks(&collections, &file_path).await.map(|_| ())
+            }
+            SecondaryWrite::SwapAliases(swaps) => store.swap_aliases(&swaps).await.map(|_| ()),
+        }
+    }
+}
+
+struct Secondary {
+    store: Arc<ElasticsearchService>,
+    /// Queue of writes to replay; None when the secondary is synced from snapshots
+    writes: Option<ReplayQueue>,
+}
+
+// Writes waiting for the replay task, and what's needed to record the ones it never gets
+struct ReplayQueue {
+    sender: mpsc::Sender<SecondaryWrite>,
+    jobs: BackgroundJobs,
+    db: Arc<PgPool>,
+}
+
+/// Elasticsearch as the vector store, with an optional secondary cluster in another region.
+/// Reads go to the primary and fall back to the secondary while the primary is unreachable;
+/// writes go to the primary only, and are replayed on the secondary in `dual_write` mode
+pub struct ElasticsearchFailover {
+    primary: ElasticsearchService,
+    secondary: Option<Secondary>,
+    /// Unix time in milliseconds until which reads skip the primary
+    primary_down_until: AtomicI64,
+}
+
+impl ElasticsearchFailover {
+    pub fn new(primary: ElasticsearchService) -> Self {
+        Self { primary, secondary: None, primary_down_until: AtomicI64::new(0) }
+    }
+
+    /// Add a secondary cluster. In `dual_write` mode this starts the task replaying writes on it;
+    /// collections whose writes it misses are recorded in `secondary_resyncs`
+    pub fn with_secondary(
+        mut self,
+        secondary: ElasticsearchService,
+        sync: SecondarySync,
+        jobs: &BackgroundJobs,
+        db: Arc<PgPool>,
+    ) -> Self {
+        let store = Arc::new(secondary);
+        let writes = match sync {
+            SecondarySync::DualWrite => {
+                let (sender, receiver) = mpsc::channel(REPLAY_QUEUE_SIZE);
+                spawn_replay_task(jobs, db.clone(), store.clone(), receiver);
+                Some(ReplayQueue { sender, jobs: jobs.clone(), db })
+            }
+            SecondarySync::Snapshot => None,
+        };
+        self.secondary = Some(Secondary { store, writes });
+        self
+    }
+
+    /// See `ElasticsearchService::index_stats`; read from the secondary while the primary is down
+    pub async fn index_stats(&self, index_names: &[String]) -> Result<Vec<IndexStats>> {
+        self.read("index_stats", |store| store.index_stats(index_names)).await
+    }
+
+    // Run a read on the primary, or on the secondary when the primary is unreachable
+    async fn read<'a, T, F, Fut>(&'a self, operation: &'static str, read: F) -> Result<T>
+    where
+        F: Fn(&'a ElasticsearchService) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let Some(secondary) = &self.secondary else {
+            return read(&self.primary).await;
+        };
+
+        let now = chrono::Utc::now().timestamp_millis();
+        if primary_available(self.primary_down_until.load(Ordering::Relaxed), now) {
+            match read(&self.primary).await {
+                Ok(value) => return Ok(value),
+                Err(e) if is_outage(&e) => {
+                    tracing::warn!(
+                        "⚠️ Primary Elasticsearch failed {} ({}), reading from the secondary",
+                        operation,
+                        e
+                    );
+                    self.primary_down_until.store(now + PRIMARY_COOLDOWN_MS, Ordering::Relaxed);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        read(secondary.store.as_ref()).await
+    }
+
+    fn replay_queue(&self) -> Option<&ReplayQueue> {
+        self.secondary.as_ref().and_then(|secondary| secondary.writes.as_ref())
+    }
+
+    // Queue a write the primary accepted for replay on the secondary. A write that can't be queued
+    // is dropped and its collections marked for a resync
+    fn replay(&self, write: impl FnOnce() -> SecondaryWrite) {
+        let Some(queue) = self.replay_queue() else {
+            return;
+        };
+        let write = match queue.sender.try_send(write()) {
+            Ok(()) => return,
+            Err(TrySendError::Full(write)) => {
+                tracing::error!(
+                    "❌ Secondary Elasticsearch is {} writes behind, dropped {}",
+                    REPLAY_QUEUE_SIZE,
+                    write.operation()
+                );
+                write
+            }
+            Err(TrySendError::Closed(write)) => {
+                tracing::error!("❌ Secondary Elasticsearch replay stopped, dropped {}", write.operation());
+                write
+            }
+        };
+
+        let db = queue.db.clone();
+        queue.jobs.spawn(async move {
+            record_missed_write(&db, &write.collections(), write.operation()).await;
+        });
+    }
+}
+
+// Whether reads may go to the primary at `now`, given when its last failure's cooldown ends
+fn primary_available(down_until: i64, now: i64) -> bool {
+    now >= down_until
+}
+
+// Transport failures and overloaded responses mean the cluster is unreachable; other errors,
+// such as a rejected query, would fail on the secondary too
+fn is_outage(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<elasticsearch::Error>().is_some() || error.downcast_ref::<UpstreamStatus>().is_some()
+}
+
+// Apply queued writes to the secondary one at a time, so they land in the primary's order. On
+// shutdown the queue stops taking writes and the ones already in it are still applied
+fn spawn_replay_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    store: Arc<ElasticsearchService>,
+    mut writes: mpsc::Receiver<SecondaryWrite>,
+) {
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        loop {
+            let write = tokio::select! {
+                write = writes.recv() => write,
+                _ = shutdown.cancelled() => break,
+            };
+            let Some(write) = write else {
+                return;
+            };
+            replay_write(&db, &store, write).await;
+        }
+
+        writes.close();
+        while let Some(write) = writes.recv().await {
+            replay_write(&db, &store, write).await;
+        }
+    });
+}
+
+async fn replay_write(db: &PgPool, store: &ElasticsearchService, write: SecondaryWrite) {
+    let (operation, collections) = (write.operation(), write.collections());
+    if let Err(e) = write.apply(store).await {
+        tracing::error!("❌ Failed to replay {} on the secondary Elasticsearch: {}", operation, e);
+        record_missed_write(db, &collections, operation).await;
+    }
+}
+
+// Mark the collections of a write the secondary missed, so an operator resyncs them from a snapshot
+async fn record_missed_write(db: &PgPool, collections: &[String], operation: &str) {
+    match mark_secondary_resync(db, collections, operation).await {
+        Ok(()) => tracing::warn!("⚠️ Marked {:?} for a full resync of the secondary Elasticsearch", collections),
+        Err(e) => tracing::error!("❌ Failed to mark {:?} for a secondary resync: {}", collections, e),
+    }
+}
+
+impl VectorStore for ElasticsearchFailover {
+    async fn create_collection(&self, name: &str, embedding_dim: usize) -> Result<()> {
+        self.primary.create_collection(name, embedding_dim).await?;
+        self.replay(|| SecondaryWrite::CreateCollection { name: name.to_string(), embedding_dim });
+        Ok(())
+    }
+
+    async fn index_documents(&self, collection: &str, documents: Vec<DocumentWithEmbedding>) -> Result<usize> {
+        // Embeddings are copied only when the secondary will get them
+        let replayed = self.replay_queue().map(|_| documents.clone());
+        let indexed = self.primary.index_documents(collection, documents).await?;
+        if let Some(documents) = replayed {
+            self.replay(|| SecondaryWrite::IndexDocuments { collection: collection.to_string(), documents });
+        }
+        Ok(indexed)
+    }
+
+    async fn search_similar(
+        &self,
+        collection: &str,
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<SearchResult>> {
+        self.read("search", |store| store.search_similar(collection, query_embedding.clone(), limit)).await
+    }
+
+    async fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        self.read("search", |store| store.search_collections(collections, query_embedding.clone(), limit)).await
+    }
+
+    async fn count_documents(&self, collections: &[String]) -> Result<u64> {
+        self.read("count", |store| store.count_documents(collections)).await
+    }
+
+    async fn delete_collections(&self, collections: &[String]) -> Result<()> {
+        self.primary.delete_collections(collections).await?;
+        self.replay(|| SecondaryWrite::DeleteCollections(collections.to_vec()));
+        Ok(())
+    }
+
+    async fn close_collections(&self, collections: &[String]) -> Result<()> {
+        self.primary.close_collections(collections).await?;
+        self.replay(|| SecondaryWrite::CloseCollections(collections.to_vec()));
+        Ok(())
+    }
+
+    async fn open_collections(&self, collections: &[String]) -> Result<()> {
+        self.primary.open_collections(collections).await?;
+        self.replay(|| SecondaryWrite::OpenCollections(collections.to_vec()));
+        Ok(())
+    }
+
+    async fn list_collections(&self, pattern: &str) -> Result<Vec<String>> {
+        self.read("list_collections", |store| store.list_collections(pattern)).await
+    }
+
+    async fn fetch_document_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+    ) -> Result<Vec<(String, DocumentWithEmbedding)>> {
+        self.read("fetch_document_chunks", |store| store.fetch_document_chunks(collections, file_path)).await
+    }
+
+    async fn fetch_section_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> Result<Vec<DocumentWithEmbedding>> {
+        self.read("fetch_section_chunks", |store| store.fetch_section_chunks(collections, file_path, parent_indices))
+            .await
+    }
+
+    async fn delete_document_chunks(&self, collections: &[String], file_path: &str) -> Result<u64> {
+        let deleted = self.primary.delete_document_chunks(collections, file_path).await?;
+        self.replay(|| SecondaryWrite::DeleteDocumentChunks {
+            collections: collections.to_vec(),
+            file_path: file_path.to_string(),
+        });
+        Ok(deleted)
+    }
+
+    async fn scroll_chunks(&self, collection: &str, cursor: Option<&str>, limit: usize) -> Result<ChunkPage> {
+        // A scroll cursor only works on the cluster that issued it, so pages never fail over
+        self.primary.scroll_chunks(collection, cursor, limit).await
+    }
+
+    async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        self.read("resolve_alias", |store| store.resolve_alias(name)).await
+    }
+
+    async fn swap_aliases(&self, swaps: &[(String, String)]) -> Result<Vec<String>> {
+        let previous = self.primary.swap_aliases(swaps).await?;
+        self.replay(|| SecondaryWrite::SwapAliases(swaps.to_vec()));
+        Ok(previous)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let Err(e) = self.primary.ping().await else {
+            return Ok(());
+        };
+        let Some(secondary) = &self.secondary else {
+            return Err(e);
+        };
+        // Chat keeps working on the secondary, so the server stays ready
+        secondary.store.ping().await.map_err(|_| e)?;
+        tracing::warn!("⚠️ Primary Elasticsearch is unreachable, serving reads from the secondary");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_secondary_sync() {
+        assert_eq!("dual_write".parse(), Ok(SecondarySync::DualWrite));
+        assert_eq!("Dual-Write".parse(), Ok(SecondarySync::DualWrite));
+        assert_eq!("snapshot".parse(), Ok(SecondarySync::Snapshot));
+        assert!("ccr".parse::<SecondarySync>().is_err());
+        assert_eq!(SecondarySync::default(), SecondarySync::DualWrite);
+    }
+
+    #[test]
+    fn test_primary_cooldown() {
+        assert!(primary_available(0, 1_000));
+        assert!(!primary_available(1_000 + PRIMARY_COOLDOWN_MS, 1_000));
+        assert!(primary_available(1_000, 1_000));
+    }
+
+    #[test]
+    fn test_write_collections() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let write = SecondaryWrite::CreateCollection { name: "bot_a".to_string(), embedding_dim: 768 };
+        assert_eq!(write.collections(), names(&["bot_a"]));
+        let write = SecondaryWrite::DeleteDocumentChunks { collections: names(&["bot_a", "bot_b"]), file_path: "a.pdf".to_string() };
+        assert_eq!(write.collections(), names(&["bot_a", "bot_b"]));
+        let write = SecondaryWrite::SwapAliases(vec![("bot_a".to_string(), "bot_a_v2".to_string())]);
+        assert_eq!(write.collections(), names(&["bot_a"]));
+    }
+
+    #[test]
+    fn test_is_outage() {
+        assert!(is_outage(&anyhow::Error::new(UpstreamStatus { status: 503 })));
+        assert!(!is_outage(&anyhow::anyhow!("Search failed")));
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::Result;
+use mail_parser::MessageParser;
+use sha2::{Digest, Sha256};
+
+use crate::utils::pdf::chunk_text;
+
+const DEFAULT_IMAP_PORT: u16 = 993;
+const DEFAULT_IMAP_FOLDER: &str = "INBOX";
+const DEFAULT_MAX_MESSAGES: u32 = 500;
+
+/// One email, reduced to what is worth searching
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub message_id: String,
+    pub from: String,
+    /// RFC 3339, when the message has a parseable `Date` header
+    pub date: Option<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+impl EmailMessage {
+    /// Document path for the message's chunks, so re-importing a mailbox replaces them
+    pub fn file_path(&self, source: &str) -> String {
+        format!("{}/{}", mailbox_path(source), self.message_id)
+    }
+
+    /// Chunk the body, starting every chunk with the sender, date and subject so they are
+    /// searchable and show up in citations
+    pub fn chunks(&self, chunk_size: usize, overlap: usize) -> Vec<String> {
+        let mut header = format!("From: {}\n", self.from);
+        if let Some(date) = &self.date {
+            header.push_str(&format!("Date: {}\n", date));
+        }
+        header.push_str(&format!("Subject: {}\n\n", self.subject));
+
+        chunk_text(&self.body, chunk_size, overlap)
+            .into_iter()
+            .map(|chunk| format!("{}{}", header, chunk))
+            .collect()
+    }
+}
+
+/// Path of a whole mailbox, under which its messages' paths are nested
+pub fn mailbox_path(source: &str) -> String {
+    format!("email:{}", source)
+}
+
+/// Split an mbox file into raw messages. Messages start at `From ` lines, and `>From ` lines
+/// inside a body are unescaped
+pub fn split_mbox(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+
+    for line in data.split_inclusive(|b| *b == b'\n') {
+        if line.starts_with(b"From ") {
+            messages.extend(current.take());
+            current = Some(Vec::new());
+            continue;
+        }
+        // Text before the first separator is not a message
+        let Some(message) = current.as_mut() else {
+            continue;
+        };
+        let line = if is_escaped_from(line) { &line[1..] } else { line };
+        message.extend_from_slice(line);
+    }
+    messages.extend(current);
+    messages.retain(|message| !message.iter().all(u8::is_ascii_whitespace));
+    messages
+}
+
+// `>From `, `>>From `, ... lines were escaped by the writer by adding one `>`
+fn is_escaped_from(line: &[u8]) -> bool {
+    let quotes = line.iter().take_while(|b| **b == b'>').count();
+    quotes > 0 && line[quotes..].starts_with(b"From ")
+}
+
+// Drop quoted replies and the signature, which repeat or don't belong in the knowledge base
+fn strip_quotes(body: &str) -> String {
+    let mut lines = Vec::new();
+    for line in body.lines() {
+        if line == "-- " {
+            break;
+        }
+        if line.trim_start().starts_with('>') {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Parse a raw RFC 5322 message. None when it has no text body
+pub fn parse_message(raw: &[u8]) -> Option<EmailMessage> {
+    let message = MessageParser::default().parse(raw)?;
+    let body = strip_quotes(&message.body_text(0)?);
+    if body.is_empty() {
+        return None;
+    }
+
+    let from = message
+        .from()
+        .and_then(|address| address.first())
+        .map(|addr| match (addr.name(), addr.address()) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (None, Some(address)) => address.to_string(),
+            (Some(name), None) => name.to_string(),
+            (None, None) => "unknown".to_string(),
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Without a Message-ID, identify the message by its content so re-imports still match
+    let message_id = match message.message_id() {
+        Some(id) => id.to_string(),
+        None => {
+            let digest = Sha256::digest(raw);
+            digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+        }
+    };
+
+    Some(EmailMessage {
+        message_id,
+        from,
+        date: message.date().map(|date| date.to_rfc3339()),
+        subject: message.subject().unwrap_or("(no subject)").to_string(),
+        body,
+    })
+}
+
+/// An IMAP folder to import from, over TLS
+#[derive(Debug, Clone)]
+pub struct ImapSource {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub folder: String,
+    /// Only the newest this many messages are fetched
+    pub max_messages: u32,
+}
+
+impl ImapSource {
+    pub fn new(host: String, username: String, password: String) -> Self {
+        Self {
+            host,
+            port: DEFAULT_IMAP_PORT,
+            username,
+            password,
+            folder: DEFAULT_IMAP_FOLDER.to_string(),
+            max_messages: DEFAULT_MAX_MESSAGES,
+        }
+    }
+
+    /// Names the import's documents, e.g. `support@example.com@imap.example.com/INBOX`
+    pub fn name(&self) -> String {
+        format!("{}@{}/{}", self.username, self.host, self.folder)
+    }
+
+    /// Fetch the newest messages without marking them as read
+    pub async fn fetch(&self) -> Result<Vec<Vec<u8>>> {
+        #[cfg(feature = "imap")]
+        {
+            use futures_util::TryStreamExt;
+
+            let tcp = tokio::net::TcpStream::connect((self.host.as_str(), self.port)).await?;
+            let tls = async_native_tls::TlsConnector::new().connect(&self.host, tcp).await?;
+            let mut session = async_imap::Client::new(tls)
+                .login(&self.username, &self.password)
+                .await
+                .map_err(|(e, _)| e)?;
+
+            let mailbox = session.select(&self.folder).await?;
+            if mailbox.exists == 0 {
+                session.logout().await?;
+                return Ok(Vec::new());
+            }
+            let first = mailbox.exists.saturating_sub(self.max_messages.max(1)) + 1;
+            let fetched: Vec<_> = session
+                .fetch(format!("{}:{}", first, mailbox.exists), "BODY.PEEK[]")
+                .await?
+                .try_collect()
+                .await?;
+            let messages = fetched
+                .iter()
+                .filter_map(|fetch| fetch.body().map(<[u8]>::to_vec))
+                .collect();
+
+            session.logout().await?;
+            Ok(messages)
+        }
+
+        #[cfg(not(feature = "imap"))]
+        {
+            anyhow::bail!("this build lacks the imap feature")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MBOX: &[u8] = b"From alice@example.com Mon Mar  2 10:00:00 2026\n\
+From: Alice <alice@example.com>\n\
+Subject: Refund policy\n\
+Message-ID: <1@example.com>\n\
+Date: Mon, 2 Mar 2026 10:00:00 +0000\n\
+\n\
+Refunds are issued within 14 days.\n\
+>From now on, ask for the order number.\n\
+\n\
+From bob@example.com Mon Mar  2 11:00:00 2026\n\
+From: bob@example.com\n\
+Subject: Re: Refund policy\n\
+\n\
+Thanks, noted.\n\
+> Refunds are issued within 14 days.\n\
+-- \n\
+Bob\n";
+
+    #[test]
+    fn test_split_mbox() {
+        let messages = split_mbox(MBOX);
+        assert_eq!(messages.len(), 2);
+        let first = String::from_utf8_lossy(&messages[0]);
+        assert!(first.starts_with("From: Alice"));
+        assert!(first.contains("\nFrom now on"));
+        assert!(!first.contains(">From"));
+    }
+
+    #[test]
+    fn test_split_mbox_ignores_preamble_and_blank_input() {
+        assert!(split_mbox(b"not an mbox\n").is_empty());
+        assert!(split_mbox(b"").is_empty());
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let messages = split_mbox(MBOX);
+        let first = parse_message(&messages[0]).unwrap();
+        assert_eq!(first.message_id, "1@example.com");
+        assert_eq!(first.from, "Alice <alice@example.com>");
+        assert_eq!(first.subject, "Refund policy");
+        assert!(first.date.as_deref().unwrap().starts_with("2026-03-02T10:00:00"));
+        assert_eq!(first.file_path("archive.mbox"), "email:archive.mbox/1@example.com");
+
+        // Quoted text and the signature are dropped; no Message-ID falls back to a content hash
+        let reply = parse_message(&messages[1]).unwrap();
+        assert_eq!(reply.body, "Thanks, noted.");
+        assert_eq!(reply.message_id.len(), 16);
+        assert_eq!(reply.from, "bob@example.com");
+    }
+
+    #[test]
+    fn test_chunks_carry_headers() {
+        let message = EmailMessage {
+            message_id: "1@example.com".to_string(),
+            from: "alice@example.com".to_string(),
+            date: None,
+            subject: "Hours".to_string(),
+            body: "one two three four five".to_string(),
+        };
+        let chunks = message.chunks(3, 1);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], "From: alice@example.com\nSubject: Hours\n\none two three");
+        assert!(chunks[1].ends_with("three four five"));
+    }
+}
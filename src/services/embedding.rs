@@ -1,56 +1,229 @@
 use anyhow::Result;
-use elasticsearch::Elasticsearch;
+use futures_util::future::join_all;
+use sqlx::PgPool;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tracing;
 use uuid::Uuid;
 
+use crate::db::models::ChatBot;
+use crate::db::queries::{assign_document_shard, get_document_shard, update_chat_bot_shard_count};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
 use crate::services::candle_embedding::{CandleEmbeddingService, EmbeddingConfig};
-use crate::services::elasticsearch::{DocumentWithEmbedding, ElasticsearchService};
-use crate::utils::pdf::process_pdf_file;
+use crate::services::sharding::{
+    max_chunks_per_shard, required_shard_count, shard_for_new_document, shard_index_name, shard_indices,
+};
+use crate::services::vector::{
+    chatbot_index_name, chunk_id, DocumentWithEmbedding, SearchResult, VectorBackend, VectorStore,
+};
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::ingest_webhook::IngestWebhook;
+use crate::services::plugins::PluginHost;
+use crate::services::sections::{matched_sections, replace_with_sections, section_texts};
+use crate::utils::chunking::{
+    merge_sentences, semantic_threshold, small_to_big_chunks, split_sentences, ChunkingStrategy,
+};
+use crate::utils::pdf::{extract_text_from_pdf, process_pdf_file};
+
+// Small-to-big chunking: 100-word chunks are matched, the 500-word section around them is the context
+const SECTION_WORDS: usize = 500;
+const SECTION_CHUNK_WORDS: usize = 100;
+
+/// A chatbot's MMR lambda must be between 0 (only diversity) and 1 (only relevance)
+pub fn validate_mmr_lambda(lambda: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&lambda) {
+        Ok(())
+    } else {
+        Err(format!("mmr_lambda must be between 0 and 1, got {}", lambda))
+    }
+}
+
+/// Maximal marginal relevance: pick up to `limit` candidates one at a time, each maximising
+/// `lambda * sim(query, c) - (1 - lambda) * max sim(c, picked)`. Returns candidate positions in
+/// pick order, so near-duplicates of a chunk already picked drop down the list
+pub fn mmr_order(query: &[f32], candidates: &[Vec<f32>], lambda: f32, limit: usize) -> Vec<usize> {
+    let relevance: Vec<f32> = candidates
+        .iter()
+        .map(|candidate| CandleEmbeddingService::cosine_similarity(query, candidate))
+        .collect();
+    // Highest similarity of each candidate to anything picked so far
+    let mut redundancy = vec![f32::NEG_INFINITY; candidates.len()];
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+    let mut picked = Vec::with_capacity(limit.min(candidates.len()));
+
+    while picked.len() < limit && !remaining.is_empty() {
+        let marginal = |i: usize| {
+            let penalty = if redundancy[i].is_finite() { redundancy[i] } else { 0.0 };
+            lambda * relevance[i] - (1.0 - lambda) * penalty
+        };
+        // First of equals wins, so ties keep the search order
+        let mut best = 0;
+        for position in 1..remaining.len() {
+            if marginal(remaining[position]) > marginal(remaining[best]) {
+                best = position;
+            }
+        }
+        let choice = remaining.remove(best);
+        for &i in &remaining {
+            let similarity = CandleEmbeddingService::cosine_similarity(&candidates[i], &candidates[choice]);
+            redundancy[i] = redundancy[i].max(similarity);
+        }
+        picked.push(choice);
+    }
+    picked
+}
 
 pub struct EmbeddingService {
-    elasticsearch_service: ElasticsearchService,
+    vector_store: Arc<VectorBackend>,
     candle_service: CandleEmbeddingService,
+    cache: Arc<EmbeddingCache>,
+    embedding_calls: AtomicU64,
+    searches: AtomicU64,
 }
 
 impl EmbeddingService {
-    pub fn new(elasticsearch: Arc<Elasticsearch>) -> Result<Self> {
-        tracing::info!("Initializing EmbeddingService with Elasticsearch backend");
+    pub fn new(vector_store: Arc<VectorBackend>, cache: Arc<EmbeddingCache>) -> Result<Self> {
+        tracing::info!("Initializing EmbeddingService with {} backend", vector_store.kind());
         
         // Initialize Candle embedding service
-        let config = EmbeddingConfig {
-            model_name: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
-            max_length: 512,
-            embedding_dim: 384,
-        };
+        let config = EmbeddingConfig::from_env()?;
         
-        let candle_service = CandleEmbeddingService::new(Some(config))?;
-        let elasticsearch_service = ElasticsearchService::new(elasticsearch);
+        let candle_service = CandleEmbeddingService::new(Some(config))?.with_cache(cache.clone());
         
         Ok(Self {
-            elasticsearch_service,
+            vector_store,
             candle_service,
+            cache,
+            embedding_calls: AtomicU64::new(0),
+            searches: AtomicU64::new(0),
         })
     }
 
+    // Pull persisted embeddings for texts missing from the in-memory cache in one query. Returns the
+    // position and cache key of each text cached nowhere, the only ones worth writing back. The cache
+    // only saves inference, so a failing lookup is logged and its texts count as misses
+    async fn warm_cache(&self, texts: &[String]) -> Vec<(usize, String)> {
+        if !self.cache.is_persistent() {
+            return Vec::new();
+        }
+
+        let misses: Vec<(usize, String)> = texts
+            .iter()
+            .enumerate()
+            .map(|(position, text)| (position, self.candle_service.cache_key(text)))
+            .filter(|(_, key)| self.cache.get(key).is_none())
+            .collect();
+        if misses.is_empty() {
+            return misses;
+        }
+
+        let keys: Vec<String> = misses.iter().map(|(_, key)| key.clone()).collect();
+        match self.cache.load_persisted(&keys).await {
+            Ok(found) => misses.into_iter().filter(|(_, key)| !found.contains(key)).collect(),
+            Err(e) => {
+                tracing::warn!("⚠️ Failed to load {} persisted embeddings: {}", keys.len(), e);
+                misses
+            }
+        }
+    }
+
+    // Write the embeddings computed for cache misses through to the persistent cache in one insert.
+    // A failing write is logged, since the embeddings are already in hand
+    async fn persist_cache(&self, misses: &[(usize, String)], embeddings: &[Vec<f32>]) {
+        let entries: Vec<(String, &[f32])> = misses
+            .iter()
+            .filter_map(|(position, key)| embeddings.get(*position).map(|embedding| (key.clone(), embedding.as_slice())))
+            .collect();
+        if let Err(e) = self.cache.persist(self.candle_service.model_name(), &entries).await {
+            tracing::warn!("⚠️ Failed to persist {} embeddings: {}", entries.len(), e);
+        }
+    }
+
+    // Batched inference is CPU-bound, so keep it from stalling other tasks on this runtime thread
+    fn run_inference(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        tokio::task::block_in_place(|| self.candle_service.embed_texts(texts))
+    }
+
     // Create an index for a chatbot if it doesn't exist
     pub async fn create_collection_if_not_exists(&self, collection_name: &str) -> Result<()> {
-        self.elasticsearch_service
-            .create_index_if_not_exists(collection_name, self.candle_service.embedding_dim())
+        self.vector_store
+            .create_collection(collection_name, self.candle_service.embedding_dim())
             .await
     }
 
-    // Process PDF file and create embeddings
+    // Grow the chatbot's shards if they are full, then create the collection a document is routed to
+    pub async fn prepare_chatbot_collection(
+        &self,
+        db: &PgPool,
+        chatbot_cache: &ChatbotCache,
+        organization_id: Uuid,
+        chatbot: &ChatBot,
+        document_name: &str,
+    ) -> Result<String> {
+        // Add a shard once the existing ones are full
+        let base_index = chatbot_index_name(organization_id, chatbot.id);
+        let existing_chunks = self
+            .count_documents(&shard_indices(&base_index, chatbot.shard_count))
+            .await?;
+        let shard_count = required_shard_count(existing_chunks + 1, max_chunks_per_shard(), chatbot.shard_count);
+
+        if shard_count > chatbot.shard_count {
+            tracing::info!("Growing chatbot {} from {} to {} shards ({} chunks)",
+                           chatbot.id, chatbot.shard_count, shard_count, existing_chunks);
+            update_chat_bot_shard_count(db, organization_id, chatbot.id, shard_count).await?;
+            publish(db, chatbot_cache, CacheEvent::ChatbotSettingsChanged { chatbot_id: chatbot.id }).await?;
+        }
+
+        // Keep a document in the shard it was first indexed into, so re-indexing overwrites its chunks.
+        // Documents indexed before shards were recorded may sit in any shard, so their chunks are
+        // cleared everywhere before they get one
+        let shard = match get_document_shard(db, chatbot.id, document_name).await? {
+            Some(shard) => shard,
+            None => {
+                self.vector_store
+                    .delete_document_chunks(&shard_indices(&base_index, shard_count), document_name)
+                    .await?;
+                assign_document_shard(db, chatbot.id, document_name, shard_for_new_document(shard_count) as i32).await?
+            }
+        };
+        let collection_name = shard_index_name(&base_index, shard as u32);
+
+        // Ensure collection exists
+        self.create_collection_if_not_exists(&collection_name).await?;
+
+        Ok(collection_name)
+    }
+
+    // Process PDF file and create embeddings, passing chunks through the chatbot's ingest webhook if it has one.
+    // A chunker plugin takes precedence over the chunking strategy
     pub async fn process_pdf_file(
         &self,
         file_path: &PathBuf,
         collection_name: &str,
+        webhook: Option<&IngestWebhook>,
+        plugins: &PluginHost,
+        strategy: ChunkingStrategy,
     ) -> Result<usize> {
-        tracing::info!("Processing PDF file: {:?}", file_path);
+        tracing::info!("Processing PDF file: {:?} ({} chunking)", file_path, strategy.as_str());
 
-        // Extract text from PDF and chunk it
-        let chunks = process_pdf_file(file_path, 200, 50)?; // 200 words per chunk, 50 word overlap
+        // Extract text from PDF and chunk it; OCR of scanned PDFs can take a while, so keep it off the runtime
+        let path = file_path.clone();
+        let (chunks, sections) = if plugins.has_chunker() {
+            let text = tokio::task::spawn_blocking(move || extract_text_from_pdf(path)).await??;
+            (plugins.chunk(&file_path.to_string_lossy(), &text).await?, None)
+        } else if strategy == ChunkingStrategy::Semantic {
+            let text = tokio::task::spawn_blocking(move || extract_text_from_pdf(path)).await??;
+            (self.semantic_chunks(&text, 200).await?, None)
+        } else if strategy == ChunkingStrategy::SmallToBig {
+            let text = tokio::task::spawn_blocking(move || extract_text_from_pdf(path)).await??;
+            let (chunks, sections): (Vec<String>, Vec<i64>) =
+                small_to_big_chunks(&text, SECTION_WORDS, SECTION_CHUNK_WORDS).into_iter().unzip();
+            (chunks, Some(sections))
+        } else {
+            // 200 words per chunk, 50 word overlap
+            (tokio::task::spawn_blocking(move || process_pdf_file(path, 200, 50)).await??, None)
+        };
         
         if chunks.is_empty() {
             tracing::warn!("No text chunks extracted from PDF");
@@ -59,31 +232,89 @@ impl EmbeddingService {
 
         tracing::info!("Extracted {} text chunks from PDF", chunks.len());
 
-        // Generate embeddings for all chunks
-        let embeddings = self.candle_service.embed_texts(&chunks)?;
+        self.index_chunks_in_sections(&file_path.to_string_lossy(), chunks, sections, collection_name, webhook).await
+    }
+
+    // Split text into sentences and merge neighbours while their embeddings stay similar, so chunks
+    // end where the topic changes rather than mid-sentence. Costs one embedding per sentence
+    pub async fn semantic_chunks(&self, text: &str, max_words: usize) -> Result<Vec<String>> {
+        let sentences = split_sentences(text, max_words);
+        if sentences.len() < 2 {
+            return Ok(sentences);
+        }
+
+        let embeddings = self.embed_texts(&sentences).await?;
+        let similarities: Vec<f32> = embeddings
+            .windows(2)
+            .map(|pair| CandleEmbeddingService::cosine_similarity(&pair[0], &pair[1]))
+            .collect();
+        let chunks = merge_sentences(&sentences, &similarities, semantic_threshold(), max_words);
+        tracing::info!("Merged {} sentences into {} semantic chunks", sentences.len(), chunks.len());
+        Ok(chunks)
+    }
+
+    // Embed one document's chunks and store them, passing them through the ingest webhook if there is one
+    pub async fn index_chunks(
+        &self,
+        file_path: &str,
+        chunks: Vec<String>,
+        collection_name: &str,
+        webhook: Option<&IngestWebhook>,
+    ) -> Result<usize> {
+        self.index_chunks_in_sections(file_path, chunks, None, collection_name, webhook).await
+    }
+
+    // Embed and store chunks, recording the section each was cut from when chunked small-to-big
+    async fn index_chunks_in_sections(
+        &self,
+        file_path: &str,
+        chunks: Vec<String>,
+        sections: Option<Vec<i64>>,
+        collection_name: &str,
+        webhook: Option<&IngestWebhook>,
+    ) -> Result<usize> {
+        let (chunks, sections) = match webhook {
+            Some(webhook) => {
+                let transformed = webhook.transform_chunks(file_path, &chunks).await?;
+                // Sections only line up while the webhook keeps one chunk for each it was sent
+                let sections = sections.filter(|sections| sections.len() == transformed.len());
+                (transformed, sections)
+            }
+            None => (chunks, sections),
+        };
+        if chunks.is_empty() {
+            tracing::warn!("Ingest webhook dropped every chunk");
+            return Ok(0);
+        }
+
+        // Generate embeddings for all chunks, reusing cached vectors where possible
+        let misses = self.warm_cache(&chunks).await;
+        let embeddings = self.run_inference(&chunks)?;
+        self.persist_cache(&misses, &embeddings).await;
         
         if embeddings.len() != chunks.len() {
             tracing::error!("Mismatch between chunks ({}) and embeddings ({})", chunks.len(), embeddings.len());
             return Err(anyhow::anyhow!("Embedding generation failed"));
         }
 
-        // Create documents for Elasticsearch
+        // Create documents for the vector store
         let mut documents = Vec::new();
         
         for (i, (chunk, embedding)) in chunks.iter().zip(embeddings.iter()).enumerate() {
             let document = DocumentWithEmbedding {
-                id: Uuid::new_v4().to_string(),
+                id: chunk_id(file_path, i as i64, chunk),
                 text: chunk.clone(),
                 embedding: embedding.clone(),
                 chunk_index: i as i64,
-                file_path: file_path.to_string_lossy().to_string(),
+                file_path: file_path.to_string(),
                 chunk_count: chunks.len() as i64,
+                parent_index: sections.as_ref().and_then(|sections| sections.get(i).copied()),
             };
             documents.push(document);
         }
 
-        // Index all documents in Elasticsearch
-        let indexed_count = self.elasticsearch_service
+        // Index all documents in the vector store
+        let indexed_count = self.vector_store
             .index_documents(collection_name, documents)
             .await?;
         
@@ -92,30 +323,161 @@ impl EmbeddingService {
         Ok(indexed_count)
     }
 
-    // Search for similar embeddings
+    // Search for similar embeddings, fanning out across every shard index and merging by score
     pub async fn search_similar(
         &self,
-        collection_name: &str,
+        index_names: &[String],
         query_text: &str,
         limit: u64,
-    ) -> Result<Vec<crate::services::elasticsearch::SearchResult>> {
-        tracing::info!("Searching for similar embeddings in index '{}'", collection_name);
+    ) -> Result<Vec<SearchResult>> {
+        tracing::info!("Searching for similar embeddings in indices {:?}", index_names);
 
         // Generate embedding for the query text
+        let query_texts = [query_text.to_string()];
+        let misses = self.warm_cache(&query_texts).await;
         let query_embedding = self.candle_service.embed_text(query_text)?;
+        self.persist_cache(&misses, std::slice::from_ref(&query_embedding)).await;
+        self.embedding_calls.fetch_add(1, Ordering::Relaxed);
+        self.searches.fetch_add(1, Ordering::Relaxed);
 
-        // Search in Elasticsearch
-        let search_results = self.elasticsearch_service
-            .search_similar(collection_name, query_embedding, limit)
-            .await?;
+        // Search every shard concurrently
+        let searches = index_names.iter().map(|index_name| {
+            self.vector_store
+                .search_similar(index_name, query_embedding.clone(), limit)
+        });
+
+        let mut search_results = Vec::new();
+        for shard_results in join_all(searches).await {
+            search_results.extend(shard_results?);
+        }
+
+        search_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        search_results.truncate(limit as usize);
+        let search_results = self.expand_sections(index_names, search_results).await;
         
         tracing::info!("Found {} similar documents", search_results.len());
 
         Ok(search_results)
     }
 
+    /// Search many chatbots' indices in one request, keeping the index of every hit. Sections
+    /// aren't expanded, so each hit is the chunk as stored
+    pub async fn search_collections(
+        &self,
+        index_names: &[String],
+        query_text: &str,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        tracing::info!("Searching for similar embeddings across {} indices", index_names.len());
+
+        let query_texts = [query_text.to_string()];
+        let misses = self.warm_cache(&query_texts).await;
+        let query_embedding = self.candle_service.embed_text(query_text)?;
+        self.persist_cache(&misses, std::slice::from_ref(&query_embedding)).await;
+        self.embedding_calls.fetch_add(1, Ordering::Relaxed);
+        self.searches.fetch_add(1, Ordering::Relaxed);
+
+        self.vector_store.search_collections(index_names, query_embedding, limit).await
+    }
+
+    // Second fetch for documents chunked small-to-big: swap matched chunks for the sections they were
+    // cut from, one fetch per document. If a fetch fails, that document's chunks are kept as matched
+    async fn expand_sections(&self, index_names: &[String], results: Vec<SearchResult>) -> Vec<SearchResult> {
+        let documents = matched_sections(&results);
+        if documents.is_empty() {
+            return results;
+        }
+
+        let fetches = documents.iter().map(|(file_path, sections)| {
+            self.vector_store.fetch_section_chunks(index_names, file_path, sections)
+        });
+        let mut chunks = Vec::new();
+        for (fetched, (file_path, _)) in join_all(fetches).await.into_iter().zip(&documents) {
+            match fetched {
+                Ok(fetched) => chunks.extend(fetched),
+                Err(e) => tracing::warn!("⚠️ Failed to fetch sections of {}, using matched chunks: {}", file_path, e),
+            }
+        }
+
+        replace_with_sections(results, &section_texts(&chunks))
+    }
+
+    // Reorder hits by maximal marginal relevance to the query and keep `limit` of them. Hits keep
+    // their similarity scores, so score thresholds still apply afterwards
+    pub async fn diversify(
+        &self,
+        query_text: &str,
+        results: Vec<SearchResult>,
+        lambda: f32,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        if results.len() <= 1 {
+            return Ok(results);
+        }
+
+        let mut texts = vec![query_text.to_string()];
+        texts.extend(results.iter().map(|result| result.text.clone()));
+        let mut embeddings = self.embed_texts(&texts).await?;
+        let candidates = embeddings.split_off(1);
+
+        let order = mmr_order(&embeddings[0], &candidates, lambda, limit);
+        let mut results: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+        Ok(order.into_iter().filter_map(|i| results[i].take()).collect())
+    }
+
+    // Embed arbitrary texts, going through the embedding cache
+    pub async fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let misses = self.warm_cache(texts).await;
+        let embeddings = self.run_inference(texts)?;
+        self.persist_cache(&misses, &embeddings).await;
+        self.embedding_calls.fetch_add(texts.len() as u64, Ordering::Relaxed);
+        Ok(embeddings)
+    }
+
+    // Count indexed chunks across a chatbot's shards
+    pub async fn count_documents(&self, index_names: &[String]) -> Result<u64> {
+        self.vector_store.count_documents(index_names).await
+    }
+
+    // Texts embedded and searches run by this service so far, for usage tracking
+    pub fn usage(&self) -> (u64, u64) {
+        (self.embedding_calls.load(Ordering::Relaxed), self.searches.load(Ordering::Relaxed))
+    }
+
     // Get embedding dimension
     pub fn embedding_dim(&self) -> usize {
         self.candle_service.embedding_dim()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_mmr_lambda() {
+        assert!(validate_mmr_lambda(0.0).is_ok());
+        assert!(validate_mmr_lambda(0.7).is_ok());
+        assert!(validate_mmr_lambda(1.0).is_ok());
+        assert!(validate_mmr_lambda(-0.1).is_err());
+        assert!(validate_mmr_lambda(1.5).is_err());
+        assert!(validate_mmr_lambda(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_mmr_order_skips_near_duplicates() {
+        let query = [1.0, 0.0];
+        // Two copies of the most relevant chunk and a less relevant but different one
+        let candidates = vec![vec![1.0, 0.1], vec![1.0, 0.1], vec![0.8, -0.6]];
+
+        assert_eq!(mmr_order(&query, &candidates, 0.5, 3), vec![0, 2, 1]);
+        assert_eq!(mmr_order(&query, &candidates, 1.0, 3), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_mmr_order_limit() {
+        let candidates = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.7, 0.7]];
+        assert_eq!(mmr_order(&[1.0, 0.0], &candidates, 0.5, 2).len(), 2);
+        assert!(mmr_order(&[1.0, 0.0], &[], 0.5, 2).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing;
+
+use crate::services::candle_embedding::{CandleEmbeddingService, EmbeddingConfig};
+
+const VOCABULARY: &[&str] = &[
+    "retrieval", "augmented", "generation", "document", "embedding", "vector", "search",
+    "chatbot", "knowledge", "context", "answer", "question", "model", "latency", "index",
+    "session", "conversation", "elasticsearch", "postgres", "token", "chunk", "overlap",
+];
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub chunks: usize,
+    pub words_per_chunk: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            chunks: 1000,
+            words_per_chunk: 200,
+        }
+    }
+}
+
+impl BenchConfig {
+    /// Parse `bench [chunks] [words_per_chunk]` arguments
+    pub fn from_args(args: &[String]) -> Self {
+        let defaults = Self::default();
+        Self {
+            chunks: args.first().and_then(|v| v.parse().ok()).unwrap_or(defaults.chunks),
+            words_per_chunk: args
+                .get(1)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.words_per_chunk),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub model_name: String,
+    pub embedding_dim: usize,
+    pub chunks: usize,
+    pub words_per_chunk: usize,
+    pub total_secs: f64,
+    pub chunks_per_sec: f64,
+    pub p50_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub batch_size: usize,
+    pub batched_total_secs: f64,
+    pub batched_chunks_per_sec: f64,
+    pub peak_rss_kb: Option<u64>,
+}
+
+/// Deterministic synthetic corpus so runs are comparable across builds
+pub fn synthetic_corpus(chunks: usize, words_per_chunk: usize) -> Vec<String> {
+    (0..chunks)
+        .map(|i| {
+            (0..words_per_chunk)
+                .map(|j| VOCABULARY[(i * 7 + j * 13) % VOCABULARY.len()])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of an already sorted slice
+pub fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Peak resident set size from /proc, only available on Linux
+fn peak_rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+}
+
+/// Embed a synthetic corpus one chunk at a time, then in parallel batches, and report throughput
+pub fn run_embedding_bench(config: &BenchConfig) -> Result<BenchReport> {
+    let embedding_config = EmbeddingConfig::from_env()?;
+    let service = CandleEmbeddingService::new(Some(embedding_config.clone()))?;
+    let corpus = synthetic_corpus(config.chunks, config.words_per_chunk);
+
+    tracing::info!(
+        "Benchmarking {} chunks of {} words with model {}",
+        config.chunks,
+        config.words_per_chunk,
+        embedding_config.model_name
+    );
+
+    let mut latencies = Vec::with_capacity(corpus.len());
+    let started = Instant::now();
+    for chunk in &corpus {
+        let chunk_started = Instant::now();
+        service.embed_text(chunk)?;
+        latencies.push(chunk_started.elapsed());
+    }
+    let total = started.elapsed();
+
+    let batched_started = Instant::now();
+    service.embed_texts(&corpus)?;
+    let batched_total = batched_started.elapsed();
+
+    latencies.sort();
+    let to_ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let per_sec = |d: Duration| if d.is_zero() { 0.0 } else { config.chunks as f64 / d.as_secs_f64() };
+
+    Ok(BenchReport {
+        model_name: embedding_config.model_name,
+        embedding_dim: service.embedding_dim(),
+        chunks: config.chunks,
+        words_per_chunk: config.words_per_chunk,
+        total_secs: total.as_secs_f64(),
+        chunks_per_sec: per_sec(total),
+        p50_latency_ms: to_ms(percentile(&latencies, 50.0)),
+        p95_latency_ms: to_ms(percentile(&latencies, 95.0)),
+        max_latency_ms: to_ms(latencies.last().copied().unwrap_or_default()),
+        batch_size: embedding_config.batch_size,
+        batched_total_secs: batched_total.as_secs_f64(),
+        batched_chunks_per_sec: per_sec(batched_total),
+        peak_rss_kb: peak_rss_kb(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 95.0), Duration::from_millis(95));
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&[], 95.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_synthetic_corpus_shape() {
+        let corpus = synthetic_corpus(3, 10);
+        assert_eq!(corpus.len(), 3);
+        assert_eq!(corpus[0].split_whitespace().count(), 10);
+        assert_eq!(corpus, synthetic_corpus(3, 10));
+    }
+}
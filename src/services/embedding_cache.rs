@@ -0,0 +1,127 @@
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use tracing;
+
+use crate::db::queries::{get_cached_embeddings, insert_cached_embeddings};
+use crate::errors::AppResult;
+
+const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Embedding cache keyed by the SHA-256 of model name + text.
+///
+/// Lookups always hit the in-memory LRU first; when a database pool is
+/// configured, misses fall back to the `embedding_cache` table so cached
+/// vectors survive restarts and are shared between replicas.
+pub struct EmbeddingCache {
+    memory: Mutex<LruCache<String, Vec<f32>>>,
+    db: Option<Arc<PgPool>>,
+}
+
+impl EmbeddingCache {
+    /// Create a cache holding up to `capacity` embeddings in memory
+    pub fn new(capacity: usize, db: Option<Arc<PgPool>>) -> Self {
+        let capacity = NonZeroUsize::new(capacity)
+            .unwrap_or(NonZeroUsize::new(DEFAULT_CAPACITY).unwrap());
+
+        Self {
+            memory: Mutex::new(LruCache::new(capacity)),
+            db,
+        }
+    }
+
+    /// Build the cache from `EMBEDDING_CACHE_CAPACITY` and `EMBEDDING_CACHE_PERSIST`
+    pub fn from_env(db: Arc<PgPool>) -> Self {
+        let capacity = std::env::var("EMBEDDING_CACHE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let persist = std::env::var("EMBEDDING_CACHE_PERSIST")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        tracing::info!("Embedding cache: capacity {}, persistent: {}", capacity, persist);
+        Self::new(capacity, if persist { Some(db) } else { None })
+    }
+
+    /// Compute the cache key for a piece of text embedded with a given model
+    pub fn cache_key(model_name: &str, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model_name.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(text.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Look up an embedding in the in-memory cache
+    pub fn get(&self, key: &str) -> Option<Vec<f32>> {
+        self.memory.lock().ok()?.get(key).cloned()
+    }
+
+    /// Store an embedding in the in-memory cache
+    pub fn put(&self, key: String, embedding: Vec<f32>) {
+        if let Ok(mut memory) = self.memory.lock() {
+            memory.put(key, embedding);
+        }
+    }
+
+    /// Whether misses are backed by the Postgres table
+    pub fn is_persistent(&self) -> bool {
+        self.db.is_some()
+    }
+
+    /// Load the persisted embeddings of `keys` into memory in one query, returning the keys found
+    pub async fn load_persisted(&self, keys: &[String]) -> AppResult<HashSet<String>> {
+        let Some(db) = &self.db else {
+            return Ok(HashSet::new());
+        };
+
+        let mut found = HashSet::new();
+        for (key, embedding) in get_cached_embeddings(db, keys).await? {
+            self.put(key.clone(), embedding);
+            found.insert(key);
+        }
+
+        Ok(found)
+    }
+
+    /// Write embeddings through to Postgres, keyed by their cache keys
+    pub async fn persist(&self, model_name: &str, entries: &[(String, &[f32])]) -> AppResult<()> {
+        if let Some(db) = &self.db
+            && !entries.is_empty()
+        {
+            insert_cached_embeddings(db, model_name, entries).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_depends_on_model_and_text() {
+        let a = EmbeddingCache::cache_key("model-a", "hello");
+        let b = EmbeddingCache::cache_key("model-b", "hello");
+        let c = EmbeddingCache::cache_key("model-a", "hello");
+
+        assert_eq!(a.len(), 64);
+        assert_ne!(a, b);
+        assert_eq!(a, c);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = EmbeddingCache::new(2, None);
+        cache.put("a".to_string(), vec![1.0]);
+        cache.put("b".to_string(), vec![2.0]);
+        cache.put("c".to_string(), vec![3.0]);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("c"), Some(vec![3.0]));
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Most question/answer pairs one evaluation may hold; each costs a search and two model calls
+pub const MAX_EVALUATION_CASES: usize = 50;
+/// Both scores must reach this for a question to pass, unless the request sets `pass_threshold`
+pub const DEFAULT_PASS_THRESHOLD: f64 = 0.7;
+
+/// The judge's verdict on one answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct JudgeScores {
+    /// 0 to 1: how much of the answer the retrieved context supports
+    pub faithfulness: f64,
+    /// 0 to 1: how well the answer addresses the question and agrees with the expected answer
+    pub relevance: f64,
+    #[serde(default)]
+    pub explanation: String,
+}
+
+impl JudgeScores {
+    pub fn passes(&self, threshold: f64) -> bool {
+        self.faithfulness >= threshold && self.relevance >= threshold
+    }
+}
+
+/// The scores in a judge reply, which should be a JSON object but may come wrapped in prose or a
+/// code fence. None when a score is missing or outside 0 to 1
+pub fn parse_judge_reply(reply: &str) -> Option<JudgeScores> {
+    let start = reply.find('{')?;
+    let end = reply.rfind('}')?;
+    let scores: JudgeScores = serde_json::from_str(reply.get(start..=end)?).ok()?;
+    let in_range = |score: f64| (0.0..=1.0).contains(&score);
+    (in_range(scores.faithfulness) && in_range(scores.relevance)).then_some(scores)
+}
+
+/// How one question of an evaluation went
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CaseResult {
+    pub question: String,
+    pub expected_answer: String,
+    /// The chatbot's answer, unless answering failed
+    pub answer: Option<String>,
+    /// Files of the chunks the answer was generated from
+    pub context_used: Vec<String>,
+    /// Whether the chatbot replied with its fallback message
+    pub fallback: bool,
+    /// Unset when answering or judging failed
+    pub scores: Option<JudgeScores>,
+    pub passed: bool,
+    pub error: Option<String>,
+}
+
+/// Metrics over every question of an evaluation
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct EvaluationSummary {
+    pub cases: usize,
+    /// Questions the judge scored; the rest failed to be answered or judged
+    pub judged: usize,
+    pub passed: usize,
+    /// Passed questions over all questions, so ones that couldn't be judged count as failures
+    pub pass_rate: f64,
+    /// Means over judged questions, unset when none was judged
+    pub mean_faithfulness: Option<f64>,
+    pub mean_relevance: Option<f64>,
+    pub fallbacks: usize,
+}
+
+pub fn summarize(results: &[CaseResult]) -> EvaluationSummary {
+    let scores: Vec<&JudgeScores> = results.iter().filter_map(|result| result.scores.as_ref()).collect();
+    let mean = |score: fn(&JudgeScores) -> f64| {
+        (!scores.is_empty()).then(|| scores.iter().map(|s| score(s)).sum::<f64>() / scores.len() as f64)
+    };
+    let passed = results.iter().filter(|result| result.passed).count();
+
+    EvaluationSummary {
+        cases: results.len(),
+        judged: scores.len(),
+        passed,
+        pass_rate: if results.is_empty() { 0.0 } else { passed as f64 / results.len() as f64 },
+        mean_faithfulness: mean(|s| s.faithfulness),
+        mean_relevance: mean(|s| s.relevance),
+        fallbacks: results.iter().filter(|result| result.fallback).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(scores: Option<(f64, f64)>, fallback: bool) -> CaseResult {
+        let scores = scores.map(|(faithfulness, relevance)| JudgeScores {
+            faithfulness,
+            relevance,
+            explanation: String::new(),
+        });
+        CaseResult {
+            question: "q".to_string(),
+            expected_answer: "a".to_string(),
+            answer: scores.as_ref().map(|_| "answer".to_string()),
+            context_used: Vec::new(),
+            fallback,
+            passed: scores.as_ref().is_some_and(|s| s.passes(DEFAULT_PASS_THRESHOLD)),
+            scores,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_judge_reply() {
+        let scores = parse_judge_reply(r#"{"faithfulness": 0.9, "relevance": 0.5, "explanation": "Partly"}"#).unwrap();
+        assert_eq!(scores.faithfulness, 0.9);
+        assert_eq!(scores.relevance, 0.5);
+        assert_eq!(scores.explanation, "Partly");
+
+        let fenced = "```json\n{\"faithfulness\": 1, \"relevance\": 0}\n```";
+        assert_eq!(parse_judge_reply(fenced).unwrap().relevance, 0.0);
+
+        assert!(parse_judge_reply(r#"{"faithfulness": 1.5, "relevance": 0.5}"#).is_none());
+        assert!(parse_judge_reply(r#"{"faithfulness": 0.5}"#).is_none());
+        assert!(parse_judge_reply("Looks good to me").is_none());
+    }
+
+    #[test]
+    fn test_passes_needs_both_scores() {
+        let scores = JudgeScores { faithfulness: 0.9, relevance: 0.6, explanation: String::new() };
+        assert!(!scores.passes(0.7));
+        assert!(scores.passes(0.6));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let summary = summarize(&[
+            result(Some((1.0, 0.8)), false),
+            result(Some((0.5, 0.2)), true),
+            result(None, false),
+        ]);
+
+        assert_eq!(summary.cases, 3);
+        assert_eq!(summary.judged, 2);
+        assert_eq!(summary.passed, 1);
+        assert!((summary.pass_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(summary.mean_faithfulness, Some(0.75));
+        assert!((summary.mean_relevance.unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(summary.fallbacks, 1);
+
+        let empty = summarize(&[]);
+        assert_eq!(empty.pass_rate, 0.0);
+        assert_eq!(empty.mean_faithfulness, None);
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::models::FaqCluster;
+use crate::db::queries::{list_chatbot_user_queries, list_chatbots_with_recent_conversations, replace_faq_clusters};
+use crate::services::candle_embedding::CandleEmbeddingService;
+use crate::services::embedding::EmbeddingService;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::VectorBackend;
+
+const DEFAULT_THRESHOLD: f32 = 0.8;
+const DEFAULT_DAYS: i32 = 30;
+const DEFAULT_INTERVAL_SECS: u64 = 6 * 3600;
+// Most recent questions clustered per chatbot
+const MAX_QUESTIONS: i64 = 5000;
+// Clusters kept per chatbot
+const MAX_CLUSTERS: usize = 50;
+// Example questions kept per cluster
+const MAX_SAMPLES: usize = 5;
+
+/// Similarity a question needs to a cluster's centroid to join it, from `FAQ_CLUSTER_THRESHOLD`
+pub fn cluster_threshold() -> f32 {
+    std::env::var("FAQ_CLUSTER_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &f32| (0.0..=1.0).contains(v))
+        .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+/// Days of questions clustered, from `FAQ_CLUSTER_DAYS`
+pub fn cluster_days() -> i32 {
+    std::env::var("FAQ_CLUSTER_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &i32| *v > 0)
+        .unwrap_or(DEFAULT_DAYS)
+}
+
+/// Distinct questions with how often each was asked, most frequent first. Questions differing only
+/// in case, spacing or trailing punctuation count as one, shown as first asked
+pub fn count_questions(queries: &[String]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, (String, usize)> = HashMap::new();
+    let mut order = Vec::new();
+    for query in queries {
+        let text = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        let key = text.trim_end_matches(['?', '!', '.']).to_lowercase();
+        if key.is_empty() {
+            continue;
+        }
+        counts
+            .entry(key.clone())
+            .and_modify(|(_, count)| *count += 1)
+            .or_insert_with(|| {
+                order.push(key);
+                (text, 1)
+            });
+    }
+
+    let mut questions: Vec<(String, usize)> = order.into_iter().filter_map(|key| counts.remove(&key)).collect();
+    questions.sort_by(|a, b| b.1.cmp(&a.1));
+    questions
+}
+
+/// A group of similar questions
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionCluster {
+    /// The cluster's most asked question
+    pub representative: String,
+    /// Its most asked questions, representative first
+    pub samples: Vec<String>,
+    /// Times any of its questions was asked
+    pub hits: usize,
+}
+
+/// Greedily cluster questions, most frequent first: each joins the cluster whose centroid is most
+/// similar if that reaches `threshold`, else starts one. Clusters come back by hits, largest first
+pub fn cluster_questions(questions: &[(String, usize)], embeddings: &[Vec<f32>], threshold: f32) -> Vec<QuestionCluster> {
+    let mut clusters: Vec<QuestionCluster> = Vec::new();
+    // Sum of member embeddings, weighted by how often each was asked
+    let mut centroids: Vec<Vec<f32>> = Vec::new();
+
+    for ((question, count), embedding) in questions.iter().zip(embeddings) {
+        let best = centroids
+            .iter()
+            .map(|centroid| CandleEmbeddingService::cosine_similarity(centroid, embedding))
+            .enumerate()
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((index, _)) => {
+                let cluster = &mut clusters[index];
+                cluster.hits += count;
+                if cluster.samples.len() < MAX_SAMPLES {
+                    cluster.samples.push(question.clone());
+                }
+                for (sum, value) in centroids[index].iter_mut().zip(embedding) {
+                    *sum += value * *count as f32;
+                }
+            }
+            None => {
+                clusters.push(QuestionCluster {
+                    representative: question.clone(),
+                    samples: vec![question.clone()],
+                    hits: *count,
+                });
+                centroids.push(embedding.iter().map(|value| value * *count as f32).collect());
+            }
+        }
+    }
+
+    clusters.sort_by(|a, b| b.hits.cmp(&a.hits));
+    clusters
+}
+
+/// Re-cluster a chatbot's recent questions and replace its stored clusters. Returns how many were
+/// stored; clusters asked only once aren't frequent and are left out
+pub async fn refresh_faq_clusters(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_id: Uuid,
+) -> Result<usize> {
+    let queries = list_chatbot_user_queries(db, chatbot_id, cluster_days(), MAX_QUESTIONS).await?;
+    let questions = count_questions(&queries);
+    let texts: Vec<String> = questions.iter().map(|(question, _)| question.clone()).collect();
+
+    let embeddings = if texts.is_empty() {
+        Vec::new()
+    } else {
+        EmbeddingService::new(vector_store.clone(), embedding_cache)?.embed_texts(&texts).await?
+    };
+    let clusters: Vec<FaqCluster> = cluster_questions(&questions, &embeddings, cluster_threshold())
+        .into_iter()
+        .filter(|cluster| cluster.hits > 1)
+        .take(MAX_CLUSTERS)
+        .enumerate()
+        .map(|(index, cluster)| FaqCluster {
+            chatbot_id,
+            rank: index as i32 + 1,
+            representative_question: cluster.representative,
+            sample_questions: cluster.samples,
+            hit_count: cluster.hits as i64,
+            computed_at: chrono::Utc::now(),
+        })
+        .collect();
+
+    replace_faq_clusters(db, chatbot_id, &clusters).await?;
+    tracing::info!("✅ Clustered {} questions of chatbot {} into {} FAQ clusters", queries.len(), chatbot_id, clusters.len());
+    Ok(clusters.len())
+}
+
+// Spawn the background task that re-clusters the questions of chatbots with recent conversations
+pub fn spawn_faq_cluster_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+) {
+    let interval_secs = std::env::var("FAQ_CLUSTER_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let chatbot_ids = match list_chatbots_with_recent_conversations(&db, cluster_days()).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    tracing::error!("❌ FAQ cluster task failed to list chatbots: {}", e);
+                    continue;
+                }
+            };
+            for chatbot_id in chatbot_ids {
+                if let Err(e) = refresh_faq_clusters(&db, &vector_store, embedding_cache.clone(), chatbot_id).await {
+                    tracing::error!("❌ Failed to cluster questions of chatbot {}: {}", chatbot_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_questions() {
+        let queries: Vec<String> = ["How do I reset my password?", "how do I  reset my password", "Where is my order?"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            count_questions(&queries),
+            vec![("How do I reset my password?".to_string(), 2), ("Where is my order?".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_cluster_questions() {
+        let questions = vec![
+            ("Reset password".to_string(), 5),
+            ("Track my order".to_string(), 3),
+            ("Forgot password".to_string(), 2),
+        ];
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.9, 0.1]];
+        let clusters = cluster_questions(&questions, &embeddings, 0.8);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].representative, "Reset password");
+        assert_eq!(clusters[0].samples, vec!["Reset password", "Forgot password"]);
+        assert_eq!(clusters[0].hits, 7);
+        assert_eq!(clusters[1].hits, 3);
+    }
+}
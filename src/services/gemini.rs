@@ -1,9 +1,11 @@
 use crate::errors::AppResult;
+use crate::services::retry::{with_retry, Upstream};
 use gemini_rust::Gemini;
 use serde::Serialize;
 use std::env;
 use futures_util::{Stream, stream, TryStreamExt};
 use std::pin::Pin;
+use tracing::Instrument;
 
 #[derive(Debug, Serialize)]
 pub struct StreamingChunk {
@@ -28,49 +30,152 @@ impl GeminiService {
         })
     }
 
-    pub async fn generate_response(&self, user_query: &str, context: &str) -> AppResult<String> {
+    // Send a single-turn request, retrying rate limits and unavailable errors
+    async fn generate_text(&self, prompt: &str, operation: &'static str) -> AppResult<String> {
+        let response = with_retry(Upstream::Gemini, operation, || async {
+            self.client
+                .generate_content()
+                .with_user_message(prompt)
+                .execute()
+                .await
+                .map_err(|e| crate::errors::AppError::Other(format!("Gemini API error: {}", e)))
+        })
+        .await?;
+
+        Ok(response.text())
+    }
+
+    // Generate an answer for a fully rendered prompt
+    #[tracing::instrument(name = "gemini.generate", skip_all)]
+    pub async fn generate_response(&self, prompt: &str) -> AppResult<String> {
+        tracing::info!("Sending request to Gemini API");
+
+        let response_text = self.generate_text(prompt, "generate").await?;
+        tracing::info!("✅ Generated response from Gemini API");
+
+        Ok(response_text)
+    }
+
+    // Turn a follow-up question into one that can be searched without the conversation
+    #[tracing::instrument(name = "gemini.rewrite_query", skip_all)]
+    pub async fn rewrite_query(&self, conversation_history: &str, user_query: &str) -> AppResult<String> {
         let prompt = format!(
-            "You are a helpful AI assistant. Based on the following context, please answer the user's question. If the context doesn't contain enough information to answer the question, please say so.\n\nContext:\n{}\n\nUser Question: {}\n\nAnswer:",
-            context,
+            "Rewrite the follow-up question so it can be understood without the conversation, resolving references like \"it\" or \"the second one\". Keep the original language. If it is already standalone, repeat it unchanged. Reply with the question only.\n\nConversation:\n{}\n\nFollow-up question: {}\n\nStandalone question:",
+            conversation_history,
             user_query
         );
 
-        tracing::info!("Sending request to Gemini API");
+        self.generate_text(&prompt, "rewrite_query").await
+    }
 
-        let response = self.client
-            .generate_content()
-            .with_user_message(&prompt)
-            .execute()
-            .await
-            .map_err(|e| crate::errors::AppError::Other(format!("Gemini API error: {}", e)))?;
+    // Write `count` differently worded versions of a question to search with, one per line
+    #[tracing::instrument(name = "gemini.paraphrase_query", skip_all)]
+    pub async fn paraphrase_query(&self, user_query: &str, count: usize) -> AppResult<String> {
+        let prompt = format!(
+            "Write {} different ways to ask the question below, to search a knowledge base with. Vary the wording, use synonyms and spell out abbreviations, but keep the meaning and the original language. Reply with one question per line and nothing else.\n\nQuestion: {}",
+            count,
+            user_query
+        );
 
-        let response_text = response.text();
-        tracing::info!("✅ Generated response from Gemini API");
+        self.generate_text(&prompt, "paraphrase_query").await
+    }
 
-        Ok(response_text)
+    // Translate text, leaving the ⟦n⟧ citation placeholders where they are
+    #[tracing::instrument(name = "gemini.translate", skip(self, text))]
+    pub async fn translate(&self, text: &str, language: &str) -> AppResult<String> {
+        let prompt = format!(
+            "Translate the text below into {}. Keep every placeholder of the form ⟦n⟧ exactly as written and in the matching position. Keep document names, product names, code and numbers unchanged. Preserve the formatting. Reply with the translation only.\n\nText:\n{}",
+            language,
+            text
+        );
+
+        self.generate_text(&prompt, "translate").await
     }
 
-    pub async fn generate_response_stream(
+    // Label a user message's sentiment and intent, replying `sentiment,intent`
+    #[tracing::instrument(name = "gemini.classify_message", skip_all)]
+    pub async fn classify_message(&self, message: &str) -> AppResult<String> {
+        let prompt = format!(
+            "Classify the customer's message to a support chatbot. Reply with exactly two lowercase words separated by a comma and nothing else: the sentiment (positive, neutral, negative or frustrated) and the intent (question, complaint, praise, escalation or other). Use frustrated for heated or repeated complaints, and escalation when they ask for a person.\n\nMessage:\n{}",
+            message
+        );
+
+        self.generate_text(&prompt, "classify_message").await
+    }
+
+    // Write one read-only query over the described tables that answers the question, or NONE
+    #[tracing::instrument(name = "gemini.generate_sql", skip_all)]
+    pub async fn generate_sql(&self, schema: &str, question: &str) -> AppResult<String> {
+        let prompt = format!(
+            "Write one PostgreSQL SELECT query that answers the question using only the tables below. Do not modify data, use other tables or system catalogs, or add comments. Prefer aggregates over listing rows, and return at most 50 rows. If the tables can't answer the question, reply with exactly NONE. Reply with the SQL only.\n\nTables:\n{}\n\nQuestion: {}\n\nSQL:",
+            schema,
+            question
+        );
+
+        self.generate_text(&prompt, "generate_sql").await
+    }
+
+    // Grade a chatbot's answer against the context it was given and the answer expected, replying
+    // with a JSON object of scores
+    #[tracing::instrument(name = "gemini.judge_answer", skip_all)]
+    pub async fn judge_answer(
         &self,
-        user_query: &str,
+        question: &str,
+        expected_answer: &str,
         context: &str,
-    ) -> AppResult<Pin<Box<dyn Stream<Item = AppResult<StreamingChunk>> + Send>>> {
+        answer: &str,
+    ) -> AppResult<String> {
         let prompt = format!(
-            "You are a helpful AI assistant. Based on the following context, please answer the user's question. If the context doesn't contain enough information to answer the question, please say so.\n\nContext:\n{}\n\nUser Question: {}\n\nAnswer:",
+            "You are grading a support chatbot's answer. Score it from 0 to 1 on two criteria. faithfulness: how much of the answer is supported by the context, where 1 means every claim is backed by it and 0 means it is made up. relevance: how well the answer addresses the question and agrees with the expected answer, where 1 means it gives the same information and 0 means it misses or contradicts it. Reply with only a JSON object like {{\"faithfulness\": 0.8, \"relevance\": 0.9, \"explanation\": \"one sentence\"}}.\n\nQuestion: {}\n\nExpected answer: {}\n\nContext:\n{}\n\nChatbot answer: {}",
+            question,
+            expected_answer,
             context,
-            user_query
+            answer
         );
 
-        tracing::info!("Starting streaming request to Gemini API");
+        self.generate_text(&prompt, "judge_answer").await
+    }
+
+    // Ask the model to copy out only the sentences relevant to the question
+    #[tracing::instrument(name = "gemini.extract_relevant_sentences", skip_all)]
+    pub async fn extract_relevant_sentences(&self, user_query: &str, chunk: &str) -> AppResult<Option<String>> {
+        let prompt = format!(
+            "Copy, word for word, only the sentences from the passage below that help answer the question. Do not add, rephrase or explain anything. If no sentence is relevant, reply with exactly NONE.\n\nQuestion: {}\n\nPassage:\n{}\n\nRelevant sentences:",
+            user_query,
+            chunk
+        );
+
+        let response = self.generate_text(&prompt, "extract_relevant_sentences").await?;
+
+        let text = response.trim().to_string();
+        if text.is_empty() || text == "NONE" {
+            Ok(None)
+        } else {
+            Ok(Some(text))
+        }
+    }
+
+    pub async fn generate_response_stream(
+        &self,
+        prompt: &str,
+    ) -> AppResult<Pin<Box<dyn Stream<Item = AppResult<StreamingChunk>> + Send>>> {
+        // The stream is polled after the handler returns, so carry the span along explicitly
+        let span = tracing::info_span!("gemini.generate_stream");
+        span.in_scope(|| tracing::info!("Starting streaming request to Gemini API"));
 
-        let gemini_stream = self.client
-            .generate_content()
-            .with_user_message(&prompt)
-            .execute_stream()
-            .await
-            .map_err(|e| crate::errors::AppError::Other(format!("Failed to start Gemini streaming: {}", e)))?;
+        // Only opening the stream is retried; chunks may already have been sent when a later read fails
+        let gemini_stream = with_retry(Upstream::Gemini, "generate_stream", || async {
+            self.client
+                .generate_content()
+                .with_user_message(prompt)
+                .execute_stream()
+                .await
+                .map_err(|e| crate::errors::AppError::Other(format!("Failed to start Gemini streaming: {}", e)))
+        })
+        .instrument(span.clone())
+        .await?;
 
-        let stream = stream::unfold(gemini_stream, |mut stream| async move {
+        let stream = stream::unfold(gemini_stream, move |mut stream| async move {
             match stream.try_next().await {
                 Ok(Some(chunk)) => {
                     let chunk_text = chunk.text();
@@ -104,7 +209,7 @@ impl GeminiService {
                     ))
                 }
             }
-        });
+        }.instrument(span.clone()));
 
         Ok(Box::pin(stream))
     }
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::db::models::SearchableChatbot;
+use crate::services::index_lifecycle::parse_chatbot_index;
+use crate::services::sharding::shard_indices;
+use crate::services::vector::{chatbot_index_name, SearchResult};
+
+/// Hits returned by a search across chatbots when no limit is given, and the most allowed
+pub const DEFAULT_GLOBAL_SEARCH_RESULTS: u64 = 20;
+pub const MAX_GLOBAL_SEARCH_RESULTS: u64 = 100;
+
+/// One chatbot's hits in a search across chatbots
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatbotHits {
+    pub chatbot_id: Uuid,
+    pub organization_id: Uuid,
+    pub name: String,
+    /// Score of its best hit
+    pub best_score: f32,
+    pub results: Vec<SearchResult>,
+}
+
+/// Every shard index of the chatbots, to be searched together
+pub fn chatbot_indices(chatbots: &[SearchableChatbot]) -> Vec<String> {
+    chatbots
+        .iter()
+        .flat_map(|chatbot| shard_indices(&chatbot_index_name(chatbot.organization_id, chatbot.id), chatbot.shard_count))
+        .collect()
+}
+
+/// Hits grouped by the chatbot whose index they came from, the chatbot with the best hit first.
+/// Hits are expected best first; ones from indices of chatbots not searched are dropped
+pub fn group_by_chatbot(chatbots: &[SearchableChatbot], hits: Vec<(String, SearchResult)>) -> Vec<ChatbotHits> {
+    let by_id: HashMap<Uuid, &SearchableChatbot> = chatbots.iter().map(|chatbot| (chatbot.id, chatbot)).collect();
+    let mut groups: Vec<ChatbotHits> = Vec::new();
+    for (index_name, result) in hits {
+        let Some(chatbot) = parse_chatbot_index(&index_name).and_then(|id| by_id.get(&id)) else {
+            continue;
+        };
+        match groups.iter_mut().find(|group| group.chatbot_id == chatbot.id) {
+            Some(group) => {
+                group.best_score = group.best_score.max(result.score);
+                group.results.push(result);
+            }
+            None => groups.push(ChatbotHits {
+                chatbot_id: chatbot.id,
+                organization_id: chatbot.organization_id,
+                name: chatbot.name.clone(),
+                best_score: result.score,
+                results: vec![result],
+            }),
+        }
+    }
+    groups.sort_by(|a, b| b.best_score.total_cmp(&a.best_score));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chatbot(id: u128, shard_count: i32) -> SearchableChatbot {
+        SearchableChatbot {
+            id: Uuid::from_u128(id),
+            organization_id: Uuid::from_u128(100),
+            name: format!("bot {}", id),
+            shard_count,
+        }
+    }
+
+    fn hit(index_name: String, file_path: &str, score: f32) -> (String, SearchResult) {
+        let result = SearchResult {
+            text: String::new(),
+            score,
+            chunk_index: 0,
+            file_path: file_path.to_string(),
+            parent_index: None,
+        };
+        (index_name, result)
+    }
+
+    #[test]
+    fn test_indices_cover_every_shard() {
+        let indices = chatbot_indices(&[chatbot(1, 2), chatbot(2, 1)]);
+        assert_eq!(indices.len(), 3);
+        assert!(indices.iter().all(|name| parse_chatbot_index(name).is_some()));
+    }
+
+    #[test]
+    fn test_hits_grouped_by_chatbot() {
+        let chatbots = [chatbot(1, 2), chatbot(2, 1)];
+        let indices = chatbot_indices(&chatbots);
+        let hits = vec![
+            hit(indices[2].clone(), "pricing.md", 0.9),
+            hit(format!("{}_v2", indices[1]), "faq.md", 0.8),
+            hit(indices[0].clone(), "setup.md", 0.7),
+            hit(indices[2].clone(), "refunds.md", 0.6),
+        ];
+
+        let groups = group_by_chatbot(&chatbots, hits);
+        let ids: Vec<Uuid> = groups.iter().map(|group| group.chatbot_id).collect();
+        assert_eq!(ids, [Uuid::from_u128(2), Uuid::from_u128(1)]);
+        assert_eq!(groups[0].results.len(), 2);
+        // A reindexed shard's hits still count for its chatbot
+        let files: Vec<&str> = groups[1].results.iter().map(|result| result.file_path.as_str()).collect();
+        assert_eq!(files, ["faq.md", "setup.md"]);
+        assert_eq!(groups[1].best_score, 0.8);
+    }
+
+    #[test]
+    fn test_unknown_indices_dropped() {
+        let other = chatbot_indices(&[chatbot(3, 1)]);
+        let hits = vec![hit("embedding_cache".to_string(), "a.md", 0.9), hit(other[0].clone(), "b.md", 0.8)];
+        assert!(group_by_chatbot(&[chatbot(1, 1)], hits).is_empty());
+    }
+}
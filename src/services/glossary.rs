@@ -0,0 +1,95 @@
+use crate::db::models::GlossaryEntry;
+
+/// Lowercased alphanumeric words of `text`
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Whether `phrase` occurs in `query_words` as a run of whole words
+fn contains_phrase(query_words: &[String], phrase: &str) -> bool {
+    let phrase_words = words(phrase);
+    !phrase_words.is_empty()
+        && query_words
+            .windows(phrase_words.len())
+            .any(|window| window == phrase_words.as_slice())
+}
+
+/// Glossary entries whose term or one of its aliases appears in the query
+pub fn matching_entries<'a>(query: &str, entries: &'a [GlossaryEntry]) -> Vec<&'a GlossaryEntry> {
+    let query_words = words(query);
+
+    entries
+        .iter()
+        .filter(|entry| {
+            contains_phrase(&query_words, &entry.term)
+                || entry.aliases.iter().any(|alias| contains_phrase(&query_words, alias))
+        })
+        .collect()
+}
+
+/// Render matched entries as a prompt section; empty when nothing matched
+pub fn format_glossary(entries: &[&GlossaryEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| format!("- {}: {}", entry.term, entry.definition))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("Glossary (always use these terms as defined):\n{}\n\n", lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn entry(term: &str, aliases: &[&str]) -> GlossaryEntry {
+        GlossaryEntry {
+            id: Uuid::new_v4(),
+            chatbot_id: Uuid::new_v4(),
+            term: term.to_string(),
+            definition: format!("definition of {}", term),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_matches_whole_words_and_aliases() {
+        let entries = vec![
+            entry("Workspace", &[]),
+            entry("Single Sign-On", &["SSO"]),
+            entry("API", &[]),
+        ];
+
+        let matched = matching_entries("How do I enable sso for my workspace?", &entries);
+        let terms: Vec<&str> = matched.iter().map(|e| e.term.as_str()).collect();
+
+        assert_eq!(terms, vec!["Workspace", "Single Sign-On"]);
+        assert!(matching_entries("Where is the rapid setup guide?", &entries).is_empty());
+    }
+
+    #[test]
+    fn test_multi_word_terms_match_across_punctuation() {
+        let entries = vec![entry("Single Sign-On", &[])];
+        assert_eq!(matching_entries("Does single sign on work?", &entries).len(), 1);
+    }
+
+    #[test]
+    fn test_format_glossary() {
+        let entries = vec![entry("Workspace", &[])];
+        let matched: Vec<&GlossaryEntry> = entries.iter().collect();
+
+        assert_eq!(format_glossary(&[]), "");
+        assert!(format_glossary(&matched).contains("- Workspace: definition of Workspace\n"));
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+const DEFAULT_SESSION_TTL_SECS: i64 = 1800;
+const VERIFY_TIMEOUT_SECS: u64 = 10;
+
+/// Service that checks the CAPTCHA solved before a guest session is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    Turnstile,
+    HCaptcha,
+}
+
+impl CaptchaProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptchaProvider::Turnstile => "turnstile",
+            CaptchaProvider::HCaptcha => "hcaptcha",
+        }
+    }
+
+    fn verify_url(self) -> &'static str {
+        match self {
+            CaptchaProvider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+        }
+    }
+}
+
+impl FromStr for CaptchaProvider {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "turnstile" => Ok(CaptchaProvider::Turnstile),
+            "hcaptcha" => Ok(CaptchaProvider::HCaptcha),
+            other => Err(format!("unknown CAPTCHA provider '{}'; use turnstile or hcaptcha", other)),
+        }
+    }
+}
+
+impl fmt::Display for CaptchaProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Reply of either provider's siteverify endpoint
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Checks CAPTCHA tokens with the provider's siteverify endpoint
+#[derive(Debug, Clone)]
+pub struct CaptchaVerifier {
+    pub provider: CaptchaProvider,
+    secret: String,
+}
+
+impl CaptchaVerifier {
+    pub fn new(provider: CaptchaProvider, secret: String) -> Self {
+        Self { provider, secret }
+    }
+
+    /// Whether the provider accepts the token a visitor's widget produced
+    pub async fn verify(&self, token: &str, remote_ip: Option<&str>) -> Result<bool> {
+        let mut form = vec![("secret", self.secret.as_str()), ("response", token)];
+        if let Some(remote_ip) = remote_ip {
+            form.push(("remoteip", remote_ip));
+        }
+
+        let response: SiteVerifyResponse = Client::builder()
+            .timeout(Duration::from_secs(VERIFY_TIMEOUT_SECS))
+            .build()?
+            .post(self.provider.verify_url())
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if !response.success {
+            tracing::warn!("CAPTCHA rejected by {}: {:?}", self.provider, response.error_codes);
+        }
+        Ok(response.success)
+    }
+}
+
+/// Settings for anonymous guest sessions
+#[derive(Debug, Clone)]
+pub struct GuestConfig {
+    /// Seconds a guest token stays valid, from `GUEST_SESSION_TTL_SECS`
+    pub session_ttl_secs: i64,
+    /// Unset when `CAPTCHA_PROVIDER` is unset; guest sessions are then opened without a CAPTCHA
+    pub captcha: Option<CaptchaVerifier>,
+}
+
+impl GuestConfig {
+    /// Fails if `CAPTCHA_PROVIDER` names an unknown provider or `CAPTCHA_SECRET` is missing
+    pub fn from_env() -> Result<Self> {
+        let captcha = match std::env::var("CAPTCHA_PROVIDER") {
+            Ok(provider) if !provider.trim().is_empty() => {
+                let provider: CaptchaProvider = provider.trim().parse().map_err(anyhow::Error::msg)?;
+                let secret = std::env::var("CAPTCHA_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty())
+                    .ok_or_else(|| anyhow::anyhow!("CAPTCHA_SECRET must be set when CAPTCHA_PROVIDER is set"))?;
+                Some(CaptchaVerifier::new(provider, secret))
+            }
+            _ => None,
+        };
+        let session_ttl_secs = std::env::var("GUEST_SESSION_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|v: &i64| *v > 0)
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+
+        Ok(Self { session_ttl_secs, captcha })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_parses_case_insensitively() {
+        assert_eq!("turnstile".parse::<CaptchaProvider>(), Ok(CaptchaProvider::Turnstile));
+        assert_eq!("hCaptcha".parse::<CaptchaProvider>(), Ok(CaptchaProvider::HCaptcha));
+        assert!("recaptcha".parse::<CaptchaProvider>().is_err());
+    }
+
+    #[test]
+    fn test_siteverify_response() {
+        let rejected: SiteVerifyResponse =
+            serde_json::from_str(r#"{"success": false, "error-codes": ["invalid-input-response"]}"#).unwrap();
+        assert!(!rejected.success);
+        assert_eq!(rejected.error_codes, vec!["invalid-input-response"]);
+
+        let accepted: SiteVerifyResponse = serde_json::from_str(r#"{"success": true, "hostname": "x"}"#).unwrap();
+        assert!(accepted.success);
+        assert!(accepted.error_codes.is_empty());
+    }
+}
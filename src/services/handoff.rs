@@ -0,0 +1,196 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::models::{ChatBot, ConversationExport};
+use crate::db::queries::{list_conversation_exports, mark_chat_escalated};
+use crate::errors::AppResult;
+use crate::services::ingest_webhook::{sign, SIGNATURE_HEADER};
+use crate::services::public_url::public_client_builder;
+use crate::services::sentiment::{classify_lexicon, Intent};
+use crate::services::shutdown::BackgroundJobs;
+
+static CONFIG: LazyLock<HandoffConfig> = LazyLock::new(HandoffConfig::from_env);
+
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_NEGATIVE_FEEDBACK: i64 = 2;
+const DEFAULT_MESSAGE: &str = "I've passed this conversation to our team. Someone will reply here shortly.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationReason {
+    /// The user asked for a person
+    Requested,
+    /// Enough of the chat's answers were rated thumbs-down
+    NegativeFeedback,
+}
+
+impl EscalationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EscalationReason::Requested => "requested",
+            EscalationReason::NegativeFeedback => "negative_feedback",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HandoffConfig {
+    negative_feedback: i64,
+    message: String,
+}
+
+impl HandoffConfig {
+    // `HANDOFF_NEGATIVE_FEEDBACK` (default 2, 0 disables) and `HANDOFF_MESSAGE`
+    fn from_env() -> Self {
+        Self {
+            negative_feedback: std::env::var("HANDOFF_NEGATIVE_FEEDBACK")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_NEGATIVE_FEEDBACK),
+            message: std::env::var("HANDOFF_MESSAGE")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+        }
+    }
+}
+
+/// A chatbot's handoff webhook: escalated chats are sent here with their transcript
+#[derive(Debug, Clone)]
+pub struct Handoff {
+    pub chatbot_id: Uuid,
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct HandoffEvent<'a> {
+    event: &'static str,
+    organization_id: Uuid,
+    chatbot_id: Uuid,
+    chat_id: Uuid,
+    reason: &'static str,
+    escalated_at: DateTime<Utc>,
+    transcript: &'a [ConversationExport],
+}
+
+impl Handoff {
+    pub fn for_chatbot(chatbot: &ChatBot) -> Option<Self> {
+        chatbot.handoff_webhook_url.as_ref().map(|url| Self {
+            chatbot_id: chatbot.id,
+            url: url.clone(),
+            secret: chatbot.handoff_webhook_secret.clone(),
+        })
+    }
+
+    async fn notify(&self, event: &HandoffEvent<'_>) -> Result<()> {
+        let timeout_secs = std::env::var("HANDOFF_WEBHOOK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        let body = serde_json::to_vec(event)?;
+        let mut request = public_client_builder()
+            .build()?
+            .post(&self.url)
+            .timeout(Duration::from_secs(timeout_secs))
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+
+        request.body(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Whether a message asks to talk to a person instead of the bot
+pub fn wants_human(message: &str) -> bool {
+    classify_lexicon(message).intent == Intent::Escalation
+}
+
+/// Whether this many thumbs-down ratings in one chat should hand it off
+pub fn feedback_escalates(thumbs_down: i64) -> bool {
+    CONFIG.negative_feedback > 0 && thumbs_down >= CONFIG.negative_feedback
+}
+
+/// Reply stored instead of a bot answer while a chat is escalated
+pub fn handoff_message() -> &'static str {
+    &CONFIG.message
+}
+
+/// Mark the chat as escalated and send its transcript to the handoff webhook in the background.
+/// Returns false when the chat was already escalated
+pub async fn escalate_chat(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    handoff: Handoff,
+    organization_id: Uuid,
+    chat_id: Uuid,
+    reason: EscalationReason,
+) -> AppResult<bool> {
+    let Some(chat) = mark_chat_escalated(&db, organization_id, chat_id, reason.as_str()).await? else {
+        return Ok(false);
+    };
+    tracing::info!("Chat {} escalated to a person ({})", chat_id, reason.as_str());
+
+    let escalated_at = chat.escalated_at.unwrap_or_else(Utc::now);
+    jobs.spawn(async move {
+        let transcript = match list_conversation_exports(&db, organization_id, chat_id).await {
+            Ok(transcript) => transcript,
+            Err(e) => {
+                tracing::error!("❌ Failed to load transcript for handoff of chat {}: {}", chat_id, e);
+                return;
+            }
+        };
+        let event = HandoffEvent {
+            event: "chat.escalated",
+            organization_id,
+            chatbot_id: handoff.chatbot_id,
+            chat_id,
+            reason: reason.as_str(),
+            escalated_at,
+            transcript: &transcript,
+        };
+        match handoff.notify(&event).await {
+            Ok(()) => tracing::info!("✅ Handoff webhook notified for chat {}", chat_id),
+            Err(e) => tracing::error!("❌ Handoff webhook failed for chat {}: {}", chat_id, e),
+        }
+    });
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wants_human() {
+        assert!(wants_human("Can I talk to someone please?"));
+        assert!(wants_human("This is useless, get me a real person"));
+        assert!(!wants_human("How do I reset my password?"));
+        assert!(!wants_human("Humane societies near me"));
+    }
+
+    #[test]
+    fn test_event_payload() {
+        let event = HandoffEvent {
+            event: "chat.escalated",
+            organization_id: Uuid::nil(),
+            chatbot_id: Uuid::nil(),
+            chat_id: Uuid::nil(),
+            reason: EscalationReason::NegativeFeedback.as_str(),
+            escalated_at: Utc::now(),
+            transcript: &[],
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], "chat.escalated");
+        assert_eq!(json["reason"], "negative_feedback");
+        assert_eq!(json["transcript"], serde_json::json!([]));
+    }
+}
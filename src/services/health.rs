@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use crate::db::queries::ping_database;
+use crate::services::vector::{VectorBackend, VectorStore};
+
+const DEFAULT_CACHE_SECS: u64 = 10;
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+// Listing one model is free and proves the API key works
+const GEMINI_MODELS_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyState {
+    Up,
+    Down,
+}
+
+/// Result of pinging one dependency
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DependencyStatus {
+    pub status: DependencyState,
+    pub latency_ms: u64,
+    /// Whether the server stops being ready while this dependency is down
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    /// Every dependency is up
+    Ready,
+    /// Only optional dependencies are down; requests that need them will fail
+    Degraded,
+    /// A required dependency is down
+    NotReady,
+}
+
+/// Readiness report returned by `/health/ready`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: ReadinessStatus,
+    pub checked_at: DateTime<Utc>,
+    /// True when this is a recent report reused instead of pinging again
+    pub cached: bool,
+    pub dependencies: BTreeMap<String, DependencyStatus>,
+}
+
+impl Readiness {
+    fn new(dependencies: BTreeMap<String, DependencyStatus>, checked_at: DateTime<Utc>) -> Self {
+        let any_down = |required: bool| {
+            dependencies
+                .values()
+                .any(|dependency| dependency.required == required && dependency.status == DependencyState::Down)
+        };
+        let status = if any_down(true) {
+            ReadinessStatus::NotReady
+        } else if any_down(false) {
+            ReadinessStatus::Degraded
+        } else {
+            ReadinessStatus::Ready
+        };
+        Self { status, checked_at, cached: false, dependencies }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status != ReadinessStatus::NotReady
+    }
+}
+
+struct CachedReadiness {
+    readiness: Readiness,
+    checked: Instant,
+}
+
+/// Pings the database, vector store and LLM provider, reusing the last report for a few seconds
+/// so frequent probes don't load the dependencies
+pub struct HealthChecker {
+    http: reqwest::Client,
+    cache_ttl: Duration,
+    timeout: Duration,
+    last: Mutex<Option<CachedReadiness>>,
+}
+
+impl HealthChecker {
+    /// Reads `HEALTH_CACHE_SECS` (default 10) and `HEALTH_CHECK_TIMEOUT_MS` (default 2000)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let env_u64 = |name: &str, default: u64| -> anyhow::Result<u64> {
+            match std::env::var(name) {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("{} must be a non-negative number, got '{}'", name, value)),
+                Err(_) => Ok(default),
+            }
+        };
+        let timeout = Duration::from_millis(env_u64("HEALTH_CHECK_TIMEOUT_MS", DEFAULT_TIMEOUT_MS)?.max(1));
+
+        Ok(Self {
+            http: reqwest::Client::builder().timeout(timeout).build()?,
+            cache_ttl: Duration::from_secs(env_u64("HEALTH_CACHE_SECS", DEFAULT_CACHE_SECS)?),
+            timeout,
+            last: Mutex::new(None),
+        })
+    }
+
+    pub async fn readiness(&self, db: &PgPool, vector_store: &VectorBackend) -> Readiness {
+        // Holding the lock while checking makes concurrent probes share one round of pings
+        let mut last = self.last.lock().await;
+        if let Some(cached) = last.as_ref()
+            && cached.checked.elapsed() < self.cache_ttl
+        {
+            return Readiness { cached: true, ..cached.readiness.clone() };
+        }
+
+        let (database, vector, llm) = tokio::join!(
+            self.check("database", true, ping_database(db)),
+            self.check("vector_store", true, vector_store.ping()),
+            // Without the LLM, search and admin still work, so it only degrades readiness
+            self.check("llm", false, self.ping_gemini()),
+        );
+        let dependencies = BTreeMap::from([
+            ("database".to_string(), database),
+            ("vector_store".to_string(), vector),
+            ("llm".to_string(), llm),
+        ]);
+        let readiness = Readiness::new(dependencies, Utc::now());
+
+        *last = Some(CachedReadiness { readiness: readiness.clone(), checked: Instant::now() });
+        readiness
+    }
+
+    async fn check<E: Display>(
+        &self,
+        name: &str,
+        required: bool,
+        ping: impl Future<Output = Result<(), E>>,
+    ) -> DependencyStatus {
+        let started = Instant::now();
+        let outcome = tokio::time::timeout(self.timeout, ping).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let status = match outcome {
+            Ok(Ok(())) => DependencyState::Up,
+            Ok(Err(e)) => {
+                tracing::warn!("⚠️ Readiness check for {} failed: {}", name, e);
+                DependencyState::Down
+            }
+            Err(_) => {
+                tracing::warn!("⚠️ Readiness check for {} timed out after {:?}", name, self.timeout);
+                DependencyState::Down
+            }
+        };
+        DependencyStatus { status, latency_ms, required }
+    }
+
+    async fn ping_gemini(&self) -> anyhow::Result<()> {
+        let api_key = std::env::var("GEMINI_API_KEY").map_err(|_| anyhow::anyhow!("GEMINI_API_KEY is not set"))?;
+        let response = self.http.get(GEMINI_MODELS_URL).header("x-goog-api-key", api_key).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Gemini returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readiness(dependencies: &[(&str, bool, DependencyState)]) -> Readiness {
+        let dependencies = dependencies
+            .iter()
+            .map(|(name, required, status)| {
+                (name.to_string(), DependencyStatus { status: *status, latency_ms: 1, required: *required })
+            })
+            .collect();
+        Readiness::new(dependencies, Utc::now())
+    }
+
+    #[test]
+    fn test_ready_when_everything_is_up() {
+        let report = readiness(&[("database", true, DependencyState::Up), ("llm", false, DependencyState::Up)]);
+        assert_eq!(report.status, ReadinessStatus::Ready);
+        assert!(report.is_ready());
+    }
+
+    #[test]
+    fn test_optional_dependency_down_degrades() {
+        let report = readiness(&[("database", true, DependencyState::Up), ("llm", false, DependencyState::Down)]);
+        assert_eq!(report.status, ReadinessStatus::Degraded);
+        assert!(report.is_ready());
+    }
+
+    #[test]
+    fn test_required_dependency_down_is_not_ready() {
+        let report = readiness(&[("database", true, DependencyState::Down), ("llm", false, DependencyState::Down)]);
+        assert_eq!(report.status, ReadinessStatus::NotReady);
+        assert!(!report.is_ready());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "not_ready");
+        assert_eq!(json["dependencies"]["database"]["status"], "down");
+    }
+}
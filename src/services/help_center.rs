@@ -0,0 +1,458 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::db::models::HelpCenterConnector;
+use crate::utils::pdf::chunk_text;
+
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+// Stop paging after this many pages, in case an API keeps returning a next page
+const MAX_PAGES: usize = 500;
+const INTERCOM_API: &str = "https://api.intercom.io";
+const INTERCOM_VERSION: &str = "2.11";
+const INTERCOM_PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpCenterProvider {
+    Zendesk,
+    Intercom,
+}
+
+impl HelpCenterProvider {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "zendesk" => Some(Self::Zendesk),
+            "intercom" => Some(Self::Intercom),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zendesk => "zendesk",
+            Self::Intercom => "intercom",
+        }
+    }
+}
+
+/// Zendesk subdomains are letters, digits and hyphens; anything else could point requests at
+/// another host
+pub fn is_valid_subdomain(subdomain: &str) -> bool {
+    !subdomain.is_empty()
+        && subdomain.len() <= 63
+        && subdomain.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        && !subdomain.starts_with('-')
+        && !subdomain.ends_with('-')
+}
+
+/// Document path for an article's chunks, so a re-sync replaces them
+pub fn article_path(provider: HelpCenterProvider, article_id: &str) -> String {
+    format!("helpcenter:{}/{}", provider.as_str(), article_id)
+}
+
+/// A published help-center article, with its body as plain text
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpArticle {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    /// Name of the section (Zendesk) or collection (Intercom) the article is in
+    pub section: Option<String>,
+    pub body: String,
+}
+
+impl HelpArticle {
+    /// Chunk the body, starting every chunk with the title, section and URL so they are
+    /// searchable and show up in citations
+    pub fn chunks(&self, chunk_size: usize, overlap: usize) -> Vec<String> {
+        let mut header = format!("Title: {}\n", self.title);
+        if let Some(section) = &self.section {
+            header.push_str(&format!("Section: {}\n", section));
+        }
+        if let Some(url) = &self.url {
+            header.push_str(&format!("URL: {}\n", url));
+        }
+        header.push('\n');
+
+        chunk_text(&self.body, chunk_size, overlap)
+            .into_iter()
+            .map(|chunk| format!("{}{}", header, chunk))
+            .collect()
+    }
+}
+
+/// Articles changed since the last sync
+#[derive(Debug, Default)]
+pub struct ArticleChanges {
+    /// Published articles to (re)index
+    pub published: Vec<HelpArticle>,
+    /// Ids of articles that were unpublished or emptied, whose chunks should go
+    pub unpublished: Vec<String>,
+}
+
+// Block elements whose boundaries become line breaks
+const BLOCK_TAGS: &[&str] = &[
+    "p", "br", "div", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "blockquote", "table",
+];
+
+/// Reduce article HTML to plain text: tags are dropped, block elements end lines, script and style
+/// contents are skipped and the common entities are decoded
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut skip_until: Option<&str> = None;
+
+    while let Some(start) = rest.find('<') {
+        // Line breaks in the source are just whitespace; block elements decide where lines end
+        if skip_until.is_none() {
+            text.push_str(&rest[..start].replace('\n', " "));
+        }
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        rest = &rest[start + end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+
+        if let Some(until) = skip_until {
+            if closing && name == until {
+                skip_until = None;
+            }
+            continue;
+        }
+        match name.as_str() {
+            "script" if !closing => skip_until = Some("script"),
+            "style" if !closing => skip_until = Some("style"),
+            _ if BLOCK_TAGS.contains(&name.as_str()) => text.push('\n'),
+            _ => {}
+        }
+    }
+    if skip_until.is_none() {
+        text.push_str(&rest.replace('\n', " "));
+    }
+
+    let text = decode_entities(&text);
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Decode the HTML entities common in article bodies
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[derive(Debug, Deserialize)]
+struct ZendeskArticle {
+    id: u64,
+    title: String,
+    html_url: Option<String>,
+    body: Option<String>,
+    section_id: Option<u64>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZendeskArticlePage {
+    #[serde(default)]
+    articles: Vec<ZendeskArticle>,
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZendeskSection {
+    id: u64,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZendeskSectionPage {
+    #[serde(default)]
+    sections: Vec<ZendeskSection>,
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntercomArticle {
+    id: Value,
+    title: String,
+    url: Option<String>,
+    body: Option<String>,
+    state: String,
+    updated_at: i64,
+    parent_id: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntercomCollection {
+    id: Value,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntercomPages {
+    total_pages: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntercomPage<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+    pages: Option<IntercomPages>,
+}
+
+// Intercom ids come back as strings or numbers depending on the object
+fn id_string(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A chatbot's help-center connector, ready to fetch from
+#[derive(Debug, Clone)]
+pub struct HelpCenterSource {
+    pub provider: HelpCenterProvider,
+    pub subdomain: Option<String>,
+    pub email: Option<String>,
+    pub api_token: String,
+}
+
+impl HelpCenterSource {
+    /// None when the stored provider is unknown
+    pub fn from_connector(connector: &HelpCenterConnector) -> Option<Self> {
+        Some(Self {
+            provider: HelpCenterProvider::parse(&connector.provider)?,
+            subdomain: connector.subdomain.clone(),
+            email: connector.email.clone(),
+            api_token: connector.api_token.clone(),
+        })
+    }
+
+    /// Human-readable source name, also used to route the articles to a shard
+    pub fn name(&self) -> String {
+        match (self.provider, &self.subdomain) {
+            (HelpCenterProvider::Zendesk, Some(subdomain)) => format!("zendesk:{}", subdomain),
+            (provider, _) => provider.as_str().to_string(),
+        }
+    }
+
+    /// Fetch the articles changed since `since`, or every article when it is None
+    pub async fn fetch_changes(&self, since: Option<DateTime<Utc>>) -> Result<ArticleChanges> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()?;
+        match self.provider {
+            HelpCenterProvider::Zendesk => self.fetch_zendesk(&client, since).await,
+            HelpCenterProvider::Intercom => self.fetch_intercom(&client, since).await,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, client: &reqwest::Client, url: &str) -> Result<T> {
+        let request = match self.provider {
+            HelpCenterProvider::Zendesk => {
+                let email = self.email.as_deref().unwrap_or_default();
+                client.get(url).basic_auth(format!("{}/token", email), Some(&self.api_token))
+            }
+            HelpCenterProvider::Intercom => client
+                .get(url)
+                .bearer_auth(&self.api_token)
+                .header("Intercom-Version", INTERCOM_VERSION),
+        };
+        let response = request.header(reqwest::header::ACCEPT, "application/json").send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("{} returned {}", url, response.status()));
+        }
+        Ok(response.json().await?)
+    }
+
+    // Zendesk's incremental export returns every article changed since `start_time`, drafts included
+    async fn fetch_zendesk(&self, client: &reqwest::Client, since: Option<DateTime<Utc>>) -> Result<ArticleChanges> {
+        let subdomain = self
+            .subdomain
+            .as_deref()
+            .filter(|subdomain| is_valid_subdomain(subdomain))
+            .ok_or_else(|| anyhow::anyhow!("Zendesk connector needs a valid subdomain"))?;
+        let base = format!("https://{}.zendesk.com/api/v2/help_center", subdomain);
+
+        let mut sections = HashMap::new();
+        let mut next = Some(format!("{}/sections.json?per_page=100", base));
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let page: ZendeskSectionPage = self.get(client, &url).await?;
+            sections.extend(page.sections.into_iter().map(|section| (section.id, section.name)));
+            next = page.next_page.filter(|next_url| *next_url != url);
+        }
+
+        let start_time = since.map(|since| since.timestamp()).unwrap_or(0);
+        let mut changes = ArticleChanges::default();
+        let mut next = Some(format!("{}/incremental/articles.json?start_time={}", base, start_time));
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let page: ZendeskArticlePage = self.get(client, &url).await?;
+            if page.articles.is_empty() {
+                break;
+            }
+            for article in page.articles {
+                let body = html_to_text(article.body.as_deref().unwrap_or_default());
+                if article.draft || body.is_empty() {
+                    changes.unpublished.push(article.id.to_string());
+                    continue;
+                }
+                changes.published.push(HelpArticle {
+                    id: article.id.to_string(),
+                    title: article.title,
+                    url: article.html_url,
+                    section: article.section_id.and_then(|id| sections.get(&id).cloned()),
+                    body,
+                });
+            }
+            next = page.next_page.filter(|next_url| *next_url != url);
+        }
+
+        Ok(changes)
+    }
+
+    // Intercom can't filter articles by update time, so every page is read and older ones skipped
+    async fn fetch_intercom(&self, client: &reqwest::Client, since: Option<DateTime<Utc>>) -> Result<ArticleChanges> {
+        let collections: Vec<IntercomCollection> = self.fetch_intercom_pages(client, "help_center/collections").await?;
+        let collections: HashMap<String, String> = collections
+            .into_iter()
+            .map(|collection| (id_string(&collection.id), collection.name))
+            .collect();
+
+        let mut changes = ArticleChanges::default();
+        let articles: Vec<IntercomArticle> = self.fetch_intercom_pages(client, "articles").await?;
+        for article in articles {
+            let updated_at = Utc.timestamp_opt(article.updated_at, 0).single();
+            if let (Some(since), Some(updated_at)) = (since, updated_at)
+                && updated_at <= since
+            {
+                continue;
+            }
+            let id = id_string(&article.id);
+            let body = html_to_text(article.body.as_deref().unwrap_or_default());
+            if article.state != "published" || body.is_empty() {
+                changes.unpublished.push(id);
+                continue;
+            }
+            changes.published.push(HelpArticle {
+                id,
+                title: article.title,
+                url: article.url,
+                section: article.parent_id.and_then(|parent| collections.get(&id_string(&parent)).cloned()),
+                body,
+            });
+        }
+
+        Ok(changes)
+    }
+
+    async fn fetch_intercom_pages<T: DeserializeOwned>(&self, client: &reqwest::Client, resource: &str) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        for page_number in 1..=MAX_PAGES {
+            let url = format!("{}/{}?page={}&per_page={}", INTERCOM_API, resource, page_number, INTERCOM_PAGE_SIZE);
+            let page: IntercomPage<T> = self.get(client, &url).await?;
+            let count = page.data.len();
+            items.extend(page.data);
+            let total_pages = page.pages.and_then(|pages| pages.total_pages).unwrap_or(page_number);
+            if count == 0 || page_number >= total_pages {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Fragments of broken markup: unclosed and stray tags, partial entities and text between them
+    const MALFORMED_HTML: &str =
+        "(<[a-zA-Z/! =\"]{0,10}>?|</?(script|style|p|br)>|&[a-z#0-9]{0,6};?|[^<>&]{0,8}|\n){0,40}";
+
+    proptest! {
+        #[test]
+        fn prop_html_to_text_lines_are_trimmed_and_non_empty(html in MALFORMED_HTML) {
+            let text = html_to_text(&html);
+            if !text.is_empty() {
+                for line in text.split('\n') {
+                    prop_assert!(!line.is_empty());
+                    prop_assert_eq!(line, line.split_whitespace().collect::<Vec<_>>().join(" "));
+                }
+            }
+        }
+
+        #[test]
+        fn prop_html_to_text_handles_any_input(html in any::<String>()) {
+            html_to_text(&html);
+        }
+
+        #[test]
+        fn prop_text_without_markup_keeps_its_words(text in "[^<&]{0,200}") {
+            prop_assert_eq!(html_to_text(&text), text.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<h1>Resetting&nbsp;your password</h1><p>Open <b>Settings</b> &amp; choose\n   <i>Security</i>.</p>\
+                    <script>track();</script><ul><li>One</li><li>Two</li></ul>";
+        assert_eq!(html_to_text(html), "Resetting your password\nOpen Settings & choose Security.\nOne\nTwo");
+    }
+
+    #[test]
+    fn test_html_to_text_keeps_text_after_unclosed_tag() {
+        assert_eq!(html_to_text("a < b"), "a < b");
+    }
+
+    #[test]
+    fn test_subdomain_validation() {
+        assert!(is_valid_subdomain("acme-support"));
+        assert!(!is_valid_subdomain(""));
+        assert!(!is_valid_subdomain("evil.com/"));
+        assert!(!is_valid_subdomain("-acme"));
+    }
+
+    #[test]
+    fn test_article_chunks_start_with_metadata() {
+        let article = HelpArticle {
+            id: "42".to_string(),
+            title: "Billing".to_string(),
+            url: Some("https://acme.zendesk.com/hc/articles/42".to_string()),
+            section: Some("Payments".to_string()),
+            body: "Invoices are sent monthly.".to_string(),
+        };
+        let chunks = article.chunks(200, 50);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].starts_with(
+            "Title: Billing\nSection: Payments\nURL: https://acme.zendesk.com/hc/articles/42\n\n"
+        ));
+        assert_eq!(article_path(HelpCenterProvider::Zendesk, &article.id), "helpcenter:zendesk/42");
+    }
+}
@@ -0,0 +1,201 @@
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing;
+use uuid::Uuid;
+
+use crate::db::models::SearchableChatbot;
+use crate::db::queries::{list_retained_chat_bot_ids, list_retained_chat_bots};
+use crate::services::reindex::alias_of;
+use crate::services::sharding::shard_indices;
+use crate::services::vector::{chatbot_index_name, VectorBackend, VectorStore};
+use crate::services::shutdown::BackgroundJobs;
+
+const DEFAULT_RECONCILE_INTERVAL_SECS: u64 = 86_400;
+
+/// Matches both `chatbot_<uuid>` and `org_<uuid>_chatbot_<uuid>` indices, with or without shard and version suffixes
+const CHATBOT_INDEX_PATTERN: &str = "*chatbot_*";
+
+/// The chatbot an index belongs to, for `chatbot_<id>`, `org_<org>_chatbot_<id>` and their `_shard_<n>`
+/// and reindex `_v<n>` suffixes
+pub fn parse_chatbot_index(index_name: &str) -> Option<Uuid> {
+    let index_name = alias_of(index_name);
+    let rest = match index_name.strip_prefix("org_") {
+        Some(rest) => {
+            let (organization_id, rest) = rest.split_once('_')?;
+            Uuid::parse_str(organization_id).ok()?;
+            rest
+        }
+        None => index_name,
+    };
+
+    let rest = rest.strip_prefix("chatbot_")?;
+    let (chatbot_id, suffix) = match rest.split_once('_') {
+        Some((chatbot_id, suffix)) => (chatbot_id, Some(suffix)),
+        None => (rest, None),
+    };
+
+    if let Some(suffix) = suffix {
+        let shard = suffix.strip_prefix("shard_")?;
+        shard.parse::<u32>().ok()?;
+    }
+
+    Uuid::parse_str(chatbot_id).ok()
+}
+
+/// Chatbot indices whose chatbot is missing or soft-deleted
+pub fn find_orphans(index_names: &[String], retained_chatbots: &HashSet<Uuid>) -> Vec<String> {
+    let mut orphans: Vec<String> = index_names
+        .iter()
+        .filter(|name| parse_chatbot_index(name).is_some_and(|id| !retained_chatbots.contains(&id)))
+        .cloned()
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+/// List chatbot indices that no active or archived chatbot owns
+pub async fn find_orphaned_indices(db: &PgPool, vector_store: &VectorBackend) -> anyhow::Result<Vec<String>> {
+    let retained: HashSet<Uuid> = list_retained_chat_bot_ids(db).await?.into_iter().collect();
+    let indices = vector_store.list_collections(CHATBOT_INDEX_PATTERN).await?;
+
+    Ok(find_orphans(&indices, &retained))
+}
+
+/// Pairs of `(org_<org>_chatbot_<id>, chatbot_<id>)` shard names for indices created before chatbots were
+/// scoped by organization. A shard that already has an index under its new name is left alone
+pub fn legacy_index_aliases(chatbots: &[SearchableChatbot], index_names: &[String]) -> Vec<(String, String)> {
+    let existing: HashSet<&str> = index_names.iter().map(|name| name.as_str()).collect();
+    chatbots
+        .iter()
+        .flat_map(|chatbot| {
+            let current = shard_indices(&chatbot_index_name(chatbot.organization_id, chatbot.id), chatbot.shard_count);
+            let legacy = shard_indices(&format!("chatbot_{}", chatbot.id), chatbot.shard_count);
+            current.into_iter().zip(legacy)
+        })
+        .filter(|(current, legacy)| existing.contains(legacy.as_str()) && !existing.contains(current.as_str()))
+        .collect()
+}
+
+/// Point the organization-scoped name of every legacy chatbot index at it, so chatbots created before
+/// organizations keep their knowledge. Run at startup; aliases already in place are skipped
+pub async fn alias_legacy_indices(db: &PgPool, vector_store: &VectorBackend) -> anyhow::Result<()> {
+    let chatbots = list_retained_chat_bots(db).await?;
+    let indices = vector_store.list_collections(CHATBOT_INDEX_PATTERN).await?;
+
+    for (alias, legacy) in legacy_index_aliases(&chatbots, &indices) {
+        if vector_store.resolve_alias(&alias).await?.is_some() {
+            continue;
+        }
+        match vector_store.swap_aliases(&[(alias.clone(), legacy.clone())]).await {
+            Ok(_) => tracing::info!("✅ Legacy index {} is now served as {}", legacy, alias),
+            Err(e) => tracing::warn!("⚠️ Failed to alias legacy index {} as {}: {}", legacy, alias, e),
+        }
+    }
+
+    Ok(())
+}
+
+// Spawn the background task that reports (and optionally deletes) orphaned chatbot indices
+pub fn spawn_index_reconciliation_task(jobs: &BackgroundJobs, db: Arc<PgPool>, vector_store: Arc<VectorBackend>) {
+    let interval_secs = std::env::var("INDEX_RECONCILE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RECONCILE_INTERVAL_SECS);
+    let delete_orphans = std::env::var("INDEX_RECONCILE_DELETE_ORPHANS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+
+    tracing::info!(
+        "Starting index reconciliation task: interval {}s, delete orphans: {}",
+        interval_secs,
+        delete_orphans
+    );
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            let orphans = match find_orphaned_indices(&db, &vector_store).await {
+                Ok(orphans) => orphans,
+                Err(e) => {
+                    tracing::error!("❌ Index reconciliation failed: {}", e);
+                    continue;
+                }
+            };
+
+            if orphans.is_empty() {
+                tracing::debug!("Index reconciliation found no orphaned indices");
+                continue;
+            }
+
+            tracing::warn!("⚠️ Found {} orphaned chatbot indices: {:?}", orphans.len(), orphans);
+            if delete_orphans
+                && let Err(e) = vector_store.delete_collections(&orphans).await
+            {
+                tracing::error!("❌ Failed to delete orphaned indices: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_indices_aliased_under_organization_names() {
+        let chatbot = SearchableChatbot {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            name: "bot".to_string(),
+            shard_count: 2,
+        };
+        let current = chatbot_index_name(chatbot.organization_id, chatbot.id);
+        let indices = vec![
+            format!("chatbot_{}", chatbot.id),
+            format!("chatbot_{}_shard_1", chatbot.id),
+            format!("{}_shard_1", current),
+        ];
+
+        // The second shard was already written under its new name
+        assert_eq!(
+            legacy_index_aliases(&[chatbot.clone()], &indices),
+            vec![(current, format!("chatbot_{}", chatbot.id))]
+        );
+    }
+
+    #[test]
+    fn test_parse_chatbot_index() {
+        let org = Uuid::new_v4();
+        let bot = Uuid::new_v4();
+
+        assert_eq!(parse_chatbot_index(&format!("chatbot_{}", bot)), Some(bot));
+        assert_eq!(parse_chatbot_index(&format!("org_{}_chatbot_{}", org, bot)), Some(bot));
+        assert_eq!(parse_chatbot_index(&format!("org_{}_chatbot_{}_shard_3", org, bot)), Some(bot));
+        assert_eq!(parse_chatbot_index(&format!("org_{}_chatbot_{}_v2", org, bot)), Some(bot));
+        assert_eq!(parse_chatbot_index(&format!("org_{}_chatbot_{}_shard_3_v2", org, bot)), Some(bot));
+        assert_eq!(parse_chatbot_index(&format!("chatbot_{}_backup", bot)), None);
+        assert_eq!(parse_chatbot_index("my_chatbot_logs"), None);
+    }
+
+    #[test]
+    fn test_find_orphans() {
+        let live = Uuid::new_v4();
+        let dead = Uuid::new_v4();
+        let indices = vec![
+            format!("chatbot_{}", live),
+            format!("chatbot_{}_shard_1", dead),
+            format!("chatbot_{}", dead),
+            "unrelated".to_string(),
+        ];
+
+        let orphans = find_orphans(&indices, &HashSet::from([live]));
+        assert_eq!(orphans, vec![format!("chatbot_{}", dead), format!("chatbot_{}_shard_1", dead)]);
+    }
+}
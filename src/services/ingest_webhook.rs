@@ -0,0 +1,154 @@
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing;
+use url::Url;
+use uuid::Uuid;
+
+use crate::db::models::ChatBot;
+use crate::services::public_url::{check_public_host, public_client_builder};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// A chatbot's pre-index webhook: extracted chunks are sent here before embedding
+#[derive(Debug, Clone)]
+pub struct IngestWebhook {
+    pub chatbot_id: Uuid,
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookChunk<'a> {
+    index: usize,
+    text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookRequest<'a> {
+    chatbot_id: Uuid,
+    file_path: &'a str,
+    chunks: Vec<WebhookChunk<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransformedChunk {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookResponse {
+    chunks: Vec<TransformedChunk>,
+}
+
+impl IngestWebhook {
+    pub fn for_chatbot(chatbot: &ChatBot) -> Option<Self> {
+        chatbot.ingest_webhook_url.as_ref().map(|url| Self {
+            chatbot_id: chatbot.id,
+            url: url.clone(),
+            secret: chatbot.ingest_webhook_secret.clone(),
+        })
+    }
+
+    /// POST the chunks to the webhook and return what it sends back; chunks it omits are dropped
+    pub async fn transform_chunks(&self, file_path: &str, chunks: &[String]) -> Result<Vec<String>> {
+        let timeout_secs = std::env::var("INGEST_WEBHOOK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        let body = serde_json::to_vec(&WebhookRequest {
+            chatbot_id: self.chatbot_id,
+            file_path,
+            chunks: chunks
+                .iter()
+                .enumerate()
+                .map(|(index, text)| WebhookChunk { index, text })
+                .collect(),
+        })?;
+
+        let mut request = public_client_builder()
+            .build()?
+            .post(&self.url)
+            .timeout(Duration::from_secs(timeout_secs))
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+
+        let response = request.body(body).send().await?.error_for_status()?;
+        let transformed = parse_response(&response.bytes().await?)?;
+
+        tracing::info!(
+            "Ingest webhook returned {} of {} chunks for chatbot {}",
+            transformed.len(),
+            chunks.len(),
+            self.chatbot_id
+        );
+        Ok(transformed)
+    }
+}
+
+/// `sha256=<hex HMAC-SHA256 of the request body>`, so receivers can verify the payload came from us
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+fn parse_response(body: &[u8]) -> Result<Vec<String>> {
+    let response: WebhookResponse = serde_json::from_slice(body)
+        .map_err(|e| anyhow::anyhow!("Ingest webhook returned an invalid response: {}", e))?;
+
+    Ok(response
+        .chunks
+        .into_iter()
+        .map(|chunk| chunk.text)
+        .filter(|text| !text.trim().is_empty())
+        .collect())
+}
+
+/// Webhook URLs must be absolute http(s) URLs whose host resolves to public addresses only
+pub async fn validate_webhook_url(url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() => {
+            check_public_host(&parsed).await
+        }
+        Ok(_) => Err(format!("Webhook URL must use http or https: {}", url)),
+        Err(e) => Err(format!("Invalid webhook URL '{}': {}", url, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_matches_rfc_4231() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_parse_response_drops_blank_chunks() {
+        let chunks = parse_response(br#"{"chunks": [{"text": "Enriched"}, {"text": "  "}, {"text": "Kept", "extra": 1}]}"#).unwrap();
+        assert_eq!(chunks, vec!["Enriched", "Kept"]);
+
+        assert!(parse_response(br#"{"items": []}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://93.184.216.34/ingest").await.is_ok());
+        assert!(validate_webhook_url("ftp://hooks.example.com").await.is_err());
+        assert!(validate_webhook_url("not a url").await.is_err());
+        assert!(validate_webhook_url("http://10.0.0.5/hook").await.is_err());
+        assert!(validate_webhook_url("http://[::ffff:169.254.169.254]/").await.is_err());
+    }
+}
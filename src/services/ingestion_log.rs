@@ -0,0 +1,294 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
+use tracing;
+
+use crate::db::models::{IngestionLogEntry, NewDocument};
+use crate::db::queries::{
+    claim_unfinished_ingestions, complete_ingestion, fail_ingestion, remove_cold_document, set_ingestion_stage,
+    upsert_document,
+};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
+use crate::services::document_store::DocumentStore;
+use crate::services::embedding::EmbeddingService;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::ingest_webhook::IngestWebhook;
+use crate::services::plugins::PluginHost;
+use crate::services::sharding::shard_indices;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::{chatbot_index_name, VectorBackend, VectorStore};
+use crate::utils::chunking::ChunkingStrategy;
+
+const DEFAULT_STALE_SECS: i64 = 900;
+// Replays of one upload before it is marked failed
+const MAX_ATTEMPTS: i32 = 3;
+const TICK_SECS: u64 = 60;
+const BATCH_SIZE: i64 = 10;
+const NOT_SAVED: &str = "The server stopped before the upload was saved; upload the file again";
+
+/// Where an accepted upload has got to. Each stage is recorded once its side effect is done, so
+/// after a crash the stage says what is left to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionStage {
+    /// Logged, nothing else done yet
+    Accepted,
+    /// The original is in the document store
+    Stored,
+    /// Chunks are embedded and indexed
+    Indexed,
+    /// The document is recorded
+    Completed,
+    Failed,
+}
+
+impl IngestionStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IngestionStage::Accepted => "accepted",
+            IngestionStage::Stored => "stored",
+            IngestionStage::Indexed => "indexed",
+            IngestionStage::Completed => "completed",
+            IngestionStage::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for IngestionStage {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "accepted" => Ok(IngestionStage::Accepted),
+            "stored" => Ok(IngestionStage::Stored),
+            "indexed" => Ok(IngestionStage::Indexed),
+            "completed" => Ok(IngestionStage::Completed),
+            "failed" => Ok(IngestionStage::Failed),
+            other => Err(format!("unknown ingestion stage '{}'", other)),
+        }
+    }
+}
+
+/// Seconds an upload may stay in one stage before the recovery task treats the server that accepted
+/// it as gone, from `INGESTION_STALE_SECS`. Must be longer than the slowest upload takes to index
+pub fn stale_after_secs() -> i64 {
+    std::env::var("INGESTION_STALE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &i64| *v > 0)
+        .unwrap_or(DEFAULT_STALE_SECS)
+}
+
+/// The document an ingestion records once its chunks are indexed
+pub fn document_of(entry: &IngestionLogEntry) -> NewDocument {
+    NewDocument {
+        organization_id: entry.organization_id,
+        chatbot_id: entry.chatbot_id,
+        file_path: entry.file_path.clone(),
+        file_name: entry.file_name.clone(),
+        content_type: entry.content_type.clone(),
+        storage_key: entry.storage_key.clone(),
+        size_bytes: entry.size_bytes,
+        sha256: entry.sha256.clone(),
+    }
+}
+
+// What the recovery task does with an unfinished upload
+#[derive(Debug, PartialEq)]
+enum Replay {
+    /// Chunk and embed the stored original again, then record it
+    Reprocess,
+    /// Chunks are in place; only the document record is missing
+    Record,
+    GiveUp(String),
+}
+
+// Plan the replay of an upload claimed for the `attempts`-th time
+fn replay_plan(stage: IngestionStage, attempts: i32) -> Replay {
+    if attempts > MAX_ATTEMPTS {
+        return Replay::GiveUp(format!("Gave up after {} attempts to finish the upload", MAX_ATTEMPTS));
+    }
+    match stage {
+        IngestionStage::Stored => Replay::Reprocess,
+        IngestionStage::Indexed => Replay::Record,
+        // Without the original there is nothing to replay from
+        IngestionStage::Accepted => Replay::GiveUp(NOT_SAVED.to_string()),
+        IngestionStage::Completed | IngestionStage::Failed => {
+            Replay::GiveUp(format!("Upload is already {}", stage.as_str()))
+        }
+    }
+}
+
+// What replaying uploads needs
+struct Recovery {
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: Arc<ChatbotCache>,
+    plugins: PluginHost,
+    document_store: Option<Arc<DocumentStore>>,
+}
+
+impl Recovery {
+    // Finish one unfinished upload, or mark it failed when it can't be finished
+    async fn replay(&self, entry: &IngestionLogEntry) -> Result<()> {
+        let stage: IngestionStage = entry.stage.parse().map_err(anyhow::Error::msg)?;
+        let result = match replay_plan(stage, entry.attempts) {
+            Replay::Reprocess => self.reprocess(entry).await,
+            Replay::Record => self.record(entry, None).await,
+            Replay::GiveUp(reason) => {
+                tracing::warn!("⚠️ Upload {} of {} can't be finished: {}", entry.id, entry.file_name, reason);
+                fail_ingestion(&self.db, entry.id, &reason).await?;
+                return Ok(());
+            }
+        };
+
+        // Left as it is, the upload is claimed again once it goes stale, until it runs out of attempts
+        if let Err(e) = &result
+            && entry.attempts >= MAX_ATTEMPTS
+        {
+            fail_ingestion(&self.db, entry.id, &e.to_string()).await?;
+        }
+        result
+    }
+
+    // Re-chunk and re-embed the stored original under its logged path, replacing any chunks the
+    // interrupted attempt wrote
+    async fn reprocess(&self, entry: &IngestionLogEntry) -> Result<()> {
+        let Some(store) = self.document_store.as_ref() else {
+            anyhow::bail!("The document store is disabled, so the original can't be read back");
+        };
+        let Some(chatbot) = self.chatbot_cache.get_chatbot(&self.db, entry.organization_id, entry.chatbot_id).await?
+        else {
+            fail_ingestion(&self.db, entry.id, "The chatbot no longer exists").await?;
+            return Ok(());
+        };
+        let chunking_strategy: ChunkingStrategy = entry.chunking_strategy.parse().map_err(anyhow::Error::msg)?;
+
+        let data = store.get(&entry.storage_key).await?;
+        let index_names = shard_indices(&chatbot_index_name(entry.organization_id, chatbot.id), chatbot.shard_count);
+        self.vector_store.delete_document_chunks(&index_names, &entry.file_path).await?;
+
+        // The pipeline names chunks after the file they were read from, so write it back to that path
+        let file_path = PathBuf::from(&entry.file_path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&file_path, &data).await?;
+        let embedding_service = EmbeddingService::new(self.vector_store.clone(), self.embedding_cache.clone())?;
+        let collection_name = embedding_service
+            .prepare_chatbot_collection(
+                &self.db,
+                &self.chatbot_cache,
+                entry.organization_id,
+                &chatbot,
+                &entry.file_name,
+            )
+            .await;
+        let embedding_count = match collection_name {
+            Ok(collection_name) => {
+                let webhook = IngestWebhook::for_chatbot(&chatbot);
+                embedding_service
+                    .process_pdf_file(&file_path, &collection_name, webhook.as_ref(), &self.plugins, chunking_strategy)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let _ = fs::remove_file(&file_path).await;
+        let embedding_count = embedding_count?;
+
+        set_ingestion_stage(&self.db, entry.id, IngestionStage::Indexed.as_str()).await?;
+        self.record(entry, Some(embedding_count as i64)).await
+    }
+
+    // Record the indexed document and mark the upload completed
+    async fn record(&self, entry: &IngestionLogEntry, embedding_count: Option<i64>) -> Result<()> {
+        let document = upsert_document(&self.db, &document_of(entry)).await?;
+        if let Err(e) = remove_cold_document(&self.db, entry.chatbot_id, &entry.file_path).await {
+            tracing::warn!("⚠️ Failed to record document usage: {}", e);
+        }
+        let embedding_count = embedding_count.or(entry.embedding_count).unwrap_or_default();
+        complete_ingestion(&self.db, entry.id, embedding_count, Some(document.id)).await?;
+
+        let event = CacheEvent::KnowledgeVersionChanged { chatbot_id: entry.chatbot_id };
+        if let Err(e) = publish(&self.db, &self.chatbot_cache, event).await {
+            tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+        }
+        tracing::info!("✅ Recovered upload {} of {} for chatbot {}", entry.id, entry.file_name, entry.chatbot_id);
+        Ok(())
+    }
+}
+
+/// Spawn the background task that finishes uploads a crashed or restarted server left half done.
+/// It runs right away at startup, then every minute
+pub fn spawn_ingestion_recovery_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: Arc<ChatbotCache>,
+    plugins: PluginHost,
+    document_store: Option<Arc<DocumentStore>>,
+) {
+    let recovery = Recovery { db, vector_store, embedding_cache, chatbot_cache, plugins, document_store };
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let unfinished = match claim_unfinished_ingestions(&recovery.db, stale_after_secs(), BATCH_SIZE).await {
+                Ok(unfinished) => unfinished,
+                Err(e) => {
+                    tracing::error!("❌ Ingestion recovery failed to list uploads: {}", e);
+                    continue;
+                }
+            };
+
+            for entry in &unfinished {
+                tracing::info!("Replaying upload {} of {} from stage {}", entry.id, entry.file_name, entry.stage);
+                if let Err(e) = recovery.replay(entry).await {
+                    tracing::error!("❌ Failed to replay upload {} (attempt {}): {}", entry.id, entry.attempts, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_round_trips() {
+        for stage in [
+            IngestionStage::Accepted,
+            IngestionStage::Stored,
+            IngestionStage::Indexed,
+            IngestionStage::Completed,
+            IngestionStage::Failed,
+        ] {
+            assert_eq!(stage.as_str().parse::<IngestionStage>(), Ok(stage));
+        }
+        assert!("running".parse::<IngestionStage>().is_err());
+    }
+
+    #[test]
+    fn test_replay_plan_resumes_from_the_last_recorded_stage() {
+        assert_eq!(replay_plan(IngestionStage::Stored, 1), Replay::Reprocess);
+        assert_eq!(replay_plan(IngestionStage::Indexed, 1), Replay::Record);
+        assert_eq!(replay_plan(IngestionStage::Accepted, 1), Replay::GiveUp(NOT_SAVED.to_string()));
+    }
+
+    #[test]
+    fn test_replay_plan_gives_up_after_max_attempts() {
+        assert_eq!(replay_plan(IngestionStage::Stored, MAX_ATTEMPTS), Replay::Reprocess);
+        assert!(matches!(replay_plan(IngestionStage::Stored, MAX_ATTEMPTS + 1), Replay::GiveUp(_)));
+    }
+}
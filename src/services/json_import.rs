@@ -0,0 +1,191 @@
+use serde_json::Value;
+
+use crate::db::models::JsonFieldMapping;
+use crate::utils::pdf::chunk_text;
+
+/// A record of a JSON or JSONL import, with its mapped fields as text
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRecord {
+    pub id: String,
+    pub title: Option<String>,
+    /// Metadata fields that had a value, in mapping order
+    pub metadata: Vec<(String, String)>,
+    pub content: String,
+}
+
+impl JsonRecord {
+    pub fn file_path(&self, source: &str) -> String {
+        format!("{}/{}", json_source_path(source), self.id)
+    }
+
+    /// Chunk the content, starting every chunk with the title and metadata so they are
+    /// searchable and show up in citations
+    pub fn chunks(&self, chunk_size: usize, overlap: usize) -> Vec<String> {
+        let mut header = String::new();
+        if let Some(title) = &self.title {
+            header.push_str(&format!("Title: {}\n", title));
+        }
+        for (field, value) in &self.metadata {
+            header.push_str(&format!("{}: {}\n", field, value));
+        }
+        if !header.is_empty() {
+            header.push('\n');
+        }
+
+        chunk_text(&self.content, chunk_size, overlap)
+            .into_iter()
+            .map(|chunk| format!("{}{}", header, chunk))
+            .collect()
+    }
+}
+
+/// Path of a whole import, under which its records' paths are nested
+pub fn json_source_path(source: &str) -> String {
+    format!("json:{}", source)
+}
+
+/// A mapping needs at least one content field, and no field name may be blank
+pub fn validate_mapping(mapping: &JsonFieldMapping) -> Result<(), String> {
+    if mapping.content.is_empty() {
+        return Err("mapping.content must name at least one field".to_string());
+    }
+    let mut fields = mapping.content.iter().chain(&mapping.metadata).chain(&mapping.title).chain(&mapping.id);
+    if fields.any(|field| field.trim().is_empty()) {
+        return Err("mapping field names can't be blank".to_string());
+    }
+    Ok(())
+}
+
+/// Records of a JSONL export, one object per line. Blank lines are skipped; a line that isn't
+/// JSON fails the whole import with its line number
+pub fn parse_jsonl(text: &str) -> Result<Vec<Value>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).map_err(|e| format!("line {} is not valid JSON: {}", number + 1, e))
+        })
+        .collect()
+}
+
+// A field's value as text. Dots step into nested objects, so "fields.body" reads a CMS entry's
+// body. Missing, null and empty values have none
+fn field_text(record: &Value, field: &str) -> Option<String> {
+    let value = field.split('.').try_fold(record, |value, key| value.get(key))?;
+    let text = match value {
+        Value::Null => return None,
+        Value::String(text) => text.trim().to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// Map each record's fields to content, title, metadata and id. Records that aren't objects or
+/// have no content are left out and counted as skipped. Records without an id field are
+/// numbered by their position
+pub fn map_records(records: &[Value], mapping: &JsonFieldMapping) -> (Vec<JsonRecord>, usize) {
+    let mut mapped = Vec::with_capacity(records.len());
+    for (position, record) in records.iter().enumerate() {
+        if !record.is_object() {
+            continue;
+        }
+        let content: Vec<String> = mapping.content.iter().filter_map(|field| field_text(record, field)).collect();
+        if content.is_empty() {
+            continue;
+        }
+
+        mapped.push(JsonRecord {
+            id: mapping
+                .id
+                .as_deref()
+                .and_then(|field| field_text(record, field))
+                .unwrap_or_else(|| (position + 1).to_string()),
+            title: mapping.title.as_deref().and_then(|field| field_text(record, field)),
+            metadata: mapping
+                .metadata
+                .iter()
+                .filter_map(|field| field_text(record, field).map(|value| (field.clone(), value)))
+                .collect(),
+            content: content.join("\n\n"),
+        });
+    }
+
+    let skipped = records.len() - mapped.len();
+    (mapped, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mapping() -> JsonFieldMapping {
+        JsonFieldMapping {
+            content: vec!["question".to_string(), "answer".to_string()],
+            title: Some("question".to_string()),
+            id: Some("id".to_string()),
+            metadata: vec!["category".to_string(), "meta.tags".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_map_records() {
+        let records = [
+            json!({"id": 7, "question": "Can I return shoes?", "answer": "Within 30 days.", "category": "Returns",
+                "meta": {"tags": ["shoes", "returns"]}}),
+            json!({"question": "  ", "answer": null}),
+            json!("not an object"),
+            json!({"answer": "Orders ship in two days."}),
+        ];
+        let (mapped, skipped) = map_records(&records, &mapping());
+
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            mapped[0],
+            JsonRecord {
+                id: "7".to_string(),
+                title: Some("Can I return shoes?".to_string()),
+                metadata: vec![
+                    ("category".to_string(), "Returns".to_string()),
+                    ("meta.tags".to_string(), "shoes, returns".to_string()),
+                ],
+                content: "Can I return shoes?\n\nWithin 30 days.".to_string(),
+            }
+        );
+        assert_eq!(mapped[1].id, "4");
+        assert_eq!(mapped[1].title, None);
+        assert_eq!(mapped[1].file_path("faq"), "json:faq/4");
+    }
+
+    #[test]
+    fn test_chunks_start_with_title_and_metadata() {
+        let record = JsonRecord {
+            id: "1".to_string(),
+            title: Some("Returns".to_string()),
+            metadata: vec![("category".to_string(), "Policies".to_string())],
+            content: "Within 30 days.".to_string(),
+        };
+        assert_eq!(record.chunks(200, 50), vec!["Title: Returns\ncategory: Policies\n\nWithin 30 days."]);
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let records = parse_jsonl("{\"a\": 1}\n\n{\"a\": 2}\n").unwrap();
+        assert_eq!(records, vec![json!({"a": 1}), json!({"a": 2})]);
+
+        let error = parse_jsonl("{\"a\": 1}\n{oops").unwrap_err();
+        assert!(error.starts_with("line 2 "), "{}", error);
+    }
+
+    #[test]
+    fn test_validate_mapping() {
+        assert!(validate_mapping(&mapping()).is_ok());
+        assert!(validate_mapping(&JsonFieldMapping { content: Vec::new(), ..mapping() }).is_err());
+        assert!(validate_mapping(&JsonFieldMapping { id: Some(" ".to_string()), ..mapping() }).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use axum::body::Body;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use futures_util::future::ready;
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// How a streamed listing is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingFormat {
+    /// The usual `success`/`message`/`data` envelope, with the item array written as it is read
+    Json,
+    /// One item per line, without the envelope
+    Ndjson,
+}
+
+impl ListingFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ListingFormat::Json => "application/json",
+            ListingFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+impl FromStr for ListingFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "json" => Ok(ListingFormat::Json),
+            "ndjson" => Ok(ListingFormat::Ndjson),
+            other => Err(format!("unknown listing format '{}'; use json or ndjson", other)),
+        }
+    }
+}
+
+/// A listing written out item by item as its source is read, so memory stays flat however many
+/// items there are
+pub struct StreamedListing {
+    pub format: ListingFormat,
+    pub message: &'static str,
+    /// Fields of `data` written before the items, such as the chat id
+    pub fields: Map<String, Value>,
+    /// Key of the item array in `data`; a `count` field follows the array
+    pub items_key: &'static str,
+}
+
+impl StreamedListing {
+    // Everything before the first item
+    fn head(&self) -> String {
+        if self.format == ListingFormat::Ndjson {
+            return String::new();
+        }
+        let mut head = format!("{{\"success\":true,\"message\":{},\"data\":{{", Value::from(self.message));
+        for (key, value) in &self.fields {
+            head.push_str(&format!("{}:{},", Value::from(key.as_str()), value));
+        }
+        head.push_str(&format!("{}:[", Value::from(self.items_key)));
+        head
+    }
+
+    // One serialized item, with its separator from the item before it
+    fn item(format: ListingFormat, position: usize, item: &str) -> String {
+        match format {
+            ListingFormat::Json if position == 0 => item.to_string(),
+            ListingFormat::Json => format!(",{}", item),
+            ListingFormat::Ndjson => format!("{}\n", item),
+        }
+    }
+
+    // Everything after the last item
+    fn tail(format: ListingFormat, count: usize) -> String {
+        match format {
+            ListingFormat::Json => format!("],\"count\":{}}}}}", count),
+            ListingFormat::Ndjson => String::new(),
+        }
+    }
+
+    /// Respond with the listing. The status is sent before the first item is read, so an error
+    /// partway through is logged and ends the body early
+    pub fn into_response<S, T, E>(self, items: S) -> Response
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: std::fmt::Display,
+    {
+        let format = self.format;
+        let count = Arc::new(AtomicUsize::new(0));
+        let counted = count.clone();
+
+        let items = items.map(move |item| {
+            let item = item.map_err(|e| {
+                tracing::error!("❌ Listing stopped partway: {}", e);
+                std::io::Error::other(e.to_string())
+            })?;
+            let item = serde_json::to_string(&item).map_err(std::io::Error::other)?;
+            Ok(Self::item(format, counted.fetch_add(1, Ordering::Relaxed), &item))
+        });
+        let body = stream::once(ready(Ok::<_, std::io::Error>(self.head())))
+            .chain(items)
+            .chain(stream::once(async move { Ok(Self::tail(format, count.load(Ordering::Relaxed))) }));
+
+        ([(header::CONTENT_TYPE, format.content_type())], Body::from_stream(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn listing(format: ListingFormat) -> StreamedListing {
+        let mut fields = Map::new();
+        fields.insert("chat_id".to_string(), json!("c1"));
+        StreamedListing { format, message: "Chat history retrieved successfully", fields, items_key: "conversations" }
+    }
+
+    fn write(format: ListingFormat, items: &[Value]) -> String {
+        let listing = listing(format);
+        let mut body = listing.head();
+        for (position, item) in items.iter().enumerate() {
+            body.push_str(&StreamedListing::item(format, position, &item.to_string()));
+        }
+        body.push_str(&StreamedListing::tail(format, items.len()));
+        body
+    }
+
+    #[test]
+    fn test_json_listing_matches_collected_envelope() {
+        let items = [json!({"id": 1, "text": "a \"quoted\" turn"}), json!({"id": 2})];
+        let written: Value = serde_json::from_str(&write(ListingFormat::Json, &items)).unwrap();
+
+        assert_eq!(
+            written,
+            json!({
+                "success": true,
+                "message": "Chat history retrieved successfully",
+                "data": {"chat_id": "c1", "conversations": items, "count": 2}
+            })
+        );
+    }
+
+    #[test]
+    fn test_empty_json_listing() {
+        let written: Value = serde_json::from_str(&write(ListingFormat::Json, &[])).unwrap();
+        assert_eq!(written["data"], json!({"chat_id": "c1", "conversations": [], "count": 0}));
+    }
+
+    #[test]
+    fn test_ndjson_listing_is_one_item_per_line() {
+        let written = write(ListingFormat::Ndjson, &[json!({"id": 1}), json!({"id": 2})]);
+        assert_eq!(written, "{\"id\":1}\n{\"id\":2}\n");
+    }
+
+    #[test]
+    fn test_parse_listing_format() {
+        assert_eq!("NDJSON".parse::<ListingFormat>(), Ok(ListingFormat::Ndjson));
+        assert_eq!("json".parse::<ListingFormat>(), Ok(ListingFormat::Json));
+        assert!("xml".parse::<ListingFormat>().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::db::models::RatedCitation;
+
+/// A chunk cited by rated answers
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChunkGap {
+    pub chunk_index: i64,
+    pub excerpt: Option<String>,
+    pub url: Option<String>,
+    pub thumbs_down: usize,
+    pub thumbs_up: usize,
+    pub impact: f64,
+}
+
+/// A document whose chunks were cited by rated answers, with those chunks most associated with
+/// bad answers first
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DocumentGap {
+    pub file_path: String,
+    /// Answers citing any of its chunks, each counted once
+    pub thumbs_down: usize,
+    pub thumbs_up: usize,
+    pub impact: f64,
+    pub chunks: Vec<ChunkGap>,
+}
+
+// The answers rated up and down that cited something
+#[derive(Debug, Default)]
+struct Ratings {
+    down: HashSet<Uuid>,
+    up: HashSet<Uuid>,
+}
+
+impl Ratings {
+    fn add(&mut self, citation: &RatedCitation) {
+        match citation.rating.as_str() {
+            "down" => self.down.insert(citation.conversation_id),
+            _ => self.up.insert(citation.conversation_id),
+        };
+    }
+}
+
+/// How strongly something is tied to bad answers: its thumbs-down count weighted by the share of
+/// its rated answers that were thumbs-down. A chunk in 10 bad answers and no good ones outranks one
+/// in 10 bad answers and 30 good ones, which is likely just cited often
+pub fn impact(thumbs_down: usize, thumbs_up: usize) -> f64 {
+    let rated = thumbs_down + thumbs_up;
+    if rated == 0 {
+        return 0.0;
+    }
+    (thumbs_down * thumbs_down) as f64 / rated as f64
+}
+
+// Highest impact first, then most thumbs-down
+fn by_impact(a: (f64, usize), b: (f64, usize)) -> std::cmp::Ordering {
+    b.0.total_cmp(&a.0).then(b.1.cmp(&a.1))
+}
+
+/// Documents and chunks cited by at least one thumbs-down answer, the highest impact first
+pub fn knowledge_gaps(citations: &[RatedCitation], limit: usize) -> Vec<DocumentGap> {
+    let mut documents: HashMap<&str, Ratings> = HashMap::new();
+    let mut chunks: HashMap<(&str, i64), (Ratings, &RatedCitation)> = HashMap::new();
+    for citation in citations {
+        documents.entry(citation.file_path.as_str()).or_default().add(citation);
+        chunks
+            .entry((citation.file_path.as_str(), citation.chunk_index))
+            .or_insert_with(|| (Ratings::default(), citation))
+            .0
+            .add(citation);
+    }
+
+    let mut chunk_gaps: HashMap<&str, Vec<ChunkGap>> = HashMap::new();
+    for ((file_path, chunk_index), (ratings, citation)) in chunks {
+        if ratings.down.is_empty() {
+            continue;
+        }
+        chunk_gaps.entry(file_path).or_default().push(ChunkGap {
+            chunk_index,
+            excerpt: citation.excerpt.clone(),
+            url: citation.url.clone(),
+            thumbs_down: ratings.down.len(),
+            thumbs_up: ratings.up.len(),
+            impact: impact(ratings.down.len(), ratings.up.len()),
+        });
+    }
+
+    let mut gaps: Vec<DocumentGap> = documents
+        .into_iter()
+        .filter_map(|(file_path, ratings)| {
+            let mut chunks = chunk_gaps.remove(file_path)?;
+            chunks.sort_by(|a, b| {
+                by_impact((a.impact, a.thumbs_down), (b.impact, b.thumbs_down)).then(a.chunk_index.cmp(&b.chunk_index))
+            });
+            Some(DocumentGap {
+                file_path: file_path.to_string(),
+                thumbs_down: ratings.down.len(),
+                thumbs_up: ratings.up.len(),
+                impact: impact(ratings.down.len(), ratings.up.len()),
+                chunks,
+            })
+        })
+        .collect();
+    gaps.sort_by(|a, b| {
+        by_impact((a.impact, a.thumbs_down), (b.impact, b.thumbs_down)).then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    gaps.truncate(limit);
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cited(conversation: u128, rating: &str, file_path: &str, chunk_index: i64) -> RatedCitation {
+        RatedCitation {
+            conversation_id: Uuid::from_u128(conversation),
+            rating: rating.to_string(),
+            file_path: file_path.to_string(),
+            chunk_index,
+            excerpt: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn test_impact_favors_mostly_bad_answers() {
+        assert_eq!(impact(0, 5), 0.0);
+        assert_eq!(impact(4, 0), 4.0);
+        assert!(impact(10, 0) > impact(10, 30));
+        assert_eq!(impact(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_documents_sorted_by_impact() {
+        let citations = [
+            cited(1, "down", "pricing.md", 0),
+            cited(2, "down", "pricing.md", 0),
+            cited(3, "down", "faq.md", 1),
+            cited(4, "up", "faq.md", 1),
+            cited(5, "up", "setup.md", 0),
+        ];
+        let gaps = knowledge_gaps(&citations, 10);
+        let files: Vec<&str> = gaps.iter().map(|gap| gap.file_path.as_str()).collect();
+        // setup.md only appears in good answers
+        assert_eq!(files, ["pricing.md", "faq.md"]);
+        assert_eq!((gaps[1].thumbs_down, gaps[1].thumbs_up), (1, 1));
+    }
+
+    #[test]
+    fn test_answer_counts_once_per_document() {
+        let citations = [
+            cited(1, "down", "pricing.md", 0),
+            cited(1, "down", "pricing.md", 1),
+            cited(2, "up", "pricing.md", 1),
+        ];
+        let gaps = knowledge_gaps(&citations, 10);
+        assert_eq!(gaps[0].thumbs_down, 1);
+        assert_eq!(gaps[0].thumbs_up, 1);
+        let chunks: Vec<i64> = gaps[0].chunks.iter().map(|chunk| chunk.chunk_index).collect();
+        assert_eq!(chunks, [0, 1]);
+    }
+
+    #[test]
+    fn test_limit_keeps_highest_impact() {
+        let citations = [cited(1, "down", "a.md", 0), cited(2, "down", "b.md", 0), cited(3, "down", "b.md", 1)];
+        let gaps = knowledge_gaps(&citations, 1);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].file_path, "b.md");
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::db::models::LanguageCount;
+
+// Fewer words than this are too little to tell Latin-script languages apart
+const MIN_WORDS: usize = 2;
+// Share of detected queries outside the main language that makes a chatbot multilingual
+const MULTILINGUAL_SHARE: f64 = 0.1;
+
+// Common function words, which make up a large share of any sentence in their language
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "are", "you", "to", "of", "in", "it", "what", "how", "my", "can", "do", "for", "with", "this", "that", "i", "does"]),
+    ("es", &["el", "la", "los", "las", "es", "y", "que", "de", "en", "un", "una", "por", "para", "como", "mi", "cómo", "qué", "puedo", "con", "está"]),
+    ("fr", &["le", "la", "les", "est", "et", "que", "de", "des", "en", "un", "une", "pour", "comment", "je", "mon", "ma", "vous", "avec", "ce", "qui"]),
+    ("de", &["der", "die", "das", "ist", "und", "nicht", "ich", "ein", "eine", "wie", "mit", "zu", "den", "mein", "meine", "kann", "für", "auf", "sie", "was"]),
+    ("it", &["il", "lo", "la", "gli", "è", "e", "che", "di", "un", "una", "per", "come", "mio", "mia", "posso", "con", "non", "sono", "del", "della"]),
+    ("pt", &["o", "os", "as", "é", "e", "que", "de", "do", "da", "um", "uma", "para", "como", "meu", "minha", "posso", "com", "não", "em", "está"]),
+    ("nl", &["de", "het", "een", "is", "en", "niet", "ik", "hoe", "wat", "mijn", "kan", "voor", "met", "van", "zijn", "dat", "je", "op", "te", "er"]),
+];
+
+/// Guess the ISO 639-1 language of a message, from its script and, for Latin script, its function
+/// words. `None` when the text is too short or mixed to tell
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut scripts: HashMap<&'static str, usize> = HashMap::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        *scripts.entry(script(c)).or_default() += 1;
+    }
+    let (dominant, _) = scripts.into_iter().max_by_key(|(script, count)| (*count, *script))?;
+
+    match dominant {
+        "latin" => detect_latin(text),
+        "cyrillic" if text.chars().any(|c| matches!(c, 'і' | 'ї' | 'є' | 'ґ' | 'І' | 'Ї' | 'Є' | 'Ґ')) => Some("uk"),
+        "cyrillic" => Some("ru"),
+        // Japanese mixes kanji with kana, Chinese has no kana at all
+        "han" if text.chars().any(|c| matches!(c, '\u{3040}'..='\u{30ff}')) => Some("ja"),
+        "han" => Some("zh"),
+        "kana" => Some("ja"),
+        "other" => None,
+        language => Some(language),
+    }
+}
+
+// The script of a letter, named by the language it points to when that is the only common one
+fn script(c: char) -> &'static str {
+    match c {
+        'a'..='z' | 'A'..='Z' | '\u{00c0}'..='\u{024f}' => "latin",
+        '\u{0370}'..='\u{03ff}' => "el",
+        '\u{0400}'..='\u{04ff}' => "cyrillic",
+        '\u{0590}'..='\u{05ff}' => "he",
+        '\u{0600}'..='\u{06ff}' => "ar",
+        '\u{0900}'..='\u{097f}' => "hi",
+        '\u{0e00}'..='\u{0e7f}' => "th",
+        '\u{3040}'..='\u{30ff}' => "kana",
+        '\u{4e00}'..='\u{9fff}' => "han",
+        '\u{ac00}'..='\u{d7af}' => "ko",
+        _ => "other",
+    }
+}
+
+// The language whose function words appear most, if any do and there is no tie
+fn detect_latin(text: &str) -> Option<&'static str> {
+    let lowered = text.to_lowercase();
+    let words: Vec<&str> = lowered
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| (*language, words.iter().filter(|word| stopwords.contains(*word)).count()))
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+    match scores.as_slice() {
+        [(language, best), (_, second), ..] if *best > 0 && best > second => Some(*language),
+        _ => None,
+    }
+}
+
+/// The language most queries were asked in, and the share of detected queries asked in others
+pub fn primary_language(counts: &[LanguageCount]) -> (Option<String>, f64) {
+    let detected: i64 = counts.iter().map(|count| count.queries).sum();
+    let Some(primary) = counts.iter().filter(|count| count.queries > 0).max_by_key(|count| count.queries) else {
+        return (None, 0.0);
+    };
+    let other_rate = (detected - primary.queries) as f64 / detected as f64;
+    (Some(primary.language.clone()), other_rate)
+}
+
+/// Whether enough queries come in other languages to need multilingual embeddings or translated documents
+pub fn is_multilingual(other_language_rate: f64) -> bool {
+    other_language_rate >= MULTILINGUAL_SHARE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_latin_languages_by_function_words() {
+        assert_eq!(detect_language("How do I reset my password?"), Some("en"));
+        assert_eq!(detect_language("¿Cómo puedo cambiar mi contraseña?"), Some("es"));
+        assert_eq!(detect_language("Comment je change mon mot de passe ?"), Some("fr"));
+        assert_eq!(detect_language("Wie kann ich mein Passwort ändern?"), Some("de"));
+        assert_eq!(detect_language("Hoe kan ik mijn wachtwoord wijzigen?"), Some("nl"));
+    }
+
+    #[test]
+    fn test_detects_other_scripts() {
+        assert_eq!(detect_language("Как сменить пароль?"), Some("ru"));
+        assert_eq!(detect_language("Як змінити пароль?"), Some("uk"));
+        assert_eq!(detect_language("パスワードを変更するには？"), Some("ja"));
+        assert_eq!(detect_language("如何更改密码？"), Some("zh"));
+        assert_eq!(detect_language("비밀번호를 변경하는 방법"), Some("ko"));
+        assert_eq!(detect_language("كيف أغير كلمة المرور؟"), Some("ar"));
+    }
+
+    #[test]
+    fn test_undetermined_text() {
+        assert_eq!(detect_language(""), None);
+        assert_eq!(detect_language("12345 ?!"), None);
+        assert_eq!(detect_language("Password"), None);
+        assert_eq!(detect_language("SKU-42 XR9 pricing"), None);
+    }
+
+    #[test]
+    fn test_primary_language() {
+        let counts = vec![
+            LanguageCount { language: "en".to_string(), queries: 80, answers: 95 },
+            LanguageCount { language: "de".to_string(), queries: 15, answers: 5 },
+            LanguageCount { language: "fr".to_string(), queries: 5, answers: 0 },
+        ];
+        let (primary, other_rate) = primary_language(&counts);
+        assert_eq!(primary.as_deref(), Some("en"));
+        assert!((other_rate - 0.2).abs() < 1e-9);
+        assert!(is_multilingual(other_rate));
+
+        assert_eq!(primary_language(&[]), (None, 0.0));
+        assert!(!is_multilingual(0.05));
+    }
+}
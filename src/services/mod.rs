@@ -1,5 +1,73 @@
+pub mod answer_cache;
+pub mod answer_policy;
+pub mod attribution;
+pub mod cache_invalidation;
 pub mod candle_embedding;
+pub mod chatbot_health;
+pub mod cold_storage;
+pub mod compression;
+pub mod confluence;
+pub mod connector;
+pub mod conversation_export;
+pub mod custom_domain;
+pub mod document_store;
 pub mod elasticsearch;
+pub mod elasticsearch_failover;
+pub mod email;
 pub mod embedding;
+pub mod embedding_bench;
+pub mod embedding_cache;
+pub mod evaluation;
+pub mod faq_clusters;
 pub mod gemini;
+pub mod global_search;
+pub mod glossary;
+pub mod guest;
+pub mod handoff;
+pub mod health;
+pub mod help_center;
+pub mod index_lifecycle;
+pub mod ingest_webhook;
+pub mod ingestion_log;
+pub mod json_import;
+pub mod json_stream;
+pub mod knowledge_gaps;
+pub mod language;
+pub mod notion;
+pub mod oidc;
+pub mod output_filter;
+pub mod partial_response;
+pub mod pgvector;
+#[cfg(feature = "wasm-plugins")]
+pub mod plugin_runtime;
+pub mod plugins;
+pub mod prompt_template;
+pub mod public_url;
+pub mod purge;
+pub mod qdrant;
+pub mod query_expansion;
+pub mod query_rewrite;
+pub mod raw_vectors;
+pub mod reindex;
+pub mod reply_suggestion;
+pub mod reports;
+pub mod retrieval;
+pub mod retrieval_eval;
+pub mod retrieval_log;
+pub mod retry;
+pub mod scripting;
+pub mod sentiment;
+pub mod sharding;
+pub mod scim;
+pub mod sections;
+pub mod shutdown;
+pub mod sql_connector;
+pub mod sql_tool;
+pub mod stitching;
+pub mod token_budget;
+pub mod translation;
+pub mod usage;
+pub mod user_auth;
 pub mod vector;
+pub mod web_source;
+pub mod widget_config;
@@ -0,0 +1,545 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::services::connector::{Connector, ConnectorChanges, ConnectorDocument};
+
+const NOTION_API: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+const PAGE_SIZE: usize = 100;
+// Stop paging after this many result pages, in case the API keeps returning a cursor
+const MAX_RESULT_PAGES: usize = 500;
+// Pages walked from the root pages, and how deep blocks nest before the rest is skipped
+const MAX_TRAVERSED_PAGES: usize = 5000;
+const MAX_BLOCK_DEPTH: usize = 8;
+// Notion allows about three requests a second and answers 429 with `Retry-After` beyond that
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+
+/// Notion settings kept in a connector's `config`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotionConfig {
+    /// Sync only these pages and their sub-pages instead of every page shared with the integration
+    #[serde(default)]
+    pub root_page_ids: Vec<String>,
+}
+
+/// Notion ids are 32 hex digits, with or without the dashes of a UUID
+pub fn is_valid_page_id(id: &str) -> bool {
+    let digits: Vec<char> = id.chars().filter(|c| *c != '-').collect();
+    digits.len() == 32 && digits.iter().all(|c| c.is_ascii_hexdigit())
+}
+
+/// A block with its nested blocks, as returned by the blocks API
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotionBlock {
+    pub block: Value,
+    pub children: Vec<NotionBlock>,
+}
+
+impl NotionBlock {
+    fn kind(&self) -> &str {
+        self.block["type"].as_str().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListResponse {
+    #[serde(default)]
+    results: Vec<Value>,
+    #[serde(default)]
+    has_more: bool,
+    next_cursor: Option<String>,
+}
+
+/// Rich text as markdown: bold, italic, strikethrough, inline code and links are kept
+pub fn rich_text_to_markdown(rich_text: &Value) -> String {
+    let Some(spans) = rich_text.as_array() else {
+        return String::new();
+    };
+    spans
+        .iter()
+        .map(|span| {
+            let text = span["plain_text"].as_str().unwrap_or_default();
+            if text.trim().is_empty() {
+                return text.to_string();
+            }
+            let annotations = &span["annotations"];
+            let mut text = text.to_string();
+            if annotations["code"].as_bool().unwrap_or(false) {
+                text = format!("`{}`", text);
+            }
+            if annotations["bold"].as_bool().unwrap_or(false) {
+                text = format!("**{}**", text);
+            }
+            if annotations["italic"].as_bool().unwrap_or(false) {
+                text = format!("*{}*", text);
+            }
+            if annotations["strikethrough"].as_bool().unwrap_or(false) {
+                text = format!("~~{}~~", text);
+            }
+            match span["href"].as_str() {
+                Some(href) => format!("[{}]({})", text, href),
+                None => text,
+            }
+        })
+        .collect()
+}
+
+fn plain_text(rich_text: &Value) -> String {
+    rich_text
+        .as_array()
+        .map(|spans| spans.iter().filter_map(|span| span["plain_text"].as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Convert a page's blocks to markdown. Sub-pages and databases are left out; sub-pages are synced
+/// as documents of their own
+pub fn blocks_to_markdown(blocks: &[NotionBlock]) -> String {
+    let mut lines = Vec::new();
+    write_blocks(blocks, 0, &mut lines);
+    lines.join("\n")
+}
+
+fn write_blocks(blocks: &[NotionBlock], indent: usize, lines: &mut Vec<String>) {
+    let pad = "  ".repeat(indent);
+    let mut number = 0;
+    for block in blocks {
+        let kind = block.kind();
+        let data = &block.block[kind];
+        let text = rich_text_to_markdown(&data["rich_text"]);
+        number = if kind == "numbered_list_item" { number + 1 } else { 0 };
+
+        let line = match kind {
+            "paragraph" | "toggle" => Some(text),
+            "heading_1" => Some(format!("# {}", text)),
+            "heading_2" => Some(format!("## {}", text)),
+            "heading_3" => Some(format!("### {}", text)),
+            "bulleted_list_item" => Some(format!("- {}", text)),
+            "numbered_list_item" => Some(format!("{}. {}", number, text)),
+            "to_do" => {
+                let checked = if data["checked"].as_bool().unwrap_or(false) { "x" } else { " " };
+                Some(format!("- [{}] {}", checked, text))
+            }
+            "quote" | "callout" => Some(format!("> {}", text)),
+            "code" => {
+                let language = data["language"].as_str().unwrap_or_default();
+                Some(format!("```{}\n{}\n```", language, plain_text(&data["rich_text"])))
+            }
+            "equation" => data["expression"].as_str().map(|expression| format!("$${}$$", expression)),
+            "divider" => Some("---".to_string()),
+            "bookmark" | "embed" | "link_preview" => data["url"].as_str().map(str::to_string),
+            "table" => {
+                write_table(block, &pad, lines);
+                None
+            }
+            _ => None,
+        };
+        if let Some(line) = line.filter(|line| !line.trim().is_empty()) {
+            lines.push(format!("{}{}", pad, line));
+        }
+
+        // List items and toggles nest their children; other containers, like columns, don't
+        let child_indent = match kind {
+            "bulleted_list_item" | "numbered_list_item" | "to_do" | "toggle" => indent + 1,
+            _ => indent,
+        };
+        if !matches!(kind, "table" | "child_page" | "child_database") {
+            write_blocks(&block.children, child_indent, lines);
+        }
+    }
+}
+
+// Table rows as a markdown table, the first row taken as the header
+fn write_table(table: &NotionBlock, pad: &str, lines: &mut Vec<String>) {
+    let rows: Vec<Vec<String>> = table
+        .children
+        .iter()
+        .filter(|row| row.kind() == "table_row")
+        .map(|row| {
+            row.block["table_row"]["cells"]
+                .as_array()
+                .map(|cells| cells.iter().map(|cell| rich_text_to_markdown(cell).replace('|', "\\|")).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+    for (i, cells) in rows.iter().enumerate() {
+        lines.push(format!("{}| {} |", pad, cells.join(" | ")));
+        if i == 0 {
+            lines.push(format!("{}|{}", pad, " --- |".repeat(cells.len())));
+        }
+    }
+}
+
+// Ids of the sub-pages linked from a page's blocks, wherever they are nested
+fn child_page_ids(blocks: &[NotionBlock], ids: &mut Vec<String>) {
+    for block in blocks {
+        if block.kind() == "child_page" {
+            ids.extend(block.block["id"].as_str().map(str::to_string));
+        } else {
+            child_page_ids(&block.children, ids);
+        }
+    }
+}
+
+/// A page's title: the text of its title property, whatever that property is called
+pub fn page_title(page: &Value) -> String {
+    page["properties"]
+        .as_object()
+        .and_then(|properties| properties.values().find(|property| property["type"] == "title"))
+        .map(|property| plain_text(&property["title"]))
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+fn edited_at(page: &Value) -> Option<DateTime<Utc>> {
+    page["last_edited_time"]
+        .as_str()
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn is_archived(page: &Value) -> bool {
+    page["archived"].as_bool().unwrap_or(false) || page["in_trash"].as_bool().unwrap_or(false)
+}
+
+/// Exchange the code from Notion's OAuth redirect for an access token, with the public
+/// integration's `NOTION_CLIENT_ID` and `NOTION_CLIENT_SECRET`
+pub async fn exchange_oauth_code(code: &str, redirect_uri: Option<&str>) -> Result<String> {
+    let client_id = std::env::var("NOTION_CLIENT_ID").map_err(|_| anyhow::anyhow!("NOTION_CLIENT_ID is not set"))?;
+    let client_secret =
+        std::env::var("NOTION_CLIENT_SECRET").map_err(|_| anyhow::anyhow!("NOTION_CLIENT_SECRET is not set"))?;
+
+    let mut body = json!({ "grant_type": "authorization_code", "code": code });
+    if let Some(redirect_uri) = redirect_uri {
+        body["redirect_uri"] = json!(redirect_uri);
+    }
+    let response = reqwest::Client::new()
+        .post(format!("{}/oauth/token", NOTION_API))
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .basic_auth(client_id, Some(client_secret))
+        .json(&body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Notion OAuth token exchange returned {}", response.status()));
+    }
+    let token: Value = response.json().await?;
+    token["access_token"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("Notion OAuth response has no access token"))
+}
+
+/// A Notion workspace, read through an integration's token
+pub struct NotionConnector {
+    access_token: String,
+    config: NotionConfig,
+    client: reqwest::Client,
+}
+
+impl NotionConnector {
+    pub fn new(access_token: &str, config: &Value) -> Result<Self, String> {
+        let config: NotionConfig = if config.is_null() {
+            NotionConfig::default()
+        } else {
+            serde_json::from_value(config.clone()).map_err(|e| format!("Invalid Notion config: {}", e))?
+        };
+        if let Some(id) = config.root_page_ids.iter().find(|id| !is_valid_page_id(id)) {
+            return Err(format!("Invalid Notion page id '{}'", id));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { access_token: access_token.trim().to_string(), config, client })
+    }
+
+    // Call the API, waiting out rate limits
+    async fn request(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{}/{}", NOTION_API, path);
+        let mut retries = 0;
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), &url)
+                .bearer_auth(&self.access_token)
+                .header("Notion-Version", NOTION_VERSION);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request.send().await?;
+
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS && retries < MAX_RATE_LIMIT_RETRIES {
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(1)
+                    .min(MAX_RETRY_AFTER_SECS);
+                retries += 1;
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(anyhow::anyhow!("{} returned {}", url, response.status()));
+            }
+            return Ok(response.json().await?);
+        }
+    }
+
+    // Pages shared with the integration, most recently edited first, down to `since`. Notion keeps
+    // edit times to the minute, so pages edited in the cursor's minute are fetched again
+    async fn search_pages(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Value>> {
+        let mut pages = Vec::new();
+        let mut start_cursor: Option<String> = None;
+        for _ in 0..MAX_RESULT_PAGES {
+            let mut body = json!({
+                "filter": { "property": "object", "value": "page" },
+                "sort": { "direction": "descending", "timestamp": "last_edited_time" },
+                "page_size": PAGE_SIZE,
+            });
+            if let Some(cursor) = &start_cursor {
+                body["start_cursor"] = json!(cursor);
+            }
+            let response: ListResponse = serde_json::from_value(self.request(Method::POST, "search", Some(&body)).await?)?;
+
+            let mut reached_since = false;
+            for page in response.results {
+                if let (Some(since), Some(edited)) = (since, edited_at(&page))
+                    && edited < since
+                {
+                    reached_since = true;
+                    break;
+                }
+                pages.push(page);
+            }
+            start_cursor = response.next_cursor.filter(|_| response.has_more);
+            if reached_since || start_cursor.is_none() {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+
+    // A block's children, with theirs, down to `MAX_BLOCK_DEPTH`. Sub-pages and databases are not
+    // entered
+    fn fetch_blocks<'a>(
+        &'a self,
+        block_id: &'a str,
+        depth: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<NotionBlock>>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut blocks = Vec::new();
+            let mut start_cursor: Option<String> = None;
+            for _ in 0..MAX_RESULT_PAGES {
+                let mut path = format!("blocks/{}/children?page_size={}", block_id, PAGE_SIZE);
+                if let Some(cursor) = &start_cursor {
+                    path.push_str(&format!("&start_cursor={}", cursor));
+                }
+                let response: ListResponse = serde_json::from_value(self.request(Method::GET, &path, None).await?)?;
+                for block in response.results {
+                    let mut node = NotionBlock { block, children: Vec::new() };
+                    let nested = node.block["has_children"].as_bool().unwrap_or(false)
+                        && !matches!(node.kind(), "child_page" | "child_database");
+                    if nested && depth < MAX_BLOCK_DEPTH {
+                        let id = node.block["id"].as_str().unwrap_or_default().to_string();
+                        node.children = self.fetch_blocks(&id, depth + 1).await?;
+                    }
+                    blocks.push(node);
+                }
+                start_cursor = response.next_cursor.filter(|_| response.has_more);
+                if start_cursor.is_none() {
+                    break;
+                }
+            }
+            Ok(blocks)
+        })
+    }
+
+    fn page_document(page: &Value, blocks: &[NotionBlock]) -> ConnectorDocument {
+        ConnectorDocument {
+            key: page["id"].as_str().unwrap_or_default().to_string(),
+            title: page_title(page),
+            url: page["url"].as_str().map(str::to_string),
+            metadata: Vec::new(),
+            text: blocks_to_markdown(blocks),
+        }
+    }
+
+    // Every page shared with the integration, or those edited since the cursor
+    async fn sync_workspace(&self, since: Option<DateTime<Utc>>) -> Result<ConnectorChanges> {
+        let pages = self.search_pages(since).await?;
+        let mut changes = ConnectorChanges {
+            listed: since.is_none().then(HashSet::new),
+            cursor: pages.iter().filter_map(edited_at).max().map(|time| time.to_rfc3339()),
+            ..Default::default()
+        };
+        for page in &pages {
+            let Some(id) = page["id"].as_str() else { continue };
+            if is_archived(page) {
+                changes.removed.push(id.to_string());
+                continue;
+            }
+            if let Some(listed) = &mut changes.listed {
+                listed.insert(id.to_string());
+            }
+            let blocks = self.fetch_blocks(id, 0).await?;
+            changes.updated.push(Self::page_document(page, &blocks));
+        }
+        Ok(changes)
+    }
+
+    // Walk the root pages and their sub-pages. Every page is visited to find its sub-pages, so
+    // the listing is always complete, but only pages edited since the cursor are returned
+    async fn sync_tree(&self, since: Option<DateTime<Utc>>) -> Result<ConnectorChanges> {
+        let mut changes = ConnectorChanges { listed: Some(HashSet::new()), ..Default::default() };
+        let mut latest: Option<DateTime<Utc>> = None;
+        let mut queue: VecDeque<String> = self.config.root_page_ids.iter().cloned().collect();
+        let mut seen = HashSet::new();
+
+        while let Some(id) = queue.pop_front() {
+            if seen.len() >= MAX_TRAVERSED_PAGES {
+                return Err(anyhow::anyhow!("More than {} pages under the root pages", MAX_TRAVERSED_PAGES));
+            }
+            if !seen.insert(id.replace('-', "")) {
+                continue;
+            }
+            let page = self.request(Method::GET, &format!("pages/{}", id), None).await?;
+            let key = page["id"].as_str().unwrap_or(&id).to_string();
+            if is_archived(&page) {
+                changes.removed.push(key);
+                continue;
+            }
+
+            let blocks = self.fetch_blocks(&key, 0).await?;
+            let mut children = Vec::new();
+            child_page_ids(&blocks, &mut children);
+            queue.extend(children);
+
+            let edited = edited_at(&page);
+            latest = latest.max(edited);
+            if let Some(listed) = &mut changes.listed {
+                listed.insert(key.clone());
+            }
+            if since.is_none_or(|since| edited.is_none_or(|edited| edited >= since)) {
+                changes.updated.push(Self::page_document(&page, &blocks));
+            }
+        }
+        changes.cursor = latest.map(|time| time.to_rfc3339());
+        Ok(changes)
+    }
+}
+
+impl Connector for NotionConnector {
+    async fn sync(&self, cursor: Option<&str>) -> Result<ConnectorChanges> {
+        let since = match cursor {
+            Some(cursor) => Some(
+                DateTime::parse_from_rfc3339(cursor)
+                    .map_err(|e| anyhow::anyhow!("Invalid Notion cursor '{}': {}", cursor, e))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        if self.config.root_page_ids.is_empty() {
+            self.sync_workspace(since).await
+        } else {
+            self.sync_tree(since).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> Value {
+        json!([{ "plain_text": content, "annotations": {}, "href": null }])
+    }
+
+    fn block(kind: &str, data: Value, children: Vec<NotionBlock>) -> NotionBlock {
+        let mut block = json!({ "id": "b", "type": kind });
+        block[kind] = data;
+        NotionBlock { block, children }
+    }
+
+    #[test]
+    fn test_rich_text_to_markdown() {
+        let spans = json!([
+            { "plain_text": "Open ", "annotations": {} },
+            { "plain_text": "Settings", "annotations": { "bold": true } },
+            { "plain_text": " and read ", "annotations": {} },
+            { "plain_text": "the guide", "annotations": { "italic": true }, "href": "https://example.com/guide" },
+            { "plain_text": "run()", "annotations": { "code": true } },
+        ]);
+        assert_eq!(
+            rich_text_to_markdown(&spans),
+            "Open **Settings** and read [*the guide*](https://example.com/guide)`run()`"
+        );
+        assert_eq!(rich_text_to_markdown(&Value::Null), "");
+    }
+
+    #[test]
+    fn test_blocks_to_markdown() {
+        let blocks = vec![
+            block("heading_1", json!({ "rich_text": text("Onboarding") }), vec![]),
+            block("paragraph", json!({ "rich_text": text("Welcome aboard.") }), vec![]),
+            block("numbered_list_item", json!({ "rich_text": text("Get a laptop") }), vec![
+                block("bulleted_list_item", json!({ "rich_text": text("Mac or Linux") }), vec![]),
+            ]),
+            block("numbered_list_item", json!({ "rich_text": text("Meet your buddy") }), vec![]),
+            block("to_do", json!({ "rich_text": text("Sign the handbook"), "checked": true }), vec![]),
+            block("code", json!({ "rich_text": text("make setup"), "language": "shell" }), vec![]),
+            block("child_page", json!({ "title": "Benefits" }), vec![]),
+            block("paragraph", json!({ "rich_text": [] }), vec![]),
+            block("divider", json!({}), vec![]),
+        ];
+        assert_eq!(
+            blocks_to_markdown(&blocks),
+            "# Onboarding\nWelcome aboard.\n1. Get a laptop\n  - Mac or Linux\n2. Meet your buddy\n\
+             - [x] Sign the handbook\n```shell\nmake setup\n```\n---"
+        );
+    }
+
+    #[test]
+    fn test_table_to_markdown() {
+        let row = |cells: Vec<&str>| {
+            block("table_row", json!({ "cells": cells.into_iter().map(text).collect::<Vec<_>>() }), vec![])
+        };
+        let table = block("table", json!({ "table_width": 2 }), vec![row(vec!["Plan", "Price"]), row(vec!["Pro", "$10|mo"])]);
+        assert_eq!(blocks_to_markdown(&[table]), "| Plan | Price |\n| --- | --- |\n| Pro | $10\\|mo |");
+    }
+
+    #[test]
+    fn test_child_page_ids_are_found_in_nested_blocks() {
+        let page = |id: &str| NotionBlock { block: json!({ "id": id, "type": "child_page", "child_page": {} }), children: vec![] };
+        let blocks = vec![page("p1"), block("toggle", json!({ "rich_text": text("More") }), vec![page("p2")])];
+        let mut ids = Vec::new();
+        child_page_ids(&blocks, &mut ids);
+        assert_eq!(ids, vec!["p1".to_string(), "p2".to_string()]);
+    }
+
+    #[test]
+    fn test_page_title() {
+        let page = json!({ "properties": { "Name": { "type": "title", "title": text("Team wiki") }, "Tags": { "type": "multi_select" } } });
+        assert_eq!(page_title(&page), "Team wiki");
+        assert_eq!(page_title(&json!({ "properties": {} })), "Untitled");
+    }
+
+    #[test]
+    fn test_page_id_validation() {
+        assert!(is_valid_page_id("59833787-2cf9-4fdf-8782-e53db20768a5"));
+        assert!(is_valid_page_id("598337872cf94fdf8782e53db20768a5"));
+        assert!(!is_valid_page_id("598337872cf94fdf"));
+        assert!(!is_valid_page_id("../../users/me"));
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+const DEFAULT_SCOPES: &str = "openid email profile";
+const DEFAULT_GROUPS_CLAIM: &str = "groups";
+const DEFAULT_SESSION_TTL_SECS: u64 = 8 * 3600;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+/// How long a user has to finish signing in at the identity provider
+pub const LOGIN_STATE_MAX_AGE_SECS: i64 = 600;
+
+/// What an admin session may do. Viewers can only call read-only admin endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AdminRole {
+    Viewer,
+    Admin,
+}
+
+impl AdminRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for AdminRole {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(AdminRole::Viewer),
+            "admin" => Ok(AdminRole::Admin),
+            other => Err(format!("unknown admin role '{}'; use admin or viewer", other)),
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse `OIDC_ROLE_MAPPING`, e.g. `rag-admins=admin,support=viewer`
+pub fn parse_role_mapping(raw: &str) -> Result<Vec<(String, AdminRole)>, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (group, role) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| format!("role mapping entry '{}' must look like group=role", entry))?;
+            let group = group.trim();
+            if group.is_empty() {
+                return Err(format!("role mapping entry '{}' has no group", entry));
+            }
+            Ok((group.to_string(), role.trim().parse()?))
+        })
+        .collect()
+}
+
+/// The highest role any of the user's groups maps to
+pub fn role_for_groups(mapping: &[(String, AdminRole)], groups: &[String]) -> Option<AdminRole> {
+    mapping
+        .iter()
+        .filter(|(group, _)| groups.contains(group))
+        .map(|(_, role)| *role)
+        .max()
+}
+
+// Groups from a claim holding either a list of names or a single name
+fn claim_groups(claims: &Map<String, Value>, claim: &str) -> Vec<String> {
+    match claims.get(claim) {
+        Some(Value::Array(values)) => values.iter().filter_map(|v| v.as_str()).map(str::to_string).collect(),
+        Some(Value::String(group)) => vec![group.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Single sign-on settings for the admin API, from `OIDC_*` environment variables
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub scopes: String,
+    pub groups_claim: String,
+    pub role_mapping: Vec<(String, AdminRole)>,
+    pub session_ttl: Duration,
+}
+
+impl OidcConfig {
+    /// `None` when `OIDC_ISSUER_URL` is unset. Fails if SSO is only partly configured
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(issuer_url) = std::env::var("OIDC_ISSUER_URL") else {
+            return Ok(None);
+        };
+        let required = |name: &str| {
+            std::env::var(name).map_err(|_| anyhow::anyhow!("{} must be set when OIDC_ISSUER_URL is set", name))
+        };
+        let client_id = required("OIDC_CLIENT_ID")?;
+        let client_secret = required("OIDC_CLIENT_SECRET")?;
+        let redirect_url = required("OIDC_REDIRECT_URL")?;
+        let role_mapping = parse_role_mapping(&required("OIDC_ROLE_MAPPING")?)
+            .map_err(|e| anyhow::anyhow!("Invalid OIDC_ROLE_MAPPING: {}", e))?;
+        if role_mapping.is_empty() {
+            return Err(anyhow::anyhow!("OIDC_ROLE_MAPPING must map at least one group"));
+        }
+        for (name, value) in [("OIDC_ISSUER_URL", &issuer_url), ("OIDC_REDIRECT_URL", &redirect_url)] {
+            Url::parse(value).map_err(|_| anyhow::anyhow!("{} is not a valid URL: '{}'", name, value))?;
+        }
+
+        let session_ttl_secs = std::env::var("ADMIN_SESSION_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+
+        Ok(Some(Self {
+            issuer_url: issuer_url.trim_end_matches('/').to_string(),
+            client_id,
+            client_secret,
+            redirect_url,
+            scopes: std::env::var("OIDC_SCOPES").unwrap_or_else(|_| DEFAULT_SCOPES.to_string()),
+            groups_claim: std::env::var("OIDC_GROUPS_CLAIM").unwrap_or_else(|_| DEFAULT_GROUPS_CLAIM.to_string()),
+            role_mapping,
+            session_ttl: Duration::from_secs(session_ttl_secs),
+        }))
+    }
+}
+
+/// Endpoints from the provider's discovery document
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// Values kept between redirecting to the provider and its callback
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+impl PendingLogin {
+    pub fn generate() -> Self {
+        let random = || Uuid::new_v4().simple().to_string();
+        Self {
+            state: random(),
+            nonce: random(),
+            // PKCE verifiers need at least 43 characters
+            code_verifier: format!("{}{}", random(), random()),
+        }
+    }
+}
+
+/// PKCE S256 challenge for a verifier
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// The provider's sign-in page for an authorization code request
+pub fn authorization_url(config: &OidcConfig, metadata: &ProviderMetadata, login: &PendingLogin) -> Result<String> {
+    let mut url = Url::parse(&metadata.authorization_endpoint)?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_url)
+        .append_pair("scope", &config.scopes)
+        .append_pair("state", &login.state)
+        .append_pair("nonce", &login.nonce)
+        .append_pair("code_challenge", &code_challenge(&login.code_verifier))
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
+}
+
+/// Who signed in, from a verified ID token
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub subject: String,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+    nonce: Option<String>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+/// Talks to the identity provider for the authorization code flow
+pub struct OidcClient {
+    config: OidcConfig,
+    client: Client,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+            .build()?;
+        Ok(Self { config, client })
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Fetch the discovery document; its issuer must match the configured one exactly
+    pub async fn discover(&self) -> Result<ProviderMetadata> {
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer_url);
+        let metadata: ProviderMetadata = self.client.get(&url).send().await?.error_for_status()?.json().await?;
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url {
+            return Err(anyhow::anyhow!(
+                "Discovery document issuer '{}' does not match OIDC_ISSUER_URL",
+                metadata.issuer
+            ));
+        }
+        Ok(metadata)
+    }
+
+    /// Trade the authorization code for tokens and verify the ID token it returns
+    pub async fn exchange_code(&self, metadata: &ProviderMetadata, code: &str, login: &PendingLogin) -> Result<Identity> {
+        let response = self
+            .client
+            .post(&metadata.token_endpoint)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("code_verifier", login.code_verifier.as_str()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            tracing::error!("Token endpoint returned {}: {}", status, error_text);
+            return Err(anyhow::anyhow!("Token exchange failed with status {}", status));
+        }
+        let tokens: TokenResponse = response.json().await?;
+
+        self.verify_id_token(metadata, &tokens.id_token, &login.nonce).await
+    }
+
+    // Check the ID token's signature against the provider's keys, then its issuer, audience, expiry and nonce
+    async fn verify_id_token(&self, metadata: &ProviderMetadata, id_token: &str, nonce: &str) -> Result<Identity> {
+        let header = decode_header(id_token)?;
+        // Shared-secret algorithms would let anyone holding the client secret mint tokens
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(anyhow::anyhow!("ID token uses unsupported algorithm {:?}", header.alg));
+        }
+
+        let jwks: JwkSet = self.client.get(&metadata.jwks_uri).send().await?.error_for_status()?.json().await?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("No signing key matches the ID token"))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+        let claims = decode::<IdTokenClaims>(id_token, &DecodingKey::from_jwk(jwk)?, &validation)?.claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(anyhow::anyhow!("ID token nonce does not match the login request"));
+        }
+
+        Ok(Identity {
+            subject: claims.sub,
+            email: claims.email,
+            groups: claim_groups(&claims.other, &self.config.groups_claim),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_role_mapping() {
+        let mapping = parse_role_mapping("rag-admins=admin, support = viewer,").unwrap();
+        assert_eq!(
+            mapping,
+            vec![("rag-admins".to_string(), AdminRole::Admin), ("support".to_string(), AdminRole::Viewer)]
+        );
+        assert!(parse_role_mapping("rag-admins").is_err());
+        assert!(parse_role_mapping("rag-admins=owner").is_err());
+        assert!(parse_role_mapping("=admin").is_err());
+    }
+
+    #[test]
+    fn test_role_for_groups_picks_highest() {
+        let mapping = parse_role_mapping("support=viewer,rag-admins=admin").unwrap();
+        let groups = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(role_for_groups(&mapping, &groups(&["support", "rag-admins"])), Some(AdminRole::Admin));
+        assert_eq!(role_for_groups(&mapping, &groups(&["support"])), Some(AdminRole::Viewer));
+        assert_eq!(role_for_groups(&mapping, &groups(&["everyone"])), None);
+    }
+
+    #[test]
+    fn test_claim_groups_accepts_list_or_string() {
+        let claims = json!({ "groups": ["a", "b", 3], "role": "admins" });
+        let claims = claims.as_object().unwrap();
+        assert_eq!(claim_groups(claims, "groups"), vec!["a", "b"]);
+        assert_eq!(claim_groups(claims, "role"), vec!["admins"]);
+        assert!(claim_groups(claims, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_code_challenge_matches_rfc7636_example() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gXFrsFEtM"),
+            "E9Melhoa2OwvRrWbgwQNSQN9RNMyI3sPFgO3cUWS8NA"
+        );
+    }
+
+    #[test]
+    fn test_authorization_url_carries_pkce_and_state() {
+        let config = OidcConfig {
+            issuer_url: "https://idp.example.com".to_string(),
+            client_id: "rag".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://rag.example.com/api/admin/sso/callback".to_string(),
+            scopes: DEFAULT_SCOPES.to_string(),
+            groups_claim: DEFAULT_GROUPS_CLAIM.to_string(),
+            role_mapping: Vec::new(),
+            session_ttl: Duration::from_secs(60),
+        };
+        let metadata = ProviderMetadata {
+            issuer: "https://idp.example.com".to_string(),
+            authorization_endpoint: "https://idp.example.com/authorize".to_string(),
+            token_endpoint: "https://idp.example.com/token".to_string(),
+            jwks_uri: "https://idp.example.com/jwks".to_string(),
+        };
+        let login = PendingLogin::generate();
+
+        let url = Url::parse(&authorization_url(&config, &metadata, &login).unwrap()).unwrap();
+        let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["state"], login.state);
+        assert_eq!(params["code_challenge"], code_challenge(&login.code_verifier));
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["redirect_uri"], config.redirect_url);
+        assert!(login.code_verifier.len() >= 43);
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::models::{FilterAction, FilterStage, OutputFilterConfig, OutputFilterRule};
+use crate::db::queries::insert_output_filter_incidents;
+use crate::services::shutdown::BackgroundJobs;
+
+pub const DEFAULT_BLOCK_MESSAGE: &str = "I'm sorry, I can't share that answer.";
+pub const DEFAULT_QUERY_BLOCK_MESSAGE: &str = "I'm sorry, I can't help with that request.";
+const DEFAULT_REPLACEMENT: &str = "***";
+const DEFAULT_CLASSIFIER_TIMEOUT_SECS: u64 = 10;
+
+const MAX_RULES: usize = 100;
+// Compiled size limit per rule, so a huge word list or pattern can't eat memory
+const MAX_REGEX_SIZE: usize = 1024 * 1024;
+// Longest excerpt of matched text kept on an incident
+const MAX_MATCHED_CHARS: usize = 200;
+
+/// A rule that matched an answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterIncident {
+    pub rule_name: String,
+    pub action: FilterAction,
+    pub matched_text: String,
+}
+
+#[derive(Debug)]
+pub struct FilteredAnswer {
+    pub text: String,
+    pub blocked: bool,
+    pub incidents: Vec<FilterIncident>,
+}
+
+// One case-insensitive regex for the rule's words (as whole words) and pattern
+fn compile_rule(rule: &OutputFilterRule) -> Result<Regex, String> {
+    let mut alternatives: Vec<String> = rule
+        .words
+        .iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!(r"\b{}\b", regex::escape(word)))
+        .collect();
+    if let Some(pattern) = rule.pattern.as_deref().filter(|p| !p.is_empty()) {
+        alternatives.push(format!("(?:{})", pattern));
+    }
+    if alternatives.is_empty() {
+        return Err(format!("Filter '{}' needs words or a pattern", rule.name));
+    }
+
+    RegexBuilder::new(&alternatives.join("|"))
+        .case_insensitive(true)
+        .size_limit(MAX_REGEX_SIZE)
+        .build()
+        .map_err(|e| format!("Filter '{}' has an invalid pattern: {}", rule.name, e))
+}
+
+/// Check a chatbot's filters before storing them
+pub fn validate_filters(config: &OutputFilterConfig) -> Result<(), String> {
+    if config.rules.len() > MAX_RULES {
+        return Err(format!("At most {} filters are allowed", MAX_RULES));
+    }
+    for rule in &config.rules {
+        if rule.name.trim().is_empty() {
+            return Err("Every filter needs a name".to_string());
+        }
+        compile_rule(rule)?;
+    }
+    if config.classifier && std::env::var("OUTPUT_CLASSIFIER_URL").is_err() {
+        return Err("The classifier is not configured on this server (OUTPUT_CLASSIFIER_URL)".to_string());
+    }
+    Ok(())
+}
+
+fn excerpt(text: &str) -> String {
+    text.chars().take(MAX_MATCHED_CHARS).collect()
+}
+
+fn custom_block_message(config: &OutputFilterConfig) -> Option<&str> {
+    config.block_message.as_deref().map(str::trim).filter(|message| !message.is_empty())
+}
+
+fn block_message(config: &OutputFilterConfig) -> String {
+    custom_block_message(config).unwrap_or(DEFAULT_BLOCK_MESSAGE).to_string()
+}
+
+/// Run the word list and regex rules in order. Replace rules mask their matches;
+/// any block rule that matches replaces the whole answer with the block message
+pub fn apply_rules(config: &OutputFilterConfig, answer: &str) -> Result<FilteredAnswer, String> {
+    let mut text = answer.to_string();
+    let mut incidents = Vec::new();
+
+    for rule in &config.rules {
+        let regex = compile_rule(rule)?;
+        let matches: Vec<String> = regex.find_iter(&text).map(|m| excerpt(m.as_str())).collect();
+        if matches.is_empty() {
+            continue;
+        }
+
+        if rule.action == FilterAction::Replace {
+            let replacement = rule.replacement.as_deref().unwrap_or(DEFAULT_REPLACEMENT);
+            text = regex.replace_all(&text, regex::NoExpand(replacement)).into_owned();
+        }
+        incidents.extend(matches.into_iter().map(|matched_text| FilterIncident {
+            rule_name: rule.name.clone(),
+            action: rule.action,
+            matched_text,
+        }));
+    }
+
+    let blocked = incidents.iter().any(|incident| incident.action == FilterAction::Block);
+    if blocked {
+        text = block_message(config);
+    }
+    Ok(FilteredAnswer { text, blocked, incidents })
+}
+
+#[derive(Debug, Serialize)]
+struct ClassifierRequest<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClassifierResponse {
+    flagged: bool,
+    label: Option<String>,
+}
+
+// Ask the external classifier about an answer; returns its label when the answer is flagged
+async fn classify(url: &str, text: &str) -> Result<Option<String>> {
+    let timeout_secs = std::env::var("OUTPUT_CLASSIFIER_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CLASSIFIER_TIMEOUT_SECS);
+
+    let response: ClassifierResponse = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .json(&ClassifierRequest { text })
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(response
+        .flagged
+        .then(|| response.label.unwrap_or_else(|| "flagged".to_string())))
+}
+
+/// Apply a chatbot's rules and, if enabled, the classifier to a generated answer
+pub async fn filter_answer(config: &OutputFilterConfig, answer: &str) -> Result<FilteredAnswer> {
+    let mut filtered = apply_rules(config, answer).map_err(|e| anyhow::anyhow!(e))?;
+    if filtered.blocked || !config.classifier {
+        return Ok(filtered);
+    }
+
+    let Ok(url) = std::env::var("OUTPUT_CLASSIFIER_URL") else {
+        return Err(anyhow::anyhow!("Output classifier enabled but OUTPUT_CLASSIFIER_URL is not set"));
+    };
+    if let Some(label) = classify(&url, &filtered.text).await? {
+        filtered.incidents.push(FilterIncident {
+            rule_name: format!("classifier:{}", label),
+            action: FilterAction::Block,
+            matched_text: excerpt(&filtered.text),
+        });
+        filtered.text = block_message(config);
+        filtered.blocked = true;
+    }
+    Ok(filtered)
+}
+
+/// Screen a user query with a chatbot's query filters before it reaches retrieval or the model.
+/// Replace rules mask the query; a blocked query's text is the message to reply with instead
+pub async fn moderate_query(config: &OutputFilterConfig, query: &str) -> Result<FilteredAnswer> {
+    let mut filtered = filter_answer(config, query).await?;
+    if filtered.blocked && custom_block_message(config).is_none() {
+        filtered.text = DEFAULT_QUERY_BLOCK_MESSAGE.to_string();
+    }
+    Ok(filtered)
+}
+
+/// Save incidents in the background so the answer isn't delayed. Blocked queries have no conversation
+pub fn record_incidents(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    chatbot_id: Uuid,
+    stage: FilterStage,
+    conversation_id: Option<Uuid>,
+    incidents: Vec<FilterIncident>,
+) {
+    if incidents.is_empty() {
+        return;
+    }
+    tracing::warn!(
+        "⚠️ {} filters matched {} time(s) for chatbot {}",
+        stage.as_str(),
+        incidents.len(),
+        chatbot_id
+    );
+
+    jobs.spawn(async move {
+        if let Err(e) = insert_output_filter_incidents(&db, chatbot_id, stage, conversation_id, &incidents).await {
+            tracing::warn!("⚠️ Failed to record output filter incidents: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, words: &[&str], pattern: Option<&str>, action: FilterAction) -> OutputFilterRule {
+        OutputFilterRule {
+            name: name.to_string(),
+            words: words.iter().map(|w| w.to_string()).collect(),
+            pattern: pattern.map(str::to_string),
+            action,
+            replacement: None,
+        }
+    }
+
+    fn config(rules: Vec<OutputFilterRule>) -> OutputFilterConfig {
+        OutputFilterConfig { rules, classifier: false, block_message: None }
+    }
+
+    #[test]
+    fn test_replace_masks_whole_words_case_insensitively() {
+        let config = config(vec![rule("profanity", &["darn", "heck"], None, FilterAction::Replace)]);
+        let filtered = apply_rules(&config, "Darn, the checkout is down. Heck!").unwrap();
+
+        assert_eq!(filtered.text, "***, the checkout is down. ***!");
+        assert!(!filtered.blocked);
+        assert_eq!(filtered.incidents.len(), 2);
+        assert_eq!(filtered.incidents[0].matched_text, "Darn");
+    }
+
+    #[test]
+    fn test_block_replaces_the_answer() {
+        let mut config = config(vec![
+            rule("competitors", &["Globex"], None, FilterAction::Block),
+            rule("card numbers", &[], Some(r"\d{4}-\d{4}-\d{4}-\d{4}"), FilterAction::Replace),
+        ]);
+        config.block_message = Some("Please contact support.".to_string());
+
+        let filtered = apply_rules(&config, "Try Globex, card 1234-5678-9012-3456").unwrap();
+        assert!(filtered.blocked);
+        assert_eq!(filtered.text, "Please contact support.");
+        assert_eq!(filtered.incidents.len(), 2);
+
+        let clean = apply_rules(&config, "All good").unwrap();
+        assert_eq!(clean.text, "All good");
+        assert!(clean.incidents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_moderate_query() {
+        let mut config = config(vec![
+            rule("threats", &["kill"], None, FilterAction::Block),
+            rule("emails", &[], Some(r"[\w.]+@[\w.]+"), FilterAction::Replace),
+        ]);
+
+        let masked = moderate_query(&config, "My email is jo@example.com").await.unwrap();
+        assert!(!masked.blocked);
+        assert_eq!(masked.text, "My email is ***");
+
+        let blocked = moderate_query(&config, "I will kill the server").await.unwrap();
+        assert!(blocked.blocked);
+        assert_eq!(blocked.text, DEFAULT_QUERY_BLOCK_MESSAGE);
+
+        config.block_message = Some("Please keep it civil.".to_string());
+        assert_eq!(moderate_query(&config, "kill").await.unwrap().text, "Please keep it civil.");
+    }
+
+    #[test]
+    fn test_validate_filters() {
+        assert!(validate_filters(&config(vec![rule("ok", &["a"], Some("b+"), FilterAction::Replace)])).is_ok());
+        assert!(validate_filters(&config(vec![rule("empty", &[], None, FilterAction::Block)])).is_err());
+        assert!(validate_filters(&config(vec![rule("bad", &[], Some("(unclosed"), FilterAction::Block)])).is_err());
+        assert!(validate_filters(&config(vec![rule(" ", &["a"], None, FilterAction::Block)])).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::db::queries::{fail_generation, fail_stale_generations, save_partial_response, update_conversation_response};
+use crate::services::shutdown::BackgroundJobs;
+
+const DEFAULT_SAVE_INTERVAL_MS: u64 = 2000;
+const DEFAULT_STALE_SECS: i64 = 300;
+const SWEEP_INTERVAL_SECS: u64 = 60;
+
+/// A streamed answer, saved to its conversation every `PARTIAL_SAVE_INTERVAL_MS` while it streams so
+/// a crash loses at most that much of it. Dropping it before `complete` or `fail`, as happens when
+/// the client disconnects, marks the generation failed with what was streamed so far
+pub struct PartialResponse {
+    jobs: BackgroundJobs,
+    db: Arc<PgPool>,
+    organization_id: Uuid,
+    conversation_id: Uuid,
+    text: String,
+    save_interval: Duration,
+    last_saved: Option<Instant>,
+    finished: bool,
+}
+
+impl PartialResponse {
+    pub fn new(jobs: BackgroundJobs, db: Arc<PgPool>, organization_id: Uuid, conversation_id: Uuid) -> Self {
+        let save_interval = std::env::var("PARTIAL_SAVE_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SAVE_INTERVAL_MS);
+        Self {
+            jobs,
+            db,
+            organization_id,
+            conversation_id,
+            text: String::new(),
+            save_interval: Duration::from_millis(save_interval),
+            last_saved: None,
+            finished: false,
+        }
+    }
+
+    /// Append a chunk, saving the answer so far if the last save is older than the interval. The
+    /// first chunk is saved right away, which moves the conversation to `streaming`
+    pub fn push(&mut self, text: &str) {
+        if self.finished || text.is_empty() {
+            return;
+        }
+        self.text.push_str(text);
+        if self.last_saved.is_some_and(|saved| saved.elapsed() < self.save_interval) {
+            return;
+        }
+        self.last_saved = Some(Instant::now());
+
+        let (db, conversation_id, partial) = (self.db.clone(), self.conversation_id, self.text.clone());
+        self.jobs.spawn(async move {
+            if let Err(e) = save_partial_response(&db, conversation_id, &partial).await {
+                tracing::warn!("⚠️ Failed to save partial response of {}: {}", conversation_id, e);
+            }
+        });
+    }
+
+    /// Append the final chunk and save the whole answer as complete
+    pub fn complete(&mut self, text: &str) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.text.push_str(text);
+
+        let (db, organization_id, conversation_id) = (self.db.clone(), self.organization_id, self.conversation_id);
+        let answer = std::mem::take(&mut self.text);
+        self.jobs.spawn(async move {
+            if let Err(e) = update_conversation_response(&db, organization_id, conversation_id, answer).await {
+                tracing::error!("❌ Failed to save response of {}: {}", conversation_id, e);
+            }
+        });
+    }
+
+    /// Mark the generation failed, keeping what was streamed before it stopped
+    pub fn fail(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        let (db, conversation_id) = (self.db.clone(), self.conversation_id);
+        let partial = Some(std::mem::take(&mut self.text)).filter(|text| !text.is_empty());
+        self.jobs.spawn(async move {
+            if let Err(e) = fail_generation(&db, conversation_id, partial.as_deref()).await {
+                tracing::warn!("⚠️ Failed to mark generation of {} failed: {}", conversation_id, e);
+            }
+        });
+    }
+}
+
+impl Drop for PartialResponse {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::warn!("⚠️ Response stream of {} ended before the answer was complete", self.conversation_id);
+            self.fail();
+        }
+    }
+}
+
+// Spawn the background task that fails generations left pending or streaming by a server that
+// crashed or restarted mid-answer
+pub fn spawn_stale_generation_task(jobs: &BackgroundJobs, db: Arc<PgPool>) {
+    let stale_secs = std::env::var("GENERATION_STALE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_STALE_SECS);
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SWEEP_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            match fail_stale_generations(&db, stale_secs).await {
+                Ok(0) => {}
+                Ok(count) => tracing::warn!("⚠️ Marked {} interrupted generations failed", count),
+                Err(e) => tracing::error!("❌ Stale generation task failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::db::models::VectorChunkText;
+use crate::db::queries::{
+    count_vector_chunks, create_vector_collection, delete_vector_chunks_by_file, delete_vector_collections,
+    list_vector_chunks_by_file, list_vector_chunks_by_parent, list_vector_chunks_page, list_vector_collections,
+    ping_database, resolve_vector_alias, search_vector_chunks, set_vector_collections_closed, swap_vector_aliases,
+    upsert_vector_chunks,
+};
+use crate::db::run_pgvector_migrations;
+use crate::services::vector::{merge_collection_hits, ChunkPage, DocumentWithEmbedding, SearchResult, VectorStore};
+
+/// pgvector's text form of an embedding, e.g. `[0.1,0.2]`
+pub fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+// Parse pgvector's text form back into an embedding
+fn parse_vector_literal(literal: &str) -> Result<Vec<f32>> {
+    let inner = literal
+        .trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| anyhow::anyhow!("Malformed vector: {}", literal))?;
+    if inner.is_empty() {
+        return Ok(Vec::new());
+    }
+    inner
+        .split(',')
+        .map(|value| value.trim().parse::<f32>().map_err(|e| anyhow::anyhow!("Malformed vector value '{}': {}", value, e)))
+        .collect()
+}
+
+// Turn a `*` wildcard pattern into a LIKE pattern; collection names contain `_`, which LIKE treats as a wildcard
+fn like_pattern(pattern: &str) -> String {
+    let mut like = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        match c {
+            '*' => like.push('%'),
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            _ => like.push(c),
+        }
+    }
+    like
+}
+
+fn chunk_without_embedding(chunk: VectorChunkText) -> DocumentWithEmbedding {
+    DocumentWithEmbedding {
+        id: chunk.id,
+        text: chunk.text,
+        embedding: Vec::new(),
+        chunk_index: chunk.chunk_index,
+        file_path: chunk.file_path,
+        chunk_count: chunk.chunk_count,
+        parent_index: chunk.parent_index,
+    }
+}
+
+/// Vector store in the application's Postgres database, for deployments without Elasticsearch.
+/// Search is an exact nearest-neighbour scan of one collection
+pub struct PgVectorStore {
+    pool: Arc<PgPool>,
+}
+
+impl PgVectorStore {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Install the extension and tables. Fails if the Postgres server doesn't have pgvector
+    pub async fn init(&self) -> Result<()> {
+        run_pgvector_migrations(&self.pool).await
+    }
+}
+
+impl VectorStore for PgVectorStore {
+    async fn create_collection(&self, name: &str, embedding_dim: usize) -> Result<()> {
+        create_vector_collection(&self.pool, name, embedding_dim as i32).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "pgvector.index_documents", skip(self, documents), fields(count = documents.len()))]
+    async fn index_documents(&self, collection: &str, documents: Vec<DocumentWithEmbedding>) -> Result<usize> {
+        let stored = upsert_vector_chunks(&self.pool, collection, &documents).await?;
+        tracing::info!("✅ Stored {} chunks in collection '{}'", stored, collection);
+        Ok(stored as usize)
+    }
+
+    #[tracing::instrument(name = "pgvector.search", skip(self, query_embedding))]
+    async fn search_similar(&self, collection: &str, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<SearchResult>> {
+        let results = search_vector_chunks(&self.pool, collection, &vector_literal(&query_embedding), limit as i64).await?;
+        tracing::info!("Found {} similar documents", results.len());
+        Ok(results)
+    }
+
+    async fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        let mut searches = Vec::new();
+        for collection in collections {
+            let results = self.search_similar(collection, query_embedding.clone(), limit).await?;
+            searches.push((collection.clone(), results));
+        }
+        Ok(merge_collection_hits(searches, limit))
+    }
+
+    #[tracing::instrument(name = "pgvector.count", skip(self))]
+    async fn count_documents(&self, collections: &[String]) -> Result<u64> {
+        Ok(count_vector_chunks(&self.pool, collections).await? as u64)
+    }
+
+    async fn delete_collections(&self, collections: &[String]) -> Result<()> {
+        delete_vector_collections(&self.pool, collections).await?;
+        tracing::info!("✅ Deleted collections: {:?}", collections);
+        Ok(())
+    }
+
+    async fn close_collections(&self, collections: &[String]) -> Result<()> {
+        set_vector_collections_closed(&self.pool, collections, true).await?;
+        Ok(())
+    }
+
+    async fn open_collections(&self, collections: &[String]) -> Result<()> {
+        set_vector_collections_closed(&self.pool, collections, false).await?;
+        Ok(())
+    }
+
+    async fn list_collections(&self, pattern: &str) -> Result<Vec<String>> {
+        Ok(list_vector_collections(&self.pool, &like_pattern(pattern)).await?)
+    }
+
+    async fn fetch_document_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+    ) -> Result<Vec<(String, DocumentWithEmbedding)>> {
+        list_vector_chunks_by_file(&self.pool, collections, file_path)
+            .await?
+            .into_iter()
+            .map(|chunk| {
+                let embedding = parse_vector_literal(&chunk.embedding)?;
+                Ok((
+                    chunk.collection,
+                    DocumentWithEmbedding {
+                        id: chunk.id,
+                        text: chunk.text,
+                        embedding,
+                        chunk_index: chunk.chunk_index,
+                        file_path: chunk.file_path,
+                        chunk_count: chunk.chunk_count,
+                        parent_index: chunk.parent_index,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    async fn fetch_section_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> Result<Vec<DocumentWithEmbedding>> {
+        Ok(list_vector_chunks_by_parent(&self.pool, collections, file_path, parent_indices)
+            .await?
+            .into_iter()
+            .map(chunk_without_embedding)
+            .collect())
+    }
+
+    async fn delete_document_chunks(&self, collections: &[String], file_path: &str) -> Result<u64> {
+        Ok(delete_vector_chunks_by_file(&self.pool, collections, file_path).await?)
+    }
+
+    // The cursor is the last chunk id read
+    async fn scroll_chunks(&self, collection: &str, cursor: Option<&str>, limit: usize) -> Result<ChunkPage> {
+        let chunks: Vec<DocumentWithEmbedding> = list_vector_chunks_page(&self.pool, collection, cursor, limit as i64)
+            .await?
+            .into_iter()
+            .map(chunk_without_embedding)
+            .collect();
+
+        let cursor = if chunks.len() < limit { None } else { chunks.last().map(|chunk| chunk.id.clone()) };
+        Ok(ChunkPage { chunks, cursor })
+    }
+
+    async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        Ok(resolve_vector_alias(&self.pool, name).await?)
+    }
+
+    async fn swap_aliases(&self, swaps: &[(String, String)]) -> Result<Vec<String>> {
+        let previous = swap_vector_aliases(&self.pool, swaps).await?;
+        tracing::info!("✅ Swapped aliases: {:?}", swaps);
+        Ok(previous)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        Ok(ping_database(&self.pool).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_literal_round_trip() {
+        let embedding = vec![0.25, -1.0, 3.5];
+        assert_eq!(vector_literal(&embedding), "[0.25,-1,3.5]");
+        assert_eq!(parse_vector_literal("[0.25,-1,3.5]").unwrap(), embedding);
+        assert_eq!(parse_vector_literal("[]").unwrap(), Vec::<f32>::new());
+        assert!(parse_vector_literal("0.25,1").is_err());
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_underscores() {
+        assert_eq!(like_pattern("*chatbot_*"), "%chatbot\\_%");
+        assert_eq!(like_pattern("org_1%"), "org\\_1\\%");
+    }
+}
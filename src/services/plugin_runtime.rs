@@ -0,0 +1,355 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tracing;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::services::vector::SearchResult;
+
+/// Guest API version this host implements. Bump only for breaking changes to the exports below
+pub const PLUGIN_API_VERSION: i32 = 1;
+
+// Every plugin exports `memory`, `rag_alloc(len) -> ptr` and `rag_plugin_api_version() -> i32`,
+// plus at least one stage. Stages take a pointer/length pair to UTF-8 JSON input and return the
+// output location packed as `(ptr << 32) | len`
+const EXPORT_MEMORY: &str = "memory";
+const EXPORT_ALLOC: &str = "rag_alloc";
+const EXPORT_API_VERSION: &str = "rag_plugin_api_version";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// `rag_chunk`: `{"file_path", "text"}` -> `{"chunks": [string]}`
+    Chunk,
+    /// `rag_filter`: `{"query", "results"}` -> `{"indices": [int]}`, a subset of results to keep
+    Filter,
+    /// `rag_rerank`: `{"query", "results"}` -> `{"indices": [int]}`, results in their new order
+    Rerank,
+}
+
+impl Capability {
+    const ALL: [Capability; 3] = [Capability::Chunk, Capability::Filter, Capability::Rerank];
+
+    fn export_name(self) -> &'static str {
+        match self {
+            Capability::Chunk => "rag_chunk",
+            Capability::Filter => "rag_filter",
+            Capability::Rerank => "rag_rerank",
+        }
+    }
+}
+
+/// Resource limits applied to every plugin call
+#[derive(Debug, Clone, Copy)]
+pub struct PluginLimits {
+    pub fuel: u64,
+    pub max_memory_bytes: usize,
+}
+
+impl PluginLimits {
+    /// `PLUGIN_FUEL` (instructions, roughly) and `PLUGIN_MAX_MEMORY_MB`
+    pub fn from_env() -> Self {
+        Self {
+            fuel: std::env::var("PLUGIN_FUEL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1_000_000_000),
+            max_memory_bytes: std::env::var("PLUGIN_MAX_MEMORY_MB")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(64)
+                * 1024
+                * 1024,
+        }
+    }
+}
+
+pub struct Plugin {
+    pub name: String,
+    pub capabilities: Vec<Capability>,
+    module: Module,
+}
+
+#[derive(Serialize)]
+struct ChunkInput<'a> {
+    file_path: &'a str,
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChunkOutput {
+    chunks: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PluginSearchResult<'a> {
+    text: &'a str,
+    file_path: &'a str,
+    chunk_index: i64,
+    score: f32,
+}
+
+#[derive(Serialize)]
+struct ResultsInput<'a> {
+    query: &'a str,
+    results: Vec<PluginSearchResult<'a>>,
+}
+
+#[derive(Deserialize)]
+struct ResultsOutput {
+    indices: Vec<usize>,
+}
+
+/// Loaded WASM plugins. Each call gets a fresh, import-free instance, so plugins can't keep
+/// state between requests or reach the host
+pub struct PluginRuntime {
+    engine: Engine,
+    limits: PluginLimits,
+    plugins: Vec<Plugin>,
+}
+
+impl PluginRuntime {
+    /// Load every `.wasm` file in `dir`, in file name order
+    pub fn load_dir(dir: &Path, limits: PluginLimits) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read plugin directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+            .collect();
+        paths.sort();
+
+        let mut runtime = Self { engine, limits, plugins: Vec::new() };
+        for path in paths {
+            let plugin = runtime
+                .load_plugin(&path)
+                .with_context(|| format!("Failed to load plugin {}", path.display()))?;
+            tracing::info!("✅ Loaded plugin '{}' with stages {:?}", plugin.name, plugin.capabilities);
+            runtime.plugins.push(plugin);
+        }
+
+        Ok(runtime)
+    }
+
+    fn load_plugin(&self, path: &Path) -> Result<Plugin> {
+        let module = Module::from_file(&self.engine, path)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if let Some(import) = module.imports().next() {
+            anyhow::bail!(
+                "plugins may not import anything, found {}::{}",
+                import.module(),
+                import.name()
+            );
+        }
+
+        let exports: HashSet<&str> = module.exports().map(|export| export.name()).collect();
+        for required in [EXPORT_MEMORY, EXPORT_ALLOC, EXPORT_API_VERSION] {
+            if !exports.contains(required) {
+                anyhow::bail!("missing required export '{}'", required);
+            }
+        }
+
+        let capabilities: Vec<Capability> = Capability::ALL
+            .into_iter()
+            .filter(|capability| exports.contains(capability.export_name()))
+            .collect();
+        if capabilities.is_empty() {
+            anyhow::bail!("exports none of rag_chunk, rag_filter or rag_rerank");
+        }
+
+        let (mut store, instance) = self.instantiate(&module)?;
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, EXPORT_API_VERSION)?
+            .call(&mut store, ())?;
+        if version != PLUGIN_API_VERSION {
+            anyhow::bail!("targets plugin API v{}, this server supports v{}", version, PLUGIN_API_VERSION);
+        }
+
+        Ok(Plugin { name, capabilities, module })
+    }
+
+    fn instantiate(&self, module: &Module) -> Result<(Store<StoreLimits>, Instance)> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.limits.fuel)?;
+        let instance = Instance::new(&mut store, module, &[])?;
+        Ok((store, instance))
+    }
+
+    // Copy JSON input into a fresh instance, run the stage and read its JSON output back
+    fn call(&self, plugin: &Plugin, capability: Capability, input: &[u8]) -> Result<Vec<u8>> {
+        let (mut store, instance) = self.instantiate(&plugin.module)?;
+        let memory = instance
+            .get_memory(&mut store, EXPORT_MEMORY)
+            .context("plugin does not export its memory")?;
+        let input_len = i32::try_from(input.len())?;
+
+        let input_ptr = instance
+            .get_typed_func::<i32, i32>(&mut store, EXPORT_ALLOC)?
+            .call(&mut store, input_len)?;
+        memory.write(&mut store, input_ptr as u32 as usize, input)?;
+
+        let packed = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, capability.export_name())?
+            .call(&mut store, (input_ptr, input_len))?;
+        let (output_ptr, output_len) = unpack(packed);
+        if output_len > self.limits.max_memory_bytes {
+            anyhow::bail!("plugin '{}' returned {} bytes, more than its memory limit", plugin.name, output_len);
+        }
+
+        let mut output = vec![0; output_len];
+        memory.read(&store, output_ptr, &mut output)?;
+        Ok(output)
+    }
+
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.plugins.iter().any(|plugin| plugin.capabilities.contains(&capability))
+    }
+
+    /// Split text with the first plugin that provides a chunker
+    pub fn chunk(&self, file_path: &str, text: &str) -> Result<Vec<String>> {
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.capabilities.contains(&Capability::Chunk))
+            .context("no chunker plugin loaded")?;
+
+        let output = self.call(plugin, Capability::Chunk, &serde_json::to_vec(&ChunkInput { file_path, text })?)?;
+        let output: ChunkOutput = serde_json::from_slice(&output)
+            .with_context(|| format!("plugin '{}' returned invalid chunker output", plugin.name))?;
+
+        Ok(output.chunks.into_iter().filter(|chunk| !chunk.trim().is_empty()).collect())
+    }
+
+    /// Run every filter plugin, then every reranker, in load order
+    pub fn filter_and_rerank(&self, query: &str, mut results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+        for capability in [Capability::Filter, Capability::Rerank] {
+            for plugin in self.plugins.iter().filter(|plugin| plugin.capabilities.contains(&capability)) {
+                let input = serde_json::to_vec(&ResultsInput {
+                    query,
+                    results: results
+                        .iter()
+                        .map(|result| PluginSearchResult {
+                            text: &result.text,
+                            file_path: &result.file_path,
+                            chunk_index: result.chunk_index,
+                            score: result.score,
+                        })
+                        .collect(),
+                })?;
+
+                let output = self.call(plugin, capability, &input)?;
+                let output: ResultsOutput = serde_json::from_slice(&output)
+                    .with_context(|| format!("plugin '{}' returned invalid {:?} output", plugin.name, capability))?;
+                results = select_results(results, &output.indices, capability)
+                    .with_context(|| format!("plugin '{}' returned invalid indices", plugin.name))?;
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    let packed = packed as u64;
+    ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize)
+}
+
+// Apply plugin-chosen indices; filters must keep the original order, and nothing may repeat
+fn select_results(results: Vec<SearchResult>, indices: &[usize], capability: Capability) -> Result<Vec<SearchResult>> {
+    let mut seen = HashSet::new();
+    for &index in indices {
+        if index >= results.len() {
+            anyhow::bail!("index {} out of range for {} results", index, results.len());
+        }
+        if !seen.insert(index) {
+            anyhow::bail!("index {} returned more than once", index);
+        }
+    }
+    if capability == Capability::Filter && !indices.is_sorted() {
+        anyhow::bail!("filters may drop results but not reorder them");
+    }
+
+    let mut slots: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+    Ok(indices.iter().filter_map(|&index| slots[index].take()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(text: &str) -> SearchResult {
+        SearchResult {
+            text: text.to_string(),
+            file_path: "doc.pdf".to_string(),
+            chunk_index: 0,
+            score: 0.5,
+            parent_index: None,
+        }
+    }
+
+    fn texts(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_unpack() {
+        assert_eq!(unpack((1024_i64 << 32) | 37), (1024, 37));
+        assert_eq!(unpack(((0xffff_fff0_u64 << 32) | 5) as i64), (0xffff_fff0, 5));
+    }
+
+    #[test]
+    fn test_select_results() {
+        let results = vec![result("a"), result("b"), result("c")];
+
+        let reranked = select_results(results.clone(), &[2, 0], Capability::Rerank).unwrap();
+        assert_eq!(texts(&reranked), vec!["c", "a"]);
+
+        let filtered = select_results(results.clone(), &[0, 2], Capability::Filter).unwrap();
+        assert_eq!(texts(&filtered), vec!["a", "c"]);
+
+        assert!(select_results(results.clone(), &[2, 0], Capability::Filter).is_err());
+        assert!(select_results(results.clone(), &[1, 1], Capability::Rerank).is_err());
+        assert!(select_results(results, &[3], Capability::Rerank).is_err());
+    }
+
+    #[test]
+    fn test_rerank_plugin_round_trip() {
+        // Reverses two results: ignores its input and always returns {"indices":[1,0]}
+        let wat = r#"
+            (module
+              (memory (export "memory") 1)
+              (data (i32.const 0) "{\"indices\":[1,0]}")
+              (func (export "rag_plugin_api_version") (result i32) i32.const 1)
+              (func (export "rag_alloc") (param i32) (result i32) i32.const 1024)
+              (func (export "rag_rerank") (param i32 i32) (result i64) i64.const 17))
+        "#;
+        let dir = std::env::temp_dir().join(format!("rag_plugins_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Wasmtime accepts the text format wherever it accepts binaries
+        std::fs::write(dir.join("reverse.wasm"), wat).unwrap();
+
+        let runtime = PluginRuntime::load_dir(&dir, PluginLimits { fuel: 1_000_000, max_memory_bytes: 1 << 20 }).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(runtime.has(Capability::Rerank));
+        assert!(!runtime.has(Capability::Chunk));
+        let reranked = runtime.filter_and_rerank("q", vec![result("a"), result("b")]).unwrap();
+        assert_eq!(texts(&reranked), vec!["b", "a"]);
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+#[cfg(feature = "wasm-plugins")]
+use std::sync::Arc;
+use tracing;
+
+use crate::services::vector::SearchResult;
+#[cfg(feature = "wasm-plugins")]
+use crate::services::plugin_runtime::{Capability, PluginLimits, PluginRuntime};
+
+/// Deployment-wide pipeline plugins loaded from `PLUGIN_DIR`. Without the `wasm-plugins`
+/// feature, or with no directory configured, every stage is a no-op
+#[derive(Clone, Default)]
+pub struct PluginHost {
+    #[cfg(feature = "wasm-plugins")]
+    runtime: Option<Arc<PluginRuntime>>,
+}
+
+impl PluginHost {
+    pub fn from_env() -> Result<Self> {
+        let Ok(dir) = std::env::var("PLUGIN_DIR") else {
+            return Ok(Self::default());
+        };
+
+        #[cfg(feature = "wasm-plugins")]
+        {
+            let runtime = PluginRuntime::load_dir(std::path::Path::new(&dir), PluginLimits::from_env())?;
+            tracing::info!("✅ Loaded {} plugins from {}", runtime.plugins().len(), dir);
+            Ok(Self { runtime: Some(Arc::new(runtime)) })
+        }
+
+        #[cfg(not(feature = "wasm-plugins"))]
+        {
+            tracing::warn!("⚠️ PLUGIN_DIR is set to {} but this build lacks the wasm-plugins feature; plugins are disabled", dir);
+            Ok(Self::default())
+        }
+    }
+
+    pub fn has_chunker(&self) -> bool {
+        #[cfg(feature = "wasm-plugins")]
+        if let Some(runtime) = &self.runtime {
+            return runtime.has(Capability::Chunk);
+        }
+        false
+    }
+
+    pub fn has_result_stages(&self) -> bool {
+        #[cfg(feature = "wasm-plugins")]
+        if let Some(runtime) = &self.runtime {
+            return runtime.has(Capability::Filter) || runtime.has(Capability::Rerank);
+        }
+        false
+    }
+
+    /// Chunk extracted text with the chunker plugin; only call when `has_chunker` is true
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(unused_variables))]
+    pub async fn chunk(&self, file_path: &str, text: &str) -> Result<Vec<String>> {
+        #[cfg(feature = "wasm-plugins")]
+        if let Some(runtime) = self.runtime.clone() {
+            let (file_path, text) = (file_path.to_string(), text.to_string());
+            return tokio::task::spawn_blocking(move || runtime.chunk(&file_path, &text)).await?;
+        }
+        anyhow::bail!("no chunker plugin loaded")
+    }
+
+    /// Pass search results through the filter and rerank plugins
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(unused_variables))]
+    pub async fn filter_and_rerank(&self, query: &str, results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+        #[cfg(feature = "wasm-plugins")]
+        if let Some(runtime) = self.runtime.clone() {
+            let query = query.to_string();
+            return tokio::task::spawn_blocking(move || runtime.filter_and_rerank(&query, results)).await?;
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,242 @@
+use handlebars::{no_escape, Handlebars};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::models::ChatBot;
+use crate::db::queries::{get_prompt_template, set_conversation_prompt_variant};
+use crate::errors::{AppError, AppResult};
+use crate::services::shutdown::BackgroundJobs;
+
+/// Prompt used by chatbots without their own template
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "You are a helpful AI assistant. Based on the following context, please answer the user's question. If the context doesn't contain enough information to answer the question, please say so.\n\nContext:\n{{context}}\n\nUser Question: {{question}}\n\nAnswer:";
+
+/// Variables filled in by the server; request variables cannot override them
+const RESERVED_VARIABLES: &[&str] = &["context", "history", "query", "question"];
+
+/// The parts of a prompt assembled by the chat handlers
+pub struct PromptContext<'a> {
+    pub glossary: &'a str,
+    pub history: &'a str,
+    pub documents: &'a str,
+}
+
+impl PromptContext<'_> {
+    // Glossary, optionally the conversation so far, then the retrieved documents
+    fn combined(&self, include_history: bool) -> String {
+        if include_history && !self.history.is_empty() {
+            format!(
+                "{}Previous conversation:\n{}\n\nRelevant documents:\n{}",
+                self.glossary, self.history, self.documents
+            )
+        } else {
+            format!("{}Relevant documents:\n{}", self.glossary, self.documents)
+        }
+    }
+}
+
+fn registry() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    // Prompts are plain text, so HTML escaping would only mangle them
+    handlebars.register_escape_fn(no_escape);
+    handlebars
+}
+
+/// Check that a chatbot's template compiles before storing it
+pub fn validate_template(template: &str) -> AppResult<()> {
+    registry()
+        .register_template_string("prompt", template)
+        .map_err(|e| AppError::Other(format!("Invalid prompt template: {}", e)))
+}
+
+/// Which of a chatbot's templates answered while a canary was running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptVariant {
+    Current,
+    Canary,
+}
+
+impl PromptVariant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PromptVariant::Current => "current",
+            PromptVariant::Canary => "canary",
+        }
+    }
+}
+
+/// The template for one request, and its variant when a canary is running
+#[derive(Debug)]
+pub struct ChosenTemplate {
+    pub template: Option<String>,
+    pub variant: Option<PromptVariant>,
+}
+
+/// Whether a chat falls in the canary's share of traffic. Bucketing by chat id keeps every
+/// turn of a chat on the same variant
+pub fn in_canary(chat_id: Uuid, percent: i16) -> bool {
+    (chat_id.as_u128() % 100) < percent.clamp(0, 100) as u128
+}
+
+/// Share of rated turns that were rated up
+pub fn approval_rate(up: i64, rated: i64) -> Option<f64> {
+    (rated > 0).then(|| up as f64 / rated as f64)
+}
+
+/// The chatbot's selected shared template, else its inline one.
+/// A selected template that has since been deleted falls back to the inline one
+pub async fn current_template(pool: &PgPool, organization_id: Uuid, chatbot: &ChatBot) -> AppResult<Option<String>> {
+    if let Some(template_id) = chatbot.prompt_template_id {
+        match get_prompt_template(pool, organization_id, template_id).await? {
+            Some(prompt_template) => return Ok(Some(prompt_template.template)),
+            None => tracing::warn!(
+                "⚠️ Prompt template {} selected by chatbot {} not found, using its inline template",
+                template_id,
+                chatbot.id
+            ),
+        }
+    }
+    Ok(chatbot.prompt_template.clone())
+}
+
+/// The template a chat is answered with: the canary template for chats in its share of traffic,
+/// otherwise the chatbot's current template
+pub async fn chatbot_template(
+    pool: &PgPool,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    chat_id: Uuid,
+) -> AppResult<ChosenTemplate> {
+    let Some(canary_id) = chatbot.canary_template_id.filter(|_| chatbot.canary_percent > 0) else {
+        return Ok(ChosenTemplate {
+            template: current_template(pool, organization_id, chatbot).await?,
+            variant: None,
+        });
+    };
+
+    if in_canary(chat_id, chatbot.canary_percent) {
+        match get_prompt_template(pool, organization_id, canary_id).await? {
+            Some(prompt_template) => {
+                return Ok(ChosenTemplate {
+                    template: Some(prompt_template.template),
+                    variant: Some(PromptVariant::Canary),
+                });
+            }
+            None => tracing::warn!(
+                "⚠️ Canary template {} of chatbot {} not found, using its current template",
+                canary_id,
+                chatbot.id
+            ),
+        }
+    }
+    Ok(ChosenTemplate {
+        template: current_template(pool, organization_id, chatbot).await?,
+        variant: Some(PromptVariant::Current),
+    })
+}
+
+/// Tag the conversation with the variant that answered it, in the background
+pub fn record_variant(jobs: &BackgroundJobs, db: Arc<PgPool>, conversation_id: Uuid, variant: PromptVariant) {
+    jobs.spawn(async move {
+        if let Err(e) = set_conversation_prompt_variant(&db, conversation_id, variant.as_str()).await {
+            tracing::warn!("⚠️ Failed to record prompt variant: {}", e);
+        }
+    });
+}
+
+/// Render the chatbot's prompt template (or the default) with the request's variables.
+/// `{{context}}` includes the conversation history unless the template places `{{history}}` itself
+pub fn render_prompt(
+    template: Option<&str>,
+    context: &PromptContext,
+    question: &str,
+    variables: Option<&HashMap<String, Value>>,
+) -> AppResult<String> {
+    let template = template.unwrap_or(DEFAULT_PROMPT_TEMPLATE);
+
+    let mut data = Map::new();
+    if let Some(variables) = variables {
+        for (name, value) in variables {
+            if !RESERVED_VARIABLES.contains(&name.as_str()) {
+                data.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    let include_history = !template.contains("history");
+    data.insert("context".to_string(), Value::String(context.combined(include_history)));
+    data.insert("history".to_string(), Value::String(context.history.to_string()));
+    data.insert("query".to_string(), Value::String(question.to_string()));
+    data.insert("question".to_string(), Value::String(question.to_string()));
+
+    registry()
+        .render_template(template, &Value::Object(data))
+        .map_err(|e| AppError::Other(format!("Failed to render prompt template: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTEXT: PromptContext<'static> = PromptContext {
+        glossary: "",
+        history: "User: Hi\nAssistant: Hello",
+        documents: "Docs & notes",
+    };
+
+    #[test]
+    fn test_default_template_renders_context_and_question() {
+        let prompt = render_prompt(None, &CONTEXT, "What's new?", None).unwrap();
+
+        assert!(prompt.contains("Previous conversation:\nUser: Hi\nAssistant: Hello\n"));
+        assert!(prompt.contains("Relevant documents:\nDocs & notes\n"));
+        assert!(prompt.contains("User Question: What's new?\n"));
+    }
+
+    #[test]
+    fn test_history_is_left_out_of_context_when_the_template_places_it() {
+        let template = "{{history}}|{{context}}|{{query}}";
+        let prompt = render_prompt(Some(template), &CONTEXT, "Q", None).unwrap();
+
+        assert_eq!(prompt, "User: Hi\nAssistant: Hello|Relevant documents:\nDocs & notes|Q");
+    }
+
+    #[test]
+    fn test_request_variables_are_substituted_but_cannot_override_reserved() {
+        let template = "Hi {{customer_name}} on {{plan}}.{{#if vip}} VIP{{/if}} Q: {{question}}";
+        let variables: HashMap<String, Value> = HashMap::from([
+            ("customer_name".to_string(), json!("Ada")),
+            ("plan".to_string(), json!("Pro")),
+            ("vip".to_string(), json!(true)),
+            ("question".to_string(), json!("injected")),
+        ]);
+
+        let prompt = render_prompt(Some(template), &CONTEXT, "real question", Some(&variables)).unwrap();
+        assert_eq!(prompt, "Hi Ada on Pro. VIP Q: real question");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("Hello {{name}}").is_ok());
+        assert!(validate_template("Hello {{#if name}}").is_err());
+    }
+
+    #[test]
+    fn test_canary_bucketing_is_sticky_and_proportional() {
+        let chat_id = Uuid::new_v4();
+        assert_eq!(in_canary(chat_id, 30), in_canary(chat_id, 30));
+        assert!(!in_canary(chat_id, 0));
+        assert!(in_canary(chat_id, 100));
+
+        let in_share = (0..2000).filter(|_| in_canary(Uuid::new_v4(), 25)).count();
+        assert!((300..700).contains(&in_share), "{} of 2000 chats in a 25% canary", in_share);
+    }
+
+    #[test]
+    fn test_approval_rate() {
+        assert_eq!(approval_rate(3, 4), Some(0.75));
+        assert_eq!(approval_rate(0, 0), None);
+    }
+}
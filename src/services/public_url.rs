@@ -0,0 +1,166 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::{Attempt, Policy};
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+// Redirects followed before a request gives up, as many as reqwest follows by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether requests to user-supplied URLs may reach private addresses, from `ALLOW_PRIVATE_URLS`.
+/// Off by default; for deployments whose sources or webhooks live on an internal network
+pub fn allow_private() -> bool {
+    std::env::var("ALLOW_PRIVATE_URLS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+/// Whether an address is on the public internet rather than loopback, a private or carrier-grade NAT
+/// network, link-local (which holds cloud metadata endpoints such as 169.254.169.254) or unroutable
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()),
+        },
+    }
+}
+
+// The address a URL names directly, if its host is an IP literal
+fn literal_ip(url: &Url) -> Option<IpAddr> {
+    match url.host()? {
+        Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+        Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+        Host::Domain(_) => None,
+    }
+}
+
+/// Resolve a URL's host and reject it unless every address it resolves to is public. Only the host
+/// is looked up, so this works for any scheme, not just http(s)
+pub async fn check_public_host(url: &Url) -> Result<(), String> {
+    if allow_private() {
+        return Ok(());
+    }
+
+    let addresses: Vec<IpAddr> = match (literal_ip(url), url.host_str()) {
+        (Some(ip), _) => vec![ip],
+        (None, Some(host)) => {
+            tokio::net::lookup_host((host, 0))
+                .await
+                .map_err(|e| format!("Could not resolve '{}': {}", host, e))?
+                .map(|address| address.ip())
+                .collect()
+        }
+        (None, None) => return Err(format!("URL has no host: {}", url)),
+    };
+
+    let host = url.host_str().unwrap_or_default();
+    if addresses.is_empty() {
+        return Err(format!("'{}' does not resolve to any address", host));
+    }
+    match addresses.into_iter().find(|ip| !is_public_ip(*ip)) {
+        Some(ip) => Err(format!("'{}' resolves to non-public address {}", host, ip)),
+        None => Ok(()),
+    }
+}
+
+// Resolves with the system resolver but fails for hosts with a non-public address. Checked on every
+// connection, so a host can't pass validation and later be pointed at an internal address
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(address) = addresses.iter().find(|address| !is_public_ip(address.ip())) {
+                let error = format!("'{}' resolves to non-public address {}", name.as_str(), address.ip());
+                return Err(Box::<dyn Error + Send + Sync>::from(error));
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+// Follow redirects only to http(s) URLs; hostnames are checked by the resolver when connecting,
+// IP literals here since they skip resolution
+fn redirect_policy() -> Policy {
+    Policy::custom(|attempt: Attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if !matches!(attempt.url().scheme(), "http" | "https") {
+            let error = format!("redirect to non-http URL {}", attempt.url());
+            attempt.error(error)
+        } else if let Some(ip) = literal_ip(attempt.url())
+            && !is_public_ip(ip)
+        {
+            let error = format!("redirect to non-public address {}", ip);
+            attempt.error(error)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Client builder for requests to user-supplied URLs. Unless `ALLOW_PRIVATE_URLS` is set, every
+/// address it connects to, including after redirects, has to be public
+pub fn public_client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    if allow_private() {
+        return builder;
+    }
+    builder
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(redirect_policy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00:ec2::254",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_public_host_rejects_internal_literals() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert!(check_public_host(&url("http://93.184.216.34/page")).await.is_ok());
+        assert!(check_public_host(&url("http://169.254.169.254/latest/meta-data")).await.is_err());
+        assert!(check_public_host(&url("http://[::1]:8080/")).await.is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing;
+
+use crate::db::queries::purge_deleted_rows;
+use crate::services::shutdown::BackgroundJobs;
+
+const DEFAULT_RETENTION_DAYS: i32 = 30;
+const DEFAULT_PURGE_INTERVAL_SECS: u64 = 3600;
+
+// Spawn the background task that hard-deletes soft-deleted rows past retention
+pub fn spawn_purge_task(jobs: &BackgroundJobs, db: Arc<PgPool>) {
+    let retention_days = std::env::var("SOFT_DELETE_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    let interval_secs = std::env::var("PURGE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_PURGE_INTERVAL_SECS);
+
+    tracing::info!(
+        "Starting purge task: retention {} days, interval {}s",
+        retention_days,
+        interval_secs
+    );
+
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            match purge_deleted_rows(&db, retention_days).await {
+                Ok(0) => tracing::debug!("Purge task found nothing to delete"),
+                Ok(count) => tracing::info!("🧹 Purged {} soft-deleted rows", count),
+                Err(e) => tracing::error!("❌ Purge task failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,530 @@
+use anyhow::Result;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::services::retry::{is_transient_status, with_retry, Upstream, UpstreamStatus};
+use crate::services::vector::{merge_collection_hits, ChunkPage, DocumentWithEmbedding, SearchResult, VectorStore};
+
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+// Points sent per upsert request and fetched per scroll page
+const BATCH_SIZE: usize = 256;
+
+// Send a request, retrying timeouts and 408/429/502/503/504 responses with backoff. Other
+// non-success responses are returned for the caller to handle
+async fn send_with_retry<F>(operation: &'static str, mut build: F) -> Result<Response>
+where
+    F: FnMut() -> RequestBuilder,
+{
+    with_retry(Upstream::Qdrant, operation, || {
+        let request = build().send();
+        async move {
+            let response = request.await?;
+            let status = response.status().as_u16();
+            if is_transient_status(status) {
+                return Err(anyhow::Error::new(UpstreamStatus { status }));
+            }
+            Ok(response)
+        }
+    })
+    .await
+}
+
+// Fail with Qdrant's error message unless the request succeeded
+async fn expect_success(response: Response, what: &str) -> Result<Value> {
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        tracing::error!("{} failed: {}", what, error_text);
+        return Err(anyhow::anyhow!("{} failed", what));
+    }
+    Ok(response.json().await?)
+}
+
+// Whether a collection name matches a pattern where `*` matches any run of characters
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return name.is_empty();
+    };
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: the pattern must match the whole name
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn file_path_filter(file_path: &str) -> Value {
+    json!({ "must": [{ "key": "file_path", "match": { "value": file_path } }] })
+}
+
+/// Vector store backed by a Qdrant server's REST API. A collection is a Qdrant collection with
+/// cosine distance; chunk metadata is kept in the point payload
+pub struct QdrantVectorStore {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl QdrantVectorStore {
+    /// Connect and check the server answers. Fails if it is unreachable or rejects the API key
+    pub async fn connect(url: &str, api_key: Option<String>) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()?;
+        let store = Self {
+            client,
+            base_url: url.trim_end_matches('/').to_string(),
+            api_key,
+        };
+
+        let response = store.request(reqwest::Method::GET, "/collections").send().await?;
+        expect_success(response, "Qdrant connection check").await?;
+        tracing::info!("✅ Qdrant connection verified at {}", store.base_url);
+        Ok(store)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.header("api-key", api_key),
+            None => request,
+        }
+    }
+
+    // Count points in one collection, optionally only those matching a filter. Missing collections count 0
+    async fn count_points(&self, collection: &str, filter: Option<Value>) -> Result<u64> {
+        let body = json!({ "exact": true, "filter": filter });
+        let path = format!("/collections/{}/points/count", collection);
+        let response = send_with_retry("count", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(0);
+        }
+        let body = expect_success(response, "Count").await?;
+        Ok(body["result"]["count"].as_u64().unwrap_or(0))
+    }
+
+    // Delete one collection, ignoring it if it doesn't exist
+    async fn delete_collection(&self, collection: &str) -> Result<()> {
+        let path = format!("/collections/{}", collection);
+        let response = send_with_retry("delete_collection", || self.request(reqwest::Method::DELETE, &path)).await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            expect_success(response, "Deleting collection").await?;
+        }
+        Ok(())
+    }
+}
+
+impl VectorStore for QdrantVectorStore {
+    async fn create_collection(&self, name: &str, embedding_dim: usize) -> Result<()> {
+        let path = format!("/collections/{}", name);
+        let response = send_with_retry("get_collection", || self.request(reqwest::Method::GET, &path)).await?;
+        if response.status().is_success() || self.resolve_alias(name).await?.is_some() {
+            tracing::info!("Collection '{}' already exists", name);
+            return Ok(());
+        }
+
+        let body = json!({ "vectors": { "size": embedding_dim, "distance": "Cosine" } });
+        let response = send_with_retry("create_collection", || self.request(reqwest::Method::PUT, &path).json(&body)).await?;
+        expect_success(response, "Creating collection").await?;
+
+        // Keyword index so per-document filters don't scan every payload
+        let index_path = format!("/collections/{}/index?wait=true", name);
+        let index = json!({ "field_name": "file_path", "field_schema": "keyword" });
+        let response = send_with_retry("create_payload_index", || {
+            self.request(reqwest::Method::PUT, &index_path).json(&index)
+        })
+        .await?;
+        expect_success(response, "Creating payload index").await?;
+
+        tracing::info!("✅ Collection '{}' created successfully", name);
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "qdrant.index_documents", skip(self, documents), fields(count = documents.len()))]
+    async fn index_documents(&self, collection: &str, documents: Vec<DocumentWithEmbedding>) -> Result<usize> {
+        let path = format!("/collections/{}/points?wait=true", collection);
+        let created_at = chrono::Utc::now().to_rfc3339();
+        let mut stored = 0;
+
+        for batch in documents.chunks(BATCH_SIZE) {
+            let points: Vec<Value> = batch
+                .iter()
+                .map(|doc| {
+                    json!({
+                        "id": doc.id,
+                        "vector": doc.embedding,
+                        "payload": {
+                            "text": doc.text,
+                            "chunk_index": doc.chunk_index,
+                            "file_path": doc.file_path,
+                            "chunk_count": doc.chunk_count,
+                            "parent_index": doc.parent_index,
+                            "created_at": created_at
+                        }
+                    })
+                })
+                .collect();
+            let body = json!({ "points": points });
+
+            let response = send_with_retry("upsert", || self.request(reqwest::Method::PUT, &path).json(&body)).await?;
+            expect_success(response, "Upserting points").await?;
+            stored += batch.len();
+        }
+
+        tracing::info!("✅ Stored {} points in collection '{}'", stored, collection);
+        Ok(stored)
+    }
+
+    #[tracing::instrument(name = "qdrant.search", skip(self, query_embedding))]
+    async fn search_similar(&self, collection: &str, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<SearchResult>> {
+        let path = format!("/collections/{}/points/search", collection);
+        let body = json!({ "vector": query_embedding, "limit": limit, "with_payload": true });
+        let response = send_with_retry("search", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+        // Like an Elasticsearch search with ignore_unavailable, a missing collection has no results
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let body = expect_success(response, "Search").await?;
+
+        let empty_vec = vec![];
+        let results: Vec<SearchResult> = body["result"]
+            .as_array()
+            .unwrap_or(&empty_vec)
+            .iter()
+            .map(|point| {
+                let payload = &point["payload"];
+                let cosine = point["score"].as_f64().unwrap_or(0.0);
+                SearchResult {
+                    text: payload["text"].as_str().unwrap_or("").to_string(),
+                    // Same scale as Elasticsearch's cosine similarity
+                    score: ((1.0 + cosine) / 2.0) as f32,
+                    chunk_index: payload["chunk_index"].as_i64().unwrap_or(0),
+                    file_path: payload["file_path"].as_str().unwrap_or("").to_string(),
+                    parent_index: payload["parent_index"].as_i64(),
+                }
+            })
+            .collect();
+
+        tracing::info!("Found {} similar documents", results.len());
+        Ok(results)
+    }
+
+    async fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        let mut searches = Vec::new();
+        for collection in collections {
+            let results = self.search_similar(collection, query_embedding.clone(), limit).await?;
+            searches.push((collection.clone(), results));
+        }
+        Ok(merge_collection_hits(searches, limit))
+    }
+
+    #[tracing::instrument(name = "qdrant.count", skip(self))]
+    async fn count_documents(&self, collections: &[String]) -> Result<u64> {
+        let mut total = 0;
+        for collection in collections {
+            total += self.count_points(collection, None).await?;
+        }
+        Ok(total)
+    }
+
+    // An alias is deleted through the collection it points at, which takes its aliases with it
+    async fn delete_collections(&self, collections: &[String]) -> Result<()> {
+        for collection in collections {
+            let target = self.resolve_alias(collection).await?;
+            self.delete_collection(target.as_deref().unwrap_or(collection)).await?;
+        }
+        tracing::info!("✅ Deleted collections: {:?}", collections);
+        Ok(())
+    }
+
+    // Qdrant has no closed state; archived chatbots are never searched, so their collections just stay loaded
+    async fn close_collections(&self, _collections: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn open_collections(&self, _collections: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn list_collections(&self, pattern: &str) -> Result<Vec<String>> {
+        let response = send_with_retry("list_collections", || self.request(reqwest::Method::GET, "/collections")).await?;
+        let body = expect_success(response, "Listing collections").await?;
+
+        let empty_vec = vec![];
+        let mut names: Vec<String> = body["result"]["collections"]
+            .as_array()
+            .unwrap_or(&empty_vec)
+            .iter()
+            .filter_map(|collection| collection["name"].as_str())
+            .filter(|name| glob_matches(pattern, name))
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn fetch_document_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+    ) -> Result<Vec<(String, DocumentWithEmbedding)>> {
+        let mut chunks = Vec::new();
+
+        for collection in collections {
+            let path = format!("/collections/{}/points/scroll", collection);
+            let mut offset = Value::Null;
+            loop {
+                let body = json!({
+                    "filter": file_path_filter(file_path),
+                    "limit": BATCH_SIZE,
+                    "offset": offset,
+                    "with_payload": true,
+                    "with_vector": true
+                });
+                let response = send_with_retry("scroll", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+                if response.status() == StatusCode::NOT_FOUND {
+                    break;
+                }
+                let body = expect_success(response, "Fetching document chunks").await?;
+
+                let empty_vec = vec![];
+                for point in body["result"]["points"].as_array().unwrap_or(&empty_vec) {
+                    let payload = &point["payload"];
+                    let embedding: Vec<f32> = point["vector"]
+                        .as_array()
+                        .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                        .unwrap_or_default();
+                    let id = match &point["id"] {
+                        Value::String(id) => id.clone(),
+                        id => id.to_string(),
+                    };
+
+                    chunks.push((
+                        collection.clone(),
+                        DocumentWithEmbedding {
+                            id,
+                            text: payload["text"].as_str().unwrap_or("").to_string(),
+                            embedding,
+                            chunk_index: payload["chunk_index"].as_i64().unwrap_or(0),
+                            file_path: payload["file_path"].as_str().unwrap_or("").to_string(),
+                            chunk_count: payload["chunk_count"].as_i64().unwrap_or(0),
+                            parent_index: payload["parent_index"].as_i64(),
+                        },
+                    ));
+                }
+
+                offset = body["result"]["next_page_offset"].clone();
+                if offset.is_null() {
+                    break;
+                }
+            }
+        }
+
+        chunks.sort_by_key(|(_, chunk)| chunk.chunk_index);
+        Ok(chunks)
+    }
+
+    async fn fetch_section_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> Result<Vec<DocumentWithEmbedding>> {
+        let filter = json!({
+            "must": [
+                { "key": "file_path", "match": { "value": file_path } },
+                { "key": "parent_index", "match": { "any": parent_indices } }
+            ]
+        });
+        let mut chunks = Vec::new();
+
+        for collection in collections {
+            let path = format!("/collections/{}/points/scroll", collection);
+            let mut offset = Value::Null;
+            loop {
+                let body = json!({
+                    "filter": filter,
+                    "limit": BATCH_SIZE,
+                    "offset": offset,
+                    "with_payload": true,
+                    "with_vector": false
+                });
+                let response = send_with_retry("scroll", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+                if response.status() == StatusCode::NOT_FOUND {
+                    break;
+                }
+                let body = expect_success(response, "Fetching section chunks").await?;
+
+                let empty_vec = vec![];
+                for point in body["result"]["points"].as_array().unwrap_or(&empty_vec) {
+                    let payload = &point["payload"];
+                    chunks.push(DocumentWithEmbedding {
+                        id: match &point["id"] {
+                            Value::String(id) => id.clone(),
+                            id => id.to_string(),
+                        },
+                        text: payload["text"].as_str().unwrap_or("").to_string(),
+                        embedding: Vec::new(),
+                        chunk_index: payload["chunk_index"].as_i64().unwrap_or(0),
+                        file_path: payload["file_path"].as_str().unwrap_or("").to_string(),
+                        chunk_count: payload["chunk_count"].as_i64().unwrap_or(0),
+                        parent_index: payload["parent_index"].as_i64(),
+                    });
+                }
+
+                offset = body["result"]["next_page_offset"].clone();
+                if offset.is_null() {
+                    break;
+                }
+            }
+        }
+
+        chunks.sort_by_key(|chunk| chunk.chunk_index);
+        Ok(chunks)
+    }
+
+    async fn delete_document_chunks(&self, collections: &[String], file_path: &str) -> Result<u64> {
+        let mut deleted = 0;
+
+        for collection in collections {
+            // Qdrant doesn't report how many points a delete removed, so count them first
+            let count = self.count_points(collection, Some(file_path_filter(file_path))).await?;
+            if count == 0 {
+                continue;
+            }
+
+            let path = format!("/collections/{}/points/delete?wait=true", collection);
+            let body = json!({ "filter": file_path_filter(file_path) });
+            let response = send_with_retry("delete_points", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+            expect_success(response, "Deleting document chunks").await?;
+            deleted += count;
+        }
+
+        Ok(deleted)
+    }
+
+    async fn scroll_chunks(&self, collection: &str, cursor: Option<&str>, limit: usize) -> Result<ChunkPage> {
+        let offset: Value = match cursor {
+            Some(cursor) => serde_json::from_str(cursor)?,
+            None => Value::Null,
+        };
+        let path = format!("/collections/{}/points/scroll", collection);
+        let body = json!({ "limit": limit, "offset": offset, "with_payload": true, "with_vector": false });
+        let response = send_with_retry("scroll", || self.request(reqwest::Method::POST, &path).json(&body)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(ChunkPage::default());
+        }
+        let body = expect_success(response, "Scrolling collection").await?;
+
+        let empty_vec = vec![];
+        let chunks = body["result"]["points"]
+            .as_array()
+            .unwrap_or(&empty_vec)
+            .iter()
+            .map(|point| {
+                let payload = &point["payload"];
+                DocumentWithEmbedding {
+                    id: match &point["id"] {
+                        Value::String(id) => id.clone(),
+                        id => id.to_string(),
+                    },
+                    text: payload["text"].as_str().unwrap_or("").to_string(),
+                    embedding: Vec::new(),
+                    chunk_index: payload["chunk_index"].as_i64().unwrap_or(0),
+                    file_path: payload["file_path"].as_str().unwrap_or("").to_string(),
+                    chunk_count: payload["chunk_count"].as_i64().unwrap_or(0),
+                    parent_index: payload["parent_index"].as_i64(),
+                }
+            })
+            .collect();
+
+        let next = &body["result"]["next_page_offset"];
+        Ok(ChunkPage {
+            chunks,
+            cursor: (!next.is_null()).then(|| next.to_string()),
+        })
+    }
+
+    async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        let response = send_with_retry("list_aliases", || self.request(reqwest::Method::GET, "/aliases")).await?;
+        let body = expect_success(response, "Listing aliases").await?;
+
+        let empty_vec = vec![];
+        Ok(body["result"]["aliases"]
+            .as_array()
+            .unwrap_or(&empty_vec)
+            .iter()
+            .find(|alias| alias["alias_name"].as_str() == Some(name))
+            .and_then(|alias| alias["collection_name"].as_str())
+            .map(str::to_string))
+    }
+
+    // Alias changes in one request are applied together. Replacing a collection with an alias can't
+    // be, so a collection under an alias's name is deleted first and briefly searches as empty
+    async fn swap_aliases(&self, swaps: &[(String, String)]) -> Result<Vec<String>> {
+        let mut actions = Vec::new();
+        let mut previous = Vec::new();
+
+        for (alias, collection) in swaps {
+            match self.resolve_alias(alias).await? {
+                Some(current) => {
+                    actions.push(json!({ "delete_alias": { "alias_name": alias } }));
+                    previous.push(current);
+                }
+                None => self.delete_collection(alias).await?,
+            }
+            actions.push(json!({ "create_alias": { "collection_name": collection, "alias_name": alias } }));
+        }
+
+        let body = json!({ "actions": actions });
+        let response = send_with_retry("update_aliases", || {
+            self.request(reqwest::Method::POST, "/collections/aliases").json(&body)
+        })
+        .await?;
+        expect_success(response, "Swapping aliases").await?;
+
+        tracing::info!("✅ Swapped aliases: {:?}", swaps);
+        Ok(previous)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let response = self.request(reqwest::Method::GET, "/readyz").send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Qdrant readiness check returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("*chatbot_*", "org_1_chatbot_2_shard_1"));
+        assert!(glob_matches("*chatbot_*", "chatbot_2"));
+        assert!(!glob_matches("*chatbot_*", "embeddings"));
+        assert!(glob_matches("org_*_chatbot", "org_1_chatbot"));
+        assert!(!glob_matches("org_*_chatbot", "org_1_chatbot_2"));
+        assert!(glob_matches("exact", "exact"));
+        assert!(!glob_matches("exact", "exactly"));
+        assert!(glob_matches("*", "anything"));
+    }
+}
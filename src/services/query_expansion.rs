@@ -0,0 +1,178 @@
+use anyhow::Result;
+use futures_util::future::try_join_all;
+use std::collections::HashMap;
+
+use crate::services::embedding::EmbeddingService;
+use crate::services::gemini::GeminiService;
+use crate::services::vector::SearchResult;
+
+/// Fewest paraphrases a chatbot with query expansion searches with
+pub const MIN_PARAPHRASES: i16 = 3;
+/// Most paraphrases a chatbot with query expansion searches with
+pub const MAX_PARAPHRASES: i16 = 5;
+// Damping constant of reciprocal rank fusion, as in the original paper
+const RRF_K: f64 = 60.0;
+// With MMR on, this many times the hits kept are fetched for it to choose from
+const MMR_CANDIDATE_FACTOR: u64 = 4;
+
+/// A chatbot's paraphrase count must be 0 (off) or between 3 and 5
+pub fn validate_paraphrases(count: i16) -> Result<(), String> {
+    if count == 0 || (MIN_PARAPHRASES..=MAX_PARAPHRASES).contains(&count) {
+        Ok(())
+    } else {
+        Err(format!("query_paraphrases must be 0 or between {} and {}", MIN_PARAPHRASES, MAX_PARAPHRASES))
+    }
+}
+
+// A reply line without its "1." or "-" list marker
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+    let unnumbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let line = match unnumbered.strip_prefix(['.', ')']) {
+        Some(rest) if unnumbered.len() < line.len() => rest,
+        _ => line,
+    };
+    line.trim_start_matches(['-', '*', '•']).trim()
+}
+
+/// Paraphrases from the model's reply, one per line, without list markers, quotes, repeats or
+/// the original question; at most `count` of them
+pub fn parse_paraphrases(reply: &str, original: &str, count: usize) -> Vec<String> {
+    let mut paraphrases: Vec<String> = Vec::new();
+    for line in reply.lines() {
+        let line = strip_list_marker(line).trim_matches(|c| c == '"' || c == '\'' || c == '`').trim();
+        let seen = line.eq_ignore_ascii_case(original.trim())
+            || paraphrases.iter().any(|paraphrase| paraphrase.eq_ignore_ascii_case(line));
+        if !line.is_empty() && !seen {
+            paraphrases.push(line.to_string());
+        }
+    }
+
+    paraphrases.truncate(count);
+    paraphrases
+}
+
+/// The query followed by up to `count` paraphrases of it. Returns the query alone if the LLM
+/// call fails
+pub async fn expand_query(query: &str, count: usize) -> Vec<String> {
+    let mut queries = vec![query.to_string()];
+
+    let gemini_service = match GeminiService::new() {
+        Ok(service) => service,
+        Err(e) => {
+            tracing::warn!("⚠️ Query expansion unavailable: {}", e);
+            return queries;
+        }
+    };
+
+    match gemini_service.paraphrase_query(query, count).await {
+        Ok(reply) => {
+            let paraphrases = parse_paraphrases(&reply, query, count);
+            tracing::info!("Expanded query '{}' with {} paraphrases", query, paraphrases.len());
+            queries.extend(paraphrases);
+        }
+        Err(e) => tracing::warn!("⚠️ Query expansion failed, searching with the original query: {}", e),
+    }
+    queries
+}
+
+/// Merge ranked lists of hits with reciprocal rank fusion: a chunk scores the sum of
+/// 1 / (60 + rank) over the lists it is in. Hits keep their best similarity score, so score
+/// thresholds still apply after fusion
+pub fn fuse_results(lists: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let mut fused: Vec<(f64, SearchResult)> = Vec::new();
+    let mut positions: HashMap<(String, i64), usize> = HashMap::new();
+
+    for list in lists {
+        for (rank, hit) in list.into_iter().enumerate() {
+            let gain = 1.0 / (RRF_K + rank as f64 + 1.0);
+            let key = (hit.file_path.clone(), hit.chunk_index);
+            match positions.get(&key).copied() {
+                Some(position) => {
+                    let (score, best) = &mut fused[position];
+                    *score += gain;
+                    if hit.score > best.score {
+                        *best = hit;
+                    }
+                }
+                None => {
+                    positions.insert(key, fused.len());
+                    fused.push((gain, hit));
+                }
+            }
+        }
+    }
+
+    // Stable, so ties keep the order the hits were first seen in
+    fused.sort_by(|a, b| b.0.total_cmp(&a.0));
+    fused.into_iter().take(limit).map(|(_, hit)| hit).collect()
+}
+
+/// Search with every query concurrently and fuse the hits. A single query is searched as is. With
+/// `mmr_lambda` set, more candidates are fetched and diversified against the first query
+pub async fn search_fused(
+    embedding_service: &EmbeddingService,
+    index_names: &[String],
+    queries: &[String],
+    limit: u64,
+    mmr_lambda: Option<f32>,
+) -> Result<Vec<SearchResult>> {
+    let candidates = if mmr_lambda.is_some() { limit * MMR_CANDIDATE_FACTOR } else { limit };
+    let results = if let [query] = queries {
+        embedding_service.search_similar(index_names, query, candidates).await?
+    } else {
+        let searches = queries.iter().map(|query| embedding_service.search_similar(index_names, query, candidates));
+        fuse_results(try_join_all(searches).await?, candidates as usize)
+    };
+
+    match (mmr_lambda, queries.first()) {
+        (Some(lambda), Some(query)) => embedding_service.diversify(query, results, lambda, limit as usize).await,
+        _ => Ok(results),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(file_path: &str, chunk_index: i64, score: f32) -> SearchResult {
+        SearchResult { text: String::new(), score, chunk_index, file_path: file_path.to_string(), parent_index: None }
+    }
+
+    #[test]
+    fn test_validate_paraphrases() {
+        assert!(validate_paraphrases(0).is_ok());
+        assert!(validate_paraphrases(3).is_ok());
+        assert!(validate_paraphrases(5).is_ok());
+        assert!(validate_paraphrases(2).is_err());
+        assert!(validate_paraphrases(6).is_err());
+    }
+
+    #[test]
+    fn test_parse_paraphrases() {
+        let reply = "1. How do I reset my password?\n- \"Forgot password steps\"\n\nreset password\n\
+            How do I reset my password?\n2FA reset\nChange login credentials";
+        assert_eq!(
+            parse_paraphrases(reply, "Reset password", 3),
+            vec!["How do I reset my password?", "Forgot password steps", "2FA reset"]
+        );
+    }
+
+    #[test]
+    fn test_fuse_results_favours_hits_found_by_several_queries() {
+        let first = vec![hit("a.pdf", 0, 0.9), hit("b.pdf", 0, 0.8)];
+        let second = vec![hit("b.pdf", 0, 0.85), hit("c.pdf", 0, 0.7)];
+        let fused = fuse_results(vec![first, second], 5);
+
+        let order: Vec<&str> = fused.iter().map(|hit| hit.file_path.as_str()).collect();
+        assert_eq!(order, vec!["b.pdf", "a.pdf", "c.pdf"]);
+        assert_eq!(fused[0].score, 0.85);
+    }
+
+    #[test]
+    fn test_fuse_results_truncates() {
+        let fused = fuse_results(vec![vec![hit("a.pdf", 0, 0.9), hit("a.pdf", 1, 0.8)]], 1);
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].chunk_index, 0);
+    }
+}
@@ -0,0 +1,82 @@
+use tracing;
+
+use crate::services::gemini::GeminiService;
+
+// Rewrites longer than this many times the original are treated as the model answering instead
+const MAX_REWRITE_GROWTH: usize = 4;
+
+/// Whether to condense follow-ups, from the request or `QUERY_REWRITE` (default on)
+pub fn rewrite_enabled(requested: Option<bool>) -> bool {
+    requested.unwrap_or_else(|| {
+        std::env::var("QUERY_REWRITE")
+            .map(|v| v != "false" && v != "0")
+            .unwrap_or(true)
+    })
+}
+
+/// Tidy the model's rewrite, falling back to the original query if it isn't usable
+pub fn clean_rewrite(rewrite: &str, original: &str) -> String {
+    let line = rewrite.lines().find(|line| !line.trim().is_empty()).unwrap_or("").trim();
+    let line = line
+        .strip_prefix("Standalone question:")
+        .unwrap_or(line)
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .trim();
+
+    let limit = original.chars().count().max(25) * MAX_REWRITE_GROWTH;
+    if line.is_empty() || line.chars().count() > limit {
+        original.to_string()
+    } else {
+        line.to_string()
+    }
+}
+
+/// Rewrite a follow-up into a standalone question using the conversation history.
+/// Returns the original query when there is no history or the LLM call fails.
+pub async fn rewrite_query(history: &str, query: &str) -> String {
+    if history.trim().is_empty() {
+        return query.to_string();
+    }
+
+    let gemini_service = match GeminiService::new() {
+        Ok(service) => service,
+        Err(e) => {
+            tracing::warn!("⚠️ Query rewriting unavailable: {}", e);
+            return query.to_string();
+        }
+    };
+
+    match gemini_service.rewrite_query(history, query).await {
+        Ok(rewrite) => {
+            let rewrite = clean_rewrite(&rewrite, query);
+            tracing::info!("Rewrote query '{}' as '{}'", query, rewrite);
+            rewrite
+        }
+        Err(e) => {
+            tracing::warn!("⚠️ Query rewriting failed, using original query: {}", e);
+            query.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_rewrite_strips_labels_and_quotes() {
+        let original = "what about the second one?";
+        assert_eq!(
+            clean_rewrite("Standalone question: \"What is the price of the Pro plan?\"\n", original),
+            "What is the price of the Pro plan?"
+        );
+    }
+
+    #[test]
+    fn test_clean_rewrite_falls_back_on_unusable_output() {
+        let original = "and the second?";
+        assert_eq!(clean_rewrite("   \n", original), original);
+        assert_eq!(clean_rewrite(&"very long answer ".repeat(50), original), original);
+    }
+}
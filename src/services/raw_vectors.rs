@@ -0,0 +1,106 @@
+use crate::db::models::RawVectorChunk;
+use crate::services::vector::{chunk_id, DocumentWithEmbedding};
+
+/// Chunks accepted in one upsert
+pub const MAX_RAW_CHUNKS: usize = 1000;
+
+/// A document's path must be set, and every chunk needs text and a finite embedding of the
+/// index's dimensions
+pub fn validate_chunks(file_path: &str, chunks: &[RawVectorChunk], embedding_dim: usize) -> Result<(), String> {
+    if file_path.trim().is_empty() {
+        return Err("file_path can't be blank".to_string());
+    }
+    if chunks.is_empty() || chunks.len() > MAX_RAW_CHUNKS {
+        return Err(format!("send 1 to {} chunks", MAX_RAW_CHUNKS));
+    }
+    for (i, chunk) in chunks.iter().enumerate() {
+        if chunk.text.trim().is_empty() {
+            return Err(format!("chunk {} has no text", i));
+        }
+        if chunk.embedding.len() != embedding_dim {
+            return Err(format!(
+                "chunk {} has {} dimensions; the index has {}",
+                i,
+                chunk.embedding.len(),
+                embedding_dim
+            ));
+        }
+        if chunk.embedding.iter().any(|value| !value.is_finite()) {
+            return Err(format!("chunk {} has a NaN or infinite value", i));
+        }
+    }
+    Ok(())
+}
+
+// The chunk's text with its metadata as a header, the way JSON imports store records
+fn chunk_text(chunk: &RawVectorChunk) -> String {
+    if chunk.metadata.is_empty() {
+        return chunk.text.clone();
+    }
+    let header: String = chunk.metadata.iter().map(|(key, value)| format!("{}: {}\n", key, value)).collect();
+    format!("{}\n{}", header, chunk.text)
+}
+
+/// The chunks as stored, numbered in the order they were sent
+pub fn to_documents(file_path: &str, chunks: Vec<RawVectorChunk>) -> Vec<DocumentWithEmbedding> {
+    let chunk_count = chunks.len() as i64;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let text = chunk_text(&chunk);
+            DocumentWithEmbedding {
+                id: chunk_id(file_path, i as i64, &text),
+                text,
+                embedding: chunk.embedding,
+                chunk_index: i as i64,
+                file_path: file_path.to_string(),
+                chunk_count,
+                parent_index: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn chunk(text: &str, embedding: Vec<f32>) -> RawVectorChunk {
+        RawVectorChunk {
+            text: text.to_string(),
+            embedding,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_dimensions() {
+        assert!(validate_chunks("doc", &[chunk("a", vec![0.1, 0.2])], 2).is_ok());
+        let error = validate_chunks("doc", &[chunk("a", vec![0.1, 0.2]), chunk("b", vec![0.1])], 2).unwrap_err();
+        assert_eq!(error, "chunk 1 has 1 dimensions; the index has 2");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_chunks() {
+        assert!(validate_chunks(" ", &[chunk("a", vec![0.1])], 1).is_err());
+        assert!(validate_chunks("doc", &[], 1).is_err());
+        assert!(validate_chunks("doc", &[chunk("  ", vec![0.1])], 1).is_err());
+        assert!(validate_chunks("doc", &[chunk("a", vec![f32::NAN])], 1).is_err());
+    }
+
+    #[test]
+    fn test_documents_carry_metadata_and_order() {
+        let mut first = chunk("Refunds take 5 days.", vec![1.0]);
+        first.metadata.insert("source".to_string(), "crm".to_string());
+        first.metadata.insert("lang".to_string(), "en".to_string());
+        let documents = to_documents("kb/refunds", vec![first, chunk("Contact support.", vec![0.5])]);
+
+        assert_eq!(documents[0].text, "lang: en\nsource: crm\n\nRefunds take 5 days.");
+        assert_eq!(documents[1].text, "Contact support.");
+        assert_eq!(documents[1].chunk_index, 1);
+        assert_eq!(documents[1].chunk_count, 2);
+        assert_eq!(documents[0].id, chunk_id("kb/refunds", 0, &documents[0].text));
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing;
+
+use crate::db::models::ReindexJob;
+use crate::db::queries::{finish_reindex_job, update_reindex_job_progress};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
+use crate::services::embedding::EmbeddingService;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::sharding::shard_indices;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::{chatbot_index_name, DocumentWithEmbedding, VectorBackend, VectorStore};
+
+const DEFAULT_STALE_SECS: i64 = 1800;
+// Chunks read, embedded and written per step
+const PAGE_SIZE: usize = 256;
+
+/// Seconds a running job may go without reporting progress before it is considered dead, from
+/// `REINDEX_STALE_SECS`. Jobs die with the server that runs them
+pub fn stale_after_secs() -> i64 {
+    std::env::var("REINDEX_STALE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &i64| *v > 0)
+        .unwrap_or(DEFAULT_STALE_SECS)
+}
+
+/// Index a reindex writes to before it takes over `alias`, e.g. `org_<org>_chatbot_<id>_v2`
+pub fn versioned_index_name(alias: &str, version: i32) -> String {
+    format!("{}_v{}", alias, version)
+}
+
+/// The alias a versioned index serves; other names are returned unchanged
+pub fn alias_of(index_name: &str) -> &str {
+    match index_name.rsplit_once("_v") {
+        Some((alias, version)) if !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) => alias,
+        _ => index_name,
+    }
+}
+
+/// Run a reindex job in the background: re-embed every chunk of the chatbot's shards into new
+/// versioned indices, then swap each shard's alias over to its new index and drop the old ones.
+/// Searches keep using the old indices until the swap
+pub fn spawn_reindex(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: Arc<ChatbotCache>,
+    job: ReindexJob,
+    shard_count: i32,
+) {
+    jobs.spawn(async move {
+        tracing::info!("Starting reindex of chatbot {} into version {}", job.chatbot_id, job.version);
+
+        match reindex(&db, &vector_store, embedding_cache, &job, shard_count).await {
+            Ok(processed) => {
+                if let Err(e) = finish_reindex_job(&db, job.id, None).await {
+                    tracing::error!("❌ Failed to record completed reindex job {}: {}", job.id, e);
+                }
+                let event = CacheEvent::KnowledgeVersionChanged { chatbot_id: job.chatbot_id };
+                if let Err(e) = publish(&db, &chatbot_cache, event).await {
+                    tracing::warn!("⚠️ Failed to publish knowledge version change: {}", e);
+                }
+                tracing::info!(
+                    "✅ Reindexed {} chunks of chatbot {} into version {}",
+                    processed,
+                    job.chatbot_id,
+                    job.version
+                );
+            }
+            Err(e) => {
+                tracing::error!("❌ Reindex job {} failed: {}", job.id, e);
+                if let Err(e) = finish_reindex_job(&db, job.id, Some(&e.to_string())).await {
+                    tracing::error!("❌ Failed to record failed reindex job {}: {}", job.id, e);
+                }
+            }
+        }
+    });
+}
+
+// Copy, swap and clean up. Returns how many chunks were reindexed
+async fn reindex(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    job: &ReindexJob,
+    shard_count: i32,
+) -> Result<u64> {
+    let embedding_service = EmbeddingService::new(vector_store.clone(), embedding_cache)?;
+    let aliases = shard_indices(&chatbot_index_name(job.organization_id, job.chatbot_id), shard_count);
+    let targets: Vec<String> = aliases.iter().map(|alias| versioned_index_name(alias, job.version)).collect();
+
+    let processed = match copy_chunks(db, vector_store, &embedding_service, job, &aliases, &targets).await {
+        Ok(processed) => processed,
+        Err(e) => {
+            // Nothing points at the new indices yet, so they can go
+            if let Err(cleanup) = vector_store.delete_collections(&targets).await {
+                tracing::warn!("⚠️ Failed to delete indices of failed reindex {}: {}", job.id, cleanup);
+            }
+            return Err(e);
+        }
+    };
+
+    let swaps: Vec<(String, String)> = aliases.into_iter().zip(targets).collect();
+    let previous = vector_store.swap_aliases(&swaps).await?;
+    if !previous.is_empty()
+        && let Err(e) = vector_store.delete_collections(&previous).await
+    {
+        tracing::warn!("⚠️ Failed to delete indices replaced by reindex {}: {:?}: {}", job.id, previous, e);
+    }
+
+    Ok(processed)
+}
+
+// Re-embed every chunk of each alias into its target index, keeping chunk ids and metadata
+async fn copy_chunks(
+    db: &PgPool,
+    vector_store: &VectorBackend,
+    embedding_service: &EmbeddingService,
+    job: &ReindexJob,
+    aliases: &[String],
+    targets: &[String],
+) -> Result<u64> {
+    let total = vector_store.count_documents(aliases).await? as i64;
+    update_reindex_job_progress(db, job.id, total, 0).await?;
+
+    let mut processed = 0;
+    for (alias, target) in aliases.iter().zip(targets) {
+        embedding_service.create_collection_if_not_exists(target).await?;
+
+        let mut cursor = None;
+        loop {
+            let page = vector_store.scroll_chunks(alias, cursor.as_deref(), PAGE_SIZE).await?;
+            if !page.chunks.is_empty() {
+                let texts: Vec<String> = page.chunks.iter().map(|chunk| chunk.text.clone()).collect();
+                let embeddings = embedding_service.embed_texts(&texts).await?;
+                let documents: Vec<DocumentWithEmbedding> = page
+                    .chunks
+                    .into_iter()
+                    .zip(embeddings)
+                    .map(|(chunk, embedding)| DocumentWithEmbedding { embedding, ..chunk })
+                    .collect();
+                processed += vector_store.index_documents(target, documents).await? as u64;
+
+                if !update_reindex_job_progress(db, job.id, total.max(processed as i64), processed as i64).await? {
+                    return Err(anyhow::anyhow!("Job is no longer running"));
+                }
+            }
+
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+    }
+
+    Ok(processed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_index_name_round_trips() {
+        let alias = "org_a_chatbot_b_shard_2";
+        let index_name = versioned_index_name(alias, 3);
+        assert_eq!(index_name, "org_a_chatbot_b_shard_2_v3");
+        assert_eq!(alias_of(&index_name), alias);
+    }
+
+    #[test]
+    fn test_alias_of_leaves_other_names() {
+        assert_eq!(alias_of("org_a_chatbot_b"), "org_a_chatbot_b");
+        assert_eq!(alias_of("org_a_chatbot_b_v"), "org_a_chatbot_b_v");
+        assert_eq!(alias_of("org_a_chatbot_b_vx"), "org_a_chatbot_b_vx");
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use crate::db::models::{ChatBot, Conversation};
+use crate::services::answer_policy::filter_by_min_score;
+use crate::services::embedding::EmbeddingService;
+use crate::services::gemini::GeminiService;
+use crate::services::query_expansion::search_fused;
+use crate::services::stitching::group_by_document;
+use crate::services::token_budget::{format_document, join_sections};
+use crate::services::vector::SearchResult;
+
+/// Earlier turns of the chat's thread the draft is written with
+pub const SUGGESTION_HISTORY_TURNS: i64 = 10;
+// Chunks retrieved for the user's latest message
+const SUGGESTION_CHUNKS: u64 = 5;
+
+const NO_DOCUMENTS: &str = "No relevant documents were found.";
+
+/// A draft reply for an agent, with the prompt and chunks it was written from
+#[derive(Debug, Clone)]
+pub struct Draft {
+    pub text: String,
+    pub prompt: String,
+    pub sources: Vec<SearchResult>,
+}
+
+/// The chat as the agent sees it: user messages, bot answers (including the handoff notice) and
+/// earlier agent replies
+pub fn transcript(turns: &[Conversation]) -> String {
+    let mut lines = Vec::new();
+    for turn in turns {
+        if !turn.user_query.is_empty() {
+            lines.push(format!("User: {}", turn.user_query));
+        }
+        let reply = turn.bot_response.as_deref().unwrap_or_default();
+        if !reply.is_empty() {
+            let responder = if turn.author == "human" { "Agent" } else { "Bot" };
+            lines.push(format!("{}: {}", responder, reply));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Prompt for a reply a human agent will review before sending. The draft speaks as the agent, not
+/// as the bot, and sticks to the documents
+pub fn suggestion_prompt(transcript: &str, documents: &str, question: &str) -> String {
+    format!(
+        "You are drafting a reply for a human support agent who has taken over this chat from a bot. \
+         The agent will review and edit the draft before sending it.\n\
+         Write in the first person as the agent, in the language of the user's message. Answer from the \
+         documents below; when they don't cover the question, say what the agent should check instead \
+         of guessing. Return only the reply text.\n\n\
+         Documents:\n{}\n\n\
+         Conversation so far:\n{}\n\n\
+         User's latest message: {}\n\n\
+         Draft reply:",
+        documents, transcript, question
+    )
+}
+
+/// Draft a reply to the user's latest message from the chatbot's documents and the chat so far
+pub async fn draft_reply(
+    embedding_service: &EmbeddingService,
+    gemini: &GeminiService,
+    chatbot: &ChatBot,
+    index_names: &[String],
+    turns: &[Conversation],
+    question: &str,
+) -> Result<Draft> {
+    let results = search_fused(
+        embedding_service,
+        index_names,
+        &[question.to_string()],
+        SUGGESTION_CHUNKS,
+        chatbot.mmr_lambda,
+    )
+    .await?;
+    let sources = filter_by_min_score(results, chatbot.min_score);
+
+    let documents = if sources.is_empty() {
+        NO_DOCUMENTS.to_string()
+    } else {
+        join_sections(group_by_document(&sources).iter().map(format_document))
+    };
+    let prompt = suggestion_prompt(&transcript(turns), &documents, question);
+    let text = gemini.generate_response(&prompt).await?;
+
+    Ok(Draft {
+        text: text.trim().to_string(),
+        prompt,
+        sources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn turn(user_query: &str, bot_response: &str, author: &str) -> Conversation {
+        Conversation {
+            id: Uuid::nil(),
+            session_id: Uuid::nil(),
+            chat_id: Uuid::nil(),
+            chatbot_id: None,
+            thread_id: None,
+            sequence_number: 1,
+            user_query: user_query.to_string(),
+            bot_response: Some(bot_response.to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            status: "active".to_string(),
+            generation_status: "complete".to_string(),
+            author: author.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_transcript_labels_agent_replies() {
+        let turns = [
+            turn("Where is my order?", "It ships in 2 days.", "bot"),
+            turn("Can I talk to someone?", "", "bot"),
+            turn("", "Hi, I'm looking into it.", "human"),
+        ];
+        assert_eq!(
+            transcript(&turns),
+            "User: Where is my order?\nBot: It ships in 2 days.\nUser: Can I talk to someone?\nAgent: Hi, I'm looking into it."
+        );
+    }
+
+    #[test]
+    fn test_prompt_includes_documents_and_question() {
+        let prompt = suggestion_prompt("User: hi", "Document: faq.md\nContent: Refunds take 5 days", "How long do refunds take?");
+        assert!(prompt.contains("Content: Refunds take 5 days"));
+        assert!(prompt.contains("Conversation so far:\nUser: hi"));
+        assert!(prompt.ends_with("User's latest message: How long do refunds take?\n\nDraft reply:"));
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::models::{ChatBot, FaqCluster, FeedbackSummary, ReportSchedule, UsageTotals};
+use crate::db::queries::{
+    claim_due_report_schedules, get_feedback_summary_between, list_daily_usage, list_faq_clusters,
+    record_report_error, record_report_sent,
+};
+use crate::services::cache_invalidation::ChatbotCache;
+use crate::services::ingest_webhook::{sign, SIGNATURE_HEADER};
+use crate::services::public_url::public_client_builder;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::usage::usage_totals;
+
+pub const MAX_RECIPIENTS: usize = 20;
+const TOP_QUESTIONS: i64 = 5;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const TICK_SECS: u64 = 300;
+// Schedules claimed per tick
+const BATCH_SIZE: i64 = 20;
+// A failed delivery is retried this long after, until the next report is due
+const RETRY_MINUTES: i32 = 60;
+
+/// A chatbot's activity over a range of UTC days
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyReport {
+    pub chatbot_id: Uuid,
+    pub chatbot_name: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub usage: UsageTotals,
+    /// Ratings given to turns during the range
+    pub feedback: FeedbackSummary,
+    /// The chatbot's current most asked question clusters
+    pub top_questions: Vec<FaqCluster>,
+}
+
+/// The Monday-to-Sunday week before the one `today` is in
+pub fn previous_week(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let to = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64 + 1);
+    (to - chrono::Duration::days(6), to)
+}
+
+/// Loose check that a recipient looks like an address; the mail server has the final say
+pub fn is_valid_email(address: &str) -> bool {
+    match address.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !address.chars().any(|c| c.is_whitespace() || c == ',')
+        }
+        None => false,
+    }
+}
+
+/// Gather a chatbot's usage, feedback and top questions between two UTC days, inclusive
+pub async fn build_report(
+    db: &PgPool,
+    organization_id: Uuid,
+    chatbot: &ChatBot,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<WeeklyReport> {
+    let days = list_daily_usage(db, organization_id, chatbot.id, from, to).await?;
+    let feedback = get_feedback_summary_between(db, organization_id, chatbot.id, from, to).await?;
+    let top_questions = list_faq_clusters(db, organization_id, chatbot.id, TOP_QUESTIONS).await?;
+
+    Ok(WeeklyReport {
+        chatbot_id: chatbot.id,
+        chatbot_name: chatbot.name.clone(),
+        from,
+        to,
+        usage: usage_totals(&days),
+        feedback,
+        top_questions,
+    })
+}
+
+/// The report as the body of a plain-text email
+pub fn report_text(report: &WeeklyReport) -> String {
+    let mut text = format!("Weekly report for {}\n{} to {}\n\n", report.chatbot_name, report.from, report.to);
+
+    text.push_str("Usage\n");
+    text.push_str(&format!("  Requests: {}\n", report.usage.requests));
+    text.push_str(&format!("  Tokens: {} in, {} out\n", report.usage.input_tokens, report.usage.output_tokens));
+    text.push_str(&format!("  Searches: {}\n", report.usage.searches));
+    text.push_str(&format!("  Embedding calls: {}\n\n", report.usage.embedding_calls));
+
+    text.push_str("Feedback\n");
+    if report.feedback.total == 0 {
+        text.push_str("  No ratings\n\n");
+    } else {
+        text.push_str(&format!(
+            "  {} ratings: {} thumbs up, {} thumbs down ({:.0}% positive)\n\n",
+            report.feedback.total,
+            report.feedback.thumbs_up,
+            report.feedback.thumbs_down,
+            100.0 * report.feedback.thumbs_up as f64 / report.feedback.total as f64
+        ));
+    }
+
+    text.push_str("Top questions\n");
+    if report.top_questions.is_empty() {
+        text.push_str("  No question clusters yet\n");
+    }
+    for cluster in &report.top_questions {
+        text.push_str(&format!(
+            "  {}. {} (asked {} times)\n",
+            cluster.rank, cluster.representative_question, cluster.hit_count
+        ));
+    }
+    text
+}
+
+#[derive(Debug, Serialize)]
+struct ReportDelivery<'a> {
+    event: &'static str,
+    organization_id: Uuid,
+    report: &'a WeeklyReport,
+}
+
+// POST the report to the schedule's webhook, signed when it has a secret
+async fn post_webhook(url: &str, secret: Option<&str>, delivery: &ReportDelivery<'_>) -> Result<()> {
+    let timeout_secs = std::env::var("REPORT_WEBHOOK_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+    let body = serde_json::to_vec(delivery)?;
+    let mut request = public_client_builder()
+        .build()?
+        .post(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = secret {
+        request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+    }
+
+    request.body(body).send().await?.error_for_status()?;
+    Ok(())
+}
+
+// Send a plain-text email over SMTP with STARTTLS. `SMTP_HOST` and `REPORT_EMAIL_FROM` are
+// required; `SMTP_PORT` defaults to 587 and `SMTP_USERNAME`/`SMTP_PASSWORD` are optional
+async fn send_email(recipients: &[String], subject: &str, body: &str) -> Result<()> {
+    #[cfg(feature = "smtp")]
+    {
+        use lettre::message::header::ContentType;
+        use lettre::transport::smtp::authentication::Credentials;
+        use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+        let host = std::env::var("SMTP_HOST").map_err(|_| anyhow::anyhow!("SMTP_HOST is not set"))?;
+        let from = std::env::var("REPORT_EMAIL_FROM").map_err(|_| anyhow::anyhow!("REPORT_EMAIL_FROM is not set"))?;
+        let port = std::env::var("SMTP_PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(587);
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?.port(port);
+        if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+        let mailer = transport.build();
+
+        for recipient in recipients {
+            let message = Message::builder()
+                .from(from.parse()?)
+                .to(recipient.parse()?)
+                .subject(subject)
+                .header(ContentType::TEXT_PLAIN)
+                .body(body.to_string())?;
+            mailer.send(message).await?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "smtp"))]
+    {
+        let _ = (recipients, subject, body);
+        anyhow::bail!("this build lacks the smtp feature")
+    }
+}
+
+/// Send the report to the schedule's webhook and email recipients. Every channel is tried; the
+/// first failure is returned
+pub async fn deliver_report(schedule: &ReportSchedule, report: &WeeklyReport) -> Result<()> {
+    let mut result = Ok(());
+
+    if let Some(url) = schedule.webhook_url.as_deref() {
+        let delivery = ReportDelivery {
+            event: "chatbot.weekly_report",
+            organization_id: schedule.organization_id,
+            report,
+        };
+        if let Err(e) = post_webhook(url, schedule.webhook_secret.as_deref(), &delivery).await {
+            tracing::error!("❌ Report webhook failed for chatbot {}: {}", schedule.chatbot_id, e);
+            result = Err(e);
+        }
+    }
+
+    if !schedule.email_recipients.is_empty() {
+        let subject = format!("Weekly report for {}: {} to {}", report.chatbot_name, report.from, report.to);
+        if let Err(e) = send_email(&schedule.email_recipients, &subject, &report_text(report)).await {
+            tracing::error!("❌ Report email failed for chatbot {}: {}", schedule.chatbot_id, e);
+            result = result.and(Err(e));
+        }
+    }
+    result
+}
+
+// Build last week's report for a claimed schedule and deliver it
+async fn send_scheduled_report(db: &PgPool, chatbot_cache: &ChatbotCache, schedule: &ReportSchedule) -> Result<()> {
+    let chatbot = chatbot_cache
+        .get_chatbot(db, schedule.organization_id, schedule.chatbot_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Chatbot {} not found", schedule.chatbot_id))?;
+    let (from, to) = previous_week(Utc::now().date_naive());
+    let report = build_report(db, schedule.organization_id, &chatbot, from, to).await?;
+    deliver_report(schedule, &report).await
+}
+
+// Spawn the background task that delivers weekly reports when they are due
+pub fn spawn_report_task(jobs: &BackgroundJobs, db: Arc<PgPool>, chatbot_cache: Arc<ChatbotCache>) {
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let due = match claim_due_report_schedules(&db, BATCH_SIZE).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!("❌ Report task failed to list schedules: {}", e);
+                    continue;
+                }
+            };
+
+            for schedule in &due {
+                let recorded = match send_scheduled_report(&db, &chatbot_cache, schedule).await {
+                    Ok(()) => {
+                        tracing::info!("✅ Weekly report delivered for chatbot {}", schedule.chatbot_id);
+                        record_report_sent(&db, schedule.id).await
+                    }
+                    Err(e) => {
+                        tracing::error!("❌ Weekly report for chatbot {} failed: {}", schedule.chatbot_id, e);
+                        record_report_error(&db, schedule.id, &e.to_string(), RETRY_MINUTES).await
+                    }
+                };
+                if let Err(e) = recorded {
+                    tracing::error!("❌ Failed to record report delivery for chatbot {}: {}", schedule.chatbot_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> WeeklyReport {
+        WeeklyReport {
+            chatbot_id: Uuid::nil(),
+            chatbot_name: "Support".to_string(),
+            from: "2026-03-02".parse().unwrap(),
+            to: "2026-03-08".parse().unwrap(),
+            usage: UsageTotals {
+                requests: 120,
+                input_tokens: 50_000,
+                output_tokens: 9_000,
+                embedding_calls: 130,
+                searches: 120,
+            },
+            feedback: FeedbackSummary { total: 8, thumbs_up: 6, thumbs_down: 2 },
+            top_questions: vec![FaqCluster {
+                chatbot_id: Uuid::nil(),
+                rank: 1,
+                representative_question: "How do I reset my password?".to_string(),
+                sample_questions: Vec::new(),
+                hit_count: 14,
+                computed_at: "2026-03-08T00:00:00Z".parse().unwrap(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_previous_week() {
+        let week = ("2026-03-02".parse().unwrap(), "2026-03-08".parse().unwrap());
+        // Monday and Sunday of the following week both report the same week
+        assert_eq!(previous_week("2026-03-09".parse().unwrap()), week);
+        assert_eq!(previous_week("2026-03-15".parse().unwrap()), week);
+    }
+
+    #[test]
+    fn test_is_valid_email() {
+        assert!(is_valid_email("ops@example.com"));
+        assert!(!is_valid_email("ops@localhost"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("a@example.com, b@example.com"));
+    }
+
+    #[test]
+    fn test_report_text() {
+        let text = report_text(&report());
+        assert!(text.starts_with("Weekly report for Support\n2026-03-02 to 2026-03-08\n"));
+        assert!(text.contains("  Requests: 120\n"));
+        assert!(text.contains("  8 ratings: 6 thumbs up, 2 thumbs down (75% positive)\n"));
+        assert!(text.contains("  1. How do I reset my password? (asked 14 times)\n"));
+
+        let quiet = WeeklyReport {
+            feedback: FeedbackSummary { total: 0, thumbs_up: 0, thumbs_down: 0 },
+            top_questions: Vec::new(),
+            ..report()
+        };
+        let text = report_text(&quiet);
+        assert!(text.contains("  No ratings\n"));
+        assert!(text.contains("  No question clusters yet\n"));
+    }
+}
@@ -0,0 +1,224 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing;
+
+// Weight of the newest observation in the per-stage latency moving average
+const EWMA_ALPHA: f64 = 0.2;
+
+/// Per-request wall-clock budget for retrieval.
+///
+/// Optional stages (reranking, expansion, compression, ...) only run when their
+/// expected latency fits in what is left; base kNN results are always kept.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyBudget {
+    started: Instant,
+    budget: Option<Duration>,
+}
+
+impl LatencyBudget {
+    /// Budget from the request, falling back to `RETRIEVAL_LATENCY_BUDGET_MS`. No budget means unlimited.
+    pub fn from_request(budget_ms: Option<u64>) -> Self {
+        let budget_ms = budget_ms.or_else(|| {
+            std::env::var("RETRIEVAL_LATENCY_BUDGET_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+        });
+        Self::new(budget_ms.map(Duration::from_millis))
+    }
+
+    pub fn new(budget: Option<Duration>) -> Self {
+        Self {
+            started: Instant::now(),
+            budget,
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.started.elapsed()))
+    }
+
+    /// Whether a stage expected to take `estimate` still fits
+    pub fn allows(&self, estimate: Duration) -> bool {
+        self.remaining().map(|remaining| estimate <= remaining).unwrap_or(true)
+    }
+}
+
+/// Moving average of observed latency per stage, shared across requests
+#[derive(Default)]
+pub struct StageTimings {
+    averages: Mutex<HashMap<&'static str, f64>>,
+}
+
+impl StageTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expected latency of a stage; zero until it has been observed once
+    pub fn estimate(&self, stage: &'static str) -> Duration {
+        self.averages
+            .lock()
+            .ok()
+            .and_then(|a| a.get(stage).copied())
+            .map(Duration::from_secs_f64)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub fn observe(&self, stage: &'static str, elapsed: Duration) {
+        if let Ok(mut averages) = self.averages.lock() {
+            let sample = elapsed.as_secs_f64();
+            averages
+                .entry(stage)
+                .and_modify(|avg| *avg = EWMA_ALPHA * sample + (1.0 - EWMA_ALPHA) * *avg)
+                .or_insert(sample);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    Ran,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTrace {
+    pub stage: &'static str,
+    pub status: StageStatus,
+    pub elapsed_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Which retrieval stages ran or were skipped for a request
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetrievalTrace {
+    pub budget_ms: Option<u64>,
+    pub stages: Vec<StageTrace>,
+}
+
+impl RetrievalTrace {
+    pub fn skipped_stages(&self) -> Vec<&'static str> {
+        self.stages
+            .iter()
+            .filter(|s| s.status == StageStatus::Skipped)
+            .map(|s| s.stage)
+            .collect()
+    }
+}
+
+/// Runs retrieval stages against a latency budget and records the trace
+pub struct RetrievalRun {
+    pub budget: LatencyBudget,
+    pub trace: RetrievalTrace,
+    timings: Arc<StageTimings>,
+}
+
+impl RetrievalRun {
+    pub fn new(budget: LatencyBudget, timings: Arc<StageTimings>) -> Self {
+        Self {
+            trace: RetrievalTrace {
+                budget_ms: budget.budget.map(|b| b.as_millis() as u64),
+                stages: Vec::new(),
+            },
+            budget,
+            timings,
+        }
+    }
+
+    /// Run a mandatory stage, recording its latency
+    pub async fn run_required<T, Fut>(&mut self, stage: &'static str, run: impl FnOnce() -> Fut) -> T
+    where
+        Fut: Future<Output = T>,
+    {
+        let started = Instant::now();
+        let result = run().await;
+        self.record_ran(stage, started.elapsed());
+        result
+    }
+
+    /// Run an optional stage only if its expected latency fits in the remaining budget
+    pub async fn run_optional<T, Fut>(&mut self, stage: &'static str, run: impl FnOnce() -> Fut) -> Option<T>
+    where
+        Fut: Future<Output = T>,
+    {
+        let estimate = self.timings.estimate(stage);
+        if !self.budget.allows(estimate) {
+            let reason = format!(
+                "expected {}ms, {}ms left",
+                estimate.as_millis(),
+                self.budget.remaining().unwrap_or_default().as_millis()
+            );
+            tracing::info!("Skipping retrieval stage '{}': {}", stage, reason);
+            self.trace.stages.push(StageTrace {
+                stage,
+                status: StageStatus::Skipped,
+                elapsed_ms: 0.0,
+                reason: Some(reason),
+            });
+            return None;
+        }
+
+        let started = Instant::now();
+        let result = run().await;
+        self.record_ran(stage, started.elapsed());
+        Some(result)
+    }
+
+    fn record_ran(&mut self, stage: &'static str, elapsed: Duration) {
+        self.timings.observe(stage, elapsed);
+        self.trace.stages.push(StageTrace {
+            stage,
+            status: StageStatus::Ran,
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            reason: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_allows() {
+        let unlimited = LatencyBudget::new(None);
+        assert!(unlimited.allows(Duration::from_secs(3600)));
+
+        let budget = LatencyBudget::new(Some(Duration::from_secs(60)));
+        assert!(budget.allows(Duration::from_millis(10)));
+        assert!(!budget.allows(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn test_stage_timings_moving_average() {
+        let timings = StageTimings::new();
+        assert_eq!(timings.estimate("rerank"), Duration::ZERO);
+
+        timings.observe("rerank", Duration::from_millis(100));
+        assert_eq!(timings.estimate("rerank"), Duration::from_millis(100));
+
+        timings.observe("rerank", Duration::from_millis(200));
+        let estimate = timings.estimate("rerank").as_secs_f64() * 1000.0;
+        assert!((estimate - 120.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_optional_stage_skipped_when_over_budget() {
+        let timings = Arc::new(StageTimings::new());
+        timings.observe("rerank", Duration::from_secs(10));
+
+        let mut run = RetrievalRun::new(LatencyBudget::new(Some(Duration::from_millis(50))), timings);
+        let base = run.run_required("knn", || async { 1 }).await;
+        let reranked = run.run_optional("rerank", || async { 2 }).await;
+
+        assert_eq!(base, 1);
+        assert_eq!(reranked, None);
+        assert_eq!(run.trace.skipped_stages(), vec!["rerank"]);
+    }
+}
@@ -0,0 +1,198 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::models::RelevantChunk;
+use crate::services::vector::SearchResult;
+
+/// Most labeled queries one run may hold
+pub const MAX_GOLDEN_QUERIES: usize = 200;
+/// Largest cutoff a run may report; the search fetches this many hits per query at most
+pub const MAX_K: usize = 50;
+const DEFAULT_K_VALUES: [usize; 4] = [1, 3, 5, 10];
+
+/// Recall and nDCG at one cutoff
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CutoffMetrics {
+    pub k: usize,
+    /// Share of the labeled chunks found in the top `k` hits
+    pub recall: f64,
+    /// Discounted gain of the top `k` hits over the best possible ranking, with binary relevance
+    pub ndcg: f64,
+}
+
+/// How the search did on one labeled query
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct QueryMetrics {
+    pub query: String,
+    /// 1-based rank of the first relevant hit; unset when none is within the largest cutoff
+    pub first_relevant_rank: Option<usize>,
+    pub reciprocal_rank: f64,
+    pub cutoffs: Vec<CutoffMetrics>,
+}
+
+/// Means over every labeled query of a run
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RetrievalMetrics {
+    pub queries: usize,
+    /// Mean reciprocal rank within the largest cutoff
+    pub mrr: f64,
+    pub cutoffs: Vec<CutoffMetrics>,
+}
+
+/// The cutoffs to report, sorted and without duplicates; 1, 3, 5 and 10 unless the request sets them
+pub fn k_values(requested: Option<Vec<usize>>) -> Result<Vec<usize>, String> {
+    let mut k_values = requested.unwrap_or_else(|| DEFAULT_K_VALUES.to_vec());
+    if k_values.is_empty() || k_values.iter().any(|&k| k == 0 || k > MAX_K) {
+        return Err(format!("k_values must be between 1 and {}", MAX_K));
+    }
+    k_values.sort_unstable();
+    k_values.dedup();
+    Ok(k_values)
+}
+
+fn matches(label: &RelevantChunk, hit: &SearchResult) -> bool {
+    label.file_path == hit.file_path && label.chunk_index.is_none_or(|index| index == hit.chunk_index)
+}
+
+// Whether each hit is relevant. A label is credited to the first hit it matches only, so that a
+// file labeled as a whole counts once however many of its chunks are retrieved
+fn relevance(hits: &[SearchResult], relevant: &[RelevantChunk]) -> Vec<bool> {
+    let mut credited = vec![false; relevant.len()];
+    hits.iter()
+        .map(|hit| match (0..relevant.len()).find(|&i| !credited[i] && matches(&relevant[i], hit)) {
+            Some(i) => {
+                credited[i] = true;
+                true
+            }
+            None => false,
+        })
+        .collect()
+}
+
+// Gain of a relevant hit at 0-based `position`
+fn discount(position: usize) -> f64 {
+    1.0 / (position as f64 + 2.0).log2()
+}
+
+fn ratio(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 { part / whole } else { 0.0 }
+}
+
+/// Score the hits of one query, best first, against the chunks labeled relevant for it
+pub fn score_query(query: &str, hits: &[SearchResult], relevant: &[RelevantChunk], k_values: &[usize]) -> QueryMetrics {
+    let relevance = relevance(hits, relevant);
+    let first_relevant_rank = relevance.iter().position(|&relevant| relevant).map(|position| position + 1);
+    let cutoffs = k_values
+        .iter()
+        .map(|&k| {
+            let top = &relevance[..k.min(relevance.len())];
+            let found = top.iter().filter(|&&relevant| relevant).count();
+            let gain: f64 = (0..top.len()).filter(|&position| top[position]).map(discount).sum();
+            let ideal_gain: f64 = (0..k.min(relevant.len())).map(discount).sum();
+            CutoffMetrics { k, recall: ratio(found as f64, relevant.len() as f64), ndcg: ratio(gain, ideal_gain) }
+        })
+        .collect();
+
+    QueryMetrics {
+        query: query.to_string(),
+        first_relevant_rank,
+        reciprocal_rank: first_relevant_rank.map_or(0.0, |rank| 1.0 / rank as f64),
+        cutoffs,
+    }
+}
+
+/// Average the metrics of every query scored with the same `k_values`
+pub fn aggregate(results: &[QueryMetrics], k_values: &[usize]) -> RetrievalMetrics {
+    let mean = |metric: &dyn Fn(&QueryMetrics) -> f64| ratio(results.iter().map(metric).sum(), results.len() as f64);
+
+    RetrievalMetrics {
+        queries: results.len(),
+        mrr: mean(&|result| result.reciprocal_rank),
+        cutoffs: k_values
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| CutoffMetrics {
+                k,
+                recall: mean(&|result| result.cutoffs[i].recall),
+                ndcg: mean(&|result| result.cutoffs[i].ndcg),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(file_path: &str, chunk_index: i64) -> SearchResult {
+        SearchResult {
+            text: String::new(),
+            score: 0.5,
+            chunk_index,
+            file_path: file_path.to_string(),
+            parent_index: None,
+        }
+    }
+
+    fn label(file_path: &str, chunk_index: Option<i64>) -> RelevantChunk {
+        RelevantChunk { file_path: file_path.to_string(), chunk_index }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_k_values() {
+        assert_eq!(k_values(None).unwrap(), vec![1, 3, 5, 10]);
+        assert_eq!(k_values(Some(vec![5, 1, 5])).unwrap(), vec![1, 5]);
+        assert!(k_values(Some(vec![])).is_err());
+        assert!(k_values(Some(vec![0])).is_err());
+        assert!(k_values(Some(vec![MAX_K + 1])).is_err());
+    }
+
+    #[test]
+    fn test_score_query() {
+        let hits = [hit("other.pdf", 0), hit("a.pdf", 0), hit("b.pdf", 1)];
+        let relevant = [label("a.pdf", Some(0)), label("b.pdf", Some(1))];
+        let metrics = score_query("q", &hits, &relevant, &[1, 3]);
+
+        assert_eq!(metrics.first_relevant_rank, Some(2));
+        assert!(close(metrics.reciprocal_rank, 0.5));
+        assert_eq!(metrics.cutoffs[0], CutoffMetrics { k: 1, recall: 0.0, ndcg: 0.0 });
+        assert!(close(metrics.cutoffs[1].recall, 1.0));
+        // (1/log2(3) + 1/log2(4)) / (1 + 1/log2(3))
+        assert!(close(metrics.cutoffs[1].ndcg, 0.693426));
+    }
+
+    #[test]
+    fn test_whole_file_label_counts_once() {
+        let hits = [hit("a.pdf", 3), hit("a.pdf", 4)];
+        let metrics = score_query("q", &hits, &[label("a.pdf", None)], &[2]);
+
+        assert_eq!(metrics.first_relevant_rank, Some(1));
+        assert!(close(metrics.cutoffs[0].recall, 1.0));
+        assert!(close(metrics.cutoffs[0].ndcg, 1.0));
+    }
+
+    #[test]
+    fn test_nothing_relevant_retrieved() {
+        let metrics = score_query("q", &[hit("other.pdf", 0)], &[label("a.pdf", Some(0))], &[1]);
+
+        assert_eq!(metrics.first_relevant_rank, None);
+        assert_eq!(metrics.reciprocal_rank, 0.0);
+        assert_eq!(metrics.cutoffs[0], CutoffMetrics { k: 1, recall: 0.0, ndcg: 0.0 });
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let found = score_query("q1", &[hit("a.pdf", 0)], &[label("a.pdf", Some(0))], &[1]);
+        let missed = score_query("q2", &[hit("other.pdf", 0)], &[label("a.pdf", Some(0))], &[1]);
+        let metrics = aggregate(&[found, missed], &[1]);
+
+        assert_eq!(metrics.queries, 2);
+        assert!(close(metrics.mrr, 0.5));
+        assert_eq!(metrics.cutoffs, vec![CutoffMetrics { k: 1, recall: 0.5, ndcg: 0.5 }]);
+        assert_eq!(aggregate(&[], &[1]).cutoffs[0].recall, 0.0);
+    }
+}
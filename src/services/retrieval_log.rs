@@ -0,0 +1,39 @@
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::models::LoggedChunk;
+use crate::db::queries::insert_retrieval_log;
+use crate::services::retrieval::RetrievalTrace;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::SearchResult;
+
+/// Store the chunks, scores and prompt an answer is generated from, in the background, so the
+/// answer can be debugged later. Only turns that reach the LLM are logged
+pub fn record_context(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    conversation_id: Uuid,
+    results: &[SearchResult],
+    prompt: &str,
+    trace: &RetrievalTrace,
+) {
+    let chunks: Vec<LoggedChunk> = results
+        .iter()
+        .map(|result| LoggedChunk {
+            file_path: result.file_path.clone(),
+            chunk_index: result.chunk_index,
+            score: result.score,
+            text: result.text.clone(),
+        })
+        .collect();
+    let prompt = prompt.to_string();
+    let trace = json!(trace);
+
+    jobs.spawn(async move {
+        if let Err(e) = insert_retrieval_log(&db, conversation_id, &chunks, &prompt, &trace).await {
+            tracing::warn!("⚠️ Failed to record retrieval log: {}", e);
+        }
+    });
+}
@@ -0,0 +1,329 @@
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+// Statuses worth retrying: timeouts, rate limits and overloaded or restarting upstreams
+const TRANSIENT_STATUSES: [u16; 5] = [408, 429, 502, 503, 504];
+
+// Error codes Google APIs use alongside 429 and 503
+const TRANSIENT_CODES: [&str; 2] = ["RESOURCE_EXHAUSTED", "UNAVAILABLE"];
+
+static POLICY: LazyLock<RetryPolicy> = LazyLock::new(RetryPolicy::from_env);
+
+/// How many times to try a call and how long to wait in between
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// From `RETRY_MAX_ATTEMPTS` (default 3), `RETRY_BASE_DELAY_MS` (200) and `RETRY_MAX_DELAY_MS` (5000)
+    pub fn from_env() -> Self {
+        let env_u64 = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_attempts: env_u64("RETRY_MAX_ATTEMPTS", 3).clamp(1, 10) as u32,
+            base_delay: Duration::from_millis(env_u64("RETRY_BASE_DELAY_MS", 200)),
+            max_delay: Duration::from_millis(env_u64("RETRY_MAX_DELAY_MS", 5000)),
+        }
+    }
+
+    /// Delay before retry number `retry` (0-based). The cap doubles each time up to `max_delay`,
+    /// and `jitter` in [0, 1] picks a point in its upper half so concurrent callers spread out
+    pub fn backoff(&self, retry: u32, jitter: f64) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        cap.mul_f64(0.5 + 0.5 * jitter.clamp(0.0, 1.0))
+    }
+}
+
+// Uniform value in [0, 1) from the random low bits of a v4 UUID
+fn random_unit() -> f64 {
+    const BITS: u32 = 53;
+    let bits = Uuid::new_v4().as_u128() as u64 & ((1 << BITS) - 1);
+    bits as f64 / (1u64 << BITS) as f64
+}
+
+/// Non-success HTTP status from an upstream, kept so it can be classified
+#[derive(Debug, thiserror::Error)]
+#[error("upstream returned status {status}")]
+pub struct UpstreamStatus {
+    pub status: u16,
+}
+
+pub fn is_transient_status(status: u16) -> bool {
+    TRANSIENT_STATUSES.contains(&status)
+}
+
+/// Classify an error message for clients that only expose text, such as gemini-rust
+pub fn is_transient_message(message: &str) -> bool {
+    let lowercase = message.to_lowercase();
+    if lowercase.contains("timed out") || lowercase.contains("timeout") {
+        return true;
+    }
+    message
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .any(|token| {
+            TRANSIENT_CODES.contains(&token)
+                || token.parse::<u16>().is_ok_and(is_transient_status)
+        })
+}
+
+/// Errors that may succeed if the call is repeated
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+fn reqwest_is_transient(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| is_transient_status(s.as_u16()))
+}
+
+fn elasticsearch_is_transient(e: &elasticsearch::Error) -> bool {
+    e.is_timeout() || e.status_code().is_some_and(|s| is_transient_status(s.as_u16()))
+}
+
+impl Transient for AppError {
+    fn is_transient(&self) -> bool {
+        match self {
+            AppError::Database(_) => false,
+            AppError::Elasticsearch(e) => elasticsearch_is_transient(e),
+            AppError::Reqwest(e) => reqwest_is_transient(e),
+            AppError::Other(message) => is_transient_message(message),
+        }
+    }
+}
+
+impl Transient for anyhow::Error {
+    fn is_transient(&self) -> bool {
+        if let Some(e) = self.downcast_ref::<UpstreamStatus>() {
+            is_transient_status(e.status)
+        } else if let Some(e) = self.downcast_ref::<elasticsearch::Error>() {
+            elasticsearch_is_transient(e)
+        } else if let Some(e) = self.downcast_ref::<reqwest::Error>() {
+            reqwest_is_transient(e)
+        } else if let Some(e) = self.downcast_ref::<AppError>() {
+            e.is_transient()
+        } else {
+            false
+        }
+    }
+}
+
+/// Services whose calls are retried
+#[derive(Debug, Clone, Copy)]
+pub enum Upstream {
+    Gemini,
+    Elasticsearch,
+    Qdrant,
+}
+
+impl Upstream {
+    fn counters(self) -> &'static Counters {
+        match self {
+            Upstream::Gemini => &GEMINI_COUNTERS,
+            Upstream::Elasticsearch => &ELASTICSEARCH_COUNTERS,
+            Upstream::Qdrant => &QDRANT_COUNTERS,
+        }
+    }
+}
+
+impl fmt::Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Upstream::Gemini => f.write_str("Gemini"),
+            Upstream::Elasticsearch => f.write_str("Elasticsearch"),
+            Upstream::Qdrant => f.write_str("Qdrant"),
+        }
+    }
+}
+
+struct Counters {
+    calls: AtomicU64,
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            calls: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            recovered: AtomicU64::new(0),
+            exhausted: AtomicU64::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> RetryCounts {
+        RetryCounts {
+            calls: self.calls.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            recovered: self.recovered.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static GEMINI_COUNTERS: Counters = Counters::new();
+static ELASTICSEARCH_COUNTERS: Counters = Counters::new();
+static QDRANT_COUNTERS: Counters = Counters::new();
+
+/// Retry counters for one upstream since the process started
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetryCounts {
+    /// Calls made through the retry wrapper
+    pub calls: u64,
+    /// Extra attempts after a transient failure
+    pub retries: u64,
+    /// Calls that succeeded after at least one retry
+    pub recovered: u64,
+    /// Calls that failed with a transient error after the last attempt
+    pub exhausted: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetryMetrics {
+    pub gemini: RetryCounts,
+    pub elasticsearch: RetryCounts,
+    pub qdrant: RetryCounts,
+}
+
+pub fn retry_metrics() -> RetryMetrics {
+    RetryMetrics {
+        gemini: GEMINI_COUNTERS.snapshot(),
+        elasticsearch: ELASTICSEARCH_COUNTERS.snapshot(),
+        qdrant: QDRANT_COUNTERS.snapshot(),
+    }
+}
+
+/// Run `call`, repeating it with exponential backoff and jitter while it fails with a transient
+/// error and attempts remain. Permanent errors are returned straight away
+pub async fn with_retry<T, E, F, Fut>(upstream: Upstream, operation: &'static str, mut call: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Transient + fmt::Display,
+{
+    let policy = *POLICY;
+    let counters = upstream.counters();
+    counters.calls.fetch_add(1, Ordering::Relaxed);
+
+    let mut retry = 0;
+    loop {
+        match call().await {
+            Ok(value) => {
+                if retry > 0 {
+                    counters.recovered.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!("✅ {} {} succeeded after {} retries", upstream, operation, retry);
+                }
+                return Ok(value);
+            }
+            Err(e) if e.is_transient() && retry + 1 < policy.max_attempts => {
+                let delay = policy.backoff(retry, random_unit());
+                tracing::warn!(
+                    "⚠️ {} {} failed ({}), retrying in {:?} (attempt {}/{})",
+                    upstream,
+                    operation,
+                    e,
+                    delay,
+                    retry + 2,
+                    policy.max_attempts
+                );
+                counters.retries.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            Err(e) => {
+                if e.is_transient() {
+                    counters.exhausted.fetch_add(1, Ordering::Relaxed);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        assert_eq!(policy.backoff(0, 1.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, 1.0), Duration::from_millis(400));
+        assert_eq!(policy.backoff(2, 0.0), Duration::from_millis(200));
+        assert_eq!(policy.backoff(10, 1.0), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(40, 1.0), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_random_unit_range() {
+        for _ in 0..100 {
+            let value = random_unit();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(is_transient_message("Gemini API error: bad response from server; code 503"));
+        assert!(is_transient_message("status: RESOURCE_EXHAUSTED"));
+        assert!(is_transient_message("operation timed out"));
+        assert!(!is_transient_message("Gemini API error: code 400; API key not valid"));
+        assert!(!is_transient_message("model returned 4290 tokens"));
+
+        assert!(anyhow::Error::new(UpstreamStatus { status: 429 }).is_transient());
+        assert!(!anyhow::Error::new(UpstreamStatus { status: 404 }).is_transient());
+        assert!(!anyhow::anyhow!("Search failed").is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_stops_on_permanent_errors() {
+        let mut calls = 0;
+        let result: Result<(), AppError> = with_retry(Upstream::Gemini, "test", || {
+            calls += 1;
+            async { Err(AppError::Other("code 400".to_string())) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_recovers_from_transient_errors() {
+        let mut calls = 0;
+        let result = with_retry(Upstream::Elasticsearch, "test", || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 2 {
+                    Err(anyhow::Error::new(UpstreamStatus { status: 503 }))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+    }
+}
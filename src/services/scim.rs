@@ -0,0 +1,425 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::db::models::{GroupMembership, User, UserGroup};
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const SERVICE_PROVIDER_CONFIG_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig";
+
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// A filter of the form `attribute eq "value"`, the only kind identity providers need for provisioning
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EqFilter {
+    /// Lowercased, since SCIM attribute names are case-insensitive
+    pub attribute: String,
+    pub value: String,
+}
+
+pub fn parse_eq_filter(filter: &str) -> Result<EqFilter, String> {
+    let unsupported = || format!("Unsupported filter '{}'; only `attribute eq \"value\"` is supported", filter);
+
+    let mut parts = filter.trim().splitn(3, char::is_whitespace);
+    let (Some(attribute), Some(operator), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(unsupported());
+    };
+    if !operator.eq_ignore_ascii_case("eq") {
+        return Err(unsupported());
+    }
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(unsupported)?;
+
+    Ok(EqFilter {
+        attribute: attribute.to_lowercase(),
+        value: value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    })
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScimName {
+    pub formatted: Option<String>,
+    #[serde(rename = "givenName")]
+    pub given_name: Option<String>,
+    #[serde(rename = "familyName")]
+    pub family_name: Option<String>,
+}
+
+impl ScimName {
+    // `formatted`, else given and family names joined
+    fn display(&self) -> Option<String> {
+        if let Some(formatted) = &self.formatted {
+            return Some(formatted.clone());
+        }
+        let parts: Vec<&str> =
+            [&self.given_name, &self.family_name].into_iter().flatten().map(String::as_str).collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+/// User resource sent on create and replace. `userName` is the user's email
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimUserRequest {
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    #[serde(rename = "externalId")]
+    pub external_id: Option<String>,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub name: Option<ScimName>,
+    pub active: Option<bool>,
+}
+
+impl ScimUserRequest {
+    pub fn display_name(&self) -> Option<String> {
+        self.display_name.clone().or_else(|| self.name.as_ref().and_then(ScimName::display))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimMemberRef {
+    pub value: String,
+}
+
+/// Group resource sent on create and replace
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimGroupRequest {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(rename = "externalId")]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub members: Vec<ScimMemberRef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatchRequest {
+    #[serde(rename = "Operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+/// Attributes a user PATCH sets; unset fields are left alone
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserChanges {
+    pub user_name: Option<String>,
+    pub display_name: Option<String>,
+    pub external_id: Option<String>,
+    pub active: Option<bool>,
+}
+
+// Some providers send booleans as "True"/"False" strings
+fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.to_lowercase().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_string(attribute: &str, value: &Value) -> Result<String, String> {
+    value.as_str().map(str::to_string).ok_or_else(|| format!("{} must be a string", attribute))
+}
+
+fn apply_user_attribute(changes: &mut UserChanges, attribute: &str, value: &Value) -> Result<(), String> {
+    match attribute.to_lowercase().as_str() {
+        "active" => changes.active = Some(as_bool(value).ok_or("active must be a boolean")?),
+        "username" => changes.user_name = Some(as_string("userName", value)?),
+        "displayname" | "name.formatted" => changes.display_name = Some(as_string("displayName", value)?),
+        "externalid" => changes.external_id = Some(as_string("externalId", value)?),
+        // Other attributes (phone numbers, titles, ...) aren't stored
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Collect the attributes a user PATCH request sets. Only add and replace are supported
+pub fn user_changes(operations: &[PatchOperation]) -> Result<UserChanges, String> {
+    let mut changes = UserChanges::default();
+    for operation in operations {
+        if !matches!(operation.op.to_lowercase().as_str(), "add" | "replace") {
+            return Err(format!("Unsupported operation '{}' on a user", operation.op));
+        }
+        let value = operation.value.as_ref().ok_or("Patch operation has no value")?;
+        match &operation.path {
+            Some(path) => apply_user_attribute(&mut changes, path, value)?,
+            None => {
+                let attributes = value.as_object().ok_or("Patch value without a path must be an object")?;
+                for (attribute, value) in attributes {
+                    apply_user_attribute(&mut changes, attribute, value)?;
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Attributes and membership changes a group PATCH makes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupChanges {
+    pub display_name: Option<String>,
+    pub external_id: Option<String>,
+    /// Remove every member before adding
+    pub replace_members: bool,
+    pub add_members: Vec<Uuid>,
+    pub remove_members: Vec<Uuid>,
+}
+
+/// Parse member references; ids that aren't UUIDs can't belong to any user
+pub fn member_ids(members: &[ScimMemberRef]) -> Result<Vec<Uuid>, String> {
+    members
+        .iter()
+        .map(|member| Uuid::parse_str(&member.value).map_err(|_| format!("Unknown member '{}'", member.value)))
+        .collect()
+}
+
+fn member_values(value: Option<&Value>) -> Result<Vec<Uuid>, String> {
+    let Some(value) = value else {
+        return Ok(Vec::new());
+    };
+    let members: Vec<ScimMemberRef> =
+        serde_json::from_value(value.clone()).map_err(|_| "members must be a list of {\"value\": id}".to_string())?;
+    member_ids(&members)
+}
+
+// The member id in a path such as `members[value eq "2819c223-..."]`
+fn member_path_id(path: &str) -> Result<Option<Uuid>, String> {
+    let Some(filter) = path.strip_prefix("members[").and_then(|rest| rest.strip_suffix(']')) else {
+        return Ok(None);
+    };
+    let filter = parse_eq_filter(filter)?;
+    if filter.attribute != "value" {
+        return Err(format!("Unsupported member filter '{}'", path));
+    }
+    Uuid::parse_str(&filter.value).map(Some).map_err(|_| format!("Unknown member '{}'", filter.value))
+}
+
+fn apply_group_attribute(
+    changes: &mut GroupChanges,
+    op: &str,
+    attribute: &str,
+    value: Option<&Value>,
+) -> Result<(), String> {
+    match (op, attribute.to_lowercase().as_str()) {
+        ("add", "members") => changes.add_members.extend(member_values(value)?),
+        ("replace", "members") => {
+            changes.replace_members = true;
+            changes.add_members = member_values(value)?;
+            changes.remove_members.clear();
+        }
+        // Removing `members` without a value empties the group
+        ("remove", "members") => match value {
+            Some(_) => changes.remove_members.extend(member_values(value)?),
+            None => {
+                changes.replace_members = true;
+                changes.add_members.clear();
+            }
+        },
+        ("add" | "replace", "displayname") => {
+            changes.display_name = Some(as_string("displayName", value.ok_or("displayName needs a value")?)?)
+        }
+        ("add" | "replace", "externalid") => {
+            changes.external_id = Some(as_string("externalId", value.ok_or("externalId needs a value")?)?)
+        }
+        _ => return Err(format!("Unsupported {} of '{}' on a group", op, attribute)),
+    }
+    Ok(())
+}
+
+/// Collect the changes a group PATCH request makes, in order
+pub fn group_changes(operations: &[PatchOperation]) -> Result<GroupChanges, String> {
+    let mut changes = GroupChanges::default();
+    for operation in operations {
+        let op = operation.op.to_lowercase();
+        if !matches!(op.as_str(), "add" | "replace" | "remove") {
+            return Err(format!("Unsupported operation '{}' on a group", operation.op));
+        }
+        match operation.path.as_deref() {
+            Some(path) => match member_path_id(path)? {
+                Some(id) if op == "remove" => changes.remove_members.push(id),
+                Some(_) => return Err(format!("Unsupported {} of '{}' on a group", op, path)),
+                None => apply_group_attribute(&mut changes, &op, path, operation.value.as_ref())?,
+            },
+            None => {
+                let value = operation.value.as_ref().ok_or("Patch operation has no value")?;
+                let attributes = value.as_object().ok_or("Patch value without a path must be an object")?;
+                for (attribute, value) in attributes {
+                    apply_group_attribute(&mut changes, &op, attribute, Some(value))?;
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Zero-based offset and page size from SCIM's 1-based `startIndex` and `count`
+pub fn page(start_index: Option<i64>, count: Option<i64>) -> (i64, i64) {
+    let offset = start_index.unwrap_or(1).max(1) - 1;
+    let limit = count.unwrap_or(DEFAULT_PAGE_SIZE).clamp(0, MAX_PAGE_SIZE);
+    (offset, limit)
+}
+
+pub fn list_response(resources: Vec<Value>, total: i64, offset: i64) -> Value {
+    json!({
+        "schemas": [LIST_RESPONSE_SCHEMA],
+        "totalResults": total,
+        "startIndex": offset + 1,
+        "itemsPerPage": resources.len(),
+        "Resources": resources
+    })
+}
+
+pub fn user_resource(user: &User, memberships: &[GroupMembership]) -> Value {
+    let groups: Vec<Value> = memberships
+        .iter()
+        .filter(|membership| membership.user_id == user.id)
+        .map(|membership| json!({ "value": membership.group_id, "display": membership.group_name }))
+        .collect();
+
+    json!({
+        "schemas": [USER_SCHEMA],
+        "id": user.id,
+        "externalId": user.external_id,
+        "userName": user.email,
+        "displayName": user.display_name,
+        "emails": [{ "value": user.email, "primary": true }],
+        "active": user.active,
+        "groups": groups,
+        "meta": {
+            "resourceType": "User",
+            "created": user.created_at.to_rfc3339(),
+            "lastModified": user.updated_at.to_rfc3339()
+        }
+    })
+}
+
+pub fn group_resource(group: &UserGroup, memberships: &[GroupMembership]) -> Value {
+    let members: Vec<Value> = memberships
+        .iter()
+        .filter(|membership| membership.group_id == group.id)
+        .map(|membership| json!({ "value": membership.user_id, "display": membership.email }))
+        .collect();
+
+    json!({
+        "schemas": [GROUP_SCHEMA],
+        "id": group.id,
+        "externalId": group.external_id,
+        "displayName": group.display_name,
+        "members": members,
+        "meta": {
+            "resourceType": "Group",
+            "created": group.created_at.to_rfc3339(),
+            "lastModified": group.updated_at.to_rfc3339()
+        }
+    })
+}
+
+/// What this server supports, for identity providers that check before provisioning
+pub fn service_provider_config() -> Value {
+    json!({
+        "schemas": [SERVICE_PROVIDER_CONFIG_SCHEMA],
+        "patch": { "supported": true },
+        "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+        "filter": { "supported": true, "maxResults": MAX_PAGE_SIZE },
+        "changePassword": { "supported": false },
+        "sort": { "supported": false },
+        "etag": { "supported": false },
+        "authenticationSchemes": [{
+            "type": "oauthbearertoken",
+            "name": "OAuth Bearer Token",
+            "description": "The organization's SCIM token in the Authorization header"
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operations(value: Value) -> Vec<PatchOperation> {
+        serde_json::from_value::<PatchRequest>(json!({ "Operations": value })).unwrap().operations
+    }
+
+    #[test]
+    fn test_parse_eq_filter() {
+        assert_eq!(
+            parse_eq_filter("userName eq \"ada@example.com\"").unwrap(),
+            EqFilter { attribute: "username".to_string(), value: "ada@example.com".to_string() }
+        );
+        assert_eq!(parse_eq_filter("displayName EQ \"Support Team\"").unwrap().value, "Support Team");
+        assert!(parse_eq_filter("userName sw \"ada\"").is_err());
+        assert!(parse_eq_filter("userName eq ada").is_err());
+    }
+
+    #[test]
+    fn test_user_changes_with_and_without_path() {
+        // Azure AD style
+        let changes = user_changes(&operations(json!([
+            { "op": "Replace", "path": "active", "value": "False" },
+            { "op": "Replace", "path": "displayName", "value": "Ada L." }
+        ])))
+        .unwrap();
+        assert_eq!(changes.active, Some(false));
+        assert_eq!(changes.display_name.as_deref(), Some("Ada L."));
+
+        // Okta style
+        let changes = user_changes(&operations(json!([{ "op": "replace", "value": { "active": true } }]))).unwrap();
+        assert_eq!(changes, UserChanges { active: Some(true), ..Default::default() });
+
+        assert!(user_changes(&operations(json!([{ "op": "remove", "path": "active" }]))).is_err());
+    }
+
+    #[test]
+    fn test_group_changes() {
+        let id = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let changes = group_changes(&operations(json!([
+            { "op": "add", "path": "members", "value": [{ "value": id.to_string() }] },
+            { "op": "remove", "path": format!("members[value eq \"{}\"]", other) },
+            { "op": "replace", "value": { "displayName": "Support" } }
+        ])))
+        .unwrap();
+        assert_eq!(changes.add_members, vec![id]);
+        assert_eq!(changes.remove_members, vec![other]);
+        assert_eq!(changes.display_name.as_deref(), Some("Support"));
+        assert!(!changes.replace_members);
+
+        let changes = group_changes(&operations(json!([{ "op": "remove", "path": "members" }]))).unwrap();
+        assert!(changes.replace_members && changes.add_members.is_empty());
+
+        assert!(group_changes(&operations(json!([
+            { "op": "add", "path": "members", "value": [{ "value": "not-a-uuid" }] }
+        ])))
+        .is_err());
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!(page(None, None), (0, DEFAULT_PAGE_SIZE));
+        assert_eq!(page(Some(11), Some(10)), (10, 10));
+        assert_eq!(page(Some(0), Some(10_000)), (0, MAX_PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_user_request_display_name_falls_back_to_name() {
+        let request: ScimUserRequest = serde_json::from_value(json!({
+            "userName": "ada@example.com",
+            "name": { "givenName": "Ada", "familyName": "Lovelace" }
+        }))
+        .unwrap();
+        assert_eq!(request.display_name().as_deref(), Some("Ada Lovelace"));
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use rhai::{Dynamic, Engine, Scope};
+use std::sync::LazyLock;
+use tracing;
+
+const DEFAULT_MAX_OPERATIONS: u64 = 100_000;
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+
+// One sandboxed engine shared by every request. Rhai has no file or network access, and the
+// operation limit stops runaway loops
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let max_operations = std::env::var("SCRIPT_MAX_OPERATIONS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_OPERATIONS);
+
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.on_print(|text| tracing::info!("Script: {}", text));
+    engine.on_debug(|text, _, _| tracing::debug!("Script: {}", text));
+    engine
+});
+
+/// Check that a hook script compiles
+pub fn validate_script(script: &str) -> Result<(), String> {
+    ENGINE
+        .compile(script)
+        .map(|_| ())
+        .map_err(|e| format!("Script does not compile: {}", e))
+}
+
+// Run a script with the given string variables. A string result replaces `original`; `()` keeps it
+fn run_hook(script: &str, variables: &[(&str, &str)], original: &str) -> Result<String> {
+    let mut scope = Scope::new();
+    for (name, value) in variables {
+        scope.push(*name, value.to_string());
+    }
+
+    let result: Dynamic = ENGINE
+        .eval_with_scope(&mut scope, script)
+        .map_err(|e| anyhow::anyhow!("Script failed: {}", e))?;
+
+    if result.is_unit() {
+        return Ok(original.to_string());
+    }
+    result
+        .into_string()
+        .map_err(|type_name| anyhow::anyhow!("Script must return a string, got {}", type_name))
+}
+
+/// Rewrite the query used for retrieval; the script sees `query`
+pub fn run_query_hook(script: &str, query: &str) -> Result<String> {
+    run_hook(script, &[("query", query)], query)
+}
+
+/// Rewrite the answer before it is stored and returned; the script sees `query` and `answer`
+pub fn run_answer_hook(script: &str, query: &str, answer: &str) -> Result<String> {
+    run_hook(script, &[("query", query), ("answer", answer)], answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_hook() {
+        let script = r#"if query.contains("SKU") { query + " product catalog" } else { query }"#;
+        assert_eq!(run_query_hook(script, "price of SKU 42").unwrap(), "price of SKU 42 product catalog");
+        assert_eq!(run_query_hook(script, "opening hours").unwrap(), "opening hours");
+    }
+
+    #[test]
+    fn test_answer_hook() {
+        let script = r#"answer.replace("Acme Corp", "Acme"); answer"#;
+        assert_eq!(run_answer_hook(script, "q", "Ask Acme Corp.").unwrap(), "Ask Acme.");
+
+        // A script that returns nothing leaves the answer alone
+        assert_eq!(run_answer_hook("let x = 1;", "q", "unchanged").unwrap(), "unchanged");
+    }
+
+    #[test]
+    fn test_hook_errors() {
+        assert!(run_query_hook("42", "q").is_err());
+        assert!(run_query_hook("loop { }", "q").is_err());
+        assert!(validate_script("if query {").is_err());
+        assert!(validate_script("query.to_upper()").is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::services::vector::{DocumentWithEmbedding, SearchResult};
+
+/// Sections of each document that matched chunks were cut from, in the order the documents first
+/// matched. Chunks of documents not chunked small-to-big have no section and are left out
+pub fn matched_sections(results: &[SearchResult]) -> Vec<(String, Vec<i64>)> {
+    let mut documents: Vec<(String, Vec<i64>)> = Vec::new();
+    for result in results {
+        let Some(parent_index) = result.parent_index else {
+            continue;
+        };
+        match documents.iter_mut().find(|(file_path, _)| *file_path == result.file_path) {
+            Some((_, sections)) if sections.contains(&parent_index) => {}
+            Some((_, sections)) => sections.push(parent_index),
+            None => documents.push((result.file_path.clone(), vec![parent_index])),
+        }
+    }
+    documents
+}
+
+/// Each section's text, rebuilt from its chunks in document order. Keyed by file path and section
+pub fn section_texts(chunks: &[DocumentWithEmbedding]) -> HashMap<(String, i64), String> {
+    let mut ordered: Vec<&DocumentWithEmbedding> = chunks.iter().collect();
+    ordered.sort_by_key(|chunk| chunk.chunk_index);
+
+    let mut sections: HashMap<(String, i64), String> = HashMap::new();
+    for chunk in ordered {
+        let Some(parent_index) = chunk.parent_index else {
+            continue;
+        };
+        let text = sections.entry((chunk.file_path.clone(), parent_index)).or_default();
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&chunk.text);
+    }
+    sections
+}
+
+/// Swap each matched chunk for the section it was cut from. A section matched by several chunks
+/// appears once, where its best chunk ranked; chunks whose section wasn't found are kept as they are
+pub fn replace_with_sections(
+    results: Vec<SearchResult>,
+    sections: &HashMap<(String, i64), String>,
+) -> Vec<SearchResult> {
+    let mut seen: Vec<(String, i64)> = Vec::new();
+    let mut replaced = Vec::with_capacity(results.len());
+    for mut result in results {
+        if let Some(parent_index) = result.parent_index {
+            let key = (result.file_path.clone(), parent_index);
+            if let Some(section) = sections.get(&key) {
+                if seen.contains(&key) {
+                    continue;
+                }
+                result.text = section.clone();
+                seen.push(key);
+            }
+        }
+        replaced.push(result);
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(file_path: &str, chunk_index: i64, parent_index: Option<i64>) -> SearchResult {
+        SearchResult {
+            text: format!("chunk {}", chunk_index),
+            score: 0.5,
+            chunk_index,
+            file_path: file_path.to_string(),
+            parent_index,
+        }
+    }
+
+    fn stored(file_path: &str, chunk_index: i64, parent_index: i64, text: &str) -> DocumentWithEmbedding {
+        DocumentWithEmbedding {
+            id: format!("{}-{}", file_path, chunk_index),
+            text: text.to_string(),
+            embedding: Vec::new(),
+            chunk_index,
+            file_path: file_path.to_string(),
+            chunk_count: 4,
+            parent_index: Some(parent_index),
+        }
+    }
+
+    #[test]
+    fn test_matched_sections_per_document() {
+        let results = [
+            hit("a.pdf", 3, Some(1)),
+            hit("b.pdf", 0, None),
+            hit("a.pdf", 2, Some(1)),
+            hit("a.pdf", 0, Some(0)),
+        ];
+        assert_eq!(matched_sections(&results), vec![("a.pdf".to_string(), vec![1, 0])]);
+    }
+
+    #[test]
+    fn test_section_texts_follow_document_order() {
+        let chunks = [
+            stored("a.pdf", 3, 1, "five days."),
+            stored("a.pdf", 2, 1, "Refunds take"),
+            stored("a.pdf", 0, 0, "Intro"),
+        ];
+        let sections = section_texts(&chunks);
+
+        assert_eq!(sections[&("a.pdf".to_string(), 1)], "Refunds take five days.");
+        assert_eq!(sections[&("a.pdf".to_string(), 0)], "Intro");
+    }
+
+    #[test]
+    fn test_replace_with_sections_collapses_chunks_of_one_section() {
+        let sections = HashMap::from([(("a.pdf".to_string(), 1), "whole section".to_string())]);
+        let results = vec![
+            hit("a.pdf", 3, Some(1)),
+            hit("b.pdf", 0, None),
+            hit("a.pdf", 2, Some(1)),
+            hit("a.pdf", 9, Some(4)),
+        ];
+        let texts: Vec<String> = replace_with_sections(results, &sections).into_iter().map(|r| r.text).collect();
+
+        assert_eq!(texts, vec!["whole section", "chunk 0", "chunk 9"]);
+    }
+}
@@ -0,0 +1,302 @@
+use sqlx::PgPool;
+use std::sync::{Arc, LazyLock};
+use uuid::Uuid;
+
+use crate::db::queries::set_conversation_labels;
+use crate::services::gemini::GeminiService;
+use crate::services::shutdown::BackgroundJobs;
+
+static CLASSIFIER: LazyLock<SentimentClassifier> = LazyLock::new(SentimentClassifier::from_env);
+
+const POSITIVE: &[&str] = &[
+    "thanks", "thank you", "thx", "great", "perfect", "awesome", "helpful", "love", "excellent",
+    "amazing", "nice", "appreciate", "good", "brilliant", "works now",
+];
+const NEGATIVE: &[&str] = &[
+    "bad", "terrible", "awful", "horrible", "worst", "hate", "annoying", "disappointed", "useless",
+    "stupid", "ridiculous", "waste", "unhelpful", "not helpful", "pointless", "rubbish",
+];
+const COMPLAINT: &[&str] = &[
+    "not working", "doesn't work", "does not work", "didn't work", "isn't working", "broken", "wrong",
+    "error", "problem", "issue", "refund", "failed", "fails", "bug", "can't", "cannot", "won't",
+];
+const ESCALATION: &[&str] = &[
+    "human", "real person", "agent", "representative", "operator", "speak to someone",
+    "talk to someone", "manager", "customer service", "support team",
+];
+const FRUSTRATION: &[&str] = &[
+    "again", "still", "already told", "already said", "for the last time", "seriously", "wtf",
+    "fed up", "give up", "waste of time", "not what i asked", "you don't understand", "makes no sense",
+];
+const QUESTION_WORDS: &[&str] = &[
+    "what", "how", "why", "when", "where", "who", "which", "can", "could", "do", "does", "is", "are",
+    "will", "should", "would",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sentiment {
+    Positive,
+    Neutral,
+    Negative,
+    /// Negative and heated: shouting, repeating themselves or asking for a human
+    Frustrated,
+}
+
+impl Sentiment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sentiment::Positive => "positive",
+            Sentiment::Neutral => "neutral",
+            Sentiment::Negative => "negative",
+            Sentiment::Frustrated => "frustrated",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "positive" => Some(Sentiment::Positive),
+            "neutral" => Some(Sentiment::Neutral),
+            "negative" => Some(Sentiment::Negative),
+            "frustrated" => Some(Sentiment::Frustrated),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    Question,
+    Complaint,
+    Praise,
+    /// Asking for a person instead of the bot
+    Escalation,
+    Other,
+}
+
+impl Intent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Intent::Question => "question",
+            Intent::Complaint => "complaint",
+            Intent::Praise => "praise",
+            Intent::Escalation => "escalation",
+            Intent::Other => "other",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "question" => Some(Intent::Question),
+            "complaint" => Some(Intent::Complaint),
+            "praise" => Some(Intent::Praise),
+            "escalation" => Some(Intent::Escalation),
+            "other" => Some(Intent::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLabels {
+    pub sentiment: Sentiment,
+    pub intent: Intent,
+}
+
+/// How user messages are labelled, chosen with `SENTIMENT_CLASSIFIER`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SentimentClassifier {
+    /// Keyword and punctuation rules, run locally (`lexicon`, the default)
+    Lexicon,
+    /// Ask Gemini, falling back to the lexicon when it fails (`llm`)
+    Llm,
+    /// Don't label messages (`off`)
+    Off,
+}
+
+impl SentimentClassifier {
+    pub fn from_env() -> Self {
+        match std::env::var("SENTIMENT_CLASSIFIER").map(|v| v.to_lowercase()).as_deref() {
+            Ok("llm") => SentimentClassifier::Llm,
+            Ok("off") | Ok("false") => SentimentClassifier::Off,
+            Ok("lexicon") | Err(_) => SentimentClassifier::Lexicon,
+            Ok(other) => {
+                tracing::warn!("⚠️ Unknown SENTIMENT_CLASSIFIER '{}', using the lexicon", other);
+                SentimentClassifier::Lexicon
+            }
+        }
+    }
+}
+
+// Lowercase words separated by single spaces, padded so phrases can be matched on word boundaries
+fn normalize(text: &str) -> String {
+    let words: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            '\u{2019}' => '\'',
+            c if c.is_alphanumeric() || c == '\'' => c,
+            _ => ' ',
+        })
+        .collect();
+    format!(" {} ", words.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn hits(normalized: &str, phrases: &[&str]) -> usize {
+    phrases
+        .iter()
+        .filter(|phrase| normalized.contains(&format!(" {} ", phrase)))
+        .count()
+}
+
+// Repeated `!`/`?`, or mostly capital letters
+fn is_shouting(text: &str) -> bool {
+    let repeated_marks = text
+        .chars()
+        .zip(text.chars().skip(1))
+        .any(|(a, b)| matches!(a, '!' | '?') && matches!(b, '!' | '?'));
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    let capitals = letters.iter().filter(|c| c.is_uppercase()).count();
+    repeated_marks || (letters.len() >= 8 && capitals * 4 >= letters.len() * 3)
+}
+
+/// Label a message with keyword and punctuation rules
+pub fn classify_lexicon(text: &str) -> MessageLabels {
+    let normalized = normalize(text);
+    let positive = hits(&normalized, POSITIVE);
+    let negative = hits(&normalized, NEGATIVE) + hits(&normalized, COMPLAINT);
+    let escalation = hits(&normalized, ESCALATION) > 0;
+    let heated = is_shouting(text) || escalation || hits(&normalized, FRUSTRATION) > 0;
+
+    let sentiment = if negative > 0 && heated {
+        Sentiment::Frustrated
+    } else if negative > positive {
+        Sentiment::Negative
+    } else if positive > 0 {
+        Sentiment::Positive
+    } else {
+        Sentiment::Neutral
+    };
+
+    let first_word = normalized.split_whitespace().next().unwrap_or("");
+    let intent = if escalation {
+        Intent::Escalation
+    } else if hits(&normalized, COMPLAINT) > 0 || matches!(sentiment, Sentiment::Negative | Sentiment::Frustrated) {
+        Intent::Complaint
+    } else if text.contains('?') || QUESTION_WORDS.contains(&first_word) {
+        Intent::Question
+    } else if sentiment == Sentiment::Positive {
+        Intent::Praise
+    } else {
+        Intent::Other
+    };
+
+    MessageLabels { sentiment, intent }
+}
+
+/// Parse the model's `sentiment,intent` reply
+pub fn parse_llm_labels(reply: &str) -> Option<MessageLabels> {
+    let reply = reply.trim().trim_matches(|c: char| c == '`' || c == '.').to_lowercase();
+    let (sentiment, intent) = reply.split_once(',')?;
+    Some(MessageLabels {
+        sentiment: Sentiment::parse(sentiment.trim())?,
+        intent: Intent::parse(intent.trim())?,
+    })
+}
+
+async fn classify(classifier: SentimentClassifier, text: &str) -> Option<MessageLabels> {
+    match classifier {
+        SentimentClassifier::Off => None,
+        SentimentClassifier::Lexicon => Some(classify_lexicon(text)),
+        SentimentClassifier::Llm => {
+            let reply = match GeminiService::new() {
+                Ok(gemini) => gemini.classify_message(text).await,
+                Err(e) => Err(e),
+            };
+            match reply.as_deref().map(parse_llm_labels) {
+                Ok(Some(labels)) => Some(labels),
+                Ok(None) => {
+                    tracing::warn!("⚠️ Unexpected sentiment reply, using the lexicon instead");
+                    Some(classify_lexicon(text))
+                }
+                Err(e) => {
+                    tracing::warn!("⚠️ Failed to classify message with Gemini, using the lexicon instead: {}", e);
+                    Some(classify_lexicon(text))
+                }
+            }
+        }
+    }
+}
+
+/// Label a conversation turn's user message, in the background
+pub fn tag_conversation(jobs: &BackgroundJobs, db: Arc<PgPool>, conversation_id: Uuid, user_query: String) {
+    let classifier = *CLASSIFIER;
+    if classifier == SentimentClassifier::Off {
+        return;
+    }
+
+    jobs.spawn(async move {
+        let Some(labels) = classify(classifier, &user_query).await else {
+            return;
+        };
+        if let Err(e) =
+            set_conversation_labels(&db, conversation_id, labels.sentiment.as_str(), labels.intent.as_str()).await
+        {
+            tracing::warn!("⚠️ Failed to record conversation sentiment: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(text: &str) -> (Sentiment, Intent) {
+        let labels = classify_lexicon(text);
+        (labels.sentiment, labels.intent)
+    }
+
+    #[test]
+    fn test_neutral_question() {
+        assert_eq!(labels("How do I reset my password?"), (Sentiment::Neutral, Intent::Question));
+        assert_eq!(labels("what are your opening hours"), (Sentiment::Neutral, Intent::Question));
+    }
+
+    #[test]
+    fn test_praise() {
+        assert_eq!(labels("Thank you, that was really helpful"), (Sentiment::Positive, Intent::Praise));
+        assert_eq!(labels("Thanks! How do I export it?"), (Sentiment::Positive, Intent::Question));
+    }
+
+    #[test]
+    fn test_complaint_and_frustration() {
+        assert_eq!(labels("The export button is broken"), (Sentiment::Negative, Intent::Complaint));
+        assert_eq!(labels("It's STILL not working!!"), (Sentiment::Frustrated, Intent::Complaint));
+        assert_eq!(labels("THIS IS USELESS"), (Sentiment::Frustrated, Intent::Complaint));
+        assert_eq!(
+            labels("This is useless, let me talk to a real person"),
+            (Sentiment::Frustrated, Intent::Escalation)
+        );
+    }
+
+    #[test]
+    fn test_escalation_without_complaint() {
+        assert_eq!(labels("Can I speak to someone please"), (Sentiment::Neutral, Intent::Escalation));
+    }
+
+    #[test]
+    fn test_matches_whole_words_only() {
+        // "bug" in "debugging" and "good" in "goodbye" are not hits
+        assert_eq!(labels("I am debugging, goodbye"), (Sentiment::Neutral, Intent::Other));
+        assert_eq!(labels("It doesn\u{2019}t work"), (Sentiment::Negative, Intent::Complaint));
+    }
+
+    #[test]
+    fn test_parse_llm_labels() {
+        assert_eq!(
+            parse_llm_labels(" Frustrated, Escalation.\n"),
+            Some(MessageLabels { sentiment: Sentiment::Frustrated, intent: Intent::Escalation })
+        );
+        assert_eq!(parse_llm_labels("angry,complaint"), None);
+        assert_eq!(parse_llm_labels("neutral"), None);
+    }
+}
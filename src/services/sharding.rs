@@ -0,0 +1,69 @@
+const DEFAULT_MAX_CHUNKS_PER_SHARD: u64 = 500_000;
+
+/// Maximum number of chunks a single chatbot index should hold before a new
+/// shard is added, from `INDEX_SHARD_MAX_CHUNKS`
+pub fn max_chunks_per_shard() -> u64 {
+    std::env::var("INDEX_SHARD_MAX_CHUNKS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &u64| *v > 0)
+        .unwrap_or(DEFAULT_MAX_CHUNKS_PER_SHARD)
+}
+
+/// Name of a shard index. Shard 0 is the base index so unsharded chatbots keep
+/// their existing index.
+pub fn shard_index_name(base_index: &str, shard: u32) -> String {
+    if shard == 0 {
+        base_index.to_string()
+    } else {
+        format!("{}_shard_{}", base_index, shard)
+    }
+}
+
+/// All indices a search for this chatbot has to fan out to
+pub fn shard_indices(base_index: &str, shard_count: i32) -> Vec<String> {
+    (0..shard_count.max(1) as u32)
+        .map(|shard| shard_index_name(base_index, shard))
+        .collect()
+}
+
+/// Shard a document seen for the first time goes to. Shards fill one after another, so only the
+/// newest one still has room; documents already indexed stay in the shard recorded for them
+pub fn shard_for_new_document(shard_count: i32) -> u32 {
+    (shard_count.max(1) - 1) as u32
+}
+
+/// Shard count needed to keep every shard under the chunk threshold. Never
+/// shrinks, since searches already fan out over existing shards.
+pub fn required_shard_count(total_chunks: u64, max_chunks_per_shard: u64, current: i32) -> i32 {
+    let needed = total_chunks.div_ceil(max_chunks_per_shard.max(1)).max(1);
+    (needed.min(i32::MAX as u64) as i32).max(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_zero_is_base_index() {
+        assert_eq!(shard_index_name("org_a_chatbot_b", 0), "org_a_chatbot_b");
+        assert_eq!(shard_index_name("org_a_chatbot_b", 2), "org_a_chatbot_b_shard_2");
+        assert_eq!(shard_indices("idx", 3), vec!["idx", "idx_shard_1", "idx_shard_2"]);
+        assert_eq!(shard_indices("idx", 0), vec!["idx"]);
+    }
+
+    #[test]
+    fn test_new_documents_go_to_newest_shard() {
+        assert_eq!(shard_for_new_document(4), 3);
+        assert_eq!(shard_for_new_document(1), 0);
+        assert_eq!(shard_for_new_document(0), 0);
+    }
+
+    #[test]
+    fn test_required_shard_count() {
+        assert_eq!(required_shard_count(0, 100, 1), 1);
+        assert_eq!(required_shard_count(100, 100, 1), 1);
+        assert_eq!(required_shard_count(101, 100, 1), 2);
+        assert_eq!(required_shard_count(50, 100, 3), 3);
+    }
+}
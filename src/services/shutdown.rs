@@ -0,0 +1,102 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{self, Instrument};
+
+/// Background work that shutdown stops and waits for: periodic jobs exit between runs,
+/// one-off writes are allowed to finish
+#[derive(Clone, Default)]
+pub struct BackgroundJobs {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl BackgroundJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        // Keep the spawning request's span so its request_id shows up on the job's logs
+        self.tracker.spawn(future.in_current_span());
+    }
+
+    /// Resolves once shutdown has started
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Signal every job to stop and wait up to `timeout` for them. Returns false if some were still running
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.token.cancel();
+        self.tracker.close();
+        tokio::time::timeout(timeout, self.tracker.wait()).await.is_ok()
+    }
+}
+
+// Resolve on Ctrl+C or, on Unix, SIGTERM (what Kubernetes sends before killing a pod)
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("❌ Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl+C, shutting down..."),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down..."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_drain_stops_periodic_jobs_and_waits_for_writes() {
+        let jobs = BackgroundJobs::new();
+        let written = Arc::new(AtomicBool::new(false));
+
+        let periodic = jobs.clone();
+        jobs.spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(3600)) => {}
+                    _ = periodic.cancelled() => break,
+                }
+            }
+        });
+
+        let flag = written.clone();
+        jobs.spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        assert!(jobs.drain(Duration::from_secs(5)).await);
+        assert!(written.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,426 @@
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::{Connection, PgConnection};
+use std::collections::HashSet;
+
+use crate::db::models::SqlTool;
+use crate::services::gemini::GeminiService;
+use crate::services::sql_connector::{check_database_url, validate_query};
+
+/// Rows of a generated query that reach the prompt
+pub const MAX_RESULT_ROWS: usize = 50;
+const QUERY_TIMEOUT_SECS: u64 = 10;
+// Functions whose argument is a source, not a table, e.g. EXTRACT(YEAR FROM created_at)
+const FROM_FUNCTIONS: [&str; 5] = ["extract", "substring", "trim", "overlay", "position"];
+// Functions a generated query may call. Anything else, such as query_to_xml('select ...'), could
+// read tables the whitelist doesn't see
+const ALLOWED_FUNCTIONS: [&str; 52] = [
+    "count", "sum", "avg", "min", "max", "string_agg", "array_agg", "bool_and", "bool_or", "coalesce",
+    "nullif", "greatest", "least", "abs", "round", "ceil", "floor", "trunc", "mod", "lower", "upper",
+    "initcap", "length", "char_length", "substring", "substr", "trim", "concat", "concat_ws", "replace",
+    "left", "right", "split_part", "position", "overlay", "extract", "date_trunc", "date_part", "to_char",
+    "to_date", "age", "now", "cast", "row_number", "rank", "dense_rank", "lag", "lead", "percentile_cont",
+    "numeric", "decimal", "varchar",
+];
+// Keywords that may be followed by a parenthesis without being a function call
+const PARENTHESIZED_KEYWORDS: [&str; 30] = [
+    "select", "from", "join", "where", "and", "or", "not", "in", "exists", "any", "all", "as", "on", "using",
+    "over", "filter", "group", "by", "when", "then", "else", "between", "lateral", "materialized", "union",
+    "intersect", "except", "having", "values", "distinct",
+];
+// Words that can follow a table reference, so they're never taken as its alias
+const CLAUSE_KEYWORDS: [&str; 22] = [
+    "where", "join", "inner", "left", "right", "full", "cross", "natural", "on", "using", "group", "order",
+    "having", "limit", "offset", "union", "intersect", "except", "window", "fetch", "for", "lateral",
+];
+
+/// Whitelisted tables are plain or schema-qualified lowercase identifiers, e.g. `orders` or
+/// `sales.orders`
+pub fn is_valid_table_name(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('.').collect();
+    parts.len() <= 2
+        && parts.iter().all(|part| {
+            part.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+                && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+}
+
+// Unqualified names live in `public`
+fn qualified(name: &str) -> String {
+    if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("public.{}", name)
+    }
+}
+
+// Split a query into lowercase identifiers (dotted names kept whole, quotes dropped) and single
+// punctuation characters. String literals become one `'` token so their contents are never read
+// as SQL
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '\'' {
+            while let Some(next) = chars.next() {
+                if next == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            tokens.push("'".to_string());
+        } else if c.is_alphanumeric() || c == '_' || c == '"' {
+            let mut token = String::new();
+            let mut current = Some(c);
+            while let Some(c) = current {
+                if c == '"' {
+                    // Quoted identifier, possibly part of a dotted name
+                    chars.by_ref().take_while(|c| *c != '"').for_each(|c| token.push(c));
+                } else {
+                    token.push(c);
+                }
+                current = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '"' || *c == '.');
+            }
+            tokens.push(token.to_lowercase());
+        } else {
+            tokens.push(c.to_string());
+        }
+    }
+    tokens
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+/// Tables a query reads: the names after `FROM` and `JOIN`, including comma-separated lists, less
+/// the names of its own CTEs
+pub fn referenced_tables(query: &str) -> Vec<String> {
+    let tokens = tokenize(query);
+    let ctes: HashSet<&str> = tokens
+        .windows(3)
+        .filter(|w| is_identifier(&w[0]) && w[1] == "as" && (w[2] == "(" || w[2] == "materialized" || w[2] == "not"))
+        .map(|w| w[0].as_str())
+        .collect();
+
+    let mut tables = Vec::new();
+    // Token before each open parenthesis, to tell EXTRACT(... FROM ...) from a FROM clause
+    let mut openers: Vec<&str> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.as_str() {
+            "(" => openers.push(if i > 0 { tokens[i - 1].as_str() } else { "" }),
+            ")" => {
+                openers.pop();
+            }
+            "from" | "join" => {
+                let in_function = openers.last().is_some_and(|opener| FROM_FUNCTIONS.contains(opener));
+                let distinct_from = i > 0 && tokens[i - 1] == "distinct";
+                if in_function || distinct_from {
+                    continue;
+                }
+
+                let mut j = i + 1;
+                while let Some(name) = tokens.get(j).filter(|t| is_identifier(t)) {
+                    if name != "lateral" && !ctes.contains(name.as_str()) {
+                        tables.push(name.clone());
+                    }
+                    j += 1;
+                    // Skip the alias, with or without AS
+                    if tokens.get(j).is_some_and(|t| t == "as") {
+                        j += 2;
+                    } else if tokens.get(j).is_some_and(|t| is_identifier(t) && !CLAUSE_KEYWORDS.contains(&t.as_str())) {
+                        j += 1;
+                    }
+                    if tokens.get(j).is_some_and(|t| t == ",") {
+                        j += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tables
+}
+
+/// A generated query may only be one `SELECT` reading whitelisted tables, without comments, system
+/// catalogs or functions outside a short list
+pub fn check_query(query: &str, allowed_tables: &[String]) -> Result<(), String> {
+    validate_query(query)?;
+    if query.contains("--") || query.contains("/*") {
+        return Err("Query must not contain comments".to_string());
+    }
+
+    let tokens = tokenize(query);
+    if let Some(token) = tokens.iter().find(|token| {
+        token.starts_with("pg_")
+            || token.contains(".pg_")
+            || token.starts_with("information_schema")
+            || token.starts_with("dblink")
+            || token.as_str() == "set_config"
+    }) {
+        return Err(format!("Query must not use '{}'", token));
+    }
+
+    // TABLE x is a SELECT * FROM x that the FROM and JOIN scan doesn't see
+    if tokens.iter().any(|token| token == "table") {
+        return Err("Query must not use 'table'".to_string());
+    }
+    for (token, next) in tokens.iter().zip(tokens.iter().skip(1)) {
+        if next == "("
+            && is_identifier(token)
+            && !PARENTHESIZED_KEYWORDS.contains(&token.as_str())
+            && !ALLOWED_FUNCTIONS.contains(&token.as_str())
+        {
+            return Err(format!("Function '{}' is not allowed", token));
+        }
+    }
+
+    let allowed: HashSet<String> = allowed_tables.iter().map(|table| qualified(table)).collect();
+    for table in referenced_tables(query) {
+        if !allowed.contains(&qualified(&table)) {
+            return Err(format!("Table '{}' is not whitelisted", table));
+        }
+    }
+    Ok(())
+}
+
+/// Pull the query out of the model's reply: `None` when it answered NONE, code fences removed
+pub fn parse_generated_sql(response: &str) -> Option<String> {
+    let text = response.trim();
+    let text = text
+        .strip_prefix("```sql")
+        .or_else(|| text.strip_prefix("```"))
+        .map(|inner| inner.trim_end().trim_end_matches("```"))
+        .unwrap_or(text)
+        .trim();
+    if text.is_empty() || text.eq_ignore_ascii_case("none") {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+// The query check is a lexer, so the database user is the real boundary: it must not be a
+// superuser or be able to read any table outside the whitelist
+async fn check_query_role(connection: &mut PgConnection, qualified_names: &[String]) -> Result<()> {
+    let superuser = sqlx::query_scalar::<_, bool>("SELECT rolsuper FROM pg_roles WHERE rolname = current_user")
+        .fetch_one(&mut *connection)
+        .await?;
+    if superuser {
+        return Err(anyhow::anyhow!("The database user is a superuser"));
+    }
+
+    let readable = sqlx::query_scalar::<_, String>(
+        "SELECT n.nspname || '.' || c.relname
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+         WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f')
+           AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+           AND NOT (n.nspname || '.' || c.relname = ANY($1))
+           AND has_table_privilege(c.oid, 'SELECT')
+         ORDER BY 1
+         LIMIT 5",
+    )
+    .bind(qualified_names)
+    .fetch_all(&mut *connection)
+    .await?;
+    if !readable.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The database user can read tables that aren't whitelisted: {}",
+            readable.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Describe the whitelisted tables' columns for the model, one `table (column type, ...)` line per
+/// table. Fails when a whitelisted table doesn't exist or can't be read, or when the database user
+/// can read more than the whitelisted tables
+pub async fn describe_schema(database_url: &str, allowed_tables: &[String]) -> Result<String> {
+    let qualified_names: Vec<String> = allowed_tables.iter().map(|table| qualified(table)).collect();
+
+    check_database_url(database_url).await.map_err(|e| anyhow::anyhow!(e))?;
+    let mut connection = PgConnection::connect(database_url).await?;
+    check_query_role(&mut connection, &qualified_names).await?;
+    let columns = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT table_schema || '.' || table_name, column_name::text, data_type::text
+         FROM information_schema.columns
+         WHERE table_schema || '.' || table_name = ANY($1)
+         ORDER BY table_schema, table_name, ordinal_position",
+    )
+    .bind(&qualified_names)
+    .fetch_all(&mut connection)
+    .await?;
+
+    let mut lines = Vec::new();
+    for (table, name) in allowed_tables.iter().zip(&qualified_names) {
+        let table_columns: Vec<String> = columns
+            .iter()
+            .filter(|(column_table, _, _)| column_table == name)
+            .map(|(_, column, data_type)| format!("{} {}", column, data_type))
+            .collect();
+        if table_columns.is_empty() {
+            return Err(anyhow::anyhow!("Table '{}' doesn't exist or can't be read", table));
+        }
+        lines.push(format!("{} ({})", table, table_columns.join(", ")));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Rows a generated query returned
+#[derive(Debug, Clone)]
+pub struct SqlToolResult {
+    pub query: String,
+    pub rows: Vec<Value>,
+    /// More rows matched than were kept
+    pub truncated: bool,
+}
+
+impl SqlToolResult {
+    /// The query and its rows as a section of the prompt's documents
+    pub fn context(&self) -> String {
+        let mut lines = vec![
+            format!("Database query:\n{}", self.query),
+            format!(
+                "Result ({} rows{}):",
+                self.rows.len(),
+                if self.truncated { ", truncated" } else { "" }
+            ),
+        ];
+        lines.extend(self.rows.iter().map(|row| row.to_string()));
+        lines.join("\n")
+    }
+}
+
+// Run a checked query in a read-only transaction with a statement timeout
+async fn run_query(database_url: &str, query: &str) -> Result<SqlToolResult> {
+    let query = query.trim().trim_end_matches(';');
+
+    check_database_url(database_url).await.map_err(|e| anyhow::anyhow!(e))?;
+    let mut connection = PgConnection::connect(database_url).await?;
+    let mut tx = connection.begin().await?;
+    sqlx::query("SET TRANSACTION READ ONLY").execute(&mut *tx).await?;
+    sqlx::query(&format!("SET LOCAL statement_timeout = {}", QUERY_TIMEOUT_SECS * 1000))
+        .execute(&mut *tx)
+        .await?;
+    let mut rows = sqlx::query_scalar::<_, Value>(&format!(
+        "SELECT row_to_json(q) FROM ({}) AS q LIMIT {}",
+        query,
+        MAX_RESULT_ROWS + 1
+    ))
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.rollback().await?;
+
+    let truncated = rows.len() > MAX_RESULT_ROWS;
+    rows.truncate(MAX_RESULT_ROWS);
+    Ok(SqlToolResult { query: query.to_string(), rows, truncated })
+}
+
+/// Have the model write a query over the whitelisted tables for the question and run it. `None`
+/// when the model finds the tables can't answer it
+pub async fn answer_with_sql(gemini: &GeminiService, tool: &SqlTool, question: &str) -> Result<Option<SqlToolResult>> {
+    let schema = describe_schema(&tool.database_url, &tool.allowed_tables).await?;
+    let response = gemini.generate_sql(&schema, question).await?;
+    let Some(query) = parse_generated_sql(&response) else {
+        tracing::info!("SQL tool: the whitelisted tables can't answer the question");
+        return Ok(None);
+    };
+
+    check_query(&query, &tool.allowed_tables).map_err(|e| anyhow::anyhow!("Rejected generated SQL: {}", e))?;
+    let result = run_query(&tool.database_url, &query).await?;
+    tracing::info!("SQL tool returned {} rows", result.rows.len());
+    Ok(Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn allowed() -> Vec<String> {
+        vec!["orders".to_string(), "sales.customers".to_string()]
+    }
+
+    #[test]
+    fn test_table_names() {
+        assert!(is_valid_table_name("orders"));
+        assert!(is_valid_table_name("sales.customers_2024"));
+        assert!(!is_valid_table_name("Orders"));
+        assert!(!is_valid_table_name("a.b.c"));
+        assert!(!is_valid_table_name("orders; drop"));
+        assert!(!is_valid_table_name(""));
+    }
+
+    #[test]
+    fn test_referenced_tables() {
+        assert_eq!(
+            referenced_tables("SELECT c.name, sum(o.total) FROM orders o JOIN sales.customers AS c ON c.id = o.customer_id"),
+            vec!["orders", "sales.customers"]
+        );
+        assert_eq!(referenced_tables("SELECT * FROM orders a, \"Refunds\" r WHERE a.id = r.id"), vec!["orders", "refunds"]);
+        assert_eq!(
+            referenced_tables("WITH recent AS (SELECT * FROM orders) SELECT EXTRACT(MONTH FROM created_at) FROM recent"),
+            vec!["orders"]
+        );
+        assert_eq!(referenced_tables("SELECT 'from users' FROM orders"), vec!["orders"]);
+    }
+
+    #[test]
+    fn test_check_query() {
+        assert!(check_query("SELECT count(*) FROM orders;", &allowed()).is_ok());
+        assert!(check_query("SELECT * FROM public.orders", &allowed()).is_ok());
+        assert!(check_query("SELECT * FROM customers", &allowed()).is_err());
+        assert!(check_query("SELECT * FROM orders o JOIN users u ON u.id = o.user_id", &allowed()).is_err());
+        assert!(check_query("SELECT * FROM (SELECT * FROM users) u", &allowed()).is_err());
+        assert!(check_query("SELECT pg_read_file('/etc/passwd')", &allowed()).is_err());
+        assert!(check_query("SELECT * FROM information_schema.tables", &allowed()).is_err());
+        assert!(check_query("SELECT * FROM orders -- comment", &allowed()).is_err());
+        assert!(check_query("DELETE FROM orders", &allowed()).is_err());
+    }
+
+    #[test]
+    fn test_check_query_rejects_table_readers() {
+        assert!(check_query("SELECT query_to_xml('select * from users', true, false, '')", &allowed()).is_err());
+        assert!(check_query("SELECT table_to_xml('users', true, false, '')", &allowed()).is_err());
+        assert!(check_query("SELECT * FROM orders WHERE EXISTS (TABLE users)", &allowed()).is_err());
+        assert!(check_query("SELECT \"Query_To_Xml\"('select 1', true, false, '')", &allowed()).is_err());
+        assert!(check_query("SELECT pg_catalog.lower(status) FROM orders", &allowed()).is_err());
+
+        assert!(check_query(
+            "SELECT date_trunc('month', created_at), count(*) FILTER (WHERE total > 0) FROM orders \
+             WHERE status IN ('paid') GROUP BY 1",
+            &allowed()
+        )
+        .is_ok());
+        assert!(check_query(
+            "WITH recent AS (SELECT * FROM orders) SELECT rank() OVER (ORDER BY total) FROM recent",
+            &allowed()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_parse_generated_sql() {
+        assert_eq!(parse_generated_sql("```sql\nSELECT 1\n```"), Some("SELECT 1".to_string()));
+        assert_eq!(parse_generated_sql("  SELECT 1;  "), Some("SELECT 1;".to_string()));
+        assert_eq!(parse_generated_sql("NONE"), None);
+    }
+
+    #[test]
+    fn test_result_context() {
+        let result = SqlToolResult {
+            query: "SELECT count(*) FROM orders".to_string(),
+            rows: vec![json!({"count": 3})],
+            truncated: false,
+        };
+        assert_eq!(result.context(), "Database query:\nSELECT count(*) FROM orders\nResult (1 rows):\n{\"count\":3}");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::services::vector::SearchResult;
+
+// Words shared by the end of `previous` and the start of `next`, as chunking with overlap leaves them
+fn overlap_words(previous: &[&str], next: &[&str]) -> usize {
+    (1..=previous.len().min(next.len()))
+        .rev()
+        .find(|&n| previous[previous.len() - n..] == next[..n])
+        .unwrap_or(0)
+}
+
+fn follows(previous: &SearchResult, next: &SearchResult) -> bool {
+    next.file_path == previous.file_path && next.chunk_index == previous.chunk_index + 1
+}
+
+// Join a run of consecutive chunks of one document, dropping the words each repeats from the one before
+fn merge_run(run: &[&SearchResult]) -> SearchResult {
+    let mut words: Vec<&str> = run[0].text.split_whitespace().collect();
+    for chunk in &run[1..] {
+        let next: Vec<&str> = chunk.text.split_whitespace().collect();
+        let overlap = overlap_words(&words, &next);
+        words.extend_from_slice(&next[overlap..]);
+    }
+
+    SearchResult {
+        text: words.join(" "),
+        score: run.iter().map(|chunk| chunk.score).fold(f32::MIN, f32::max),
+        chunk_index: run[0].chunk_index,
+        file_path: run[0].file_path.clone(),
+        parent_index: run[0].parent_index,
+    }
+}
+
+/// A document's retrieved passages, in document order
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceDocument {
+    pub file_path: String,
+    pub passages: Vec<String>,
+}
+
+/// Merge retrieved chunks that are adjacent in the same document into one passage in document
+/// order, so the prompt doesn't repeat the words their overlap shares. A passage keeps the first
+/// chunk's index and the best score, and takes the place of its best-ranked chunk
+pub fn stitch_chunks(chunks: &[SearchResult]) -> Vec<SearchResult> {
+    let mut by_position: Vec<usize> = (0..chunks.len()).collect();
+    by_position.sort_by(|&a, &b| {
+        (&chunks[a].file_path, chunks[a].chunk_index).cmp(&(&chunks[b].file_path, chunks[b].chunk_index))
+    });
+
+    // Each passage with the rank of its best-ranked chunk
+    let mut passages: Vec<(usize, SearchResult)> = Vec::new();
+    let mut run: Vec<usize> = Vec::new();
+    for (i, &index) in by_position.iter().enumerate() {
+        run.push(index);
+        if by_position.get(i + 1).is_some_and(|&next| follows(&chunks[index], &chunks[next])) {
+            continue;
+        }
+        let rank = run.iter().copied().min().unwrap_or(index);
+        let passage = merge_run(&run.iter().map(|&i| &chunks[i]).collect::<Vec<_>>());
+        passages.push((rank, passage));
+        run.clear();
+    }
+
+    passages.sort_by_key(|(rank, _)| *rank);
+    passages.into_iter().map(|(_, passage)| passage).collect()
+}
+
+/// Stitch adjacent chunks, then group the passages by document. Documents keep the order of their
+/// best-ranked chunk; within a document, passages follow their position in it
+pub fn group_by_document(chunks: &[SearchResult]) -> Vec<SourceDocument> {
+    let mut documents: Vec<(String, Vec<SearchResult>)> = Vec::new();
+    for passage in stitch_chunks(chunks) {
+        match documents.iter_mut().find(|(file_path, _)| *file_path == passage.file_path) {
+            Some((_, passages)) => passages.push(passage),
+            None => documents.push((passage.file_path.clone(), vec![passage])),
+        }
+    }
+
+    documents
+        .into_iter()
+        .map(|(file_path, mut passages)| {
+            passages.sort_by_key(|passage| passage.chunk_index);
+            SourceDocument { file_path, passages: passages.into_iter().map(|passage| passage.text).collect() }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(file_path: &str, chunk_index: i64, score: f32, text: &str) -> SearchResult {
+        SearchResult {
+            text: text.to_string(),
+            score,
+            chunk_index,
+            file_path: file_path.to_string(),
+            parent_index: None,
+        }
+    }
+
+    #[test]
+    fn test_merges_adjacent_chunks_without_overlap_repeated() {
+        let chunks = [
+            chunk("a.pdf", 4, 0.6, "refunds take five days after approval"),
+            chunk("a.pdf", 3, 0.9, "submit the form online. refunds take five days"),
+        ];
+        let stitched = stitch_chunks(&chunks);
+
+        assert_eq!(stitched.len(), 1);
+        assert_eq!(stitched[0].text, "submit the form online. refunds take five days after approval");
+        assert_eq!(stitched[0].chunk_index, 3);
+        assert_eq!(stitched[0].score, 0.9);
+    }
+
+    #[test]
+    fn test_keeps_gaps_and_other_documents_apart() {
+        let chunks = [
+            chunk("a.pdf", 1, 0.9, "first"),
+            chunk("b.pdf", 2, 0.8, "other"),
+            chunk("a.pdf", 3, 0.7, "third"),
+        ];
+        let texts: Vec<String> = stitch_chunks(&chunks).into_iter().map(|chunk| chunk.text).collect();
+
+        assert_eq!(texts, vec!["first", "other", "third"]);
+    }
+
+    #[test]
+    fn test_passage_takes_the_place_of_its_best_chunk() {
+        let chunks = [
+            chunk("b.pdf", 0, 0.9, "best"),
+            chunk("a.pdf", 1, 0.8, "one two"),
+            chunk("c.pdf", 0, 0.7, "middle"),
+            chunk("a.pdf", 0, 0.6, "zero one"),
+        ];
+        let texts: Vec<String> = stitch_chunks(&chunks).into_iter().map(|chunk| chunk.text).collect();
+
+        assert_eq!(texts, vec!["best", "zero one two", "middle"]);
+    }
+
+    #[test]
+    fn test_groups_passages_by_document_in_document_order() {
+        let chunks = [
+            chunk("a.pdf", 7, 0.9, "late"),
+            chunk("b.pdf", 0, 0.8, "other"),
+            chunk("a.pdf", 2, 0.7, "early"),
+            chunk("a.pdf", 3, 0.6, "next"),
+        ];
+
+        assert_eq!(
+            group_by_document(&chunks),
+            vec![
+                SourceDocument { file_path: "a.pdf".to_string(), passages: vec!["early next".into(), "late".into()] },
+                SourceDocument { file_path: "b.pdf".to_string(), passages: vec!["other".into()] },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunks_without_overlap_are_joined() {
+        let stitched = stitch_chunks(&[chunk("a.pdf", 0, 0.5, "one two"), chunk("a.pdf", 1, 0.5, "three")]);
+
+        assert_eq!(stitched[0].text, "one two three");
+    }
+}
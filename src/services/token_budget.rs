@@ -0,0 +1,305 @@
+use std::fmt;
+use tokenizers::Tokenizer;
+
+use crate::services::stitching::SourceDocument;
+use crate::services::vector::SearchResult;
+
+const DEFAULT_CONTEXT_WINDOW_TOKENS: usize = 32_768;
+const DEFAULT_RESPONSE_RESERVE_TOKENS: usize = 1_024;
+// Rough average for English text when no tokenizer is configured
+const ESTIMATED_CHARS_PER_TOKEN: usize = 4;
+// Separator between history turns and between documents in the prompt
+const SEPARATOR: &str = "\n\n";
+
+/// Counts prompt tokens with the model's tokenizer, or estimates them from the text length
+pub enum TokenCounter {
+    Tokenizer(Box<Tokenizer>),
+    Estimate,
+}
+
+impl TokenCounter {
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            TokenCounter::Tokenizer(tokenizer) => match tokenizer.encode(text, false) {
+                Ok(encoding) => encoding.len(),
+                Err(e) => {
+                    tracing::warn!("⚠️ Failed to tokenize prompt text, estimating instead: {}", e);
+                    estimate(text)
+                }
+            },
+            TokenCounter::Estimate => estimate(text),
+        }
+    }
+}
+
+fn estimate(text: &str) -> usize {
+    text.chars().count().div_ceil(ESTIMATED_CHARS_PER_TOKEN)
+}
+
+/// Where a prompt's tokens went after budgeting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenBreakdown {
+    /// Template, glossary and question, which are never trimmed
+    pub fixed: usize,
+    pub history: usize,
+    pub documents: usize,
+    pub history_turns_dropped: usize,
+    pub chunks_dropped: usize,
+}
+
+impl TokenBreakdown {
+    pub fn total(&self) -> usize {
+        self.fixed + self.history + self.documents
+    }
+}
+
+/// History and chunks that fit the context window
+#[derive(Debug)]
+pub struct FittedContext {
+    /// Oldest first, like the input
+    pub history: Vec<String>,
+    /// In their original order
+    pub chunks: Vec<SearchResult>,
+    pub breakdown: TokenBreakdown,
+}
+
+/// A rendered prompt that would leave the model less room for its answer than the reserve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptTooLarge {
+    pub prompt_tokens: usize,
+    /// Context window less the response reserve
+    pub max_prompt_tokens: usize,
+    /// Template, glossary and question, which trimming history and documents can't shrink
+    pub fixed_tokens: usize,
+}
+
+impl PromptTooLarge {
+    /// Whether the prompt is too large even without history or documents
+    pub fn fixed_too_large(&self) -> bool {
+        self.fixed_tokens > self.max_prompt_tokens
+    }
+}
+
+impl fmt::Display for PromptTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "prompt needs {} tokens, more than the {} the context window leaves for it",
+            self.prompt_tokens, self.max_prompt_tokens
+        )
+    }
+}
+
+impl std::error::Error for PromptTooLarge {}
+
+/// Keeps prompts within the model's context window, leaving room for the answer
+pub struct TokenBudget {
+    counter: TokenCounter,
+    context_window: usize,
+    response_reserve: usize,
+}
+
+impl TokenBudget {
+    pub fn new(counter: TokenCounter, context_window: usize, response_reserve: usize) -> Self {
+        Self { counter, context_window, response_reserve }
+    }
+
+    /// Reads `PROMPT_TOKENIZER_FILE` (the `tokenizer.json` of the answering model),
+    /// `CONTEXT_WINDOW_TOKENS` and `RESPONSE_RESERVE_TOKENS`
+    pub fn from_env() -> anyhow::Result<Self> {
+        let counter = match std::env::var("PROMPT_TOKENIZER_FILE") {
+            Ok(path) => {
+                let tokenizer = Tokenizer::from_file(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to load PROMPT_TOKENIZER_FILE {}: {}", path, e))?;
+                tracing::info!("✅ Counting prompt tokens with {}", path);
+                TokenCounter::Tokenizer(Box::new(tokenizer))
+            }
+            Err(_) => {
+                tracing::info!("PROMPT_TOKENIZER_FILE not set, estimating prompt tokens from text length");
+                TokenCounter::Estimate
+            }
+        };
+        let context_window = env_tokens("CONTEXT_WINDOW_TOKENS", DEFAULT_CONTEXT_WINDOW_TOKENS)?;
+        let response_reserve = env_tokens("RESPONSE_RESERVE_TOKENS", DEFAULT_RESPONSE_RESERVE_TOKENS)?;
+        if response_reserve >= context_window {
+            anyhow::bail!("RESPONSE_RESERVE_TOKENS must be less than CONTEXT_WINDOW_TOKENS");
+        }
+
+        Ok(Self::new(counter, context_window, response_reserve))
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        self.counter.count(text)
+    }
+
+    /// Largest prompt that still leaves `RESPONSE_RESERVE_TOKENS` for the answer
+    pub fn max_prompt_tokens(&self) -> usize {
+        self.context_window.saturating_sub(self.response_reserve)
+    }
+
+    /// Size of the final prompt, or an error when it is too large to send to the model.
+    /// `fixed_tokens` is the size `fit` was given
+    pub fn check(&self, prompt: &str, fixed_tokens: usize) -> Result<usize, PromptTooLarge> {
+        let prompt_tokens = self.counter.count(prompt);
+        let max_prompt_tokens = self.max_prompt_tokens();
+        if prompt_tokens > max_prompt_tokens {
+            return Err(PromptTooLarge { prompt_tokens, max_prompt_tokens, fixed_tokens });
+        }
+        Ok(prompt_tokens)
+    }
+
+    /// Drop the oldest history turns, then the lowest-scoring chunks, until the prompt fits.
+    /// `fixed_tokens` is the size of the prompt rendered without history or documents
+    pub fn fit(&self, fixed_tokens: usize, history: Vec<String>, chunks: Vec<SearchResult>) -> FittedContext {
+        let available = self.context_window.saturating_sub(self.response_reserve + fixed_tokens);
+        let separator = self.counter.count(SEPARATOR);
+
+        let history_tokens: Vec<usize> = history.iter().map(|turn| self.counter.count(turn) + separator).collect();
+        let chunk_tokens: Vec<usize> = chunks.iter().map(|chunk| self.chunk_tokens(chunk) + separator).collect();
+
+        let mut used: usize = history_tokens.iter().sum::<usize>() + chunk_tokens.iter().sum::<usize>();
+        let mut history_dropped = 0;
+        while used > available && history_dropped < history.len() {
+            used -= history_tokens[history_dropped];
+            history_dropped += 1;
+        }
+
+        // Lowest score first; ties drop the later chunk
+        let mut by_score: Vec<usize> = (0..chunks.len()).collect();
+        by_score.sort_by(|&a, &b| chunks[a].score.total_cmp(&chunks[b].score).then(b.cmp(&a)));
+        let mut keep = vec![true; chunks.len()];
+        for index in by_score {
+            if used <= available {
+                break;
+            }
+            used -= chunk_tokens[index];
+            keep[index] = false;
+        }
+
+        let breakdown = TokenBreakdown {
+            fixed: fixed_tokens,
+            history: history_tokens[history_dropped..].iter().sum(),
+            documents: chunk_tokens.iter().zip(&keep).filter(|(_, kept)| **kept).map(|(tokens, _)| tokens).sum(),
+            history_turns_dropped: history_dropped,
+            chunks_dropped: keep.iter().filter(|kept| !**kept).count(),
+        };
+        if fixed_tokens > self.max_prompt_tokens() {
+            tracing::warn!(
+                "⚠️ Prompt without history or documents needs {} tokens, more than the {} available",
+                fixed_tokens,
+                self.max_prompt_tokens()
+            );
+        }
+
+        FittedContext {
+            history: history.into_iter().skip(history_dropped).collect(),
+            chunks: chunks.into_iter().zip(keep).filter(|(_, kept)| *kept).map(|(chunk, _)| chunk).collect(),
+            breakdown,
+        }
+    }
+
+    fn chunk_tokens(&self, chunk: &SearchResult) -> usize {
+        self.counter.count(&format_chunk(chunk))
+    }
+}
+
+fn env_tokens(name: &str, default: usize) -> anyhow::Result<usize> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|tokens| *tokens > 0)
+            .ok_or_else(|| anyhow::anyhow!("{} must be a positive number of tokens, got '{}'", name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// How a retrieved chunk appears in the prompt
+pub fn format_chunk(chunk: &SearchResult) -> String {
+    format!("Document: {}\nContent: {}", chunk.file_path, chunk.text)
+}
+
+/// How a document's passages appear in the prompt: under one header, with `[...]` standing for
+/// the text between them
+pub fn format_document(document: &SourceDocument) -> String {
+    format!("Document: {}\nContent: {}", document.file_path, document.passages.join("\n[...]\n"))
+}
+
+/// Join history turns or formatted chunks the way the prompt lays them out
+pub fn join_sections(sections: impl IntoIterator<Item = String>) -> String {
+    sections.into_iter().collect::<Vec<_>>().join(SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(name: &str, score: f32, chars: usize) -> SearchResult {
+        SearchResult {
+            text: "x".repeat(chars),
+            score,
+            chunk_index: 0,
+            file_path: name.to_string(),
+            parent_index: None,
+        }
+    }
+
+    fn turn(chars: usize) -> String {
+        "y".repeat(chars)
+    }
+
+    #[test]
+    fn test_everything_fits() {
+        let budget = TokenBudget::new(TokenCounter::Estimate, 1_000, 100);
+        let fitted = budget.fit(50, vec![turn(40), turn(40)], vec![chunk("a", 0.9, 40)]);
+
+        assert_eq!(fitted.history.len(), 2);
+        assert_eq!(fitted.chunks.len(), 1);
+        assert_eq!(fitted.breakdown.history_turns_dropped, 0);
+        assert_eq!(fitted.breakdown.chunks_dropped, 0);
+        assert_eq!(fitted.breakdown.total(), 50 + fitted.breakdown.history + fitted.breakdown.documents);
+    }
+
+    #[test]
+    fn test_drops_oldest_history_first() {
+        let budget = TokenBudget::new(TokenCounter::Estimate, 200, 50);
+        // 100 tokens available: each turn and chunk is ~40 tokens
+        let history = vec![turn(160), turn(156)];
+        let fitted = budget.fit(50, history.clone(), vec![chunk("a", 0.9, 140)]);
+
+        assert_eq!(fitted.history, vec![history[1].clone()]);
+        assert_eq!(fitted.chunks.len(), 1);
+        assert_eq!(fitted.breakdown.history_turns_dropped, 1);
+        assert!(fitted.breakdown.total() <= 150);
+    }
+
+    #[test]
+    fn test_drops_lowest_scoring_chunks_after_history() {
+        let budget = TokenBudget::new(TokenCounter::Estimate, 200, 50);
+        let chunks = vec![chunk("a", 0.5, 140), chunk("b", 0.9, 140), chunk("c", 0.2, 140)];
+        let fitted = budget.fit(50, vec![turn(160)], chunks);
+
+        let names: Vec<&str> = fitted.chunks.iter().map(|c| c.file_path.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert!(fitted.history.is_empty());
+        assert_eq!(fitted.breakdown.chunks_dropped, 1);
+    }
+
+    #[test]
+    fn test_check_prompt_size() {
+        let budget = TokenBudget::new(TokenCounter::Estimate, 200, 50);
+        assert_eq!(budget.max_prompt_tokens(), 150);
+        assert_eq!(budget.check(&turn(600), 10), Ok(150));
+
+        let error = budget.check(&turn(604), 10).unwrap_err();
+        assert_eq!(error, PromptTooLarge { prompt_tokens: 151, max_prompt_tokens: 150, fixed_tokens: 10 });
+        assert!(!error.fixed_too_large());
+        assert!(budget.check(&turn(800), 160).unwrap_err().fixed_too_large());
+    }
+
+    #[test]
+    fn test_estimate_rounds_up() {
+        assert_eq!(TokenCounter::Estimate.count(""), 0);
+        assert_eq!(TokenCounter::Estimate.count("abcde"), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{Duration, NaiveDate};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::db::models::{UsageDay, UsageEvent, UsageTotals};
+use crate::db::queries::record_usage_event;
+use crate::services::embedding::EmbeddingService;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::token_budget::TokenBudget;
+
+/// Days reported when the request doesn't give a start date
+pub const DEFAULT_USAGE_DAYS: i64 = 30;
+/// Longest range one usage request can cover
+pub const MAX_USAGE_DAYS: i64 = 366;
+
+/// Add the embeddings and searches a request's embedding service ran to its usage
+pub fn with_embedding_usage(event: UsageEvent, embedding_service: &EmbeddingService) -> UsageEvent {
+    let (embedding_calls, searches) = embedding_service.usage();
+    UsageEvent {
+        embedding_calls: embedding_calls as i64,
+        searches: searches as i64,
+        ..event
+    }
+}
+
+/// Store what a request used, in the background
+pub fn record_usage(jobs: &BackgroundJobs, db: Arc<PgPool>, event: UsageEvent) {
+    jobs.spawn(async move {
+        if let Err(e) = record_usage_event(&db, &event).await {
+            tracing::warn!("⚠️ Failed to record usage: {}", e);
+        }
+    });
+}
+
+/// Usage of a streamed answer, recorded when the response stream is dropped so that
+/// answers the client stopped reading are counted too
+pub struct StreamUsage {
+    jobs: BackgroundJobs,
+    db: Arc<PgPool>,
+    token_budget: Arc<TokenBudget>,
+    event: UsageEvent,
+    // Model output seen so far, when output tokens are counted from the stream
+    streamed: Option<String>,
+}
+
+impl StreamUsage {
+    /// `count_streamed` counts output tokens from the text passed to `push`
+    pub fn new(
+        jobs: BackgroundJobs,
+        db: Arc<PgPool>,
+        token_budget: Arc<TokenBudget>,
+        event: UsageEvent,
+        count_streamed: bool,
+    ) -> Self {
+        let streamed = count_streamed.then(String::new);
+        Self { jobs, db, token_budget, event, streamed }
+    }
+
+    pub fn push(&mut self, text: &str) {
+        if let Some(streamed) = &mut self.streamed {
+            streamed.push_str(text);
+        }
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        if let Some(streamed) = &self.streamed {
+            self.event.output_tokens = self.token_budget.count(streamed) as i64;
+        }
+        record_usage(&self.jobs, self.db.clone(), self.event.clone());
+    }
+}
+
+/// Inclusive date range for a usage report; `to` defaults to today and `from` to 30 days before it
+pub fn usage_range(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    today: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let to = to.unwrap_or(today);
+    let from = from.unwrap_or(to - Duration::days(DEFAULT_USAGE_DAYS - 1));
+    if from > to {
+        return Err(format!("from ({}) is after to ({})", from, to));
+    }
+    if (to - from).num_days() >= MAX_USAGE_DAYS {
+        return Err(format!("Usage can be requested for at most {} days at a time", MAX_USAGE_DAYS));
+    }
+    Ok((from, to))
+}
+
+/// Sum of daily totals over the whole range
+pub fn usage_totals(days: &[UsageDay]) -> UsageTotals {
+    days.iter().fold(UsageTotals::default(), |total, day| UsageTotals {
+        requests: total.requests + day.requests,
+        input_tokens: total.input_tokens + day.input_tokens,
+        output_tokens: total.output_tokens + day.output_tokens,
+        embedding_calls: total.embedding_calls + day.embedding_calls,
+        searches: total.searches + day.searches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_usage_range_defaults_to_last_30_days() {
+        let today = date("2026-03-31");
+        assert_eq!(usage_range(None, None, today), Ok((date("2026-03-02"), today)));
+        assert_eq!(
+            usage_range(None, Some(date("2026-01-31")), today),
+            Ok((date("2026-01-02"), date("2026-01-31")))
+        );
+    }
+
+    #[test]
+    fn test_usage_range_rejects_bad_ranges() {
+        let today = date("2026-03-31");
+        assert!(usage_range(Some(date("2026-04-01")), None, today).is_err());
+        assert!(usage_range(Some(date("2024-01-01")), None, today).is_err());
+        assert!(usage_range(Some(date("2025-03-31")), None, today).is_ok());
+    }
+
+    #[test]
+    fn test_usage_totals() {
+        let day = |day: &str, requests| UsageDay {
+            day: date(day),
+            requests,
+            input_tokens: 100 * requests,
+            output_tokens: 10 * requests,
+            embedding_calls: requests,
+            searches: requests,
+        };
+        let totals = usage_totals(&[day("2026-03-01", 2), day("2026-03-03", 3)]);
+
+        assert_eq!(totals.requests, 5);
+        assert_eq!(totals.input_tokens, 500);
+        assert_eq!(totals.output_tokens, 50);
+        assert_eq!(totals.searches, 5);
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+const DEFAULT_ACCESS_TTL_SECS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TTL_DAYS: i64 = 30;
+// HS256 keys shorter than the hash output are easy to brute-force
+const MIN_SECRET_LEN: usize = 32;
+pub const MIN_PASSWORD_LEN: usize = 8;
+const ISSUER: &str = "rust_rag";
+
+/// What a user may do in their organization. Viewers can only chat and read, editors can also
+/// change chatbots and their knowledge, and owners can also manage users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl UserRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserRole::Viewer => "viewer",
+            UserRole::Editor => "editor",
+            UserRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "viewer" => Ok(UserRole::Viewer),
+            "editor" => Ok(UserRole::Editor),
+            "owner" => Ok(UserRole::Owner),
+            other => Err(format!("unknown user role '{}'; use owner, editor or viewer", other)),
+        }
+    }
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Claims of a user access token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessClaims {
+    /// The user's id
+    pub sub: Uuid,
+    /// The user's organization
+    pub org: Uuid,
+    /// The user's role when the token was issued
+    pub role: UserRole,
+    pub iss: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// User sign-in settings. `JWT_SECRET` signs access tokens, which last `JWT_ACCESS_TTL_SECS`
+/// (default 900); refresh tokens last `JWT_REFRESH_TTL_DAYS` (default 30)
+#[derive(Clone)]
+pub struct UserAuthConfig {
+    secret: String,
+    pub access_ttl_secs: i64,
+    pub refresh_ttl_secs: i64,
+}
+
+impl UserAuthConfig {
+    /// `None` when `JWT_SECRET` is not set, which turns user sign-in off
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(secret) = std::env::var("JWT_SECRET") else {
+            return Ok(None);
+        };
+        if secret.len() < MIN_SECRET_LEN {
+            anyhow::bail!("JWT_SECRET must be at least {} characters", MIN_SECRET_LEN);
+        }
+
+        let access_ttl_secs = std::env::var("JWT_ACCESS_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|ttl: &i64| *ttl > 0)
+            .unwrap_or(DEFAULT_ACCESS_TTL_SECS);
+        let refresh_ttl_days = std::env::var("JWT_REFRESH_TTL_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|ttl: &i64| *ttl > 0)
+            .unwrap_or(DEFAULT_REFRESH_TTL_DAYS);
+
+        Ok(Some(Self::new(secret, access_ttl_secs, refresh_ttl_days * 86_400)))
+    }
+
+    pub fn new(secret: String, access_ttl_secs: i64, refresh_ttl_secs: i64) -> Self {
+        Self { secret, access_ttl_secs, refresh_ttl_secs }
+    }
+
+    /// A signed access token for the user
+    pub fn issue_access_token(&self, user_id: Uuid, organization_id: Uuid, role: UserRole) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let claims = AccessClaims {
+            sub: user_id,
+            org: organization_id,
+            role,
+            iss: ISSUER.to_string(),
+            iat: now,
+            exp: now + self.access_ttl_secs,
+        };
+        Ok(encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(self.secret.as_bytes()))?)
+    }
+
+    /// The claims of an access token this server issued that hasn't expired
+    pub fn verify_access_token(&self, token: &str) -> Result<AccessClaims> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[ISSUER]);
+        validation.leeway = 0;
+        Ok(decode::<AccessClaims>(token, &DecodingKey::from_secret(self.secret.as_bytes()), &validation)?.claims)
+    }
+}
+
+/// An argon2 hash of the password in PHC string form, with a random salt
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to encode salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Whether the password matches a hash from `hash_password`
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(access_ttl_secs: i64) -> UserAuthConfig {
+        UserAuthConfig::new("0123456789abcdef0123456789abcdef".to_string(), access_ttl_secs, 86_400)
+    }
+
+    #[test]
+    fn test_access_token_round_trip() {
+        let (user_id, organization_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let token = config(900).issue_access_token(user_id, organization_id, UserRole::Editor).unwrap();
+
+        let claims = config(900).verify_access_token(&token).unwrap();
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.org, organization_id);
+        assert_eq!(claims.role, UserRole::Editor);
+
+        let other = UserAuthConfig::new("fedcba9876543210fedcba9876543210".to_string(), 900, 86_400);
+        assert!(other.verify_access_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_expired_access_token_is_rejected() {
+        let token = config(-60).issue_access_token(Uuid::new_v4(), Uuid::new_v4(), UserRole::Viewer).unwrap();
+        assert!(config(900).verify_access_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_user_roles_are_ordered_and_round_trip() {
+        assert!(UserRole::Viewer < UserRole::Editor);
+        assert!(UserRole::Editor < UserRole::Owner);
+        for role in [UserRole::Viewer, UserRole::Editor, UserRole::Owner] {
+            assert_eq!(role.as_str().parse::<UserRole>(), Ok(role));
+            assert_eq!(serde_json::to_value(role).unwrap(), serde_json::json!(role.as_str()));
+        }
+        assert!("admin".parse::<UserRole>().is_err());
+    }
+
+    #[test]
+    fn test_password_hash() {
+        let hash = hash_password("correct horse").unwrap();
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+}
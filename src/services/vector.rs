@@ -0,0 +1,392 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::services::document_store::content_hash;
+use crate::services::elasticsearch_failover::ElasticsearchFailover;
+use crate::services::pgvector::PgVectorStore;
+use crate::services::qdrant::QdrantVectorStore;
+
+/// Collection holding a chatbot's chunks, namespaced by organization
+pub fn chatbot_index_name(organization_id: Uuid, chatbot_id: Uuid) -> String {
+    format!("org_{}_chatbot_{}", organization_id, chatbot_id)
+}
+
+/// The best `limit` results of searches run one collection at a time, each with its collection
+pub fn merge_collection_hits(searches: Vec<(String, Vec<SearchResult>)>, limit: u64) -> Vec<(String, SearchResult)> {
+    let mut hits: Vec<(String, SearchResult)> = searches
+        .into_iter()
+        .flat_map(|(collection, results)| results.into_iter().map(move |result| (collection.clone(), result)))
+        .collect();
+    hits.sort_by(|a, b| b.1.score.total_cmp(&a.1.score));
+    hits.truncate(limit as usize);
+    hits
+}
+
+// Namespace of the name-based UUIDs chunks are stored under
+const CHUNK_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c2a57_9d4e_4b8a_a3f0_5c7e1d92b84e);
+
+/// Id a chunk is stored under, derived from its document, position and content. A retried write
+/// of the same chunk upserts it rather than adding a duplicate. A UUID, since Qdrant point ids
+/// must be one
+pub fn chunk_id(file_path: &str, chunk_index: i64, text: &str) -> String {
+    let name = format!("{}\n{}\n{}", file_path, chunk_index, content_hash(text.as_bytes()));
+    Uuid::new_v5(&CHUNK_ID_NAMESPACE, name.as_bytes()).to_string()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DocumentWithEmbedding {
+    pub id: String,
+    pub text: String,
+    pub embedding: Vec<f32>,
+    pub chunk_index: i64,
+    pub file_path: String,
+    pub chunk_count: i64,
+    /// Section of the document this chunk was cut from, for documents chunked small-to-big
+    #[serde(default)]
+    pub parent_index: Option<i64>,
+}
+
+/// One page of a collection's chunks, read without their embeddings
+#[derive(Debug, Default)]
+pub struct ChunkPage {
+    pub chunks: Vec<DocumentWithEmbedding>,
+    /// Pass back to read the next page; None after the last one
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct SearchResult {
+    pub text: String,
+    pub score: f32,
+    pub chunk_index: i64,
+    pub file_path: String,
+    /// Section the matched chunk belongs to; the second fetch swaps the chunk for the whole section
+    #[serde(skip)]
+    #[sqlx(default)]
+    pub parent_index: Option<i64>,
+}
+
+/// Where chunk embeddings are stored and searched. A collection is an Elasticsearch index or
+/// its equivalent; scores are cosine similarity scaled to [0, 1] on every backend
+pub trait VectorStore {
+    /// Create a collection for embeddings of `embedding_dim` dimensions if it doesn't exist
+    fn create_collection(&self, name: &str, embedding_dim: usize) -> impl Future<Output = Result<()>> + Send;
+
+    /// Store chunks, replacing any with the same id. Returns how many were stored
+    fn index_documents(
+        &self,
+        collection: &str,
+        documents: Vec<DocumentWithEmbedding>,
+    ) -> impl Future<Output = Result<usize>> + Send;
+
+    /// The `limit` chunks closest to `query_embedding`, best first
+    fn search_similar(
+        &self,
+        collection: &str,
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Vec<SearchResult>>> + Send;
+
+    /// The `limit` chunks closest to `query_embedding` across collections, best first, each with the
+    /// collection it was found in. That may be the collection an alias points at rather than the alias
+    fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Vec<(String, SearchResult)>>> + Send;
+
+    /// Count chunks across collections, skipping ones that don't exist yet
+    fn count_documents(&self, collections: &[String]) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Delete collections, ignoring ones that don't exist
+    fn delete_collections(&self, collections: &[String]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Take collections out of search while keeping their chunks
+    fn close_collections(&self, collections: &[String]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Make closed collections searchable again
+    fn open_collections(&self, collections: &[String]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Collection names matching a pattern where `*` matches any run of characters
+    fn list_collections(&self, pattern: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Every chunk of a document, with its embedding and the collection it lives in
+    fn fetch_document_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+    ) -> impl Future<Output = Result<Vec<(String, DocumentWithEmbedding)>>> + Send;
+
+    /// The chunks cut from some of a document's sections, in document order and without embeddings
+    fn fetch_section_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> impl Future<Output = Result<Vec<DocumentWithEmbedding>>> + Send;
+
+    /// Delete every chunk of a document. Returns how many were deleted
+    fn delete_document_chunks(&self, collections: &[String], file_path: &str) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Read a collection's chunks a page at a time, with empty embeddings. A missing collection has no chunks
+    fn scroll_chunks(
+        &self,
+        collection: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> impl Future<Output = Result<ChunkPage>> + Send;
+
+    /// The collection an alias points at, or None when `name` isn't an alias
+    fn resolve_alias(&self, name: &str) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Point each `(alias, collection)` alias at its collection, in one step where the backend allows
+    /// it. A collection already named like an alias is deleted to make room. Returns the collections
+    /// the aliases pointed at before
+    fn swap_aliases(&self, swaps: &[(String, String)]) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Check the store is reachable and serving, without retrying
+    fn ping(&self) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Vector store backends, chosen per deployment with `VECTOR_BACKEND`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorBackendKind {
+    #[default]
+    Elasticsearch,
+    Pgvector,
+    Qdrant,
+}
+
+impl FromStr for VectorBackendKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "elasticsearch" => Ok(VectorBackendKind::Elasticsearch),
+            "pgvector" => Ok(VectorBackendKind::Pgvector),
+            "qdrant" => Ok(VectorBackendKind::Qdrant),
+            other => Err(format!("unknown vector backend '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for VectorBackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VectorBackendKind::Elasticsearch => f.write_str("Elasticsearch"),
+            VectorBackendKind::Pgvector => f.write_str("pgvector"),
+            VectorBackendKind::Qdrant => f.write_str("Qdrant"),
+        }
+    }
+}
+
+/// The deployment's vector store
+pub enum VectorBackend {
+    Elasticsearch(ElasticsearchFailover),
+    Pgvector(PgVectorStore),
+    Qdrant(QdrantVectorStore),
+}
+
+impl VectorBackend {
+    pub fn kind(&self) -> VectorBackendKind {
+        match self {
+            VectorBackend::Elasticsearch(_) => VectorBackendKind::Elasticsearch,
+            VectorBackend::Pgvector(_) => VectorBackendKind::Pgvector,
+            VectorBackend::Qdrant(_) => VectorBackendKind::Qdrant,
+        }
+    }
+}
+
+impl VectorStore for VectorBackend {
+    async fn create_collection(&self, name: &str, embedding_dim: usize) -> Result<()> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.create_collection(name, embedding_dim).await,
+            VectorBackend::Pgvector(store) => store.create_collection(name, embedding_dim).await,
+            VectorBackend::Qdrant(store) => store.create_collection(name, embedding_dim).await,
+        }
+    }
+
+    async fn index_documents(&self, collection: &str, documents: Vec<DocumentWithEmbedding>) -> Result<usize> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.index_documents(collection, documents).await,
+            VectorBackend::Pgvector(store) => store.index_documents(collection, documents).await,
+            VectorBackend::Qdrant(store) => store.index_documents(collection, documents).await,
+        }
+    }
+
+    async fn search_similar(&self, collection: &str, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<SearchResult>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.search_similar(collection, query_embedding, limit).await,
+            VectorBackend::Pgvector(store) => store.search_similar(collection, query_embedding, limit).await,
+            VectorBackend::Qdrant(store) => store.search_similar(collection, query_embedding, limit).await,
+        }
+    }
+
+    async fn search_collections(
+        &self,
+        collections: &[String],
+        query_embedding: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, SearchResult)>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.search_collections(collections, query_embedding, limit).await,
+            VectorBackend::Pgvector(store) => store.search_collections(collections, query_embedding, limit).await,
+            VectorBackend::Qdrant(store) => store.search_collections(collections, query_embedding, limit).await,
+        }
+    }
+
+    async fn count_documents(&self, collections: &[String]) -> Result<u64> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.count_documents(collections).await,
+            VectorBackend::Pgvector(store) => store.count_documents(collections).await,
+            VectorBackend::Qdrant(store) => store.count_documents(collections).await,
+        }
+    }
+
+    async fn delete_collections(&self, collections: &[String]) -> Result<()> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.delete_collections(collections).await,
+            VectorBackend::Pgvector(store) => store.delete_collections(collections).await,
+            VectorBackend::Qdrant(store) => store.delete_collections(collections).await,
+        }
+    }
+
+    async fn close_collections(&self, collections: &[String]) -> Result<()> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.close_collections(collections).await,
+            VectorBackend::Pgvector(store) => store.close_collections(collections).await,
+            VectorBackend::Qdrant(store) => store.close_collections(collections).await,
+        }
+    }
+
+    async fn open_collections(&self, collections: &[String]) -> Result<()> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.open_collections(collections).await,
+            VectorBackend::Pgvector(store) => store.open_collections(collections).await,
+            VectorBackend::Qdrant(store) => store.open_collections(collections).await,
+        }
+    }
+
+    async fn list_collections(&self, pattern: &str) -> Result<Vec<String>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.list_collections(pattern).await,
+            VectorBackend::Pgvector(store) => store.list_collections(pattern).await,
+            VectorBackend::Qdrant(store) => store.list_collections(pattern).await,
+        }
+    }
+
+    async fn fetch_document_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+    ) -> Result<Vec<(String, DocumentWithEmbedding)>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.fetch_document_chunks(collections, file_path).await,
+            VectorBackend::Pgvector(store) => store.fetch_document_chunks(collections, file_path).await,
+            VectorBackend::Qdrant(store) => store.fetch_document_chunks(collections, file_path).await,
+        }
+    }
+
+    async fn fetch_section_chunks(
+        &self,
+        collections: &[String],
+        file_path: &str,
+        parent_indices: &[i64],
+    ) -> Result<Vec<DocumentWithEmbedding>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => {
+                store.fetch_section_chunks(collections, file_path, parent_indices).await
+            }
+            VectorBackend::Pgvector(store) => store.fetch_section_chunks(collections, file_path, parent_indices).await,
+            VectorBackend::Qdrant(store) => store.fetch_section_chunks(collections, file_path, parent_indices).await,
+        }
+    }
+
+    async fn delete_document_chunks(&self, collections: &[String], file_path: &str) -> Result<u64> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.delete_document_chunks(collections, file_path).await,
+            VectorBackend::Pgvector(store) => store.delete_document_chunks(collections, file_path).await,
+            VectorBackend::Qdrant(store) => store.delete_document_chunks(collections, file_path).await,
+        }
+    }
+
+    async fn scroll_chunks(&self, collection: &str, cursor: Option<&str>, limit: usize) -> Result<ChunkPage> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.scroll_chunks(collection, cursor, limit).await,
+            VectorBackend::Pgvector(store) => store.scroll_chunks(collection, cursor, limit).await,
+            VectorBackend::Qdrant(store) => store.scroll_chunks(collection, cursor, limit).await,
+        }
+    }
+
+    async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.resolve_alias(name).await,
+            VectorBackend::Pgvector(store) => store.resolve_alias(name).await,
+            VectorBackend::Qdrant(store) => store.resolve_alias(name).await,
+        }
+    }
+
+    async fn swap_aliases(&self, swaps: &[(String, String)]) -> Result<Vec<String>> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.swap_aliases(swaps).await,
+            VectorBackend::Pgvector(store) => store.swap_aliases(swaps).await,
+            VectorBackend::Qdrant(store) => store.swap_aliases(swaps).await,
+        }
+    }
+
+    async fn ping(&self) -> Result<()> {
+        match self {
+            VectorBackend::Elasticsearch(store) => store.ping().await,
+            VectorBackend::Pgvector(store) => store.ping().await,
+            VectorBackend::Qdrant(store) => store.ping().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend_kind() {
+        assert_eq!("pgvector".parse(), Ok(VectorBackendKind::Pgvector));
+        assert_eq!("Elasticsearch".parse(), Ok(VectorBackendKind::Elasticsearch));
+        assert_eq!("qdrant".parse(), Ok(VectorBackendKind::Qdrant));
+        assert!("milvus".parse::<VectorBackendKind>().is_err());
+        assert_eq!(VectorBackendKind::default(), VectorBackendKind::Elasticsearch);
+    }
+
+    #[test]
+    fn test_chunk_id_is_stable() {
+        let id = chunk_id("manual.pdf", 3, "Refunds take five days.");
+        assert_eq!(id, chunk_id("manual.pdf", 3, "Refunds take five days."));
+        assert!(Uuid::parse_str(&id).is_ok());
+
+        assert_ne!(id, chunk_id("manual.pdf", 4, "Refunds take five days."));
+        assert_ne!(id, chunk_id("guide.pdf", 3, "Refunds take five days."));
+        assert_ne!(id, chunk_id("manual.pdf", 3, "Refunds take ten days."));
+    }
+
+    #[test]
+    fn test_merge_collection_hits() {
+        let result = |score: f32| SearchResult {
+            text: String::new(),
+            score,
+            chunk_index: 0,
+            file_path: String::new(),
+            parent_index: None,
+        };
+        let hits = merge_collection_hits(
+            vec![("a".to_string(), vec![result(0.9), result(0.5)]), ("b".to_string(), vec![result(0.7)])],
+            2,
+        );
+        let collections: Vec<&str> = hits.iter().map(|(collection, _)| collection.as_str()).collect();
+        assert_eq!(collections, ["a", "b"]);
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+use crate::db::models::WebSource;
+use crate::db::queries::{
+    claim_due_web_sources, is_reindex_running, record_web_source_crawled, record_web_source_error,
+    remove_cold_document,
+};
+use crate::services::cache_invalidation::{publish, CacheEvent, ChatbotCache};
+use crate::services::document_store::content_hash;
+use crate::services::embedding::EmbeddingService;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::help_center::html_to_text;
+use crate::services::ingest_webhook::IngestWebhook;
+use crate::services::public_url::{check_public_host, public_client_builder};
+use crate::services::reindex::stale_after_secs;
+use crate::services::sharding::shard_indices;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::vector::{chatbot_index_name, VectorBackend, VectorStore};
+use crate::utils::pdf::chunk_text;
+
+pub const DEFAULT_INTERVAL_MINUTES: i32 = 1440;
+pub const MIN_INTERVAL_MINUTES: i32 = 15;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+// Pages larger than this are rejected rather than indexed in part
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+const TICK_SECS: u64 = 60;
+// Sources claimed per tick
+const BATCH_SIZE: i64 = 20;
+
+/// A web source must be an absolute `http` or `https` URL whose host resolves to public addresses only
+pub async fn validate_source_url(url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some() => {
+            check_public_host(&parsed).await
+        }
+        Ok(_) => Err(format!("Web source URL must use http or https: {}", url)),
+        Err(e) => Err(format!("Invalid web source URL '{}': {}", url, e)),
+    }
+}
+
+/// A fetched page's text: HTML is reduced to its visible text, other text types are kept as they are
+pub fn page_text(content_type: &str, body: &[u8]) -> Result<String, String> {
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+    let body = String::from_utf8_lossy(body);
+    let text = match mime.as_str() {
+        "text/html" | "application/xhtml+xml" => html_to_text(&body),
+        _ if mime.starts_with("text/") => body.trim().to_string(),
+        _ => return Err(format!("Unsupported content type '{}'", mime)),
+    };
+    if text.is_empty() {
+        return Err("Page has no text".to_string());
+    }
+    Ok(text)
+}
+
+/// Chunk a page, starting every chunk with its URL so it shows up in citations
+pub fn page_chunks(url: &str, text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+    chunk_text(text, chunk_size, overlap)
+        .into_iter()
+        .map(|chunk| format!("Source: {}\n\n{}", url, chunk))
+        .collect()
+}
+
+// GET the page and return its text, refusing to connect to private addresses even after a redirect.
+// `WEB_SOURCE_TIMEOUT_SECS` (default 30) bounds the request
+async fn fetch_page(url: &str) -> Result<String> {
+    let timeout_secs = std::env::var("WEB_SOURCE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+    let response = public_client_builder()
+        .build()?
+        .get(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .header(reqwest::header::USER_AGENT, concat!("rust_rag/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await?
+        .error_for_status()?;
+    if response.content_length().is_some_and(|length| length as usize > MAX_PAGE_BYTES) {
+        return Err(anyhow::anyhow!("Page is larger than {} bytes", MAX_PAGE_BYTES));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html")
+        .to_string();
+    let body = response.bytes().await?;
+    if body.len() > MAX_PAGE_BYTES {
+        return Err(anyhow::anyhow!("Page is larger than {} bytes", MAX_PAGE_BYTES));
+    }
+    page_text(&content_type, &body).map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Outcome of one crawl
+#[derive(Debug)]
+pub struct WebCrawl {
+    pub content_hash: String,
+    /// Whether the page's text differed from the last crawl and was re-embedded
+    pub changed: bool,
+    pub embedding_count: usize,
+}
+
+/// Fetch a source's page and re-embed it when its text changed since the last crawl
+pub async fn crawl_source(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: &ChatbotCache,
+    source: &WebSource,
+) -> Result<WebCrawl> {
+    let chatbot = chatbot_cache
+        .get_chatbot(db, source.organization_id, source.chatbot_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Chatbot {} not found", source.chatbot_id))?;
+    // Chunks written during a reindex would be lost at its swap
+    if is_reindex_running(db, chatbot.id, stale_after_secs()).await? {
+        return Err(anyhow::anyhow!("Chatbot is being reindexed"));
+    }
+
+    let text = fetch_page(&source.url).await?;
+    let hash = content_hash(text.as_bytes());
+    if source.content_hash.as_ref() == Some(&hash) {
+        return Ok(WebCrawl { content_hash: hash, changed: false, embedding_count: 0 });
+    }
+
+    let embedding_service = EmbeddingService::new(vector_store.clone(), embedding_cache)?;
+    let collection_name = embedding_service
+        .prepare_chatbot_collection(db, chatbot_cache, source.organization_id, &chatbot, &source.url)
+        .await?;
+    let index_names = shard_indices(&chatbot_index_name(source.organization_id, chatbot.id), chatbot.shard_count);
+    let webhook = IngestWebhook::for_chatbot(&chatbot);
+
+    vector_store.delete_document_chunks(&index_names, &source.url).await?;
+    let embedding_count = embedding_service
+        .index_chunks(&source.url, page_chunks(&source.url, &text, 200, 50), &collection_name, webhook.as_ref())
+        .await?;
+
+    if let Err(e) = remove_cold_document(db, chatbot.id, &source.url).await {
+        tracing::warn!("⚠️ Failed to record document usage: {}", e);
+    }
+    publish(db, chatbot_cache, CacheEvent::KnowledgeVersionChanged { chatbot_id: chatbot.id }).await?;
+    Ok(WebCrawl { content_hash: hash, changed: true, embedding_count })
+}
+
+/// Crawl a source and record the outcome on it
+pub async fn run_crawl(
+    db: &PgPool,
+    vector_store: &Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: &ChatbotCache,
+    source: &WebSource,
+) -> Result<WebCrawl> {
+    match crawl_source(db, vector_store, embedding_cache, chatbot_cache, source).await {
+        Ok(crawl) => {
+            record_web_source_crawled(db, source.id, &crawl.content_hash, crawl.changed).await?;
+            tracing::info!(
+                "✅ Crawled web source {} ({})",
+                source.url,
+                if crawl.changed { "changed" } else { "unchanged" }
+            );
+            Ok(crawl)
+        }
+        Err(e) => {
+            if let Err(record) = record_web_source_error(db, source.id, &e.to_string()).await {
+                tracing::error!("❌ Failed to record error of web source {}: {}", source.id, record);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Spawn the background task that re-crawls web sources when they are due
+pub fn spawn_web_source_task(
+    jobs: &BackgroundJobs,
+    db: Arc<PgPool>,
+    vector_store: Arc<VectorBackend>,
+    embedding_cache: Arc<EmbeddingCache>,
+    chatbot_cache: Arc<ChatbotCache>,
+) {
+    let shutdown = jobs.clone();
+    jobs.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(TICK_SECS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let due = match claim_due_web_sources(&db, BATCH_SIZE).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!("❌ Web source task failed to list sources: {}", e);
+                    continue;
+                }
+            };
+
+            for source in &due {
+                if let Err(e) = run_crawl(&db, &vector_store, embedding_cache.clone(), &chatbot_cache, source).await {
+                    tracing::error!("❌ Web source {} (chatbot {}) failed: {}", source.url, source.chatbot_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_page_text_never_panics_on_arbitrary_bodies(
+            content_type in prop_oneof![
+                Just("text/html; charset=utf-8"),
+                Just("application/xhtml+xml"),
+                Just("text/plain"),
+                Just("application/pdf"),
+            ],
+            body in prop::collection::vec(any::<u8>(), 0..512),
+        ) {
+            if let Ok(text) = page_text(content_type, &body) {
+                prop_assert!(!text.is_empty());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_source_url() {
+        assert!(validate_source_url("https://93.184.216.34/pricing").await.is_ok());
+        assert!(validate_source_url("ftp://example.com/file.txt").await.is_err());
+        assert!(validate_source_url("example.com/pricing").await.is_err());
+        assert!(validate_source_url("http://169.254.169.254/latest/meta-data").await.is_err());
+        assert!(validate_source_url("http://127.0.0.1:9200/_cat/indices").await.is_err());
+    }
+
+    #[test]
+    fn test_page_text_by_content_type() {
+        let html = b"<html><head><style>p {}</style></head><body><p>Plans</p><p>From &lt;$10</p></body></html>";
+        assert_eq!(page_text("text/html; charset=utf-8", html).unwrap(), "Plans\nFrom <$10");
+        assert_eq!(page_text("text/plain", b"  Release notes \n").unwrap(), "Release notes");
+        assert!(page_text("application/pdf", b"%PDF-1.4").is_err());
+        assert!(page_text("text/html", b"<script>x()</script>").is_err());
+    }
+
+    #[test]
+    fn test_page_chunks_start_with_url() {
+        let chunks = page_chunks("https://example.com/faq", "Refunds take five days.", 200, 50);
+        assert_eq!(chunks, vec!["Source: https://example.com/faq\n\nRefunds take five days.".to_string()]);
+    }
+}
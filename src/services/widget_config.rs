@@ -0,0 +1,148 @@
+use url::Url;
+
+use crate::db::models::UpsertWidgetConfigRequest;
+use crate::services::custom_domain::is_hex_color;
+
+const POSITIONS: &[&str] = &["bottom-right", "bottom-left"];
+const MAX_LAUNCHER_TEXT_CHARS: usize = 100;
+const MAX_ALLOWED_ORIGINS: usize = 50;
+
+/// Reduce an origin to `scheme://host[:port]`, lowercased and without a default port. A leading
+/// `*.` on the host allows its subdomains
+pub fn normalize_origin(origin: &str) -> Result<String, String> {
+    let origin = origin.trim().trim_end_matches('/');
+    let (wildcard, parsed) = match origin.split_once("://*.") {
+        Some((scheme, host)) => (true, format!("{}://{}", scheme, host)),
+        None => (false, origin.to_string()),
+    };
+    let invalid = || format!("Origin must be a scheme and host such as https://www.example.com, got '{}'", origin);
+    let url = Url::parse(&parsed).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https")
+        || url.host_str().is_none()
+        || url.path() != "/"
+        || url.query().is_some()
+        || url.fragment().is_some()
+        || !url.username().is_empty()
+    {
+        return Err(invalid());
+    }
+
+    let normalized = url.origin().ascii_serialization();
+    Ok(if wildcard { normalized.replacen("://", "://*.", 1) } else { normalized })
+}
+
+/// Whether a browser's `Origin` may use the widget. An empty list allows any site; otherwise
+/// requests without an `Origin` are refused
+pub fn origin_allowed(allowed_origins: &[String], origin: Option<&str>) -> bool {
+    if allowed_origins.is_empty() {
+        return true;
+    }
+    let Some(origin) = origin.and_then(|origin| normalize_origin(origin).ok()) else {
+        return false;
+    };
+    allowed_origins.iter().any(|allowed| match allowed.split_once("://*.") {
+        Some((scheme, domain)) => origin
+            .strip_prefix(scheme)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .is_some_and(|host| host.ends_with(&format!(".{}", domain))),
+        None => *allowed == origin,
+    })
+}
+
+/// Check a widget config and normalize its origins before storing it; the widget renders it on
+/// customer pages
+pub fn validate_widget_config(request: &mut UpsertWidgetConfigRequest) -> Result<(), String> {
+    for (field, color) in [
+        ("primary_color", &request.primary_color),
+        ("background_color", &request.background_color),
+        ("text_color", &request.text_color),
+    ] {
+        if let Some(color) = color
+            && !is_hex_color(color)
+        {
+            return Err(format!("{} must be a hex color such as #1a73e8, got '{}'", field, color));
+        }
+    }
+    if let Some(avatar_url) = &request.avatar_url {
+        match Url::parse(avatar_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => return Err("avatar_url must be an absolute http(s) URL".to_string()),
+        }
+    }
+    if let Some(position) = &request.position
+        && !POSITIONS.contains(&position.as_str())
+    {
+        return Err(format!("position must be one of {}, got '{}'", POSITIONS.join(", "), position));
+    }
+    request.launcher_text = request.launcher_text.as_ref().map(|text| text.trim().to_string()).filter(|text| !text.is_empty());
+    if request.launcher_text.as_ref().is_some_and(|text| text.chars().count() > MAX_LAUNCHER_TEXT_CHARS) {
+        return Err(format!("launcher_text must be at most {} characters", MAX_LAUNCHER_TEXT_CHARS));
+    }
+
+    let mut origins = request
+        .allowed_origins
+        .iter()
+        .map(|origin| normalize_origin(origin))
+        .collect::<Result<Vec<_>, _>>()?;
+    origins.sort();
+    origins.dedup();
+    if origins.len() > MAX_ALLOWED_ORIGINS {
+        return Err(format!("At most {} allowed origins", MAX_ALLOWED_ORIGINS));
+    }
+    request.allowed_origins = origins;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> UpsertWidgetConfigRequest {
+        UpsertWidgetConfigRequest {
+            primary_color: Some("#1a73e8".to_string()),
+            background_color: None,
+            text_color: Some("#FFF".to_string()),
+            avatar_url: Some("https://cdn.example.com/bot.png".to_string()),
+            position: Some("bottom-left".to_string()),
+            launcher_text: Some("  Ask us  ".to_string()),
+            allowed_origins: vec!["https://WWW.example.com/".to_string(), "https://www.example.com:443".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_normalize_origin() {
+        assert_eq!(normalize_origin("https://Example.com:443/").unwrap(), "https://example.com");
+        assert_eq!(normalize_origin("http://localhost:3000").unwrap(), "http://localhost:3000");
+        assert_eq!(normalize_origin("https://*.Example.com").unwrap(), "https://*.example.com");
+        assert!(normalize_origin("https://example.com/help").is_err());
+        assert!(normalize_origin("example.com").is_err());
+        assert!(normalize_origin("javascript:alert(1)").is_err());
+    }
+
+    #[test]
+    fn test_origin_allowed() {
+        let allowed = vec!["https://www.example.com".to_string(), "https://*.example.org".to_string()];
+        assert!(origin_allowed(&[], None));
+        assert!(origin_allowed(&allowed, Some("https://www.example.com")));
+        assert!(origin_allowed(&allowed, Some("https://help.example.org")));
+        assert!(!origin_allowed(&allowed, Some("https://example.org")));
+        assert!(!origin_allowed(&allowed, Some("http://www.example.com")));
+        assert!(!origin_allowed(&allowed, Some("https://evil-example.org")));
+        assert!(!origin_allowed(&allowed, None));
+    }
+
+    #[test]
+    fn test_validate_widget_config() {
+        let mut config = request();
+        assert!(validate_widget_config(&mut config).is_ok());
+        assert_eq!(config.allowed_origins, vec!["https://www.example.com".to_string()]);
+        assert_eq!(config.launcher_text.as_deref(), Some("Ask us"));
+
+        let mut config = UpsertWidgetConfigRequest { primary_color: Some("red; x".to_string()), ..request() };
+        assert!(validate_widget_config(&mut config).is_err());
+        let mut config = UpsertWidgetConfigRequest { position: Some("top".to_string()), ..request() };
+        assert!(validate_widget_config(&mut config).is_err());
+        let mut config = UpsertWidgetConfigRequest { avatar_url: Some("data:image/png;base64,x".to_string()), ..request() };
+        assert!(validate_widget_config(&mut config).is_err());
+    }
+}
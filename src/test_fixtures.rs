@@ -0,0 +1,158 @@
+use chrono::Utc;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::db::models::GlossaryEntry;
+use crate::services::answer_policy::GENERAL_KNOWLEDGE_CONTEXT;
+use crate::services::glossary::format_glossary;
+use crate::services::prompt_template::{render_prompt, PromptContext};
+use crate::services::stitching::group_by_document;
+use crate::services::token_budget::{format_document, join_sections};
+use crate::services::vector::SearchResult;
+
+// Set to rewrite golden files from the current output instead of comparing against them
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+/// A retrieved chunk with fixed contents
+pub fn chunk(file_path: &str, chunk_index: i64, score: f32, text: &str) -> SearchResult {
+    SearchResult { text: text.to_string(), score, chunk_index, file_path: file_path.to_string(), parent_index: None }
+}
+
+/// Search results as a kNN search might return them: best first, with two overlapping neighbours
+/// and two documents, so stitching and grouping both show in the prompt
+pub fn retrieval_results() -> Vec<SearchResult> {
+    vec![
+        chunk(
+            "refund-policy.pdf",
+            3,
+            0.91,
+            "Refunds are issued to the original payment method. Approved refunds take five business days",
+        ),
+        chunk("shipping.pdf", 0, 0.84, "Orders ship within two business days from our Leeds warehouse."),
+        chunk("refund-policy.pdf", 4, 0.78, "take five business days to appear on your statement."),
+        chunk("refund-policy.pdf", 9, 0.66, "Gift cards cannot be refunded."),
+    ]
+}
+
+/// One earlier turn, formatted the way the chat handlers format history
+pub fn history() -> Vec<String> {
+    vec!["User: Do you ship abroad?\nBot: Yes, to most of Europe.".to_string()]
+}
+
+/// The glossary section for a single term
+pub fn glossary() -> String {
+    let entry = GlossaryEntry {
+        id: Uuid::nil(),
+        chatbot_id: Uuid::nil(),
+        term: "RMA".to_string(),
+        definition: "Return merchandise authorization, the number a return needs".to_string(),
+        aliases: Vec::new(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    format_glossary(&[&entry])
+}
+
+/// Everything a chat handler feeds into a prompt, without the database or the search
+pub struct PromptFixture<'a> {
+    pub template: Option<&'a str>,
+    pub glossary: String,
+    pub history: Vec<String>,
+    pub chunks: Vec<SearchResult>,
+    pub question: &'a str,
+    pub variables: Option<HashMap<String, Value>>,
+}
+
+impl<'a> PromptFixture<'a> {
+    /// The default template with the fixed history and retrieval results
+    pub fn standard(question: &'a str) -> Self {
+        Self {
+            template: None,
+            glossary: String::new(),
+            history: history(),
+            chunks: retrieval_results(),
+            question,
+            variables: None,
+        }
+    }
+
+    /// Assemble the prompt the way the chat handlers do once the context fits the window
+    pub fn build(&self) -> String {
+        let sections: Vec<String> = group_by_document(&self.chunks).iter().map(format_document).collect();
+        let documents = if sections.is_empty() {
+            GENERAL_KNOWLEDGE_CONTEXT.to_string()
+        } else {
+            join_sections(sections)
+        };
+        let history = join_sections(self.history.iter().cloned());
+        let context = PromptContext { glossary: &self.glossary, history: &history, documents: &documents };
+
+        render_prompt(self.template, &context, self.question, self.variables.as_ref()).expect("fixture prompt renders")
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.txt", name))
+}
+
+/// Compare `actual` with `tests/golden/<name>.txt`. Run with `UPDATE_GOLDEN=1` to accept a change
+/// on purpose, then review the golden file's diff. The file's final newline is not part of the prompt
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::create_dir_all(path.parent().expect("golden files live in a directory")).expect("create golden dir");
+        std::fs::write(&path, format!("{}\n", actual)).expect("write golden file");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {} ({}); run with {}=1", path.display(), e, UPDATE_ENV));
+    let expected = expected.strip_suffix('\n').unwrap_or(&expected);
+    assert_eq!(
+        actual,
+        expected,
+        "prompt differs from {}; if the change is intended, rerun with {}=1",
+        path.display(),
+        UPDATE_ENV
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_default_template_prompt() {
+        let prompt = PromptFixture::standard("How long do refunds take?").build();
+        assert_golden("default_template", &prompt);
+    }
+
+    #[test]
+    fn test_custom_template_prompt() {
+        let fixture = PromptFixture {
+            template: Some(
+                "Customer: {{customer_name}}\n\n{{context}}\n\nEarlier turns:\n{{history}}\n\nQuestion: {{question}}",
+            ),
+            glossary: glossary(),
+            variables: Some(HashMap::from([
+                ("customer_name".to_string(), json!("Ada")),
+                ("question".to_string(), json!("injected")),
+            ])),
+            ..PromptFixture::standard("How long do refunds take?")
+        };
+        assert_golden("custom_template", &fixture.build());
+    }
+
+    #[test]
+    fn test_prompt_without_documents() {
+        let fixture = PromptFixture {
+            history: Vec::new(),
+            chunks: Vec::new(),
+            ..PromptFixture::standard("Can I pay with crypto?")
+        };
+        assert_golden("no_documents", &fixture.build());
+    }
+}
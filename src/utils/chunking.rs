@@ -0,0 +1,185 @@
+use std::str::FromStr;
+
+use crate::utils::pdf::chunk_text;
+
+const DEFAULT_SEMANTIC_THRESHOLD: f32 = 0.75;
+
+/// How document text is split into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkingStrategy {
+    /// Fixed windows of words with overlap
+    #[default]
+    Fixed,
+    /// Sentences, merged while adjacent ones are about the same thing
+    Semantic,
+    /// Small chunks for matching, answered with the larger section each was cut from
+    SmallToBig,
+}
+
+impl ChunkingStrategy {
+    /// Default for uploads that don't pick one, from `CHUNKING_STRATEGY`
+    pub fn from_env() -> Self {
+        std::env::var("CHUNKING_STRATEGY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChunkingStrategy::Fixed => "fixed",
+            ChunkingStrategy::Semantic => "semantic",
+            ChunkingStrategy::SmallToBig => "small_to_big",
+        }
+    }
+}
+
+impl FromStr for ChunkingStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "fixed" => Ok(ChunkingStrategy::Fixed),
+            "semantic" => Ok(ChunkingStrategy::Semantic),
+            "small_to_big" => Ok(ChunkingStrategy::SmallToBig),
+            other => Err(format!(
+                "Unknown chunking strategy '{}', expected 'fixed', 'semantic' or 'small_to_big'",
+                other
+            )),
+        }
+    }
+}
+
+/// Cosine similarity adjacent sentences need to stay in one semantic chunk, from
+/// `SEMANTIC_CHUNK_THRESHOLD`
+pub fn semantic_threshold() -> f32 {
+    std::env::var("SEMANTIC_CHUNK_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &f32| (-1.0..=1.0).contains(v))
+        .unwrap_or(DEFAULT_SEMANTIC_THRESHOLD)
+}
+
+/// Split text into sentences at `.`, `!` or `?` followed by whitespace, and at blank lines so
+/// headings and table rows don't run into the next paragraph. Sentences longer than `max_words`
+/// are cut into windows of that many words
+pub fn split_sentences(text: &str, max_words: usize) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let mut current = Vec::new();
+        for word in paragraph.split_whitespace() {
+            current.push(word);
+            if word.ends_with(['.', '!', '?']) {
+                sentences.push(current.join(" "));
+                current.clear();
+            }
+        }
+        if !current.is_empty() {
+            sentences.push(current.join(" "));
+        }
+    }
+
+    sentences
+        .into_iter()
+        .flat_map(|sentence| {
+            if sentence.split_whitespace().count() > max_words {
+                chunk_text(&sentence, max_words, 0)
+            } else {
+                vec![sentence]
+            }
+        })
+        .collect()
+}
+
+/// Merge consecutive sentences into chunks. `similarities[i]` is the similarity of sentence `i`
+/// and `i + 1`; a chunk ends where it drops below `threshold` or where the next sentence would take
+/// it past `max_words`
+pub fn merge_sentences(sentences: &[String], similarities: &[f32], threshold: f32, max_words: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_words = 0;
+
+    for (i, sentence) in sentences.iter().enumerate() {
+        let words = sentence.split_whitespace().count();
+        let related = i > 0 && similarities.get(i - 1).is_some_and(|similarity| *similarity >= threshold);
+        if !current.is_empty() && (!related || current_words + words > max_words) {
+            chunks.push(current.join(" "));
+            current.clear();
+            current_words = 0;
+        }
+        current.push(sentence);
+        current_words += words;
+    }
+    if !current.is_empty() {
+        chunks.push(current.join(" "));
+    }
+    chunks
+}
+
+/// Cut text into sections of `section_words` words, then each section into chunks of `chunk_words`
+/// words. Neither overlaps, so a section's chunks joined with spaces give the section back. Returns
+/// each chunk with the index of its section
+pub fn small_to_big_chunks(text: &str, section_words: usize, chunk_words: usize) -> Vec<(String, i64)> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words
+        .chunks(section_words.max(1))
+        .enumerate()
+        .flat_map(|(section, section_text)| {
+            section_text.chunks(chunk_words.max(1)).map(move |chunk| (chunk.join(" "), section as i64))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!("Semantic".parse::<ChunkingStrategy>(), Ok(ChunkingStrategy::Semantic));
+        assert_eq!("fixed".parse::<ChunkingStrategy>(), Ok(ChunkingStrategy::Fixed));
+        assert_eq!("small_to_big".parse::<ChunkingStrategy>(), Ok(ChunkingStrategy::SmallToBig));
+        assert!("sentences".parse::<ChunkingStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        let text = "Returns are free. Refunds take 5 days!\n\nShipping\nOrders ship in 2 days";
+        assert_eq!(
+            split_sentences(text, 50),
+            vec!["Returns are free.", "Refunds take 5 days!", "Shipping Orders ship in 2 days"]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_cuts_long_sentences() {
+        let sentences = split_sentences("one two three four five six seven", 3);
+        assert_eq!(sentences, vec!["one two three", "four five six", "seven"]);
+    }
+
+    #[test]
+    fn test_merge_sentences_breaks_on_topic_change() {
+        let sentences: Vec<String> = ["Returns are free.", "Refunds take 5 days.", "We ship worldwide."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let chunks = merge_sentences(&sentences, &[0.9, 0.2], 0.75, 200);
+        assert_eq!(chunks, vec!["Returns are free. Refunds take 5 days.", "We ship worldwide."]);
+    }
+
+    #[test]
+    fn test_small_to_big_chunks_belong_to_their_section() {
+        let chunks = small_to_big_chunks("a b c d e f g", 4, 3);
+        assert_eq!(
+            chunks,
+            vec![("a b c".to_string(), 0), ("d".to_string(), 0), ("e f g".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_merge_sentences_respects_max_words() {
+        let sentences: Vec<String> = ["a b c.", "d e f.", "g h i."].iter().map(|s| s.to_string()).collect();
+        let chunks = merge_sentences(&sentences, &[1.0, 1.0], 0.75, 6);
+        assert_eq!(chunks, vec!["a b c. d e f.", "g h i."]);
+    }
+}
@@ -1,9 +1,398 @@
-use elasticsearch::Elasticsearch;
+use anyhow::Result;
+use serde::Deserialize;
 use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+use crate::middleware::ip_filter::IpFilter;
+use crate::middleware::load_shed::LoadShedder;
+use crate::middleware::rate_limit::RateLimiter;
+use crate::services::answer_cache::AnswerCache;
+use crate::services::cache_invalidation::ChatbotCache;
+use crate::services::document_store::DocumentStore;
+use crate::services::elasticsearch_failover::SecondarySync;
+use crate::services::embedding_cache::EmbeddingCache;
+use crate::services::health::HealthChecker;
+use crate::services::plugins::PluginHost;
+use crate::services::retrieval::StageTimings;
+use crate::services::shutdown::BackgroundJobs;
+use crate::services::token_budget::TokenBudget;
+use crate::services::vector::{VectorBackend, VectorBackendKind};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+const DEFAULT_PORT: u16 = 8000;
+const DEFAULT_ELASTICSEARCH_URL: &str = "http://localhost:9200";
+const DEFAULT_QDRANT_URL: &str = "http://localhost:6333";
+const DEFAULT_DB_MAX_CONNECTIONS: u32 = 5;
+const DEFAULT_DB_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_ELASTICSEARCH_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<PgPool>,
-    pub elasticsearch: Arc<Elasticsearch>,
+    pub vector_store: Arc<VectorBackend>,
+    pub embedding_cache: Arc<EmbeddingCache>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub chatbot_cache: Arc<ChatbotCache>,
+    pub answer_cache: Arc<AnswerCache>,
+    pub stage_timings: Arc<StageTimings>,
+    pub background_jobs: BackgroundJobs,
+    pub plugins: PluginHost,
+    pub token_budget: Arc<TokenBudget>,
+    pub ip_filter: Arc<IpFilter>,
+    pub health: Arc<HealthChecker>,
+    pub load_shedder: Arc<LoadShedder>,
+    pub document_store: Option<Arc<DocumentStore>>,
+}
+
+/// Settings read from `config.toml` (or the file named by `APP_CONFIG_FILE`); every field is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub bind_address: Option<IpAddr>,
+    pub port: Option<u16>,
+    pub allowed_origins: Option<Vec<String>>,
+    pub database_url: Option<String>,
+    pub vector_backend: Option<VectorBackendKind>,
+    pub elasticsearch_url: Option<String>,
+    pub elasticsearch_secondary_url: Option<String>,
+    pub elasticsearch_secondary_sync: Option<SecondarySync>,
+    pub qdrant_url: Option<String>,
+    pub qdrant_api_key: Option<String>,
+    pub db_max_connections: Option<u32>,
+    pub db_min_connections: Option<u32>,
+    pub db_acquire_timeout_secs: Option<u64>,
+    pub elasticsearch_timeout_secs: Option<u64>,
+    pub shutdown_timeout_secs: Option<u64>,
+}
+
+/// Server configuration. Environment variables override the config file, which overrides the defaults
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// `["*"]` allows any origin
+    pub allowed_origins: Vec<String>,
+    pub database_url: String,
+    /// Where embeddings are stored; `elasticsearch` (default), `pgvector` or `qdrant`
+    pub vector_backend: VectorBackendKind,
+    pub elasticsearch_url: String,
+    /// Cluster in another region that serves reads while the primary is unreachable
+    pub elasticsearch_secondary_url: Option<String>,
+    /// How the secondary is kept in step; `dual_write` (default) or `snapshot`
+    pub elasticsearch_secondary_sync: SecondarySync,
+    pub qdrant_url: String,
+    pub qdrant_api_key: Option<String>,
+    pub db_max_connections: u32,
+    pub db_min_connections: u32,
+    pub db_acquire_timeout: Duration,
+    pub elasticsearch_timeout: Duration,
+    /// How long to wait for background jobs after the server stops accepting requests
+    pub shutdown_timeout: Duration,
+}
+
+impl AppConfig {
+    /// Load and validate the configuration, failing with every problem found
+    pub fn load() -> Result<Self> {
+        let file = match std::env::var("APP_CONFIG_FILE") {
+            Ok(path) => Some(read_config_file(Path::new(&path))?),
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Some(read_config_file(Path::new(DEFAULT_CONFIG_FILE))?)
+            }
+            Err(_) => None,
+        };
+
+        Self::from_sources(file.unwrap_or_default(), |name| std::env::var(name).ok())
+    }
+
+    /// Merge file settings with variables from `env`, then validate
+    pub fn from_sources(file: FileConfig, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut errors = Vec::new();
+
+        let bind_address = env_or(&env, "BIND_ADDRESS", &mut errors)
+            .or(file.bind_address)
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let port = env_or(&env, "PORT", &mut errors)
+            .or(file.port)
+            .unwrap_or(DEFAULT_PORT);
+        let allowed_origins = env("CORS_ALLOWED_ORIGINS")
+            .map(|raw| parse_origins(raw.split(',')))
+            .or_else(|| file.allowed_origins.as_ref().map(|origins| parse_origins(origins.iter().map(String::as_str))))
+            .unwrap_or_else(|| vec!["*".to_string()]);
+        let database_url = env("DATABASE_URL").or(file.database_url).unwrap_or_default();
+        let vector_backend = env_or(&env, "VECTOR_BACKEND", &mut errors)
+            .or(file.vector_backend)
+            .unwrap_or_default();
+        let elasticsearch_url = env("ELASTICSEARCH_URL")
+            .or(file.elasticsearch_url)
+            .unwrap_or_else(|| DEFAULT_ELASTICSEARCH_URL.to_string());
+        let elasticsearch_secondary_url = env("ELASTICSEARCH_SECONDARY_URL")
+            .or(file.elasticsearch_secondary_url)
+            .filter(|url| !url.trim().is_empty());
+        let elasticsearch_secondary_sync = env_or(&env, "ELASTICSEARCH_SECONDARY_SYNC", &mut errors)
+            .or(file.elasticsearch_secondary_sync)
+            .unwrap_or_default();
+        let qdrant_url = env("QDRANT_URL")
+            .or(file.qdrant_url)
+            .unwrap_or_else(|| DEFAULT_QDRANT_URL.to_string());
+        let qdrant_api_key = env("QDRANT_API_KEY").or(file.qdrant_api_key);
+        let db_max_connections = env_or(&env, "DB_MAX_CONNECTIONS", &mut errors)
+            .or(file.db_max_connections)
+            .unwrap_or(DEFAULT_DB_MAX_CONNECTIONS);
+        let db_min_connections = env_or(&env, "DB_MIN_CONNECTIONS", &mut errors)
+            .or(file.db_min_connections)
+            .unwrap_or(0);
+        let db_acquire_timeout_secs = env_or(&env, "DB_ACQUIRE_TIMEOUT_SECS", &mut errors)
+            .or(file.db_acquire_timeout_secs)
+            .unwrap_or(DEFAULT_DB_ACQUIRE_TIMEOUT_SECS);
+        let elasticsearch_timeout_secs = env_or(&env, "ELASTICSEARCH_TIMEOUT_SECS", &mut errors)
+            .or(file.elasticsearch_timeout_secs)
+            .unwrap_or(DEFAULT_ELASTICSEARCH_TIMEOUT_SECS);
+        let shutdown_timeout_secs = env_or(&env, "SHUTDOWN_TIMEOUT_SECS", &mut errors)
+            .or(file.shutdown_timeout_secs)
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+
+        let config = Self {
+            bind_address,
+            port,
+            allowed_origins,
+            database_url,
+            vector_backend,
+            elasticsearch_url,
+            elasticsearch_secondary_url,
+            elasticsearch_secondary_sync,
+            qdrant_url,
+            qdrant_api_key,
+            db_max_connections,
+            db_min_connections,
+            db_acquire_timeout: Duration::from_secs(db_acquire_timeout_secs),
+            elasticsearch_timeout: Duration::from_secs(elasticsearch_timeout_secs),
+            shutdown_timeout: Duration::from_secs(shutdown_timeout_secs),
+        };
+        errors.extend(config.validate());
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(anyhow::anyhow!("Invalid configuration:\n  - {}", errors.join("\n  - ")))
+        }
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.port == 0 {
+            errors.push("PORT must be between 1 and 65535".to_string());
+        }
+        if self.database_url.is_empty() {
+            errors.push("DATABASE_URL must be set".to_string());
+        } else if !self.database_url.starts_with("postgres://") && !self.database_url.starts_with("postgresql://") {
+            errors.push("DATABASE_URL must start with postgres:// or postgresql://".to_string());
+        }
+        if Url::parse(&self.elasticsearch_url).is_err() {
+            errors.push(format!("ELASTICSEARCH_URL is not a valid URL: '{}'", self.elasticsearch_url));
+        }
+        if let Some(url) = &self.elasticsearch_secondary_url {
+            if Url::parse(url).is_err() {
+                errors.push(format!("ELASTICSEARCH_SECONDARY_URL is not a valid URL: '{}'", url));
+            } else if *url == self.elasticsearch_url {
+                errors.push("ELASTICSEARCH_SECONDARY_URL must differ from ELASTICSEARCH_URL".to_string());
+            }
+        }
+        if Url::parse(&self.qdrant_url).is_err() {
+            errors.push(format!("QDRANT_URL is not a valid URL: '{}'", self.qdrant_url));
+        }
+        if self.db_max_connections == 0 {
+            errors.push("DB_MAX_CONNECTIONS must be at least 1".to_string());
+        }
+        if self.db_min_connections > self.db_max_connections {
+            errors.push(format!(
+                "DB_MIN_CONNECTIONS ({}) must not exceed DB_MAX_CONNECTIONS ({})",
+                self.db_min_connections, self.db_max_connections
+            ));
+        }
+        if self.db_acquire_timeout.is_zero() {
+            errors.push("DB_ACQUIRE_TIMEOUT_SECS must be greater than 0".to_string());
+        }
+        if self.elasticsearch_timeout.is_zero() {
+            errors.push("ELASTICSEARCH_TIMEOUT_SECS must be greater than 0".to_string());
+        }
+
+        if self.allowed_origins.is_empty() {
+            errors.push("CORS_ALLOWED_ORIGINS must list at least one origin, or '*'".to_string());
+        } else if !self.allows_any_origin() {
+            for origin in &self.allowed_origins {
+                if !is_valid_origin(origin) {
+                    errors.push(format!(
+                        "CORS origin '{}' must be a scheme and host such as https://app.example.com",
+                        origin
+                    ));
+                }
+            }
+        }
+
+        errors
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<FileConfig> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+    toml::from_str(&raw).map_err(|e| anyhow::anyhow!("Failed to parse config file {}: {}", path.display(), e))
+}
+
+// Parse an environment variable, recording a readable error instead of silently using the default
+fn env_or<T: FromStr>(env: &impl Fn(&str) -> Option<String>, name: &str, errors: &mut Vec<String>) -> Option<T> {
+    let raw = env(name)?;
+    match raw.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            errors.push(format!("{} has an invalid value: '{}'", name, raw));
+            None
+        }
+    }
+}
+
+/// Normalize origins, dropping blanks and trailing slashes
+fn parse_origins<'a>(origins: impl Iterator<Item = &'a str>) -> Vec<String> {
+    origins
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
+fn is_valid_origin(origin: &str) -> bool {
+    match Url::parse(origin) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url.host_str().is_some()
+                && url.path() == "/"
+                && url.query().is_none()
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = AppConfig::from_sources(
+            FileConfig::default(),
+            env_from(&[("DATABASE_URL", "postgres://localhost/rag")]),
+        )
+        .unwrap();
+
+        assert_eq!(config.socket_addr(), "0.0.0.0:8000".parse().unwrap());
+        assert!(config.allows_any_origin());
+        assert_eq!(config.db_max_connections, 5);
+        assert_eq!(config.vector_backend, VectorBackendKind::Elasticsearch);
+        assert_eq!(config.qdrant_url, "http://localhost:6333");
+        assert_eq!(config.elasticsearch_secondary_url, None);
+    }
+
+    #[test]
+    fn test_elasticsearch_secondary() {
+        let file = FileConfig {
+            database_url: Some("postgres://localhost/rag".to_string()),
+            elasticsearch_secondary_url: Some("http://es-west:9200".to_string()),
+            ..Default::default()
+        };
+        let config = AppConfig::from_sources(file, env_from(&[("ELASTICSEARCH_SECONDARY_SYNC", "snapshot")])).unwrap();
+        assert_eq!(config.elasticsearch_secondary_url.as_deref(), Some("http://es-west:9200"));
+        assert_eq!(config.elasticsearch_secondary_sync, SecondarySync::Snapshot);
+
+        let error = AppConfig::from_sources(
+            FileConfig::default(),
+            env_from(&[
+                ("DATABASE_URL", "postgres://localhost/rag"),
+                ("ELASTICSEARCH_SECONDARY_URL", "http://localhost:9200"),
+                ("ELASTICSEARCH_SECONDARY_SYNC", "ccr"),
+            ]),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("ELASTICSEARCH_SECONDARY_SYNC has an invalid value: 'ccr'"));
+        assert!(error.contains("ELASTICSEARCH_SECONDARY_URL must differ from ELASTICSEARCH_URL"));
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let file = FileConfig {
+            port: Some(9000),
+            db_max_connections: Some(20),
+            database_url: Some("postgres://file/rag".to_string()),
+            ..Default::default()
+        };
+        let config = AppConfig::from_sources(
+            file,
+            env_from(&[
+                ("PORT", "8080"),
+                ("VECTOR_BACKEND", "pgvector"),
+                ("CORS_ALLOWED_ORIGINS", "https://app.example.com, http://localhost:3000/"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.vector_backend, VectorBackendKind::Pgvector);
+        assert_eq!(config.db_max_connections, 20);
+        assert_eq!(config.database_url, "postgres://file/rag");
+        assert_eq!(config.allowed_origins, vec!["https://app.example.com", "http://localhost:3000"]);
+        assert!(!config.allows_any_origin());
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let error = AppConfig::from_sources(
+            FileConfig::default(),
+            env_from(&[
+                ("PORT", "eighty"),
+                ("DB_MIN_CONNECTIONS", "10"),
+                ("CORS_ALLOWED_ORIGINS", "app.example.com"),
+            ]),
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.contains("PORT has an invalid value: 'eighty'"));
+        assert!(error.contains("DATABASE_URL must be set"));
+        assert!(error.contains("DB_MIN_CONNECTIONS (10) must not exceed DB_MAX_CONNECTIONS (5)"));
+        assert!(error.contains("CORS origin 'app.example.com'"));
+    }
+
+    #[test]
+    fn test_parses_toml_file() {
+        let file: FileConfig = toml::from_str(
+            "bind_address = \"127.0.0.1\"\nport = 3000\nallowed_origins = [\"https://app.example.com\"]\n",
+        )
+        .unwrap();
+        assert_eq!(file.port, Some(3000));
+        assert_eq!(file.bind_address, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert!(toml::from_str::<FileConfig>("prot = 3000").is_err());
+    }
 }
@@ -1,2 +1,5 @@
+pub mod chunking;
 pub mod config;
 pub mod pdf;
+pub mod pdf_writer;
+pub mod telemetry;
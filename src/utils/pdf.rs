@@ -1,25 +1,135 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use pdf_extract::extract_text;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use tracing;
+use uuid::Uuid;
 
-/// Extract text content from a PDF file
+/// OCR fallback for scanned PDFs, run through the `pdftoppm` and `tesseract` binaries
+#[derive(Debug, Clone)]
+pub struct OcrConfig {
+    pub enabled: bool,
+    /// Tesseract language(s), e.g. "eng" or "eng+deu"
+    pub language: String,
+    /// Resolution pages are rendered at before OCR
+    pub dpi: u32,
+}
+
+impl OcrConfig {
+    /// Read `OCR_ENABLED` (default true), `OCR_LANGUAGE` (default eng) and `OCR_DPI` (default 300)
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("OCR_ENABLED")
+                .map(|v| v != "false" && v != "0")
+                .unwrap_or(true),
+            language: std::env::var("OCR_LANGUAGE").unwrap_or_else(|_| "eng".to_string()),
+            dpi: std::env::var("OCR_DPI")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
+        }
+    }
+}
+
+/// Extract text content from a PDF file, falling back to OCR for image-only PDFs
 pub fn extract_text_from_pdf<P: AsRef<Path>>(file_path: P) -> Result<String> {
     let path = file_path.as_ref();
     tracing::info!("Extracting text from PDF: {:?}", path);
     
-    let text = extract_text(path)?;
+    let text = extract_embedded_text(path)?;
     
     if text.trim().is_empty() {
-        tracing::warn!("PDF file appears to be empty or contains no extractable text");
-        return Ok(String::new());
+        let ocr = OcrConfig::from_env();
+        if !ocr.enabled {
+            tracing::warn!("PDF file appears to be empty or contains no extractable text");
+            return Ok(String::new());
+        }
+
+        tracing::info!("No extractable text in PDF, running OCR: {:?}", path);
+        let text = ocr_pdf(path, &ocr)?;
+        if text.trim().is_empty() {
+            tracing::warn!("OCR produced no text for PDF: {:?}", path);
+            return Ok(String::new());
+        }
+
+        tracing::info!("✅ OCR extracted {} characters from PDF", text.len());
+        return Ok(text);
     }
     
     tracing::info!("Successfully extracted {} characters from PDF", text.len());
     Ok(text)
 }
 
-/// Split text into chunks for embedding processing
+// The PDF's text layer. pdf_extract panics on some malformed files, so a panic is an error too
+fn extract_embedded_text(path: &Path) -> Result<String> {
+    match std::panic::catch_unwind(|| extract_text(path)) {
+        Ok(text) => Ok(text?),
+        Err(_) => Err(anyhow!("Malformed PDF, text extraction failed: {:?}", path)),
+    }
+}
+
+/// Render every page to PNG and OCR them in page order
+pub fn ocr_pdf(path: &Path, config: &OcrConfig) -> Result<String> {
+    let work_dir = std::env::temp_dir().join(format!("rag_ocr_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&work_dir)?;
+
+    let result = render_and_ocr(path, &work_dir, config);
+
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        tracing::warn!("Failed to clean up OCR directory {:?}: {}", work_dir, e);
+    }
+
+    result
+}
+
+fn render_and_ocr(path: &Path, work_dir: &Path, config: &OcrConfig) -> Result<String> {
+    let output = Command::new("pdftoppm")
+        .arg("-r")
+        .arg(config.dpi.to_string())
+        .arg("-png")
+        .arg(path)
+        .arg(work_dir.join("page"))
+        .output()
+        .map_err(|e| anyhow!("Failed to run pdftoppm (is poppler-utils installed?): {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("pdftoppm failed: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+
+    let pages = page_images(work_dir)?;
+    tracing::info!("Rendered {} pages for OCR", pages.len());
+
+    let mut text = String::new();
+    for page in pages {
+        let output = Command::new("tesseract")
+            .arg(&page)
+            .arg("stdout")
+            .arg("-l")
+            .arg(&config.language)
+            .output()
+            .map_err(|e| anyhow!("Failed to run tesseract (is tesseract-ocr installed?): {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!("tesseract failed on {:?}: {}", page, String::from_utf8_lossy(&output.stderr)));
+        }
+
+        text.push_str(&String::from_utf8_lossy(&output.stdout));
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+/// Rendered page images in page order. pdftoppm zero-pads page numbers, so a name sort is enough
+fn page_images(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut pages: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    pages.sort();
+    Ok(pages)
+}
+
+/// Split text into chunks for embedding processing. Each chunk starts with the last `overlap` words
+/// of the one before; an overlap of `chunk_size` or more still moves on by one word per chunk
 pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
     if text.is_empty() {
         return Vec::new();
@@ -32,6 +142,7 @@ pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String>
         return chunks;
     }
     
+    let chunk_size = chunk_size.max(1);
     let mut start = 0;
     
     while start < words.len() {
@@ -47,7 +158,7 @@ pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String>
         if end >= words.len() {
             break;
         }
-        start = end.saturating_sub(overlap);
+        start = end.saturating_sub(overlap).max(start + 1);
     }
     
     tracing::info!("Split text into {} chunks", chunks.len());
@@ -68,6 +179,92 @@ pub fn process_pdf_file<P: AsRef<Path>>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    const MINIMAL_PDF: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/minimal.pdf"));
+
+    // Words of a chunk list with each chunk's overlap with the one before dropped
+    fn unchunk(chunks: &[String], overlap: usize) -> Vec<String> {
+        chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, chunk)| chunk.split_whitespace().skip(if i == 0 { 0 } else { overlap }))
+            .map(str::to_string)
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_chunks_keep_every_word_once_beyond_overlap(
+            words in prop::collection::vec("\\S{1,8}", 0..120),
+            (chunk_size, overlap) in (1usize..30).prop_flat_map(|size| (Just(size), 0..size)),
+        ) {
+            let chunks = chunk_text(&words.join(" "), chunk_size, overlap);
+
+            prop_assert_eq!(unchunk(&chunks, overlap), words);
+            for (i, chunk) in chunks.iter().enumerate() {
+                let chunk_words: Vec<&str> = chunk.split_whitespace().collect();
+                prop_assert!(!chunk_words.is_empty() && chunk_words.len() <= chunk_size);
+                if let Some(next) = chunks.get(i + 1) {
+                    // Only the last chunk may be short, and the next one repeats this one's tail
+                    prop_assert_eq!(chunk_words.len(), chunk_size);
+                    let next_words: Vec<&str> = next.split_whitespace().collect();
+                    prop_assert_eq!(&chunk_words[chunk_size - overlap..], &next_words[..overlap]);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_chunks_are_unicode_safe(text in "\\PC{0,300}", chunk_size in 1usize..20) {
+            let chunks = chunk_text(&text, chunk_size, chunk_size / 2);
+            let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+
+            prop_assert_eq!(unchunk(&chunks, chunk_size / 2), words);
+        }
+
+        #[test]
+        fn prop_chunking_ends_whatever_the_overlap(
+            words in prop::collection::vec("[a-z]{1,5}", 1..60),
+            chunk_size in 0usize..10,
+            overlap in 0usize..20,
+        ) {
+            let chunks = chunk_text(&words.join(" "), chunk_size, overlap);
+
+            prop_assert!(!chunks.is_empty() && chunks.len() <= words.len());
+            prop_assert!(chunks.last().unwrap().ends_with(words.last().unwrap().as_str()));
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_malformed_pdfs_fail_without_panicking(
+            cut in 0..MINIMAL_PDF.len(),
+            corruptions in prop::collection::vec((0..MINIMAL_PDF.len(), any::<u8>()), 0..8),
+        ) {
+            let mut bytes = MINIMAL_PDF.to_vec();
+            for (index, byte) in corruptions {
+                bytes[index] = byte;
+            }
+            bytes.truncate(cut);
+
+            let path = std::env::temp_dir().join(format!("rag_fuzz_{}.pdf", Uuid::new_v4()));
+            std::fs::write(&path, &bytes).unwrap();
+            let _ = extract_embedded_text(&path);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_extracts_text_from_minimal_pdf() {
+        let path = std::env::temp_dir().join(format!("rag_minimal_{}.pdf", Uuid::new_v4()));
+        std::fs::write(&path, MINIMAL_PDF).unwrap();
+        let text = extract_embedded_text(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(text.unwrap().contains("Refunds take five days"));
+    }
 
     #[test]
     fn test_chunk_text() {
@@ -87,4 +284,22 @@ mod tests {
         let chunks = chunk_text("", 10, 2);
         assert!(chunks.is_empty());
     }
+
+    #[test]
+    fn test_page_images_sorted_and_filtered() {
+        let dir = std::env::temp_dir().join(format!("rag_ocr_test_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["page-10.png", "page-02.png", "page-01.png", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let pages = page_images(&dir).unwrap();
+        let names: Vec<String> = pages
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, vec!["page-01.png", "page-02.png", "page-10.png"]);
+    }
 }
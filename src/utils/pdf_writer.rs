@@ -0,0 +1,240 @@
+use chrono::{DateTime, Utc};
+
+// A4 in points, with the text area inside the margins
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+const MARGIN: usize = 50;
+const FONT_SIZE: usize = 10;
+const LEADING: usize = 13;
+// Courier glyphs are all 0.6 em wide, so a line holds a fixed number of characters
+const CHARS_PER_LINE: usize = (PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6);
+const LINES_PER_PAGE: usize = (PAGE_HEIGHT - 2 * MARGIN) / LEADING;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Regular,
+    Bold,
+}
+
+impl Style {
+    fn font(self) -> &'static str {
+        match self {
+            Style::Regular => "/F1",
+            Style::Bold => "/F2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    style: Style,
+    text: String,
+}
+
+/// A printable text document laid out on A4 pages in the standard Courier fonts, which every PDF
+/// reader has, so no font needs embedding. Characters outside Latin-1 are replaced
+#[derive(Debug, Clone)]
+pub struct PdfDocument {
+    title: String,
+    lines: Vec<Line>,
+}
+
+impl PdfDocument {
+    pub fn new(title: &str) -> Self {
+        Self { title: title.to_string(), lines: Vec::new() }
+    }
+
+    /// Add text wrapped to the page width, each line starting `indent` characters in
+    pub fn paragraph(&mut self, style: Style, indent: usize, text: &str) {
+        let indent = indent.min(CHARS_PER_LINE / 2);
+        for line in wrap(text, CHARS_PER_LINE - indent) {
+            self.lines.push(Line { style, text: format!("{}{}", " ".repeat(indent), line) });
+        }
+    }
+
+    pub fn blank_line(&mut self) {
+        self.lines.push(Line { style: Style::Regular, text: String::new() });
+    }
+
+    /// The finished file, with the title and creation time in its metadata and page numbers in
+    /// each page's footer
+    pub fn render(&self, created_at: DateTime<Utc>) -> Vec<u8> {
+        let pages: Vec<&[Line]> = if self.lines.is_empty() {
+            vec![&[]]
+        } else {
+            self.lines.chunks(LINES_PER_PAGE).collect()
+        };
+
+        // Objects 1-5 are fixed; each page then adds its page object and its content stream
+        let kids: Vec<String> = (0..pages.len()).map(|page| format!("{} 0 R", 6 + 2 * page)).collect();
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+            [
+                b"<< /Title ".as_slice(),
+                &pdf_string(&self.title),
+                b" /Producer (rust_rag) /CreationDate ",
+                &pdf_string(&created_at.format("D:%Y%m%d%H%M%SZ").to_string()),
+                b" >>",
+            ]
+            .concat(),
+        ];
+        for (index, lines) in pages.iter().enumerate() {
+            let content = page_content(lines, index + 1, pages.len());
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    7 + 2 * index
+                )
+                .into_bytes(),
+            );
+            objects.push(
+                [
+                    format!("<< /Length {} >>\nstream\n", content.len()).as_bytes(),
+                    &content,
+                    b"\nendstream",
+                ]
+                .concat(),
+            );
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref_offset = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref_offset
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+// Text operators drawing one page's lines from the top margin down, and its page number
+fn page_content(lines: &[Line], page: usize, page_count: usize) -> Vec<u8> {
+    let mut content = format!("BT\n{} TL\n{} {} Td\n", LEADING, MARGIN, PAGE_HEIGHT - MARGIN).into_bytes();
+    let mut style = None;
+    for line in lines {
+        if style != Some(line.style) {
+            content.extend_from_slice(format!("{} {} Tf\n", line.style.font(), FONT_SIZE).as_bytes());
+            style = Some(line.style);
+        }
+        content.extend_from_slice(&pdf_string(&line.text));
+        content.extend_from_slice(b" Tj T*\n");
+    }
+    content.extend_from_slice(format!("ET\nBT\n/F1 8 Tf\n{} {} Td\n", MARGIN, MARGIN / 2).as_bytes());
+    content.extend_from_slice(&pdf_string(&format!("Page {} of {}", page, page_count)));
+    content.extend_from_slice(b" Tj\nET");
+    content
+}
+
+// Split text into lines of at most `width` characters, breaking at spaces where possible. Line
+// breaks in the text are kept and an empty text gives one empty line
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_len = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            if line_len > 0 && line_len + 1 + word.len() <= width {
+                line.push(' ');
+                line.extend(&word);
+                line_len += 1 + word.len();
+                continue;
+            }
+            if line_len > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Words longer than a line are cut
+            while word.len() > width {
+                lines.push(word.drain(..width).collect());
+            }
+            line_len = word.len();
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// A PDF literal string in WinAnsi encoding, with delimiters escaped and characters outside it
+// replaced by their closest ASCII or `?`
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend_from_slice(&[b'\\', c as u8]),
+            '\t' => bytes.push(b' '),
+            '\u{2018}' | '\u{2019}' => bytes.push(b'\''),
+            '\u{201C}' | '\u{201D}' => bytes.push(b'"'),
+            '\u{2013}' | '\u{2014}' => bytes.push(b'-'),
+            '\u{2026}' => bytes.extend_from_slice(b"..."),
+            ' '..='~' | '\u{A0}'..='\u{FF}' => bytes.push(c as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes.push(b')');
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap("one\n\ntwo", 10), vec!["one", "", "two"]);
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(wrap("abcdefghijkl mn", 5), vec!["abcde", "fghij", "kl mn"]);
+    }
+
+    #[test]
+    fn test_pdf_string_escapes() {
+        assert_eq!(pdf_string("a (b) \\ c"), b"(a \\(b\\) \\\\ c)".to_vec());
+        assert_eq!(pdf_string("caf\u{e9} \u{201C}x\u{201D} \u{4e2d}"), b"(caf\xe9 \"x\" ?)".to_vec());
+    }
+
+    #[test]
+    fn test_render_offsets_point_at_objects() {
+        let mut document = PdfDocument::new("Transcript");
+        for i in 0..(LINES_PER_PAGE + 5) {
+            document.paragraph(Style::Regular, 2, &format!("line {}", i));
+        }
+        document.blank_line();
+        let pdf = document.render("2026-01-02T03:04:05Z".parse().unwrap());
+
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(D:20260102030405Z)"));
+        assert!(text.contains("(Page 2 of 2)"));
+
+        // Each xref entry gives the byte offset of its object
+        let xref = text.rfind("xref\n").unwrap();
+        for (index, entry) in text[xref..].lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", index + 1).as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::SdkTracerProvider;
+
+/// Holds the OTLP exporter, if any, so buffered spans can be flushed on shutdown
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+}
+
+// Build the OTLP/HTTP exporter when OTEL_EXPORTER_OTLP_ENDPOINT is set
+#[cfg(feature = "otel")]
+fn otlp_provider() -> Result<Option<SdkTracerProvider>> {
+    if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+        return Ok(None);
+    }
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "rag-rust".to_string());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(opentelemetry_sdk::Resource::builder().with_service_name(service_name).build())
+        .build();
+
+    Ok(Some(provider))
+}
+
+/// Log to stdout and, with the `otel` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` set, export spans over OTLP
+pub fn init_tracing() -> Result<TelemetryGuard> {
+    let registry = tracing_subscriber::registry()
+        .with(EnvFilter::new("info"))
+        .with(tracing_subscriber::fmt::layer());
+
+    #[cfg(feature = "otel")]
+    {
+        let provider = otlp_provider()?;
+        let otel_layer = provider
+            .as_ref()
+            .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("rag-rust")));
+        registry.with(otel_layer).init();
+
+        if provider.is_some() {
+            tracing::info!("✅ Exporting traces over OTLP");
+        }
+        Ok(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.init();
+
+        if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok() {
+            tracing::warn!("⚠️ OTEL_EXPORTER_OTLP_ENDPOINT is set but this build lacks the otel feature; traces are not exported");
+        }
+        Ok(TelemetryGuard {})
+    }
+}
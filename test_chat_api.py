@@ -12,12 +12,14 @@ import time
 # Configuration
 BASE_URL = "http://localhost:8000/api"
 CHATBOT_ID = "your_chatbot_id_here"  # Replace with actual chatbot ID
+API_KEY = "your_api_key_here"  # Organization API key from POST /api/admin/organizations
+HEADERS = {"X-API-Key": API_KEY}
 
 def test_create_session():
     """Test creating a new session"""
     print("🔄 Testing session creation...")
     
-    response = requests.post(f"{BASE_URL}/chat/session")
+    response = requests.post(f"{BASE_URL}/chat/session", headers=HEADERS)
     
     if response.status_code == 200:
         data = response.json()
@@ -38,7 +40,7 @@ def test_chat_without_session(chatbot_id):
         "query": "What is this document about?"
     }
     
-    response = requests.post(f"{BASE_URL}/chat", json=payload)
+    response = requests.post(f"{BASE_URL}/chat", json=payload, headers=HEADERS)
     
     if response.status_code == 200:
         data = response.json()
@@ -63,7 +65,7 @@ def test_chat_with_session(chatbot_id, session_id, chat_id):
         "chat_id": chat_id
     }
     
-    response = requests.post(f"{BASE_URL}/chat", json=payload)
+    response = requests.post(f"{BASE_URL}/chat", json=payload, headers=HEADERS)
     
     if response.status_code == 200:
         data = response.json()
@@ -79,7 +81,7 @@ def test_get_chat_history(chat_id):
     """Test getting chat history"""
     print("🔄 Testing chat history retrieval...")
     
-    response = requests.get(f"{BASE_URL}/chat/history?chat_id={chat_id}")
+    response = requests.get(f"{BASE_URL}/chat/history?chat_id={chat_id}", headers=HEADERS)
     
     if response.status_code == 200:
         data = response.json()
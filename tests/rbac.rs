@@ -0,0 +1,148 @@
+//! Role checks on the server's real router, for signed-in users calling with access tokens.
+//! The pool never connects: every request here is answered by middleware or by a handler's input
+//! validation, so a status other than 403 shows the role check let the request through
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use tower::ServiceExt;
+use tower_http::cors::AllowOrigin;
+use uuid::Uuid;
+
+use rag_rust::middleware::ip_filter::IpFilter;
+use rag_rust::middleware::load_shed::LoadShedder;
+use rag_rust::middleware::rate_limit::RateLimiter;
+use rag_rust::routes::build_router;
+use rag_rust::services::answer_cache::AnswerCache;
+use rag_rust::services::cache_invalidation::ChatbotCache;
+use rag_rust::services::embedding_cache::EmbeddingCache;
+use rag_rust::services::health::HealthChecker;
+use rag_rust::services::pgvector::PgVectorStore;
+use rag_rust::services::plugins::PluginHost;
+use rag_rust::services::retrieval::StageTimings;
+use rag_rust::services::shutdown::BackgroundJobs;
+use rag_rust::services::token_budget::TokenBudget;
+use rag_rust::services::user_auth::{UserAuthConfig, UserRole};
+use rag_rust::services::vector::VectorBackend;
+use rag_rust::utils::config::AppState;
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+const BOUNDARY: &str = "rbac-test-boundary";
+
+static JWT_SECRET: Once = Once::new();
+
+// Extractors read the signing secret from the environment, so set it before any request
+fn user_auth() -> UserAuthConfig {
+    // SAFETY: every test sets it here first, and `Once` makes the others wait until it is set
+    JWT_SECRET.call_once(|| unsafe { std::env::set_var("JWT_SECRET", SECRET) });
+    UserAuthConfig::from_env().unwrap().expect("JWT_SECRET is set")
+}
+
+// The router main serves, on a pool that fails any query
+fn app() -> Router {
+    let user_auth = user_auth();
+    let db = Arc::new(
+        PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy("postgres://rag@127.0.0.1:1/rag")
+            .unwrap(),
+    );
+    let app_state = AppState {
+        db: db.clone(),
+        vector_store: Arc::new(VectorBackend::Pgvector(PgVectorStore::new(db.clone()))),
+        embedding_cache: Arc::new(EmbeddingCache::from_env(db)),
+        rate_limiter: Arc::new(RateLimiter::from_env().unwrap()),
+        chatbot_cache: Arc::new(ChatbotCache::new()),
+        answer_cache: Arc::new(AnswerCache::from_env()),
+        stage_timings: Arc::new(StageTimings::new()),
+        background_jobs: BackgroundJobs::new(),
+        plugins: PluginHost::default(),
+        token_budget: Arc::new(TokenBudget::from_env().unwrap()),
+        ip_filter: Arc::new(IpFilter::from_env().unwrap()),
+        health: Arc::new(HealthChecker::from_env().unwrap()),
+        load_shedder: Arc::new(LoadShedder::from_env()),
+        document_store: None,
+    };
+    build_router(app_state, Some(user_auth), AllowOrigin::any())
+}
+
+fn bearer(role: UserRole) -> String {
+    let token = user_auth().issue_access_token(Uuid::new_v4(), Uuid::new_v4(), role).unwrap();
+    format!("Bearer {}", token)
+}
+
+async fn send(request: Request<Body>) -> StatusCode {
+    app().oneshot(request).await.unwrap().status()
+}
+
+// An upload whose chatbot_id the handler rejects before it looks anything up
+async fn upload_pdf(role: UserRole) -> StatusCode {
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"chatbot_id\"\r\n\r\nnot-a-uuid\r\n--{b}--\r\n",
+        b = BOUNDARY
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/upload-pdf")
+        .header(header::AUTHORIZATION, bearer(role))
+        .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY))
+        .body(Body::from(body))
+        .unwrap();
+    send(request).await
+}
+
+// A prompt template that doesn't compile, rejected before the chatbot is updated
+async fn update_prompt_template(role: UserRole) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/chatbots/{}/prompt-template", Uuid::new_v4()))
+        .header(header::AUTHORIZATION, bearer(role))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"template":"Hello {{#if name}}"}"#))
+        .unwrap();
+    send(request).await
+}
+
+// A chat for a chatbot_id the handler rejects before it looks anything up
+async fn chat(role: UserRole) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/chat")
+        .header(header::AUTHORIZATION, bearer(role))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"chatbot_id":"not-a-uuid","query":"Where is my order?"}"#))
+        .unwrap();
+    send(request).await
+}
+
+#[tokio::test]
+async fn test_viewer_cannot_upload_documents() {
+    assert_eq!(upload_pdf(UserRole::Viewer).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_viewer_cannot_change_prompt_template() {
+    assert_eq!(update_prompt_template(UserRole::Viewer).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_viewer_can_chat() {
+    assert_eq!(chat(UserRole::Viewer).await, StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .uri("/api/chat/health")
+        .header(header::AUTHORIZATION, bearer(UserRole::Viewer))
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(request).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_editor_can_upload_and_change_prompt_template() {
+    assert_eq!(upload_pdf(UserRole::Editor).await, StatusCode::BAD_REQUEST);
+    assert_eq!(update_prompt_template(UserRole::Editor).await, StatusCode::BAD_REQUEST);
+    assert_eq!(chat(UserRole::Editor).await, StatusCode::BAD_REQUEST);
+}